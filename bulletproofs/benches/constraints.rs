@@ -0,0 +1,117 @@
+#![allow(non_snake_case)]
+
+//! Proving and verifying circuits of many linear constraints over few multipliers,
+//! in which flattening the constraints into the weights of the wires dominates:
+//! the time should grow linearly with the number of constraints.
+
+#[macro_use]
+extern crate criterion;
+use criterion::{BenchmarkId, Criterion};
+
+extern crate bulletproofs;
+extern crate merlin;
+extern crate rand;
+
+use ark_pallas::Affine;
+
+use ark_ec::AffineRepr;
+use ark_std::UniformRand;
+use bulletproofs::r1cs::*;
+use bulletproofs::{BulletproofGens, PedersenGens};
+use merlin::Transcript;
+
+type Scalar = <Affine as AffineRepr>::ScalarField;
+
+/// The multipliers the constraints are over.
+const MULTIPLIERS: usize = 64;
+/// The dimension of the vector commitment the constraints are also over.
+const DIMENSION: usize = 16;
+/// The numbers of constraints to benchmark.
+const CONSTRAINTS: [usize; 3] = [1_000, 10_000, 100_000];
+
+/// Constrains both inputs of each multiplier to the committed value and to the coordinates of the committed vector,
+/// all equal, cycling through the multipliers until `constraints` constraints are added.
+fn gadget<CS: ConstraintSystem<Scalar>>(
+    cs: &mut CS,
+    v: Variable<Scalar>,
+    vector: &[Variable<Scalar>],
+    value: Option<Scalar>,
+    constraints: usize,
+) -> Result<(), R1CSError> {
+    let wires = (0..MULTIPLIERS)
+        .map(|_| cs.allocate_multiplier(value.map(|value| (value, value))))
+        .collect::<Result<Vec<_>, _>>()?;
+    for i in 0..constraints {
+        let (left, right, _) = wires[i % MULTIPLIERS];
+        cs.constrain(left + right - v - vector[i % DIMENSION]);
+    }
+    Ok(())
+}
+
+fn prove(
+    pc_gens: &PedersenGens<Affine>,
+    bp_gens: &BulletproofGens<Affine>,
+    value: Scalar,
+    constraints: usize,
+) -> (R1CSProof<Affine>, Affine, Affine) {
+    let mut rng = rand::thread_rng();
+    let mut prover = Prover::new(pc_gens, Transcript::new(b"ConstraintsBenchmark"));
+    let (commitment, v) = prover.commit(value, Scalar::rand(&mut rng));
+    let (vector_commitment, vector) =
+        prover.commit_vec(&[value; DIMENSION], Scalar::rand(&mut rng), bp_gens);
+    gadget(&mut prover, v, &vector, Some(value), constraints).unwrap();
+    let proof = prover.prove(bp_gens).unwrap();
+    (proof, commitment, vector_commitment)
+}
+
+fn bench_constraints_prove(c: &mut Criterion) {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(2 * MULTIPLIERS, 1);
+
+    let mut group = c.benchmark_group("constraints proof creation");
+    for constraints in CONSTRAINTS {
+        let value = Scalar::rand(&mut rand::thread_rng());
+        group.bench_with_input(
+            BenchmarkId::from_parameter(constraints),
+            &constraints,
+            |b, constraints| b.iter(|| prove(&pc_gens, &bp_gens, value, *constraints)),
+        );
+    }
+    group.finish()
+}
+
+fn bench_constraints_verify(c: &mut Criterion) {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(2 * MULTIPLIERS, 1);
+
+    let mut group = c.benchmark_group("constraints proof verification");
+    for constraints in CONSTRAINTS {
+        let value = Scalar::rand(&mut rand::thread_rng());
+        let (proof, commitment, vector_commitment) = prove(&pc_gens, &bp_gens, value, constraints);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(constraints),
+            &constraints,
+            |b, constraints| {
+                b.iter(|| {
+                    let mut verifier = Verifier::new(Transcript::new(b"ConstraintsBenchmark"));
+                    let v = verifier.commit(commitment);
+                    let vector = verifier.commit_vec(DIMENSION, vector_commitment);
+                    gadget(&mut verifier, v, &vector, None, *constraints).unwrap();
+                    verifier.verify(&proof, &pc_gens, &bp_gens).unwrap();
+                })
+            },
+        );
+    }
+    group.finish()
+}
+
+criterion_group! {
+    name = constraints;
+    // Proving and verifying are long, we're not microbenchmarking.
+    config = Criterion::default().sample_size(10);
+    targets =
+    bench_constraints_prove,
+    bench_constraints_verify,
+}
+
+criterion_main!(constraints);
@@ -1,6 +1,7 @@
-use ark_pallas::Affine;
+use ark_pallas::{Affine, Fr};
+use ark_std::{UniformRand, Zero};
 
-use bulletproofs::BulletproofGens;
+use bulletproofs::{BulletproofGens, PedersenGens};
 
 #[macro_use]
 extern crate criterion;
@@ -18,11 +19,32 @@ fn bp_gens(c: &mut Criterion) {
     group.finish()
 }
 
+// A commitment with a zero value or a zero blinding, by the generic commitment and by a single scalar multiplication.
+fn pedersen_single_term(c: &mut Criterion) {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let scalar = Fr::rand(&mut rand::thread_rng());
+    let mut group = c.benchmark_group("PedersenGens");
+    group.bench_function("commit, zero value", |b| {
+        b.iter(|| pc_gens.commit(Fr::zero(), scalar))
+    });
+    group.bench_function("commit_blinding_only", |b| {
+        b.iter(|| pc_gens.commit_blinding_only(scalar))
+    });
+    group.bench_function("commit, zero blinding", |b| {
+        b.iter(|| pc_gens.commit(scalar, Fr::zero()))
+    });
+    group.bench_function("commit_message_only", |b| {
+        b.iter(|| pc_gens.commit_message_only(scalar))
+    });
+    group.finish()
+}
+
 criterion_group! {
     name = bp;
     config = Criterion::default().sample_size(10);
     targets =
-        bp_gens
+        bp_gens,
+        pedersen_single_term
 }
 
 criterion_main!(bp);
@@ -58,6 +58,32 @@ impl From<MPCError> for ProofError {
     }
 }
 
+/// Represents an error in allocating [`BulletproofGens`](crate::BulletproofGens).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(Error))]
+pub enum GeneratorsError {
+    /// This error occurs when the generators would take more memory than the budget allows.
+    #[cfg_attr(
+        feature = "std",
+        error("The generators need {needed} bytes, over the budget of {budget}")
+    )]
+    BudgetExceeded {
+        /// The bytes the generators need, saturated at `usize::MAX`.
+        needed: usize,
+        /// The budget the generators were allocated with.
+        budget: usize,
+    },
+    /// This error occurs when the allocator cannot provide the memory for the generators.
+    #[cfg_attr(
+        feature = "std",
+        error("Allocating {bytes} bytes of generators failed")
+    )]
+    AllocationFailed {
+        /// The bytes of the allocation that failed.
+        bytes: usize,
+    },
+}
+
 /// Represents an error during the multiparty computation protocol for
 /// proof aggregation.
 ///
@@ -111,6 +137,33 @@ pub enum MPCError {
         /// A vector with the indexes of the parties whose shares were malformed.
         bad_shares: Vec<usize>,
     },
+    /// This error occurs when a constraint system uses randomized constraints,
+    /// vector commitments, or leaves a multiplier half allocated,
+    /// which the aggregation of R1CS proofs does not support.
+    #[cfg_attr(
+        feature = "std",
+        error("Constraint system not supported by proof aggregation")
+    )]
+    UnsupportedConstraints,
+    /// This error occurs when a party's wires or high-level commitments are not
+    /// where the joint constraint system has them.
+    #[cfg_attr(
+        feature = "std",
+        error("Wires of party {party} do not match the constraint system")
+    )]
+    WireLayoutMismatch {
+        /// The index of the first mismatched party, or the number of parties
+        /// if the constraint system has wires no party committed to.
+        party: usize,
+    },
+    /// This error occurs when every proof share opens its party's wire commitments,
+    /// but the combined shares do not satisfy the joint constraints,
+    /// or a party's polynomial commitments are inconsistent with its share.
+    #[cfg_attr(
+        feature = "std",
+        error("Proof shares do not satisfy the constraint system")
+    )]
+    UnsatisfiedConstraints,
 }
 
 /// Represents an error during the proving or verifying of a constraint system.
@@ -145,6 +198,23 @@ pub enum R1CSError {
         /// The description of the reasons for the error.
         description: String,
     },
+    /// Occurs when a proof is of another version of the format than [`R1CS_PROOF_VERSION`](::r1cs::R1CS_PROOF_VERSION),
+    /// e.g. of a later version of this crate, which is rejected before it is verified.
+    #[cfg_attr(feature = "std", error("Unsupported proof version {version}."))]
+    UnsupportedVersion {
+        /// The version of the proof.
+        version: u8,
+    },
+    /// Occurs when a proof does not verify and the prover laid down other operations than the verifier,
+    /// e.g. one more commitment, found by `Verifier::verify_diagnosed` with the `debug` feature.
+    #[cfg_attr(
+        feature = "std",
+        error("R1CSProof did not verify, the prover and the verifier diverged at {description}")
+    )]
+    OperationMismatch {
+        /// The first diverging operation and the digests of both sides.
+        description: String,
+    },
 }
 
 impl From<ProofError> for R1CSError {
@@ -7,8 +7,13 @@
 extern crate alloc;
 
 use alloc::vec::Vec;
-use ark_ec::{AffineRepr, VariableBaseMSM};
+use ark_ec::{AffineRepr, CurveGroup, Group, VariableBaseMSM};
+use ark_ff::{PrimeField, Zero};
+use core::iter;
 use std::marker::PhantomData;
+use std::sync::OnceLock;
+
+use crate::errors::{GeneratorsError, R1CSError};
 
 use crate::util;
 use digest::{ExtendableOutputDirty, Update, XofReader};
@@ -22,9 +27,9 @@ use sha3::{Sha3XofReader, Shake256};
 ///
 /// The default generators are:
 ///
-/// * `B`: the `ristretto255` basepoint;
-/// * `B_blinding`: the result of `ristretto255` SHA3-512 // todo
-/// hash-to-group on input `B_bytes`.
+/// * `B`: the generator of the curve;
+/// * `B_blinding`: the first point of the chain of [`PEDERSEN_DOMAIN`],
+///   a domain apart from that of the chains of [`BulletproofGens`], so that it is never one of their generators.
 #[derive(Clone)]
 pub struct PedersenGens<C: AffineRepr> {
     /// Bases for the committed values.
@@ -38,20 +43,51 @@ impl<C: AffineRepr> PedersenGens<C> {
     pub fn commit(&self, value: C::ScalarField, blinding: C::ScalarField) -> C {
         C::Group::msm_unchecked(&[self.B, self.B_blinding], &[value, blinding]).into()
     }
+
+    /// The commitment to zero with `blinding`, as `commit` with a zero value, in a single scalar multiplication.
+    pub fn commit_blinding_only(&self, blinding: C::ScalarField) -> C {
+        (self.B_blinding * blinding).into()
+    }
+
+    /// The commitment to `value` with a zero blinding, as `commit`, in a single scalar multiplication.
+    pub fn commit_message_only(&self, value: C::ScalarField) -> C {
+        (self.B * value).into()
+    }
+
+    /// The generators `default` returned before drawing `B_blinding` from [`PEDERSEN_DOMAIN`]: `B` is the generator
+    /// of the curve and `B_blinding` is hashed to the curve from the compressed encoding of `B`.
+    /// Kept to verify the proofs made with them.
+    pub fn hashed_from_basepoint() -> Self {
+        let basepoint = C::generator();
+        let mut buffer: Vec<u8> = Vec::new();
+        basepoint
+            .serialize_compressed(&mut buffer)
+            .expect("serializing to a vector does not fail");
+        PedersenGens {
+            B: basepoint,
+            B_blinding: util::affine_from_bytes_tai(&buffer),
+        }
+    }
 }
 
 impl<C: AffineRepr> Default for PedersenGens<C> {
     fn default() -> Self {
-        let basepoint = C::generator();
-        let mut buffer: Vec<u8> = Vec::new();
-        basepoint.serialize_compressed(&mut buffer).unwrap(); // todo use hash trait?
         PedersenGens {
             B: C::generator(),
-            B_blinding: util::affine_from_bytes_tai(&buffer),
+            B_blinding: GeneratorsChain::<C>::with_domain(PEDERSEN_DOMAIN, b"B_blinding")
+                .next()
+                .expect("the chain is endless"),
         }
     }
 }
 
+/// The domain of the chains of the vector generators of [`BulletproofGens`], absorbed before the label of each chain.
+pub const CHAIN_DOMAIN: &[u8] = b"GeneratorsChain";
+
+/// The domain of the chain of the blinding base of [`PedersenGens::default`], apart from [`CHAIN_DOMAIN`]:
+/// no label of a chain of one domain gives a chain of the other.
+pub const PEDERSEN_DOMAIN: &[u8] = b"PedersenGens";
+
 /// The `GeneratorsChain` creates an arbitrary-long sequence of
 /// orthogonal generators.  The sequence can be deterministically
 /// produced starting with an arbitrary point.
@@ -61,10 +97,26 @@ struct GeneratorsChain<C: AffineRepr> {
 }
 
 impl<C: AffineRepr> GeneratorsChain<C> {
-    /// Creates a chain of generators, determined by the hash of `label`.
+    /// Creates a chain of vector generators, determined by the hash of `label`.
     fn new(label: &[u8]) -> Self {
         let mut shake = Shake256::default();
-        shake.update(b"GeneratorsChain");
+        shake.update(CHAIN_DOMAIN);
+        shake.update(label);
+
+        GeneratorsChain {
+            curve: PhantomData,
+            reader: shake.finalize_xof_dirty(),
+        }
+    }
+
+    /// Creates a chain of generators of `domain` other than [`CHAIN_DOMAIN`], determined by the hash of `label`.
+    /// A zero byte is absorbed first, where the chains of [`CHAIN_DOMAIN`] absorb `G`,
+    /// then the length of the domain, so that no two domains and labels absorb the same bytes.
+    fn with_domain(domain: &[u8], label: &[u8]) -> Self {
+        let mut shake = Shake256::default();
+        shake.update([0u8]);
+        shake.update((domain.len() as u64).to_le_bytes());
+        shake.update(domain);
         shake.update(label);
 
         GeneratorsChain {
@@ -131,16 +183,114 @@ impl<C: AffineRepr> Iterator for GeneratorsChain<C> {
 /// chain, and even forward-compatible to multiparty aggregation of
 /// constraint system proofs, since the generators are namespaced by
 /// their party index.
+///
+/// # Lazy Generators
+///
+/// The generators are derived up front by [`BulletproofGens::new`] on native targets,
+/// and on first use by those of [`BulletproofGens::new_lazy`], which [`BulletproofGens::new`] returns
+/// on 32-bit targets such as wasm: their capacity is nominal, and only the generators
+/// of the circuits actually proven or verified take memory.
 #[derive(Clone)]
 pub struct BulletproofGens<C: AffineRepr> {
     /// The maximum number of usable generators for each party.
     pub gens_capacity: usize,
     /// Number of values or parties
     pub party_capacity: usize,
-    /// Precomputed \\(\mathbf G\\) generators for each party.
-    G_vec: Vec<Vec<C>>,
-    /// Precomputed \\(\mathbf H\\) generators for each party.
-    H_vec: Vec<Vec<C>>,
+    /// \\(\mathbf G\\) generators for each party.
+    G_vec: Vec<Chain<C>>,
+    /// \\(\mathbf H\\) generators for each party.
+    H_vec: Vec<Chain<C>>,
+}
+
+/// The number of generators of the first prefix of a lazy chain, each next prefix is twice as long.
+const LAZY_BLOCK: usize = 256;
+
+/// The generators of one chain, derived up front or on first use.
+#[derive(Clone)]
+enum Chain<C: AffineRepr> {
+    Eager(Vec<C>),
+    /// The prefixes of `LAZY_BLOCK << k` generators of the chain of `label`, for each `k`.
+    /// Each is derived from the previous one the first time a slice of it is taken,
+    /// so slices stay contiguous, and all of them take less than twice the memory of the longest.
+    Lazy {
+        label: [u8; 5],
+        prefixes: Vec<OnceLock<Vec<C>>>,
+    },
+}
+
+impl<C: AffineRepr> Chain<C> {
+    /// The number of prefixes covering `capacity` generators.
+    fn lazy_prefixes(capacity: usize) -> usize {
+        capacity
+            .div_ceil(LAZY_BLOCK)
+            .max(1)
+            .next_power_of_two()
+            .trailing_zeros() as usize
+            + 1
+    }
+
+    /// Extends the chain from `capacity` to `new_capacity` generators.
+    fn increase_capacity(&mut self, label: &[u8; 5], capacity: usize, new_capacity: usize) {
+        match self {
+            Chain::Eager(gens) => gens.extend(
+                &mut GeneratorsChain::<C>::new(label)
+                    .fast_forward(capacity)
+                    .take(new_capacity - capacity),
+            ),
+            Chain::Lazy { prefixes, .. } => {
+                prefixes.resize_with(Self::lazy_prefixes(new_capacity), OnceLock::new)
+            }
+        }
+    }
+
+    /// The first `n` generators, deriving them first if the chain is lazy.
+    fn prefix(&self, n: usize) -> &[C] {
+        match self {
+            Chain::Eager(gens) => &gens[..n],
+            Chain::Lazy { .. } if n == 0 => &[],
+            Chain::Lazy { label, prefixes } => {
+                let k = n.div_ceil(LAZY_BLOCK).next_power_of_two().trailing_zeros() as usize;
+                &Self::lazy_prefix(label, prefixes, k)[..n]
+            }
+        }
+    }
+
+    fn lazy_prefix<'a>(label: &[u8; 5], prefixes: &'a [OnceLock<Vec<C>>], k: usize) -> &'a [C] {
+        prefixes[k].get_or_init(|| {
+            let mut gens = Vec::with_capacity(LAZY_BLOCK << k);
+            if k > 0 {
+                gens.extend_from_slice(Self::lazy_prefix(label, prefixes, k - 1));
+            }
+            let derived = gens.len();
+            gens.extend(
+                GeneratorsChain::<C>::new(label)
+                    .fast_forward(derived)
+                    .take((LAZY_BLOCK << k) - derived),
+            );
+            gens
+        })
+    }
+
+    /// The number of generators derived so far.
+    fn derived(&self) -> usize {
+        match self {
+            Chain::Eager(gens) => gens.len(),
+            Chain::Lazy { prefixes, .. } => prefixes
+                .iter()
+                .rev()
+                .find_map(|prefix| prefix.get())
+                .map_or(0, Vec::len),
+        }
+    }
+}
+
+/// The label of the chain of the G or H generators of party `i`.
+fn chain_label(G_or_H: u8, i: usize) -> [u8; 5] {
+    use byteorder::{ByteOrder, LittleEndian};
+
+    let mut label = [G_or_H, 0, 0, 0, 0];
+    LittleEndian::write_u32(&mut label[1..5], i as u32);
+    label
 }
 
 // todo we are not using the multi party stuff
@@ -157,17 +307,79 @@ impl<C: AffineRepr> BulletproofGens<C> {
     ///
     /// * `party_capacity` is the maximum number of parties that can
     ///    produce an aggregated proof.
+    ///
+    /// On 32-bit targets the generators are lazy, see [`BulletproofGens::new_lazy`].
     pub fn new(gens_capacity: usize, party_capacity: usize) -> Self {
+        if cfg!(target_pointer_width = "32") {
+            return Self::new_lazy(gens_capacity, party_capacity);
+        }
         let mut gens = BulletproofGens {
             gens_capacity: 0,
             party_capacity,
-            G_vec: (0..party_capacity).map(|_| Vec::new()).collect(),
-            H_vec: (0..party_capacity).map(|_| Vec::new()).collect(),
+            G_vec: (0..party_capacity)
+                .map(|_| Chain::Eager(Vec::new()))
+                .collect(),
+            H_vec: (0..party_capacity)
+                .map(|_| Chain::Eager(Vec::new()))
+                .collect(),
         };
         gens.increase_capacity(gens_capacity);
         gens
     }
 
+    /// Like [`BulletproofGens::new`], with the generators derived up front on every target,
+    /// failing instead of aborting if they take more than `max_bytes` or cannot be allocated.
+    pub fn try_new(
+        gens_capacity: usize,
+        party_capacity: usize,
+        max_bytes: usize,
+    ) -> Result<Self, GeneratorsError> {
+        let chain_bytes = gens_capacity.saturating_mul(core::mem::size_of::<C>());
+        let needed = chain_bytes.saturating_mul(2 * party_capacity);
+        if needed > max_bytes {
+            return Err(GeneratorsError::BudgetExceeded {
+                needed,
+                budget: max_bytes,
+            });
+        }
+        let allocate = || {
+            let mut gens = Vec::new();
+            gens.try_reserve_exact(gens_capacity)
+                .map_err(|_| GeneratorsError::AllocationFailed { bytes: chain_bytes })?;
+            Ok(Chain::Eager(gens))
+        };
+        let mut gens = BulletproofGens {
+            gens_capacity: 0,
+            party_capacity,
+            G_vec: (0..party_capacity)
+                .map(|_| allocate())
+                .collect::<Result<_, _>>()?,
+            H_vec: (0..party_capacity)
+                .map(|_| allocate())
+                .collect::<Result<_, _>>()?,
+        };
+        gens.increase_capacity(gens_capacity);
+        Ok(gens)
+    }
+
+    /// Like [`BulletproofGens::new`], with the generators derived on first use:
+    /// slices of `n` generators derive the first `n`, rounded up to a power of two times 256, of each chain.
+    /// The generators are the same as those derived up front.
+    pub fn new_lazy(gens_capacity: usize, party_capacity: usize) -> Self {
+        let lazy = |G_or_H, i| Chain::Lazy {
+            label: chain_label(G_or_H, i),
+            prefixes: (0..Chain::<C>::lazy_prefixes(gens_capacity))
+                .map(|_| OnceLock::new())
+                .collect(),
+        };
+        BulletproofGens {
+            gens_capacity,
+            party_capacity,
+            G_vec: (0..party_capacity).map(|i| lazy(b'G', i)).collect(),
+            H_vec: (0..party_capacity).map(|i| lazy(b'H', i)).collect(),
+        }
+    }
+
     /// Returns j-th share of generators, with an appropriate
     /// slice of vectors G and H for the j-th range proof.
     pub fn share(&self, j: usize) -> BulletproofGensShare<'_, C> {
@@ -180,32 +392,31 @@ impl<C: AffineRepr> BulletproofGens<C> {
     /// Increases the generators' capacity to the amount specified.
     /// If less than or equal to the current capacity, does nothing.
     pub fn increase_capacity(&mut self, new_capacity: usize) {
-        use byteorder::{ByteOrder, LittleEndian};
-
         if self.gens_capacity >= new_capacity {
             return;
         }
 
         for i in 0..self.party_capacity {
-            let party_index = i as u32;
-            let mut label = [b'G', 0, 0, 0, 0];
-            LittleEndian::write_u32(&mut label[1..5], party_index);
-            self.G_vec[i].extend(
-                &mut GeneratorsChain::<C>::new(&label)
-                    .fast_forward(self.gens_capacity)
-                    .take(new_capacity - self.gens_capacity),
+            self.G_vec[i].increase_capacity(
+                &chain_label(b'G', i),
+                self.gens_capacity,
+                new_capacity,
             );
-
-            label[0] = b'H';
-            self.H_vec[i].extend(
-                &mut GeneratorsChain::<C>::new(&label)
-                    .fast_forward(self.gens_capacity)
-                    .take(new_capacity - self.gens_capacity),
+            self.H_vec[i].increase_capacity(
+                &chain_label(b'H', i),
+                self.gens_capacity,
+                new_capacity,
             );
         }
         self.gens_capacity = new_capacity;
     }
 
+    /// The number of generators of each party derived so far: the capacity if they were derived up front,
+    /// and otherwise the longest prefix used, rounded up to a power of two times 256.
+    pub fn derived_capacity(&self) -> usize {
+        self.G_vec.iter().map(Chain::derived).max().unwrap_or(0)
+    }
+
     /// Return an iterator over the aggregation of the parties' G generators with given size `n`.
     pub(crate) fn G(&self, n: usize, m: usize) -> impl Iterator<Item = &C> {
         AggregatedGensIter {
@@ -230,7 +441,7 @@ impl<C: AffineRepr> BulletproofGens<C> {
 }
 
 struct AggregatedGensIter<'a, C: AffineRepr> {
-    array: &'a Vec<Vec<C>>,
+    array: &'a Vec<Chain<C>>,
     n: usize,
     m: usize,
     party_idx: usize,
@@ -251,7 +462,7 @@ impl<'a, C: AffineRepr> Iterator for AggregatedGensIter<'a, C> {
         } else {
             let cur_gen = self.gen_idx;
             self.gen_idx += 1;
-            Some(&self.array[self.party_idx][cur_gen])
+            Some(&self.array[self.party_idx].prefix(self.n)[cur_gen])
         }
     }
 
@@ -280,12 +491,161 @@ pub struct BulletproofGensShare<'a, C: AffineRepr> {
 impl<'a, C: AffineRepr> BulletproofGensShare<'a, C> {
     /// Return an iterator over this party's G generators with given size `n`.
     pub fn G(&self, n: usize) -> impl Iterator<Item = &'a C> {
-        self.gens.G_vec[self.share].iter().take(n)
+        self.G_slice(n.min(self.gens.gens_capacity)).iter()
     }
 
     /// Return an iterator over this party's H generators with given size `n`.
-    pub(crate) fn H(&self, n: usize) -> impl Iterator<Item = &'a C> {
-        self.gens.H_vec[self.share].iter().take(n)
+    pub fn H(&self, n: usize) -> impl Iterator<Item = &'a C> {
+        self.H_slice(n.min(self.gens.gens_capacity)).iter()
+    }
+
+    /// Return this party's first `n` G generators as a slice.
+    pub fn G_slice(&self, n: usize) -> &'a [C] {
+        assert!(n <= self.gens.gens_capacity, "Not enough generators");
+        self.gens.G_vec[self.share].prefix(n)
+    }
+
+    /// Return this party's first `n` H generators as a slice.
+    pub fn H_slice(&self, n: usize) -> &'a [C] {
+        assert!(n <= self.gens.gens_capacity, "Not enough generators");
+        self.gens.H_vec[self.share].prefix(n)
+    }
+
+    /// Computes the vector commitment `<v, G> + v_blinding * B_blinding` with this party's G generators.
+    /// The multiscalar multiplication runs over chunks of `v` in place, skipping zero entries,
+    /// so its working memory does not grow with the length of `v`.
+    pub fn commit_vec(
+        &self,
+        pc_gens: &PedersenGens<C>,
+        v: &[C::ScalarField],
+        v_blinding: C::ScalarField,
+    ) -> C {
+        assert!(
+            v.len() <= self.gens.gens_capacity,
+            "Not enough generators to commit to the vector"
+        );
+        (util::msm_chunked(self.G_slice(v.len()), v) + pc_gens.B_blinding * v_blinding).into()
+    }
+}
+
+/// The generators a proof of a circuit with `padded_n` multipliers is verified with, `B`, `B_blinding`
+/// and the first `padded_n` G and H generators of party 0, with multiples of each precomputed.
+///
+/// The multiscalar multiplication over these generators dominates the cost of verifying a single proof,
+/// batch verification amortizes it over the proofs of a batch.
+/// With the multiples \\(2^{c j} P\\) of each generator \\(P\\) precomputed, the scalars are split into `c`-bit
+/// windows that are all summed into one set of buckets, skipping the doublings and the bucket sums of each window.
+/// This trades memory, about `256 / c` points for each generator, for the speed of verifying proofs one at a time.
+#[derive(Clone)]
+pub struct PrecomputedGens<C: AffineRepr> {
+    padded_n: usize,
+    /// The width `c` of the windows in bits.
+    window_bits: usize,
+    /// The number of windows of a scalar.
+    windows: usize,
+    /// `tables[i * windows + j]` is \\(2^{c j}\\) times the `i`-th of `B`, `B_blinding`, G and H.
+    tables: Vec<C>,
+}
+
+impl<C: AffineRepr> PrecomputedGens<C> {
+    /// Precomputes the multiples of the generators for proofs of circuits with `padded_n` multipliers,
+    /// the number of multipliers rounded up to the next power of two.
+    pub fn new(
+        pc_gens: &PedersenGens<C>,
+        bp_gens: &BulletproofGens<C>,
+        padded_n: usize,
+    ) -> Result<Self, R1CSError> {
+        if bp_gens.gens_capacity < padded_n {
+            return Err(R1CSError::InvalidGeneratorsLength);
+        }
+        let gens = bp_gens.share(0);
+        let generators: Vec<C> = iter::once(pc_gens.B)
+            .chain(iter::once(pc_gens.B_blinding))
+            .chain(gens.G(padded_n).copied())
+            .chain(gens.H(padded_n).copied())
+            .collect();
+
+        // An addition for each window of each generator, then a sum over the buckets, which double with each bit of the window.
+        let bits = C::ScalarField::MODULUS_BIT_SIZE as usize;
+        let window_bits = (4..=20)
+            .min_by_key(|c| generators.len() * bits.div_ceil(*c) + (2 << c))
+            .expect("a window width");
+        let windows = bits.div_ceil(window_bits);
+
+        let mut tables = Vec::with_capacity(generators.len() * windows);
+        for generator in generators {
+            let mut multiple = generator.into_group();
+            for _ in 0..windows {
+                tables.push(multiple);
+                for _ in 0..window_bits {
+                    multiple.double_in_place();
+                }
+            }
+        }
+
+        Ok(PrecomputedGens {
+            padded_n,
+            window_bits,
+            windows,
+            tables: C::Group::normalize_batch(&tables),
+        })
+    }
+
+    /// The number of multipliers, rounded up to the next power of two, of the circuits these generators verify.
+    pub fn padded_n(&self) -> usize {
+        self.padded_n
+    }
+
+    /// Computes the multiscalar multiplication of the generators with `scalars`, which are ordered as the
+    /// [`proof_independent_scalars`](crate::r1cs::VerificationTuple::proof_independent_scalars) of a proof.
+    pub(crate) fn msm(&self, scalars: &[C::ScalarField]) -> C::Group {
+        assert_eq!(scalars.len() * self.windows, self.tables.len());
+
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+
+            let chunk_size = scalars.len().div_ceil(rayon::current_num_threads()).max(1);
+            scalars
+                .par_chunks(chunk_size)
+                .enumerate()
+                .map(|(i, chunk)| self.msm_from(i * chunk_size, chunk))
+                .sum()
+        }
+        #[cfg(not(feature = "parallel"))]
+        self.msm_from(0, scalars)
+    }
+
+    /// The multiscalar multiplication of `scalars` with the generators starting at the `first`.
+    fn msm_from(&self, first: usize, scalars: &[C::ScalarField]) -> C::Group {
+        let mask = (1u64 << self.window_bits) - 1;
+        let mut buckets = vec![C::Group::zero(); 1 << self.window_bits];
+        for (i, scalar) in scalars.iter().enumerate() {
+            let scalar = scalar.into_bigint();
+            let limbs = scalar.as_ref();
+            let multiples = &self.tables[(first + i) * self.windows..][..self.windows];
+            for (j, multiple) in multiples.iter().enumerate() {
+                let start = j * self.window_bits;
+                let (limb, shift) = (start / 64, start % 64);
+                let mut digit = limbs[limb] >> shift;
+                if shift + self.window_bits > 64 && limb + 1 < limbs.len() {
+                    digit |= limbs[limb + 1] << (64 - shift);
+                }
+                let digit = (digit & mask) as usize;
+                if digit != 0 {
+                    buckets[digit] += *multiple;
+                }
+            }
+        }
+
+        // The sum of `digit * buckets[digit]`, from running sums of the buckets.
+        let mut running_sum = C::Group::zero();
+        let mut sum = C::Group::zero();
+        for bucket in buckets.into_iter().skip(1).rev() {
+            running_sum += bucket;
+            sum += running_sum;
+        }
+        sum
     }
 }
 
@@ -295,6 +655,48 @@ mod tests {
 
     use ark_pallas::*;
 
+    #[test]
+    fn commitments_to_a_single_term_match_commit() {
+        use ark_std::UniformRand;
+        let mut rng = rand::thread_rng();
+        let pc_gens = PedersenGens::<Affine>::default();
+        for _ in 0..8 {
+            let scalar = Fr::rand(&mut rng);
+            assert_eq!(
+                pc_gens.commit_blinding_only(scalar),
+                pc_gens.commit(Fr::zero(), scalar)
+            );
+            assert_eq!(
+                pc_gens.commit_message_only(scalar),
+                pc_gens.commit(scalar, Fr::zero())
+            );
+        }
+        assert_eq!(pc_gens.commit_blinding_only(Fr::zero()), Affine::zero());
+    }
+
+    #[test]
+    fn pedersen_gens_are_apart_from_the_chains() {
+        let pc_gens = PedersenGens::<Affine>::default();
+        assert_eq!(pc_gens.B, Affine::generator());
+        assert_ne!(pc_gens.B, pc_gens.B_blinding);
+        let gens = BulletproofGens::<Affine>::new(64, 2);
+        for party in 0..2 {
+            let share = gens.share(party);
+            for base in [pc_gens.B, pc_gens.B_blinding] {
+                assert!(share.G(64).chain(share.H(64)).all(|g| *g != base));
+            }
+        }
+        assert_ne!(
+            PedersenGens::<Affine>::hashed_from_basepoint().B_blinding,
+            pc_gens.B_blinding
+        );
+        // The label of the blinding base gives another point in the domain of the chains.
+        assert_ne!(
+            GeneratorsChain::<Affine>::new(b"B_blinding").next(),
+            Some(pc_gens.B_blinding)
+        );
+    }
+
     #[test]
     fn aggregated_gens_iter_matches_flat_map() {
         let gens = BulletproofGens::<Affine>::new(64, 8);
@@ -305,7 +707,7 @@ mod tests {
                 .G_vec
                 .iter()
                 .take(m)
-                .flat_map(move |G_j| G_j.iter().take(n))
+                .flat_map(move |G_j| G_j.prefix(n).iter())
                 .copied()
                 .collect();
 
@@ -314,7 +716,7 @@ mod tests {
                 .H_vec
                 .iter()
                 .take(m)
-                .flat_map(move |H_j| H_j.iter().take(n))
+                .flat_map(move |H_j| H_j.prefix(n).iter())
                 .copied()
                 .collect();
 
@@ -358,4 +760,89 @@ mod tests {
         helper(32, 8);
         helper(16, 8);
     }
+
+    #[test]
+    fn lazy_gens_match_eager_gens() {
+        let eager = BulletproofGens::<Affine>::try_new(1100, 2, usize::MAX).unwrap();
+        let mut lazy = BulletproofGens::<Affine>::new_lazy(600, 2);
+        assert_eq!(lazy.derived_capacity(), 0);
+
+        // The slices of a lazy chain are those of the eager one, across the prefixes.
+        for n in [0, 1, 255, 256, 257, 600] {
+            for j in 0..2 {
+                assert_eq!(lazy.share(j).G_slice(n), eager.share(j).G_slice(n));
+                assert_eq!(lazy.share(j).H_slice(n), eager.share(j).H_slice(n));
+            }
+        }
+        assert_eq!(lazy.derived_capacity(), 1024);
+        let agg_G: Vec<Affine> = lazy.G(300, 2).copied().collect();
+        assert_eq!(agg_G, eager.G(300, 2).copied().collect::<Vec<_>>());
+
+        lazy.increase_capacity(1100);
+        assert_eq!(lazy.share(1).G_slice(1100), eager.share(1).G_slice(1100));
+        assert_eq!(lazy.derived_capacity(), 2048);
+
+        // Only the generators used are derived, whatever the nominal capacity.
+        let large = BulletproofGens::<Affine>::new_lazy(1 << 30, 1);
+        assert_eq!(large.share(0).G(64).count(), 64);
+        assert_eq!(large.derived_capacity(), LAZY_BLOCK);
+    }
+
+    #[test]
+    fn generators_over_budget_are_an_error() {
+        let bytes = 2 * 64 * core::mem::size_of::<Affine>();
+        let gens = BulletproofGens::<Affine>::try_new(64, 1, bytes).unwrap();
+        assert_eq!(gens.derived_capacity(), 64);
+        assert_eq!(
+            BulletproofGens::<Affine>::try_new(64, 2, bytes).err(),
+            Some(GeneratorsError::BudgetExceeded {
+                needed: 2 * bytes,
+                budget: bytes
+            })
+        );
+        // Sizes overflowing the address space are over any budget.
+        assert!(matches!(
+            BulletproofGens::<Affine>::try_new(usize::MAX, 1, usize::MAX - 1),
+            Err(GeneratorsError::BudgetExceeded {
+                needed: usize::MAX,
+                ..
+            })
+        ));
+        // Allocations the allocator cannot serve fail instead of aborting.
+        assert!(matches!(
+            BulletproofGens::<Affine>::try_new(usize::MAX / 2, 1, usize::MAX),
+            Err(GeneratorsError::AllocationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn precomputed_gens_msm_matches_msm() {
+        use ark_std::UniformRand;
+
+        let pc_gens = PedersenGens::<Affine>::default();
+        let bp_gens = BulletproofGens::<Affine>::new(64, 1);
+        let precomputed = PrecomputedGens::new(&pc_gens, &bp_gens, 32).unwrap();
+        assert!(PrecomputedGens::new(&pc_gens, &bp_gens, 128).is_err());
+
+        let mut rng = rand::thread_rng();
+        // zero, small and large scalars, -1 has all bits of the modulus set
+        let scalars: Vec<Fr> = (0..2 + 2 * 32)
+            .map(|i| match i % 4 {
+                0 => Fr::zero(),
+                1 => Fr::from(i as u64),
+                2 => -Fr::from(1u64),
+                _ => Fr::rand(&mut rng),
+            })
+            .collect();
+        let generators: Vec<Affine> = iter::once(pc_gens.B)
+            .chain(iter::once(pc_gens.B_blinding))
+            .chain(bp_gens.share(0).G(32).copied())
+            .chain(bp_gens.share(0).H(32).copied())
+            .collect();
+
+        assert_eq!(
+            precomputed.msm(&scalars),
+            Projective::msm_unchecked(&generators, &scalars)
+        );
+    }
 }
@@ -17,6 +17,11 @@ use merlin::Transcript;
 
 use crate::errors::ProofError;
 use crate::transcript::TranscriptProtocol;
+use crate::util::deserialize_bounded_vec;
+
+/// The most rounds of a proof, and so the longest `L_vec` and `R_vec` deserialized:
+/// 4 billion multiplications should be enough for anyone.
+pub(crate) const MAX_LG_N: usize = 31;
 
 #[derive(Clone, Debug)]
 pub struct InnerProductProof<C: AffineRepr> {
@@ -38,202 +43,31 @@ impl<C: AffineRepr> InnerProductProof<C> {
     ///
     /// The lengths of the vectors must all be the same, and must all be
     /// either 0 or a power of 2.
+    #[cfg(feature = "prover")]
     pub fn create(
         transcript: &mut Transcript,
         Q: &C,
         G_factors: &[C::ScalarField],
         H_factors: &[C::ScalarField],
-        mut G_vec: Vec<C>,
-        mut H_vec: Vec<C>,
-        mut a_vec: Vec<C::ScalarField>,
-        mut b_vec: Vec<C::ScalarField>,
+        G_vec: Vec<C>,
+        H_vec: Vec<C>,
+        a_vec: Vec<C::ScalarField>,
+        b_vec: Vec<C::ScalarField>,
     ) -> InnerProductProof<C> {
-        // Create slices G, H, a, b backed by their respective
-        // vectors.  This lets us reslice as we compress the lengths
-        // of the vectors in the main loop below.
-        let mut G = &mut G_vec[..];
-        let mut H = &mut H_vec[..];
-        let mut a = &mut a_vec[..];
-        let mut b = &mut b_vec[..];
-
-        let mut n = G.len();
-
-        // All of the input vectors must have the same length.
-        assert_eq!(G.len(), n);
-        assert_eq!(H.len(), n);
-        assert_eq!(a.len(), n);
-        assert_eq!(b.len(), n);
-        assert_eq!(G_factors.len(), n);
-        assert_eq!(H_factors.len(), n);
-
-        // All of the input vectors must have a length that is a power of two.
-        assert!(n.is_power_of_two());
-
-        transcript.innerproduct_domain_sep(n as u64);
-
-        let lg_n = n.next_power_of_two().trailing_zeros() as usize;
-        let mut L_vec = Vec::with_capacity(lg_n);
-        let mut R_vec = Vec::with_capacity(lg_n);
-
-        // If it's the first iteration, unroll the Hprime = H*y_inv scalar mults
-        // into multiscalar muls, for performance.
-        if n != 1 {
-            n /= 2;
-            let (a_L, a_R) = a.split_at_mut(n);
-            let (b_L, b_R) = b.split_at_mut(n);
-            let (G_L, G_R) = G.split_at_mut(n);
-            let (H_L, H_R) = H.split_at_mut(n);
-
-            let c_L = inner_product(a_L, b_R);
-            let c_R = inner_product(a_R, b_L);
-
-            let l_scalars: Vec<C::ScalarField> = a_L
-                .iter()
-                .zip(G_factors[n..2 * n].iter())
-                .map(|(a_L_i, g)| *a_L_i * g)
-                .chain(
-                    b_R.iter()
-                        .zip(H_factors[0..n].iter())
-                        .map(|(b_R_i, h)| *b_R_i * h),
-                )
-                .chain(iter::once(c_L))
-                .collect();
-            let l_points: Vec<C> = G_R
-                .iter()
-                .chain(H_L.iter())
-                .chain(iter::once(Q))
-                .copied()
-                .collect();
-            let L = C::Group::msm_unchecked(l_points.as_slice(), l_scalars.as_slice()).into();
-
-            let r_scalars: Vec<C::ScalarField> = a_R
-                .iter()
-                .zip(G_factors[0..n].iter())
-                .map(|(a_R_i, g)| *a_R_i * g)
-                .chain(
-                    b_L.iter()
-                        .zip(H_factors[n..2 * n].iter())
-                        .map(|(b_L_i, h)| *b_L_i * h),
-                )
-                .chain(iter::once(c_R))
-                .collect();
-            let r_points: Vec<C> = G_L
-                .iter()
-                .chain(H_R.iter())
-                .chain(iter::once(Q))
-                .copied()
-                .collect();
-            let R = C::Group::msm_unchecked(r_points.as_slice(), r_scalars.as_slice()).into();
-
-            L_vec.push(L);
-            R_vec.push(R);
-
-            transcript.append_point(b"L", &L);
-            transcript.append_point(b"R", &R);
-
-            let u = transcript.challenge_scalar::<C>(b"u");
-            let u_inv = if let Some(res) = u.inverse() {
-                res
-            } else {
-                panic!("u challenge is zero");
-            };
-
-            for i in 0..n {
-                a_L[i] = a_L[i] * u + u_inv * a_R[i];
-                b_L[i] = b_L[i] * u_inv + u * b_R[i];
-                G_L[i] = C::Group::msm_unchecked(
-                    &[G_L[i], G_R[i]],
-                    &[(u_inv * G_factors[i]), (u * G_factors[n + i])],
-                )
-                .into();
-                H_L[i] = C::Group::msm_unchecked(
-                    &[H_L[i], H_R[i]],
-                    &[(u * H_factors[i]), (u_inv * H_factors[n + i])],
-                )
-                .into();
-            }
-
-            a = a_L;
-            b = b_L;
-            G = G_L;
-            H = H_L;
-        }
-
-        while n != 1 {
-            n /= 2;
-            let (a_L, a_R) = a.split_at_mut(n);
-            let (b_L, b_R) = b.split_at_mut(n);
-            let (G_L, G_R) = G.split_at_mut(n);
-            let (H_L, H_R) = H.split_at_mut(n);
-
-            let c_L = inner_product(a_L, b_R);
-            let c_R = inner_product(a_R, b_L);
-
-            let L = C::Group::msm_unchecked(
-                G_R.iter()
-                    .chain(H_L.iter())
-                    .chain(iter::once(Q))
-                    .copied()
-                    .collect::<Vec<C>>()
-                    .as_slice(),
-                a_L.iter()
-                    .chain(b_R.iter())
-                    .chain(iter::once(&c_L))
-                    .copied()
-                    .collect::<Vec<C::ScalarField>>()
-                    .as_slice(),
-            )
-            .into();
-
-            let R = C::Group::msm_unchecked(
-                G_L.iter()
-                    .chain(H_R.iter())
-                    .chain(iter::once(Q))
-                    .copied()
-                    .collect::<Vec<C>>()
-                    .as_slice(),
-                a_R.iter()
-                    .chain(b_L.iter())
-                    .chain(iter::once(&c_R))
-                    .copied()
-                    .collect::<Vec<C::ScalarField>>()
-                    .as_slice(),
-            )
-            .into();
-
-            L_vec.push(L);
-            R_vec.push(R);
-
-            transcript.append_point(b"L", &L);
-            transcript.append_point(b"R", &R);
-
-            let u = transcript.challenge_scalar::<C>(b"u");
-            let u_inv = if let Some(res) = u.inverse() {
-                res
-            } else {
-                panic!("u challenge is zero");
-            };
-
-            for i in 0..n {
-                a_L[i] = a_L[i] * u + u_inv * a_R[i];
-                b_L[i] = b_L[i] * u_inv + u * b_R[i];
-                G_L[i] = C::Group::msm_unchecked(&[G_L[i], G_R[i]], &[u_inv, u]).into();
-                H_L[i] = C::Group::msm_unchecked(&[H_L[i], H_R[i]], &[u, u_inv]).into();
-            }
-
-            a = a_L;
-            b = b_L;
-            G = G_L;
-            H = H_L;
-            // todo collapse iteration one and rest?
-        }
-
-        InnerProductProof {
-            L_vec,
-            R_vec,
-            a: a[0],
-            b: b[0],
+        let mut prover = InnerProductProver::new(
+            transcript,
+            *Q,
+            G_factors.to_vec(),
+            H_factors.to_vec(),
+            G_vec,
+            H_vec,
+            a_vec,
+            b_vec,
+        );
+        while prover.rounds_remaining() > 0 {
+            prover.round(transcript);
         }
+        prover.finish()
     }
 
     /// Computes three vectors of verification scalars \\([u\_{i}^{2}]\\), \\([u\_{i}^{-2}]\\) and \\([s\_{i}]\\) for combined multiscalar multiplication
@@ -252,13 +86,12 @@ impl<C: AffineRepr> InnerProductProof<C> {
         ProofError,
     > {
         let lg_n = self.L_vec.len();
-        if lg_n >= 32 {
-            // 4 billion multiplications should be enough for anyone
-            // and this check prevents overflow in 1<<lg_n below.
+        if lg_n > MAX_LG_N {
+            // This check prevents overflow in 1<<lg_n below.
             return Err(ProofError::VerificationError);
         }
 
-        if n != (1 << lg_n) {
+        if n != (1 << lg_n) || self.R_vec.len() != lg_n {
             return Err(ProofError::VerificationError);
         }
 
@@ -378,8 +211,9 @@ impl<C: AffineRepr> InnerProductProof<C> {
     pub fn serialized_size(&self, compress: Compress) -> usize {
         // size of the two scalars
         let scalars_size = self.a.serialized_size(compress) * 2;
-        // size of the 2 point vectors (should be equal)
-        let l_and_r_size = self.L_vec.serialized_size(compress) * 2;
+        // size of the 2 point vectors, which only have equal lengths in well formed proofs
+        let l_and_r_size =
+            self.L_vec.serialized_size(compress) + self.R_vec.serialized_size(compress);
         scalars_size + l_and_r_size
     }
 }
@@ -396,8 +230,8 @@ impl<C: AffineRepr> CanonicalDeserialize for InnerProductProof<C> {
         validate: ark_serialize::Validate,
     ) -> Result<Self, SerializationError> {
         Ok(Self {
-            L_vec: Vec::<C>::deserialize_with_mode(&mut reader, compress, validate)?,
-            R_vec: Vec::<C>::deserialize_with_mode(&mut reader, compress, validate)?,
+            L_vec: deserialize_bounded_vec(&mut reader, compress, validate, MAX_LG_N)?,
+            R_vec: deserialize_bounded_vec(&mut reader, compress, validate, MAX_LG_N)?,
             a: C::ScalarField::deserialize_with_mode(&mut reader, compress, validate)?,
             b: C::ScalarField::deserialize_with_mode(&mut reader, compress, validate)?,
         })
@@ -413,8 +247,8 @@ impl<C: AffineRepr> CanonicalSerialize for InnerProductProof<C> {
     fn serialized_size(&self, mode: Compress) -> usize {
         // size of the two scalars
         let scalars_size = self.a.serialized_size(mode) * 2;
-        // size of the 2 point vectors (should be equal)
-        let l_and_r_size = self.L_vec.serialized_size(mode) * 2;
+        // size of the 2 point vectors, which only have equal lengths in well formed proofs
+        let l_and_r_size = self.L_vec.serialized_size(mode) + self.R_vec.serialized_size(mode);
         scalars_size + l_and_r_size
     }
 
@@ -435,6 +269,225 @@ impl<C: AffineRepr> CanonicalSerialize for InnerProductProof<C> {
     }
 }
 
+/// The factors of the `G` and of the `H` generators.
+#[cfg(feature = "prover")]
+type Factors<F> = (Vec<F>, Vec<F>);
+
+/// An inner-product proof being created one round at a time, see [`InnerProductProof::create`].
+///
+/// Each round halves the vectors and appends its `L` and `R` to the transcript,
+/// so the rounds must all be made, in order, on the same transcript.
+#[cfg(feature = "prover")]
+pub(crate) struct InnerProductProver<C: AffineRepr> {
+    Q: C,
+    // The factors of the generators, folded into them by the first round.
+    factors: Option<Factors<C::ScalarField>>,
+    G: Vec<C>,
+    H: Vec<C>,
+    a: Vec<C::ScalarField>,
+    b: Vec<C::ScalarField>,
+    L_vec: Vec<C>,
+    R_vec: Vec<C>,
+}
+
+#[cfg(feature = "prover")]
+impl<C: AffineRepr> InnerProductProver<C> {
+    /// Starts a proof with the arguments of [`InnerProductProof::create`], appending its domain separator.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        transcript: &mut Transcript,
+        Q: C,
+        G_factors: Vec<C::ScalarField>,
+        H_factors: Vec<C::ScalarField>,
+        G: Vec<C>,
+        H: Vec<C>,
+        a: Vec<C::ScalarField>,
+        b: Vec<C::ScalarField>,
+    ) -> Self {
+        let n = G.len();
+
+        // All of the input vectors must have the same length.
+        assert_eq!(H.len(), n);
+        assert_eq!(a.len(), n);
+        assert_eq!(b.len(), n);
+        assert_eq!(G_factors.len(), n);
+        assert_eq!(H_factors.len(), n);
+
+        // All of the input vectors must have a length that is a power of two.
+        assert!(n.is_power_of_two());
+
+        transcript.innerproduct_domain_sep(n as u64);
+
+        let lg_n = n.trailing_zeros() as usize;
+        Self {
+            Q,
+            factors: Some((G_factors, H_factors)),
+            G,
+            H,
+            a,
+            b,
+            L_vec: Vec::with_capacity(lg_n),
+            R_vec: Vec::with_capacity(lg_n),
+        }
+    }
+
+    /// The number of rounds left before the proof can be finished.
+    pub(crate) fn rounds_remaining(&self) -> usize {
+        self.G.len().trailing_zeros() as usize
+    }
+
+    /// Makes the next round, halving the vectors. Panics if no round is left.
+    pub(crate) fn round(&mut self, transcript: &mut Transcript) {
+        let mut n = self.G.len();
+        assert!(n > 1, "no inner-product round is left");
+        #[cfg(feature = "trace")]
+        let _round = tracing::debug_span!("inner_product_round", n).entered();
+        n /= 2;
+        let Q = &self.Q;
+        let (a_L, a_R) = self.a.split_at_mut(n);
+        let (b_L, b_R) = self.b.split_at_mut(n);
+        let (G_L, G_R) = self.G.split_at_mut(n);
+        let (H_L, H_R) = self.H.split_at_mut(n);
+
+        let c_L = inner_product(a_L, b_R);
+        let c_R = inner_product(a_R, b_L);
+
+        let (L, R) = match &self.factors {
+            // In the first round, unroll the Hprime = H*y_inv scalar mults
+            // into multiscalar muls, for performance.
+            Some((G_factors, H_factors)) => {
+                let l_scalars: Vec<C::ScalarField> = a_L
+                    .iter()
+                    .zip(G_factors[n..2 * n].iter())
+                    .map(|(a_L_i, g)| *a_L_i * g)
+                    .chain(
+                        b_R.iter()
+                            .zip(H_factors[0..n].iter())
+                            .map(|(b_R_i, h)| *b_R_i * h),
+                    )
+                    .chain(iter::once(c_L))
+                    .collect();
+                let l_points: Vec<C> = G_R
+                    .iter()
+                    .chain(H_L.iter())
+                    .chain(iter::once(Q))
+                    .copied()
+                    .collect();
+                let L: C =
+                    C::Group::msm_unchecked(l_points.as_slice(), l_scalars.as_slice()).into();
+
+                let r_scalars: Vec<C::ScalarField> = a_R
+                    .iter()
+                    .zip(G_factors[0..n].iter())
+                    .map(|(a_R_i, g)| *a_R_i * g)
+                    .chain(
+                        b_L.iter()
+                            .zip(H_factors[n..2 * n].iter())
+                            .map(|(b_L_i, h)| *b_L_i * h),
+                    )
+                    .chain(iter::once(c_R))
+                    .collect();
+                let r_points: Vec<C> = G_L
+                    .iter()
+                    .chain(H_R.iter())
+                    .chain(iter::once(Q))
+                    .copied()
+                    .collect();
+                let R: C =
+                    C::Group::msm_unchecked(r_points.as_slice(), r_scalars.as_slice()).into();
+                (L, R)
+            }
+            None => {
+                let L: C = C::Group::msm_unchecked(
+                    G_R.iter()
+                        .chain(H_L.iter())
+                        .chain(iter::once(Q))
+                        .copied()
+                        .collect::<Vec<C>>()
+                        .as_slice(),
+                    a_L.iter()
+                        .chain(b_R.iter())
+                        .chain(iter::once(&c_L))
+                        .copied()
+                        .collect::<Vec<C::ScalarField>>()
+                        .as_slice(),
+                )
+                .into();
+
+                let R: C = C::Group::msm_unchecked(
+                    G_L.iter()
+                        .chain(H_R.iter())
+                        .chain(iter::once(Q))
+                        .copied()
+                        .collect::<Vec<C>>()
+                        .as_slice(),
+                    a_R.iter()
+                        .chain(b_L.iter())
+                        .chain(iter::once(&c_R))
+                        .copied()
+                        .collect::<Vec<C::ScalarField>>()
+                        .as_slice(),
+                )
+                .into();
+                (L, R)
+            }
+        };
+
+        self.L_vec.push(L);
+        self.R_vec.push(R);
+
+        transcript.append_point(b"L", &L);
+        transcript.append_point(b"R", &R);
+
+        let u = transcript.challenge_scalar::<C>(b"u");
+        let u_inv = if let Some(res) = u.inverse() {
+            res
+        } else {
+            panic!("u challenge is zero");
+        };
+
+        for i in 0..n {
+            a_L[i] = a_L[i] * u + u_inv * a_R[i];
+            b_L[i] = b_L[i] * u_inv + u * b_R[i];
+            match &self.factors {
+                Some((G_factors, H_factors)) => {
+                    G_L[i] = C::Group::msm_unchecked(
+                        &[G_L[i], G_R[i]],
+                        &[(u_inv * G_factors[i]), (u * G_factors[n + i])],
+                    )
+                    .into();
+                    H_L[i] = C::Group::msm_unchecked(
+                        &[H_L[i], H_R[i]],
+                        &[(u * H_factors[i]), (u_inv * H_factors[n + i])],
+                    )
+                    .into();
+                }
+                None => {
+                    G_L[i] = C::Group::msm_unchecked(&[G_L[i], G_R[i]], &[u_inv, u]).into();
+                    H_L[i] = C::Group::msm_unchecked(&[H_L[i], H_R[i]], &[u, u_inv]).into();
+                }
+            }
+        }
+
+        self.a.truncate(n);
+        self.b.truncate(n);
+        self.G.truncate(n);
+        self.H.truncate(n);
+        self.factors = None;
+    }
+
+    /// The proof, once every round is made. Panics if a round is left.
+    pub(crate) fn finish(self) -> InnerProductProof<C> {
+        assert_eq!(self.rounds_remaining(), 0, "inner-product rounds are left");
+        InnerProductProof {
+            L_vec: self.L_vec,
+            R_vec: self.R_vec,
+            a: self.a[0],
+            b: self.b[0],
+        }
+    }
+}
+
 /// Computes an inner product of two vectors
 /// \\[
 ///    {\langle {\mathbf{a}}, {\mathbf{b}} \rangle} = \sum\_{i=0}^{n-1} a\_i \cdot b\_i.
@@ -585,6 +638,19 @@ mod tests {
         test_helper_create(64);
     }
 
+    #[test]
+    fn deserialize_rejects_oversized_prefix() {
+        let mut buf = Vec::new();
+        ((MAX_LG_N + 1) as u64)
+            .serialize_compressed(&mut buf)
+            .unwrap();
+        buf.resize(buf.len() + 64 * (MAX_LG_N + 1), 0);
+        assert!(matches!(
+            InnerProductProof::<Affine>::deserialize_compressed(&buf[..]),
+            Err(SerializationError::InvalidData)
+        ));
+    }
+
     #[test]
     fn test_inner_product() {
         let a = vec![F::from(1u64), F::from(2u64), F::from(3u64), F::from(4u64)];
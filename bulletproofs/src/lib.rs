@@ -7,8 +7,8 @@ mod generators;
 mod inner_product_proof;
 mod transcript;
 
-pub use crate::errors::ProofError;
-pub use crate::generators::{BulletproofGens, BulletproofGensShare, PedersenGens};
+pub use crate::errors::{GeneratorsError, ProofError};
+pub use crate::generators::{BulletproofGens, BulletproofGensShare, PedersenGens, PrecomputedGens};
 
 #[cfg(feature = "std")]
 pub mod r1cs;
@@ -77,6 +77,36 @@ pub trait ConstraintSystem<F: Field> {
     /// lc = 0
     /// ```
     fn constrain(&mut self, lc: LinearCombination<F>);
+
+    /// Returns the constant `value`, a public input of the statement rather than a committed one,
+    /// e.g. a public amount.
+    ///
+    /// The prover bakes it into the constraints. The verifier records it and returns [`Variable::Public`]
+    /// in its place, so that [`Verifier::verify_ref`](crate::r1cs::Verifier::verify_ref) checks the constraints
+    /// against the constants of each statement. Constants of the circuit itself need not go through here.
+    fn public_constant(&mut self, value: F) -> LinearCombination<F> {
+        value.into()
+    }
+
+    /// Enters a namespace that labels the multipliers allocated until the matching `pop_namespace`.
+    /// Namespaces nest, see `Prover::multiplier_namespace`.
+    ///
+    /// Only the prover records namespaces, and only with the `debug` feature: otherwise this does nothing.
+    fn push_namespace(&mut self, _name: &str) {}
+
+    /// Leaves the namespace entered by the last call to `push_namespace`.
+    fn pop_namespace(&mut self) {}
+
+    /// The operations laid down so far, see `diagnose_mismatch`.
+    #[cfg(feature = "debug")]
+    fn operation_log(&self) -> &crate::r1cs::OperationLog;
+
+    /// The running digest of the operations laid down so far, equal for a prover and a verifier
+    /// that built the same statement in the same order.
+    #[cfg(feature = "debug")]
+    fn operation_digest(&self) -> [u8; 32] {
+        self.operation_log().digest()
+    }
 }
 
 /// An extension to the constraint system trait that permits randomized constraints.
@@ -20,6 +20,9 @@ pub enum Variable<F: Field> {
     MultiplierOutput(usize),
     /// Represents the constant 1.
     One(PhantomData<F>),
+    /// Represents the public constant of the statement at this index, as recorded by a verifier,
+    /// see [`ConstraintSystem::public_constant`](crate::r1cs::ConstraintSystem::public_constant).
+    Public(usize),
 }
 
 impl<F: Field> From<Variable<F>> for LinearCombination<F> {
@@ -52,6 +55,92 @@ pub fn constant<F: Field, I: Into<F>>(c: I) -> LinearCombination<F> {
     }
 }
 
+/// An integer of any width or sign, converted to a field element reduced modulo its characteristic,
+/// a negative integer to the negation of its absolute value.
+///
+/// `From<i64>` and the like cannot be implemented for `LinearCombination<F>`, as they would overlap
+/// with `From<F>`: use [`LinearCombination::from_integer`] or the [`lc!`](crate::lc) macro instead.
+pub trait IntoScalar: Copy {
+    fn into_scalar<F: Field>(self) -> F;
+}
+
+macro_rules! impl_into_scalar_unsigned {
+    ($($t:ty),*) => {
+        $(impl IntoScalar for $t {
+            fn into_scalar<F: Field>(self) -> F {
+                F::from(self)
+            }
+        })*
+    };
+}
+
+macro_rules! impl_into_scalar_signed {
+    ($($t:ty),*) => {
+        $(impl IntoScalar for $t {
+            fn into_scalar<F: Field>(self) -> F {
+                // The absolute value of the smallest integer does not fit the signed type, it does the unsigned one.
+                let abs = F::from(self.unsigned_abs());
+                if self < 0 {
+                    -abs
+                } else {
+                    abs
+                }
+            }
+        })*
+    };
+}
+
+impl_into_scalar_unsigned!(u8, u16, u32, u64, u128);
+impl_into_scalar_signed!(i8, i16, i32, i64, i128);
+
+impl IntoScalar for usize {
+    fn into_scalar<F: Field>(self) -> F {
+        F::from(self as u64)
+    }
+}
+
+impl IntoScalar for isize {
+    fn into_scalar<F: Field>(self) -> F {
+        (self as i64).into_scalar()
+    }
+}
+
+/// Builds a linear combination from a sum of terms, each an integer coefficient times a variable,
+/// a variable or an integer constant, e.g. `lc!(3 * a - 2 * b + 7)`.
+///
+/// A coefficient is an integer literal or a parenthesized integer expression, e.g. `(-offset) * a`,
+/// converted with [`IntoScalar`]. A variable is an identifier or a parenthesized expression
+/// of a [`Variable`] or a [`LinearCombination`], e.g. `(vars[0])`.
+#[macro_export]
+macro_rules! lc {
+    (@sum $sum:expr;) => {
+        $sum
+    };
+    (@sum $sum:expr; $op:tt $coefficient:literal * $variable:tt $($rest:tt)*) => {
+        $crate::lc!(@sum $sum $op $crate::r1cs::LinearCombination::from($variable)
+            .scalar_mul($crate::r1cs::IntoScalar::into_scalar($coefficient)); $($rest)*)
+    };
+    (@sum $sum:expr; $op:tt ($coefficient:expr) * $variable:tt $($rest:tt)*) => {
+        $crate::lc!(@sum $sum $op $crate::r1cs::LinearCombination::from($variable)
+            .scalar_mul($crate::r1cs::IntoScalar::into_scalar($coefficient)); $($rest)*)
+    };
+    (@sum $sum:expr; $op:tt $constant:literal $($rest:tt)*) => {
+        $crate::lc!(@sum $sum $op $crate::r1cs::LinearCombination::from_integer($constant); $($rest)*)
+    };
+    (@sum $sum:expr; $op:tt $variable:tt $($rest:tt)*) => {
+        $crate::lc!(@sum $sum $op $crate::r1cs::LinearCombination::from($variable); $($rest)*)
+    };
+    () => {
+        $crate::r1cs::LinearCombination::default()
+    };
+    (- $($terms:tt)+) => {
+        $crate::lc!(@sum $crate::r1cs::LinearCombination::default(); - $($terms)+)
+    };
+    ($($terms:tt)+) => {
+        $crate::lc!(@sum $crate::r1cs::LinearCombination::default(); + $($terms)+)
+    };
+}
+
 // Arithmetic on variables produces linear combinations
 
 impl<F: Field> Neg for Variable<F> {
@@ -184,6 +273,19 @@ impl<F: Field, L: Into<LinearCombination<F>>> Sub<L> for LinearCombination<F> {
     }
 }
 
+/// The difference of two borrowed linear combinations, in a single allocation with room for the term
+/// [`ConstraintSystem::multiply`](::r1cs::ConstraintSystem::multiply) appends to its operands.
+impl<'a, F: Field> Sub<&'a LinearCombination<F>> for &'a LinearCombination<F> {
+    type Output = LinearCombination<F>;
+
+    fn sub(self, rhs: &'a LinearCombination<F>) -> Self::Output {
+        let mut terms = Vec::with_capacity(self.terms.len() + rhs.terms.len() + 1);
+        terms.extend(self.terms.iter().copied());
+        terms.extend(rhs.terms.iter().map(|(var, coeff)| (*var, -(*coeff))));
+        LinearCombination { terms }
+    }
+}
+
 // impl<F: Field> Mul<LinearCombination<F>> for F {
 //     type Output = LinearCombination<F>;
 
@@ -198,6 +300,11 @@ impl<F: Field, L: Into<LinearCombination<F>>> Sub<L> for LinearCombination<F> {
 // }
 
 impl<F: Field> LinearCombination<F> {
+    /// The constant `c`, an integer of any width or sign, see [`IntoScalar`].
+    pub fn from_integer<I: IntoScalar>(c: I) -> Self {
+        constant(c.into_scalar::<F>())
+    }
+
     pub fn scalar_mul(self, scalar: F) -> LinearCombination<F> {
         let out_terms = self
             .terms
@@ -230,3 +337,74 @@ impl<F: Field, S: Into<F>> Mul<S> for LinearCombination<F> {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ark_pallas::Fr;
+    use std::str::FromStr;
+
+    // The value of `c` in the field, computed apart from `IntoScalar`.
+    fn expected(c: &str) -> Fr {
+        Fr::from_str(c).unwrap()
+    }
+
+    #[test]
+    fn integers_at_the_extremes_reduce_to_their_field_values() {
+        // The scalar field of Pallas is p = 2^254 + 45560315531506369815346746415080538113.
+        assert_eq!(
+            i64::MIN.into_scalar::<Fr>(),
+            expected(
+                "28948022309329048855892746252171976963363056481941647379670519376356508172289"
+            )
+        );
+        assert_eq!(
+            i64::MAX.into_scalar::<Fr>(),
+            expected("9223372036854775807")
+        );
+        assert_eq!(
+            u128::MAX.into_scalar::<Fr>(),
+            expected("340282366920938463463374607431768211455")
+        );
+        assert_eq!(
+            i128::MIN.into_scalar::<Fr>(),
+            expected(
+                "28948022309329048855892746252171976963192915298481178147948055444677478842369"
+            )
+        );
+        assert_eq!(
+            (-1i8).into_scalar::<Fr>(),
+            expected(
+                "28948022309329048855892746252171976963363056481941647379679742748393362948096"
+            )
+        );
+        assert_eq!((-1i8).into_scalar::<Fr>(), -Fr::from(1u64));
+        assert_eq!(0i128.into_scalar::<Fr>(), Fr::from(0u64));
+        assert_eq!(
+            LinearCombination::<Fr>::from_integer(i64::MIN),
+            constant(i64::MIN.into_scalar::<Fr>())
+        );
+    }
+
+    #[test]
+    fn lc_macro_matches_the_arithmetic_of_variables() {
+        let a = Variable::<Fr>::Committed(0);
+        let b = Variable::<Fr>::MultiplierLeft(1);
+        let vars = [a, b];
+        let offset = -5i64;
+
+        assert_eq!(
+            lc!(3 * a - 2 * b + 7),
+            LinearCombination::default() + a * Fr::from(3u64) - b * Fr::from(2u64)
+                + constant(Fr::from(7u64))
+        );
+        assert_eq!(
+            lc!(-a + (offset) * (vars[1]) - 340282366920938463463374607431768211455u128),
+            LinearCombination::default() - a + b * -Fr::from(5u64)
+                - constant(expected("340282366920938463463374607431768211455"))
+        );
+        assert_eq!(lc!(a), LinearCombination::default() + a);
+        assert_eq!(lc!(), LinearCombination::<Fr>::default());
+    }
+}
@@ -12,3 +12,112 @@ pub struct Metrics {
     /// Number of linear constraints added in the randomization phase.
     pub phase_two_constraints: usize,
 }
+
+/// Time spent in one phase of proving or verifying.
+#[cfg(feature = "trace")]
+#[derive(Debug, Clone)]
+pub struct Phase {
+    /// Name of the phase, the same as the name of its tracing span.
+    pub name: &'static str,
+    /// Wall clock time spent in the phase.
+    pub duration: std::time::Duration,
+}
+
+/// A summary of where time went in a single proof or verification.
+///
+/// See [`Prover::prove_with_metrics`](::r1cs::Prover::prove_with_metrics)
+/// and [`Verifier::verify_with_metrics`](::r1cs::Verifier::verify_with_metrics).
+#[cfg(feature = "trace")]
+#[derive(Debug, Clone)]
+pub struct ProofMetrics {
+    /// Size of the constraint system before padding.
+    pub constraint_system: Metrics,
+    /// Number of multipliers after padding to a power of two.
+    pub padded_multipliers: usize,
+    /// Number of points of the multiscalar multiplication checking a verified proof, zero for a proof made.
+    /// Only the first `padded_multipliers` G and H generators are among them, whatever the capacity of the generators.
+    pub msm_points: usize,
+    /// The phases in the order they completed.
+    pub phases: Vec<Phase>,
+}
+
+#[cfg(feature = "trace")]
+impl ProofMetrics {
+    /// Returns the total time spent in phases called `name`, if any ran.
+    pub fn duration(&self, name: &str) -> Option<std::time::Duration> {
+        self.phases
+            .iter()
+            .filter(|phase| phase.name == name)
+            .map(|phase| phase.duration)
+            .reduce(|a, b| a + b)
+    }
+}
+
+/// Collects phase timings during a proof or verification.
+/// Without the `trace` feature this is empty and recording is a no-op.
+#[derive(Default)]
+pub(crate) struct PhaseRecorder {
+    #[cfg(feature = "trace")]
+    pub(crate) phases: Vec<Phase>,
+    #[cfg(feature = "trace")]
+    pub(crate) padded_multipliers: usize,
+    #[cfg(feature = "trace")]
+    pub(crate) msm_points: usize,
+}
+
+/// A running phase, created by `start_phase!` and finished by `PhaseRecorder::end`.
+pub(crate) struct PhaseGuard {
+    #[cfg(feature = "trace")]
+    pub(crate) name: &'static str,
+    #[cfg(feature = "trace")]
+    pub(crate) start: std::time::Instant,
+    #[cfg(feature = "trace")]
+    pub(crate) span: tracing::span::EnteredSpan,
+}
+
+impl PhaseRecorder {
+    #[allow(unused_variables)]
+    pub(crate) fn end(&mut self, phase: PhaseGuard) {
+        #[cfg(feature = "trace")]
+        {
+            let duration = phase.start.elapsed();
+            drop(phase.span);
+            self.phases.push(Phase {
+                name: phase.name,
+                duration,
+            });
+        }
+    }
+
+    #[allow(unused_variables)]
+    pub(crate) fn set_padded_multipliers(&mut self, padded_multipliers: usize) {
+        #[cfg(feature = "trace")]
+        {
+            self.padded_multipliers = padded_multipliers;
+        }
+    }
+
+    #[allow(unused_variables)]
+    pub(crate) fn set_msm_points(&mut self, msm_points: usize) {
+        #[cfg(feature = "trace")]
+        {
+            self.msm_points = msm_points;
+        }
+    }
+}
+
+/// Enters a tracing span called `$name` with the given fields and starts timing it.
+/// Expands to an empty guard without the `trace` feature, so the fields are not evaluated.
+macro_rules! start_phase {
+    ($name:literal $(, $($fields:tt)*)?) => {{
+        #[cfg(feature = "trace")]
+        let phase = crate::r1cs::metrics::PhaseGuard {
+            name: $name,
+            start: std::time::Instant::now(),
+            span: tracing::info_span!($name $(, $($fields)*)?).entered(),
+        };
+        #[cfg(not(feature = "trace"))]
+        let phase = crate::r1cs::metrics::PhaseGuard {};
+        phase
+    }};
+}
@@ -1,20 +1,33 @@
+#[macro_use]
+mod metrics;
 mod constraint_system;
 mod linear_combination;
-mod metrics;
+#[cfg(feature = "prover")]
+pub mod mpc;
+#[cfg(feature = "debug")]
+mod operations;
 mod proof;
+#[cfg(feature = "prover")]
 mod prover;
+#[cfg(any(test, feature = "simulate"))]
+pub mod simulate;
 mod verifier;
 
 pub use self::constraint_system::{
     ConstraintSystem, RandomizableConstraintSystem, RandomizedConstraintSystem,
 };
-pub use self::linear_combination::{constant, LinearCombination, Variable};
+pub use self::linear_combination::{constant, IntoScalar, LinearCombination, Variable};
 pub use self::metrics::Metrics;
-pub use self::proof::R1CSProof;
-pub use self::prover::Prover;
+#[cfg(feature = "trace")]
+pub use self::metrics::{Phase, ProofMetrics};
+#[cfg(feature = "debug")]
+pub use self::operations::{diagnose_mismatch, Mismatch, Operation, OperationLog};
+pub use self::proof::{ProofComponent, R1CSProof, R1CS_PROOF_VERSION};
+#[cfg(feature = "prover")]
+pub use self::prover::{Phase1, Phase2, Prover};
 pub use self::verifier::{batch_verify, VerificationTuple, Verifier};
 
-pub use crate::errors::R1CSError;
+pub use crate::errors::{MPCError, R1CSError};
 
 fn op_splits(op_deg: usize) -> Vec<(usize, usize)> {
     debug_assert_eq!(op_deg % 2, 0);
@@ -0,0 +1,375 @@
+#![allow(non_snake_case)]
+//! The coordinator side of the aggregation protocol.
+//!
+//! The coordinator lays down the joint constraint system on an ordinary [`Verifier`],
+//! combines the parties' messages and derives the challenges from the verifier's transcript,
+//! so the proof it assembles verifies against a verifier built the same way.
+//! It moves through the rounds of the protocol by consuming its state:
+//! [`Coordinator::new`] → [`CoordinatorAwaitingWireCommitments`] →
+//! [`CoordinatorAwaitingPolyCommitments`] → [`CoordinatorAwaitingProofShares`] → [`R1CSProof`].
+
+use core::borrow::BorrowMut;
+use core::iter;
+
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::Field;
+use ark_std::{One, Zero};
+use merlin::Transcript;
+
+use super::messages::*;
+use crate::errors::MPCError;
+use crate::generators::{BulletproofGens, PedersenGens};
+use crate::inner_product_proof::{inner_product, InnerProductProof};
+use crate::r1cs::{R1CSProof, Verifier, R1CS_PROOF_VERSION};
+use crate::transcript::TranscriptProtocol;
+use crate::util;
+
+/// Used to construct a coordinator for the aggregation protocol.
+pub struct Coordinator {}
+
+impl Coordinator {
+    /// Constructs a `CoordinatorAwaitingWireCommitments` from a verifier with the joint constraint system:
+    /// the high-level commitments of all parties in party order, followed by the parties' constraints in party order,
+    /// followed by any constraints between the parties.
+    /// Constraints between the parties must be linear, every multiplier belongs to exactly one party.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<'g, T: BorrowMut<Transcript>, C: AffineRepr>(
+        verifier: Verifier<T, C>,
+        pc_gens: &'g PedersenGens<C>,
+        bp_gens: &'g BulletproofGens<C>,
+    ) -> Result<CoordinatorAwaitingWireCommitments<'g, T, C>, MPCError> {
+        if !verifier.deferred_constraints.is_empty() || !verifier.vec_comms.is_empty() {
+            return Err(MPCError::UnsupportedConstraints);
+        }
+        if bp_gens.gens_capacity < verifier.num_vars.next_power_of_two() {
+            return Err(MPCError::InvalidGeneratorsLength);
+        }
+        Ok(CoordinatorAwaitingWireCommitments {
+            verifier,
+            pc_gens,
+            bp_gens,
+        })
+    }
+}
+
+/// A coordinator waiting for the parties' commitments to their wires.
+pub struct CoordinatorAwaitingWireCommitments<'g, T: BorrowMut<Transcript>, C: AffineRepr> {
+    verifier: Verifier<T, C>,
+    pc_gens: &'g PedersenGens<C>,
+    bp_gens: &'g BulletproofGens<C>,
+}
+
+impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> CoordinatorAwaitingWireCommitments<'g, T, C> {
+    /// Combines the parties' wire commitments, which must tile the joint constraint system in party order,
+    /// and returns the challenge for each party.
+    #[allow(clippy::type_complexity)]
+    pub fn receive_wire_commitments(
+        mut self,
+        wire_commitments: Vec<WireCommitment<C>>,
+    ) -> Result<
+        (
+            CoordinatorAwaitingPolyCommitments<'g, T, C>,
+            Vec<WireChallenge<C>>,
+        ),
+        MPCError,
+    > {
+        let n = self.verifier.num_vars;
+        let (mut multipliers, mut commitments) = (0, 0);
+        for (j, wire_commitment) in wire_commitments.iter().enumerate() {
+            let V = self
+                .verifier
+                .V
+                .get(commitments..commitments + wire_commitment.V.len());
+            if wire_commitment.offset != multipliers
+                || wire_commitment.multipliers > n - multipliers
+                || V != Some(&wire_commitment.V[..])
+            {
+                return Err(MPCError::WireLayoutMismatch { party: j });
+            }
+            multipliers += wire_commitment.multipliers;
+            commitments += wire_commitment.V.len();
+        }
+        if multipliers != n || commitments != self.verifier.V.len() {
+            return Err(MPCError::WireLayoutMismatch {
+                party: wire_commitments.len(),
+            });
+        }
+
+        let sum = |point: fn(&WireCommitment<C>) -> C| -> C {
+            wire_commitments
+                .iter()
+                .map(point)
+                .sum::<C::Group>()
+                .into_affine()
+        };
+        let A_I = sum(|c| c.A_I);
+        let A_O = sum(|c| c.A_O);
+        let S = sum(|c| c.S);
+
+        // The same transcript as the verifier, for a proof without randomized constraints.
+        let transcript = self.verifier.transcript.borrow_mut();
+        transcript.append_u64(b"m", self.verifier.V.len() as u64);
+        transcript.r1cs_version(R1CS_PROOF_VERSION);
+        transcript.append_point(b"A_I1", &A_I);
+        transcript.append_point(b"A_O1", &A_O);
+        transcript.append_point(b"S1", &S);
+        transcript.r1cs_1phase_domain_sep();
+        transcript.append_point(b"A_I2", &C::zero());
+        transcript.append_point(b"A_O2", &C::zero());
+        transcript.append_point(b"S2", &C::zero());
+
+        let y = transcript.challenge_scalar::<C>(b"y");
+        let z = transcript.challenge_scalar::<C>(b"z");
+
+        let (wL, wR, wO, wV, _, wc) = self.verifier.flattened_constraints(&z);
+
+        let y_inv = y.inverse().unwrap();
+        let yneg_wR: Vec<_> = wR
+            .iter()
+            .zip(util::exp_iter(y_inv))
+            .map(|(wRi, exp_y_inv)| *wRi * exp_y_inv)
+            .collect();
+        let delta = inner_product(&yneg_wR, &wL);
+
+        let (mut multipliers, mut commitments) = (0, 0);
+        let wire_challenges = wire_commitments
+            .iter()
+            .map(|wire_commitment| {
+                let wires = multipliers..multipliers + wire_commitment.multipliers;
+                let values = commitments..commitments + wire_commitment.V.len();
+                multipliers = wires.end;
+                commitments = values.end;
+                WireChallenge {
+                    y,
+                    z,
+                    wL: wL[wires.clone()].to_vec(),
+                    wR: wR[wires.clone()].to_vec(),
+                    wO: wO[wires].to_vec(),
+                    wV: wV[values].to_vec(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let next_state = CoordinatorAwaitingPolyCommitments {
+            verifier: self.verifier,
+            pc_gens: self.pc_gens,
+            bp_gens: self.bp_gens,
+            wire_commitments,
+            wire_challenges: wire_challenges.clone(),
+            A_I,
+            A_O,
+            S,
+            y,
+            wV,
+            wc,
+            delta,
+        };
+        Ok((next_state, wire_challenges))
+    }
+}
+
+/// A coordinator which has sent the challenges \\(y, z\\)
+/// and is waiting for the parties' commitments to their shares of \\(t(x)\\).
+pub struct CoordinatorAwaitingPolyCommitments<'g, T: BorrowMut<Transcript>, C: AffineRepr> {
+    verifier: Verifier<T, C>,
+    pc_gens: &'g PedersenGens<C>,
+    bp_gens: &'g BulletproofGens<C>,
+    wire_commitments: Vec<WireCommitment<C>>,
+    wire_challenges: Vec<WireChallenge<C>>,
+    A_I: C,
+    A_O: C,
+    S: C,
+    y: C::ScalarField,
+    wV: Vec<C::ScalarField>,
+    wc: C::ScalarField,
+    delta: C::ScalarField,
+}
+
+impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> CoordinatorAwaitingPolyCommitments<'g, T, C> {
+    /// Combines the parties' commitments to \\(t(x)\\) and returns the challenge for all parties.
+    pub fn receive_poly_commitments(
+        mut self,
+        poly_commitments: Vec<PolyCommitment<C>>,
+    ) -> Result<(CoordinatorAwaitingProofShares<'g, T, C>, PolyChallenge<C>), MPCError> {
+        if poly_commitments.len() != self.wire_commitments.len() {
+            return Err(MPCError::WrongNumPolyCommitments);
+        }
+
+        let T: Vec<C> = (0..7)
+            .map(|d| {
+                poly_commitments
+                    .iter()
+                    .map(|poly_commitment| poly_commitment.T()[d])
+                    .sum::<C::Group>()
+                    .into_affine()
+            })
+            .collect();
+
+        let transcript = self.verifier.transcript.borrow_mut();
+        for (d, T_d) in T.iter().enumerate() {
+            if d != 2 {
+                transcript.append_point(util::T_LABELS[d], T_d);
+            }
+        }
+        let u = transcript.challenge_scalar::<C>(b"u");
+        let x = transcript.challenge_scalar::<C>(b"x");
+
+        let next_state = CoordinatorAwaitingProofShares {
+            previous: self,
+            T,
+            u,
+            x,
+        };
+        Ok((next_state, PolyChallenge { x }))
+    }
+}
+
+/// A coordinator which has sent the challenge \\(x\\) and is waiting for the parties' proof shares.
+pub struct CoordinatorAwaitingProofShares<'g, T: BorrowMut<Transcript>, C: AffineRepr> {
+    previous: CoordinatorAwaitingPolyCommitments<'g, T, C>,
+    T: Vec<C>,
+    u: C::ScalarField,
+    x: C::ScalarField,
+}
+
+impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> CoordinatorAwaitingProofShares<'g, T, C> {
+    /// Checks the proof shares and assembles them into a proof.
+    ///
+    /// Each share is checked against its party's wire commitments, and the combined
+    /// \\(t(x)\\) against the high-level commitments and the polynomial commitments.
+    /// The coordinator learns nothing the proof does not reveal:
+    /// the shares are masked by the parties' blinding factors.
+    pub fn receive_shares(
+        mut self,
+        proof_shares: Vec<ProofShare<C>>,
+    ) -> Result<R1CSProof<C>, MPCError> {
+        let state = &mut self.previous;
+        if proof_shares.len() != state.wire_commitments.len() {
+            return Err(MPCError::WrongNumProofShares);
+        }
+
+        let bad_shares: Vec<usize> = proof_shares
+            .iter()
+            .enumerate()
+            .filter(|(j, share)| !self.audit_share(*j, share))
+            .map(|(j, _)| j)
+            .collect();
+        if !bad_shares.is_empty() {
+            return Err(MPCError::MalformedProofShares { bad_shares });
+        }
+
+        let state = &mut self.previous;
+        let x = self.x;
+        let n = state.verifier.num_vars;
+        let padded_n = n.next_power_of_two();
+        let exp_y: Vec<_> = util::exp_iter(state.y).take(padded_n).collect();
+
+        // Beyond n, l(x) is zero and r(x) is its constant term.
+        let mut l_vec = Vec::with_capacity(padded_n);
+        let mut r_vec = Vec::with_capacity(padded_n);
+        for share in proof_shares.iter() {
+            l_vec.extend_from_slice(&share.l_vec);
+            r_vec.extend_from_slice(&share.r_vec);
+        }
+        l_vec.resize(padded_n, C::ScalarField::zero());
+        r_vec.extend(exp_y[n..].iter().map(|exp_y| -*exp_y));
+
+        let t_x = inner_product(&l_vec, &r_vec);
+        let t_x_blinding: C::ScalarField = proof_shares.iter().map(|s| s.t_x_blinding).sum();
+        let e_blinding: C::ScalarField = proof_shares.iter().map(|s| s.e_blinding).sum();
+
+        // t(x) B + t_x_blinding B_blinding = x^2 (<w_V, V> + (w_c + delta) B) + sum_{d != 2} x^d T_d
+        let xs: Vec<_> = util::exp_iter(x).take(7).collect();
+        let points: Vec<C> = iter::once(state.pc_gens.B)
+            .chain(iter::once(state.pc_gens.B_blinding))
+            .chain(state.verifier.V.iter().copied())
+            .chain(self.T.iter().copied())
+            .collect();
+        let scalars: Vec<C::ScalarField> = iter::once(t_x - xs[2] * (state.wc + state.delta))
+            .chain(iter::once(t_x_blinding))
+            .chain(state.wV.iter().map(|wVi| -xs[2] * wVi))
+            .chain(xs.iter().map(|x_d| -*x_d))
+            .collect();
+        if !C::Group::msm_unchecked(&points, &scalars).is_zero() {
+            return Err(MPCError::UnsatisfiedConstraints);
+        }
+
+        let transcript = state.verifier.transcript.borrow_mut();
+        transcript.append_scalar::<C>(b"t_x", &t_x);
+        transcript.append_scalar::<C>(b"t_x_blinding", &t_x_blinding);
+        transcript.append_scalar::<C>(b"e_blinding", &e_blinding);
+
+        // Get a challenge value to combine statements for the IPP
+        let w = transcript.challenge_scalar::<C>(b"w");
+        let Q = state.pc_gens.B.mul(w).into();
+
+        let mut G_factors = vec![C::ScalarField::one(); n];
+        G_factors.resize(padded_n, self.u);
+        let H_factors: Vec<_> = util::exp_iter(state.y.inverse().unwrap())
+            .zip(G_factors.iter())
+            .map(|(y_inv, u_or_1)| y_inv * u_or_1)
+            .collect();
+
+        let gens = state.bp_gens.share(0);
+        let ipp_proof = InnerProductProof::create(
+            transcript,
+            &Q,
+            &G_factors,
+            &H_factors,
+            gens.G(padded_n).copied().collect(),
+            gens.H(padded_n).copied().collect(),
+            l_vec,
+            r_vec,
+        );
+
+        Ok(R1CSProof {
+            version: R1CS_PROOF_VERSION,
+            A_I1: state.A_I,
+            A_O1: state.A_O,
+            S1: state.S,
+            A_I2: C::zero(),
+            A_O2: C::zero(),
+            S2: C::zero(),
+            T: self.T,
+            t_x,
+            t_x_blinding,
+            e_blinding,
+            ipp_proof,
+        })
+    }
+
+    /// Checks that party `j`'s share opens its wire commitments:
+    /// <l - x y^-n w_R, G> + <y^-n (r - w_O - x w_L) + 1, H> + e_blinding B_blinding = x A_I + x^2 A_O + x^3 S
+    fn audit_share(&self, j: usize, share: &ProofShare<C>) -> bool {
+        let state = &self.previous;
+        let (wire_commitment, wc) = (&state.wire_commitments[j], &state.wire_challenges[j]);
+        let (offset, n) = (wire_commitment.offset, wire_commitment.multipliers);
+        if share.l_vec.len() != n || share.r_vec.len() != n {
+            return false;
+        }
+        let x = self.x;
+        let exp_y_inv: Vec<_> = util::exp_iter(state.y.inverse().unwrap())
+            .skip(offset)
+            .take(n)
+            .collect();
+
+        let gens = state.bp_gens.share(0);
+        let points: Vec<C> = iter::once(state.pc_gens.B_blinding)
+            .chain(iter::once(wire_commitment.A_I))
+            .chain(iter::once(wire_commitment.A_O))
+            .chain(iter::once(wire_commitment.S))
+            .chain(gens.G(offset + n).skip(offset).copied())
+            .chain(gens.H(offset + n).skip(offset).copied())
+            .collect();
+        let scalars: Vec<C::ScalarField> = iter::once(share.e_blinding)
+            .chain(iter::once(-x))
+            .chain(iter::once(-x * x))
+            .chain(iter::once(-x * x * x))
+            .chain((0..n).map(|i| share.l_vec[i] - x * exp_y_inv[i] * wc.wR[i]))
+            .chain((0..n).map(|i| {
+                exp_y_inv[i] * (share.r_vec[i] - wc.wO[i] - x * wc.wL[i]) + C::ScalarField::one()
+            }))
+            .collect();
+        C::Group::msm_unchecked(&points, &scalars).is_zero()
+    }
+}
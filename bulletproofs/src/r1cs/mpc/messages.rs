@@ -0,0 +1,170 @@
+#![allow(non_snake_case)]
+//! The messages sent between the parties and the coordinator
+//! in the aggregation protocol.
+//!
+//! All messages serialize with the canonical arkworks serialization,
+//! fields in the order they are declared.
+
+use ark_ec::AffineRepr;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Write,
+};
+
+/// A party's commitments to its wires, sent to the coordinator in the first round.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WireCommitment<C: AffineRepr> {
+    /// Index of the party's first multiplier in the joint constraint system.
+    pub(super) offset: usize,
+    /// Number of multipliers the party allocated.
+    pub(super) multipliers: usize,
+    /// The party's commitments to its high-level variables.
+    pub(super) V: Vec<C>,
+    /// Commitment to the inputs of the party's multipliers.
+    pub(super) A_I: C,
+    /// Commitment to the outputs of the party's multipliers.
+    pub(super) A_O: C,
+    /// Commitment to the party's blinding factors.
+    pub(super) S: C,
+}
+
+impl<C: AffineRepr> WireCommitment<C> {
+    /// The party's commitments to its high-level variables,
+    /// which the verifier commits to in the joint constraint system.
+    pub fn commitments(&self) -> &[C] {
+        &self.V
+    }
+}
+
+/// The challenges \\(y, z\\) and the party's slice of the flattened constraints,
+/// sent by the coordinator to each party in the second round.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WireChallenge<C: AffineRepr> {
+    pub(super) y: C::ScalarField,
+    pub(super) z: C::ScalarField,
+    /// Weights of the party's multiplier left inputs.
+    pub(super) wL: Vec<C::ScalarField>,
+    /// Weights of the party's multiplier right inputs.
+    pub(super) wR: Vec<C::ScalarField>,
+    /// Weights of the party's multiplier outputs.
+    pub(super) wO: Vec<C::ScalarField>,
+    /// Weights of the party's high-level variables.
+    pub(super) wV: Vec<C::ScalarField>,
+}
+
+/// A party's commitments to the coefficients of its share of \\(t(x)\\),
+/// sent to the coordinator in the second round.
+///
+/// There is no commitment to the \\(x^2\\) coefficient,
+/// the verifier computes it from the high-level commitments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolyCommitment<C: AffineRepr> {
+    pub(super) T_0: C,
+    pub(super) T_1: C,
+    pub(super) T_3: C,
+    pub(super) T_4: C,
+    pub(super) T_5: C,
+    pub(super) T_6: C,
+}
+
+impl<C: AffineRepr> PolyCommitment<C> {
+    /// The commitments laid out like in [`R1CSProof`](::r1cs::R1CSProof), with the identity for \\(x^2\\).
+    pub(super) fn T(&self) -> [C; 7] {
+        [
+            self.T_0,
+            self.T_1,
+            C::zero(),
+            self.T_3,
+            self.T_4,
+            self.T_5,
+            self.T_6,
+        ]
+    }
+}
+
+/// The challenge \\(x\\), sent by the coordinator to all parties in the third round.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolyChallenge<C: AffineRepr> {
+    pub(super) x: C::ScalarField,
+}
+
+/// A party's share of the proof, sent to the coordinator in the last round.
+///
+/// The vectors are \\(l(x)\\) and \\(r(x)\\) over the party's multipliers,
+/// which are masked by the party's blinding factors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofShare<C: AffineRepr> {
+    pub(super) t_x_blinding: C::ScalarField,
+    pub(super) e_blinding: C::ScalarField,
+    pub(super) l_vec: Vec<C::ScalarField>,
+    pub(super) r_vec: Vec<C::ScalarField>,
+}
+
+/// Implements the canonical serialization of a message as its fields in order.
+macro_rules! impl_message_serialization {
+    ($message:ident { $($field:ident: $ty:ty),* $(,)? }) => {
+        impl<C: AffineRepr> CanonicalSerialize for $message<C> {
+            fn serialized_size(&self, compress: Compress) -> usize {
+                0 $(+ self.$field.serialized_size(compress))*
+            }
+
+            fn serialize_with_mode<W: Write>(
+                &self,
+                mut writer: W,
+                compress: Compress,
+            ) -> Result<(), SerializationError> {
+                $(self.$field.serialize_with_mode(&mut writer, compress)?;)*
+                Ok(())
+            }
+        }
+
+        impl<C: AffineRepr> Valid for $message<C> {
+            fn check(&self) -> Result<(), SerializationError> {
+                Ok(())
+            }
+        }
+
+        impl<C: AffineRepr> CanonicalDeserialize for $message<C> {
+            fn deserialize_with_mode<R: Read>(
+                mut reader: R,
+                compress: Compress,
+                validate: ark_serialize::Validate,
+            ) -> Result<Self, SerializationError> {
+                Ok(Self {
+                    $($field: <$ty>::deserialize_with_mode(&mut reader, compress, validate)?,)*
+                })
+            }
+        }
+    };
+}
+
+impl_message_serialization!(WireCommitment {
+    offset: usize,
+    multipliers: usize,
+    V: Vec<C>,
+    A_I: C,
+    A_O: C,
+    S: C,
+});
+impl_message_serialization!(WireChallenge {
+    y: C::ScalarField,
+    z: C::ScalarField,
+    wL: Vec<C::ScalarField>,
+    wR: Vec<C::ScalarField>,
+    wO: Vec<C::ScalarField>,
+    wV: Vec<C::ScalarField>,
+});
+impl_message_serialization!(PolyCommitment {
+    T_0: C,
+    T_1: C,
+    T_3: C,
+    T_4: C,
+    T_5: C,
+    T_6: C,
+});
+impl_message_serialization!(PolyChallenge { x: C::ScalarField });
+impl_message_serialization!(ProofShare {
+    t_x_blinding: C::ScalarField,
+    e_blinding: C::ScalarField,
+    l_vec: Vec<C::ScalarField>,
+    r_vec: Vec<C::ScalarField>,
+});
@@ -0,0 +1,22 @@
+//! Aggregation of a single proof from the witnesses of several provers, without a trusted dealer.
+//!
+//! The parties share one constraint system, each owning a contiguous range of its multipliers
+//! and high-level commitments; constraints between parties are linear.
+//! Every party proves its own part on an ordinary [`Prover`](::r1cs::Prover),
+//! and commits to its wires on its slice of the generators.
+//! A coordinator, who holds no secrets, combines the commitments and derives every challenge
+//! from the transcript of a [`Verifier`](::r1cs::Verifier) with the joint constraint system.
+//! The parties return their shares of \\(l(x)\\) and \\(r(x)\\), masked by their blinding factors,
+//! and the coordinator runs the inner product argument on the combined vectors.
+//! The resulting [`R1CSProof`](::r1cs::R1CSProof) verifies against a verifier built like the coordinator's.
+//!
+//! The rounds of the protocol are:
+//! 1. each party sends a [`WireCommitment`](messages::WireCommitment),
+//! 2. the coordinator sends each party a [`WireChallenge`](messages::WireChallenge),
+//!    and each party replies with a [`PolyCommitment`](messages::PolyCommitment),
+//! 3. the coordinator sends all parties a [`PolyChallenge`](messages::PolyChallenge),
+//!    and each party replies with a [`ProofShare`](messages::ProofShare).
+
+pub mod coordinator;
+pub mod messages;
+pub mod party;
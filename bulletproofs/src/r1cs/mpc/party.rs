@@ -0,0 +1,264 @@
+#![allow(non_snake_case)]
+//! The party side of the aggregation protocol.
+//!
+//! A party proves its own part of the joint constraint system with an ordinary
+//! [`Prover`], then moves through the rounds of the protocol by consuming its state:
+//! [`Party::new`] → [`PartyAwaitingPosition`] → [`PartyAwaitingWireChallenge`] →
+//! [`PartyAwaitingPolyChallenge`] → [`ProofShare`].
+
+use core::borrow::BorrowMut;
+
+use ark_ec::AffineRepr;
+use ark_ff::Field;
+use ark_std::{UniformRand, Zero};
+use merlin::Transcript;
+use zeroize::Zeroizing;
+
+use super::messages::*;
+use crate::errors::MPCError;
+use crate::generators::{BulletproofGens, PedersenGens};
+use crate::r1cs::prover::{commit_wires, Prover, Secrets};
+use crate::util;
+
+/// Used to construct a party for the aggregation protocol.
+pub struct Party {}
+
+impl Party {
+    /// Constructs a `PartyAwaitingPosition` from a prover the party laid down its
+    /// part of the constraint system on, after committing its high-level variables.
+    ///
+    /// The verifier must lay down the same constraints on the joint constraint system,
+    /// after committing all parties' high-level variables in party order.
+    /// The transcript of the prover is not used.
+    ///
+    /// The protocol supports neither randomized constraints nor vector commitments,
+    /// and the prover must not leave a multiplier half allocated, as the verifier would
+    /// fill it with the next party's wires.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<'g, T: BorrowMut<Transcript>, C: AffineRepr>(
+        prover: Prover<'g, T, C>,
+        bp_gens: &'g BulletproofGens<C>,
+    ) -> Result<PartyAwaitingPosition<'g, C>, MPCError> {
+        let Prover {
+            pc_gens,
+            secrets,
+            deferred_constraints,
+            pending_multiplier,
+            ..
+        } = prover;
+        if !deferred_constraints.is_empty()
+            || !secrets.vec_open.is_empty()
+            || pending_multiplier.is_some()
+        {
+            return Err(MPCError::UnsupportedConstraints);
+        }
+        Ok(PartyAwaitingPosition {
+            pc_gens,
+            bp_gens,
+            secrets,
+        })
+    }
+}
+
+/// A party waiting to learn where its multipliers sit in the joint constraint system.
+pub struct PartyAwaitingPosition<'g, C: AffineRepr> {
+    pc_gens: &'g PedersenGens<C>,
+    bp_gens: &'g BulletproofGens<C>,
+    secrets: Secrets<C::ScalarField>,
+}
+
+impl<'g, C: AffineRepr> PartyAwaitingPosition<'g, C> {
+    /// Assigns the party its first multiplier `offset` in the joint constraint system,
+    /// which is the number of multipliers of the parties before it,
+    /// and commits to its wires on the generators from that offset.
+    pub fn assign_position(
+        self,
+        offset: usize,
+    ) -> Result<(PartyAwaitingWireChallenge<'g, C>, WireCommitment<C>), MPCError> {
+        let n = self.secrets.a_L.len();
+        if self.bp_gens.gens_capacity < offset + n {
+            return Err(MPCError::InvalidGeneratorsLength);
+        }
+        let gens = self.bp_gens.share(0);
+
+        let mut rng = rand::thread_rng();
+        let i_blinding = C::ScalarField::rand(&mut rng);
+        let o_blinding = C::ScalarField::rand(&mut rng);
+        let s_blinding = C::ScalarField::rand(&mut rng);
+
+        let s_L: Zeroizing<Vec<C::ScalarField>> =
+            Zeroizing::new((0..n).map(|_| C::ScalarField::rand(&mut rng)).collect());
+        let s_R: Zeroizing<Vec<C::ScalarField>> =
+            Zeroizing::new((0..n).map(|_| C::ScalarField::rand(&mut rng)).collect());
+
+        let G = &gens.G_slice(offset + n)[offset..];
+        let H = &gens.H_slice(offset + n)[offset..];
+
+        // A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
+        let A_I = commit_wires(
+            self.pc_gens,
+            G,
+            H,
+            &self.secrets.a_L,
+            &self.secrets.a_R,
+            i_blinding,
+        );
+        // A_O = <a_O, G> + o_blinding * B_blinding
+        let A_O = commit_wires(self.pc_gens, G, &[], &self.secrets.a_O, &[], o_blinding);
+        // S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
+        let S = commit_wires(self.pc_gens, G, H, &s_L, &s_R, s_blinding);
+
+        let V = self
+            .secrets
+            .v
+            .iter()
+            .zip(self.secrets.v_blinding.iter())
+            .map(|(v, v_blinding)| self.pc_gens.commit(*v, *v_blinding))
+            .collect();
+
+        let wire_commitment = WireCommitment {
+            offset,
+            multipliers: n,
+            V,
+            A_I,
+            A_O,
+            S,
+        };
+        let next_state = PartyAwaitingWireChallenge {
+            pc_gens: self.pc_gens,
+            secrets: self.secrets,
+            offset,
+            i_blinding: Zeroizing::new(i_blinding),
+            o_blinding: Zeroizing::new(o_blinding),
+            s_blinding: Zeroizing::new(s_blinding),
+            s_L,
+            s_R,
+        };
+        Ok((next_state, wire_commitment))
+    }
+}
+
+/// A party which has committed to its wires and is waiting for the challenges \\(y, z\\).
+pub struct PartyAwaitingWireChallenge<'g, C: AffineRepr> {
+    pc_gens: &'g PedersenGens<C>,
+    secrets: Secrets<C::ScalarField>,
+    offset: usize,
+    i_blinding: Zeroizing<C::ScalarField>,
+    o_blinding: Zeroizing<C::ScalarField>,
+    s_blinding: Zeroizing<C::ScalarField>,
+    s_L: Zeroizing<Vec<C::ScalarField>>,
+    s_R: Zeroizing<Vec<C::ScalarField>>,
+}
+
+impl<'g, C: AffineRepr> PartyAwaitingWireChallenge<'g, C> {
+    /// Computes the party's share of \\(t(x)\\) from the flattened constraints
+    /// and commits to its coefficients.
+    pub fn apply_challenge(
+        self,
+        wc: &WireChallenge<C>,
+    ) -> Result<(PartyAwaitingPolyChallenge<C>, PolyCommitment<C>), MPCError> {
+        let n = self.secrets.a_L.len();
+        // A zero challenge would cancel the constraints, and the slices must cover our wires.
+        if wc.y.is_zero()
+            || wc.z.is_zero()
+            || wc.wL.len() != n
+            || wc.wR.len() != n
+            || wc.wO.len() != n
+            || wc.wV.len() != self.secrets.v.len()
+        {
+            return Err(MPCError::MaliciousDealer);
+        }
+
+        let exp_y: Vec<_> = util::exp_iter(wc.y).skip(self.offset).take(n).collect();
+        let exp_y_inv: Vec<_> = util::exp_iter(wc.y.inverse().unwrap())
+            .skip(self.offset)
+            .take(n)
+            .collect();
+
+        // l(x) = (a_L + y^-n * w_R) x + a_O x^2 + s_L x^3
+        // r(x) = (w_O - y^n) + (y^n * a_R + w_L) x + y^n * s_R x^3
+        let mut l_poly = util::VecPoly3::zero(n);
+        let mut r_poly = util::VecPoly3::zero(n);
+        for i in 0..n {
+            l_poly.1[i] = self.secrets.a_L[i] + exp_y_inv[i] * wc.wR[i];
+            l_poly.2[i] = self.secrets.a_O[i];
+            l_poly.3[i] = self.s_L[i];
+            r_poly.0[i] = wc.wO[i] - exp_y[i];
+            r_poly.1[i] = exp_y[i] * self.secrets.a_R[i] + wc.wL[i];
+            r_poly.3[i] = exp_y[i] * self.s_R[i];
+        }
+        let t_poly = util::VecPoly3::special_inner_product(&l_poly, &r_poly);
+
+        // The x^2 coefficient is committed to by the high-level commitments,
+        // its blinding is their blindings weighted by the constraints.
+        let mut rng = rand::thread_rng();
+        let t_blinding: Zeroizing<Vec<C::ScalarField>> = Zeroizing::new(
+            (0..7)
+                .map(|d| {
+                    if d == 2 {
+                        wc.wV
+                            .iter()
+                            .zip(self.secrets.v_blinding.iter())
+                            .map(|(w, v_blinding)| *w * v_blinding)
+                            .sum()
+                    } else {
+                        C::ScalarField::rand(&mut rng)
+                    }
+                })
+                .collect(),
+        );
+
+        let poly_commitment = PolyCommitment {
+            T_0: self.pc_gens.commit_blinding_only(t_blinding[0]),
+            T_1: self.pc_gens.commit(t_poly.t1, t_blinding[1]),
+            T_3: self.pc_gens.commit(t_poly.t3, t_blinding[3]),
+            T_4: self.pc_gens.commit(t_poly.t4, t_blinding[4]),
+            T_5: self.pc_gens.commit(t_poly.t5, t_blinding[5]),
+            T_6: self.pc_gens.commit(t_poly.t6, t_blinding[6]),
+        };
+        let next_state = PartyAwaitingPolyChallenge {
+            i_blinding: self.i_blinding,
+            o_blinding: self.o_blinding,
+            s_blinding: self.s_blinding,
+            t_blinding,
+            l_poly,
+            r_poly,
+        };
+        Ok((next_state, poly_commitment))
+    }
+}
+
+/// A party which has committed to its share of \\(t(x)\\) and is waiting for the challenge \\(x\\).
+pub struct PartyAwaitingPolyChallenge<C: AffineRepr> {
+    i_blinding: Zeroizing<C::ScalarField>,
+    o_blinding: Zeroizing<C::ScalarField>,
+    s_blinding: Zeroizing<C::ScalarField>,
+    t_blinding: Zeroizing<Vec<C::ScalarField>>,
+    l_poly: util::VecPoly3<C::ScalarField>,
+    r_poly: util::VecPoly3<C::ScalarField>,
+}
+
+impl<C: AffineRepr> PartyAwaitingPolyChallenge<C> {
+    /// Evaluates the party's polynomials at \\(x\\) to produce its proof share.
+    pub fn apply_challenge(self, pc: &PolyChallenge<C>) -> Result<ProofShare<C>, MPCError> {
+        let x = pc.x;
+        // A zero challenge would reveal the unmasked wires.
+        if x.is_zero() {
+            return Err(MPCError::MaliciousDealer);
+        }
+
+        let t_x_blinding = self
+            .t_blinding
+            .iter()
+            .rev()
+            .fold(C::ScalarField::zero(), |acc, t| acc * x + t);
+        let e_blinding = x * (*self.i_blinding + x * (*self.o_blinding + x * *self.s_blinding));
+
+        Ok(ProofShare {
+            t_x_blinding,
+            e_blinding,
+            l_vec: self.l_poly.eval(x),
+            r_vec: self.r_poly.eval(x),
+        })
+    }
+}
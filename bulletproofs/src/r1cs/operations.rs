@@ -0,0 +1,144 @@
+//! The operations laying down a constraint system, recorded with the `debug` feature.
+//!
+//! A prover and a verifier that build a statement in different orders, e.g. one more commitment
+//! or two gadgets called the other way around, only see a proof that does not verify.
+//! Both record the operations they lay down with their arity and a running digest of them:
+//! the digests differ from the first diverging operation on, which `diagnose_mismatch` reports.
+
+use core::fmt;
+
+use sha3::{Digest, Sha3_256};
+
+/// An operation on a constraint system, with its arity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    /// A commitment to a value, `Prover::commit` or `Verifier::commit`.
+    Commit,
+    /// A commitment to a vector of that many coordinates, `Prover::commit_vec` or `Verifier::commit_vec`.
+    CommitVec(usize),
+    /// `ConstraintSystem::multiply`, followed by the constraints of its two inputs.
+    Multiply,
+    /// `ConstraintSystem::allocate`.
+    Allocate,
+    /// `ConstraintSystem::allocate_multiplier`.
+    AllocateMultiplier,
+    /// `ConstraintSystem::constrain`, of a linear combination of that many terms.
+    Constrain(usize),
+}
+
+impl Operation {
+    fn encode(&self) -> [u8; 9] {
+        let (tag, arity) = match self {
+            Self::Commit => (0, 1),
+            Self::CommitVec(dimension) => (1, *dimension),
+            Self::Multiply => (2, 2),
+            Self::Allocate => (3, 1),
+            Self::AllocateMultiplier => (4, 2),
+            Self::Constrain(terms) => (5, *terms),
+        };
+        let mut encoding = [tag; 9];
+        encoding[1..].copy_from_slice(&(arity as u64).to_le_bytes());
+        encoding
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Commit => write!(f, "commit"),
+            Self::CommitVec(dimension) => write!(f, "commit_vec({})", dimension),
+            Self::Multiply => write!(f, "multiply"),
+            Self::Allocate => write!(f, "allocate"),
+            Self::AllocateMultiplier => write!(f, "allocate_multiplier"),
+            Self::Constrain(terms) => write!(f, "constrain({})", terms),
+        }
+    }
+}
+
+/// The operations laid down on a constraint system, in order, and the running digest of them,
+/// see `ConstraintSystem::operation_log`.
+#[derive(Clone, Debug)]
+pub struct OperationLog {
+    operations: Vec<Operation>,
+    digest: Sha3_256,
+}
+
+impl Default for OperationLog {
+    fn default() -> Self {
+        Self {
+            operations: Vec::new(),
+            digest: Sha3_256::new(),
+        }
+    }
+}
+
+impl OperationLog {
+    pub(crate) fn record(&mut self, operation: Operation) {
+        self.operations.push(operation);
+        self.digest.update(operation.encode());
+    }
+
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    /// The digest of the operations so far: equal for a prover and a verifier
+    /// that laid down the same operations in the same order.
+    pub fn digest(&self) -> [u8; 32] {
+        self.digest.clone().finalize().into()
+    }
+
+    // The digest in hexadecimal, for error messages.
+    pub(crate) fn digest_hex(&self) -> String {
+        self.digest()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+/// The first operation at which a prover and a verifier diverge, see `diagnose_mismatch`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The position of the operation in both logs.
+    pub index: usize,
+    /// The operation of the prover, none if it laid down fewer operations.
+    pub prover: Option<Operation>,
+    /// The operation of the verifier, none if it laid down fewer operations.
+    pub verifier: Option<Operation>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |operation: Option<Operation>| match operation {
+            Some(operation) => operation.to_string(),
+            None => "nothing".to_string(),
+        };
+        write!(
+            f,
+            "operation {}: the prover did {}, the verifier {}",
+            self.index,
+            name(self.prover),
+            name(self.verifier)
+        )
+    }
+}
+
+/// The first operation at which the logs of a prover and a verifier of the same statement differ,
+/// none if they laid down the same operations.
+pub fn diagnose_mismatch(prover: &OperationLog, verifier: &OperationLog) -> Option<Mismatch> {
+    if prover.digest() == verifier.digest() {
+        return None;
+    }
+    let (prover, verifier) = (prover.operations(), verifier.operations());
+    let index = prover
+        .iter()
+        .zip(verifier)
+        .position(|(p, v)| p != v)
+        .unwrap_or_else(|| prover.len().min(verifier.len()));
+    Some(Mismatch {
+        index,
+        prover: prover.get(index).copied(),
+        verifier: verifier.get(index).copied(),
+    })
+}
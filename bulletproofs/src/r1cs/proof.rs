@@ -8,10 +8,16 @@ use ark_serialize::{
 
 use crate::errors::R1CSError;
 use crate::inner_product_proof::InnerProductProof;
+use crate::util::{deserialize_bounded_vec, T_LABELS};
 
 const ONE_PHASE_COMMITMENTS: u8 = 0;
 const TWO_PHASE_COMMITMENTS: u8 = 1;
 
+/// The version of the format of an [`R1CSProof`] and of the protocol it is proven with.
+/// It is serialized first and appended to the transcript after the number of commitments,
+/// and verifiers reject proofs of other versions with [`R1CSError::UnsupportedVersion`].
+pub const R1CS_PROOF_VERSION: u8 = 1;
+
 /// A proof of some statement specified by a
 /// [`ConstraintSystem`](::r1cs::ConstraintSystem).
 ///
@@ -31,6 +37,8 @@ const TWO_PHASE_COMMITMENTS: u8 = 1;
 #[derive(Clone, Debug)]
 #[allow(non_snake_case)]
 pub struct R1CSProof<C: AffineRepr> {
+    /// The version of the format, [`R1CS_PROOF_VERSION`] for the proofs of this crate.
+    pub(super) version: u8,
     /// Commitment to the values of input wires in the first phase.
     pub(super) A_I1: C,
     /// Commitment to the values of output wires in the first phase.
@@ -60,6 +68,28 @@ impl<C: AffineRepr> R1CSProof<C> {
         self.A_I2.is_zero() && self.A_O2.is_zero() && self.S2.is_zero()
     }
 
+    /// The version of the format of the proof, which verifiers check before anything else.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// The number of multipliers of the proven circuit rounded up to the next power of two,
+    /// read off the number of rounds of the inner product proof, or `None` if there are too many rounds.
+    pub fn padded_multipliers(&self) -> Option<usize> {
+        let rounds = self.ipp_proof.L_vec.len();
+        if rounds < usize::BITS as usize {
+            Some(1 << rounds)
+        } else {
+            None
+        }
+    }
+
+    /// The bytes of the inner product proof within `serialized_size`: two points per round and two scalars.
+    /// Even for circuits of a few multipliers, this is about the size of the blinded wires it proves the inner product of.
+    pub fn inner_product_proof_size(&self, compress: Compress) -> usize {
+        self.ipp_proof.serialized_size(compress)
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(self.serialized_size(Compress::Yes));
         if let Err(e) = self.serialize_compressed(&mut buf) {
@@ -73,6 +103,113 @@ impl<C: AffineRepr> R1CSProof<C> {
     }
 }
 
+/// A component of an [`R1CSProof`], in the order the prover produces it and the verifier absorbs it
+/// into its transcript, for formats which send a proof piece by piece instead of as one serialization.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(non_snake_case)]
+pub enum ProofComponent<C: AffineRepr> {
+    /// The version of the format of the proof.
+    Version(u8),
+    /// The commitments `A_I1`, `A_O1` and `S1` of the first phase.
+    PhaseOneCommitments([C; 3]),
+    /// The commitments `A_I2`, `A_O2` and `S2` of the second phase, absent for circuits of one phase.
+    PhaseTwoCommitments(Option<[C; 3]>),
+    /// The commitments to the coefficients of \(t(x)\).
+    T(Vec<C>),
+    /// The scalars \(t(x)\), its blinding and the blinding of the inner product arguments.
+    Scalars([C::ScalarField; 3]),
+    /// The points `L` and `R` of a round of the inner product proof.
+    InnerProductRound(C, C),
+    /// The final scalars `a` and `b` of the inner product proof.
+    InnerProductScalars(C::ScalarField, C::ScalarField),
+}
+
+impl<C: AffineRepr> R1CSProof<C> {
+    /// The components of the proof, in order, which `from_components` reassembles.
+    pub fn components(&self) -> Vec<ProofComponent<C>> {
+        let mut components = vec![
+            ProofComponent::Version(self.version),
+            ProofComponent::PhaseOneCommitments([self.A_I1, self.A_O1, self.S1]),
+            ProofComponent::PhaseTwoCommitments(if self.missing_phase2_commitments() {
+                None
+            } else {
+                Some([self.A_I2, self.A_O2, self.S2])
+            }),
+            ProofComponent::T(self.T.clone()),
+            ProofComponent::Scalars([self.t_x, self.t_x_blinding, self.e_blinding]),
+        ];
+        components.extend(
+            self.ipp_proof
+                .L_vec
+                .iter()
+                .zip(self.ipp_proof.R_vec.iter())
+                .map(|(l, r)| ProofComponent::InnerProductRound(*l, *r)),
+        );
+        components.push(ProofComponent::InnerProductScalars(
+            self.ipp_proof.a,
+            self.ipp_proof.b,
+        ));
+        components
+    }
+
+    /// Reassembles a proof from its components, which must be in the order of `components`,
+    /// failing with [`R1CSError::FormatError`] if one is missing, out of order or follows the last,
+    /// or if there are more commitments to \(t(x)\) than the verifier reads.
+    pub fn from_components(
+        components: impl IntoIterator<Item = ProofComponent<C>>,
+    ) -> Result<Self, R1CSError> {
+        let mut components = components.into_iter();
+        let (
+            Some(ProofComponent::Version(version)),
+            Some(ProofComponent::PhaseOneCommitments([A_I1, A_O1, S1])),
+            Some(ProofComponent::PhaseTwoCommitments(phase_two)),
+            Some(ProofComponent::T(T)),
+            Some(ProofComponent::Scalars([t_x, t_x_blinding, e_blinding])),
+        ) = (
+            components.next(),
+            components.next(),
+            components.next(),
+            components.next(),
+            components.next(),
+        )
+        else {
+            return Err(R1CSError::FormatError);
+        };
+        if T.len() > T_LABELS.len() {
+            return Err(R1CSError::FormatError);
+        }
+        let [A_I2, A_O2, S2] = phase_two.unwrap_or([C::zero(); 3]);
+        let (mut L_vec, mut R_vec) = (Vec::new(), Vec::new());
+        let (a, b) = loop {
+            match components.next() {
+                Some(ProofComponent::InnerProductRound(l, r)) => {
+                    L_vec.push(l);
+                    R_vec.push(r);
+                }
+                Some(ProofComponent::InnerProductScalars(a, b)) => break (a, b),
+                _ => return Err(R1CSError::FormatError),
+            }
+        };
+        if components.next().is_some() {
+            return Err(R1CSError::FormatError);
+        }
+        Ok(Self {
+            version,
+            A_I1,
+            A_O1,
+            S1,
+            A_I2,
+            A_O2,
+            S2,
+            T,
+            t_x,
+            t_x_blinding,
+            e_blinding,
+            ipp_proof: InnerProductProof { L_vec, R_vec, a, b },
+        })
+    }
+}
+
 impl<C: AffineRepr> CanonicalSerialize for R1CSProof<C> {
     /// Returns the size in bytes required to serialize the `R1CSProof`.
     fn serialized_size(&self, compress: Compress) -> usize {
@@ -89,7 +226,8 @@ impl<C: AffineRepr> CanonicalSerialize for R1CSProof<C> {
         let scalars_size = 3 * self.t_x.serialized_size(compress);
         // size of the inner product proof
         let ipp_size = self.ipp_proof.serialized_size(compress);
-        points_size + t_size + scalars_size + ipp_size + 1
+        // and the version and phase flag bytes
+        points_size + t_size + scalars_size + ipp_size + 2
     }
 
     fn serialize_with_mode<W: Write>(
@@ -97,6 +235,7 @@ impl<C: AffineRepr> CanonicalSerialize for R1CSProof<C> {
         mut writer: W,
         compress: Compress,
     ) -> Result<(), SerializationError> {
+        self.version.serialize_with_mode(&mut writer, compress)?;
         // serialize first phase commitments.
         self.A_I1.serialize_with_mode(&mut writer, compress)?;
         self.A_O1.serialize_with_mode(&mut writer, compress)?;
@@ -137,27 +276,31 @@ impl<C: AffineRepr> CanonicalDeserialize for R1CSProof<C> {
         compress: Compress,
         validate: ark_serialize::Validate,
     ) -> Result<Self, SerializationError> {
+        // Any version is read, for the verifier to reject unknown ones with a typed error.
+        let version = u8::deserialize_with_mode(&mut reader, compress, validate)?;
         let A_I1 = C::deserialize_with_mode(&mut reader, compress, validate)?;
         let A_O1 = C::deserialize_with_mode(&mut reader, compress, validate)?;
         let S1 = C::deserialize_with_mode(&mut reader, compress, validate)?;
         let flag = u8::deserialize_with_mode(&mut reader, compress, validate)?;
-        let (A_I2, A_O2, S2) = if flag == TWO_PHASE_COMMITMENTS {
-            (
+        let (A_I2, A_O2, S2) = match flag {
+            TWO_PHASE_COMMITMENTS => (
                 C::deserialize_with_mode(&mut reader, compress, validate)?,
                 C::deserialize_with_mode(&mut reader, compress, validate)?,
                 C::deserialize_with_mode(&mut reader, compress, validate)?,
-            )
-        } else {
-            (C::zero(), C::zero(), C::zero())
+            ),
+            ONE_PHASE_COMMITMENTS => (C::zero(), C::zero(), C::zero()),
+            _ => return Err(SerializationError::InvalidData),
         };
         Ok(Self {
+            version,
             A_I1,
             A_O1,
             S1,
             A_I2,
             A_O2,
             S2,
-            T: Vec::<C>::deserialize_with_mode(&mut reader, compress, validate)?,
+            // one commitment per coefficient of t(x), each with its transcript label
+            T: deserialize_bounded_vec(&mut reader, compress, validate, T_LABELS.len())?,
             t_x: C::ScalarField::deserialize_with_mode(&mut reader, compress, validate)?,
             t_x_blinding: C::ScalarField::deserialize_with_mode(&mut reader, compress, validate)?,
             e_blinding: C::ScalarField::deserialize_with_mode(&mut reader, compress, validate)?,
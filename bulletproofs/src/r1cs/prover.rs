@@ -1,24 +1,31 @@
 #![allow(non_snake_case)]
 
-use ark_ec::{AffineRepr, VariableBaseMSM};
+use ark_ec::AffineRepr;
 use ark_ff::Field;
 use ark_std::{One, UniformRand, Zero};
 use core::borrow::BorrowMut;
 use core::mem;
 use merlin::Transcript;
+use rand::{CryptoRng, RngCore};
 use zeroize::{ZeroizeOnDrop, Zeroizing};
 
 use super::constraint_system::{
     ConstraintSystem, RandomizableConstraintSystem, RandomizedConstraintSystem,
 };
 use super::linear_combination::{LinearCombination, Variable};
-use super::proof::R1CSProof;
+use super::proof::{R1CSProof, R1CS_PROOF_VERSION};
 
 use crate::errors::R1CSError;
 use crate::generators::{BulletproofGens, PedersenGens};
-use crate::inner_product_proof::InnerProductProof;
+use crate::inner_product_proof::InnerProductProver;
+use crate::r1cs::metrics::PhaseRecorder;
 use crate::r1cs::Metrics;
+#[cfg(feature = "trace")]
+use crate::r1cs::ProofMetrics;
+#[cfg(feature = "debug")]
+use crate::r1cs::{Operation, OperationLog};
 use crate::transcript::TranscriptProtocol;
+use crate::util;
 
 use super::op_splits;
 
@@ -33,19 +40,29 @@ use super::op_splits;
 /// that instantiate the randomized constraints, and creates a complete proof.
 pub struct Prover<'g, T: BorrowMut<Transcript>, C: AffineRepr> {
     transcript: T,
-    pc_gens: &'g PedersenGens<C>,
+    pub(super) pc_gens: &'g PedersenGens<C>,
     /// The constraints accumulated so far.
     constraints: Vec<LinearCombination<C::ScalarField>>,
     /// Secret data
-    secrets: Secrets<C::ScalarField>,
+    pub(super) secrets: Secrets<C::ScalarField>,
 
     /// This list holds closures that will be called in the second phase of the protocol,
     /// when non-randomized variables are committed.
-    deferred_constraints:
+    pub(super) deferred_constraints:
         Vec<Box<dyn FnOnce(&mut RandomizingProver<'g, T, C>) -> Result<(), R1CSError>>>,
 
     /// Index of a pending multiplier that's not fully assigned yet.
-    pending_multiplier: Option<usize>,
+    pub(super) pending_multiplier: Option<usize>,
+
+    /// The namespaces entered with `push_namespace`, innermost last.
+    #[cfg(feature = "debug")]
+    namespaces: Vec<String>,
+    /// The namespace path each multiplier was allocated in.
+    #[cfg(feature = "debug")]
+    multiplier_namespaces: Vec<String>,
+    /// The operations laid down so far.
+    #[cfg(feature = "debug")]
+    operations: OperationLog,
 }
 
 // todo I assume this would be automatically implemented by the compiler if it did not have a a mutable borrow of a transcript
@@ -54,19 +71,19 @@ unsafe impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Send for Prover<'g, T,
 /// Separate struct to implement Drop trait for (for zeroing),
 /// so that compiler does not prohibit us from moving the Transcript out of `prove()`.
 #[derive(ZeroizeOnDrop)]
-struct Secrets<F: Field> {
+pub(super) struct Secrets<F: Field> {
     /// Stores assignments to the "left" of multiplication gates
-    a_L: Vec<F>,
+    pub(super) a_L: Vec<F>,
     /// Stores assignments to the "right" of multiplication gates
-    a_R: Vec<F>,
+    pub(super) a_R: Vec<F>,
     /// Stores assignments to the "output" of multiplication gates
-    a_O: Vec<F>,
+    pub(super) a_O: Vec<F>,
     /// High-level witness data (value openings to V commitments)
-    v: Vec<F>,
+    pub(super) v: Vec<F>,
     /// High-level witness data (blinding openings to V commitments)
-    v_blinding: Vec<F>,
+    pub(super) v_blinding: Vec<F>,
     ///
-    vec_open: Vec<(F, Vec<F>)>,
+    pub(super) vec_open: Vec<(F, Vec<F>)>,
 }
 
 /// Prover in the randomizing phase.
@@ -96,6 +113,8 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> ConstraintSystem<C::ScalarFiel
         Variable<C::ScalarField>,
         Variable<C::ScalarField>,
     ) {
+        #[cfg(feature = "debug")]
+        self.operations.record(Operation::Multiply);
         // Synthesize the assignments for l,r,o
         let l = self.eval(&left);
         let r = self.eval(&right);
@@ -109,6 +128,8 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> ConstraintSystem<C::ScalarFiel
         self.secrets.a_L.push(l);
         self.secrets.a_R.push(r);
         self.secrets.a_O.push(o);
+        #[cfg(feature = "debug")]
+        self.record_multiplier_namespace();
 
         // Constrain l,r,o:
         left.terms.push((l_var, -C::ScalarField::one()));
@@ -124,6 +145,8 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> ConstraintSystem<C::ScalarFiel
         assignment: Option<C::ScalarField>,
     ) -> Result<Variable<C::ScalarField>, R1CSError> {
         let scalar = assignment.ok_or(R1CSError::MissingAssignment)?;
+        #[cfg(feature = "debug")]
+        self.operations.record(Operation::Allocate);
 
         match self.pending_multiplier {
             None => {
@@ -132,6 +155,8 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> ConstraintSystem<C::ScalarFiel
                 self.secrets.a_L.push(scalar);
                 self.secrets.a_R.push(C::ScalarField::zero());
                 self.secrets.a_O.push(C::ScalarField::zero());
+                #[cfg(feature = "debug")]
+                self.record_multiplier_namespace();
                 Ok(Variable::MultiplierLeft(i))
             }
             Some(i) => {
@@ -155,6 +180,8 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> ConstraintSystem<C::ScalarFiel
         R1CSError,
     > {
         let (l, r) = input_assignments.ok_or(R1CSError::MissingAssignment)?;
+        #[cfg(feature = "debug")]
+        self.operations.record(Operation::AllocateMultiplier);
         let o = l * r;
 
         // Create variables for l,r,o ...
@@ -165,6 +192,8 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> ConstraintSystem<C::ScalarFiel
         self.secrets.a_L.push(l);
         self.secrets.a_R.push(r);
         self.secrets.a_O.push(o);
+        #[cfg(feature = "debug")]
+        self.record_multiplier_namespace();
 
         Ok((l_var, r_var, o_var))
     }
@@ -181,8 +210,27 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> ConstraintSystem<C::ScalarFiel
     fn constrain(&mut self, lc: LinearCombination<C::ScalarField>) {
         // TODO: check that the linear combinations are valid
         // (e.g. that variables are valid, that the linear combination evals to 0 for prover, etc).
+        #[cfg(feature = "debug")]
+        self.operations.record(Operation::Constrain(lc.terms.len()));
         self.constraints.push(lc);
     }
+
+    #[cfg(feature = "debug")]
+    fn push_namespace(&mut self, name: &str) {
+        self.namespaces.push(name.to_owned());
+    }
+
+    #[cfg(feature = "debug")]
+    fn pop_namespace(&mut self) {
+        self.namespaces
+            .pop()
+            .expect("pop_namespace without a matching push_namespace");
+    }
+
+    #[cfg(feature = "debug")]
+    fn operation_log(&self) -> &OperationLog {
+        &self.operations
+    }
 }
 
 impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> RandomizableConstraintSystem<C::ScalarField>
@@ -246,6 +294,21 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> ConstraintSystem<C::ScalarFiel
     fn constrain(&mut self, lc: LinearCombination<C::ScalarField>) {
         self.prover.constrain(lc)
     }
+
+    #[cfg(feature = "debug")]
+    fn push_namespace(&mut self, name: &str) {
+        self.prover.push_namespace(name)
+    }
+
+    #[cfg(feature = "debug")]
+    fn pop_namespace(&mut self) {
+        self.prover.pop_namespace()
+    }
+
+    #[cfg(feature = "debug")]
+    fn operation_log(&self) -> &OperationLog {
+        self.prover.operation_log()
+    }
 }
 
 impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> RandomizedConstraintSystem<C::ScalarField>
@@ -297,6 +360,12 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
             constraints: Vec::new(),
             deferred_constraints: Vec::new(),
             pending_multiplier: None,
+            #[cfg(feature = "debug")]
+            namespaces: Vec::new(),
+            #[cfg(feature = "debug")]
+            multiplier_namespaces: Vec::new(),
+            #[cfg(feature = "debug")]
+            operations: OperationLog::default(),
         }
     }
 
@@ -325,6 +394,8 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
         let i = self.secrets.v.len();
         self.secrets.v.push(v);
         self.secrets.v_blinding.push(v_blinding);
+        #[cfg(feature = "debug")]
+        self.operations.record(Operation::Commit);
 
         // Add the commitment to the transcript.
         let V = self.pc_gens.commit(v, v_blinding);
@@ -339,24 +410,9 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
         v_blinding: C::ScalarField,
         bp_gens: &BulletproofGens<C>, // same as used during proving, uses the "G" generators to commit like for a_O
     ) -> (C, Vec<Variable<C::ScalarField>>) {
-        use std::iter;
-
         // compute the commitment:
         // comm = <v, G> + <v_blinding> B_blinding
-        let gens = bp_gens.share(0);
-
-        // [b] * H + [v_1] * G1 + ... + [v_n] * Gn
-        let generators: Vec<_> = iter::once(&self.pc_gens.B_blinding)
-            .chain(gens.G(v.len()))
-            .copied()
-            .collect::<Vec<_>>();
-
-        let scalars: Vec<C::ScalarField> =
-            iter::once(&v_blinding).chain(v.iter()).copied().collect();
-
-        assert_eq!(generators.len(), scalars.len());
-
-        let comm = C::Group::msm_unchecked(generators.as_slice(), scalars.as_slice());
+        let comm = bp_gens.share(0).commit_vec(self.pc_gens, v, v_blinding);
 
         // create variables for all the addressable coordinates
         let comm_idx = self.secrets.vec_open.len();
@@ -366,14 +422,27 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
 
         // add the opening (values || blinding) to the secrets
         self.secrets.vec_open.push((v_blinding, v.to_owned()));
+        #[cfg(feature = "debug")]
+        self.operations.record(Operation::CommitVec(v.len()));
 
         // add the commitment to the transcript.
-        let comm = comm.into();
         self.transcript.borrow_mut().append_point(b"V", &comm);
 
         (comm, vars)
     }
 
+    /// Commits to a vector known to the verifier, with zero blinding.
+    ///
+    /// The verifier recomputes the commitment with [`Verifier::commit_vec_public`](::r1cs::Verifier::commit_vec_public)
+    /// instead of receiving it, which saves sending a point for each public vector.
+    pub fn commit_vec_public(
+        &mut self,
+        v: &[C::ScalarField],
+        bp_gens: &BulletproofGens<C>,
+    ) -> Vec<Variable<C::ScalarField>> {
+        self.commit_vec(v, C::ScalarField::zero(), bp_gens).1
+    }
+
     /// Use a challenge, `z`, to flatten the constraints in the
     /// constraint system into vectors used for proving and
     /// verification.
@@ -385,6 +454,10 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
     /// (wL, wR, wO, wV)
     /// ```
     /// where `w{L,R,O}` is \\( z \cdot z^Q \cdot W_{L,R,O} \\).
+    ///
+    /// The weights `wVCs` of each vector commitment are kept as `(coordinate, weight)` terms,
+    /// which may repeat a coordinate, rather than as a vector as long as the committed one.
+    #[allow(clippy::type_complexity)]
     fn flattened_constraints(
         &mut self,
         z: &C::ScalarField,
@@ -393,7 +466,7 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
         Vec<C::ScalarField>,
         Vec<C::ScalarField>,
         Vec<C::ScalarField>,
-        Vec<Vec<C::ScalarField>>,
+        Vec<Vec<(usize, C::ScalarField)>>,
     ) {
         let n = self.secrets.a_L.len();
         let m = self.secrets.v.len();
@@ -403,10 +476,7 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
         let mut wO = vec![C::ScalarField::zero(); n];
         let mut wV = vec![C::ScalarField::zero(); m];
 
-        let mut wVCs = Vec::with_capacity(self.secrets.vec_open.len());
-        for v in self.secrets.vec_open.iter() {
-            wVCs.push(vec![C::ScalarField::zero(); v.1.len()]);
-        }
+        let mut wVCs = vec![Vec::new(); self.secrets.vec_open.len()];
 
         let mut exp_z = *z;
         for lc in self.constraints.iter() {
@@ -427,9 +497,9 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
                     Variable::VectorCommit(j, i) => {
                         // j : index of commitment
                         // i : coordinate with-in commitment
-                        wVCs[*j][*i] += exp_z * coeff;
+                        wVCs[*j].push((*i, exp_z * coeff));
                     }
-                    Variable::One(_) => {
+                    Variable::One(_) | Variable::Public(_) => {
                         // The prover doesn't need to handle constant terms
                     }
                 }
@@ -453,6 +523,9 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
                         Variable::MultiplierOutput(i) => self.secrets.a_O[*i],
                         Variable::Committed(i) => self.secrets.v[*i],
                         Variable::One(_) => C::ScalarField::one(),
+                        Variable::Public(_) => {
+                            unreachable!("the prover bakes public constants into the constraints")
+                        }
                     }
             })
             .sum()
@@ -499,34 +572,100 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
         self.secrets.a_L.len()
     }
 
+    /// Returns `(i, a_L[i], a_R[i], a_O[i])` for every multiplier allocated so far.
+    ///
+    /// The right input and output of a multiplier allocated by a single call to `allocate`
+    /// stay zero until the next call to `allocate`.
+    #[cfg(feature = "debug")]
+    pub fn multiplier_assignments(
+        &self,
+    ) -> impl Iterator<Item = (usize, C::ScalarField, C::ScalarField, C::ScalarField)> + '_ {
+        self.secrets
+            .a_L
+            .iter()
+            .zip(self.secrets.a_R.iter())
+            .zip(self.secrets.a_O.iter())
+            .enumerate()
+            .map(|(i, ((l, r), o))| (i, *l, *r, *o))
+    }
+
+    /// Returns the namespaces the multiplier at `index` was allocated in, outermost first and joined by `/`.
+    /// Multipliers allocated outside any namespace have the empty path.
+    #[cfg(feature = "debug")]
+    pub fn multiplier_namespace(&self, index: usize) -> Option<&str> {
+        self.multiplier_namespaces.get(index).map(String::as_str)
+    }
+
+    #[cfg(feature = "debug")]
+    fn record_multiplier_namespace(&mut self) {
+        self.multiplier_namespaces.push(self.namespaces.join("/"));
+    }
+
     /// Consume this `ConstraintSystem` to produce a proof. Returns the proof and the transcript passed in `Prover::new`.
     pub fn prove_and_return_transcript(
-        mut self,
+        self,
         bp_gens: &BulletproofGens<C>,
     ) -> Result<(R1CSProof<C>, T), R1CSError> {
+        self.prove_with_recorder(bp_gens, &mut rand::thread_rng())
+            .map(|(proof, transcript, _recorder)| (proof, transcript))
+    }
+
+    /// Consume this `ConstraintSystem` to produce a proof, drawing the blinding factors from `rng`.
+    pub fn prove_with_rng<R: RngCore + CryptoRng>(
+        self,
+        bp_gens: &BulletproofGens<C>,
+        rng: &mut R,
+    ) -> Result<R1CSProof<C>, R1CSError> {
+        self.prove_with_recorder(bp_gens, rng)
+            .map(|(proof, _transcript, _recorder)| proof)
+    }
+
+    /// Consume this `ConstraintSystem` to produce a proof, and report how long each phase took.
+    #[cfg(feature = "trace")]
+    pub fn prove_with_metrics(
+        self,
+        bp_gens: &BulletproofGens<C>,
+    ) -> Result<(R1CSProof<C>, ProofMetrics), R1CSError> {
+        let constraint_system = self.metrics();
+        let (proof, _transcript, recorder) =
+            self.prove_with_recorder(bp_gens, &mut rand::thread_rng())?;
+        Ok((
+            proof,
+            ProofMetrics {
+                constraint_system,
+                padded_multipliers: recorder.padded_multipliers,
+                msm_points: recorder.msm_points,
+                phases: recorder.phases,
+            },
+        ))
+    }
+
+    /// Consumes this `ConstraintSystem` and starts a proof: pads the multipliers, commits to the first-phase
+    /// wires and appends the commitments to the transcript.
+    /// The blinding factors of the whole proof are drawn from `rng`, here and in `Phase1::process_challenge`.
+    pub fn commit_wires<'b, R: RngCore + CryptoRng>(
+        mut self,
+        bp_gens: &'b BulletproofGens<C>,
+        rng: &mut R,
+    ) -> Result<Phase1<'g, 'b, T, C>, R1CSError> {
+        let mut recorder = PhaseRecorder::default();
         // pad
         while self.size() > self.secrets.a_L.len() {
             self.allocate_multiplier(Some((C::ScalarField::zero(), C::ScalarField::zero())))?;
         }
 
-        use crate::util;
-        use std::iter;
-
         // number of commitments
         let ncomm = self.secrets.vec_open.len();
 
         // op_degree = 2 + 2 * floor(#comm / 2)
         let op_degree = 2 + 2 * (ncomm / 2);
 
-        let ops = op_splits(op_degree);
-        let veccom_ops = &ops[2..];
-
         #[cfg(debug_assertions)]
         {
             println!("op_degree: {}", op_degree);
             println!("number of commitments: {}", ncomm);
             println!("number of constraints: {}", self.secrets.a_L.len());
-            println!("ops = {:?}", &ops[..]);
+            println!("ops = {:?}", &op_splits(op_degree)[..]);
         }
 
         // Commit a length _suffix_ for the number of high-level variables.
@@ -536,6 +675,9 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
         self.transcript
             .borrow_mut()
             .append_u64(b"m", self.secrets.v.len() as u64);
+        self.transcript
+            .borrow_mut()
+            .r1cs_version(R1CS_PROOF_VERSION);
 
         // // Create a `TranscriptRng` from the high-level witness data
         // //
@@ -572,159 +714,186 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
         // We are performing a single-party circuit proof, so party index is 0.
         let gens = bp_gens.share(0);
 
-        let mut rng = rand::thread_rng();
-        let i_blinding1 = C::ScalarField::rand(&mut rng);
-        let o_blinding1 = C::ScalarField::rand(&mut rng);
-        let s_blinding1 = C::ScalarField::rand(&mut rng);
+        let i_blinding1 = C::ScalarField::rand(rng);
+        let o_blinding1 = C::ScalarField::rand(rng);
+        let s_blinding1 = C::ScalarField::rand(rng);
 
         let s_L1: Zeroizing<Vec<C::ScalarField>> =
-            Zeroizing::new((0..n1).map(|_| C::ScalarField::rand(&mut rng)).collect());
+            Zeroizing::new((0..n1).map(|_| C::ScalarField::rand(rng)).collect());
         let s_R1: Zeroizing<Vec<C::ScalarField>> =
-            Zeroizing::new((0..n1).map(|_| C::ScalarField::rand(&mut rng)).collect());
+            Zeroizing::new((0..n1).map(|_| C::ScalarField::rand(rng)).collect());
 
+        let phase = start_phase!("first_phase_commitments", n = n1);
         #[cfg(feature = "parallel")]
         let (A_I1, A_O1, S1) = {
-            // todo clean up when send is safely implemented
-            let blinding = self.pc_gens.B_blinding;
-            let A_I1_scalars = iter::once(&i_blinding1)
-                .chain(self.secrets.a_L.iter())
-                .chain(self.secrets.a_R.iter())
-                .copied()
-                .collect::<Vec<C::ScalarField>>();
-            let A_O1_scalars = iter::once(&o_blinding1)
-                .chain(self.secrets.a_O.iter())
-                .copied()
-                .collect::<Vec<C::ScalarField>>();
+            // The prover itself can't be shared with the threads.
+            let pc_gens = self.pc_gens;
+            let secrets = &self.secrets;
             let (mut A_I1, mut A_O1, mut S1) = (None, None, None);
             rayon::scope(|s| {
                 // A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
                 s.spawn(|_| {
-                    A_I1 = Some(
-                        C::Group::msm_unchecked(
-                            iter::once(&blinding)
-                                .chain(gens.G(n1))
-                                .chain(gens.H(n1))
-                                .copied()
-                                .collect::<Vec<C>>()
-                                .as_slice(),
-                            A_I1_scalars.as_slice(),
-                        )
-                        .into(),
-                    )
+                    A_I1 = Some(commit_wires(
+                        pc_gens,
+                        gens.G_slice(n1),
+                        gens.H_slice(n1),
+                        &secrets.a_L,
+                        &secrets.a_R,
+                        i_blinding1,
+                    ))
                 });
                 // A_O = <a_O, G> + o_blinding * B_blinding
                 s.spawn(|_| {
-                    A_O1 = Some(
-                        C::Group::msm_unchecked(
-                            iter::once(&blinding)
-                                .chain(gens.G(n1))
-                                .copied()
-                                .collect::<Vec<C>>()
-                                .as_slice(),
-                            A_O1_scalars.as_slice(),
-                        )
-                        .into(),
-                    )
+                    A_O1 = Some(commit_wires(
+                        pc_gens,
+                        gens.G_slice(n1),
+                        &[],
+                        &secrets.a_O,
+                        &[],
+                        o_blinding1,
+                    ))
                 });
-
-                // Vector commitments of the form
-                // <Vi, G> + vi_blinding * B:blinding
-
                 // S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
                 s.spawn(|_| {
-                    S1 = Some(
-                        C::Group::msm_unchecked(
-                            iter::once(&blinding)
-                                .chain(gens.G(n1))
-                                .chain(gens.H(n1))
-                                .copied()
-                                .collect::<Vec<C>>()
-                                .as_slice(),
-                            iter::once(&s_blinding1)
-                                .chain(s_L1.iter())
-                                .chain(s_R1.iter())
-                                .copied()
-                                .collect::<Vec<C::ScalarField>>()
-                                .as_slice(),
-                        )
-                        .into(),
-                    )
+                    S1 = Some(commit_wires(
+                        pc_gens,
+                        gens.G_slice(n1),
+                        gens.H_slice(n1),
+                        &s_L1,
+                        &s_R1,
+                        s_blinding1,
+                    ))
                 });
             });
 
             (A_I1.unwrap(), A_O1.unwrap(), S1.unwrap())
         };
         #[cfg(not(feature = "parallel"))]
-        let (A_I1, A_O1, S1) = {
+        let (A_I1, A_O1, S1) = (
             // A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
-            let A_I1 = C::Group::msm_unchecked(
-                iter::once(&self.pc_gens.B_blinding)
-                    .chain(gens.G(n1))
-                    .chain(gens.H(n1))
-                    .copied()
-                    .collect::<Vec<C>>()
-                    .as_slice(),
-                iter::once(&i_blinding1)
-                    .chain(self.secrets.a_L.iter())
-                    .chain(self.secrets.a_R.iter())
-                    .map(|s| (*s).into())
-                    .collect::<Vec<C::ScalarField>>()
-                    .as_slice(),
-            )
-            .into();
-
+            commit_wires(
+                self.pc_gens,
+                gens.G_slice(n1),
+                gens.H_slice(n1),
+                &self.secrets.a_L,
+                &self.secrets.a_R,
+                i_blinding1,
+            ),
             // A_O = <a_O, G> + o_blinding * B_blinding
-            let A_O1 = C::Group::msm_unchecked(
-                iter::once(&self.pc_gens.B_blinding)
-                    .chain(gens.G(n1))
-                    .copied()
-                    .collect::<Vec<C>>()
-                    .as_slice(),
-                iter::once(&o_blinding1)
-                    .chain(self.secrets.a_O.iter())
-                    .map(|s| (*s).into())
-                    .collect::<Vec<C::ScalarField>>()
-                    .as_slice(),
-            )
-            .into();
-
-            // Vector commitments of the form
-            // <Vi, G> + vi_blinding * B:blinding
-
+            commit_wires(
+                self.pc_gens,
+                gens.G_slice(n1),
+                &[],
+                &self.secrets.a_O,
+                &[],
+                o_blinding1,
+            ),
             // S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
-            let S1 = C::Group::msm_unchecked(
-                iter::once(&self.pc_gens.B_blinding)
-                    .chain(gens.G(n1))
-                    .chain(gens.H(n1))
-                    .copied()
-                    .collect::<Vec<C>>()
-                    .as_slice(),
-                iter::once(&s_blinding1)
-                    .chain(s_L1.iter())
-                    .chain(s_R1.iter())
-                    .map(|s| (*s).into())
-                    .collect::<Vec<C::ScalarField>>()
-                    .as_slice(),
-            )
-            .into();
-            (A_I1, A_O1, S1)
-        };
+            commit_wires(
+                self.pc_gens,
+                gens.G_slice(n1),
+                gens.H_slice(n1),
+                &s_L1,
+                &s_R1,
+                s_blinding1,
+            ),
+        );
+        recorder.end(phase);
 
         let transcript = self.transcript.borrow_mut();
         transcript.append_point(b"A_I1", &A_I1);
         transcript.append_point(b"A_O1", &A_O1);
         transcript.append_point(b"S1", &S1);
 
+        Ok(Phase1 {
+            prover: self,
+            bp_gens,
+            recorder,
+            n1,
+            op_degree,
+            i_blinding1,
+            o_blinding1,
+            s_blinding1,
+            s_L1,
+            s_R1,
+            A_I1,
+            A_O1,
+            S1,
+        })
+    }
+
+    fn prove_with_recorder<R: RngCore + CryptoRng>(
+        self,
+        bp_gens: &BulletproofGens<C>,
+        rng: &mut R,
+    ) -> Result<(R1CSProof<C>, T, PhaseRecorder), R1CSError> {
+        let mut phase2 = self.commit_wires(bp_gens, rng)?.process_challenge(rng)?;
+        while phase2.rounds_remaining() > 0 {
+            phase2.finish_ipp_round();
+        }
+        Ok(phase2.finish())
+    }
+}
+
+/// A proof whose first-phase wires are committed, see `Prover::commit_wires`.
+pub struct Phase1<'g, 'b, T: BorrowMut<Transcript>, C: AffineRepr> {
+    prover: Prover<'g, T, C>,
+    bp_gens: &'b BulletproofGens<C>,
+    recorder: PhaseRecorder,
+    n1: usize,
+    op_degree: usize,
+    i_blinding1: C::ScalarField,
+    o_blinding1: C::ScalarField,
+    s_blinding1: C::ScalarField,
+    s_L1: Zeroizing<Vec<C::ScalarField>>,
+    s_R1: Zeroizing<Vec<C::ScalarField>>,
+    A_I1: C,
+    A_O1: C,
+    S1: C,
+}
+
+impl<'g, 'b, T: BorrowMut<Transcript>, C: AffineRepr> Phase1<'g, 'b, T, C> {
+    /// Runs the randomized constraints with the challenges of the first-phase commitments, commits to the
+    /// second-phase wires, and computes the polynomial `t(x)` and the vectors the inner-product argument opens.
+    pub fn process_challenge<R: RngCore + CryptoRng>(
+        self,
+        rng: &mut R,
+    ) -> Result<Phase2<T, C>, R1CSError> {
+        use std::iter;
+
+        let Phase1 {
+            prover,
+            bp_gens,
+            mut recorder,
+            n1,
+            op_degree,
+            i_blinding1,
+            o_blinding1,
+            s_blinding1,
+            s_L1,
+            s_R1,
+            A_I1,
+            A_O1,
+            S1,
+        } = self;
+        let ncomm = prover.secrets.vec_open.len();
+        let ops = op_splits(op_degree);
+        let veccom_ops = &ops[2..];
+        let gens = bp_gens.share(0);
+
         // Process the remaining constraints.
-        self = self.create_randomized_constraints()?;
+        let phase = start_phase!("randomized_constraints");
+        let mut prover = prover.create_randomized_constraints()?;
+        recorder.end(phase);
 
         // Pad zeros to the next power of two (or do that implicitly when creating vectors)
 
         // If the number of multiplications is not 0 or a power of 2, then pad the circuit.
-        let n = self.size();
+        let n = prover.size();
         let n2 = n - n1;
         let padded_n = n.next_power_of_two();
         let pad = padded_n - n;
+        recorder.set_padded_multipliers(padded_n);
 
         if bp_gens.gens_capacity < padded_n {
             return Err(R1CSError::InvalidGeneratorsLength);
@@ -736,9 +905,9 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
 
         let (i_blinding2, o_blinding2, s_blinding2) = if has_2nd_phase_commitments {
             (
-                C::ScalarField::rand(&mut rng),
-                C::ScalarField::rand(&mut rng),
-                C::ScalarField::rand(&mut rng),
+                C::ScalarField::rand(rng),
+                C::ScalarField::rand(rng),
+                C::ScalarField::rand(rng),
             )
         } else {
             (
@@ -749,61 +918,43 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
         };
 
         let s_L2: Zeroizing<Vec<C::ScalarField>> =
-            Zeroizing::new((0..n2).map(|_| C::ScalarField::rand(&mut rng)).collect());
+            Zeroizing::new((0..n2).map(|_| C::ScalarField::rand(rng)).collect());
         let s_R2: Zeroizing<Vec<C::ScalarField>> =
-            Zeroizing::new((0..n2).map(|_| C::ScalarField::rand(&mut rng)).collect());
+            Zeroizing::new((0..n2).map(|_| C::ScalarField::rand(rng)).collect());
 
         // both not supported atm.
-        assert!(!has_2nd_phase_commitments || self.secrets.vec_open.is_empty());
+        assert!(!has_2nd_phase_commitments || prover.secrets.vec_open.is_empty());
 
+        let phase = start_phase!("second_phase_commitments", n = n2);
         let (A_I2, A_O2, S2) = if has_2nd_phase_commitments {
             (
                 // A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
-                C::Group::msm_unchecked(
-                    iter::once(&self.pc_gens.B_blinding)
-                        .chain(gens.G(n).skip(n1))
-                        .chain(gens.H(n).skip(n1))
-                        .copied()
-                        .collect::<Vec<C>>()
-                        .as_slice(),
-                    iter::once(&i_blinding2)
-                        .chain(self.secrets.a_L.iter().skip(n1))
-                        .chain(self.secrets.a_R.iter().skip(n1))
-                        .copied()
-                        .collect::<Vec<C::ScalarField>>()
-                        .as_slice(),
-                )
-                .into(),
+                commit_wires(
+                    prover.pc_gens,
+                    &gens.G_slice(n)[n1..],
+                    &gens.H_slice(n)[n1..],
+                    &prover.secrets.a_L[n1..],
+                    &prover.secrets.a_R[n1..],
+                    i_blinding2,
+                ),
                 // A_O = <a_O, G> + o_blinding * B_blinding
-                C::Group::msm_unchecked(
-                    iter::once(&self.pc_gens.B_blinding)
-                        .chain(gens.G(n).skip(n1))
-                        .copied()
-                        .collect::<Vec<C>>()
-                        .as_slice(),
-                    iter::once(&o_blinding2)
-                        .chain(self.secrets.a_O.iter().skip(n1))
-                        .copied()
-                        .collect::<Vec<C::ScalarField>>()
-                        .as_slice(),
-                )
-                .into(),
+                commit_wires(
+                    prover.pc_gens,
+                    &gens.G_slice(n)[n1..],
+                    &[],
+                    &prover.secrets.a_O[n1..],
+                    &[],
+                    o_blinding2,
+                ),
                 // S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
-                C::Group::msm_unchecked(
-                    iter::once(&self.pc_gens.B_blinding)
-                        .chain(gens.G(n).skip(n1))
-                        .chain(gens.H(n).skip(n1))
-                        .copied()
-                        .collect::<Vec<C>>()
-                        .as_slice(),
-                    iter::once(&s_blinding2)
-                        .chain(s_L2.iter())
-                        .chain(s_R2.iter())
-                        .copied()
-                        .collect::<Vec<C::ScalarField>>()
-                        .as_slice(),
-                )
-                .into(),
+                commit_wires(
+                    prover.pc_gens,
+                    &gens.G_slice(n)[n1..],
+                    &gens.H_slice(n)[n1..],
+                    &s_L2,
+                    &s_R2,
+                    s_blinding2,
+                ),
             )
         } else {
             // Since we are using zero blinding factors and
@@ -812,8 +963,9 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
             // so we can hardcode them saving 3 mults+compressions.
             (C::zero(), C::zero(), C::zero())
         };
+        recorder.end(phase);
 
-        let transcript = self.transcript.borrow_mut();
+        let transcript = prover.transcript.borrow_mut();
         transcript.append_point(b"A_I2", &A_I2);
         transcript.append_point(b"A_O2", &A_O2);
         transcript.append_point(b"S2", &S2);
@@ -828,17 +980,29 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
         // println!("P S2 {}", &S2);
         // println!("P z {}", z);
 
-        let (wL, wR, wO, wV, wVCs) = self.flattened_constraints(&z);
+        let phase = start_phase!(
+            "flatten_constraints",
+            constraints = prover.constraints.len(),
+            n = n
+        );
+        let (wL, wR, wO, wV, wVCs) = prover.flattened_constraints(&z);
+        recorder.end(phase);
 
         #[cfg(debug_assertions)]
         {
-            println!("Length of constraints vector: {}", self.constraints.len());
+            println!("Length of constraints vector: {}", prover.constraints.len());
             println!("prover wVCs = {:?}", &wVCs);
             println!("prover wL = {:?}", &wL);
             println!("prover wR = {:?}", &wR);
             println!("prover wO = {:?}", &wO);
         }
 
+        let phase = start_phase!(
+            "t_poly",
+            n = n,
+            vector_commitments = ncomm,
+            degree = 2 * (op_degree + 1)
+        );
         let mut l_poly = util::VecPoly::<C::ScalarField>::zero(n, op_degree + 1);
         let mut r_poly = util::VecPoly::<C::ScalarField>::zero(n, op_degree + 1);
 
@@ -866,7 +1030,7 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
         // op_degree = 2 + 2 * floor(#comm / 2)
 
         for (i, (sl, sr)) in sLsR.enumerate() {
-            debug_assert!(i < self.secrets.a_L.len());
+            debug_assert!(i < prover.secrets.a_L.len());
 
             // The first (original) op_degree is 2, which permits a single vector commitment:
             //
@@ -959,8 +1123,8 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
             // r_poly.1 = y^n * a_R + (z * z^Q * W_L)
             debug_assert_eq!(l_poly.coeff_mut(mid_degree)[i], C::ScalarField::zero());
             debug_assert_eq!(r_poly.coeff_mut(mid_degree)[i], C::ScalarField::zero());
-            l_poly.coeff_mut(ops[0].0)[i] = self.secrets.a_L[i] + exp_y_inv[i] * wR[i];
-            r_poly.coeff_mut(ops[0].1)[i] = exp_y[i] * self.secrets.a_R[i] + wL[i];
+            l_poly.coeff_mut(ops[0].0)[i] = prover.secrets.a_L[i] + exp_y_inv[i] * wR[i];
+            r_poly.coeff_mut(ops[0].1)[i] = exp_y[i] * prover.secrets.a_R[i] + wL[i];
 
             // a_O constraints:
             //
@@ -968,7 +1132,7 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
             // r_poly.0 = (z * z^Q * W_O) - y^n
             debug_assert_eq!(l_poly.coeff_mut(op_degree)[i], C::ScalarField::zero());
             debug_assert_eq!(r_poly.coeff_mut(0)[i], C::ScalarField::zero());
-            l_poly.coeff_mut(ops[1].0)[i] = self.secrets.a_O[i];
+            l_poly.coeff_mut(ops[1].0)[i] = prover.secrets.a_O[i];
             r_poly.coeff_mut(ops[1].1)[i] = wO[i] - exp_y[i];
 
             // masks:
@@ -980,16 +1144,18 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
         }
 
         // veccom constraints
-        for (j, w) in self.secrets.vec_open.iter().enumerate() {
+        for (j, w) in prover.secrets.vec_open.iter().enumerate() {
             //
             let (l_deg, r_deg) = veccom_ops[j];
 
-            // copy values to l_poly r_poly
+            // copy values to l_poly, and the weights of the constrained coordinates to r_poly
             for i in 0..w.1.len() {
                 debug_assert_eq!(l_poly.coeff_mut(l_deg)[i], C::ScalarField::zero());
                 debug_assert_eq!(r_poly.coeff_mut(r_deg)[i], C::ScalarField::zero());
                 l_poly.coeff_mut(l_deg)[i] = w.1[i];
-                r_poly.coeff_mut(r_deg)[i] = wVCs[j][i];
+            }
+            for (i, weight) in wVCs[j].iter() {
+                r_poly.coeff_mut(r_deg)[*i] += weight;
             }
         }
 
@@ -1002,7 +1168,7 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
             if d == op_degree {
                 continue;
             }
-            t_blinding_poly.coeff()[d] = C::ScalarField::rand(&mut rng);
+            t_blinding_poly.coeff()[d] = C::ScalarField::rand(rng);
             // println!("T_{}", d);
         }
 
@@ -1012,19 +1178,20 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
             if d == op_degree {
                 continue;
             }
-            T[d] = self
+            T[d] = prover
                 .pc_gens
                 .commit(t_poly.coeff()[d], t_blinding_poly.coeff()[d]);
         }
 
         // commit to T
-        let transcript = self.transcript.borrow_mut();
+        let transcript = prover.transcript.borrow_mut();
         for d in 0..t_poly.deg() + 1 {
             if d == op_degree {
                 continue;
             }
             transcript.append_point(util::T_LABELS[d], &T[d]);
         }
+        recorder.end(phase);
 
         let u = transcript.challenge_scalar::<C>(b"u");
         let x = transcript.challenge_scalar::<C>(b"x");
@@ -1040,7 +1207,7 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
 
         t_blinding_poly.coeff()[op_degree] = wV
             .iter()
-            .zip(self.secrets.v_blinding.iter())
+            .zip(prover.secrets.v_blinding.iter())
             .map(|(c, v_blinding)| *c * v_blinding)
             .sum();
 
@@ -1081,23 +1248,27 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
             let yn: Vec<_> = util::exp_iter(y).take(n).collect();
             let mut aRyn = vec![C::ScalarField::zero(); n];
             for i in 0..n {
-                aRyn[i] = self.secrets.a_R[i] * yn[i];
+                aRyn[i] = prover.secrets.a_R[i] * yn[i];
             }
 
             let mut t2 = C::ScalarField::zero();
 
             // linear term
-            t2 += inner_product(&wL, &self.secrets.a_L);
-            t2 += inner_product(&wR, &self.secrets.a_R);
-            t2 += inner_product(&wO, &self.secrets.a_O);
-
-            for i in 0..self.secrets.vec_open.len() {
-                t2 += inner_product(&wVCs[i], &self.secrets.vec_open[i].1);
+            t2 += inner_product(&wL, &prover.secrets.a_L);
+            t2 += inner_product(&wR, &prover.secrets.a_R);
+            t2 += inner_product(&wO, &prover.secrets.a_O);
+
+            for i in 0..prover.secrets.vec_open.len() {
+                let v = &prover.secrets.vec_open[i].1;
+                t2 += wVCs[i]
+                    .iter()
+                    .map(|(j, weight)| *weight * v[*j])
+                    .sum::<C::ScalarField>();
             }
 
             // product
-            t2 += inner_product(&self.secrets.a_L, &aRyn);
-            t2 -= inner_product(&self.secrets.a_O, &yn);
+            t2 += inner_product(&prover.secrets.a_L, &aRyn);
+            t2 -= inner_product(&prover.secrets.a_O, &yn);
 
             // publicly computable correction
             t2 += delta;
@@ -1121,7 +1292,7 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
         // veccom
         for j in 0..ncomm {
             debug_assert!(e_terms[veccom_ops[j].0].is_none());
-            e_terms[veccom_ops[j].0] = Some(self.secrets.vec_open[j].0);
+            e_terms[veccom_ops[j].0] = Some(prover.secrets.vec_open[j].0);
         }
 
         // blinding
@@ -1152,7 +1323,7 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
 
         // Get a challenge value to combine statements for the IPP
         let w = transcript.challenge_scalar::<C>(b"w");
-        let Q = self.pc_gens.B.mul(w).into();
+        let Q = prover.pc_gens.B.mul(w).into();
 
         let G_factors = iter::repeat(C::ScalarField::one())
             .take(n1)
@@ -1168,18 +1339,21 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
         // TODO: check if missing \circ y^{-1} on the vec. comm part:
         // everything in H_generators (r_vec) is mult. by y!
 
-        let ipp_proof = InnerProductProof::create(
+        let ipp = InnerProductProver::new(
             transcript,
-            &Q,
-            &G_factors,
-            &H_factors,
+            Q,
+            G_factors,
+            H_factors,
             gens.G(padded_n).copied().collect(),
             gens.H(padded_n).copied().collect(),
             l_vec,
             r_vec,
         );
 
-        let proof = R1CSProof {
+        Ok(Phase2 {
+            transcript: prover.transcript,
+            ipp,
+            recorder,
             A_I1,
             A_O1,
             S1,
@@ -1190,8 +1364,79 @@ impl<'g, T: BorrowMut<Transcript>, C: AffineRepr> Prover<'g, T, C> {
             t_x,
             t_x_blinding,
             e_blinding,
-            ipp_proof,
+        })
+    }
+}
+
+/// A proof left with the rounds of its inner-product argument, see `Phase1::process_challenge`.
+pub struct Phase2<T: BorrowMut<Transcript>, C: AffineRepr> {
+    transcript: T,
+    ipp: InnerProductProver<C>,
+    recorder: PhaseRecorder,
+    A_I1: C,
+    A_O1: C,
+    S1: C,
+    A_I2: C,
+    A_O2: C,
+    S2: C,
+    T: Vec<C>,
+    t_x: C::ScalarField,
+    t_x_blinding: C::ScalarField,
+    e_blinding: C::ScalarField,
+}
+
+impl<T: BorrowMut<Transcript>, C: AffineRepr> Phase2<T, C> {
+    /// The number of rounds of the inner-product argument left, each halving the vectors it opens.
+    pub fn rounds_remaining(&self) -> usize {
+        self.ipp.rounds_remaining()
+    }
+
+    /// Runs the next round of the inner-product argument. Does nothing once all rounds are run.
+    pub fn finish_ipp_round(&mut self) {
+        if self.ipp.rounds_remaining() == 0 {
+            return;
+        }
+        let phase = start_phase!("inner_product", n = 1usize << self.ipp.rounds_remaining());
+        self.ipp.round(self.transcript.borrow_mut());
+        self.recorder.end(phase);
+    }
+
+    /// Runs the rounds left and returns the proof.
+    pub fn finalize(self) -> R1CSProof<C> {
+        self.finish().0
+    }
+
+    fn finish(mut self) -> (R1CSProof<C>, T, PhaseRecorder) {
+        while self.ipp.rounds_remaining() > 0 {
+            self.finish_ipp_round();
+        }
+        let proof = R1CSProof {
+            version: R1CS_PROOF_VERSION,
+            A_I1: self.A_I1,
+            A_O1: self.A_O1,
+            S1: self.S1,
+            A_I2: self.A_I2,
+            A_O2: self.A_O2,
+            S2: self.S2,
+            T: self.T,
+            t_x: self.t_x,
+            t_x_blinding: self.t_x_blinding,
+            e_blinding: self.e_blinding,
+            ipp_proof: self.ipp.finish(),
         };
-        Ok((proof, self.transcript))
+        (proof, self.transcript, self.recorder)
     }
 }
+
+/// Computes the commitment `<l, G> + <r, H> + blinding * B_blinding` to wires,
+/// going over the wires in place a chunk at a time instead of gathering them with the generators.
+pub(super) fn commit_wires<C: AffineRepr>(
+    pc_gens: &PedersenGens<C>,
+    G: &[C],
+    H: &[C],
+    l: &[C::ScalarField],
+    r: &[C::ScalarField],
+    blinding: C::ScalarField,
+) -> C {
+    (util::msm_chunked(G, l) + util::msm_chunked(H, r) + pc_gens.B_blinding * blinding).into()
+}
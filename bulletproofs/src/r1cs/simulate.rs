@@ -0,0 +1,253 @@
+#![allow(non_snake_case)]
+
+//! Proofs made without a witness, and corruptions of real proofs, for negative tests.
+//!
+//! A simulated proof has random group elements and scalars in every position a proof of its
+//! [`ProofShape`] has, so it deserializes and is checked all the way to the final multiscalar
+//! multiplication, which it fails. [`Verifier::verification_scalars_and_points`] of a simulated proof
+//! succeeds, which measures the verifier's work without running a prover.
+//!
+//! A [`Corruption`] changes a single component of a real proof, so that adversarial tests name
+//! what they change rather than editing the serialized bytes.
+
+use ark_ec::AffineRepr;
+use ark_ff::One;
+use ark_std::rand::{CryptoRng, RngCore};
+use ark_std::UniformRand;
+use core::iter;
+
+use super::proof::{R1CSProof, R1CS_PROOF_VERSION};
+use super::verifier::Verifier;
+use crate::inner_product_proof::InnerProductProof;
+use merlin::Transcript;
+use std::borrow::BorrowMut;
+
+/// What the structure of a proof of a circuit depends on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofShape {
+    /// Number of multipliers, of which the inner product proof has one round per doubling once padded.
+    pub multipliers: usize,
+    /// Number of high-level variables committed with [`Verifier::commit`].
+    pub commitments: usize,
+    /// Number of vector commitments, which set the degree of \\(t(x)\\).
+    pub vector_commitments: usize,
+    /// Whether the circuit has randomized constraints, and so second phase commitments.
+    pub randomized: bool,
+}
+
+impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
+    /// The shape of the proofs of the constraints laid down so far.
+    /// The multipliers of randomized constraints are not counted, they are only allocated when verifying.
+    pub fn proof_shape(&self) -> ProofShape {
+        ProofShape {
+            multipliers: self.num_vars,
+            commitments: self.V.len(),
+            vector_commitments: self.vec_comms.len(),
+            randomized: !self.deferred_constraints.is_empty(),
+        }
+    }
+}
+
+/// A proof made without a witness, and the commitments a verifier checks it against.
+#[derive(Clone, Debug)]
+pub struct SimulatedProof<C: AffineRepr> {
+    pub proof: R1CSProof<C>,
+    /// The commitments to the high-level variables.
+    pub commitments: Vec<C>,
+    /// The vector commitments.
+    pub vector_commitments: Vec<C>,
+}
+
+/// A proof of `shape` with random group elements and scalars, and random commitments of the counts of `shape`.
+/// Verifying it fails, but for a negligible probability.
+pub fn simulate<C: AffineRepr, R: RngCore + CryptoRng>(
+    shape: &ProofShape,
+    rng: &mut R,
+) -> SimulatedProof<C> {
+    let mut point = || C::Group::rand(rng).into();
+    // The same structure as that of `Prover::prove`.
+    let op_degree = 2 + 2 * (shape.vector_commitments / 2);
+    let t_poly_deg = 2 * (op_degree + 1);
+    let T = (0..t_poly_deg + 1)
+        .map(|d| if d == op_degree { C::zero() } else { point() })
+        .collect();
+    let rounds = shape.multipliers.next_power_of_two().trailing_zeros() as usize;
+    let L_vec = iter::repeat_with(&mut point).take(rounds).collect();
+    let R_vec = iter::repeat_with(&mut point).take(rounds).collect();
+    let (A_I2, A_O2, S2) = if shape.randomized && shape.vector_commitments == 0 {
+        (point(), point(), point())
+    } else {
+        (C::zero(), C::zero(), C::zero())
+    };
+    let proof = R1CSProof {
+        version: R1CS_PROOF_VERSION,
+        A_I1: point(),
+        A_O1: point(),
+        S1: point(),
+        A_I2,
+        A_O2,
+        S2,
+        T,
+        t_x: C::ScalarField::rand(rng),
+        t_x_blinding: C::ScalarField::rand(rng),
+        e_blinding: C::ScalarField::rand(rng),
+        ipp_proof: InnerProductProof {
+            L_vec,
+            R_vec,
+            a: C::ScalarField::rand(rng),
+            b: C::ScalarField::rand(rng),
+        },
+    };
+    SimulatedProof {
+        proof,
+        commitments: iter::repeat_with(|| C::Group::rand(rng).into())
+            .take(shape.commitments)
+            .collect(),
+        vector_commitments: iter::repeat_with(|| C::Group::rand(rng).into())
+            .take(shape.vector_commitments)
+            .collect(),
+    }
+}
+
+/// A group element of a proof.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofPoint {
+    A_I1,
+    A_O1,
+    S1,
+    A_I2,
+    A_O2,
+    S2,
+    /// The commitment to a coefficient of \\(t(x)\\).
+    T(usize),
+    /// The left commitment of a round of the inner product proof.
+    L(usize),
+    /// The right commitment of a round of the inner product proof.
+    R(usize),
+}
+
+/// A scalar of a proof.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofScalar {
+    t_x,
+    t_x_blinding,
+    e_blinding,
+    /// The final scalar `a` of the inner product proof.
+    a,
+    /// The final scalar `b` of the inner product proof.
+    b,
+}
+
+/// A change of a single component of a proof.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Corruption {
+    /// Removes the last rounds of the inner product proof.
+    TruncateInnerProduct(usize),
+    /// Removes the last commitment to a coefficient of \\(t(x)\\).
+    TruncateT,
+    /// Exchanges two group elements.
+    Swap(ProofPoint, ProofPoint),
+    /// Adds the generator of the group to a group element.
+    Shift(ProofPoint),
+    /// Adds one to a scalar.
+    Bump(ProofScalar),
+}
+
+impl Corruption {
+    /// A corruption of each class for every component of `proof`, and swaps of its commitments.
+    /// Each changes the proof: swaps of equal points, e.g. of absent second phase commitments, are left out.
+    pub fn all<C: AffineRepr>(proof: &R1CSProof<C>) -> Vec<Corruption> {
+        use self::ProofPoint::*;
+        let rounds = proof.ipp_proof.L_vec.len();
+        let mut points = vec![A_I1, A_O1, S1, A_I2, A_O2, S2];
+        points.extend((0..proof.T.len()).map(T));
+        points.extend((0..rounds).map(L));
+        points.extend((0..rounds).map(R));
+
+        let mut corruptions = vec![Corruption::TruncateT];
+        if rounds > 0 {
+            corruptions.push(Corruption::TruncateInnerProduct(1));
+        }
+        corruptions.extend(points.iter().map(|point| Corruption::Shift(*point)));
+        corruptions.extend(
+            points
+                .windows(2)
+                .map(|pair| Corruption::Swap(pair[0], pair[1])),
+        );
+        if rounds > 0 {
+            corruptions.push(Corruption::Swap(L(0), R(0)));
+        }
+        corruptions.extend(
+            [
+                ProofScalar::t_x,
+                ProofScalar::t_x_blinding,
+                ProofScalar::e_blinding,
+                ProofScalar::a,
+                ProofScalar::b,
+            ]
+            .iter()
+            .map(|scalar| Corruption::Bump(*scalar)),
+        );
+        corruptions.retain(|corruption| corruption.apply(proof).to_bytes() != proof.to_bytes());
+        corruptions
+    }
+
+    /// `proof` with this corruption.
+    ///
+    /// # Panics
+    ///
+    /// If the proof has no such component, e.g. fewer rounds than truncated.
+    pub fn apply<C: AffineRepr>(&self, proof: &R1CSProof<C>) -> R1CSProof<C> {
+        let mut corrupted = proof.clone();
+        match *self {
+            Corruption::TruncateInnerProduct(rounds) => {
+                let remaining = corrupted.ipp_proof.L_vec.len() - rounds;
+                corrupted.ipp_proof.L_vec.truncate(remaining);
+                corrupted.ipp_proof.R_vec.truncate(remaining);
+            }
+            Corruption::TruncateT => {
+                corrupted.T.pop().expect("a proof has commitments to t(x)");
+            }
+            Corruption::Swap(first, second) => {
+                let first_point = *point_mut(&mut corrupted, first);
+                let second_point = *point_mut(&mut corrupted, second);
+                *point_mut(&mut corrupted, first) = second_point;
+                *point_mut(&mut corrupted, second) = first_point;
+            }
+            Corruption::Shift(point) => {
+                let point = point_mut(&mut corrupted, point);
+                *point = (*point + C::generator()).into();
+            }
+            Corruption::Bump(scalar) => {
+                *scalar_mut(&mut corrupted, scalar) += C::ScalarField::one();
+            }
+        }
+        corrupted
+    }
+}
+
+fn point_mut<C: AffineRepr>(proof: &mut R1CSProof<C>, point: ProofPoint) -> &mut C {
+    match point {
+        ProofPoint::A_I1 => &mut proof.A_I1,
+        ProofPoint::A_O1 => &mut proof.A_O1,
+        ProofPoint::S1 => &mut proof.S1,
+        ProofPoint::A_I2 => &mut proof.A_I2,
+        ProofPoint::A_O2 => &mut proof.A_O2,
+        ProofPoint::S2 => &mut proof.S2,
+        ProofPoint::T(i) => &mut proof.T[i],
+        ProofPoint::L(i) => &mut proof.ipp_proof.L_vec[i],
+        ProofPoint::R(i) => &mut proof.ipp_proof.R_vec[i],
+    }
+}
+
+fn scalar_mut<C: AffineRepr>(proof: &mut R1CSProof<C>, scalar: ProofScalar) -> &mut C::ScalarField {
+    match scalar {
+        ProofScalar::t_x => &mut proof.t_x,
+        ProofScalar::t_x_blinding => &mut proof.t_x_blinding,
+        ProofScalar::e_blinding => &mut proof.e_blinding,
+        ProofScalar::a => &mut proof.ipp_proof.a,
+        ProofScalar::b => &mut proof.ipp_proof.b,
+    }
+}
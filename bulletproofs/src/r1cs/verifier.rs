@@ -2,7 +2,8 @@
 
 use ark_ec::{AffineRepr, VariableBaseMSM};
 use ark_ff::Field;
-use ark_std::{One, UniformRand, Zero};
+use ark_serialize::CanonicalSerialize;
+use ark_std::{One, Zero};
 use core::borrow::BorrowMut;
 use core::mem;
 use merlin::Transcript;
@@ -11,11 +12,16 @@ use super::constraint_system::{
     ConstraintSystem, RandomizableConstraintSystem, RandomizedConstraintSystem,
 };
 use super::linear_combination::{LinearCombination, Variable};
-use super::proof::R1CSProof;
+use super::proof::{R1CSProof, R1CS_PROOF_VERSION};
 
 use crate::errors::R1CSError;
-use crate::generators::{BulletproofGens, PedersenGens};
+use crate::generators::{BulletproofGens, PedersenGens, PrecomputedGens};
+use crate::r1cs::metrics::PhaseRecorder;
 use crate::r1cs::Metrics;
+#[cfg(feature = "trace")]
+use crate::r1cs::ProofMetrics;
+#[cfg(feature = "debug")]
+use crate::r1cs::{diagnose_mismatch, Operation, OperationLog};
 use crate::transcript::TranscriptProtocol;
 
 use super::op_splits;
@@ -29,11 +35,13 @@ use super::op_splits;
 /// When all constraints are added, the verifying code calls `verify`
 /// which consumes the `Verifier` instance, samples random challenges
 /// that instantiate the randomized constraints, and verifies the proof.
+/// Constraints without randomized constraints can instead be checked
+/// against many proofs with `verify_ref`, which borrows the `Verifier`.
 pub struct Verifier<T: BorrowMut<Transcript>, C: AffineRepr> {
-    transcript: T,
+    pub(super) transcript: T,
     constraints: Vec<LinearCombination<C::ScalarField>>,
 
-    vec_comms: Vec<(C, usize)>,
+    pub(super) vec_comms: Vec<(C, usize)>,
 
     /// Records the number of low-level variables allocated in the
     /// constraint system.
@@ -42,18 +50,25 @@ pub struct Verifier<T: BorrowMut<Transcript>, C: AffineRepr> {
     /// themselves, it doesn't record the assignments (they're all
     /// `Missing`), so the `num_vars` isn't kept implicitly in the
     /// variable assignments.
-    num_vars: usize,
-    V: Vec<C>,
+    pub(super) num_vars: usize,
+    pub(super) V: Vec<C>,
+
+    /// The public constants of the statement, in the order the gadgets asked for them.
+    public_constants: Vec<C::ScalarField>,
 
     /// This list holds closures that will be called in the second phase of the protocol,
     /// when non-randomized variables are committed.
     /// After that, the option will flip to None and additional calls to `randomize_constraints`
     /// will invoke closures immediately.
-    deferred_constraints:
+    pub(super) deferred_constraints:
         Vec<Box<dyn FnOnce(&mut RandomizingVerifier<T, C>) -> Result<(), R1CSError>>>,
 
     /// Index of a pending multiplier that's not fully assigned yet.
     pending_multiplier: Option<usize>,
+
+    /// The operations laid down so far.
+    #[cfg(feature = "debug")]
+    operations: OperationLog,
 }
 
 /// Verifier in the randomizing phase.
@@ -81,6 +96,8 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> ConstraintSystem<C::ScalarField> f
         Variable<C::ScalarField>,
         Variable<C::ScalarField>,
     ) {
+        #[cfg(feature = "debug")]
+        self.operations.record(Operation::Multiply);
         let var = self.num_vars;
         self.num_vars += 1;
 
@@ -102,6 +119,8 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> ConstraintSystem<C::ScalarField> f
         &mut self,
         _: Option<C::ScalarField>,
     ) -> Result<Variable<C::ScalarField>, R1CSError> {
+        #[cfg(feature = "debug")]
+        self.operations.record(Operation::Allocate);
         match self.pending_multiplier {
             None => {
                 let i = self.num_vars;
@@ -127,6 +146,8 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> ConstraintSystem<C::ScalarField> f
         ),
         R1CSError,
     > {
+        #[cfg(feature = "debug")]
+        self.operations.record(Operation::AllocateMultiplier);
         let var = self.num_vars;
         self.num_vars += 1;
 
@@ -151,8 +172,20 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> ConstraintSystem<C::ScalarField> f
         // TODO: check that the linear combinations are valid
         // (e.g. that variables are valid, that the linear combination
         // evals to 0 for prover, etc).
+        #[cfg(feature = "debug")]
+        self.operations.record(Operation::Constrain(lc.terms.len()));
         self.constraints.push(lc);
     }
+
+    fn public_constant(&mut self, value: C::ScalarField) -> LinearCombination<C::ScalarField> {
+        self.public_constants.push(value);
+        Variable::Public(self.public_constants.len() - 1).into()
+    }
+
+    #[cfg(feature = "debug")]
+    fn operation_log(&self) -> &OperationLog {
+        &self.operations
+    }
 }
 
 impl<T: BorrowMut<Transcript>, C: AffineRepr> RandomizableConstraintSystem<C::ScalarField>
@@ -216,6 +249,15 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> ConstraintSystem<C::ScalarField>
     fn constrain(&mut self, lc: LinearCombination<C::ScalarField>) {
         self.verifier.constrain(lc)
     }
+
+    fn public_constant(&mut self, value: C::ScalarField) -> LinearCombination<C::ScalarField> {
+        self.verifier.public_constant(value)
+    }
+
+    #[cfg(feature = "debug")]
+    fn operation_log(&self) -> &OperationLog {
+        self.verifier.operation_log()
+    }
 }
 
 impl<T: BorrowMut<Transcript>, C: AffineRepr> RandomizedConstraintSystem<C::ScalarField>
@@ -263,9 +305,12 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
             transcript,
             num_vars: 0,
             V: Vec::new(),
+            public_constants: Vec::new(),
             constraints: Vec::new(),
             deferred_constraints: Vec::new(),
             pending_multiplier: None,
+            #[cfg(feature = "debug")]
+            operations: OperationLog::default(),
         }
     }
 
@@ -294,6 +339,8 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
     pub fn commit(&mut self, commitment: C) -> Variable<C::ScalarField> {
         let i = self.V.len();
         self.V.push(commitment);
+        #[cfg(feature = "debug")]
+        self.operations.record(Operation::Commit);
 
         // Add the commitment to the transcript.
         self.transcript.borrow_mut().append_point(b"V", &commitment);
@@ -310,6 +357,8 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
 
         // add to list of commitments
         self.vec_comms.push((comm, dimension));
+        #[cfg(feature = "debug")]
+        self.operations.record(Operation::CommitVec(dimension));
 
         // create variables for all the addressable coordinates
         (0..dimension)
@@ -317,76 +366,36 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
             .collect()
     }
 
-    /// Use a challenge, `z`, to flatten the constraints in the
-    /// constraint system into vectors used for proving and
-    /// verification.
-    ///
-    /// # Output
-    ///
-    /// Returns a tuple of
-    /// ```text
-    /// (wL, wR, wO, wV, wc)
-    /// ```
-    /// where `w{L,R,O}` is \\( z \cdot z^Q \cdot W_{L,R,O} \\).
+    /// Recomputes the commitment to a public vector committed with
+    /// [`Prover::commit_vec_public`](::r1cs::Prover::commit_vec_public) and adds it to the transcript.
     ///
-    /// This has the same logic as `ProverCS::flattened_constraints()`
-    /// but also computes the constant terms (which the prover skips
-    /// because they're not needed to construct the proof).
-    fn flattened_constraints(
+    /// The `pc_gens` and `bp_gens` must be the generators the proof is later verified with.
+    pub fn commit_vec_public(
         &mut self,
-        z: &C::ScalarField,
-    ) -> (
-        Vec<C::ScalarField>,
-        Vec<C::ScalarField>,
-        Vec<C::ScalarField>,
-        Vec<C::ScalarField>,
-        Vec<Vec<C::ScalarField>>,
-        C::ScalarField,
-    ) {
-        let n = self.num_vars;
-        let m = self.V.len();
-
-        let mut wL = vec![C::ScalarField::zero(); n];
-        let mut wR = vec![C::ScalarField::zero(); n];
-        let mut wO = vec![C::ScalarField::zero(); n];
-        let mut wV = vec![C::ScalarField::zero(); m];
-        let mut wc = C::ScalarField::zero();
-
-        let mut wVCs = Vec::with_capacity(self.vec_comms.len());
-        for (_, dim) in self.vec_comms.iter() {
-            wVCs.push(vec![C::ScalarField::zero(); *dim]);
-        }
+        v: &[C::ScalarField],
+        pc_gens: &PedersenGens<C>,
+        bp_gens: &BulletproofGens<C>,
+    ) -> Vec<Variable<C::ScalarField>> {
+        let comm = bp_gens
+            .share(0)
+            .commit_vec(pc_gens, v, C::ScalarField::zero());
+        self.commit_vec(v.len(), comm)
+    }
 
-        let mut exp_z = *z;
-        for lc in self.constraints.iter() {
-            for (var, coeff) in &lc.terms {
-                match var {
-                    Variable::MultiplierLeft(i) => {
-                        wL[*i] += exp_z * coeff;
-                    }
-                    Variable::MultiplierRight(i) => {
-                        wR[*i] += exp_z * coeff;
-                    }
-                    Variable::MultiplierOutput(i) => {
-                        wO[*i] += exp_z * coeff;
-                    }
-                    Variable::Committed(i) => {
-                        wV[*i] -= exp_z * coeff;
-                    }
-                    Variable::VectorCommit(j, i) => {
-                        // j : index of commitment
-                        // i : coordinate with-in commitment
-                        wVCs[*j][*i] += exp_z * coeff;
-                    }
-                    Variable::One(_) => {
-                        wc -= exp_z * coeff;
-                    }
-                }
-            }
-            exp_z *= z;
+    /// The constraints flattened with the challenge `z`, see `Circuit::flattened_constraints`.
+    #[cfg(feature = "prover")]
+    pub(super) fn flattened_constraints(
+        &self,
+        z: &C::ScalarField,
+    ) -> FlattenedConstraints<C::ScalarField> {
+        Circuit {
+            constraints: &self.constraints,
+            num_vars: self.num_vars,
+            V: &self.V,
+            vec_comms: &self.vec_comms,
+            public_constants: &self.public_constants,
         }
-
-        (wL, wR, wO, wV, wVCs, wc)
+        .flattened_constraints(z)
     }
 
     /// Calls all remembered callbacks with an API that
@@ -424,39 +433,150 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
         pc_gens: &PedersenGens<C>,
         bp_gens: &BulletproofGens<C>,
     ) -> Result<(), R1CSError> {
-        let verification_tuple = match self.verification_scalars_and_points(proof) {
-            Err(e) => return Err(e),
-            Ok(t) => t,
-        };
-        let padded_n = (verification_tuple.proof_independent_scalars.len() - 2) / 2;
+        self.verify_with_recorder(proof, pc_gens, bp_gens, &mut PhaseRecorder::default())
+    }
+
+    /// Like [`Verifier::verify`], comparing the operations of this verifier with `prover_log`,
+    /// the `operation_log` of the prover before it proved, when the proof does not verify.
+    /// If they diverge, fails with [`R1CSError::OperationMismatch`] naming the first diverging operation
+    /// and the digests of both sides, rather than with [`R1CSError::VerificationError`].
+    #[cfg(feature = "debug")]
+    pub fn verify_diagnosed(
+        self,
+        proof: &R1CSProof<C>,
+        pc_gens: &PedersenGens<C>,
+        bp_gens: &BulletproofGens<C>,
+        prover_log: &OperationLog,
+    ) -> Result<(), R1CSError> {
+        // The randomized constraints are laid down while verifying, the logs are compared before.
+        let mismatch = diagnose_mismatch(prover_log, &self.operations);
+        let verifier_digest = self.operations.digest_hex();
+        match (self.verify(proof, pc_gens, bp_gens), mismatch) {
+            (Err(R1CSError::VerificationError), Some(mismatch)) => {
+                Err(R1CSError::OperationMismatch {
+                    description: format!(
+                        "{} (prover digest {}, verifier digest {})",
+                        mismatch,
+                        prover_log.digest_hex(),
+                        verifier_digest
+                    ),
+                })
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Like [`Verifier::verify`], but also reports how long each phase took.
+    #[cfg(feature = "trace")]
+    pub fn verify_with_metrics(
+        self,
+        proof: &R1CSProof<C>,
+        pc_gens: &PedersenGens<C>,
+        bp_gens: &BulletproofGens<C>,
+    ) -> Result<ProofMetrics, R1CSError> {
+        let constraint_system = self.metrics();
+        let mut recorder = PhaseRecorder::default();
+        self.verify_with_recorder(proof, pc_gens, bp_gens, &mut recorder)?;
+        Ok(ProofMetrics {
+            constraint_system,
+            padded_multipliers: recorder.padded_multipliers,
+            msm_points: recorder.msm_points,
+            phases: recorder.phases,
+        })
+    }
 
-        // We are performing a single-party circuit proof, so party index is 0.
-        let gens = bp_gens.share(0);
+    fn verify_with_recorder(
+        self,
+        proof: &R1CSProof<C>,
+        pc_gens: &PedersenGens<C>,
+        bp_gens: &BulletproofGens<C>,
+        recorder: &mut PhaseRecorder,
+    ) -> Result<(), R1CSError> {
+        let verification_tuple =
+            self.verification_scalars_and_points_with_recorder(proof, recorder)?;
+        check_verification_tuple(verification_tuple, pc_gens, bp_gens, recorder)
+    }
 
-        if bp_gens.gens_capacity < padded_n {
-            return Err(R1CSError::InvalidGeneratorsLength);
+    /// Verifies `proof` against the constraints of this verifier without consuming it, so that the constraints
+    /// are built once for many proofs of statements of the same shape.
+    ///
+    /// The `statement` is a fresh verifier on the transcript of the proof, in which the commitments of its statement
+    /// were committed, and anything else the gadgets bind to the transcript was appended, in the order the verifier
+    /// building the constraints did, but which has no constraints itself:
+    /// the constraints of this verifier are checked against the commitments and the transcript of `statement`.
+    /// A statement that commits another number of values, or vectors of other dimensions, is rejected with
+    /// [`R1CSError::GadgetError`], as are the verifiers with randomized constraints, whose callbacks run once.
+    ///
+    /// The constant terms of the constraints are those of this verifier, but for the public data of a statement,
+    /// e.g. a public amount, which gadgets take from [`ConstraintSystem::public_constant`]: `statement` supplies
+    /// its own by calling `public_constant` with them in the same order, and the constraints are checked with
+    /// those of `statement` in place of those they were built with. A statement that supplies another number
+    /// of public constants is rejected as one of another shape. Data of a statement baked into the constraints
+    /// otherwise, e.g. with [`constant`](crate::r1cs::constant), stays that of this verifier.
+    pub fn verify_ref<U: BorrowMut<Transcript>>(
+        &self,
+        mut statement: Verifier<U, C>,
+        proof: &R1CSProof<C>,
+        pc_gens: &PedersenGens<C>,
+        bp_gens: &BulletproofGens<C>,
+    ) -> Result<(), R1CSError> {
+        if !self.deferred_constraints.is_empty() {
+            return Err(R1CSError::GadgetError {
+                description: "randomized constraints cannot be reused".to_string(),
+            });
+        }
+        if statement.V.len() != self.V.len()
+            || !statement
+                .vec_comms
+                .iter()
+                .map(|(_, dim)| dim)
+                .eq(self.vec_comms.iter().map(|(_, dim)| dim))
+            || statement.public_constants.len() != self.public_constants.len()
+            || statement.num_vars != 0
+            || !statement.constraints.is_empty()
+            || !statement.deferred_constraints.is_empty()
+        {
+            return Err(R1CSError::GadgetError {
+                description: "the statement does not match the shape of the verifier".to_string(),
+            });
         }
 
-        use std::iter;
-        let fixed_points = iter::once(pc_gens.B)
-            .chain(iter::once(pc_gens.B_blinding))
-            .chain(gens.G(padded_n).copied())
-            .chain(gens.H(padded_n).copied());
-
-        let mega_check: C::Group = C::Group::msm_unchecked(
-            verification_tuple
-                .proof_dependent_points
-                .into_iter()
-                .chain(fixed_points)
-                .collect::<Vec<_>>()
-                .as_slice(),
-            verification_tuple
-                .proof_dependent_scalars
-                .into_iter()
-                .chain(verification_tuple.proof_independent_scalars.into_iter())
-                .collect::<Vec<_>>()
-                .as_slice(),
-        );
+        // The constraints are padded as in `verify`, without allocating the padding multipliers.
+        let circuit = Circuit {
+            constraints: &self.constraints,
+            num_vars: self.size(),
+            V: &statement.V,
+            vec_comms: &statement.vec_comms,
+            public_constants: &statement.public_constants,
+        };
+        let transcript = statement.transcript.borrow_mut();
+        let mut recorder = PhaseRecorder::default();
+        circuit.commit_first_phase(transcript, proof)?;
+        transcript.r1cs_1phase_domain_sep();
+        let verification_tuple =
+            circuit.verification_tuple(transcript, circuit.num_vars, proof, &mut recorder)?;
+        check_verification_tuple(verification_tuple, pc_gens, bp_gens, &mut recorder)
+    }
+
+    /// Like [`Verifier::verify`], with the precomputed generators of circuits of the size of this one.
+    /// Rejects proofs of circuits of another size with [`R1CSError::FormatError`].
+    pub fn verify_precomputed(
+        self,
+        proof: &R1CSProof<C>,
+        gens: &PrecomputedGens<C>,
+    ) -> Result<(), R1CSError> {
+        if proof.padded_multipliers() != Some(gens.padded_n()) {
+            return Err(R1CSError::FormatError);
+        }
+        let verification_tuple = self.verification_scalars_and_points(proof)?;
+        if verification_tuple.proof_independent_scalars.len() != 2 + 2 * gens.padded_n() {
+            return Err(R1CSError::FormatError);
+        }
+
+        let mega_check = C::Group::msm_unchecked(
+            &verification_tuple.proof_dependent_points,
+            &verification_tuple.proof_dependent_scalars,
+        ) + gens.msm(&verification_tuple.proof_independent_scalars);
 
         if !mega_check.is_zero() {
             return Err(R1CSError::VerificationError);
@@ -466,8 +586,16 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
     }
 
     pub fn verification_scalars_and_points(
+        self,
+        proof: &R1CSProof<C>,
+    ) -> Result<VerificationTuple<C>, R1CSError> {
+        self.verification_scalars_and_points_with_recorder(proof, &mut PhaseRecorder::default())
+    }
+
+    fn verification_scalars_and_points_with_recorder(
         mut self,
         proof: &R1CSProof<C>,
+        recorder: &mut PhaseRecorder,
     ) -> Result<VerificationTuple<C>, R1CSError> {
         // pad
         while self.size() > self.num_vars {
@@ -476,12 +604,66 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
 
         let n1 = self.size();
 
+        Circuit {
+            constraints: &self.constraints,
+            num_vars: self.num_vars,
+            V: &self.V,
+            vec_comms: &self.vec_comms,
+            public_constants: &self.public_constants,
+        }
+        .commit_first_phase(self.transcript.borrow_mut(), proof)?;
+
+        // Process the remaining constraints.
+        let phase = start_phase!("randomized_constraints");
+        self = self.create_randomized_constraints()?;
+        recorder.end(phase);
+
+        Circuit {
+            constraints: &self.constraints,
+            num_vars: self.num_vars,
+            V: &self.V,
+            vec_comms: &self.vec_comms,
+            public_constants: &self.public_constants,
+        }
+        .verification_tuple(self.transcript.borrow_mut(), n1, proof, recorder)
+    }
+}
+
+/// The weights `(wL, wR, wO, wV, wVCs, wc)` of flattened constraints, see `Circuit::flattened_constraints`.
+type FlattenedConstraints<F> = (Vec<F>, Vec<F>, Vec<F>, Vec<F>, Vec<Vec<F>>, F);
+
+/// The constraints of a verifier and the commitments they are checked against, borrowed from a single verifier,
+/// or from the verifier that built the constraints and the one that committed the statement, see [`Verifier::verify_ref`].
+struct Circuit<'a, C: AffineRepr> {
+    constraints: &'a [LinearCombination<C::ScalarField>],
+    /// The number of multipliers, padded to the dimension of the vector commitments.
+    num_vars: usize,
+    V: &'a [C],
+    vec_comms: &'a [(C, usize)],
+    /// The values of the `Variable::Public` terms of the constraints.
+    public_constants: &'a [C::ScalarField],
+}
+
+impl<'a, C: AffineRepr> Circuit<'a, C> {
+    /// Appends the number of commitments and the first phase of `proof` to the transcript,
+    /// once the non-randomized constraints are built.
+    fn commit_first_phase(
+        &self,
+        transcript: &mut Transcript,
+        proof: &R1CSProof<C>,
+    ) -> Result<(), R1CSError> {
+        if proof.version != R1CS_PROOF_VERSION {
+            return Err(R1CSError::UnsupportedVersion {
+                version: proof.version,
+            });
+        }
+
         // Commit a length _suffix_ for the number of high-level variables.
         // We cannot do this in advance because user can commit variables one-by-one,
         // but this suffix provides safe disambiguation because each variable
         // is prefixed with a separate label.
-        let transcript = self.transcript.borrow_mut();
         transcript.append_u64(b"m", self.V.len() as u64);
+        transcript.r1cs_version(proof.version);
 
         // number of commitments
         let ncomm = self.vec_comms.len();
@@ -489,6 +671,35 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
         // op_degree = 2 + 2 * floor(#comm / 2)
         let op_degree = 2 + 2 * (ncomm / 2);
         let t_poly_deg = 2 * (op_degree + 1);
+
+        // The proof is attacker controlled, reject malformed proofs instead of panicking on them.
+        if proof.T.len() != t_poly_deg + 1 || proof.T[op_degree] != C::zero() {
+            return Err(R1CSError::FormatError);
+        }
+        if ncomm != 0
+            && (proof.A_I2 != C::zero() || proof.A_O2 != C::zero() || proof.S2 != C::zero())
+        {
+            return Err(R1CSError::FormatError);
+        }
+
+        transcript.validate_and_append_point(b"A_I1", &proof.A_I1)?;
+        transcript.validate_and_append_point(b"A_O1", &proof.A_O1)?;
+        transcript.validate_and_append_point(b"S1", &proof.S1)?;
+        Ok(())
+    }
+
+    /// The scalars and points of the verification of `proof`, once the randomized constraints are built,
+    /// of which the first `n1` multipliers are those of the first phase.
+    fn verification_tuple(
+        &self,
+        transcript: &mut Transcript,
+        n1: usize,
+        proof: &R1CSProof<C>,
+        recorder: &mut PhaseRecorder,
+    ) -> Result<VerificationTuple<C>, R1CSError> {
+        let ncomm = self.vec_comms.len();
+        let op_degree = 2 + 2 * (ncomm / 2);
+        let t_poly_deg = 2 * (op_degree + 1);
         let ops = op_splits(op_degree);
 
         #[cfg(debug_assertions)]
@@ -502,24 +713,14 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
         let op_aO = ops[1];
         let op_vec = &ops[2..];
 
-        debug_assert_eq!(t_poly_deg + 1, proof.T.len());
-
-        transcript.validate_and_append_point(b"A_I1", &proof.A_I1)?;
-        transcript.validate_and_append_point(b"A_O1", &proof.A_O1)?;
-        transcript.validate_and_append_point(b"S1", &proof.S1)?;
-
-        // Process the remaining constraints.
-        self = self.create_randomized_constraints()?;
-
-        let n = self.size();
-
-        let transcript = self.transcript.borrow_mut();
+        let n = self.num_vars;
 
         // If the number of multiplications is not 0 or a power of 2, then pad the circuit.
 
         let n2 = n - n1;
         let padded_n = n.next_power_of_two();
         let pad = padded_n - n;
+        recorder.set_padded_multipliers(padded_n);
 
         // println!("padded_n = {}", padded_n);
 
@@ -535,7 +736,6 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
         let y = transcript.challenge_scalar::<C>(b"y");
         let z = transcript.challenge_scalar::<C>(b"z");
 
-        let transcript = self.transcript.borrow_mut();
         for d in 0..t_poly_deg + 1 {
             if d == op_degree {
                 continue;
@@ -554,8 +754,9 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
         // they are assigned the lowest powers and therefore the coefficients
         // in the combination are correspondingly assigned the highest powers
 
-        let mut rng = rand::thread_rng();
-        let r = C::ScalarField::rand(&mut rng);
+        let mut weights = weight_transcript(transcript);
+        append_serialized(&mut weights, b"proof", proof);
+        let r = weights.challenge_scalar::<C>(b"r");
 
         // precompute x powers
         let mut xs: Vec<C::ScalarField> = vec![C::ScalarField::zero(); t_poly_deg + 1];
@@ -573,16 +774,24 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
 
         let w = transcript.challenge_scalar::<C>(b"w");
 
+        let phase = start_phase!(
+            "flatten_constraints",
+            constraints = self.constraints.len(),
+            n = n
+        );
         let (wL, wR, wO, wV, wVCs, wc) = self.flattened_constraints(&z);
+        recorder.end(phase);
 
         #[cfg(debug_assertions)]
         println!("verifier wVCs = {:?}", &wVCs);
 
         // Get IPP variables
+        let phase = start_phase!("inner_product", n = padded_n);
         let (u_sq, u_inv_sq, s) = proof
             .ipp_proof
-            .verification_scalars(padded_n, self.transcript.borrow_mut())
+            .verification_scalars(padded_n, transcript)
             .map_err(|_| R1CSError::VerificationError)?;
+        recorder.end(phase);
 
         let a = proof.ipp_proof.a;
         let b = proof.ipp_proof.b;
@@ -616,6 +825,16 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
             .zip(s.iter().take(padded_n)) // s is from folding
             .map(|((yneg_wRi, u_or_1), s_i)| u_or_1 * (xwR * yneg_wRi - a * s_i));
 
+        // the weights of the vector commitments (higher degrees), combined per coordinate in a single pass
+        // over the coordinates of each commitment, rather than visiting every commitment for every coordinate
+        let mut wVC_comb = vec![C::ScalarField::zero(); padded_n];
+        for (j, wVC) in wVCs.iter().enumerate() {
+            let x = xs[op_vec[j].1];
+            for (comb, wVCji) in wVC_comb.iter_mut().zip(wVC) {
+                *comb += x * wVCji;
+            }
+        }
+
         // r(x)
         let mut h_scalars = Vec::with_capacity(padded_n);
         {
@@ -623,8 +842,9 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
             let mut wO = wO.into_iter();
             let mut s = s.iter().rev().take(padded_n);
             let mut y_inv_vec = y_inv_vec.into_iter();
+            let mut wVC_comb = wVC_comb.into_iter();
 
-            for i in 0..padded_n {
+            for _ in 0..padded_n {
                 let y_inv = y_inv_vec.next().unwrap();
                 let u_or_1 = u_for_h.next().unwrap();
 
@@ -633,17 +853,11 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
                 let wOi = wO.next().unwrap_or_default();
 
                 // compute right polynomial combination
-                let mut comb = C::ScalarField::zero();
+                let mut comb = wVC_comb.next().unwrap();
                 {
                     // special terms
                     comb += xs[op_aLaR.1] * wLi;
                     comb += xs[op_aO.1] * wOi;
-
-                    // add terms for vector commitments (higher degrees).
-                    for j in 0..wVCs.len() {
-                        let wVCji = wVCs[j].get(i).copied().unwrap_or_default();
-                        comb += xs[op_vec[j].1] * wVCji;
-                    }
                 }
 
                 // y^{-n} o (w_O + w_L * x + w_VCi * x^2)
@@ -653,9 +867,6 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
             }
         }
 
-        debug_assert_eq!(proof.T[op_degree], C::zero());
-        debug_assert_eq!(proof.T.len(), t_poly_deg + 1);
-
         // homomorphically evaluate t polynomial at x
         let mut T_points = vec![];
         let mut T_scalars = vec![];
@@ -671,10 +882,6 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
             T_scalars.push(rxs[d]);
         }
 
-        debug_assert!(ncomm == 0 || proof.A_I2 == C::zero());
-        debug_assert!(ncomm == 0 || proof.A_O2 == C::zero());
-        debug_assert!(ncomm == 0 || proof.S2 == C::zero());
-
         let xI = xs[op_aLaR.0];
         let xO = xs[op_aO.0];
         let xS = xs[op_degree + 1];
@@ -721,6 +928,149 @@ impl<T: BorrowMut<Transcript>, C: AffineRepr> Verifier<T, C> {
             proof_independent_scalars: fixed_point_scalars,
         })
     }
+
+    /// Use a challenge, `z`, to flatten the constraints in the
+    /// constraint system into vectors used for proving and
+    /// verification.
+    ///
+    /// # Output
+    ///
+    /// Returns a tuple of
+    /// ```text
+    /// (wL, wR, wO, wV, wc)
+    /// ```
+    /// where `w{L,R,O}` is \\( z \cdot z^Q \cdot W_{L,R,O} \\).
+    ///
+    /// This has the same logic as `ProverCS::flattened_constraints()`
+    /// but also computes the constant terms (which the prover skips
+    /// because they're not needed to construct the proof).
+    fn flattened_constraints(&self, z: &C::ScalarField) -> FlattenedConstraints<C::ScalarField> {
+        let n = self.num_vars;
+        let m = self.V.len();
+
+        let mut wL = vec![C::ScalarField::zero(); n];
+        let mut wR = vec![C::ScalarField::zero(); n];
+        let mut wO = vec![C::ScalarField::zero(); n];
+        let mut wV = vec![C::ScalarField::zero(); m];
+        let mut wc = C::ScalarField::zero();
+
+        let mut wVCs = Vec::with_capacity(self.vec_comms.len());
+        for (_, dim) in self.vec_comms.iter() {
+            wVCs.push(vec![C::ScalarField::zero(); *dim]);
+        }
+
+        let mut exp_z = *z;
+        for lc in self.constraints.iter() {
+            for (var, coeff) in &lc.terms {
+                match var {
+                    Variable::MultiplierLeft(i) => {
+                        wL[*i] += exp_z * coeff;
+                    }
+                    Variable::MultiplierRight(i) => {
+                        wR[*i] += exp_z * coeff;
+                    }
+                    Variable::MultiplierOutput(i) => {
+                        wO[*i] += exp_z * coeff;
+                    }
+                    Variable::Committed(i) => {
+                        wV[*i] -= exp_z * coeff;
+                    }
+                    Variable::VectorCommit(j, i) => {
+                        // j : index of commitment
+                        // i : coordinate with-in commitment
+                        wVCs[*j][*i] += exp_z * coeff;
+                    }
+                    Variable::One(_) => {
+                        wc -= exp_z * coeff;
+                    }
+                    Variable::Public(i) => {
+                        wc -= exp_z * coeff * self.public_constants[*i];
+                    }
+                }
+            }
+            exp_z *= z;
+        }
+
+        (wL, wR, wO, wV, wVCs, wc)
+    }
+}
+
+/// Checks the verification of a single proof, with the generators it is verified with.
+fn check_verification_tuple<C: AffineRepr>(
+    verification_tuple: VerificationTuple<C>,
+    pc_gens: &PedersenGens<C>,
+    bp_gens: &BulletproofGens<C>,
+    recorder: &mut PhaseRecorder,
+) -> Result<(), R1CSError> {
+    // The number of multipliers of the circuit, padded, as declared by the constraints of this verifier,
+    // which the proof was checked against: only as many generators are in the multiscalar multiplication,
+    // so small circuits are cheap to verify with generators shared with large ones.
+    let padded_n = (verification_tuple.proof_independent_scalars.len() - 2) / 2;
+
+    // We are performing a single-party circuit proof, so party index is 0.
+    let gens = bp_gens.share(0);
+
+    if bp_gens.gens_capacity < padded_n {
+        return Err(R1CSError::InvalidGeneratorsLength);
+    }
+
+    use std::iter;
+    let fixed_points = iter::once(pc_gens.B)
+        .chain(iter::once(pc_gens.B_blinding))
+        .chain(gens.G(padded_n).copied())
+        .chain(gens.H(padded_n).copied());
+
+    let msm_points = verification_tuple.proof_dependent_points.len() + 2 + 2 * padded_n;
+    recorder.set_msm_points(msm_points);
+    let phase = start_phase!("msm", points = msm_points);
+    let mega_check: C::Group = C::Group::msm_unchecked(
+        verification_tuple
+            .proof_dependent_points
+            .into_iter()
+            .chain(fixed_points)
+            .collect::<Vec<_>>()
+            .as_slice(),
+        verification_tuple
+            .proof_dependent_scalars
+            .into_iter()
+            .chain(verification_tuple.proof_independent_scalars)
+            .collect::<Vec<_>>()
+            .as_slice(),
+    );
+    recorder.end(phase);
+
+    if !mega_check.is_zero() {
+        return Err(R1CSError::VerificationError);
+    }
+
+    Ok(())
+}
+
+/// A fork of `transcript` to draw the weights of checks combined into one multiscalar multiplication from,
+/// once everything the provers sent is appended to it. With the `prover` feature, and so an rng, fresh randomness
+/// is appended too; without, e.g. with `verify-only`, the weights are challenges on the proofs, which a prover
+/// cannot predict before fixing its proof.
+fn weight_transcript(transcript: &Transcript) -> Transcript {
+    let mut weights = transcript.clone();
+    weights.append_message(b"dom-sep", b"r1cs weights");
+    #[cfg(feature = "prover")]
+    {
+        let mut entropy = [0u8; 32];
+        rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut entropy);
+        weights.append_message(b"entropy", &entropy);
+    }
+    weights
+}
+
+fn append_serialized(
+    transcript: &mut Transcript,
+    label: &'static [u8],
+    data: &impl CanonicalSerialize,
+) {
+    let mut bytes = Vec::with_capacity(data.compressed_size());
+    data.serialize_compressed(&mut bytes)
+        .expect("serializing to a vector");
+    transcript.append_message(label, &bytes);
 }
 
 pub struct VerificationTuple<C: AffineRepr> {
@@ -729,43 +1079,51 @@ pub struct VerificationTuple<C: AffineRepr> {
     pub proof_independent_scalars: Vec<C::ScalarField>,
 }
 
+/// Verifies several proofs at once, with a single multiscalar multiplication
+/// weighted by random scalars. The proofs may be of circuits of different sizes.
 pub fn batch_verify<C: AffineRepr>(
     verification_tuples: Vec<VerificationTuple<C>>,
     pc_gens: &PedersenGens<C>,
     bp_gens: &BulletproofGens<C>,
 ) -> Result<(), R1CSError> {
-    let mut rng = rand::thread_rng();
-    let mut ver_iter = verification_tuples.into_iter();
-    let vt = ver_iter.next().unwrap();
-    let (mut proof_points, mut proof_point_scalars, mut linear_combination) = (
-        vt.proof_dependent_points,
-        vt.proof_dependent_scalars,
-        vt.proof_independent_scalars,
-    );
-    let padded_n = (linear_combination.len() - 2) / 2;
-
-    for mut vt in ver_iter {
+    let mut weights = weight_transcript(&Transcript::new(b"batch_verify"));
+    for vt in &verification_tuples {
+        append_serialized(&mut weights, b"points", &vt.proof_dependent_points);
+        append_serialized(&mut weights, b"scalars", &vt.proof_dependent_scalars);
+    }
+    // The proof independent scalars are those of B, B_blinding, then padded_n of G and of H.
+    let padded_n = verification_tuples
+        .iter()
+        .map(|vt| (vt.proof_independent_scalars.len() - 2) / 2)
+        .max()
+        .unwrap_or(0);
+    let mut proof_points = Vec::new();
+    let mut proof_point_scalars = Vec::new();
+    let mut linear_combination = vec![C::ScalarField::zero(); 2 + 2 * padded_n];
+
+    for (i, mut vt) in verification_tuples.into_iter().enumerate() {
         proof_points.append(&mut vt.proof_dependent_points);
 
-        // Sample random scalar
-        let random_scalar = C::ScalarField::rand(&mut rng);
+        // Sample random scalar, the first proof keeps its own weight
+        let random_scalar = if i == 0 {
+            C::ScalarField::one()
+        } else {
+            weights.challenge_scalar::<C>(b"weight")
+        };
 
         // Multiply all scalars
         let ps = vt
             .proof_dependent_scalars
             .into_iter()
             .map(|s| s * random_scalar);
-        let fps = vt
-            .proof_independent_scalars
-            .into_iter()
-            .map(|s| s * random_scalar);
+        proof_point_scalars.extend(ps);
 
-        proof_point_scalars.append(&mut ps.collect());
-        linear_combination = linear_combination
-            .iter()
-            .zip(fps)
-            .map(|(a, b)| *a + b)
-            .collect()
+        // A smaller proof's H scalars are shifted to the start of the H generators.
+        let n = (vt.proof_independent_scalars.len() - 2) / 2;
+        for (j, s) in vt.proof_independent_scalars.into_iter().enumerate() {
+            let position = if j < 2 + n { j } else { j + padded_n - n };
+            linear_combination[position] += s * random_scalar;
+        }
     }
 
     // We are performing a single-party circuit proof, so party index is 0.
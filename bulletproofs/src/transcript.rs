@@ -17,6 +17,9 @@ pub trait TranscriptProtocol {
     /// Append a domain separator for a constraint system.
     fn r1cs_domain_sep(&mut self);
 
+    /// Append the version of the format of an R1CS proof.
+    fn r1cs_version(&mut self, version: u8);
+
     /// Commit a domain separator for a CS without randomized constraints.
     fn r1cs_1phase_domain_sep(&mut self);
 
@@ -57,6 +60,10 @@ impl TranscriptProtocol for Transcript {
         self.append_message(b"dom-sep", b"r1cs v1");
     }
 
+    fn r1cs_version(&mut self, version: u8) {
+        self.append_u64(b"r1cs-version", version as u64);
+    }
+
     fn r1cs_1phase_domain_sep(&mut self) {
         self.append_message(b"dom-sep", b"r1cs-1phase");
     }
@@ -95,20 +102,17 @@ impl TranscriptProtocol for Transcript {
     }
 
     fn challenge_scalar<C: AffineRepr>(&mut self, label: &'static [u8]) -> C::ScalarField {
-        extern crate crypto;
-        use crypto::digest::Digest;
-        use crypto::sha3::Sha3;
+        use sha3::{Digest, Sha3_256};
 
         let mut bytes = [0u8; 64];
         self.challenge_bytes(label, &mut bytes);
 
         for i in 0..=u8::max_value() {
-            let mut sha = Sha3::sha3_256();
-            sha.input(&bytes);
-            sha.input(&[i]);
-            let mut buf = [0u8; 32];
+            let mut sha = Sha3_256::new();
+            sha.update(bytes);
+            sha.update([i]);
+            let buf = sha.finalize();
 
-            sha.result(&mut buf);
             let res = <C::ScalarField as Field>::from_random_bytes(&buf);
 
             if let Some(scalar) = res {
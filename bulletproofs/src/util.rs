@@ -5,8 +5,9 @@ extern crate alloc;
 
 use alloc::vec;
 use alloc::vec::Vec;
-use ark_ec::AffineRepr;
-use ark_ff::Field;
+use ark_ec::{AffineRepr, VariableBaseMSM};
+use ark_ff::{Field, Zero};
+use ark_serialize::{CanonicalDeserialize, Compress, Read, SerializationError, Validate};
 use zeroize::ZeroizeOnDrop;
 
 use crate::inner_product_proof::inner_product;
@@ -17,6 +18,7 @@ pub struct VecPoly1<F: Field>(pub Vec<F>, pub Vec<F>);
 
 /// Represents a degree-3 vector polynomial
 /// \\(\mathbf{a} + \mathbf{b} \cdot x + \mathbf{c} \cdot x^2 + \mathbf{d} \cdot x^3 \\).
+#[cfg(feature = "prover")]
 #[derive(ZeroizeOnDrop)]
 pub struct VecPoly3<F: Field>(pub Vec<F>, pub Vec<F>, pub Vec<F>, pub Vec<F>);
 
@@ -27,10 +29,13 @@ pub const T_LABELS: [&[u8]; 25] = [
 ];
 
 /// The general case for Vector CP.
+#[cfg(feature = "prover")]
 pub struct VecPoly<F: Field>(Vec<Vec<F>>);
 
+#[cfg(feature = "prover")]
 pub struct Poly<F: Field>(Vec<F>);
 
+#[cfg(feature = "prover")]
 impl<F: Field> Poly<F> {
     pub fn zero(deg: usize) -> Self {
         Poly(vec![F::zero(); deg + 1])
@@ -54,12 +59,14 @@ impl<F: Field> Poly<F> {
     }
 }
 
+#[cfg(feature = "prover")]
 impl<F: Field> From<Vec<F>> for Poly<F> {
     fn from(v: Vec<F>) -> Self {
         Self(v)
     }
 }
 
+#[cfg(feature = "prover")]
 impl<F: Field> VecPoly<F> {
     pub fn coeff_mut(&mut self, deg: usize) -> &mut [F] {
         &mut self.0[deg]
@@ -121,6 +128,7 @@ pub struct Poly2<F: Field>(pub F, pub F, pub F);
 
 /// Represents a degree-6 scalar polynomial, without the zeroth degree
 /// \\(a \cdot x + b \cdot x^2 + c \cdot x^3 + d \cdot x^4 + e \cdot x^5 + f \cdot x^6\\)
+#[cfg(feature = "prover")]
 #[derive(ZeroizeOnDrop)]
 pub struct Poly6<F: Field> {
     pub t1: F,
@@ -153,6 +161,36 @@ impl<F: Field> Iterator for ScalarExp<F> {
     }
 }
 
+/// Number of terms of a multiscalar multiplication computed at a time by [`msm_chunked`].
+///
+/// The working memory of a multiscalar multiplication grows with its length,
+/// chunking keeps it bounded for commitments to very long vectors.
+pub const MSM_CHUNK_SIZE: usize = 1 << 12;
+
+/// Computes \( \langle \mathbf{s}, \mathbf{B} \rangle \) for the `scalars` and `bases`,
+/// [`MSM_CHUNK_SIZE`] terms at a time.
+/// Zero scalars, common in sparse and padded vectors, are skipped.
+pub fn msm_chunked<C: AffineRepr>(bases: &[C], scalars: &[C::ScalarField]) -> C::Group {
+    assert_eq!(bases.len(), scalars.len());
+    bases
+        .chunks(MSM_CHUNK_SIZE)
+        .zip(scalars.chunks(MSM_CHUNK_SIZE))
+        .map(|(bases, scalars)| {
+            if scalars.iter().any(Zero::is_zero) {
+                let (bases, scalars): (Vec<C>, Vec<C::ScalarField>) = bases
+                    .iter()
+                    .zip(scalars.iter())
+                    .filter(|(_, scalar)| !scalar.is_zero())
+                    .map(|(base, scalar)| (*base, *scalar))
+                    .unzip();
+                C::Group::msm_unchecked(&bases, &scalars)
+            } else {
+                C::Group::msm_unchecked(bases, scalars)
+            }
+        })
+        .sum()
+}
+
 /// Return an iterator of the powers of `x`.
 pub fn exp_iter<F: Field>(x: F) -> ScalarExp<F> {
     let next_exp_x = F::one();
@@ -202,6 +240,7 @@ impl<F: Field> VecPoly1<F> {
     }
 }
 
+#[cfg(feature = "prover")]
 impl<F: Field> VecPoly3<F> {
     pub fn zero(n: usize) -> Self {
         VecPoly3(
@@ -252,6 +291,7 @@ impl<F: Field> Poly2<F> {
     }
 }
 
+#[cfg(feature = "prover")]
 impl<F: Field> Poly6<F> {
     pub fn eval(&self, x: F) -> F {
         x * (self.t1 + x * (self.t2 + x * (self.t3 + x * (self.t4 + x * (self.t5 + x * self.t6)))))
@@ -310,16 +350,13 @@ pub fn read32(data: &[u8]) -> [u8; 32] {
 }
 
 pub fn affine_from_bytes_tai<C: AffineRepr>(bytes: &[u8]) -> C {
-    extern crate crypto;
-    use crypto::digest::Digest;
-    use crypto::sha3::Sha3;
+    use sha3::{Digest, Sha3_256};
 
     for i in 0..=u8::max_value() {
-        let mut sha = Sha3::sha3_256();
-        sha.input(bytes);
-        sha.input(&[i]);
-        let mut buf = [0u8; 32];
-        sha.result(&mut buf);
+        let mut sha = Sha3_256::new();
+        sha.update(bytes);
+        sha.update([i]);
+        let buf = sha.finalize();
         let res = C::from_random_bytes(&buf);
         if let Some(point) = res {
             return point;
@@ -336,6 +373,32 @@ pub fn field_as_bytes<F: Field>(field: &F) -> Vec<u8> {
     bytes
 }
 
+/// Deserializes a vector like `Vec::<T>::deserialize_with_mode`, but fails with `SerializationError::InvalidData`
+/// before reading any element if the length prefix exceeds `max_len`.
+pub(crate) fn deserialize_bounded_vec<T: CanonicalDeserialize, R: Read>(
+    mut reader: R,
+    compress: Compress,
+    validate: Validate,
+    max_len: usize,
+) -> Result<Vec<T>, SerializationError> {
+    let len = u64::deserialize_with_mode(&mut reader, compress, validate)?;
+    if len > max_len as u64 {
+        return Err(SerializationError::InvalidData);
+    }
+    let mut values = Vec::with_capacity(len as usize);
+    for _ in 0..len {
+        values.push(T::deserialize_with_mode(
+            &mut reader,
+            compress,
+            Validate::No,
+        )?);
+    }
+    if let Validate::Yes = validate {
+        T::batch_check(values.iter())?;
+    }
+    Ok(values)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -396,4 +459,34 @@ mod tests {
         assert_eq!(sum_of_powers_slow(&x, 5), Scalar::from(11111u64));
         assert_eq!(sum_of_powers_slow(&x, 6), Scalar::from(111111u64));
     }
+
+    #[test]
+    fn msm_chunked_matches_msm() {
+        use ark_ec::CurveGroup;
+        use ark_std::UniformRand;
+
+        let mut rng = rand::thread_rng();
+        let n = 2 * MSM_CHUNK_SIZE + 5;
+        let bases = Projective::normalize_batch(
+            &(0..n)
+                .map(|_| Projective::rand(&mut rng))
+                .collect::<Vec<_>>(),
+        );
+        // a chunk without zeros, then chunks with every third scalar zero
+        let scalars: Vec<Scalar> = (0..n)
+            .map(|i| {
+                if i >= MSM_CHUNK_SIZE && i % 3 == 0 {
+                    Scalar::zero()
+                } else {
+                    Scalar::rand(&mut rng)
+                }
+            })
+            .collect();
+
+        assert_eq!(
+            msm_chunked(&bases, &scalars),
+            Projective::msm_unchecked(&bases, &scalars)
+        );
+        assert_eq!(msm_chunked::<Affine>(&[], &[]), Projective::zero());
+    }
 }
@@ -0,0 +1,126 @@
+#![cfg(feature = "debug")]
+
+extern crate bulletproofs;
+extern crate merlin;
+
+use ark_pallas::{Affine, Fr};
+
+use bulletproofs::r1cs::*;
+use bulletproofs::PedersenGens;
+use merlin::Transcript;
+
+fn fr(n: u64) -> Fr {
+    Fr::from(n)
+}
+
+#[test]
+fn multiplier_assignments_and_namespaces() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"debug test"));
+    let (_, x) = prover.commit(fr(3), fr(1));
+    let (_, y) = prover.commit(fr(4), fr(1));
+
+    prover.push_namespace("outer");
+    let (_, _, xy) = prover.multiply(x.into(), y.into());
+    prover.push_namespace("inner");
+    let a = prover.allocate(Some(fr(5))).unwrap();
+    let b = prover.allocate(Some(fr(6))).unwrap();
+    prover.pop_namespace();
+    let (_, _, c) = prover.allocate_multiplier(Some((fr(2), fr(7)))).unwrap();
+    prover.pop_namespace();
+    let (_, _, d) = prover.multiply(xy + c, a - b);
+
+    let assignments: Vec<_> = prover.multiplier_assignments().collect();
+    assert_eq!(
+        assignments,
+        vec![
+            (0, fr(3), fr(4), fr(12)),
+            (1, fr(5), fr(6), fr(30)),
+            (2, fr(2), fr(7), fr(14)),
+            (3, fr(26), -fr(1), -fr(26)),
+        ]
+    );
+    let namespaces: Vec<_> = (0..4)
+        .map(|i| prover.multiplier_namespace(i).unwrap())
+        .collect();
+    assert_eq!(namespaces, vec!["outer", "outer/inner", "outer", ""]);
+    assert_eq!(prover.multiplier_namespace(4), None);
+    assert_eq!(prover.eval(&d.into()), -fr(26));
+}
+
+#[test]
+#[should_panic(expected = "pop_namespace without a matching push_namespace")]
+fn unbalanced_pop_namespace() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"debug test"));
+    prover.push_namespace("gadget");
+    prover.pop_namespace();
+    prover.pop_namespace();
+}
+
+#[test]
+fn operation_logs_of_diverging_statements() {
+    use bulletproofs::BulletproofGens;
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(16, 1);
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"debug test"));
+    let (x_commitment, x) = prover.commit(fr(3), fr(1));
+    let (y_commitment, y) = prover.commit(fr(4), fr(1));
+    let (_, _, xy) = prover.multiply(x.into(), y.into());
+    prover.constrain(xy - constant(12u64));
+    let prover_log = prover.operation_log().clone();
+    assert_eq!(
+        prover_log.operations(),
+        &[
+            Operation::Commit,
+            Operation::Commit,
+            Operation::Multiply,
+            Operation::Constrain(2),
+            Operation::Constrain(2),
+            Operation::Constrain(2),
+        ]
+    );
+    let proof = prover.prove(&bp_gens).unwrap();
+
+    // The same statement has the same digest, and verifies.
+    let mut verifier = Verifier::new(Transcript::new(b"debug test"));
+    let x = verifier.commit(x_commitment);
+    let y = verifier.commit(y_commitment);
+    let (_, _, xy) = verifier.multiply(x.into(), y.into());
+    verifier.constrain(xy - constant(12u64));
+    assert_eq!(verifier.operation_digest(), prover_log.digest());
+    assert_eq!(
+        diagnose_mismatch(&prover_log, verifier.operation_log()),
+        None
+    );
+    assert!(verifier
+        .verify_diagnosed(&proof, &pc_gens, &bp_gens, &prover_log)
+        .is_ok());
+
+    // A verifier allocating the product instead of multiplying diverges at the third operation.
+    let mut verifier = Verifier::new(Transcript::new(b"debug test"));
+    let x = verifier.commit(x_commitment);
+    verifier.commit(y_commitment);
+    let (_, _, xy) = verifier.allocate_multiplier(None).unwrap();
+    verifier.constrain(xy - x);
+    let mismatch = diagnose_mismatch(&prover_log, verifier.operation_log()).unwrap();
+    assert_eq!(
+        mismatch,
+        Mismatch {
+            index: 2,
+            prover: Some(Operation::Multiply),
+            verifier: Some(Operation::AllocateMultiplier),
+        }
+    );
+    assert_eq!(
+        mismatch.to_string(),
+        "operation 2: the prover did multiply, the verifier allocate_multiplier"
+    );
+    match verifier.verify_diagnosed(&proof, &pc_gens, &bp_gens, &prover_log) {
+        Err(R1CSError::OperationMismatch { description }) => {
+            assert!(description.starts_with(&mismatch.to_string()))
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}
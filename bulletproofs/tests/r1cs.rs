@@ -11,7 +11,7 @@ use ark_std::UniformRand;
 use ark_pallas::Affine;
 
 use bulletproofs::r1cs::*;
-use bulletproofs::{BulletproofGens, PedersenGens};
+use bulletproofs::{BulletproofGens, PedersenGens, PrecomputedGens};
 use merlin::Transcript;
 use rand::seq::SliceRandom;
 
@@ -247,6 +247,38 @@ fn kshuffle_batch_helper(k: usize, n: usize) {
     assert!(batch_verify(vsps, &pc_gens, &bp_gens).is_ok());
 }
 
+#[test]
+fn shuffle_gadget_batch_of_different_sizes() {
+    type Scalar = <Affine as AffineRepr>::ScalarField;
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(16, 1);
+
+    let vsps: Vec<_> = [1, 8, 3]
+        .iter()
+        .map(|&k| {
+            let input: Vec<Scalar> = (0..k).map(|i| Scalar::from(i as u64)).collect();
+            let mut output = input.clone();
+            output.reverse();
+            let mut prover_transcript = Transcript::new(b"ShuffleProofTest");
+            let (proof, input_commitments, output_commitments) =
+                ShuffleProof::prove(&pc_gens, &bp_gens, &mut prover_transcript, &input, &output)
+                    .unwrap();
+            let mut verifier_transcript = Transcript::new(b"ShuffleProofTest");
+            proof
+                .verification_scalars_and_points(
+                    &mut verifier_transcript,
+                    &input_commitments,
+                    &output_commitments,
+                )
+                .unwrap()
+        })
+        .collect();
+
+    assert!(batch_verify(vsps, &pc_gens, &bp_gens).is_ok());
+    assert!(batch_verify(Vec::new(), &pc_gens, &bp_gens).is_ok());
+}
+
 #[test]
 fn shuffle_gadget_test_1() {
     kshuffle_helper(1);
@@ -497,3 +529,408 @@ fn range_proof_helper<C: AffineRepr>(v_val: u64, n: usize) -> Result<(), R1CSErr
     // Verifier verifies proof
     verifier.verify(&proof, &pc_gens, &bp_gens)
 }
+
+#[test]
+fn range_proof_precomputed_gens() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(128, 1);
+    let n = 32;
+
+    let prove = |v_val: u64| {
+        let mut prover = Prover::new(&pc_gens, Transcript::new(b"RangeProofTest"));
+        let (com, var) = prover.commit(v_val.into(), UniformRand::rand(&mut rand::thread_rng()));
+        range_proof(&mut prover, var.into(), Some(v_val), n).unwrap();
+        (prover.prove(&bp_gens).unwrap(), com)
+    };
+    let verify = |proof: &R1CSProof<Affine>, commitment, gens: &PrecomputedGens<Affine>| {
+        let mut verifier = Verifier::new(Transcript::new(b"RangeProofTest"));
+        let var = verifier.commit(commitment);
+        range_proof(&mut verifier, var.into(), None, n).unwrap();
+        verifier.verify_precomputed(proof, gens)
+    };
+
+    // A range proof over 32 bits has 32 multipliers.
+    let gens = PrecomputedGens::new(&pc_gens, &bp_gens, 32).unwrap();
+    let (proof, commitment) = prove(7);
+    assert!(verify(&proof, commitment, &gens).is_ok());
+    let (_, other_commitment) = prove(8);
+    assert!(matches!(
+        verify(&proof, other_commitment, &gens),
+        Err(R1CSError::VerificationError)
+    ));
+
+    // The generators of circuits of another size reject the proof before verifying it.
+    let gens = PrecomputedGens::new(&pc_gens, &bp_gens, 64).unwrap();
+    assert!(matches!(
+        verify(&proof, commitment, &gens),
+        Err(R1CSError::FormatError)
+    ));
+}
+
+#[test]
+fn range_proof_reused_verifier() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(128, 1);
+    let n = 32;
+
+    // The constraints are built once, against a placeholder commitment.
+    let mut verifier = Verifier::new(Transcript::new(b"RangeProofTest"));
+    let var = verifier.commit(Affine::generator());
+    range_proof(&mut verifier, var.into(), None, n).unwrap();
+    let statement = |commitment| {
+        let mut statement = Verifier::new(Transcript::new(b"RangeProofTest"));
+        statement.commit(commitment);
+        statement
+    };
+
+    let (proof, commitment) = range_proof_prove(&pc_gens, &bp_gens, 7, n);
+    let (other_proof, other_commitment) = range_proof_prove(&pc_gens, &bp_gens, 1 << 20, n);
+    assert!(verifier
+        .verify_ref(statement(commitment), &proof, &pc_gens, &bp_gens)
+        .is_ok());
+    assert!(verifier
+        .verify_ref(
+            statement(other_commitment),
+            &other_proof,
+            &pc_gens,
+            &bp_gens
+        )
+        .is_ok());
+    assert!(matches!(
+        verifier.verify_ref(statement(other_commitment), &proof, &pc_gens, &bp_gens),
+        Err(R1CSError::VerificationError)
+    ));
+    // Reusing the verifier does not change its verdicts, nor does consuming it afterwards.
+    assert!(verifier
+        .verify_ref(statement(commitment), &proof, &pc_gens, &bp_gens)
+        .is_ok());
+    assert!(range_proof_verify(&pc_gens, &bp_gens, &proof, commitment, n).is_ok());
+
+    // A statement of another shape.
+    let mut two_commitments = statement(commitment);
+    two_commitments.commit(commitment);
+    assert!(matches!(
+        verifier.verify_ref(two_commitments, &proof, &pc_gens, &bp_gens),
+        Err(R1CSError::GadgetError { .. })
+    ));
+}
+
+/// Constrains the committed `var` to equal the public `amount`, baked into the constraints as a constant.
+fn equals_amount<CS: ConstraintSystem<ark_pallas::Fr>>(
+    cs: &mut CS,
+    var: Variable<ark_pallas::Fr>,
+    amount: u64,
+) {
+    let amount = cs.public_constant(amount.into());
+    let (_, _, product) = cs.multiply(var.into(), ark_pallas::Fr::from(1u64).into());
+    cs.constrain(product - amount);
+}
+
+#[test]
+fn reused_verifier_public_constants() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(8, 1);
+    let prove = |amount: u64| {
+        let mut prover = Prover::new(&pc_gens, Transcript::new(b"PublicConstantTest"));
+        let (commitment, var) =
+            prover.commit(amount.into(), ark_pallas::Fr::rand(&mut rand::thread_rng()));
+        equals_amount(&mut prover, var, amount);
+        (prover.prove(&bp_gens).unwrap(), commitment)
+    };
+    let (proof, commitment) = prove(5);
+    let (other_proof, other_commitment) = prove(6);
+
+    // The constraints are built once, with the amount of the first statement.
+    let mut verifier = Verifier::new(Transcript::new(b"PublicConstantTest"));
+    let var = verifier.commit(commitment);
+    equals_amount(&mut verifier, var, 5);
+    let statement = |commitment, amount: u64| {
+        let mut statement = Verifier::new(Transcript::new(b"PublicConstantTest"));
+        statement.commit(commitment);
+        statement.public_constant(amount.into());
+        statement
+    };
+
+    assert!(verifier
+        .verify_ref(statement(commitment, 5), &proof, &pc_gens, &bp_gens)
+        .is_ok());
+    // The amount of each statement replaces the one the constraints were built with.
+    assert!(verifier
+        .verify_ref(
+            statement(other_commitment, 6),
+            &other_proof,
+            &pc_gens,
+            &bp_gens
+        )
+        .is_ok());
+    assert!(matches!(
+        verifier.verify_ref(statement(commitment, 6), &proof, &pc_gens, &bp_gens),
+        Err(R1CSError::VerificationError)
+    ));
+    assert!(matches!(
+        verifier.verify_ref(
+            statement(other_commitment, 5),
+            &other_proof,
+            &pc_gens,
+            &bp_gens
+        ),
+        Err(R1CSError::VerificationError)
+    ));
+    // A statement that does not supply the constants.
+    let mut statement = Verifier::new(Transcript::new(b"PublicConstantTest"));
+    statement.commit(commitment);
+    assert!(matches!(
+        verifier.verify_ref(statement, &proof, &pc_gens, &bp_gens),
+        Err(R1CSError::GadgetError { .. })
+    ));
+}
+
+/// Reads the length prefix of the serialized vector starting at `offset`.
+fn read_len(bytes: &[u8], offset: usize) -> usize {
+    let mut len = [0u8; 8];
+    len.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(len) as usize
+}
+
+fn range_proof_verify(
+    pc_gens: &PedersenGens<Affine>,
+    bp_gens: &BulletproofGens<Affine>,
+    proof: &R1CSProof<Affine>,
+    commitment: Affine,
+    n: usize,
+) -> Result<(), R1CSError> {
+    let mut verifier_transcript = Transcript::new(b"RangeProofTest");
+    let mut verifier = Verifier::new(&mut verifier_transcript);
+    let var = verifier.commit(commitment);
+    range_proof(&mut verifier, var.into(), None, n)?;
+    verifier.verify(proof, pc_gens, bp_gens)
+}
+
+fn range_proof_prove(
+    pc_gens: &PedersenGens<Affine>,
+    bp_gens: &BulletproofGens<Affine>,
+    v: u64,
+    n: usize,
+) -> (R1CSProof<Affine>, Affine) {
+    let mut prover = Prover::new(pc_gens, Transcript::new(b"RangeProofTest"));
+    let (com, var) = prover.commit(v.into(), ark_pallas::Fr::rand(&mut rand::thread_rng()));
+    range_proof(&mut prover, var.into(), Some(v), n).unwrap();
+    (prover.prove(bp_gens).unwrap(), com)
+}
+
+// Even the smallest circuits have no smaller encoding sending the blinded wires l(x) and r(x) instead of
+// the inner product proof: as vectors with their length prefixes, they tie at 1 multiplier, save the two
+// bytes of the flags of the points of the single round at 2, and are larger from 4.
+#[test]
+fn inner_product_proof_of_small_circuits() {
+    use ark_serialize::{CanonicalSerialize, Compress};
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(128, 1);
+    let point_size = Affine::generator().compressed_size();
+    let scalar_size = ark_pallas::Fr::from(0u64).compressed_size();
+    for n in [1, 2, 4, 8] {
+        let (proof, commitment) = range_proof_prove(&pc_gens, &bp_gens, 1, n);
+        assert!(range_proof_verify(&pc_gens, &bp_gens, &proof, commitment, n).is_ok());
+        assert_eq!(proof.padded_multipliers(), Some(n));
+        let wires_size = 2 * (8 + n * scalar_size);
+        let ipp_size = proof.inner_product_proof_size(Compress::Yes);
+        if n <= 2 {
+            // No rounds, or one round of two points, a byte larger than scalars each.
+            assert_eq!(
+                ipp_size,
+                wires_size + (n - 1) * 2 * (point_size - scalar_size)
+            );
+        } else {
+            assert!(ipp_size < wires_size);
+        }
+    }
+}
+
+// The phases of a proof, driven one at a time, make the same proof as `prove_with_rng` with the same randomness.
+#[test]
+fn phased_proofs_match_one_shot_proofs() {
+    use ark_serialize::CanonicalSerialize;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(128, 1);
+    let n = 16;
+    let v = 1234u64;
+    let prover = |seed: u64| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut prover = Prover::new(&pc_gens, Transcript::new(b"RangeProofTest"));
+        let (com, var) = prover.commit(v.into(), ark_pallas::Fr::rand(&mut rng));
+        range_proof(&mut prover, var.into(), Some(v), n).unwrap();
+        (prover, com, rng)
+    };
+
+    let (one_shot, commitment, mut rng) = prover(715);
+    let one_shot = one_shot.prove_with_rng(&bp_gens, &mut rng).unwrap();
+
+    let (phased, _, mut rng) = prover(715);
+    let mut phase2 = phased
+        .commit_wires(&bp_gens, &mut rng)
+        .unwrap()
+        .process_challenge(&mut rng)
+        .unwrap();
+    assert_eq!(phase2.rounds_remaining(), n.trailing_zeros() as usize);
+    let mut rounds = 0;
+    while phase2.rounds_remaining() > 0 {
+        phase2.finish_ipp_round();
+        rounds += 1;
+    }
+    assert_eq!(rounds, n.trailing_zeros());
+    let phased = phase2.finalize();
+
+    let (mut one_shot_bytes, mut phased_bytes) = (Vec::new(), Vec::new());
+    one_shot.serialize_compressed(&mut one_shot_bytes).unwrap();
+    phased.serialize_compressed(&mut phased_bytes).unwrap();
+    assert_eq!(one_shot_bytes, phased_bytes);
+    assert!(range_proof_verify(&pc_gens, &bp_gens, &phased, commitment, n).is_ok());
+}
+
+// Malformed serializations, rejected when parsing. Corruptions of the parsed components are in
+// `range_proof_corrupted_proofs`.
+#[test]
+fn range_proof_malformed_proofs() {
+    use ark_serialize::CanonicalSerialize;
+
+    let n = 8;
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(128, 1);
+    let (proof, commitment) = range_proof_prove(&pc_gens, &bp_gens, 42, n);
+    assert!(range_proof_verify(&pc_gens, &bp_gens, &proof, commitment, n).is_ok());
+    let bytes = proof.to_bytes();
+    let point_size = Affine::generator().compressed_size();
+    let scalar_size = 32;
+
+    // A single phase proof: the version, A_I1, A_O1, S1, the phase flag, T, 3 scalars, then L_vec and R_vec.
+    assert_eq!(bytes[0], R1CS_PROOF_VERSION);
+    let flag_offset = 1 + 3 * point_size;
+    let t_offset = flag_offset + 1;
+    let t_len = read_len(&bytes, t_offset);
+    let l_offset = t_offset + 8 + t_len * point_size + 3 * scalar_size;
+    let l_len = read_len(&bytes, l_offset);
+    let r_offset = l_offset + 8 + l_len * point_size;
+
+    let mut unknown_flag = bytes.clone();
+    unknown_flag[flag_offset] = 2;
+    assert_eq!(
+        R1CSProof::<Affine>::from_bytes(&unknown_flag).unwrap_err(),
+        R1CSError::FormatError
+    );
+
+    // A proof of a later version parses, and is rejected before it is verified.
+    let mut later_version = bytes.clone();
+    later_version[0] = R1CS_PROOF_VERSION + 1;
+    let later_version = R1CSProof::<Affine>::from_bytes(&later_version).unwrap();
+    assert_eq!(
+        range_proof_verify(&pc_gens, &bp_gens, &later_version, commitment, n).unwrap_err(),
+        R1CSError::UnsupportedVersion {
+            version: R1CS_PROOF_VERSION + 1
+        }
+    );
+
+    // Length prefixes longer than any proof has are rejected before reading the vectors.
+    for offset in [t_offset, l_offset, r_offset] {
+        let mut oversized = bytes.clone();
+        oversized[offset..offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(
+            R1CSProof::<Affine>::from_bytes(&oversized).unwrap_err(),
+            R1CSError::FormatError
+        );
+    }
+}
+
+#[cfg(feature = "simulate")]
+#[test]
+fn range_proof_corrupted_proofs() {
+    use bulletproofs::r1cs::simulate::Corruption;
+
+    let n = 8;
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(128, 1);
+    let (proof, commitment) = range_proof_prove(&pc_gens, &bp_gens, 42, n);
+    let (_, other_commitment) = range_proof_prove(&pc_gens, &bp_gens, 42, n);
+    assert!(range_proof_verify(&pc_gens, &bp_gens, &proof, commitment, n).is_ok());
+    assert!(range_proof_verify(&pc_gens, &bp_gens, &proof, other_commitment, n).is_err());
+
+    // Every corruption serializes to a proof that parses back, and is rejected, not panicked on.
+    let corruptions = Corruption::all(&proof);
+    assert!(corruptions.contains(&Corruption::TruncateT));
+    assert!(corruptions.contains(&Corruption::TruncateInnerProduct(1)));
+    for corruption in corruptions {
+        let corrupted = R1CSProof::<Affine>::from_bytes(&corruption.apply(&proof).to_bytes())
+            .unwrap_or_else(|e| panic!("{:?} does not parse: {:?}", corruption, e));
+        assert!(
+            range_proof_verify(&pc_gens, &bp_gens, &corrupted, commitment, n).is_err(),
+            "{:?} verifies",
+            corruption
+        );
+    }
+}
+
+#[cfg(feature = "simulate")]
+#[test]
+fn simulated_proofs_fail_verification() {
+    use bulletproofs::r1cs::simulate::{simulate, ProofShape};
+
+    let mut rng = rand::thread_rng();
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(128, 1);
+
+    // A range proof, with a single phase.
+    let n = 8;
+    let shape = {
+        let mut verifier = Verifier::<_, Affine>::new(Transcript::new(b"RangeProofTest"));
+        let var = verifier.commit(Affine::generator());
+        range_proof(&mut verifier, var.into(), None, n).unwrap();
+        verifier.proof_shape()
+    };
+    assert_eq!(
+        shape,
+        ProofShape {
+            multipliers: n,
+            commitments: 1,
+            vector_commitments: 0,
+            randomized: false,
+        }
+    );
+    let simulated = simulate::<Affine, _>(&shape, &mut rng);
+    let proof = R1CSProof::<Affine>::from_bytes(&simulated.proof.to_bytes()).unwrap();
+    assert_eq!(
+        range_proof_verify(&pc_gens, &bp_gens, &proof, simulated.commitments[0], n),
+        Err(R1CSError::VerificationError)
+    );
+
+    // A shuffle, with randomized constraints: its 2(k-1) multipliers are only allocated when verifying.
+    let k = 4;
+    let shape = ProofShape {
+        multipliers: 2 * (k - 1),
+        commitments: 2 * k,
+        vector_commitments: 0,
+        randomized: true,
+    };
+    let simulated = simulate::<Affine, _>(&shape, &mut rng);
+    let (input, output) = simulated.commitments.split_at(k);
+    let proof = ShuffleProof(simulated.proof);
+    assert!(proof
+        .verification_scalars_and_points(
+            &mut Transcript::new(b"ShuffleTest"),
+            &input.to_vec(),
+            &output.to_vec()
+        )
+        .is_ok());
+    assert_eq!(
+        proof.verify(
+            &pc_gens,
+            &bp_gens,
+            &mut Transcript::new(b"ShuffleTest"),
+            &input.to_vec(),
+            &output.to_vec()
+        ),
+        Err(R1CSError::VerificationError)
+    );
+}
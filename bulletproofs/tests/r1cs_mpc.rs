@@ -0,0 +1,252 @@
+#![allow(non_snake_case)]
+
+extern crate bulletproofs;
+extern crate merlin;
+extern crate rand;
+
+use ark_ff::Field;
+use ark_pallas::{Affine, Fr};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::UniformRand;
+
+use bulletproofs::r1cs::mpc::coordinator::Coordinator;
+use bulletproofs::r1cs::mpc::messages::*;
+use bulletproofs::r1cs::mpc::party::{Party, PartyAwaitingPosition};
+use bulletproofs::r1cs::*;
+use bulletproofs::{BulletproofGens, PedersenGens};
+use merlin::Transcript;
+
+// A coinjoin: every party spends an input and creates an output,
+// the outputs must be in range and the amounts must balance across the parties.
+
+const BITS: usize = 8;
+
+/// Enforces that the quantity of v is in the range [0, 2^n).
+fn range_proof<F: Field, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    mut v: LinearCombination<F>,
+    v_assignment: Option<u64>,
+    n: usize,
+) -> Result<(), R1CSError> {
+    let mut exp_2 = F::one();
+    for i in 0..n {
+        let (a, b, o) = cs.allocate_multiplier(v_assignment.map(|q| {
+            let bit: u64 = (q >> i) & 1;
+            ((1 - bit).into(), bit.into())
+        }))?;
+        cs.constrain(o.into());
+        cs.constrain(a + (b - constant(1u64)));
+        v = v - b * exp_2;
+        exp_2 = exp_2 + exp_2;
+    }
+    cs.constrain(v);
+    Ok(())
+}
+
+/// Every party's (input, output) commitments, then the range proofs in party order, then the balance.
+fn joint_verifier(commitments: &[Vec<Affine>]) -> Verifier<Transcript, Affine> {
+    let mut verifier = Verifier::new(Transcript::new(b"coinjoin"));
+    let vars: Vec<Vec<_>> = commitments
+        .iter()
+        .map(|V| V.iter().map(|V| verifier.commit(*V)).collect())
+        .collect();
+    for party in vars.iter() {
+        range_proof(&mut verifier, party[1].into(), None, BITS).unwrap();
+    }
+    let balance = vars.iter().fold(LinearCombination::default(), |lc, party| {
+        lc + party[0] - party[1]
+    });
+    verifier.constrain(balance);
+    verifier
+}
+
+fn party<'g>(
+    pc_gens: &'g PedersenGens<Affine>,
+    bp_gens: &'g BulletproofGens<Affine>,
+    input: u64,
+    output: u64,
+) -> PartyAwaitingPosition<'g, Affine> {
+    let mut rng = rand::thread_rng();
+    let mut prover = Prover::new(pc_gens, Transcript::new(b"party"));
+    let _ = prover.commit(input.into(), Fr::rand(&mut rng));
+    let (_, output_var) = prover.commit(output.into(), Fr::rand(&mut rng));
+    range_proof(&mut prover, output_var.into(), Some(output), BITS).unwrap();
+    Party::new(prover, bp_gens).unwrap()
+}
+
+// Messages cross the network serialized.
+fn send<M: CanonicalSerialize + CanonicalDeserialize>(message: &M) -> M {
+    let mut bytes = Vec::new();
+    message.serialize_compressed(&mut bytes).unwrap();
+    M::deserialize_compressed(bytes.as_slice()).unwrap()
+}
+
+/// Runs the protocol, optionally flipping a bit of a party's proof share.
+fn aggregate(
+    amounts: &[(u64, u64)],
+    tampered_share: Option<usize>,
+) -> (Result<R1CSProof<Affine>, MPCError>, Vec<Vec<Affine>>) {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(64, 1);
+
+    let (parties, wire_commitments): (Vec<_>, Vec<_>) = amounts
+        .iter()
+        .enumerate()
+        .map(|(j, (input, output))| {
+            party(&pc_gens, &bp_gens, *input, *output)
+                .assign_position(j * BITS)
+                .unwrap()
+        })
+        .unzip();
+    let commitments: Vec<_> = wire_commitments
+        .iter()
+        .map(|c| c.commitments().to_vec())
+        .collect();
+
+    let coordinator = Coordinator::new(joint_verifier(&commitments), &pc_gens, &bp_gens).unwrap();
+    let (coordinator, wire_challenges) = coordinator
+        .receive_wire_commitments(wire_commitments.iter().map(send).collect())
+        .unwrap();
+
+    let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+        .into_iter()
+        .zip(wire_challenges.iter())
+        .map(|(party, wire_challenge)| party.apply_challenge(&send(wire_challenge)).unwrap())
+        .unzip();
+    let (coordinator, poly_challenge) = coordinator
+        .receive_poly_commitments(poly_commitments.iter().map(send).collect())
+        .unwrap();
+
+    let proof_shares: Vec<_> = parties
+        .into_iter()
+        .enumerate()
+        .map(|(j, party)| {
+            let share = party.apply_challenge(&send(&poly_challenge)).unwrap();
+            let mut bytes = Vec::new();
+            share.serialize_compressed(&mut bytes).unwrap();
+            if tampered_share == Some(j) {
+                // the first entry of l(x), after the two blindings and the length
+                bytes[72] ^= 1;
+            }
+            ProofShare::deserialize_compressed(bytes.as_slice()).unwrap()
+        })
+        .collect();
+    (coordinator.receive_shares(proof_shares), commitments)
+}
+
+#[test]
+fn two_party_proof_verifies() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(64, 1);
+
+    let (proof, commitments) = aggregate(&[(10, 3), (5, 12)], None);
+    let proof = R1CSProof::<Affine>::from_bytes(&proof.unwrap().to_bytes()).unwrap();
+    assert!(joint_verifier(&commitments)
+        .verify(&proof, &pc_gens, &bp_gens)
+        .is_ok());
+
+    // The proof is bound to the commitments of both parties.
+    let mut swapped = commitments.clone();
+    swapped.swap(0, 1);
+    assert!(joint_verifier(&swapped)
+        .verify(&proof, &pc_gens, &bp_gens)
+        .is_err());
+}
+
+#[test]
+fn unsatisfied_constraints_are_rejected() {
+    // unbalanced
+    assert_eq!(
+        aggregate(&[(10, 3), (5, 11)], None).0.unwrap_err(),
+        MPCError::UnsatisfiedConstraints
+    );
+    // balanced, but an output is out of range
+    assert_eq!(
+        aggregate(&[(300, 3), (5, 302)], None).0.unwrap_err(),
+        MPCError::UnsatisfiedConstraints
+    );
+}
+
+#[test]
+fn malformed_share_is_blamed() {
+    assert_eq!(
+        aggregate(&[(10, 3), (5, 12)], Some(1)).0.unwrap_err(),
+        MPCError::MalformedProofShares {
+            bad_shares: vec![1]
+        }
+    );
+}
+
+#[test]
+fn wire_layout_is_checked() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(64, 1);
+
+    let (_, first) = party(&pc_gens, &bp_gens, 10, 3).assign_position(0).unwrap();
+    let (_, overlapping) = party(&pc_gens, &bp_gens, 5, 12)
+        .assign_position(BITS - 1)
+        .unwrap();
+    let commitments = vec![
+        first.commitments().to_vec(),
+        overlapping.commitments().to_vec(),
+    ];
+    let coordinator = Coordinator::new(joint_verifier(&commitments), &pc_gens, &bp_gens).unwrap();
+    assert_eq!(
+        coordinator
+            .receive_wire_commitments(vec![first.clone(), overlapping])
+            .err(),
+        Some(MPCError::WireLayoutMismatch { party: 1 })
+    );
+
+    // A missing party leaves wires of the constraint system uncommitted.
+    let coordinator = Coordinator::new(joint_verifier(&commitments), &pc_gens, &bp_gens).unwrap();
+    assert_eq!(
+        coordinator.receive_wire_commitments(vec![first]).err(),
+        Some(MPCError::WireLayoutMismatch { party: 1 })
+    );
+}
+
+#[test]
+fn party_rejects_zero_challenge() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(64, 1);
+
+    let (party, wire_commitment) = party(&pc_gens, &bp_gens, 10, 10)
+        .assign_position(0)
+        .unwrap();
+    let commitments = vec![wire_commitment.commitments().to_vec()];
+    let coordinator = Coordinator::new(joint_verifier(&commitments), &pc_gens, &bp_gens).unwrap();
+    let (_, wire_challenges) = coordinator
+        .receive_wire_commitments(vec![wire_commitment])
+        .unwrap();
+
+    // y is the first field of the challenge
+    let mut bytes = Vec::new();
+    wire_challenges[0].serialize_compressed(&mut bytes).unwrap();
+    bytes[..32].fill(0);
+    let wire_challenge = WireChallenge::deserialize_compressed(bytes.as_slice()).unwrap();
+    assert_eq!(
+        party.apply_challenge(&wire_challenge).err(),
+        Some(MPCError::MaliciousDealer)
+    );
+}
+
+#[test]
+fn unsupported_constraint_systems_are_rejected() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(64, 1);
+
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"party"));
+    prover.allocate(Some(Fr::from(1u64))).unwrap();
+    assert_eq!(
+        Party::new(prover, &bp_gens).err(),
+        Some(MPCError::UnsupportedConstraints)
+    );
+
+    let mut verifier = Verifier::new(Transcript::new(b"coinjoin"));
+    verifier.commit_vec(2, Affine::default());
+    assert_eq!(
+        Coordinator::new(verifier, &pc_gens, &bp_gens).err(),
+        Some(MPCError::UnsupportedConstraints)
+    );
+}
@@ -0,0 +1,127 @@
+#![allow(non_snake_case)]
+
+extern crate bulletproofs;
+extern crate merlin;
+extern crate rand;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use ark_pallas::{Affine, Fr};
+use ark_std::{UniformRand, Zero};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use sha3::{Digest, Sha3_256};
+
+use bulletproofs::r1cs::*;
+use bulletproofs::{BulletproofGens, PedersenGens};
+use merlin::Transcript;
+
+/// Counts the bytes currently allocated and the peak since the last reset.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// The tests of this file measure the allocations of the whole process, so they run one at a time.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+/// Returns the peak number of bytes allocated while running `f`, beyond those allocated before.
+fn peak_allocation<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    let result = f();
+    (result, PEAK.load(Ordering::SeqCst) - before)
+}
+
+/// A vector with every third entry zero, like the sparse vectors the tree commits to.
+fn sparse_vector(len: usize) -> Vec<Fr> {
+    (0..len)
+        .map(|i| {
+            if i % 3 == 0 {
+                Fr::zero()
+            } else {
+                Fr::from(i as u64)
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn commit_vec_memory_is_bounded_by_the_chunk() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+
+    let n = 1 << 15;
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(n, 1);
+    let v = sparse_vector(n);
+
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"streaming"));
+    let ((_, vars), peak) = peak_allocation(|| prover.commit_vec(&v, Fr::from(7u64), &bp_gens));
+
+    // The prover keeps the opening and returns a variable per coordinate,
+    // the commitment itself must only need memory proportional to a chunk.
+    let kept = n * (std::mem::size_of::<Fr>() + std::mem::size_of::<Variable<Fr>>());
+    assert_eq!(vars.len(), n);
+    assert!(
+        peak < kept + (1 << 20),
+        "committing used {} bytes beyond the {} kept",
+        peak - kept,
+        kept
+    );
+}
+
+#[test]
+fn proof_bytes_are_unchanged() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+
+    // long enough to span several chunks of the multiscalar multiplications
+    let n: usize = 5_000;
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(n.next_power_of_two(), 1);
+    let v = sparse_vector(n);
+    let mut rng = StdRng::seed_from_u64(42);
+
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"streaming"));
+    let (comm, vars) = prover.commit_vec(&v, Fr::rand(&mut rng), &bp_gens);
+    let (V, x) = prover.commit(Fr::from(7u64), Fr::rand(&mut rng));
+    let (_, _, o) = prover.multiply(vars[2].into(), vars[n - 1].into());
+    prover.constrain(o - vars[2] * v[n - 1]);
+    prover.constrain(x - vars[7]);
+    let proof = prover.prove_with_rng(&bp_gens, &mut rng).unwrap();
+
+    let mut verifier = Verifier::new(Transcript::new(b"streaming"));
+    let vars = verifier.commit_vec(n, comm);
+    let x = verifier.commit(V);
+    let (_, _, o) = verifier.multiply(vars[2].into(), vars[n - 1].into());
+    verifier.constrain(o - vars[2] * v[n - 1]);
+    verifier.constrain(x - vars[7]);
+    assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+    // The digest of the proof produced before the prover streamed its commitments,
+    // updated for the version byte of the format and for the blinding base drawn apart from the chains.
+    assert_eq!(
+        format!("{:x}", Sha3_256::digest(&proof.to_bytes())),
+        "fecb9964105f3ac54a5010b06565a53bf0ea522852522eec0987551610f4c4da"
+    );
+}
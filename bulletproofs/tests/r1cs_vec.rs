@@ -760,3 +760,114 @@ mod veccom_mul {
         assert!(gadget_roundtrip_helper::<Affine>(5.into(), 5.into(), 25.into()).is_ok());
     }
 }
+
+mod veccom_public {
+    use super::*;
+
+    const DIM: usize = 0x40;
+
+    /// Constrains the sum of the public entries to equal the secret s
+    fn gadget<F: Field, CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        s: Variable<F>,
+        public: Vec<Variable<F>>,
+    ) {
+        let sum = public
+            .into_iter()
+            .fold(LinearCombination::default(), |sum, v| sum + v);
+        cs.constrain(sum - s);
+    }
+
+    // A sparse vector, like the padded children of a node
+    fn public_values<F: Field>(sum: u64) -> Vec<F> {
+        let mut v = vec![F::zero(); DIM];
+        v[3] = F::from(sum - 1);
+        v[DIM - 1] = F::one();
+        v
+    }
+
+    // Prover's scope
+    fn gadget_proof<C: AffineRepr>(
+        pc_gens: &PedersenGens<C>,
+        bp_gens: &BulletproofGens<C>,
+        s: u64,
+    ) -> Result<(R1CSProof<C>, C), R1CSError> {
+        let mut transcript = Transcript::new(b"R1CSExampleGadget");
+
+        // 1. Create a prover
+        let mut prover = Prover::new(pc_gens, &mut transcript);
+
+        // 2. Commit high-level variables
+        let (comm, s) = prover.commit(s.into(), C::ScalarField::rand(&mut rand::thread_rng()));
+        let public = prover.commit_vec_public(&public_values(10), bp_gens);
+
+        // 3. Build a CS
+        gadget(&mut prover, s, public);
+
+        // 4. Make a proof
+        let proof = prover.prove(bp_gens)?;
+
+        Ok((proof, comm))
+    }
+
+    // Verifier logic
+    fn gadget_verify<C: AffineRepr>(
+        pc_gens: &PedersenGens<C>,
+        bp_gens: &BulletproofGens<C>,
+        proof: R1CSProof<C>,
+        comm: C,
+        sum: u64,
+    ) -> Result<(), R1CSError> {
+        let mut transcript = Transcript::new(b"R1CSExampleGadget");
+
+        // 1. Create a verifier
+        let mut verifier = Verifier::new(&mut transcript);
+
+        // 2. Commit high-level variables
+        let s = verifier.commit(comm);
+        let public = verifier.commit_vec_public(&public_values(sum), pc_gens, bp_gens);
+
+        // 3. Build a CS
+        gadget(&mut verifier, s, public);
+
+        // 4. Verify the proof
+        verifier
+            .verify(&proof, pc_gens, bp_gens)
+            .map_err(|_| R1CSError::VerificationError)
+    }
+
+    fn gadget_roundtrip_helper<C: AffineRepr>(s: u64, sum: u64) -> Result<(), R1CSError> {
+        // Common
+        let pc_gens = PedersenGens::<C>::default();
+        let bp_gens = BulletproofGens::<C>::new(DIM, 1);
+
+        let (proof, comm) = gadget_proof::<C>(&pc_gens, &bp_gens, s)?;
+
+        gadget_verify::<C>(&pc_gens, &bp_gens, proof, comm, sum)
+    }
+
+    #[test]
+    fn test() {
+        assert!(gadget_roundtrip_helper::<Affine>(10, 10).is_ok());
+        assert!(gadget_roundtrip_helper::<Affine>(11, 10).is_err());
+        // the verifier recomputes the commitment from different public values
+        assert!(gadget_roundtrip_helper::<Affine>(10, 11).is_err());
+    }
+
+    #[test]
+    fn sparse_commitment() {
+        let pc_gens = PedersenGens::<Affine>::default();
+        let bp_gens = BulletproofGens::<Affine>::new(DIM, 1);
+        let v = public_values(10);
+        let blinding = <Affine as AffineRepr>::ScalarField::rand(&mut rand::thread_rng());
+        let expected = bp_gens
+            .share(0)
+            .G(DIM)
+            .zip(v.iter())
+            .fold(pc_gens.B_blinding * blinding, |sum, (g, v)| sum + *g * v);
+        assert_eq!(
+            bp_gens.share(0).commit_vec(&pc_gens, &v, blinding),
+            Affine::from(expected)
+        );
+    }
+}
@@ -0,0 +1,227 @@
+#![cfg(feature = "trace")]
+
+extern crate bulletproofs;
+extern crate merlin;
+extern crate rand;
+
+use std::sync::{Arc, Mutex};
+
+use ark_pallas::{Affine, Fr};
+use ark_std::UniformRand;
+
+use bulletproofs::r1cs::*;
+use bulletproofs::{BulletproofGens, PedersenGens};
+use merlin::Transcript;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// A span seen by `SpanRecorder`, with its integer fields.
+#[derive(Debug, Clone)]
+struct RecordedSpan {
+    name: &'static str,
+    fields: Vec<(&'static str, u64)>,
+}
+
+impl RecordedSpan {
+    fn field(&self, name: &str) -> Option<u64> {
+        self.fields
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+impl Visit for RecordedSpan {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields.push((field.name(), value));
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
+/// Records every span created while it is the default subscriber.
+#[derive(Clone, Default)]
+struct SpanRecorder {
+    spans: Arc<Mutex<Vec<RecordedSpan>>>,
+}
+
+impl SpanRecorder {
+    fn spans(&self) -> Vec<RecordedSpan> {
+        self.spans.lock().unwrap().clone()
+    }
+
+    fn names(&self) -> Vec<&'static str> {
+        self.spans().iter().map(|span| span.name).collect()
+    }
+}
+
+impl Subscriber for SpanRecorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let mut span = RecordedSpan {
+            name: attributes.metadata().name(),
+            fields: Vec::new(),
+        };
+        attributes.record(&mut span);
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(span);
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, id: &Id, values: &Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        values.record(&mut spans[id.into_u64() as usize - 1]);
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+/// Proves that the committed `x` and `y` multiply to `z` in either order,
+/// with randomized constraints on the outputs.
+fn multiplication_gadget<CS: RandomizableConstraintSystem<Fr>>(
+    cs: &mut CS,
+    x: Variable<Fr>,
+    y: Variable<Fr>,
+    z: Variable<Fr>,
+) -> Result<(), R1CSError> {
+    let (_, _, xy) = cs.multiply(x.into(), y.into());
+    let (_, _, yx) = cs.multiply(y.into(), x.into());
+    cs.specify_randomized_constraints(move |cs| {
+        let c = cs.challenge_scalar(b"multiplication challenge");
+        cs.constrain((xy - z) * c);
+        cs.constrain((yx - z) * c);
+        Ok(())
+    })
+}
+
+#[test]
+fn prove_and_verify_with_metrics() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(16, 1);
+    let mut rng = rand::thread_rng();
+    let x = Fr::rand(&mut rng);
+    let y = Fr::rand(&mut rng);
+
+    let recorder = SpanRecorder::default();
+    let (proof, commitments, prover_metrics) =
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let mut prover = Prover::new(&pc_gens, Transcript::new(b"trace test"));
+            let (commitments, variables): (Vec<_>, Vec<_>) = [x, y, x * y]
+                .iter()
+                .map(|value| prover.commit(*value, Fr::rand(&mut rng)))
+                .unzip();
+            multiplication_gadget(&mut prover, variables[0], variables[1], variables[2]).unwrap();
+            let (proof, metrics) = prover.prove_with_metrics(&bp_gens).unwrap();
+            (proof, commitments, metrics)
+        });
+
+    let names = recorder.names();
+    for phase in [
+        "first_phase_commitments",
+        "randomized_constraints",
+        "second_phase_commitments",
+        "flatten_constraints",
+        "t_poly",
+        "inner_product",
+    ] {
+        assert!(names.contains(&phase), "missing prover span {}", phase);
+        assert!(prover_metrics.duration(phase).is_some());
+    }
+    assert!(names.contains(&"inner_product_round"));
+    let flatten = recorder
+        .spans()
+        .into_iter()
+        .find(|span| span.name == "flatten_constraints")
+        .unwrap();
+    assert_eq!(flatten.field("n"), Some(2));
+    assert_eq!(flatten.field("constraints"), Some(6));
+    assert_eq!(prover_metrics.constraint_system.multipliers, 2);
+    assert_eq!(prover_metrics.padded_multipliers, 2);
+
+    let recorder = SpanRecorder::default();
+    let verifier_metrics = tracing::subscriber::with_default(recorder.clone(), || {
+        let mut verifier = Verifier::new(Transcript::new(b"trace test"));
+        let variables: Vec<_> = commitments
+            .iter()
+            .map(|commitment| verifier.commit(*commitment))
+            .collect();
+        multiplication_gadget(&mut verifier, variables[0], variables[1], variables[2]).unwrap();
+        verifier
+            .verify_with_metrics(&proof, &pc_gens, &bp_gens)
+            .unwrap()
+    });
+
+    let names = recorder.names();
+    for phase in [
+        "randomized_constraints",
+        "flatten_constraints",
+        "inner_product",
+        "msm",
+    ] {
+        assert!(names.contains(&phase), "missing verifier span {}", phase);
+        assert!(verifier_metrics.duration(phase).is_some());
+    }
+    assert!(recorder
+        .spans()
+        .iter()
+        .any(|span| span.name == "msm" && span.field("points").unwrap() > 0));
+    assert_eq!(verifier_metrics.constraint_system.multipliers, 2);
+}
+
+/// Squares the committed `x` 64 times, for a circuit of 64 multipliers.
+fn squares_gadget<CS: ConstraintSystem<Fr>>(cs: &mut CS, x: Variable<Fr>) {
+    let mut square: LinearCombination<Fr> = x.into();
+    for _ in 0..64 {
+        let (_, _, next) = cs.multiply(square.clone(), square);
+        square = next.into();
+    }
+}
+
+#[test]
+fn verify_small_circuit_with_large_generators() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let small_gens = BulletproofGens::<Affine>::new(64, 1);
+    let large_gens = BulletproofGens::<Affine>::new(4096, 1);
+    let mut rng = rand::thread_rng();
+    let x = Fr::rand(&mut rng);
+
+    let prove = |bp_gens: &BulletproofGens<Affine>| {
+        let mut prover = Prover::new(&pc_gens, Transcript::new(b"small circuit"));
+        let (commitment, variable) = prover.commit(x, Fr::rand(&mut rand::thread_rng()));
+        squares_gadget(&mut prover, variable);
+        (prover.prove(bp_gens).unwrap(), commitment)
+    };
+    let verifier = |commitment| {
+        let mut verifier = Verifier::new(Transcript::new(b"small circuit"));
+        let variable = verifier.commit(commitment);
+        squares_gadget(&mut verifier, variable);
+        verifier
+    };
+
+    // The generators a proof is made with only have to be as many as the padded multipliers,
+    // the first of a larger set are the same.
+    for (prover_gens, verifier_gens) in [(&small_gens, &large_gens), (&large_gens, &small_gens)] {
+        let (proof, commitment) = prove(prover_gens);
+        let proof_dependent_points = verifier(commitment)
+            .verification_scalars_and_points(&proof)
+            .unwrap()
+            .proof_dependent_points
+            .len();
+        let metrics = verifier(commitment)
+            .verify_with_metrics(&proof, &pc_gens, verifier_gens)
+            .unwrap();
+        assert_eq!(metrics.padded_multipliers, 64);
+        // B, B_blinding and 64 of the G and H generators, not 4096.
+        assert_eq!(metrics.msm_points, proof_dependent_points + 2 + 2 * 64);
+    }
+}
@@ -0,0 +1,283 @@
+//! A command line walk through the full flow: generating parameters, building a Curve Tree,
+//! proving membership of a leaf with select-and-rerandomize, and verifying the proof against a root.
+//!
+//! Like the bindings, the tool fixes the curves to Pallas (even levels and leaves) and Vesta (odd levels),
+//! and the branching factor to `BRANCHING_FACTOR`.
+//! Every file holds compressed canonical serializations:
+//! - parameters: the Pedersen generators, the capacity of the generators and the constants of the hash of each curve,
+//! - leaves: a vector of permissible Pallas points,
+//! - tree: the height followed by the leaves, the tree is rebuilt from them when loaded,
+//! - proof: a `MembershipProof`, bound to the parameters and the number of leaves of the tree,
+//!   in the format of the bindings.
+//!
+//! `verify` takes the tree file besides the root: a proof is verified against the children of the root,
+//! which the tool only has by rebuilding the tree, and the rebuilt root is checked to be the given one.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use ark_ec::short_weierstrass::Affine;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::UniformRand;
+use ark_std::Zero;
+use clap::{Parser, Subcommand};
+use relations::curve_tree::{CurveTree, MembershipProof, SelRerandParameters};
+use relations::cycle::EvenScalar;
+use relations::encoding::{Commitment, Root};
+use relations::permissible::is_permissible;
+
+type PallasConfig = ark_pallas::PallasConfig;
+type VestaConfig = ark_vesta::VestaConfig;
+type PallasA = Affine<PallasConfig>;
+type PallasScalar = ark_pallas::Fr;
+
+/// The branching factor of the trees built by the tool.
+const BRANCHING_FACTOR: usize = 256;
+/// The number of generators per curve, enough for trees of height up to 4.
+const GENERATORS_LENGTH: usize = 1 << 12;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+type Tree = CurveTree<BRANCHING_FACTOR, PallasConfig, VestaConfig>;
+type Proof = MembershipProof<BRANCHING_FACTOR, PallasConfig, VestaConfig>;
+
+#[derive(Parser)]
+#[command(about = "Build Curve Trees and prove and verify membership in them")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Writes the parameters of both curves, whose generators are derived from their labels.
+    GenParams {
+        #[arg(long, default_value = "params.bin")]
+        out: PathBuf,
+    },
+    /// Writes `count` random permissible leaves, handy for trying out the other commands.
+    GenLeaves {
+        #[arg(long)]
+        count: usize,
+        #[arg(long, default_value = "params.bin")]
+        params: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Builds a tree over the leaves and prints its root.
+    BuildTree {
+        #[arg(long)]
+        leaves: PathBuf,
+        /// The minimum height of the tree, the root is on Pallas so the height must be even.
+        #[arg(long, default_value_t = 2)]
+        height: usize,
+        #[arg(long, default_value = "params.bin")]
+        params: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Proves that the leaf at `index` is in the tree and prints its rerandomization.
+    Prove {
+        #[arg(long)]
+        tree: PathBuf,
+        #[arg(long)]
+        index: usize,
+        #[arg(long, default_value = "params.bin")]
+        params: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Verifies a proof against the root of the tree, printing the rerandomized leaf.
+    /// Unlike a verifier holding only the root, this takes the tree too: the proof is verified against
+    /// the children of the root, so the tree is rebuilt from its leaves and its root checked to be `root`.
+    Verify {
+        /// The hex encoded compressed root, as printed by `build-tree`.
+        #[arg(long)]
+        root: Root<PallasConfig>,
+        #[arg(long)]
+        proof: PathBuf,
+        #[arg(long)]
+        tree: PathBuf,
+        #[arg(long, default_value = "params.bin")]
+        params: PathBuf,
+    },
+}
+
+fn read<T: CanonicalDeserialize>(path: &Path) -> Result<T> {
+    let bytes = fs::read(path).map_err(|e| format!("Reading {}: {}", path.display(), e))?;
+    let mut reader = bytes.as_slice();
+    let t = T::deserialize_compressed(&mut reader)
+        .map_err(|e| format!("Decoding {}: {}", path.display(), e))?;
+    if !reader.is_empty() {
+        return Err(format!("Decoding {}: trailing bytes", path.display()).into());
+    }
+    Ok(t)
+}
+
+fn write<T: CanonicalSerialize>(path: &Path, t: &T) -> Result<usize> {
+    let mut bytes = Vec::with_capacity(t.compressed_size());
+    t.serialize_compressed(&mut bytes)?;
+    fs::write(path, &bytes).map_err(|e| format!("Writing {}: {}", path.display(), e))?;
+    Ok(bytes.len())
+}
+
+fn load_parameters(path: &Path) -> Result<SelRerandParameters<PallasConfig, VestaConfig>> {
+    let start = Instant::now();
+    let parameters = read(path)?;
+    println!("Derived parameters in {:.2?}", start.elapsed());
+    Ok(parameters)
+}
+
+fn build(
+    leaves: &[PallasA],
+    height: usize,
+    parameters: &SelRerandParameters<PallasConfig, VestaConfig>,
+) -> Result<Tree> {
+    if leaves.is_empty() {
+        return Err("The curve tree must have at least one leaf".into());
+    }
+    if let Some(leaf) = leaves
+        .iter()
+        .position(|leaf| !is_permissible(leaf, &parameters.even_parameters))
+    {
+        return Err(format!("Leaf {} is not permissible", leaf).into());
+    }
+    let start = Instant::now();
+    let tree = CurveTree::from_set(leaves, parameters, Some(height))?;
+    if tree.height() % 2 == 1 {
+        return Err(format!(
+            "{} leaves need a tree of odd height {}, whose root is on Vesta",
+            leaves.len(),
+            tree.height()
+        )
+        .into());
+    }
+    println!(
+        "Built a tree of height {} over {} leaves in {:.2?}",
+        tree.height(),
+        leaves.len(),
+        start.elapsed()
+    );
+    Ok(tree)
+}
+
+fn load_tree(
+    path: &Path,
+    parameters: &SelRerandParameters<PallasConfig, VestaConfig>,
+) -> Result<(Tree, usize)> {
+    let (height, leaves): (u64, Vec<PallasA>) = read(path)?;
+    let tree = build(&leaves, height as usize, parameters)?;
+    Ok((tree, leaves.len()))
+}
+
+fn gen_leaves(count: usize, params: &Path, out: &Path) -> Result<()> {
+    let parameters = load_parameters(params)?;
+    let mut rng = rand::thread_rng();
+    let leaves = (0..count)
+        .map(|_| {
+            let (leaf, _) = parameters.even_parameters.permissible_commitment(
+                &[PallasScalar::rand(&mut rng)],
+                PallasScalar::rand(&mut rng),
+                0,
+            )?;
+            Ok(leaf)
+        })
+        .collect::<Result<Vec<PallasA>>>()?;
+    write(out, &leaves)?;
+    println!("Wrote {} leaves to {}", count, out.display());
+    Ok(())
+}
+
+fn build_tree(leaves: &Path, height: usize, params: &Path, out: &Path) -> Result<()> {
+    if height % 2 == 1 {
+        return Err("The height must be even".into());
+    }
+    let parameters = load_parameters(params)?;
+    let leaves: Vec<PallasA> = read(leaves)?;
+    let tree = build(&leaves, height, &parameters)?;
+    write(out, &(height as u64, leaves))?;
+    println!("Root: {:#}", Root(tree.root_node()?));
+    Ok(())
+}
+
+fn prove(tree: &Path, index: usize, params: &Path, out: &Path) -> Result<()> {
+    let parameters = load_parameters(params)?;
+    let (tree, leaves) = load_tree(tree, &parameters)?;
+    if index >= leaves {
+        return Err(format!("Index {} out of range for {} leaves", index, leaves).into());
+    }
+
+    let start = Instant::now();
+    // The tool does not keep the blinding of the leaves, so the blinding of the rerandomized leaf is not reported.
+    let (proof, _) = tree.prove_membership(
+        index,
+        EvenScalar(PallasScalar::zero()),
+        &parameters,
+        &mut rand::thread_rng(),
+    )?;
+    println!("Proved membership in {:.2?}", start.elapsed());
+
+    let size = write(out, &proof)?;
+    println!(
+        "Wrote a proof of {} bytes to {} (path {}, even proof {}, odd proof {})",
+        size,
+        out.display(),
+        proof.path.compressed_size(),
+        proof.even_proof.compressed_size(),
+        proof.odd_proof.compressed_size()
+    );
+    println!(
+        "Rerandomized leaf: {:#}",
+        Commitment(proof.rerandomized_leaf())
+    );
+    Ok(())
+}
+
+fn verify(root: Root<PallasConfig>, proof: &Path, tree: &Path, params: &Path) -> Result<()> {
+    let parameters = load_parameters(params)?;
+    let (tree, _) = load_tree(tree, &parameters)?;
+    if tree.root_node()? != root.0 {
+        return Err("The tree does not have the given root".into());
+    }
+    let proof: Proof = read(proof)?;
+
+    let start = Instant::now();
+    let rerandomized_leaf = proof.verify(&tree, &parameters)?;
+    println!("Verified membership in {:.2?}", start.elapsed());
+    println!("Rerandomized leaf: {:#}", Commitment(rerandomized_leaf));
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::GenParams { out } => {
+            let parameters = SelRerandParameters::<PallasConfig, VestaConfig>::new(
+                GENERATORS_LENGTH,
+                GENERATORS_LENGTH,
+            );
+            write(&out, &parameters)?;
+            println!("Wrote the parameters to {}", out.display());
+            Ok(())
+        }
+        Command::GenLeaves { count, params, out } => gen_leaves(count, &params, &out),
+        Command::BuildTree {
+            leaves,
+            height,
+            params,
+            out,
+        } => build_tree(&leaves, height, &params, &out),
+        Command::Prove {
+            tree,
+            index,
+            params,
+            out,
+        } => prove(&tree, index, &params, &out),
+        Command::Verify {
+            root,
+            proof,
+            tree,
+            params,
+        } => verify(root, &proof, &tree, &params),
+    }
+}
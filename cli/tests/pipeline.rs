@@ -0,0 +1,122 @@
+//! Runs the binary through the whole flow, from parameters to a verified proof.
+
+use std::path::Path;
+
+use assert_cmd::Command;
+
+fn run(directory: &Path, args: &[&str]) -> String {
+    let output = Command::cargo_bin("curve-trees")
+        .unwrap()
+        .current_dir(directory)
+        .args(args)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    String::from_utf8(output).unwrap()
+}
+
+// Returns the hex value printed after `label` by a command.
+fn printed(output: &str, label: &str) -> String {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix(label))
+        .unwrap_or_else(|| panic!("`{}` not in output:\n{}", label, output))
+        .to_string()
+}
+
+#[test]
+fn test_full_pipeline() {
+    let directory = tempfile::tempdir().unwrap();
+    let directory = directory.path();
+
+    run(directory, &["gen-params"]);
+    run(
+        directory,
+        &["gen-leaves", "--count", "5", "--out", "leaves.bin"],
+    );
+    let output = run(
+        directory,
+        &["build-tree", "--leaves", "leaves.bin", "--out", "tree.bin"],
+    );
+    let root = printed(&output, "Root: ");
+
+    let output = run(
+        directory,
+        &[
+            "prove",
+            "--tree",
+            "tree.bin",
+            "--index",
+            "3",
+            "--out",
+            "proof.bin",
+        ],
+    );
+    assert!(output.contains("Wrote a proof of"));
+    let rerandomized_leaf = printed(&output, "Rerandomized leaf: ");
+
+    let output = run(
+        directory,
+        &[
+            "verify",
+            "--root",
+            &root,
+            "--proof",
+            "proof.bin",
+            "--tree",
+            "tree.bin",
+        ],
+    );
+    assert_eq!(printed(&output, "Rerandomized leaf: "), rerandomized_leaf);
+
+    // A different root, or a tampered proof, is rejected.
+    let other_root = printed(
+        &run(
+            directory,
+            &[
+                "build-tree",
+                "--leaves",
+                "leaves.bin",
+                "--height",
+                "4",
+                "--out",
+                "tall.bin",
+            ],
+        ),
+        "Root: ",
+    );
+    Command::cargo_bin("curve-trees")
+        .unwrap()
+        .current_dir(directory)
+        .args([
+            "verify",
+            "--root",
+            &other_root,
+            "--proof",
+            "proof.bin",
+            "--tree",
+            "tree.bin",
+        ])
+        .assert()
+        .failure();
+    let mut proof = std::fs::read(directory.join("proof.bin")).unwrap();
+    let last = proof.len() - 1;
+    proof[last] ^= 1;
+    std::fs::write(directory.join("proof.bin"), proof).unwrap();
+    Command::cargo_bin("curve-trees")
+        .unwrap()
+        .current_dir(directory)
+        .args([
+            "verify",
+            "--root",
+            &root,
+            "--proof",
+            "proof.bin",
+            "--tree",
+            "tree.bin",
+        ])
+        .assert()
+        .failure();
+}
@@ -0,0 +1,5 @@
+//! Tests of the contract between the `bulletproofs` and `relations` crates, in `tests/`.
+//!
+//! The tests use the public API of each crate only, as a downstream user would, so that a refactor of either
+//! changing how the other's constraint systems are built, e.g. the variables of vector commitments,
+//! the generators they use or what proofs depend on, fails here rather than in the proofs of deployments.
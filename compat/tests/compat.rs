@@ -0,0 +1,206 @@
+//! The select-and-rerandomize statement built with the public APIs of bulletproofs and relations,
+//! pinning what relations relies on bulletproofs for.
+
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_pallas::{Affine as PallasAffine, Fr as PallasScalar, PallasConfig, Projective};
+use ark_serialize::CanonicalSerialize;
+use ark_std::UniformRand;
+use ark_vesta::VestaConfig;
+use bulletproofs::r1cs::{ConstraintSystem, Prover, R1CSProof, Variable, Verifier};
+use bulletproofs::{BulletproofGens, PedersenGens};
+use merlin::Transcript;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use relations::curve_tree::{CurveTree, SelRerandParameters};
+use relations::encoding::fingerprint;
+use relations::layout::{CommitmentLayout, TAG, VALUE};
+use relations::protocol;
+
+const L: usize = 4;
+
+// The fingerprint of the path and proofs of `prove_membership` from the seed 692.
+// Changes with the labels of the protocol, the gadgets of relations, or how bulletproofs proves;
+// update it only with a deliberate change of the proofs.
+const PINNED_PROOFS: &str = "feaca1d1f3d5a802";
+
+// Commitments to vectors have a variable per coordinate, in the order of the coordinates,
+// numbered apart from the commitments to single values.
+#[test]
+fn test_commit_vec_variables() {
+    let mut rng = StdRng::seed_from_u64(692);
+    let pc_gens = PedersenGens::<PallasAffine>::default();
+    let bp_gens = BulletproofGens::<PallasAffine>::new(16, 1);
+    let values: Vec<PallasScalar> = (1..=3u64).map(PallasScalar::from).collect();
+    let blinding = PallasScalar::rand(&mut rng);
+
+    let prove = || {
+        let mut prover = Prover::new(&pc_gens, Transcript::new(b"compat"));
+        let (single, variable) = prover.commit(PallasScalar::from(7u64), blinding);
+        assert_eq!(variable, Variable::Committed(0));
+        let (commitment, variables) = prover.commit_vec(&values, blinding, &bp_gens);
+        assert_eq!(
+            variables,
+            (0..3)
+                .map(|i| Variable::VectorCommit(0, i))
+                .collect::<Vec<_>>()
+        );
+        let (commitment_1, variables_1) = prover.commit_vec(&values[..2], blinding, &bp_gens);
+        assert_eq!(
+            variables_1,
+            vec![Variable::VectorCommit(1, 0), Variable::VectorCommit(1, 1)]
+        );
+        for (variable, value) in variables.iter().zip(&values) {
+            prover.constrain(*variable - *value);
+        }
+        let proof = prover.prove_with_rng(&bp_gens, &mut StdRng::seed_from_u64(0));
+        ([single, commitment, commitment_1], proof.unwrap())
+    };
+    let verify = |[single, commitment, commitment_1]: [PallasAffine; 3],
+                  proof: &R1CSProof<PallasAffine>,
+                  order: &[usize]| {
+        let mut verifier = Verifier::new(Transcript::new(b"compat"));
+        assert_eq!(verifier.commit(single), Variable::Committed(0));
+        let variables = verifier.commit_vec(3, commitment);
+        assert_eq!(
+            variables,
+            (0..3)
+                .map(|i| Variable::VectorCommit(0, i))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(verifier.commit_vec(2, commitment_1).len(), 2);
+        for (variable, j) in variables.iter().zip(order) {
+            verifier.constrain(*variable - values[*j]);
+        }
+        verifier.verify(proof, &pc_gens, &bp_gens)
+    };
+
+    // The commitment is to the leading G generators, coordinate by coordinate.
+    let (commitments, proof) = prove();
+    let expected = Projective::msm_unchecked(bp_gens.share(0).G_slice(3), &values)
+        + pc_gens.B_blinding * blinding;
+    assert_eq!(commitments[1], expected.into_affine());
+    assert!(verify(commitments, &proof, &[0, 1, 2]).is_ok());
+
+    // The variable of each coordinate is the wire of that coordinate only.
+    assert!(verify(commitments, &proof, &[1, 0, 2]).is_err());
+}
+
+// The commitments of relations are those of bulletproofs, to the generators of their slot.
+#[test]
+fn test_relations_commitment_generators() {
+    let parameters = SelRerandParameters::<PallasConfig, VestaConfig>::new(1 << 10, 1 << 10);
+    let even = &parameters.even_parameters;
+    let mut rng = rng(0);
+    let values: Vec<PallasScalar> = (0..L).map(|_| PallasScalar::rand(&mut rng)).collect();
+    let blinding = PallasScalar::rand(&mut rng);
+
+    let mut prover = Prover::new(&even.pc_gens, Transcript::new(b"compat"));
+    let (commitment, variables) = prover.commit_vec(&values, blinding, &even.bp_gens);
+    assert_eq!(variables.len(), L);
+    assert_eq!(even.commit(&values, blinding, 0).unwrap(), commitment);
+
+    // The generator set `i` is the `i`-th run of as many generators as there are values.
+    let generators = &even.bp_gens.share(0).G_slice(2 * L)[L..];
+    let expected =
+        Projective::msm_unchecked(generators, &values) + even.pc_gens.B_blinding * blinding;
+    assert_eq!(
+        even.commit(&values, blinding, 1).unwrap(),
+        expected.into_affine()
+    );
+
+    // The slots of a layout are the generators at their coordinates.
+    let layout = CommitmentLayout::coin();
+    let slots = even.bp_gens.share(0).G_slice(layout.width());
+    assert_eq!(layout.generator(VALUE, &even.bp_gens).unwrap(), slots[0]);
+    assert_eq!(layout.generator(TAG, &even.bp_gens).unwrap(), slots[1]);
+}
+
+fn rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+// Proves the membership of a leaf of a tree of height 2 with the gadgets of relations and the provers of
+// bulletproofs, with all randomness drawn from `seed`, and verifies the proofs.
+// Returns the serialized path and proofs.
+fn prove_membership(seed: u64) -> Vec<u8> {
+    let mut rng = rng(seed);
+    let parameters = SelRerandParameters::<PallasConfig, VestaConfig>::new(1 << 10, 1 << 10);
+    let leaves: Vec<PallasAffine> = (0..6u64)
+        .map(|value| {
+            let blinding = PallasScalar::rand(&mut rng);
+            parameters
+                .even_parameters
+                .permissible_commitment(&[PallasScalar::from(value)], blinding, 0)
+                .unwrap()
+                .0
+        })
+        .collect();
+    let tree =
+        CurveTree::<L, PallasConfig, VestaConfig>::from_set(&leaves, &parameters, Some(2)).unwrap();
+
+    let mut even_prover = Prover::new(
+        &parameters.even_parameters.pc_gens,
+        Transcript::new(protocol::MEMBERSHIP),
+    );
+    let mut odd_prover = Prover::new(
+        &parameters.odd_parameters.pc_gens,
+        Transcript::new(protocol::MEMBERSHIP),
+    );
+    let (path, _) = tree
+        .select_and_rerandomize_prover_gadget(
+            4,
+            &mut even_prover,
+            &mut odd_prover,
+            &parameters,
+            &mut rng,
+        )
+        .unwrap();
+    let even_proof = even_prover
+        .prove_with_rng(&parameters.even_parameters.bp_gens, &mut rng)
+        .unwrap();
+    let odd_proof = odd_prover
+        .prove_with_rng(&parameters.odd_parameters.bp_gens, &mut rng)
+        .unwrap();
+
+    let mut bytes = Vec::new();
+    path.serialize_compressed(&mut bytes).unwrap();
+    bytes.extend(even_proof.to_bytes());
+    bytes.extend(odd_proof.to_bytes());
+
+    let mut even_verifier = Verifier::new(Transcript::new(protocol::MEMBERSHIP));
+    let mut odd_verifier = Verifier::new(Transcript::new(protocol::MEMBERSHIP));
+    let rerandomized_leaf = tree
+        .select_and_rerandomize_verifier_gadget(
+            &mut even_verifier,
+            &mut odd_verifier,
+            path,
+            &parameters,
+        )
+        .unwrap();
+    assert!(!leaves.contains(&rerandomized_leaf));
+    assert!(!rerandomized_leaf.is_zero());
+    even_verifier
+        .verify(
+            &even_proof,
+            &parameters.even_parameters.pc_gens,
+            &parameters.even_parameters.bp_gens,
+        )
+        .unwrap();
+    odd_verifier
+        .verify(
+            &odd_proof,
+            &parameters.odd_parameters.pc_gens,
+            &parameters.odd_parameters.bp_gens,
+        )
+        .unwrap();
+    bytes
+}
+
+// Proving is a function of the randomness: the same seed gives the same bytes, on every platform and version.
+#[test]
+fn test_membership_proof_bytes() {
+    let bytes = prove_membership(692);
+    assert_eq!(bytes, prove_membership(692));
+    assert_ne!(bytes, prove_membership(693));
+    assert_eq!(fingerprint(&bytes), PINNED_PROOFS);
+}
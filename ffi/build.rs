@@ -0,0 +1,25 @@
+// Generates the C header from the bindings, then compiles the C test program against it
+// into a static library that the `c_api` integration test links against.
+// `cargo_metadata(false)` keeps it out of the library itself.
+fn main() {
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-changed=tests/c/membership.c");
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(cbindgen::Config::from_root_or_default(&crate_dir))
+        .generate()
+        .expect("Generating the C header")
+        .write_to_file("include/curve_trees.h");
+
+    cc::Build::new()
+        .file("tests/c/membership.c")
+        .include("include")
+        .cargo_metadata(false)
+        .compile("membership_test");
+    println!(
+        "cargo:rustc-link-search=native={}",
+        std::env::var("OUT_DIR").unwrap()
+    );
+}
@@ -0,0 +1,153 @@
+/*
+ * C bindings for proving and verifying membership in a Curve Tree.
+ * Generated by cbindgen from src/lib.rs when the crate is built, do not edit.
+ *
+ * Leaves and values live on Pallas, points and scalars are passed as their
+ * compressed canonical serializations of CT_POINT_SIZE and CT_SCALAR_SIZE
+ * bytes. Every function returning int32_t returns CT_OK on success and a
+ * negative error code otherwise.
+ */
+
+#ifndef CURVE_TREES_H
+#define CURVE_TREES_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+// The branching factor of trees built through the bindings.
+#define CT_BRANCHING_FACTOR 256
+
+// The number of generators per curve, enough for trees of height up to 4.
+#define CT_GENERATORS_LENGTH (1 << 12)
+
+// The size of a compressed point.
+#define CT_POINT_SIZE 33
+
+// The size of a scalar.
+#define CT_SCALAR_SIZE 32
+
+// Success.
+#define CT_OK 0
+
+// A pointer argument was null.
+#define CT_ERR_NULL_POINTER -1
+
+// An argument was out of range, e.g. a leaf that is not permissible or an index past the leaves.
+#define CT_ERR_INVALID_ARGUMENT -2
+
+// A point, scalar or proof failed to deserialize.
+#define CT_ERR_DESERIALIZATION -3
+
+// Proving failed.
+#define CT_ERR_PROVING -4
+
+// The proof did not verify.
+#define CT_ERR_VERIFICATION -5
+
+// The bindings panicked, the panic was caught at the boundary.
+#define CT_ERR_PANIC -6
+
+// Opaque handle to the parameters of both curves.
+typedef struct CtParameters CtParameters;
+
+// Opaque handle to a Curve Tree.
+typedef struct CtTree CtTree;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Derives the generators of both curves, which depend on their labels alone, and writes a handle to `out`.
+//
+// # Safety
+// `out` must be a valid pointer.
+int32_t ct_parameters_new(struct CtParameters **out);
+
+// Releases parameters created by `ct_parameters_new`. Passing null is a no-op.
+//
+// # Safety
+// `parameters` must be null or a handle returned by `ct_parameters_new` that was not yet freed.
+void ct_parameters_free(struct CtParameters *parameters);
+
+// Commits to `n_values` scalars with the given blinding and offsets the blinding until the commitment is permissible.
+// Writes the leaf to `out_leaf` and its final blinding to `out_blinding`.
+//
+// # Safety
+// `values` must point to `n_values * 32` readable bytes, `blinding` to 32 readable bytes,
+// `out_leaf` to 33 writable bytes and `out_blinding` to 32 writable bytes.
+int32_t ct_leaf_commitment(const struct CtParameters *parameters,
+                           const uint8_t *values,
+                           size_t n_values,
+                           const uint8_t *blinding,
+                           uint8_t *out_leaf,
+                           uint8_t *out_blinding);
+
+// Builds a tree from `n_leaves` permissible compressed points and writes a handle to `out`.
+// If `height` is not zero the tree is padded to at least this height.
+//
+// # Safety
+// `leaves` must point to `n_leaves * 33` readable bytes and `out` must be a valid pointer.
+int32_t ct_tree_new(const struct CtParameters *parameters,
+                    const uint8_t *leaves,
+                    size_t n_leaves,
+                    size_t height,
+                    struct CtTree **out);
+
+// Releases a tree created by `ct_tree_new`. Passing null is a no-op.
+//
+// # Safety
+// `tree` must be null or a handle returned by `ct_tree_new` that was not yet freed.
+void ct_tree_free(struct CtTree *tree);
+
+// Writes the root of a tree of even height to `out_root`.
+// Trees of odd height have their root on Vesta and are rejected with `CT_ERR_INVALID_ARGUMENT`.
+//
+// # Safety
+// `out_root` must point to 33 writable bytes.
+int32_t ct_tree_root(const struct CtTree *tree, uint8_t *out_root);
+
+// Proves that the leaf at `index` is in the tree and rerandomizes it, see `CurveTree::prove_membership`.
+// The proof is the serialization of a `MembershipProof`, bound to the parameters and the number of leaves of the tree.
+// On success `*out_proof` points to `*out_proof_len` bytes that must be released with `ct_bytes_free`,
+// the rerandomized leaf is written to `out_rerandomized_leaf`,
+// and `leaf_blinding` plus the rerandomization is written to `out_rerandomized_blinding`.
+//
+// # Safety
+// `leaf_blinding` must point to 32 readable bytes, `out_rerandomized_leaf` to 33 writable bytes,
+// `out_rerandomized_blinding` to 32 writable bytes, and `out_proof` and `out_proof_len` must be valid pointers.
+int32_t ct_prove(const struct CtParameters *parameters,
+                 const struct CtTree *tree,
+                 size_t index,
+                 const uint8_t *leaf_blinding,
+                 uint8_t **out_proof,
+                 size_t *out_proof_len,
+                 uint8_t *out_rerandomized_leaf,
+                 uint8_t *out_rerandomized_blinding);
+
+// Releases a buffer returned by `ct_prove`. Passing null is a no-op.
+//
+// # Safety
+// `bytes` must be null or a buffer returned by this library together with its length `len`, not yet freed.
+void ct_bytes_free(uint8_t *bytes,
+                   size_t len);
+
+// Verifies a proof produced by `ct_prove` against the root of the tree, see `MembershipProof::verify`,
+// and on success writes the rerandomized leaf to `out_rerandomized_leaf`.
+// Returns `CT_ERR_VERIFICATION` if the proof does not verify.
+//
+// The proof is checked against the children of the root, so the verifier needs the tree rather than its root alone:
+// to verify against a trusted root, build the tree from the leaves and compare `ct_tree_root` with it first.
+//
+// # Safety
+// `proof` must point to `proof_len` readable bytes and `out_rerandomized_leaf` to 33 writable bytes.
+int32_t ct_verify(const struct CtParameters *parameters,
+                  const struct CtTree *tree,
+                  const uint8_t *proof,
+                  size_t proof_len,
+                  uint8_t *out_rerandomized_leaf);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CURVE_TREES_H */
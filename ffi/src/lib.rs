@@ -0,0 +1,335 @@
+//! C bindings for proving and verifying membership in a Curve Tree with select-and-rerandomize.
+//!
+//! The bindings fix the curves to Pallas (even levels and leaves) and Vesta (odd levels),
+//! and the branching factor to `BRANCHING_FACTOR`.
+//! Points and scalars cross the boundary as their compressed canonical serializations of 33 and 32 bytes.
+//!
+//! Every function returning `i32` returns `CT_OK` on success and a negative error code otherwise.
+//! Panics are caught at the boundary and reported as `CT_ERR_PANIC`.
+//! Objects returned through `out` pointers are owned by the caller and must be released with the matching `_free` function.
+//! The C declarations in `include/curve_trees.h` are generated from this file by cbindgen when the crate is built.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::{ptr, slice};
+
+use ark_ec::short_weierstrass::Affine;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use relations::curve_tree::{CurveTree, MembershipProof, SelRerandParameters};
+use relations::cycle::EvenScalar;
+use relations::permissible::is_permissible;
+
+type PallasConfig = ark_pallas::PallasConfig;
+type VestaConfig = ark_vesta::VestaConfig;
+type PallasA = Affine<PallasConfig>;
+type PallasScalar = ark_pallas::Fr;
+
+/// The branching factor of trees built through the bindings.
+pub const BRANCHING_FACTOR: usize = 256;
+/// The number of generators per curve, enough for trees of height up to 4.
+pub const GENERATORS_LENGTH: usize = 1 << 12;
+/// The size of a compressed point.
+pub const CT_POINT_SIZE: usize = 33;
+/// The size of a scalar.
+pub const CT_SCALAR_SIZE: usize = 32;
+
+/// Success.
+pub const CT_OK: i32 = 0;
+/// A pointer argument was null.
+pub const CT_ERR_NULL_POINTER: i32 = -1;
+/// An argument was out of range, e.g. a leaf that is not permissible or an index past the leaves.
+pub const CT_ERR_INVALID_ARGUMENT: i32 = -2;
+/// A point, scalar or proof failed to deserialize.
+pub const CT_ERR_DESERIALIZATION: i32 = -3;
+/// Proving failed.
+pub const CT_ERR_PROVING: i32 = -4;
+/// The proof did not verify.
+pub const CT_ERR_VERIFICATION: i32 = -5;
+/// The bindings panicked, the panic was caught at the boundary.
+pub const CT_ERR_PANIC: i32 = -6;
+
+/// Opaque handle to the parameters of both curves.
+pub struct CtParameters(SelRerandParameters<PallasConfig, VestaConfig>);
+
+/// Opaque handle to a Curve Tree.
+pub struct CtTree {
+    tree: CurveTree<BRANCHING_FACTOR, PallasConfig, VestaConfig>,
+    leaves: usize,
+}
+
+// Runs `f` and converts its outcome, including a panic, into an error code.
+fn guard<F: FnOnce() -> Result<(), i32>>(f: F) -> i32 {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => CT_OK,
+        Ok(Err(code)) => code,
+        Err(_) => CT_ERR_PANIC,
+    }
+}
+
+unsafe fn input<'a>(data: *const u8, len: usize) -> Result<&'a [u8], i32> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if data.is_null() {
+        return Err(CT_ERR_NULL_POINTER);
+    }
+    Ok(slice::from_raw_parts(data, len))
+}
+
+fn byte_length(elements: usize, element_size: usize) -> Result<usize, i32> {
+    elements
+        .checked_mul(element_size)
+        .ok_or(CT_ERR_INVALID_ARGUMENT)
+}
+
+unsafe fn reference<'a, T>(p: *const T) -> Result<&'a T, i32> {
+    p.as_ref().ok_or(CT_ERR_NULL_POINTER)
+}
+
+fn deserialize<T: CanonicalDeserialize>(bytes: &[u8]) -> Result<T, i32> {
+    T::deserialize_compressed(bytes).map_err(|_| CT_ERR_DESERIALIZATION)
+}
+
+// Writes the compressed serialization of `element`, which the caller sized as `CT_POINT_SIZE` or `CT_SCALAR_SIZE`.
+unsafe fn write_element<T: CanonicalSerialize>(element: &T, out: *mut u8) -> Result<(), i32> {
+    if out.is_null() {
+        return Err(CT_ERR_NULL_POINTER);
+    }
+    let mut bytes = Vec::new();
+    element.serialize_compressed(&mut bytes).unwrap();
+    ptr::copy_nonoverlapping(bytes.as_ptr(), out, bytes.len());
+    Ok(())
+}
+
+/// Derives the generators of both curves, which depend on their labels alone, and writes a handle to `out`.
+///
+/// # Safety
+/// `out` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ct_parameters_new(out: *mut *mut CtParameters) -> i32 {
+    guard(|| {
+        if out.is_null() {
+            return Err(CT_ERR_NULL_POINTER);
+        }
+        let parameters = SelRerandParameters::new(GENERATORS_LENGTH, GENERATORS_LENGTH);
+        *out = Box::into_raw(Box::new(CtParameters(parameters)));
+        Ok(())
+    })
+}
+
+/// Releases parameters created by `ct_parameters_new`. Passing null is a no-op.
+///
+/// # Safety
+/// `parameters` must be null or a handle returned by `ct_parameters_new` that was not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn ct_parameters_free(parameters: *mut CtParameters) {
+    if !parameters.is_null() {
+        drop(Box::from_raw(parameters));
+    }
+}
+
+/// Commits to `n_values` scalars with the given blinding and offsets the blinding until the commitment is permissible.
+/// Writes the leaf to `out_leaf` and its final blinding to `out_blinding`.
+///
+/// # Safety
+/// `values` must point to `n_values * 32` readable bytes, `blinding` to 32 readable bytes,
+/// `out_leaf` to 33 writable bytes and `out_blinding` to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ct_leaf_commitment(
+    parameters: *const CtParameters,
+    values: *const u8,
+    n_values: usize,
+    blinding: *const u8,
+    out_leaf: *mut u8,
+    out_blinding: *mut u8,
+) -> i32 {
+    guard(|| {
+        let parameters = &reference(parameters)?.0;
+        let values = input(values, byte_length(n_values, CT_SCALAR_SIZE)?)?;
+        let blinding: PallasScalar = deserialize(input(blinding, CT_SCALAR_SIZE)?)?;
+        if n_values == 0 || n_values > BRANCHING_FACTOR {
+            return Err(CT_ERR_INVALID_ARGUMENT);
+        }
+        let values = values
+            .chunks_exact(CT_SCALAR_SIZE)
+            .map(deserialize)
+            .collect::<Result<Vec<PallasScalar>, _>>()?;
+        let (leaf, blinding) = parameters
+            .even_parameters
+            .permissible_commitment(&values, blinding, 0)
+            .map_err(|_| CT_ERR_INVALID_ARGUMENT)?;
+        write_element(&leaf, out_leaf)?;
+        write_element(&blinding, out_blinding)
+    })
+}
+
+/// Builds a tree from `n_leaves` permissible compressed points and writes a handle to `out`.
+/// If `height` is not zero the tree is padded to at least this height.
+///
+/// # Safety
+/// `leaves` must point to `n_leaves * 33` readable bytes and `out` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ct_tree_new(
+    parameters: *const CtParameters,
+    leaves: *const u8,
+    n_leaves: usize,
+    height: usize,
+    out: *mut *mut CtTree,
+) -> i32 {
+    guard(|| {
+        let parameters = &reference(parameters)?.0;
+        let leaves = input(leaves, byte_length(n_leaves, CT_POINT_SIZE)?)?;
+        if out.is_null() {
+            return Err(CT_ERR_NULL_POINTER);
+        }
+        if n_leaves == 0 {
+            return Err(CT_ERR_INVALID_ARGUMENT);
+        }
+        let leaves = leaves
+            .chunks_exact(CT_POINT_SIZE)
+            .map(deserialize)
+            .collect::<Result<Vec<PallasA>, _>>()?;
+        for leaf in &leaves {
+            if !is_permissible(leaf, &parameters.even_parameters) {
+                return Err(CT_ERR_INVALID_ARGUMENT);
+            }
+        }
+        let height = if height == 0 { None } else { Some(height) };
+        let tree = CurveTree::from_set(&leaves, parameters, height)
+            .map_err(|_| CT_ERR_INVALID_ARGUMENT)?;
+        *out = Box::into_raw(Box::new(CtTree {
+            tree,
+            leaves: n_leaves,
+        }));
+        Ok(())
+    })
+}
+
+/// Releases a tree created by `ct_tree_new`. Passing null is a no-op.
+///
+/// # Safety
+/// `tree` must be null or a handle returned by `ct_tree_new` that was not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn ct_tree_free(tree: *mut CtTree) {
+    if !tree.is_null() {
+        drop(Box::from_raw(tree));
+    }
+}
+
+/// Writes the root of a tree of even height to `out_root`.
+/// Trees of odd height have their root on Vesta and are rejected with `CT_ERR_INVALID_ARGUMENT`.
+///
+/// # Safety
+/// `out_root` must point to 33 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ct_tree_root(tree: *const CtTree, out_root: *mut u8) -> i32 {
+    guard(|| {
+        let tree = &reference(tree)?.tree;
+        match tree {
+            CurveTree::Even(_) => write_element(
+                &tree.root_node().map_err(|_| CT_ERR_INVALID_ARGUMENT)?,
+                out_root,
+            ),
+            CurveTree::Odd(_) => Err(CT_ERR_INVALID_ARGUMENT),
+        }
+    })
+}
+
+/// Proves that the leaf at `index` is in the tree and rerandomizes it, see `CurveTree::prove_membership`.
+/// The proof is the serialization of a `MembershipProof`, bound to the parameters and the number of leaves of the tree.
+/// On success `*out_proof` points to `*out_proof_len` bytes that must be released with `ct_bytes_free`,
+/// the rerandomized leaf is written to `out_rerandomized_leaf`,
+/// and `leaf_blinding` plus the rerandomization is written to `out_rerandomized_blinding`.
+///
+/// # Safety
+/// `leaf_blinding` must point to 32 readable bytes, `out_rerandomized_leaf` to 33 writable bytes,
+/// `out_rerandomized_blinding` to 32 writable bytes, and `out_proof` and `out_proof_len` must be valid pointers.
+#[no_mangle]
+pub unsafe extern "C" fn ct_prove(
+    parameters: *const CtParameters,
+    tree: *const CtTree,
+    index: usize,
+    leaf_blinding: *const u8,
+    out_proof: *mut *mut u8,
+    out_proof_len: *mut usize,
+    out_rerandomized_leaf: *mut u8,
+    out_rerandomized_blinding: *mut u8,
+) -> i32 {
+    guard(|| {
+        let parameters = &reference(parameters)?.0;
+        let tree = reference(tree)?;
+        let leaf_blinding: PallasScalar = deserialize(input(leaf_blinding, CT_SCALAR_SIZE)?)?;
+        if out_proof.is_null() || out_proof_len.is_null() {
+            return Err(CT_ERR_NULL_POINTER);
+        }
+        if index >= tree.leaves {
+            return Err(CT_ERR_INVALID_ARGUMENT);
+        }
+
+        let (proof, rerandomized_blinding) = tree
+            .tree
+            .prove_membership(
+                index,
+                EvenScalar(leaf_blinding),
+                parameters,
+                &mut rand::thread_rng(),
+            )
+            .map_err(|_| CT_ERR_PROVING)?;
+
+        write_element(&proof.rerandomized_leaf(), out_rerandomized_leaf)?;
+        write_element(&rerandomized_blinding.0, out_rerandomized_blinding)?;
+
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        let bytes = bytes.into_boxed_slice();
+        *out_proof_len = bytes.len();
+        *out_proof = Box::into_raw(bytes) as *mut u8;
+        Ok(())
+    })
+}
+
+/// Releases a buffer returned by `ct_prove`. Passing null is a no-op.
+///
+/// # Safety
+/// `bytes` must be null or a buffer returned by this library together with its length `len`, not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn ct_bytes_free(bytes: *mut u8, len: usize) {
+    if !bytes.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(bytes, len)));
+    }
+}
+
+/// Verifies a proof produced by `ct_prove` against the root of the tree, see `MembershipProof::verify`,
+/// and on success writes the rerandomized leaf to `out_rerandomized_leaf`.
+/// Returns `CT_ERR_VERIFICATION` if the proof does not verify.
+///
+/// The proof is checked against the children of the root, so the verifier needs the tree rather than its root alone:
+/// to verify against a trusted root, build the tree from the leaves and compare `ct_tree_root` with it first.
+///
+/// # Safety
+/// `proof` must point to `proof_len` readable bytes and `out_rerandomized_leaf` to 33 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ct_verify(
+    parameters: *const CtParameters,
+    tree: *const CtTree,
+    proof: *const u8,
+    proof_len: usize,
+    out_rerandomized_leaf: *mut u8,
+) -> i32 {
+    guard(|| {
+        let parameters = &reference(parameters)?.0;
+        let tree = &reference(tree)?.tree;
+        let mut reader = input(proof, proof_len)?;
+        let proof =
+            MembershipProof::<BRANCHING_FACTOR, PallasConfig, VestaConfig>::deserialize_compressed(
+                &mut reader,
+            )
+            .map_err(|_| CT_ERR_DESERIALIZATION)?;
+        if !reader.is_empty() {
+            return Err(CT_ERR_DESERIALIZATION);
+        }
+        let rerandomized_leaf = proof
+            .verify(tree, parameters)
+            .map_err(|_| CT_ERR_VERIFICATION)?;
+        write_element(&rerandomized_leaf, out_rerandomized_leaf)
+    })
+}
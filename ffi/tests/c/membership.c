@@ -0,0 +1,74 @@
+#include <stdio.h>
+#include <string.h>
+
+#include "curve_trees.h"
+
+#define N_LEAVES 4
+
+#define CHECK(call)                                                          \
+    do {                                                                     \
+        int32_t code = (call);                                               \
+        if (code != CT_OK) {                                                 \
+            fprintf(stderr, "%s failed with %d\n", #call, code);             \
+            return 1;                                                        \
+        }                                                                    \
+    } while (0)
+
+/* Called from tests/c_api.rs, returns zero on success. */
+int run_membership_test(void) {
+    CtParameters *parameters = NULL;
+    CHECK(ct_parameters_new(&parameters));
+
+    uint8_t leaves[N_LEAVES * CT_POINT_SIZE];
+    uint8_t blindings[N_LEAVES * CT_SCALAR_SIZE];
+    for (int i = 0; i < N_LEAVES; i++) {
+        uint8_t value[CT_SCALAR_SIZE] = {0};
+        uint8_t blinding[CT_SCALAR_SIZE] = {0};
+        value[0] = (uint8_t)(i + 1);
+        blinding[0] = (uint8_t)(42 + i);
+        CHECK(ct_leaf_commitment(parameters, value, 1, blinding,
+                                 &leaves[i * CT_POINT_SIZE],
+                                 &blindings[i * CT_SCALAR_SIZE]));
+    }
+
+    CtTree *tree = NULL;
+    CHECK(ct_tree_new(parameters, leaves, N_LEAVES, 2, &tree));
+    uint8_t root[CT_POINT_SIZE];
+    CHECK(ct_tree_root(tree, root));
+
+    size_t index = 2;
+    uint8_t *proof = NULL;
+    size_t proof_len = 0;
+    uint8_t rerandomized_leaf[CT_POINT_SIZE];
+    uint8_t rerandomized_blinding[CT_SCALAR_SIZE];
+    CHECK(ct_prove(parameters, tree, index, &blindings[index * CT_SCALAR_SIZE],
+                   &proof, &proof_len, rerandomized_leaf, rerandomized_blinding));
+
+    uint8_t verified_leaf[CT_POINT_SIZE];
+    CHECK(ct_verify(parameters, tree, proof, proof_len, verified_leaf));
+    if (memcmp(verified_leaf, rerandomized_leaf, CT_POINT_SIZE) != 0) {
+        fprintf(stderr, "rerandomized leaves differ\n");
+        return 1;
+    }
+
+    /* A tampered proof must be rejected with an error code, not a crash. */
+    proof[proof_len / 2] ^= 1;
+    if (ct_verify(parameters, tree, proof, proof_len, verified_leaf) == CT_OK) {
+        fprintf(stderr, "tampered proof verified\n");
+        return 1;
+    }
+    if (ct_verify(parameters, tree, proof, 7, verified_leaf) != CT_ERR_DESERIALIZATION) {
+        fprintf(stderr, "truncated proof was not rejected\n");
+        return 1;
+    }
+    if (ct_prove(parameters, tree, N_LEAVES, blindings, &proof, &proof_len,
+                 rerandomized_leaf, rerandomized_blinding) != CT_ERR_INVALID_ARGUMENT) {
+        fprintf(stderr, "out of range index was not rejected\n");
+        return 1;
+    }
+
+    ct_bytes_free(proof, proof_len);
+    ct_tree_free(tree);
+    ct_parameters_free(parameters);
+    return 0;
+}
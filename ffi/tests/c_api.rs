@@ -0,0 +1,16 @@
+//! Runs the C program in `tests/c/membership.c`, compiled by the build script, against the bindings.
+
+use std::os::raw::c_int;
+
+// Links the bindings that the C program calls into.
+extern crate curve_trees_ffi;
+
+#[link(name = "membership_test", kind = "static")]
+extern "C" {
+    fn run_membership_test() -> c_int;
+}
+
+#[test]
+fn test_c_membership_proof() {
+    assert_eq!(unsafe { run_membership_test() }, 0);
+}
@@ -0,0 +1,90 @@
+//! Parameters and curve trees shared by the integration tests of the workspace.
+//!
+//! Each fixture is built once per test binary, on first use, from a fixed seed: a failure reproduces
+//! with the same generators and leaves, and no test depends on which test built a fixture first.
+//! The fixtures are immutable, a test that inserts leaves clones its tree first.
+
+use ark_ec::short_weierstrass::Affine;
+use ark_std::UniformRand;
+use once_cell::sync::Lazy;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use relations::curve_tree::{CurveTree, SelRerandParameters};
+
+pub type PallasConfig = ark_pallas::PallasConfig;
+pub type VestaConfig = ark_vesta::VestaConfig;
+pub type PallasScalar = ark_pallas::Fr;
+pub type SecpConfig = ark_secp256k1::Config;
+pub type SecqConfig = ark_secq256k1::Config;
+
+/// The number of generators of each curve of the shared parameters, enough for the trees and pours of the tests.
+pub const GENERATORS_LENGTH: usize = 1 << 12;
+
+/// A deterministic RNG of `seed`, for tests to pass where an API draws secrets and requires a `CryptoRng`.
+/// Anyone knowing the seed predicts its output: never use it for real keys or blindings.
+pub fn insecure_test_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+// The randomness of a fixture, of a seed distinct per fixture.
+fn rng(seed: u64) -> StdRng {
+    insecure_test_rng(seed)
+}
+
+/// The parameters of the Pallas and Vesta cycle.
+pub fn pallas_vesta() -> &'static SelRerandParameters<PallasConfig, VestaConfig> {
+    static PARAMETERS: Lazy<SelRerandParameters<PallasConfig, VestaConfig>> =
+        Lazy::new(|| SelRerandParameters::new(GENERATORS_LENGTH, GENERATORS_LENGTH));
+    &PARAMETERS
+}
+
+/// The parameters of the secp256k1 and secq256k1 cycle.
+pub fn secp_secq() -> &'static SelRerandParameters<SecpConfig, SecqConfig> {
+    static PARAMETERS: Lazy<SelRerandParameters<SecpConfig, SecqConfig>> =
+        Lazy::new(|| SelRerandParameters::new(GENERATORS_LENGTH, GENERATORS_LENGTH));
+    &PARAMETERS
+}
+
+/// A tree of the `pallas_vesta` parameters whose leaf `i` commits to the value `i` with blinding `blindings[i]`.
+pub struct TreeFixture<const L: usize> {
+    pub tree: CurveTree<L, PallasConfig, VestaConfig>,
+    pub values: Vec<PallasScalar>,
+    pub leaves: Vec<Affine<PallasConfig>>,
+    pub blindings: Vec<PallasScalar>,
+}
+
+impl<const L: usize> TreeFixture<L> {
+    fn new(count: u64, height: Option<usize>, seed: u64) -> Self {
+        let parameters = pallas_vesta();
+        let mut rng = rng(seed);
+        let values: Vec<_> = (0..count).map(PallasScalar::from).collect();
+        let (leaves, blindings): (Vec<_>, Vec<_>) = values
+            .iter()
+            .map(|v| {
+                parameters
+                    .even_parameters
+                    .permissible_commitment(&[*v], PallasScalar::rand(&mut rng), 0)
+                    .expect("one value per commitment")
+            })
+            .unzip();
+        let tree = CurveTree::from_set(&leaves, parameters, height).expect("a leaf at least");
+        Self {
+            tree,
+            values,
+            leaves,
+            blindings,
+        }
+    }
+}
+
+/// Three leaves in a tree of branching factor 32 and height 2.
+pub fn small_tree() -> &'static TreeFixture<32> {
+    static TREE: Lazy<TreeFixture<32>> = Lazy::new(|| TreeFixture::new(3, Some(2), 2));
+    &TREE
+}
+
+/// 21 leaves in a tree of branching factor 4 and height 3, with full and partial nodes at each level.
+pub fn medium_tree() -> &'static TreeFixture<4> {
+    static TREE: Lazy<TreeFixture<4>> = Lazy::new(|| TreeFixture::new(21, None, 3));
+    &TREE
+}
@@ -0,0 +1,57 @@
+#![no_main]
+
+//! The first byte selects the limits, the rest is deserialized as each type under them:
+//! whatever deserializes fits the limits, and an oversized prefix is `Error::LimitExceeded`.
+
+use curve_trees_fuzz::{FuzzPath, FuzzPour, FuzzSignedTx, BRANCHING_FACTOR};
+use libfuzzer_sys::fuzz_target;
+use relations::circuit::{CircuitDescription, CircuitStep};
+use relations::limits::{DeserializeLimits, DeserializeWithLimits};
+
+fn check_path(path: &FuzzPath, limits: &DeserializeLimits) {
+    assert!(path.even_commitments.len() <= limits.max_depth);
+    assert!(path.odd_commitments.len() <= limits.max_depth);
+}
+
+fn check_pour(pour: &FuzzPour, limits: &DeserializeLimits) {
+    check_path(&pour.randomized_path_0, limits);
+    check_path(&pour.randomized_path_1, limits);
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((selector, data)) = data.split_first() else {
+        return;
+    };
+    let limits = DeserializeLimits {
+        max_depth: usize::from(selector % 8),
+        max_arity: 1 << (selector % 16),
+        max_circuit_steps: usize::from(selector % 4),
+        max_transaction_size: usize::from(*selector) << 8,
+    };
+
+    if let Ok(path) = FuzzPath::deserialize_compressed_with_limits(data, &limits) {
+        check_path(&path, &limits);
+    }
+    if let Ok(pour) = FuzzPour::deserialize_compressed_with_limits(data, &limits) {
+        check_pour(&pour, &limits);
+    }
+    if let Ok(tx) = FuzzSignedTx::deserialize_compressed_with_limits(data, &limits) {
+        assert!(tx.pour_bytes.len() <= limits.max_transaction_size);
+        if let Ok(pour) = tx.pour_with_limits::<BRANCHING_FACTOR>(&limits) {
+            check_pour(&pour, &limits);
+        }
+    }
+    if let Ok(description) = CircuitDescription::deserialize_compressed_with_limits(data, &limits) {
+        assert!(description.steps.len() <= limits.max_circuit_steps);
+        for step in description.steps {
+            if let CircuitStep::SelectAndRerandomize {
+                branching_factor,
+                height,
+            } = step
+            {
+                assert!(branching_factor <= limits.max_arity);
+                assert!(height <= limits.max_depth);
+            }
+        }
+    }
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use ark_serialize::CanonicalDeserialize;
+use curve_trees_fuzz::{check_roundtrip, PallasA, VestaA};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(point) = PallasA::deserialize_compressed(data) {
+        check_roundtrip(&point);
+    }
+    if let Ok(point) = VestaA::deserialize_compressed(data) {
+        check_roundtrip(&point);
+    }
+    if let Ok(scalar) = ark_pallas::Fr::deserialize_compressed(data) {
+        check_roundtrip(&scalar);
+    }
+    if let Ok(scalar) = ark_vesta::Fr::deserialize_compressed(data) {
+        check_roundtrip(&scalar);
+    }
+});
@@ -0,0 +1,15 @@
+#![no_main]
+
+use ark_serialize::CanonicalDeserialize;
+use bulletproofs::r1cs::R1CSProof;
+use curve_trees_fuzz::{check_roundtrip, PallasA, VestaA};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(proof) = R1CSProof::<PallasA>::deserialize_compressed(data) {
+        check_roundtrip(&proof);
+    }
+    if let Ok(proof) = R1CSProof::<VestaA>::deserialize_compressed(data) {
+        check_roundtrip(&proof);
+    }
+});
@@ -0,0 +1,27 @@
+#![no_main]
+
+use ark_serialize::CanonicalDeserialize;
+use bulletproofs::r1cs::Verifier;
+use curve_trees_fuzz::{check_roundtrip, setup, FuzzPath, MEMBERSHIP_LABEL};
+use libfuzzer_sys::fuzz_target;
+use merlin::Transcript;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(path) = FuzzPath::deserialize_compressed(data) else {
+        return;
+    };
+    check_roundtrip(&path);
+
+    // The verifier gadget rejects exactly the paths of the wrong shape.
+    let setup = setup();
+    let valid = setup.tree.is_valid_path(&path);
+    let mut even_verifier = Verifier::new(Transcript::new(MEMBERSHIP_LABEL));
+    let mut odd_verifier = Verifier::new(Transcript::new(MEMBERSHIP_LABEL));
+    let rerandomized_leaf = setup.tree.select_and_rerandomize_verifier_gadget(
+        &mut even_verifier,
+        &mut odd_verifier,
+        path,
+        &setup.parameters,
+    );
+    assert_eq!(rerandomized_leaf.is_ok(), valid);
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+use ark_serialize::CanonicalDeserialize;
+use curve_trees_fuzz::{check_roundtrip, FuzzPour, FuzzSignedTx};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(tx) = FuzzSignedTx::deserialize_compressed(data) {
+        check_roundtrip(&tx);
+        if let Ok(pour) = FuzzPour::deserialize_compressed(tx.pour_bytes.as_slice()) {
+            check_roundtrip(&pour);
+        }
+    }
+    if let Ok(pour) = FuzzPour::deserialize_compressed(data) {
+        check_roundtrip(&pour);
+    }
+});
@@ -0,0 +1,25 @@
+#![no_main]
+
+//! The first byte selects the statement, the rest is either a serialized proof,
+//! or if it does not deserialize, the seed for a structurally valid proof.
+
+use ark_serialize::CanonicalDeserialize;
+use bulletproofs::r1cs::R1CSProof;
+use curve_trees_fuzz::{structured_proof, verify_membership, verify_range, Input, PallasA, VestaA};
+use libfuzzer_sys::fuzz_target;
+
+fn proof<C: ark_ec::AffineRepr>(data: &[u8]) -> R1CSProof<C> {
+    R1CSProof::deserialize_compressed(data)
+        .unwrap_or_else(|_| structured_proof(&mut Input::new(data)))
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((selector, data)) = data.split_first() else {
+        return;
+    };
+    let _ = match selector % 3 {
+        0 => verify_range(&proof(data)),
+        1 => verify_membership(Some(&proof::<PallasA>(data)), None),
+        _ => verify_membership(None, Some(&proof::<VestaA>(data))),
+    };
+});
@@ -0,0 +1,119 @@
+//! Writes valid serialized artifacts to `corpus/<target>/` so fuzzing starts from meaningful inputs.
+//!
+//! Run from the `fuzz` directory with `cargo run --release --bin generate_corpus`.
+
+use std::fs;
+use std::path::Path;
+
+use ark_ec::AffineRepr;
+use ark_serialize::CanonicalSerialize;
+use bulletproofs::r1cs::Prover;
+use curve_trees_fuzz::{
+    prove_membership, setup, verify_membership, verify_range, PallasA, PallasConfig, PallasP,
+    VestaA, RANGE_BITS, RANGE_LABEL,
+};
+use merlin::Transcript;
+use rand::{rngs::StdRng, SeedableRng};
+use relations::coin::{prove_pour, Coin, SpendingInfo};
+use relations::range_proof::range_proof;
+
+/// Selects limits under which the proofs and transactions of the setup deserialize, see the `limits` target.
+const LIMITS_SELECTOR: u8 = 0xff;
+
+fn write<T: CanonicalSerialize>(target: &str, name: &str, prefix: &[u8], value: &T) {
+    let directory = Path::new("corpus").join(target);
+    fs::create_dir_all(&directory).unwrap();
+    let mut bytes = prefix.to_vec();
+    value.serialize_compressed(&mut bytes).unwrap();
+    fs::write(directory.join(name), bytes).unwrap();
+}
+
+fn main() {
+    let setup = setup();
+    let parameters = &setup.parameters;
+
+    write("points", "pallas_generator", &[], &PallasA::generator());
+    write("points", "pallas_identity", &[], &PallasA::zero());
+    write("points", "vesta_generator", &[], &VestaA::generator());
+    write("points", "leaf", &[], &setup.range_commitment);
+    write("points", "scalar", &[], &setup.range_blinding);
+
+    let range_proof_ = {
+        let mut prover: Prover<_, PallasA> = Prover::new(
+            &parameters.even_parameters.pc_gens,
+            Transcript::new(RANGE_LABEL),
+        );
+        let (_, variable) = prover.commit(setup.range_value.into(), setup.range_blinding);
+        range_proof(
+            &mut prover,
+            variable.into(),
+            Some(setup.range_value),
+            RANGE_BITS,
+        )
+        .unwrap();
+        prover.prove(&parameters.even_parameters.bp_gens).unwrap()
+    };
+    verify_range(&range_proof_).unwrap();
+    write("r1cs_proof", "range", &[], &range_proof_);
+    write("verify", "range", &[0], &range_proof_);
+
+    let (path, even_proof, odd_proof) = prove_membership(setup);
+    verify_membership(Some(&even_proof), Some(&odd_proof)).unwrap();
+    write("select_and_rerandomize_path", "membership", &[], &path);
+    write("limits", "membership", &[LIMITS_SELECTOR], &path);
+    write("r1cs_proof", "membership_even", &[], &even_proof);
+    write("r1cs_proof", "membership_odd", &[], &odd_proof);
+    write("verify", "membership_even", &[1], &even_proof);
+    write("verify", "membership_odd", &[2], &odd_proof);
+
+    let mut rng = StdRng::seed_from_u64(2);
+    let inputs: Vec<_> = setup
+        .coins
+        .iter()
+        .enumerate()
+        .map(|(index, coin)| SpendingInfo {
+            leaf: setup.tree.leaf_ref(index).unwrap(),
+            coin_aux: Coin {
+                value: coin.value,
+                tag: coin.tag,
+                commitment_blinding: coin.commitment_blinding.clone(),
+                pk_randomness: coin.pk_randomness.clone(),
+            },
+            randomized_pk: Coin::<PallasConfig, PallasP>::rerandomized_pk(
+                &setup.public_key,
+                &coin.pk_randomness.total(),
+                &setup.sig_parameters,
+            ),
+            sk: setup.secret_key.clone(),
+        })
+        .collect();
+    let tx = prove_pour(
+        Prover::new(
+            &parameters.even_parameters.pc_gens,
+            Transcript::new(b"curve_trees_fuzz_pour"),
+        ),
+        Prover::new(
+            &parameters.odd_parameters.pc_gens,
+            Transcript::new(b"curve_trees_fuzz_pour"),
+        ),
+        parameters,
+        &setup.layout,
+        &setup.tree,
+        &inputs[0],
+        &inputs[1],
+        11,
+        setup.public_key,
+        31,
+        setup.public_key,
+        &setup.sig_parameters,
+        &mut rng,
+    )
+    .unwrap();
+    write("transaction", "signed_tx", &[], &tx);
+    write("limits", "signed_tx", &[LIMITS_SELECTOR], &tx);
+    fs::write(
+        Path::new("corpus").join("transaction").join("pour"),
+        &tx.pour_bytes,
+    )
+    .unwrap();
+}
@@ -0,0 +1,269 @@
+//! Shared setup for the fuzz targets and the corpus generator.
+//!
+//! Everything is derived from fixed seeds, so the statements the `verify` target checks proofs against
+//! are the same in every run and the proofs written by `generate_corpus` verify against them.
+
+use std::sync::OnceLock;
+
+use ark_crypto_primitives::signature::{
+    schnorr::{Parameters, PublicKey, Schnorr, SecretKey},
+    SignatureScheme,
+};
+use ark_ec::{short_weierstrass::Affine, AffineRepr};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::UniformRand;
+use blake2::Blake2s256 as Blake2s;
+use bulletproofs::r1cs::{Prover, R1CSError, R1CSProof, Verifier};
+use merlin::Transcript;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use relations::coin::{Coin, Pour, SignedTx};
+use relations::curve_tree::{CurveTree, SelRerandParameters, SelectAndRerandomizePath};
+use relations::layout::CommitmentLayout;
+use relations::range_proof::range_proof;
+
+pub type PallasConfig = ark_pallas::PallasConfig;
+pub type VestaConfig = ark_vesta::VestaConfig;
+pub type PallasA = Affine<PallasConfig>;
+pub type PallasP = ark_pallas::Projective;
+pub type VestaA = Affine<VestaConfig>;
+
+/// A small branching factor keeps each verification cheap enough to fuzz.
+pub const BRANCHING_FACTOR: usize = 4;
+pub const HEIGHT: usize = 2;
+pub const RANGE_BITS: usize = 8;
+const GENERATORS_LENGTH: usize = 1 << 11;
+
+pub type FuzzCurveTree = CurveTree<BRANCHING_FACTOR, PallasConfig, VestaConfig>;
+pub type FuzzPath = SelectAndRerandomizePath<BRANCHING_FACTOR, PallasConfig, VestaConfig>;
+pub type FuzzPour = Pour<BRANCHING_FACTOR, PallasConfig, VestaConfig, PallasP>;
+pub type FuzzSignedTx = SignedTx<PallasConfig, VestaConfig, PallasP>;
+
+pub const RANGE_LABEL: &[u8] = b"curve_trees_fuzz_range";
+pub const MEMBERSHIP_LABEL: &[u8] = b"curve_trees_fuzz_membership";
+
+/// The statements proofs are verified against, and the secrets needed to prove them.
+pub struct Setup {
+    pub parameters: SelRerandParameters<PallasConfig, VestaConfig>,
+    pub sig_parameters: Parameters<PallasP, Blake2s>,
+    pub layout: CommitmentLayout,
+    pub public_key: PublicKey<PallasP>,
+    pub secret_key: SecretKey<PallasP>,
+    pub coins: Vec<Coin<PallasConfig, PallasP>>,
+    pub tree: FuzzCurveTree,
+    /// The rerandomized path to the first coin, see `prove_membership`.
+    pub path: FuzzPath,
+    pub range_value: u64,
+    pub range_blinding: ark_pallas::Fr,
+    pub range_commitment: PallasA,
+}
+
+pub fn setup() -> &'static Setup {
+    static SETUP: OnceLock<Setup> = OnceLock::new();
+    SETUP.get_or_init(|| {
+        let mut rng = StdRng::seed_from_u64(0);
+        let parameters = SelRerandParameters::new(GENERATORS_LENGTH, GENERATORS_LENGTH);
+        let sig_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let (public_key, secret_key) = Schnorr::keygen(&sig_parameters, &mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
+        let (coins, leaves): (Vec<_>, Vec<_>) = [19, 23]
+            .iter()
+            .map(|value| {
+                Coin::new(
+                    *value,
+                    &public_key,
+                    &sig_parameters,
+                    &parameters.even_parameters,
+                    &layout,
+                    &mut rng,
+                )
+                .unwrap()
+            })
+            .unzip();
+        let tree = CurveTree::from_set(&leaves, &parameters, Some(HEIGHT)).unwrap();
+        let range_value = rng.gen_range(0..1 << RANGE_BITS);
+        let range_blinding = ark_pallas::Fr::rand(&mut rng);
+        let range_commitment = parameters
+            .even_parameters
+            .pc_gens
+            .commit(range_value.into(), range_blinding);
+        let mut setup = Setup {
+            parameters,
+            sig_parameters,
+            layout,
+            public_key,
+            secret_key,
+            coins,
+            tree,
+            path: SelectAndRerandomizePath {
+                even_commitments: Vec::new(),
+                odd_commitments: Vec::new(),
+            },
+            range_value,
+            range_blinding,
+            range_commitment,
+        };
+        setup.path = prove_membership(&setup).0;
+        setup
+    })
+}
+
+/// Proves membership of the first coin, the path is the same on every call.
+pub fn prove_membership(setup: &Setup) -> (FuzzPath, R1CSProof<PallasA>, R1CSProof<VestaA>) {
+    let parameters = &setup.parameters;
+    let mut even_prover: Prover<_, PallasA> = Prover::new(
+        &parameters.even_parameters.pc_gens,
+        Transcript::new(MEMBERSHIP_LABEL),
+    );
+    let mut odd_prover: Prover<_, VestaA> = Prover::new(
+        &parameters.odd_parameters.pc_gens,
+        Transcript::new(MEMBERSHIP_LABEL),
+    );
+    let (path, _) = setup
+        .tree
+        .select_and_rerandomize_prover_gadget(
+            0,
+            &mut even_prover,
+            &mut odd_prover,
+            parameters,
+            &mut StdRng::seed_from_u64(1),
+        )
+        .unwrap();
+    let even_proof = even_prover
+        .prove(&parameters.even_parameters.bp_gens)
+        .unwrap();
+    let odd_proof = odd_prover
+        .prove(&parameters.odd_parameters.bp_gens)
+        .unwrap();
+    (path, even_proof, odd_proof)
+}
+
+/// Verifies a proof that `range_commitment` opens to a value of `RANGE_BITS` bits.
+pub fn verify_range(proof: &R1CSProof<PallasA>) -> Result<(), R1CSError> {
+    let setup = setup();
+    let even_parameters = &setup.parameters.even_parameters;
+    let mut verifier = Verifier::new(Transcript::new(RANGE_LABEL));
+    let variable = verifier.commit(setup.range_commitment);
+    range_proof(&mut verifier, variable.into(), None, RANGE_BITS)?;
+    verifier.verify(proof, &even_parameters.pc_gens, &even_parameters.bp_gens)
+}
+
+/// Verifies either half of a membership proof for `path`.
+/// On the odd curve the statement includes vector commitments, on the even curve it does not.
+pub fn verify_membership(
+    even_proof: Option<&R1CSProof<PallasA>>,
+    odd_proof: Option<&R1CSProof<VestaA>>,
+) -> Result<(), R1CSError> {
+    let setup = setup();
+    let parameters = &setup.parameters;
+    let mut even_verifier = Verifier::new(Transcript::new(MEMBERSHIP_LABEL));
+    let mut odd_verifier = Verifier::new(Transcript::new(MEMBERSHIP_LABEL));
+    let _rerandomized_leaf = setup
+        .tree
+        .select_and_rerandomize_verifier_gadget(
+            &mut even_verifier,
+            &mut odd_verifier,
+            setup.path.clone(),
+            parameters,
+        )
+        .expect("the path of the setup is a path of the tree");
+    if let Some(proof) = even_proof {
+        even_verifier.verify(
+            proof,
+            &parameters.even_parameters.pc_gens,
+            &parameters.even_parameters.bp_gens,
+        )?;
+    }
+    if let Some(proof) = odd_proof {
+        odd_verifier.verify(
+            proof,
+            &parameters.odd_parameters.pc_gens,
+            &parameters.odd_parameters.bp_gens,
+        )?;
+    }
+    Ok(())
+}
+
+/// Reads fuzzer input one byte at a time, yielding zeros once it is exhausted.
+pub struct Input<'a>(&'a [u8]);
+
+impl<'a> Input<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Input(data)
+    }
+
+    pub fn byte(&mut self) -> u8 {
+        match self.0.split_first() {
+            Some((byte, rest)) => {
+                self.0 = rest;
+                *byte
+            }
+            None => 0,
+        }
+    }
+
+    pub fn rng(&mut self) -> StdRng {
+        let mut seed = [0u8; 8];
+        seed.iter_mut().for_each(|byte| *byte = self.byte());
+        StdRng::seed_from_u64(u64::from_le_bytes(seed))
+    }
+}
+
+fn random_point<C: AffineRepr, R: Rng>(input: &mut Input, rng: &mut R) -> C {
+    // Mix in the identity, which the verifier treats specially.
+    if input.byte().is_multiple_of(8) {
+        C::zero()
+    } else {
+        C::rand(rng)
+    }
+}
+
+/// Builds a proof that deserializes but whose points, scalars and vector lengths are chosen by the fuzzer.
+pub fn structured_proof<C: AffineRepr>(input: &mut Input) -> R1CSProof<C> {
+    let mut rng = input.rng();
+    let mut bytes = Vec::new();
+    let mut points = |count: usize, input: &mut Input, bytes: &mut Vec<u8>| {
+        for _ in 0..count {
+            random_point::<C, _>(input, &mut rng)
+                .serialize_compressed(&mut *bytes)
+                .unwrap();
+        }
+    };
+
+    points(3, input, &mut bytes);
+    let two_phase = input.byte() % 2;
+    bytes.push(two_phase);
+    if two_phase == 1 {
+        points(3, input, &mut bytes);
+    }
+    let t_length = input.byte() % 16;
+    (t_length as u64).serialize_compressed(&mut bytes).unwrap();
+    points(t_length as usize, input, &mut bytes);
+    let mut scalar_rng = input.rng();
+    for _ in 0..3 {
+        C::ScalarField::rand(&mut scalar_rng)
+            .serialize_compressed(&mut bytes)
+            .unwrap();
+    }
+    for _ in 0..2 {
+        let length = input.byte() % 8;
+        (length as u64).serialize_compressed(&mut bytes).unwrap();
+        points(length as usize, input, &mut bytes);
+    }
+    for _ in 0..2 {
+        C::ScalarField::rand(&mut scalar_rng)
+            .serialize_compressed(&mut bytes)
+            .unwrap();
+    }
+    R1CSProof::deserialize_compressed(bytes.as_slice()).expect("The proof is well formed")
+}
+
+/// Checks that a deserialized value serializes back to bytes that deserialize to the same serialization.
+pub fn check_roundtrip<T: CanonicalSerialize + CanonicalDeserialize>(value: &T) {
+    let mut bytes = Vec::new();
+    value.serialize_compressed(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), value.compressed_size());
+    let decoded = T::deserialize_compressed(bytes.as_slice()).expect("Serialized values decode");
+    let mut reencoded = Vec::new();
+    decoded.serialize_compressed(&mut reencoded).unwrap();
+    assert_eq!(bytes, reencoded);
+}
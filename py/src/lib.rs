@@ -0,0 +1,223 @@
+//! Python bindings for building Curve Trees and proving and verifying membership with select-and-rerandomize.
+//!
+//! The bindings fix the curves to Pallas (even levels and leaves) and Vesta (odd levels),
+//! and the branching factor to `BRANCHING_FACTOR`.
+//! Points, scalars and proofs are passed as `bytes` holding their compressed canonical serializations.
+//! Failures raise `CurveTreesError` or one of its subclasses.
+
+// Triggered by the code `#[pymethods]` generates for `PyResult` returns
+#![allow(clippy::useless_conversion)]
+
+use ark_ec::short_weierstrass::Affine;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use relations::curve_tree::{CurveTree, MembershipProof, SelRerandParameters};
+use relations::cycle::EvenScalar;
+use relations::permissible::assert_permissible;
+
+type PallasConfig = ark_pallas::PallasConfig;
+type VestaConfig = ark_vesta::VestaConfig;
+type PallasA = Affine<PallasConfig>;
+type PallasScalar = ark_pallas::Fr;
+
+/// The branching factor of trees built through the bindings.
+pub const BRANCHING_FACTOR: usize = 256;
+/// The number of generators per curve, enough for trees of height up to 4.
+pub const GENERATORS_LENGTH: usize = 1 << 12;
+
+create_exception!(curve_trees_py, CurveTreesError, PyException);
+create_exception!(curve_trees_py, DeserializationError, CurveTreesError);
+create_exception!(curve_trees_py, ProvingError, CurveTreesError);
+create_exception!(curve_trees_py, VerificationError, CurveTreesError);
+
+fn deserialize<T: CanonicalDeserialize>(reader: &mut &[u8]) -> PyResult<T> {
+    T::deserialize_compressed(reader).map_err(|e| DeserializationError::new_err(e.to_string()))
+}
+
+fn deserialize_all<T: CanonicalDeserialize>(mut bytes: &[u8]) -> PyResult<T> {
+    let t = deserialize(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(DeserializationError::new_err("Trailing bytes"));
+    }
+    Ok(t)
+}
+
+fn to_bytes<'py, T: CanonicalSerialize>(py: Python<'py>, t: &T) -> Bound<'py, PyBytes> {
+    let mut bytes = Vec::with_capacity(t.compressed_size());
+    t.serialize_compressed(&mut bytes).unwrap();
+    PyBytes::new_bound(py, &bytes)
+}
+
+/// The generators of both curves, which depend on their labels alone.
+#[pyclass(frozen)]
+struct Parameters(SelRerandParameters<PallasConfig, VestaConfig>);
+
+#[pymethods]
+impl Parameters {
+    #[new]
+    fn new() -> Self {
+        Parameters(SelRerandParameters::new(
+            GENERATORS_LENGTH,
+            GENERATORS_LENGTH,
+        ))
+    }
+
+    /// Commits to the scalars `values` with `blinding`, offsetting the blinding until the commitment is permissible.
+    /// Returns the leaf and its final blinding.
+    fn leaf_commitment<'py>(
+        &self,
+        py: Python<'py>,
+        values: Vec<Vec<u8>>,
+        blinding: &[u8],
+    ) -> PyResult<(Bound<'py, PyBytes>, Bound<'py, PyBytes>)> {
+        if values.is_empty() || values.len() > BRANCHING_FACTOR {
+            return Err(CurveTreesError::new_err(format!(
+                "Expected between 1 and {} values",
+                BRANCHING_FACTOR
+            )));
+        }
+        let values = values
+            .iter()
+            .map(|v| deserialize_all(v))
+            .collect::<PyResult<Vec<PallasScalar>>>()?;
+        let blinding: PallasScalar = deserialize_all(blinding)?;
+        let (leaf, blinding) = self
+            .0
+            .even_parameters
+            .permissible_commitment(&values, blinding, 0)
+            .map_err(|e| CurveTreesError::new_err(e.to_string()))?;
+        Ok((to_bytes(py, &leaf), to_bytes(py, &blinding)))
+    }
+}
+
+/// A Curve Tree over permissible Pallas points.
+#[pyclass(frozen, name = "CurveTree")]
+struct PyCurveTree {
+    tree: CurveTree<BRANCHING_FACTOR, PallasConfig, VestaConfig>,
+    leaves: usize,
+}
+
+#[pymethods]
+impl PyCurveTree {
+    /// Builds a tree from compressed permissible points, padded to at least `height` if given.
+    #[new]
+    #[pyo3(signature = (parameters, leaves, height=None))]
+    fn new(parameters: &Parameters, leaves: Vec<Vec<u8>>, height: Option<usize>) -> PyResult<Self> {
+        if leaves.is_empty() {
+            return Err(CurveTreesError::new_err(
+                "The curve tree must have at least one leaf",
+            ));
+        }
+        let leaves = leaves
+            .iter()
+            .map(|leaf| deserialize_all(leaf))
+            .collect::<PyResult<Vec<PallasA>>>()?;
+        for leaf in &leaves {
+            assert_permissible(leaf, &parameters.0.even_parameters)
+                .map_err(|e| CurveTreesError::new_err(e.to_string()))?;
+        }
+        Ok(PyCurveTree {
+            tree: CurveTree::from_set(&leaves, &parameters.0, height)
+                .map_err(|e| CurveTreesError::new_err(e.to_string()))?,
+            leaves: leaves.len(),
+        })
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.tree.height()
+    }
+
+    /// The root of a tree of even height, trees of odd height have their root on Vesta and raise an error.
+    fn root<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        match self.tree {
+            CurveTree::Even(_) => {
+                let root = self
+                    .tree
+                    .root_node()
+                    .map_err(|e| CurveTreesError::new_err(e.to_string()))?;
+                Ok(to_bytes(py, &root))
+            }
+            CurveTree::Odd(_) => Err(CurveTreesError::new_err(
+                "Trees of odd height have their root on the odd curve",
+            )),
+        }
+    }
+
+    /// Proves that the leaf at `index` is in the tree and rerandomizes it, see `CurveTree::prove_membership`.
+    /// Returns the serialized `MembershipProof`, the rerandomized leaf, and the blinding of the rerandomized leaf.
+    fn prove<'py>(
+        &self,
+        py: Python<'py>,
+        parameters: &Parameters,
+        index: usize,
+        blinding: &[u8],
+    ) -> PyResult<(
+        Bound<'py, PyBytes>,
+        Bound<'py, PyBytes>,
+        Bound<'py, PyBytes>,
+    )> {
+        if index >= self.leaves {
+            return Err(CurveTreesError::new_err(format!(
+                "Index {} out of range for {} leaves",
+                index, self.leaves
+            )));
+        }
+        let blinding: PallasScalar = deserialize_all(blinding)?;
+
+        let (proof, rerandomized_blinding) = self
+            .tree
+            .prove_membership(
+                index,
+                EvenScalar(blinding),
+                &parameters.0,
+                &mut rand::thread_rng(),
+            )
+            .map_err(|e| ProvingError::new_err(e.to_string()))?;
+        Ok((
+            to_bytes(py, &proof),
+            to_bytes(py, &proof.rerandomized_leaf()),
+            to_bytes(py, &rerandomized_blinding.0),
+        ))
+    }
+
+    /// Verifies a proof produced by `prove`, see `MembershipProof::verify`, and returns the rerandomized leaf.
+    /// Raises `VerificationError` if the proof does not verify.
+    fn verify<'py>(
+        &self,
+        py: Python<'py>,
+        parameters: &Parameters,
+        proof: &[u8],
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let proof: MembershipProof<BRANCHING_FACTOR, PallasConfig, VestaConfig> =
+            deserialize_all(proof)?;
+        let rerandomized_leaf = proof
+            .verify(&self.tree, &parameters.0)
+            .map_err(|e| VerificationError::new_err(e.to_string()))?;
+        Ok(to_bytes(py, &rerandomized_leaf))
+    }
+}
+
+#[pymodule]
+fn curve_trees_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Parameters>()?;
+    m.add_class::<PyCurveTree>()?;
+    m.add(
+        "CurveTreesError",
+        m.py().get_type_bound::<CurveTreesError>(),
+    )?;
+    m.add(
+        "DeserializationError",
+        m.py().get_type_bound::<DeserializationError>(),
+    )?;
+    m.add("ProvingError", m.py().get_type_bound::<ProvingError>())?;
+    m.add(
+        "VerificationError",
+        m.py().get_type_bound::<VerificationError>(),
+    )?;
+    m.add("BRANCHING_FACTOR", BRANCHING_FACTOR)?;
+    Ok(())
+}
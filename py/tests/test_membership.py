@@ -0,0 +1,61 @@
+import os
+
+import pytest
+
+import curve_trees_py as ct
+
+
+def random_scalar():
+    # Scalars are 32 bytes little endian, clearing the top byte keeps them below the modulus
+    return os.urandom(31) + b"\x00"
+
+
+@pytest.fixture(scope="module")
+def parameters():
+    return ct.Parameters()
+
+
+@pytest.fixture(scope="module")
+def leaves(parameters):
+    return [
+        parameters.leaf_commitment([random_scalar(), random_scalar()], random_scalar())
+        for _ in range(4)
+    ]
+
+
+@pytest.fixture(scope="module")
+def tree(parameters, leaves):
+    return ct.CurveTree(parameters, [leaf for leaf, _ in leaves], height=2)
+
+
+def test_prove_verify_roundtrip(parameters, tree):
+    assert tree.height == 2
+    assert len(tree.root()) == 33
+
+    proof, rerandomized_leaf, blinding = tree.prove(parameters, 2, random_scalar())
+    assert len(rerandomized_leaf) == 33
+    assert len(blinding) == 32
+    assert tree.verify(parameters, proof) == rerandomized_leaf
+
+
+def test_tampered_proof_is_rejected(parameters, tree):
+    proof, _, _ = tree.prove(parameters, 1, random_scalar())
+    tampered = bytearray(proof)
+    tampered[-1] ^= 1
+    with pytest.raises(ct.CurveTreesError):
+        tree.verify(parameters, bytes(tampered))
+    with pytest.raises(ct.DeserializationError):
+        tree.verify(parameters, proof[:-1])
+    with pytest.raises(ct.DeserializationError):
+        tree.verify(parameters, proof + b"\x00")
+
+
+def test_invalid_inputs_raise(parameters, tree, leaves):
+    with pytest.raises(ct.CurveTreesError):
+        tree.prove(parameters, len(leaves), random_scalar())
+    with pytest.raises(ct.DeserializationError):
+        tree.prove(parameters, 0, b"\x00" * 31)
+    with pytest.raises(ct.DeserializationError):
+        ct.CurveTree(parameters, [b"\x01" * 33])
+    with pytest.raises(ct.CurveTreesError):
+        ct.CurveTree(parameters, [])
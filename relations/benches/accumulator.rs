@@ -70,7 +70,7 @@ fn bench_accumulator_with_parameters<
     let odd_generators_length = 1 << odd_generators_length_log_2;
 
     let sr_params =
-        SelRerandParameters::<P0, P1>::new(even_generators_length, odd_generators_length, &mut rng);
+        SelRerandParameters::<P0, P1>::new(even_generators_length, odd_generators_length);
 
     let leaf_elements: Vec<_> = (0..leaf_width)
         .map(|_| P0::ScalarField::rand(&mut rng))
@@ -78,10 +78,10 @@ fn bench_accumulator_with_parameters<
     let element = leaf_elements[0];
     // Unless all leafs of the curve tree are occupied, it would always be possible to open to zero by using an empty leaf.
     assert_ne!(element, P0::ScalarField::zero());
-    let leaf_commitment =
-        sr_params
-            .even_parameters
-            .commit(&leaf_elements, P0::ScalarField::zero(), 0);
+    let leaf_commitment = sr_params
+        .even_parameters
+        .commit(&leaf_elements, P0::ScalarField::zero(), 0)
+        .unwrap();
 
     let (permissible_point, permissible_randomness) =
         sr_params.even_parameters.uh.permissible_commitment(
@@ -89,7 +89,7 @@ fn bench_accumulator_with_parameters<
             &sr_params.even_parameters.pc_gens.B_blinding,
         );
     let set = vec![permissible_point];
-    let curve_tree = CurveTree::<L, P0, P1>::from_set(&set, &sr_params, Some(depth));
+    let curve_tree = CurveTree::<L, P0, P1>::from_set(&set, &sr_params, Some(depth)).unwrap();
 
     let prove = |print| {
         let pallas_transcript = Transcript::new(b"acc");
@@ -100,28 +100,31 @@ fn bench_accumulator_with_parameters<
         let mut vesta_prover: Prover<_, Affine<P1>> =
             Prover::new(&sr_params.odd_parameters.pc_gens, vesta_transcript);
 
-        let (path, rerandomization) = curve_tree.select_and_rerandomize_prover_gadget(
-            0,
-            &mut pallas_prover,
-            &mut vesta_prover,
-            &sr_params,
-            &mut rand::thread_rng(),
-        );
+        let (path, rerandomization) = curve_tree
+            .select_and_rerandomize_prover_gadget(
+                0,
+                &mut pallas_prover,
+                &mut vesta_prover,
+                &sr_params,
+                &mut rand::thread_rng(),
+            )
+            .unwrap();
 
         let (leaf_commitment, leaf_vars) = pallas_prover.commit_vec(
             &leaf_elements,
-            permissible_randomness + rerandomization,
+            permissible_randomness + rerandomization.0,
             &sr_params.even_parameters.bp_gens,
         );
         assert_eq!(leaf_commitment, path.get_rerandomized_leaf()); // sanity check
 
+        let leaf_lcs: Vec<_> = leaf_vars
+            .iter()
+            .map(|var| LinearCombination::from(*var))
+            .collect();
         select(
             &mut pallas_prover,
-            LinearCombination::from(element),
-            leaf_vars
-                .iter()
-                .map(|var| LinearCombination::from(*var))
-                .collect(),
+            &LinearCombination::from(element),
+            &leaf_lcs,
         );
 
         if print {
@@ -170,6 +173,37 @@ fn bench_accumulator_with_parameters<
         group.bench_function("prover", |b| b.iter(|| prove(false)));
     }
 
+    let verification_tuples = |path: &SelectAndRerandomizePath<L, P0, P1>| {
+        let mut pallas_verifier = Verifier::new(Transcript::new(b"acc"));
+        let mut vesta_verifier = Verifier::new(Transcript::new(b"acc"));
+        let rerandomized_leaf = curve_tree
+            .select_and_rerandomize_verifier_gadget(
+                &mut pallas_verifier,
+                &mut vesta_verifier,
+                path.clone(),
+                &sr_params,
+            )
+            .unwrap();
+        let leaf_vars = pallas_verifier.commit_vec(leaf_width, rerandomized_leaf);
+        let leaf_lcs: Vec<_> = leaf_vars
+            .iter()
+            .map(|var| LinearCombination::from(*var))
+            .collect();
+        select(
+            &mut pallas_verifier,
+            &LinearCombination::from(element),
+            &leaf_lcs,
+        );
+        (
+            pallas_verifier
+                .verification_scalars_and_points(&pallas_proof)
+                .unwrap(),
+            vesta_verifier
+                .verification_scalars_and_points(&vesta_proof)
+                .unwrap(),
+        )
+    };
+
     let group_name = format!("{}_batch_verification", &prefix_string);
     let mut group = c.benchmark_group(group_name);
     use std::iter;
@@ -181,134 +215,37 @@ fn bench_accumulator_with_parameters<
             |b, proofs| {
                 b.iter(|| {
                     #[cfg(feature = "parallel")]
-                    {
-                        let srvs = proofs.par_iter().map(|path| {
-                            curve_tree.select_and_rerandomize_verification_commitments(path.clone())
-                        });
-                        let srvs_clone = srvs.clone();
-                        rayon::join(
-                            || {
-                                let pallas_verification_scalars_and_points: Vec<_> = srvs
-                                    .map(|srv| {
-                                        let pallas_transcript = Transcript::new(b"acc");
-                                        let mut pallas_verifier = Verifier::new(pallas_transcript);
-                                        srv.even_verifier_gadget(
-                                            &mut pallas_verifier,
-                                            &sr_params,
-                                            &curve_tree,
-                                        );
-                                        let leaf_vars = pallas_verifier
-                                            .commit_vec(leaf_width, path.get_rerandomized_leaf());
-                                        select(
-                                            &mut pallas_verifier,
-                                            LinearCombination::from(element),
-                                            leaf_vars
-                                                .iter()
-                                                .map(|var| LinearCombination::from(*var))
-                                                .collect(),
-                                        );
-                                        let pallas_vt = pallas_verifier
-                                            .verification_scalars_and_points(&pallas_proof)
-                                            .unwrap();
-                                        pallas_vt
-                                    })
-                                    .collect();
-                                batch_verify(
-                                    pallas_verification_scalars_and_points,
-                                    &sr_params.even_parameters.pc_gens,
-                                    &sr_params.even_parameters.bp_gens,
-                                )
-                                .unwrap()
-                            },
-                            || {
-                                let vesta_verification_scalars_and_points: Vec<_> = srvs_clone
-                                    .map(|srv| {
-                                        let vesta_transcript = Transcript::new(b"acc");
-                                        let mut vesta_verifier = Verifier::new(vesta_transcript);
-                                        srv.odd_verifier_gadget(
-                                            &mut vesta_verifier,
-                                            &sr_params,
-                                            &curve_tree,
-                                        );
-
-                                        let vesta_vt = vesta_verifier
-                                            .verification_scalars_and_points(&vesta_proof)
-                                            .unwrap();
-                                        vesta_vt
-                                    })
-                                    .collect();
-                                batch_verify(
-                                    vesta_verification_scalars_and_points,
-                                    &sr_params.odd_parameters.pc_gens,
-                                    &sr_params.odd_parameters.bp_gens,
-                                )
-                                .unwrap()
-                            },
+                    let (
+                        pallas_verification_scalars_and_points,
+                        vesta_verification_scalars_and_points,
+                    ): (Vec<_>, Vec<_>) = proofs.par_iter().map(verification_tuples).unzip();
+                    #[cfg(not(feature = "parallel"))]
+                    let (
+                        pallas_verification_scalars_and_points,
+                        vesta_verification_scalars_and_points,
+                    ): (Vec<_>, Vec<_>) = proofs.iter().map(verification_tuples).unzip();
+                    let verify_pallas = || {
+                        batch_verify(
+                            pallas_verification_scalars_and_points,
+                            &sr_params.even_parameters.pc_gens,
+                            &sr_params.even_parameters.bp_gens,
                         )
+                        .unwrap()
                     };
+                    let verify_vesta = || {
+                        batch_verify(
+                            vesta_verification_scalars_and_points,
+                            &sr_params.odd_parameters.pc_gens,
+                            &sr_params.odd_parameters.bp_gens,
+                        )
+                        .unwrap()
+                    };
+                    #[cfg(feature = "parallel")]
+                    rayon::join(verify_pallas, verify_vesta);
                     #[cfg(not(feature = "parallel"))]
                     {
-                        let srvs = proofs.iter().map(|path| {
-                            curve_tree.select_and_rerandomize_verification_commitments(path.clone())
-                        });
-                        let srvs_clone = srvs.clone();
-                        {
-                            let pallas_verification_scalars_and_points: Vec<_> = srvs
-                                .map(|srv| {
-                                    let pallas_transcript = Transcript::new(b"acc");
-                                    let mut pallas_verifier = Verifier::new(pallas_transcript);
-                                    srv.even_verifier_gadget(
-                                        &mut pallas_verifier,
-                                        &sr_params,
-                                        &curve_tree,
-                                    );
-                                    let leaf_vars = pallas_verifier
-                                        .commit_vec(leaf_width, path.get_rerandomized_leaf());
-                                    select(
-                                        &mut pallas_verifier,
-                                        LinearCombination::from(element),
-                                        leaf_vars
-                                            .iter()
-                                            .map(|var| LinearCombination::from(*var))
-                                            .collect(),
-                                    );
-                                    let pallas_vt = pallas_verifier
-                                        .verification_scalars_and_points(&pallas_proof)
-                                        .unwrap();
-                                    pallas_vt
-                                })
-                                .collect();
-                            batch_verify(
-                                pallas_verification_scalars_and_points,
-                                &sr_params.even_parameters.pc_gens,
-                                &sr_params.even_parameters.bp_gens,
-                            )
-                            .unwrap()
-                        };
-                        {
-                            let vesta_verification_scalars_and_points: Vec<_> = srvs_clone
-                                .map(|srv| {
-                                    let vesta_transcript = Transcript::new(b"acc");
-                                    let mut vesta_verifier = Verifier::new(vesta_transcript);
-                                    srv.odd_verifier_gadget(
-                                        &mut vesta_verifier,
-                                        &sr_params,
-                                        &curve_tree,
-                                    );
-
-                                    let vesta_vt = vesta_verifier
-                                        .verification_scalars_and_points(&vesta_proof)
-                                        .unwrap();
-                                    vesta_vt
-                                })
-                                .collect();
-                            batch_verify(
-                                vesta_verification_scalars_and_points,
-                                &sr_params.odd_parameters.pc_gens,
-                                &sr_params.odd_parameters.bp_gens,
-                            )
-                            .unwrap()
-                        };
+                        verify_pallas();
+                        verify_vesta();
                     }
                 })
             },
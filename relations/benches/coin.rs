@@ -16,6 +16,8 @@ extern crate relations;
 use merlin::Transcript;
 use relations::coin::*;
 use relations::curve_tree::*;
+use relations::layout::CommitmentLayout;
+use relations::Strictness;
 
 use ark_pallas::{Fq as PallasBase, PallasConfig};
 use ark_vesta::VestaConfig;
@@ -117,49 +119,53 @@ fn bench_pour_with_parameters<
     let mut rng = rand::thread_rng();
     let generators_length = 1 << generators_length_log_2; // minimum sufficient power of 2
 
-    let sr_params =
-        SelRerandParameters::<P0, P1>::new(generators_length, generators_length, &mut rng);
+    let sr_params = SelRerandParameters::<P0, P1>::new(generators_length, generators_length);
 
     let schnorr_parameters = Schnorr::<Projective<P0>, Blake2s>::setup(&mut rng).unwrap();
     let (pk, sk) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+    let layout = CommitmentLayout::coin();
 
     let (coin_aux_0, coin_0) = Coin::<P0, Projective<P0>>::new(
         19,
         &pk,
         &schnorr_parameters,
         &sr_params.even_parameters,
+        &layout,
         &mut rng,
-    );
+    )
+    .unwrap();
     let (coin_aux_1, coin_1) = Coin::<P0, Projective<P0>>::new(
         23,
         &pk,
         &schnorr_parameters,
         &sr_params.even_parameters,
+        &layout,
         &mut rng,
-    );
+    )
+    .unwrap();
     // Curve tree with two coins
     let set = vec![coin_0, coin_1];
-    let curve_tree = CurveTree::<L, P0, P1>::from_set(&set, &sr_params, Some(depth));
+    let curve_tree = CurveTree::<L, P0, P1>::from_set(&set, &sr_params, Some(depth)).unwrap();
 
     let randomized_pk_0 = Coin::<P0, Projective<P0>>::rerandomized_pk(
         &pk,
-        &coin_aux_0.pk_randomness,
+        &coin_aux_0.pk_randomness.total(),
         &schnorr_parameters,
     );
     let input0 = SpendingInfo {
         coin_aux: coin_aux_0,
-        index: 0,
+        leaf: curve_tree.leaf_ref(0).unwrap(),
         randomized_pk: randomized_pk_0,
         sk: sk.clone(),
     };
     let randomized_pk_1 = Coin::<P0, Projective<P0>>::rerandomized_pk(
         &pk,
-        &coin_aux_1.pk_randomness,
+        &coin_aux_1.pk_randomness.total(),
         &schnorr_parameters,
     );
     let input1 = SpendingInfo {
         coin_aux: coin_aux_1,
-        index: 1,
+        leaf: curve_tree.leaf_ref(1).unwrap(),
         randomized_pk: randomized_pk_1,
         sk: sk,
     };
@@ -179,6 +185,7 @@ fn bench_pour_with_parameters<
             pallas_prover,
             vesta_prover,
             &sr_params,
+            &layout,
             &curve_tree,
             &input0,
             &input1,
@@ -189,6 +196,7 @@ fn bench_pour_with_parameters<
             &schnorr_parameters,
             &mut rand::thread_rng(),
         )
+        .unwrap()
     };
     let tx = prove();
     let pour_proof =
@@ -199,6 +207,7 @@ fn bench_pour_with_parameters<
         &prefix_string,
         tx.serialized_size(Compress::Yes)
     );
+    println!("{}\n", tx.size_report::<L>().unwrap());
 
     {
         let mut group = c.benchmark_group(&prefix_string);
@@ -209,12 +218,15 @@ fn bench_pour_with_parameters<
         #[cfg(feature = "detailed_benchmarks")]
         group.bench_function("verification_gadget", |b| {
             b.iter(|| {
-                tx.clone().verification_gadget(
-                    b"select_and_rerandomize",
-                    &sr_params,
-                    &curve_tree,
-                    &schnorr_parameters,
-                );
+                tx.clone()
+                    .verification_gadget(
+                        b"select_and_rerandomize",
+                        &sr_params,
+                        &layout,
+                        &curve_tree,
+                        &schnorr_parameters,
+                    )
+                    .unwrap();
             })
         });
         #[cfg(feature = "detailed_benchmarks")]
@@ -228,12 +240,16 @@ fn bench_pour_with_parameters<
         #[cfg(feature = "detailed_benchmarks")]
         group.bench_function("verify_single_with_deserialization", |b| {
             b.iter(|| {
-                let (pallas_vt, vesta_vt) = tx.clone().verification_gadget(
-                    b"select_and_rerandomize",
-                    &sr_params,
-                    &curve_tree,
-                    &schnorr_parameters,
-                );
+                let (pallas_vt, vesta_vt) = tx
+                    .clone()
+                    .verification_gadget(
+                        b"select_and_rerandomize",
+                        &sr_params,
+                        &layout,
+                        &curve_tree,
+                        &schnorr_parameters,
+                    )
+                    .unwrap();
 
                 batch_verify(
                     vec![pallas_vt],
@@ -268,11 +284,14 @@ fn bench_pour_with_parameters<
                             Vec::with_capacity(proofs.len());
                         for proof in proofs {
                             let p = proof.clone();
-                            let (pallas_vt, vesta_vt) = p.verification_gadget(
-                                b"select_and_rerandomize",
-                                &sr_params,
-                                &curve_tree,
-                            );
+                            let (pallas_vt, vesta_vt) = p
+                                .verification_gadget(
+                                    b"select_and_rerandomize",
+                                    &sr_params,
+                                    &layout,
+                                    &curve_tree,
+                                )
+                                .unwrap();
 
                             pallas_verification_scalars_and_points.push(pallas_vt);
                             vesta_verification_scalars_and_points.push(vesta_vt);
@@ -292,56 +311,39 @@ fn bench_pour_with_parameters<
                     }
                     #[cfg(feature = "parallel")]
                     {
-                        let proofs_and_commitment_paths = proofs.par_iter().map(|proof| {
-                            let cp0 = curve_tree.select_and_rerandomize_verification_commitments(
-                                proof.clone().randomized_path_0.clone(),
-                            );
-                            let cp1 = curve_tree.select_and_rerandomize_verification_commitments(
-                                proof.clone().randomized_path_1.clone(),
-                            );
-                            (proof, cp0, cp1)
-                        });
-                        let proofs_and_commitment_paths_clone = proofs_and_commitment_paths.clone();
+                        let (
+                            pallas_verification_scalars_and_points,
+                            vesta_verification_scalars_and_points,
+                        ): (Vec<_>, Vec<_>) = proofs
+                            .par_iter()
+                            .map(|proof| {
+                                proof
+                                    .clone()
+                                    .verification_gadget(
+                                        b"select_and_rerandomize",
+                                        &sr_params,
+                                        &layout,
+                                        &curve_tree,
+                                    )
+                                    .unwrap()
+                            })
+                            .unzip();
                         rayon::join(
                             || {
-                                // even verification tuples
-                                let event_vts: Vec<_> = proofs_and_commitment_paths
-                                    .map(|(proof, path0, path1)| {
-                                        proof.even_verification_gadget(
-                                            b"select_and_rerandomize",
-                                            &sr_params,
-                                            &path0,
-                                            &path1,
-                                            &curve_tree,
-                                        )
-                                    })
-                                    .collect();
                                 batch_verify(
-                                    event_vts,
+                                    pallas_verification_scalars_and_points,
                                     &sr_params.even_parameters.pc_gens,
                                     &sr_params.even_parameters.bp_gens,
                                 )
-                                .unwrap();
+                                .unwrap()
                             },
                             || {
-                                // odd verification tuples
-                                let odd_vts: Vec<_> = proofs_and_commitment_paths_clone
-                                    .map(|(proof, path0, path1)| {
-                                        proof.odd_verification_gadget(
-                                            b"select_and_rerandomize",
-                                            &sr_params,
-                                            &path0,
-                                            &path1,
-                                            &curve_tree,
-                                        )
-                                    })
-                                    .collect();
                                 batch_verify(
-                                    odd_vts,
+                                    vesta_verification_scalars_and_points,
                                     &sr_params.odd_parameters.pc_gens,
                                     &sr_params.odd_parameters.bp_gens,
                                 )
-                                .unwrap();
+                                .unwrap()
                             },
                         )
                     }
@@ -351,11 +353,144 @@ fn bench_pour_with_parameters<
     }
 }
 
+fn bench_mint_burst(c: &mut Criterion) {
+    const OUTPUTS: u64 = 100;
+    let mut rng = rand::thread_rng();
+    let sr_params = SelRerandParameters::<PallasConfig, VestaConfig>::new(1 << 8, 1 << 8);
+    let schnorr_parameters = Schnorr::<Projective<PallasConfig>, Blake2s>::setup(&mut rng).unwrap();
+    let layout = CommitmentLayout::coin();
+    let outputs: Vec<_> = (0..OUTPUTS)
+        .map(|value| {
+            let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+            (value, pk)
+        })
+        .collect();
+
+    let mut group = c.benchmark_group(format!("Mint_burst_Outputs:{OUTPUTS}"));
+    group.bench_function("Sequential", |b| {
+        b.iter(|| {
+            outputs
+                .iter()
+                .map(|(value, pk)| {
+                    Coin::<PallasConfig, Projective<PallasConfig>>::new(
+                        *value,
+                        pk,
+                        &schnorr_parameters,
+                        &sr_params.even_parameters,
+                        &layout,
+                        &mut rng,
+                    )
+                    .unwrap()
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("Batch", |b| {
+        b.iter(|| {
+            Coin::<PallasConfig, Projective<PallasConfig>>::new_batch(
+                &outputs,
+                &schnorr_parameters,
+                &sr_params.even_parameters,
+                &layout,
+                &mut rng,
+            )
+            .unwrap()
+        })
+    });
+    group.finish();
+}
+
+// The cost of checking the witness of a spend before proving it, see `Strictness`.
+fn bench_spend_strictness(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let sr_params = SelRerandParameters::<PallasConfig, VestaConfig>::new(1 << 12, 1 << 12);
+    let schnorr_parameters = Schnorr::<Projective<PallasConfig>, Blake2s>::setup(&mut rng).unwrap();
+    let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+    let layout = CommitmentLayout::coin();
+    let (coin, commitment) = Coin::<PallasConfig, Projective<PallasConfig>>::new(
+        19,
+        &pk,
+        &schnorr_parameters,
+        &sr_params.even_parameters,
+        &layout,
+        &mut rng,
+    )
+    .unwrap();
+    let curve_tree =
+        CurveTree::<256, PallasConfig, VestaConfig>::from_set(&[commitment], &sr_params, Some(2))
+            .unwrap();
+    let opening = coin.opening();
+
+    let mut group = c.benchmark_group("Spend_strictness_L:256_D:2");
+    for strictness in [Strictness::Checked, Strictness::Fast] {
+        group.bench_function(format!("{strictness:?}"), |b| {
+            b.iter(|| {
+                let mut even_prover = Prover::new(
+                    &sr_params.even_parameters.pc_gens,
+                    Transcript::new(b"spend"),
+                );
+                let mut odd_prover =
+                    Prover::new(&sr_params.odd_parameters.pc_gens, Transcript::new(b"spend"));
+                opening
+                    .prove_spend_with_strictness(
+                        0,
+                        &mut even_prover,
+                        &mut odd_prover,
+                        &sr_params,
+                        &layout,
+                        &curve_tree,
+                        strictness,
+                    )
+                    .unwrap();
+                (
+                    even_prover
+                        .prove(&sr_params.even_parameters.bp_gens)
+                        .unwrap(),
+                    odd_prover.prove(&sr_params.odd_parameters.bp_gens).unwrap(),
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_signatures_batch(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let schnorr_parameters = Schnorr::<Projective<VestaConfig>, Blake2s>::setup(&mut rng).unwrap();
+    for count in [100u64, 1000] {
+        let messages: Vec<[u8; 8]> = (0..count).map(u64::to_le_bytes).collect();
+        let items: Vec<SignatureItem<Projective<VestaConfig>>> = messages
+            .iter()
+            .map(|msg| {
+                let (pk, sk) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+                let signature = Schnorr::sign(&schnorr_parameters, &sk, msg, &mut rng).unwrap();
+                (pk, msg.as_slice(), signature)
+            })
+            .collect();
+
+        let mut group = c.benchmark_group(format!("Signatures:{count}"));
+        group.bench_function("Sequential", |b| {
+            b.iter(|| {
+                assert!(items.iter().all(|(pk, msg, signature)| {
+                    Schnorr::verify(&schnorr_parameters, pk, msg, signature).unwrap()
+                }))
+            })
+        });
+        group.bench_function("Batch", |b| {
+            b.iter(|| verify_signatures_batch(&schnorr_parameters, &items).unwrap())
+        });
+        group.finish();
+    }
+}
+
 criterion_group! {
     name = pour;
     config = Criterion::default().sample_size(50);
     targets =
     bench_pour,
+    bench_mint_burst,
+    bench_spend_strictness,
+    bench_signatures_batch,
 }
 
 criterion_main!(pour);
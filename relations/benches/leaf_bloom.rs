@@ -0,0 +1,76 @@
+#[macro_use]
+extern crate criterion;
+use criterion::{BenchmarkId, Criterion};
+
+extern crate relations;
+use relations::curve_tree::*;
+
+use ark_ec::CurveGroup;
+use ark_pallas::{Fr as PallasScalar, PallasConfig};
+use ark_vesta::VestaConfig;
+
+use ark_std::UniformRand;
+
+const L: usize = 256;
+const LEAVES: usize = 100_000;
+// The commitments a wallet looks for, most of which are not leaves.
+const CANDIDATES: usize = 1000;
+const MEMBERS: usize = 10;
+
+// A wallet scanning a tree for the commitments it looks for: against every leaf,
+// or against the leaves of the commitments its bloom filter does not rule out.
+fn bench_leaf_bloom(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let sr_params = SelRerandParameters::<PallasConfig, VestaConfig>::new(1 << 12, 1 << 12);
+    let values: Vec<_> = (0..LEAVES)
+        .map(|_| [PallasScalar::rand(&mut rng)])
+        .collect();
+    let leaves: Vec<_> = sr_params
+        .even_parameters
+        .permissible_commitments(&values, 0)
+        .unwrap()
+        .into_iter()
+        .map(|(leaf, _)| leaf)
+        .collect();
+    let tree =
+        CurveTree::<L, PallasConfig, VestaConfig>::from_set(&leaves, &sr_params, None).unwrap();
+    let candidates: Vec<_> = leaves[..MEMBERS]
+        .iter()
+        .copied()
+        .chain((MEMBERS..CANDIDATES).map(|_| ark_pallas::Projective::rand(&mut rng).into_affine()))
+        .collect();
+    let scan = |candidate| leaves.iter().position(|leaf| *leaf == candidate);
+
+    let mut group = c.benchmark_group(format!("Leaf_bloom_Leaves:{LEAVES}"));
+    group.bench_function("Scan", |b| {
+        b.iter(|| candidates.iter().filter_map(|c| scan(*c)).count())
+    });
+    for bits_per_leaf in [4, 10] {
+        let bloom = tree.build_bloom(bits_per_leaf);
+        group.bench_with_input(
+            BenchmarkId::new("Scan_filtered", bits_per_leaf),
+            &bloom,
+            |b, bloom| {
+                b.iter(|| {
+                    candidates
+                        .iter()
+                        .filter(|c| bloom.maybe_contains(*c))
+                        .filter_map(|c| scan(*c))
+                        .count()
+                })
+            },
+        );
+    }
+    group.bench_function(BenchmarkId::new("Build", 10), |b| {
+        b.iter(|| tree.build_bloom(10))
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = leaf_bloom;
+    config = Criterion::default().sample_size(10);
+    targets = bench_leaf_bloom,
+}
+
+criterion_main!(leaf_bloom);
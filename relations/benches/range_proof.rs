@@ -0,0 +1,81 @@
+#[macro_use]
+extern crate criterion;
+use criterion::Criterion;
+
+extern crate bulletproofs;
+use bulletproofs::r1cs::{Prover, R1CSProof, Verifier};
+use bulletproofs::{BulletproofGens, PedersenGens};
+
+extern crate relations;
+use relations::range_proof::*;
+
+use ark_ec::AffineRepr;
+use ark_std::UniformRand;
+use merlin::Transcript;
+
+type VestaA = ark_vesta::Affine;
+type VestaScalar = <VestaA as AffineRepr>::ScalarField;
+
+fn bench_range_proof(c: &mut Criterion) {
+    bench_range_proof_with_parameters(c, 8, 64);
+}
+
+fn bench_range_proof_with_parameters(c: &mut Criterion, m: usize, n: usize) {
+    let mut rng = rand::thread_rng();
+    let pg = PedersenGens::default();
+    let bpg = BulletproofGens::new(2 * m * n, 1);
+    let values: Vec<u64> = (0..m).map(|_| u64::rand(&mut rng) >> (64 - n)).collect();
+    let scalars: Vec<VestaScalar> = values.iter().map(|v| VestaScalar::from(*v)).collect();
+
+    // One range proof over the elements of a single vector commitment.
+    let prove_vec = || {
+        let mut transcript = Transcript::new(b"range_proof_bench");
+        let mut prover: Prover<_, VestaA> = Prover::new(&pg, &mut transcript);
+        let (comm, vars) = prover.commit_vec(
+            scalars.as_slice(),
+            VestaScalar::rand(&mut rand::thread_rng()),
+            &bpg,
+        );
+        range_proof_vec(&mut prover, &vars, Some(&values), n).unwrap();
+        (prover.prove(&bpg).unwrap(), comm)
+    };
+
+    // A separate range proof call for each of the m values.
+    let prove_separate = || {
+        let mut transcript = Transcript::new(b"range_proof_bench");
+        let mut prover: Prover<_, VestaA> = Prover::new(&pg, &mut transcript);
+        let (comm, vars) = prover.commit_vec(
+            scalars.as_slice(),
+            VestaScalar::rand(&mut rand::thread_rng()),
+            &bpg,
+        );
+        for (var, value) in vars.iter().zip(values.iter()) {
+            range_proof(&mut prover, (*var).into(), Some(*value), n).unwrap();
+        }
+        (prover.prove(&bpg).unwrap(), comm)
+    };
+
+    let verify_vec = |proof: &R1CSProof<VestaA>, comm: VestaA| {
+        let mut transcript = Transcript::new(b"range_proof_bench");
+        let mut verifier = Verifier::new(&mut transcript);
+        let vars = verifier.commit_vec(m, comm);
+        range_proof_vec(&mut verifier, &vars, None, n).unwrap();
+        verifier.verify(proof, &pg, &bpg).unwrap()
+    };
+
+    let (proof, comm) = prove_vec();
+
+    let mut group = c.benchmark_group(format!("range_proof_m{}_n{}", m, n));
+    group.bench_function("prove_vec", |b| b.iter(prove_vec));
+    group.bench_function("prove_separate", |b| b.iter(prove_separate));
+    group.bench_function("verify_vec", |b| b.iter(|| verify_vec(&proof, comm)));
+}
+
+criterion_group! {
+    name = range_proofs;
+    config = Criterion::default().sample_size(50);
+    targets =
+    bench_range_proof,
+}
+
+criterion_main!(range_proofs);
@@ -8,6 +8,7 @@ use bulletproofs::r1cs::{batch_verify, Prover, Verifier};
 
 extern crate relations;
 use relations::curve_tree::*;
+use relations::size::SizeReport;
 
 use ark_pallas::{Fq as PallasBase, PallasConfig};
 use ark_vesta::VestaConfig;
@@ -116,8 +117,7 @@ fn bench_select_and_rerandomize_with_parameters<
     let mut rng = rand::thread_rng();
     let generators_length = 1 << generators_length_log_2;
 
-    let sr_params =
-        SelRerandParameters::<P0, P1>::new(generators_length, generators_length, &mut rng);
+    let sr_params = SelRerandParameters::<P0, P1>::new(generators_length, generators_length);
 
     let some_point = Affine::<P0>::rand(&mut rng);
     let (permissible_point, _) = sr_params
@@ -125,7 +125,7 @@ fn bench_select_and_rerandomize_with_parameters<
         .uh
         .permissible_commitment(&some_point, &sr_params.even_parameters.pc_gens.B_blinding);
     let set = vec![permissible_point];
-    let curve_tree = CurveTree::<L, P0, P1>::from_set(&set, &sr_params, Some(depth));
+    let curve_tree = CurveTree::<L, P0, P1>::from_set(&set, &sr_params, Some(depth)).unwrap();
 
     let prove = |print| {
         let pallas_transcript = Transcript::new(b"select_and_rerandomize");
@@ -136,13 +136,15 @@ fn bench_select_and_rerandomize_with_parameters<
         let mut vesta_prover: Prover<_, Affine<P1>> =
             Prover::new(&sr_params.odd_parameters.pc_gens, vesta_transcript);
 
-        let (path, _) = curve_tree.select_and_rerandomize_prover_gadget(
-            0,
-            &mut pallas_prover,
-            &mut vesta_prover,
-            &sr_params,
-            &mut rand::thread_rng(),
-        );
+        let (path, _) = curve_tree
+            .select_and_rerandomize_prover_gadget(
+                0,
+                &mut pallas_prover,
+                &mut vesta_prover,
+                &sr_params,
+                &mut rand::thread_rng(),
+            )
+            .unwrap();
         if print {
             println!(
                 "{}_Constraints: {}",
@@ -183,6 +185,59 @@ fn bench_select_and_rerandomize_with_parameters<
             + pallas_proof.serialized_size(Compress::Yes)
             + vesta_proof.serialized_size(Compress::Yes)
     );
+    let mut report = SizeReport::new();
+    report.add_serialized("path commitments", &path);
+    report.add_proof("even proof", "even proof rounds", &pallas_proof);
+    report.add_proof("odd proof", "odd proof rounds", &vesta_proof);
+    println!("{}\n", report);
+
+    // The verifiers of both curves build their constraint systems together,
+    // since the gadget binds their transcripts to each other.
+    let verification_tuples = |path: &SelectAndRerandomizePath<L, P0, P1>| {
+        let mut pallas_verifier = Verifier::new(Transcript::new(b"select_and_rerandomize"));
+        let mut vesta_verifier = Verifier::new(Transcript::new(b"select_and_rerandomize"));
+        let _rerandomized_leaf = curve_tree
+            .select_and_rerandomize_verifier_gadget(
+                &mut pallas_verifier,
+                &mut vesta_verifier,
+                path.clone(),
+                &sr_params,
+            )
+            .unwrap();
+        (
+            pallas_verifier
+                .verification_scalars_and_points(&pallas_proof)
+                .unwrap(),
+            vesta_verifier
+                .verification_scalars_and_points(&vesta_proof)
+                .unwrap(),
+        )
+    };
+    let batch_verify_tuples = |pallas_vts, vesta_vts| {
+        let verify_pallas = || {
+            batch_verify(
+                pallas_vts,
+                &sr_params.even_parameters.pc_gens,
+                &sr_params.even_parameters.bp_gens,
+            )
+            .unwrap()
+        };
+        let verify_vesta = || {
+            batch_verify(
+                vesta_vts,
+                &sr_params.odd_parameters.pc_gens,
+                &sr_params.odd_parameters.bp_gens,
+            )
+            .unwrap()
+        };
+        #[cfg(feature = "parallel")]
+        rayon::join(verify_pallas, verify_vesta);
+        #[cfg(not(feature = "parallel"))]
+        {
+            verify_pallas();
+            verify_vesta();
+        }
+    };
 
     {
         let mut group = c.benchmark_group(&prefix_string);
@@ -198,13 +253,15 @@ fn bench_select_and_rerandomize_with_parameters<
                 let mut vesta_prover: Prover<_, Affine<P1>> =
                     Prover::new(&sr_params.odd_parameters.pc_gens, vesta_transcript);
 
-                let (_path, _) = curve_tree.select_and_rerandomize_prover_gadget(
-                    0,
-                    &mut pallas_prover,
-                    &mut vesta_prover,
-                    &sr_params,
-                    &mut rng,
-                );
+                let (_path, _) = curve_tree
+                    .select_and_rerandomize_prover_gadget(
+                        0,
+                        &mut pallas_prover,
+                        &mut vesta_prover,
+                        &sr_params,
+                        &mut rng,
+                    )
+                    .unwrap();
             })
         });
 
@@ -215,18 +272,22 @@ fn bench_select_and_rerandomize_with_parameters<
         group.bench_function("verification_gadget", |b| {
             b.iter(|| {
                 // Common part
-                let srv = curve_tree.select_and_rerandomize_verification_commitments(path.clone());
+                let srv = curve_tree
+                    .select_and_rerandomize_verification_commitments(path.clone())
+                    .unwrap();
 
                 let even_verification_gadget = || {
                     let pallas_transcript = Transcript::new(b"select_and_rerandomize");
                     let mut pallas_verifier = Verifier::new(pallas_transcript);
-                    srv.even_verifier_gadget(&mut pallas_verifier, &sr_params, &curve_tree);
+                    srv.even_verifier_gadget(&mut pallas_verifier, &sr_params, &curve_tree)
+                        .unwrap();
                 };
 
                 let odd_verification_gadget = || {
                     let vesta_transcript = Transcript::new(b"select_and_rerandomize");
                     let mut vesta_verifier = Verifier::new(vesta_transcript);
-                    srv.odd_verifier_gadget(&mut vesta_verifier, &sr_params, &curve_tree);
+                    srv.odd_verifier_gadget(&mut vesta_verifier, &sr_params, &curve_tree)
+                        .unwrap();
                 };
 
                 #[cfg(not(feature = "parallel"))]
@@ -244,86 +305,14 @@ fn bench_select_and_rerandomize_with_parameters<
         #[cfg(feature = "detailed_benchmarks")]
         group.bench_function("verification_tuples", |b| {
             b.iter(|| {
-                // Common part
-                let srv = curve_tree.select_and_rerandomize_verification_commitments(path.clone());
-
-                let even_verification_gadget = || {
-                    let pallas_transcript = Transcript::new(b"select_and_rerandomize");
-                    let mut pallas_verifier = Verifier::new(pallas_transcript);
-                    srv.even_verifier_gadget(&mut pallas_verifier, &sr_params, &curve_tree);
-                    let _ = pallas_verifier
-                        .verification_scalars_and_points(&pallas_proof)
-                        .unwrap();
-                };
-
-                let odd_verification_gadget = || {
-                    let vesta_transcript = Transcript::new(b"select_and_rerandomize");
-                    let mut vesta_verifier = Verifier::new(vesta_transcript);
-                    srv.odd_verifier_gadget(&mut vesta_verifier, &sr_params, &curve_tree);
-                    let _ = vesta_verifier
-                        .verification_scalars_and_points(&vesta_proof)
-                        .unwrap();
-                };
-
-                #[cfg(not(feature = "parallel"))]
-                {
-                    even_verification_gadget();
-                    odd_verification_gadget()
-                }
-                #[cfg(feature = "parallel")]
-                {
-                    rayon::join(even_verification_gadget, odd_verification_gadget);
-                }
+                let _ = verification_tuples(&path);
             })
         });
         #[cfg(feature = "detailed_benchmarks")]
         group.bench_function("verify_single", |b| {
             b.iter(|| {
-                // Common part
-                let srv = curve_tree.select_and_rerandomize_verification_commitments(path.clone());
-
-                let even_verification_gadget = || {
-                    let pallas_transcript = Transcript::new(b"select_and_rerandomize");
-                    let mut pallas_verifier = Verifier::new(pallas_transcript);
-                    srv.even_verifier_gadget(&mut pallas_verifier, &sr_params, &curve_tree);
-                    let pallas_vt = pallas_verifier
-                        .verification_scalars_and_points(&pallas_proof)
-                        .unwrap();
-
-                    let res = batch_verify(
-                        vec![pallas_vt],
-                        &sr_params.even_parameters.pc_gens,
-                        &sr_params.even_parameters.bp_gens,
-                    );
-                    assert_eq!(res, Ok(()))
-                };
-
-                let odd_verification_gadget = || {
-                    let vesta_transcript = Transcript::new(b"select_and_rerandomize");
-                    let mut vesta_verifier = Verifier::new(vesta_transcript);
-                    srv.odd_verifier_gadget(&mut vesta_verifier, &sr_params, &curve_tree);
-
-                    let vesta_vt = vesta_verifier
-                        .verification_scalars_and_points(&vesta_proof)
-                        .unwrap();
-
-                    let res = batch_verify(
-                        vec![vesta_vt],
-                        &sr_params.odd_parameters.pc_gens,
-                        &sr_params.odd_parameters.bp_gens,
-                    );
-                    assert_eq!(res, Ok(()))
-                };
-
-                #[cfg(not(feature = "parallel"))]
-                {
-                    even_verification_gadget();
-                    odd_verification_gadget()
-                }
-                #[cfg(feature = "parallel")]
-                {
-                    rayon::join(even_verification_gadget, odd_verification_gadget);
-                }
+                let (pallas_vt, vesta_vt) = verification_tuples(&path);
+                batch_verify_tuples(vec![pallas_vt], vec![vesta_vt]);
             })
         });
     }
@@ -340,114 +329,19 @@ fn bench_select_and_rerandomize_with_parameters<
             |b, proofs| {
                 b.iter(|| {
                     #[cfg(feature = "parallel")]
-                    {
-                        let srvs = proofs.par_iter().map(|path| {
-                            curve_tree.select_and_rerandomize_verification_commitments(path.clone())
-                        });
-                        let srvs_clone = srvs.clone();
-                        rayon::join(
-                            || {
-                                let pallas_verification_scalars_and_points: Vec<_> = srvs
-                                    .map(|srv| {
-                                        let pallas_transcript =
-                                            Transcript::new(b"select_and_rerandomize");
-                                        let mut pallas_verifier = Verifier::new(pallas_transcript);
-                                        srv.even_verifier_gadget(
-                                            &mut pallas_verifier,
-                                            &sr_params,
-                                            &curve_tree,
-                                        );
-                                        let pallas_vt = pallas_verifier
-                                            .verification_scalars_and_points(&pallas_proof)
-                                            .unwrap();
-                                        pallas_vt
-                                    })
-                                    .collect();
-                                batch_verify(
-                                    pallas_verification_scalars_and_points,
-                                    &sr_params.even_parameters.pc_gens,
-                                    &sr_params.even_parameters.bp_gens,
-                                )
-                                .unwrap()
-                            },
-                            || {
-                                let vesta_verification_scalars_and_points: Vec<_> = srvs_clone
-                                    .map(|srv| {
-                                        let vesta_transcript =
-                                            Transcript::new(b"select_and_rerandomize");
-                                        let mut vesta_verifier = Verifier::new(vesta_transcript);
-                                        srv.odd_verifier_gadget(
-                                            &mut vesta_verifier,
-                                            &sr_params,
-                                            &curve_tree,
-                                        );
-
-                                        let vesta_vt = vesta_verifier
-                                            .verification_scalars_and_points(&vesta_proof)
-                                            .unwrap();
-                                        vesta_vt
-                                    })
-                                    .collect();
-                                batch_verify(
-                                    vesta_verification_scalars_and_points,
-                                    &sr_params.odd_parameters.pc_gens,
-                                    &sr_params.odd_parameters.bp_gens,
-                                )
-                                .unwrap()
-                            },
-                        )
-                    }
+                    let (
+                        pallas_verification_scalars_and_points,
+                        vesta_verification_scalars_and_points,
+                    ): (Vec<_>, Vec<_>) = proofs.par_iter().map(verification_tuples).unzip();
                     #[cfg(not(feature = "parallel"))]
-                    {
-                        let mut pallas_verification_scalars_and_points =
-                            Vec::with_capacity(proofs.len());
-                        let mut vesta_verification_scalars_and_points =
-                            Vec::with_capacity(proofs.len());
-                        for path in proofs {
-                            let srv = curve_tree
-                                .select_and_rerandomize_verification_commitments(path.clone());
-                            {
-                                let pallas_transcript = Transcript::new(b"select_and_rerandomize");
-                                let mut pallas_verifier = Verifier::new(pallas_transcript);
-                                srv.even_verifier_gadget(
-                                    &mut pallas_verifier,
-                                    &sr_params,
-                                    &curve_tree,
-                                );
-                                let pallas_vt = pallas_verifier
-                                    .verification_scalars_and_points(&pallas_proof)
-                                    .unwrap();
-                                pallas_verification_scalars_and_points.push(pallas_vt);
-                            }
-
-                            {
-                                let vesta_transcript = Transcript::new(b"select_and_rerandomize");
-                                let mut vesta_verifier = Verifier::new(vesta_transcript);
-                                srv.odd_verifier_gadget(
-                                    &mut vesta_verifier,
-                                    &sr_params,
-                                    &curve_tree,
-                                );
-
-                                let vesta_vt = vesta_verifier
-                                    .verification_scalars_and_points(&vesta_proof)
-                                    .unwrap();
-                                vesta_verification_scalars_and_points.push(vesta_vt);
-                            }
-                        }
-                        batch_verify(
-                            pallas_verification_scalars_and_points,
-                            &sr_params.even_parameters.pc_gens,
-                            &sr_params.even_parameters.bp_gens,
-                        )
-                        .unwrap();
-                        batch_verify(
-                            vesta_verification_scalars_and_points,
-                            &sr_params.odd_parameters.pc_gens,
-                            &sr_params.odd_parameters.bp_gens,
-                        )
-                        .unwrap()
-                    }
+                    let (
+                        pallas_verification_scalars_and_points,
+                        vesta_verification_scalars_and_points,
+                    ): (Vec<_>, Vec<_>) = proofs.iter().map(verification_tuples).unzip();
+                    batch_verify_tuples(
+                        pallas_verification_scalars_and_points,
+                        vesta_verification_scalars_and_points,
+                    );
                 })
             },
         );
@@ -0,0 +1,72 @@
+#[macro_use]
+extern crate criterion;
+use criterion::Criterion;
+
+extern crate relations;
+use relations::circuit::*;
+use relations::curve_tree::*;
+use relations::session::*;
+
+use ark_pallas::{Fr as PallasScalar, PallasConfig};
+use ark_std::UniformRand;
+use ark_vesta::VestaConfig;
+
+const L: usize = 256;
+const LABELS: [&[u8]; 4] = [
+    b"session_bench_0",
+    b"session_bench_1",
+    b"session_bench_2",
+    b"session_bench_3",
+];
+
+fn bench_session(c: &mut Criterion) {
+    bench_session_with_parameters(c, 8, 12);
+}
+
+// Proves `LABELS.len()` small range proofs in a row, each on its own and then forked from one session,
+// where the per-proof setup is a larger share of the cost.
+fn bench_session_with_parameters(c: &mut Criterion, bits: usize, generators_length_log_2: usize) {
+    let prefix_string = format!("Session_Proofs:{}_Range:{bits}", LABELS.len());
+    let mut rng = rand::thread_rng();
+    let parameters = SelRerandParameters::<PallasConfig, VestaConfig>::new(
+        1 << generators_length_log_2,
+        1 << generators_length_log_2,
+    );
+    let description = CircuitDescription::new(vec![CircuitStep::RangeProof { bits }]);
+    let witnesses = |rng: &mut _| -> Vec<StepWitness<L, PallasConfig, VestaConfig>> {
+        vec![StepWitness::RangeProof {
+            value: 1 << (bits - 1),
+            blinding: PallasScalar::rand(rng),
+        }]
+    };
+
+    let mut group = c.benchmark_group(&prefix_string);
+    group.bench_function("prove", |b| {
+        b.iter(|| {
+            for label in LABELS {
+                description
+                    .prove(label, &witnesses(&mut rng), &parameters, &mut rng)
+                    .unwrap();
+            }
+        })
+    });
+    group.bench_function("prove_in_session", |b| {
+        b.iter(|| {
+            let mut session = ProvingSession::new(&parameters);
+            for label in LABELS {
+                session
+                    .prove(label, &description, &witnesses(&mut rng), &mut rng)
+                    .unwrap();
+            }
+        })
+    });
+}
+
+criterion_group! {
+    name = session;
+    config = Criterion::default().sample_size(20);
+    targets =
+    bench_session,
+}
+
+criterion_main!(session);
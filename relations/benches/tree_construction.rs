@@ -0,0 +1,53 @@
+#[macro_use]
+extern crate criterion;
+use criterion::{BenchmarkId, Criterion};
+
+extern crate relations;
+use relations::curve_tree::*;
+
+use ark_pallas::{Fr as PallasScalar, PallasConfig};
+use ark_vesta::VestaConfig;
+
+use ark_std::UniformRand;
+
+// Trees over few leaves are mostly padding: most of the x-coordinates their nodes commit to are zero.
+fn bench_sparse_tree_construction(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let sr_params = SelRerandParameters::<PallasConfig, VestaConfig>::new(1 << 12, 1 << 12);
+    let leaves: Vec<_> = (0..1024)
+        .map(|_| {
+            sr_params
+                .even_parameters
+                .permissible_commitment(
+                    &[PallasScalar::rand(&mut rng)],
+                    PallasScalar::rand(&mut rng),
+                    0,
+                )
+                .unwrap()
+                .0
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("Tree_construction_L:256_D:4");
+    for count in [1, 32, 1024] {
+        group.bench_with_input(BenchmarkId::new("Leaves", count), &count, |b, &count| {
+            b.iter(|| {
+                CurveTree::<256, PallasConfig, VestaConfig>::from_set(
+                    &leaves[..count],
+                    &sr_params,
+                    Some(4),
+                )
+                .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = tree_construction;
+    config = Criterion::default().sample_size(10);
+    targets = bench_sparse_tree_construction,
+}
+
+criterion_main!(tree_construction);
@@ -0,0 +1,130 @@
+#[macro_use]
+extern crate criterion;
+use criterion::{BenchmarkId, Criterion};
+
+extern crate relations;
+use relations::curve_tree::*;
+use relations::cycle::EvenScalar;
+
+use ark_pallas::{Fr as PallasScalar, PallasConfig};
+use ark_vesta::VestaConfig;
+
+use ark_std::UniformRand;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const L: usize = 256;
+const LEAVES_LOG_2: usize = 18;
+
+// Counts the allocations and the bytes allocated and not yet freed, to compare the memory of the storages.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn bench_tree_storage(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let sr_params = SelRerandParameters::<PallasConfig, VestaConfig>::new(1 << 12, 1 << 12);
+    let values: Vec<_> = (0..1 << LEAVES_LOG_2)
+        .map(|_| [PallasScalar::rand(&mut rng)])
+        .collect();
+    let (leaves, blindings): (Vec<_>, Vec<_>) = sr_params
+        .even_parameters
+        .permissible_commitments(&values, 0)
+        .unwrap()
+        .into_iter()
+        .unzip();
+
+    let storages = [
+        ("Affine", PointStorage::Affine),
+        ("Compressed", PointStorage::Compressed),
+    ];
+    let trees: Vec<_> = storages
+        .iter()
+        .map(|(name, storage)| {
+            let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+            let live_bytes = LIVE_BYTES.load(Ordering::Relaxed);
+            let tree = CurveTree::<L, PallasConfig, VestaConfig>::from_set_with_storage(
+                &leaves, &sr_params, None, *storage,
+            )
+            .unwrap();
+            println!(
+                "{} storage of 2^{} leaves: {} allocations, {} MiB live",
+                name,
+                LEAVES_LOG_2,
+                ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+                (LIVE_BYTES.load(Ordering::Relaxed) - live_bytes) >> 20
+            );
+            tree
+        })
+        .collect();
+
+    let mut group = c.benchmark_group(format!("Tree_storage_L:{L}_Leaves:2^{LEAVES_LOG_2}"));
+    for ((name, storage), tree) in storages.iter().zip(&trees) {
+        group.bench_with_input(
+            BenchmarkId::new("Construction", name),
+            storage,
+            |b, storage| {
+                b.iter(|| {
+                    CurveTree::<L, PallasConfig, VestaConfig>::from_set_with_storage(
+                        &leaves, &sr_params, None, *storage,
+                    )
+                    .unwrap()
+                })
+            },
+        );
+        // Each witness in another subtree, so that the compressed tree decompresses the children of every level below the root's.
+        let mut index = 0;
+        group.bench_function(BenchmarkId::new("Witness", name), |b| {
+            b.iter(|| {
+                index = (index + L * L + 1) % leaves.len();
+                tree.select_and_rerandomize_prover_witness(index).unwrap()
+            })
+        });
+        // The same witness again, the compressed tree reads the children from its caches.
+        group.bench_function(BenchmarkId::new("Repeated_witness", name), |b| {
+            b.iter(|| tree.select_and_rerandomize_prover_witness(index).unwrap())
+        });
+        // Proving the membership of the same leaf again, reading its path from the tree or from a prepared witness.
+        group.bench_function(BenchmarkId::new("Prove_membership", name), |b| {
+            b.iter(|| {
+                tree.prove_membership(index, EvenScalar(blindings[index]), &sr_params, &mut rng)
+                    .unwrap()
+            })
+        });
+        let prepared = tree
+            .prepare_membership(index, EvenScalar(blindings[index]))
+            .unwrap();
+        group.bench_function(BenchmarkId::new("Prove_membership_prepared", name), |b| {
+            b.iter(|| {
+                tree.prove_membership_prepared(&prepared, &sr_params, &mut rng)
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = tree_storage;
+    config = Criterion::default().sample_size(10);
+    targets = bench_tree_storage,
+}
+
+criterion_main!(tree_storage);
@@ -0,0 +1,130 @@
+#[macro_use]
+extern crate criterion;
+use criterion::Criterion;
+
+extern crate relations;
+use relations::circuit::*;
+use relations::curve_tree::*;
+
+use ark_pallas::{Fr as PallasScalar, PallasConfig};
+use ark_std::UniformRand;
+use ark_vesta::VestaConfig;
+use std::time::{Duration, Instant};
+
+const L: usize = 256;
+const LABEL: &[u8] = b"verification_context_bench";
+
+fn bench_verification_context(c: &mut Criterion) {
+    bench_verification_context_with_parameters(c, 3, 64, 12);
+}
+
+fn bench_verification_context_with_parameters(
+    c: &mut Criterion,
+    height: usize,
+    bits: usize,
+    generators_length_log_2: usize,
+) {
+    let prefix_string = format!("VerificationContext_L:{L}_D:{height}_Range:{bits}");
+    let mut rng = rand::thread_rng();
+    let parameters = SelRerandParameters::<PallasConfig, VestaConfig>::new(
+        1 << generators_length_log_2,
+        1 << generators_length_log_2,
+    );
+    let (leaf, _) = parameters
+        .even_parameters
+        .permissible_commitment(
+            &[PallasScalar::rand(&mut rng)],
+            PallasScalar::rand(&mut rng),
+            0,
+        )
+        .unwrap();
+    let tree =
+        CurveTree::<L, PallasConfig, VestaConfig>::from_set(&[leaf], &parameters, Some(height))
+            .unwrap();
+    let description = CircuitDescription::new(vec![
+        CircuitStep::SelectAndRerandomize {
+            branching_factor: L,
+            height,
+        },
+        CircuitStep::RangeProof { bits },
+    ]);
+    let (proof, statements) = description
+        .prove(
+            LABEL,
+            &[
+                StepWitness::SelectAndRerandomize {
+                    tree: &tree,
+                    index: 0,
+                },
+                StepWitness::RangeProof {
+                    value: 1 << (bits - 1),
+                    blinding: PallasScalar::rand(&mut rng),
+                },
+            ],
+            &parameters,
+            &mut rng,
+        )
+        .unwrap();
+    let path = match &statements[0] {
+        StepStatement::SelectAndRerandomize { path, .. } => path.clone(),
+        _ => unreachable!("the first step selects and rerandomizes"),
+    };
+    let commitment = match &statements[1] {
+        StepStatement::RangeProof { commitment } => *commitment,
+        _ => unreachable!("the second step is a range proof"),
+    };
+    let statements = || {
+        vec![
+            StepStatement::SelectAndRerandomize {
+                path: path.clone(),
+                rerandomization: None,
+            },
+            StepStatement::RangeProof { commitment },
+        ]
+    };
+
+    let start = Instant::now();
+    let context =
+        SelRerandVerificationContext::<L, _, _>::new(&parameters, description.clone()).unwrap();
+    println!("{}_Setup: {:?}", &prefix_string, start.elapsed());
+
+    let verify = || {
+        description
+            .verify(LABEL, statements(), &[&tree], &proof, &parameters)
+            .unwrap()
+    };
+    let verify_with_context = || {
+        context
+            .verify(LABEL, statements(), &[&tree], &proof)
+            .unwrap()
+    };
+
+    // The per proof speedup of verifying single proofs, each the first proof of its verifier.
+    let time = |verify: &dyn Fn()| {
+        (0..10)
+            .map(|_| {
+                let start = Instant::now();
+                verify();
+                start.elapsed()
+            })
+            .sum::<Duration>()
+    };
+    println!(
+        "{}_Speedup: {:.2}\n",
+        &prefix_string,
+        time(&verify).as_secs_f64() / time(&verify_with_context).as_secs_f64()
+    );
+
+    let mut group = c.benchmark_group(&prefix_string);
+    group.bench_function("verify", |b| b.iter(verify));
+    group.bench_function("verify_with_context", |b| b.iter(verify_with_context));
+}
+
+criterion_group! {
+    name = verification_context;
+    config = Criterion::default().sample_size(20);
+    targets =
+    bench_verification_context,
+}
+
+criterion_main!(verification_context);
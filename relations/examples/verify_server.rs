@@ -0,0 +1,282 @@
+//! A verification server: transactions arrive serialized on a channel, are grouped into batches by format version,
+//! and the batches are verified on a pool of worker threads in the verification context of their version,
+//! reporting the time each batch took and the indices of the transactions that fail:
+//!
+//! ```text
+//! cargo run --release --example verify_server
+//! ```
+//!
+//! A prover thread stands for the network: it proves pours and sends them, a few with a forged signature
+//! or an unknown version byte. The queues between the threads are bounded, so a prover faster than the verifiers
+//! blocks instead of filling the memory of the server. The grouping and the verification of the batches
+//! are those of `relations::batching`, this example only wires them to threads.
+
+extern crate relations;
+
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread;
+use std::time::Instant;
+
+use ark_crypto_primitives::signature::schnorr::{Parameters, PublicKey, Schnorr};
+use ark_crypto_primitives::signature::SignatureScheme;
+use ark_pallas::PallasConfig;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_vesta::VestaConfig;
+use blake2::Blake2s256 as Blake2s;
+use bulletproofs::r1cs::Prover;
+use merlin::Transcript;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use relations::batching::{version_of, Batch, BatchReport, Batcher, Router};
+use relations::coin::*;
+use relations::curve_tree::*;
+use relations::layout::CommitmentLayout;
+use relations::version::FormatVersion;
+use relations::Error;
+
+type PallasP = ark_pallas::Projective;
+type Tree = CurveTree<L, PallasConfig, VestaConfig>;
+type Tx = SignedTx<PallasConfig, VestaConfig, PallasP>;
+
+const L: usize = 32;
+const HEIGHT: usize = 2;
+const GENERATORS_LENGTH: usize = 1 << 12;
+const LABEL: &[u8] = b"verify_server";
+// The pours sent by the prover, each spending two fresh coins.
+const TRANSACTIONS: usize = 12;
+// The most transactions of a batch, and of each queue: transactions received, and batches to verify.
+const MAX_BATCH_SIZE: usize = 4;
+const TRANSACTION_QUEUE: usize = 4;
+const BATCH_QUEUE: usize = 2;
+const WORKERS: usize = 4;
+
+/// What the transactions of a format version are verified with, prepared once and shared by the workers.
+struct VerificationContext<'a> {
+    parameters: &'a SelRerandParameters<PallasConfig, VestaConfig>,
+    sig_parameters: &'a Parameters<PallasP, Blake2s>,
+    layout: CommitmentLayout,
+    tree: &'a Tree,
+}
+
+impl VerificationContext<'_> {
+    fn verify_batch(&self, transactions: &[Vec<u8>]) -> Result<(), Error> {
+        let txs = transactions
+            .iter()
+            .map(|bytes| Tx::deserialize_compressed(bytes.as_slice()))
+            .collect::<Result<Vec<_>, _>>()?;
+        verify_transactions_batched(
+            &txs,
+            LABEL,
+            self.parameters,
+            &self.layout,
+            self.tree,
+            self.sig_parameters,
+        )
+    }
+
+    fn verify_one(&self, bytes: &[u8]) -> Result<(), Error> {
+        Tx::deserialize_compressed(bytes)?.verify(
+            LABEL,
+            self.parameters,
+            &self.layout,
+            self.tree,
+            self.sig_parameters,
+        )?;
+        Ok(())
+    }
+}
+
+/// The chain the pours spend from: a tree of coins minted to a single key, two per pour.
+struct Chain {
+    pk: PublicKey<PallasP>,
+    parameters: SelRerandParameters<PallasConfig, VestaConfig>,
+    sig_parameters: Parameters<PallasP, Blake2s>,
+    tree: Tree,
+    inputs: Vec<SpendingInfo<PallasConfig, PallasP>>,
+}
+
+impl Chain {
+    fn new(rng: &mut StdRng) -> Result<Self, Error> {
+        let parameters = SelRerandParameters::new(GENERATORS_LENGTH, GENERATORS_LENGTH);
+        let sig_parameters = Schnorr::<PallasP, Blake2s>::setup(rng).expect("setup does not fail");
+        let (pk, sk) = Schnorr::keygen(&sig_parameters, rng).expect("keygen does not fail");
+        let layout = CommitmentLayout::coin();
+        let mut leaves = Vec::new();
+        let mut coins = Vec::new();
+        for _ in 0..2 * TRANSACTIONS {
+            let (coin, leaf) = Coin::<PallasConfig, PallasP>::new(
+                10,
+                &pk,
+                &sig_parameters,
+                &parameters.even_parameters,
+                &layout,
+                rng,
+            )?;
+            leaves.push(leaf);
+            coins.push(coin);
+        }
+        let tree = Tree::from_set(&leaves, &parameters, Some(HEIGHT))?;
+        let inputs = coins
+            .into_iter()
+            .enumerate()
+            .map(|(index, coin)| {
+                Ok(SpendingInfo {
+                    leaf: tree.leaf_ref(index)?,
+                    randomized_pk: Coin::<PallasConfig, PallasP>::rerandomized_pk(
+                        &pk,
+                        &coin.pk_randomness.total(),
+                        &sig_parameters,
+                    ),
+                    coin_aux: coin,
+                    sk: sk.clone(),
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self {
+            pk,
+            parameters,
+            sig_parameters,
+            tree,
+            inputs,
+        })
+    }
+
+    /// The serialized pour of the `i`th pair of coins: every fifth with a forged signature,
+    /// and the seventh of an unknown version.
+    fn transaction(&self, i: usize, rng: &mut StdRng) -> Result<Vec<u8>, Error> {
+        let mut tx = prove_pour(
+            Prover::new(
+                &self.parameters.even_parameters.pc_gens,
+                Transcript::new(LABEL),
+            ),
+            Prover::new(
+                &self.parameters.odd_parameters.pc_gens,
+                Transcript::new(LABEL),
+            ),
+            &self.parameters,
+            &CommitmentLayout::coin(),
+            &self.tree,
+            &self.inputs[2 * i],
+            &self.inputs[2 * i + 1],
+            5,
+            self.pk,
+            15,
+            self.pk,
+            &self.sig_parameters,
+            rng,
+        )?;
+        if i % 5 == 4 {
+            tx.signature_prover_response_0 += ark_pallas::Fr::from(1u64);
+        }
+        let mut bytes = Vec::new();
+        tx.serialize_compressed(&mut bytes)?;
+        if i == 6 {
+            bytes[0] = FormatVersion::CURRENT.byte() + 8;
+        }
+        Ok(bytes)
+    }
+}
+
+/// Groups the transactions received into batches, blocking the sender when the verifiers are behind.
+fn batch(transactions: Receiver<Vec<u8>>, batches: SyncSender<Batch<FormatVersion, Vec<u8>>>) {
+    let mut batcher = Batcher::new(MAX_BATCH_SIZE).expect("a positive batch size");
+    for bytes in transactions {
+        match batcher.push(version_of(&bytes), bytes) {
+            Ok(Some(batch)) => batches
+                .send(batch)
+                .expect("the verifiers outlive the batcher"),
+            Ok(None) => {}
+            Err((index, e)) => println!("transaction {index:>3} rejected before batching: {e}"),
+        }
+    }
+    for batch in batcher.flush() {
+        batches
+            .send(batch)
+            .expect("the verifiers outlive the batcher");
+    }
+}
+
+fn print_report(report: &BatchReport<FormatVersion>) {
+    println!(
+        "batch of {:?} {:?}: {} in {:.2?}",
+        report.key,
+        report.indices,
+        if report.is_valid() {
+            "valid"
+        } else {
+            "invalid"
+        },
+        report.elapsed
+    );
+    for (index, e) in &report.failures {
+        println!("    transaction {index:>3} fails: {e}");
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let setup = Instant::now();
+    let chain = Chain::new(&mut rng)?;
+    println!(
+        "{} coins in a tree of height {HEIGHT} in {:.2?}",
+        chain.tree.leaf_count(),
+        setup.elapsed()
+    );
+
+    let mut router = Router::new();
+    router.insert(
+        FormatVersion::CURRENT,
+        VerificationContext {
+            parameters: &chain.parameters,
+            sig_parameters: &chain.sig_parameters,
+            layout: CommitmentLayout::coin(),
+            tree: &chain.tree,
+        },
+    );
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(WORKERS)
+        .build()
+        .expect("the pool starts");
+
+    let (transaction_sender, transaction_receiver) = sync_channel(TRANSACTION_QUEUE);
+    let (batch_sender, batch_receiver) = sync_channel(BATCH_QUEUE);
+    let (report_sender, report_receiver) = sync_channel(BATCH_QUEUE);
+    let start = Instant::now();
+    thread::scope(|scope| {
+        let chain = &chain;
+        scope.spawn(move || {
+            let mut rng = StdRng::seed_from_u64(1);
+            for i in 0..TRANSACTIONS {
+                let bytes = chain.transaction(i, &mut rng).expect("the pours prove");
+                transaction_sender
+                    .send(bytes)
+                    .expect("the batcher outlives the prover");
+            }
+        });
+        scope.spawn(move || batch(transaction_receiver, batch_sender));
+        scope.spawn(move || {
+            // Each batch is a task of the pool, whose idle workers steal the tasks of busy ones.
+            pool.scope(|tasks| {
+                for batch in batch_receiver {
+                    let (router, report_sender) = (&router, report_sender.clone());
+                    tasks.spawn(move |_| {
+                        let report = router.verify(
+                            &batch,
+                            VerificationContext::verify_batch,
+                            |context, bytes| context.verify_one(bytes),
+                        );
+                        report_sender
+                            .send(report)
+                            .expect("the reports are read to the end");
+                    });
+                }
+            });
+            drop(report_sender);
+        });
+        for report in report_receiver {
+            print_report(&report);
+        }
+    });
+    println!("{TRANSACTIONS} transactions in {:.2?}", start.elapsed());
+    Ok(())
+}
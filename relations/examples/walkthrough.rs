@@ -0,0 +1,369 @@
+//! A walkthrough of a curve tree membership proof, from the parameters to the verification.
+//!
+//! Builds a tree of branching factor 4 and height 2 over a few committed values, proves that one of its leaves
+//! is in it revealing only a rerandomization of the leaf, and verifies the proof, printing what each step computes:
+//!
+//! ```text
+//! cargo run --release --example walkthrough
+//! ```
+//!
+//! Each step is a method of `Walkthrough` returning a report of its intermediate values, which `main` prints
+//! and the test at the end checks, so the steps can be inspected one by one, e.g. from a debugger.
+
+extern crate relations;
+
+use ark_ec::short_weierstrass::Affine;
+use ark_pallas::{Fr as PallasScalar, PallasConfig};
+use ark_std::UniformRand;
+use ark_vesta::VestaConfig;
+use bulletproofs::r1cs::{ConstraintSystem, Prover};
+use merlin::Transcript;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use relations::curve_tree::*;
+use relations::encoding::fingerprint;
+use relations::protocol;
+use relations::size::SizeReport;
+use relations::Error;
+use std::fmt;
+
+// The branching factor and height of the tree: two levels of nodes of 4 children above the leaves.
+const L: usize = 4;
+const HEIGHT: usize = 2;
+// Enough generators for the constraints of a proof on either curve.
+const GENERATORS_LENGTH: usize = 1 << 10;
+
+type Tree = CurveTree<L, PallasConfig, VestaConfig>;
+
+/// The parameters of both curves, derived from fixed labels.
+#[derive(Debug)]
+pub struct SetupReport {
+    pub generators: usize,
+    pub fingerprint: String,
+}
+
+/// The leaves, the root, and the nodes on the path to the proven leaf.
+#[derive(Debug)]
+pub struct TreeReport {
+    /// The fingerprints of the leaves, commitments to the values on the even curve.
+    pub leaves: Vec<String>,
+    pub root: String,
+    pub height: usize,
+    /// For each node on the path to the proven leaf, from the root: its curve and the fingerprints of its children.
+    pub path: Vec<(&'static str, Vec<String>)>,
+}
+
+/// What the prover computes: the rerandomized path, the constraints on each curve and the sizes of the proofs.
+#[derive(Debug)]
+pub struct ProofReport {
+    pub index: usize,
+    /// The scalar added to the blinding of the leaf, hiding which leaf it is.
+    pub rerandomization: PallasScalar,
+    pub rerandomized_leaf: String,
+    /// The fingerprints of the rerandomized commitments below the root, on each curve.
+    pub even_commitments: Vec<String>,
+    pub odd_commitments: Vec<String>,
+    pub even_constraints: usize,
+    pub odd_constraints: usize,
+    pub sizes: SizeReport,
+}
+
+/// What the verifier learns: a rerandomized leaf, which the prover opens to the value of the proven leaf.
+#[derive(Debug)]
+pub struct VerificationReport {
+    pub rerandomized_leaf: String,
+    pub opens_to_value: bool,
+}
+
+/// The report of each step, in order.
+#[derive(Debug)]
+pub enum Step {
+    Setup(SetupReport),
+    Tree(TreeReport),
+    Proof(ProofReport),
+    Verification(VerificationReport),
+}
+
+/// The state of the walkthrough, which each step advances.
+pub struct Walkthrough {
+    rng: StdRng,
+    index: usize,
+    values: Vec<PallasScalar>,
+    parameters: Option<SelRerandParameters<PallasConfig, VestaConfig>>,
+    blindings: Vec<PallasScalar>,
+    tree: Option<Tree>,
+    proof: Option<(MembershipProof<L, PallasConfig, VestaConfig>, PallasScalar)>,
+    verified: bool,
+}
+
+impl Walkthrough {
+    /// A walkthrough proving the membership of the leaf at `index` among leaves committing to `values`.
+    pub fn new(values: &[u64], index: usize, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            index,
+            values: values.iter().map(|v| PallasScalar::from(*v)).collect(),
+            parameters: None,
+            blindings: Vec::new(),
+            tree: None,
+            proof: None,
+            verified: false,
+        }
+    }
+
+    /// Runs the next step, or returns `None` once the proof is verified.
+    pub fn next_step(&mut self) -> Result<Option<Step>, Error> {
+        Ok(Some(if self.parameters.is_none() {
+            Step::Setup(self.setup())
+        } else if self.tree.is_none() {
+            Step::Tree(self.build_tree()?)
+        } else if self.proof.is_none() {
+            Step::Proof(self.prove()?)
+        } else if !self.verified {
+            Step::Verification(self.verify()?)
+        } else {
+            return Ok(None);
+        }))
+    }
+
+    fn parameters(&self) -> &SelRerandParameters<PallasConfig, VestaConfig> {
+        self.parameters.as_ref().expect("the setup step ran")
+    }
+
+    fn tree(&self) -> &Tree {
+        self.tree.as_ref().expect("the tree step ran")
+    }
+
+    // The generators of both curves. The randomness is not used, the generators are derived from labels.
+    fn setup(&mut self) -> SetupReport {
+        let parameters = SelRerandParameters::new(GENERATORS_LENGTH, GENERATORS_LENGTH);
+        let report = SetupReport {
+            generators: GENERATORS_LENGTH,
+            fingerprint: fingerprint(&parameters.fingerprint()),
+        };
+        self.parameters = Some(parameters);
+        report
+    }
+
+    // Commits to each value with a random blinding, adjusted to a permissible point as leaves must be,
+    // and combines the leaves into nodes level by level, alternating between the curves.
+    fn build_tree(&mut self) -> Result<TreeReport, Error> {
+        let mut leaves = Vec::with_capacity(self.values.len());
+        for value in &self.values {
+            let blinding = PallasScalar::rand(&mut self.rng);
+            let (leaf, blinding) =
+                self.parameters()
+                    .even_parameters
+                    .permissible_commitment(&[*value], blinding, 0)?;
+            leaves.push(leaf);
+            self.blindings.push(blinding);
+        }
+        let tree = Tree::from_set(&leaves, self.parameters(), Some(HEIGHT))?;
+
+        let attestation = tree.inclusion_attestation(self.index)?;
+        // The levels alternate between the curves, from the even root.
+        let mut path = Vec::new();
+        for level in 0..tree.height() {
+            path.push(if level % 2 == 0 {
+                let node = &attestation.even_levels[level / 2];
+                ("even", node.children.iter().map(fingerprint).collect())
+            } else {
+                let node = &attestation.odd_levels[level / 2];
+                ("odd", node.children.iter().map(fingerprint).collect())
+            });
+        }
+        let report = TreeReport {
+            leaves: leaves.iter().map(fingerprint).collect(),
+            root: fingerprint(&tree.root_node()?),
+            height: tree.height(),
+            path,
+        };
+        self.tree = Some(tree);
+        Ok(report)
+    }
+
+    // Lays down the select-and-rerandomize constraints of each level on both curves, and proves them.
+    // This is `CurveTree::prove_membership`, step by step.
+    fn prove(&mut self) -> Result<ProofReport, Error> {
+        let parameters = self.parameters();
+        let tree = self.tree();
+        let mut even_prover = Prover::new(
+            &parameters.even_parameters.pc_gens,
+            Transcript::new(protocol::MEMBERSHIP),
+        );
+        let mut odd_prover = Prover::new(
+            &parameters.odd_parameters.pc_gens,
+            Transcript::new(protocol::MEMBERSHIP),
+        );
+        let leaf_count = tree.leaf_count() as u64;
+        bind_leaf_count(
+            even_prover.transcript(),
+            odd_prover.transcript(),
+            leaf_count,
+        );
+        let (path, rerandomization) = tree.select_and_rerandomize_prover_gadget(
+            self.index,
+            &mut even_prover,
+            &mut odd_prover,
+            parameters,
+            &mut StdRng::seed_from_u64(self.index as u64),
+        )?;
+        let (even_constraints, odd_constraints) = (
+            even_prover.number_of_constraints(),
+            odd_prover.number_of_constraints(),
+        );
+        let proof = MembershipProof {
+            even_proof: even_prover.prove(&parameters.even_parameters.bp_gens)?,
+            odd_proof: odd_prover.prove(&parameters.odd_parameters.bp_gens)?,
+            path,
+            parameters_fingerprint: parameters.fingerprint(),
+            leaf_count,
+        };
+
+        let report = ProofReport {
+            index: self.index,
+            rerandomization: rerandomization.0,
+            rerandomized_leaf: fingerprint(&proof.rerandomized_leaf()),
+            even_commitments: proof
+                .path
+                .even_commitments
+                .iter()
+                .map(fingerprint)
+                .collect(),
+            odd_commitments: proof.path.odd_commitments.iter().map(fingerprint).collect(),
+            even_constraints,
+            odd_constraints,
+            sizes: proof.size_report(),
+        };
+        let blinding = self.blindings[self.index] + rerandomization.0;
+        self.proof = Some((proof, blinding));
+        Ok(report)
+    }
+
+    // Checks the proof against the root, and the opening of the rerandomized leaf the prover keeps.
+    fn verify(&mut self) -> Result<VerificationReport, Error> {
+        let (proof, blinding) = self.proof.as_ref().expect("the proof step ran");
+        let rerandomized_leaf: Affine<PallasConfig> =
+            proof.verify(self.tree(), self.parameters())?;
+        let opening =
+            self.parameters()
+                .even_parameters
+                .commit(&[self.values[self.index]], *blinding, 0)?;
+        self.verified = true;
+        Ok(VerificationReport {
+            rerandomized_leaf: fingerprint(&rerandomized_leaf),
+            opens_to_value: opening == rerandomized_leaf,
+        })
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Setup(report) => {
+                writeln!(f, "1. Setup")?;
+                writeln!(f, "   generators per curve: {}", report.generators)?;
+                write!(f, "   parameters fingerprint: {}", report.fingerprint)
+            }
+            Step::Tree(report) => {
+                writeln!(f, "2. Tree of height {}", report.height)?;
+                writeln!(f, "   leaves: {}", report.leaves.join(" "))?;
+                writeln!(f, "   root: {}", report.root)?;
+                for (level, (curve, children)) in report.path.iter().enumerate() {
+                    writeln!(
+                        f,
+                        "   level {} ({} node), children: {}",
+                        level,
+                        curve,
+                        children.join(" ")
+                    )?;
+                }
+                Ok(())
+            }
+            Step::Proof(report) => {
+                writeln!(f, "3. Proof of the leaf at index {}", report.index)?;
+                writeln!(f, "   rerandomization: {}", report.rerandomization)?;
+                writeln!(f, "   rerandomized leaf: {}", report.rerandomized_leaf)?;
+                writeln!(
+                    f,
+                    "   rerandomized path: even {} odd {}",
+                    report.even_commitments.join(" "),
+                    report.odd_commitments.join(" ")
+                )?;
+                writeln!(
+                    f,
+                    "   constraints: {} even, {} odd",
+                    report.even_constraints, report.odd_constraints
+                )?;
+                write!(f, "{}", report.sizes)
+            }
+            Step::Verification(report) => {
+                writeln!(f, "4. Verification")?;
+                writeln!(f, "   rerandomized leaf: {}", report.rerandomized_leaf)?;
+                write!(
+                    f,
+                    "   opens to the value of the leaf: {}",
+                    report.opens_to_value
+                )
+            }
+        }
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let mut walkthrough = Walkthrough::new(&[3, 1, 4, 1, 5, 9], 4, 0);
+    while let Some(step) = walkthrough.next_step()? {
+        println!("{}\n", step);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_walkthrough() {
+        let mut walkthrough = Walkthrough::new(&[3, 1, 4, 1, 5, 9], 4, 0);
+        let mut steps = Vec::new();
+        while let Some(step) = walkthrough.next_step().unwrap() {
+            // Every step prints.
+            assert!(!step.to_string().is_empty());
+            steps.push(step);
+        }
+        assert_eq!(steps.len(), 4);
+
+        let tree = match &steps[1] {
+            Step::Tree(tree) => tree,
+            step => panic!("expected the tree, got {:?}", step),
+        };
+        assert_eq!(tree.height, HEIGHT);
+        assert_eq!(tree.leaves.len(), 6);
+        // The root has two children, of 4 and 2 leaves, and the leaf at index 4 is the first of the second.
+        assert_eq!(tree.path[0].1.len(), 2);
+        assert_eq!(tree.path[1].1.len(), 2);
+        assert_eq!(tree.path[1].1[0], tree.leaves[4]);
+
+        let proof = match &steps[2] {
+            Step::Proof(proof) => proof,
+            step => panic!("expected the proof, got {:?}", step),
+        };
+        // The rerandomized path hides the nodes of the tree.
+        assert_eq!(
+            proof.even_commitments.len() + proof.odd_commitments.len(),
+            HEIGHT
+        );
+        assert!(!tree.leaves.contains(&proof.rerandomized_leaf));
+        assert!(proof.even_constraints > 0 && proof.odd_constraints > 0);
+        assert!(proof.sizes.total() > 0);
+
+        match &steps[3] {
+            Step::Verification(verification) => {
+                assert_eq!(verification.rerandomized_leaf, proof.rerandomized_leaf);
+                assert!(verification.opens_to_value);
+            }
+            step => panic!("expected the verification, got {:?}", step),
+        }
+        assert!(walkthrough.next_step().unwrap().is_none());
+    }
+}
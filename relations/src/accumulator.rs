@@ -0,0 +1,198 @@
+use crate::curve_tree::*;
+use crate::cycle::EvenScalar;
+use crate::error::Error;
+use crate::metrics::NoMetrics;
+
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine};
+use ark_ff::PrimeField;
+#[cfg(feature = "prover")]
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+
+/// A set of commitments with a public root, whose members prove membership by revealing only a rerandomization of their commitment.
+///
+/// Application code written against the trait can swap the curve tree for other accumulators,
+/// e.g. in benchmarks or in fast tests. The proofs hide the element and its position:
+/// the prover learns the blinding of the rerandomized element and the verifier the rerandomized element,
+/// for proving further statements about it, and nothing of how the accumulator proves membership.
+pub trait Accumulator {
+    /// The members of the set, commitments.
+    type Element;
+    /// The blinding of an element, and of its rerandomizations.
+    type Blinding;
+    type Root;
+    /// What the prover needs, besides the accumulator, to prove the membership of an element, returned by `insert`.
+    type Witness;
+    type Proof;
+    type Parameters;
+
+    /// Adds `elements` to the set, returning the witness of each, in order.
+    fn insert(
+        &mut self,
+        elements: &[Self::Element],
+        parameters: &Self::Parameters,
+    ) -> Result<Vec<Self::Witness>, Error>;
+
+    fn root(&self) -> Self::Root;
+
+    /// Proves the membership of the element at `witness`, of blinding `blinding`.
+    /// Returns the proof and the blinding of the rerandomized element.
+    #[cfg(feature = "prover")]
+    fn prove_membership<R: RngCore + CryptoRng>(
+        &self,
+        witness: &Self::Witness,
+        blinding: Self::Blinding,
+        parameters: &Self::Parameters,
+        rng: &mut R,
+    ) -> Result<(Self::Proof, Self::Blinding), Error>;
+
+    /// The rerandomized element of which `proof` proves membership, unverified.
+    fn rerandomized_element(proof: &Self::Proof) -> Self::Element;
+
+    /// Verifies `proof` against the set and returns the rerandomized element.
+    fn verify_membership(
+        &self,
+        proof: &Self::Proof,
+        parameters: &Self::Parameters,
+    ) -> Result<Self::Element, Error>;
+}
+
+/// The curve tree as an accumulator of its leaves, the witness of a leaf being its index.
+/// Inserting commits the nodes on the paths of the new leaves again, keeping the storage and at least the height.
+impl<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    > Accumulator for CurveTree<L, P0, P1>
+{
+    type Element = Affine<P0>;
+    type Blinding = EvenScalar<P0>;
+    type Root = CyclePoint<P0, P1>;
+    type Witness = usize;
+    type Proof = MembershipProof<L, P0, P1>;
+    type Parameters = SelRerandParameters<P0, P1>;
+
+    fn insert(
+        &mut self,
+        elements: &[Affine<P0>],
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<Vec<usize>, Error> {
+        self.insert_with_metrics(elements, parameters, &NoMetrics)
+    }
+
+    fn root(&self) -> CyclePoint<P0, P1> {
+        CurveTree::root(self)
+    }
+
+    #[cfg(feature = "prover")]
+    fn prove_membership<R: RngCore + CryptoRng>(
+        &self,
+        witness: &usize,
+        blinding: EvenScalar<P0>,
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<(MembershipProof<L, P0, P1>, EvenScalar<P0>), Error> {
+        CurveTree::prove_membership(self, *witness, blinding, parameters, rng)
+    }
+
+    fn rerandomized_element(proof: &MembershipProof<L, P0, P1>) -> Affine<P0> {
+        proof.rerandomized_leaf()
+    }
+
+    fn verify_membership(
+        &self,
+        proof: &MembershipProof<L, P0, P1>,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<Affine<P0>, Error> {
+        proof.verify(self, parameters)
+    }
+}
+
+/// An accumulator for fast tests of code written against `Accumulator`.
+/// Its proofs reveal the position of the element and the rerandomization, and so hide nothing.
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::*;
+    use crate::single_level_select_and_rerandomize::SingleLayerParameters;
+    use ark_ec::CurveGroup;
+    use ark_std::UniformRand;
+
+    pub(crate) struct MockAccumulator<P: SWCurveConfig> {
+        pub(crate) elements: Vec<Affine<P>>,
+    }
+
+    pub(crate) struct MockProof<P: SWCurveConfig> {
+        index: usize,
+        rerandomization: P::ScalarField,
+        rerandomized_element: Affine<P>,
+    }
+
+    impl<P: SWCurveConfig + Copy> Accumulator for MockAccumulator<P> {
+        type Element = Affine<P>;
+        type Blinding = EvenScalar<P>;
+        // the number of elements
+        type Root = usize;
+        type Witness = usize;
+        type Proof = MockProof<P>;
+        type Parameters = SingleLayerParameters<P>;
+
+        fn insert(
+            &mut self,
+            elements: &[Affine<P>],
+            _parameters: &SingleLayerParameters<P>,
+        ) -> Result<Vec<usize>, Error> {
+            let first = self.elements.len();
+            self.elements.extend_from_slice(elements);
+            Ok((first..self.elements.len()).collect())
+        }
+
+        fn root(&self) -> usize {
+            self.elements.len()
+        }
+
+        fn prove_membership<R: RngCore + CryptoRng>(
+            &self,
+            witness: &usize,
+            blinding: EvenScalar<P>,
+            parameters: &SingleLayerParameters<P>,
+            rng: &mut R,
+        ) -> Result<(MockProof<P>, EvenScalar<P>), Error> {
+            let element = self.elements.get(*witness).ok_or_else(|| {
+                Error::inconsistent_witness(format!("No element at index {}", witness))
+            })?;
+            let rerandomization = P::ScalarField::rand(rng);
+            let proof = MockProof {
+                index: *witness,
+                rerandomization,
+                rerandomized_element: (*element
+                    + parameters.pc_gens.commit_blinding_only(rerandomization))
+                .into_affine(),
+            };
+            Ok((proof, blinding + EvenScalar(rerandomization)))
+        }
+
+        fn rerandomized_element(proof: &MockProof<P>) -> Affine<P> {
+            proof.rerandomized_element
+        }
+
+        fn verify_membership(
+            &self,
+            proof: &MockProof<P>,
+            parameters: &SingleLayerParameters<P>,
+        ) -> Result<Affine<P>, Error> {
+            let element = self.elements.get(proof.index).ok_or(Error::InvalidPath)?;
+            if (*element
+                + parameters
+                    .pc_gens
+                    .commit_blinding_only(proof.rerandomization))
+            .into_affine()
+                != proof.rerandomized_element
+            {
+                return Err(Error::InvalidPath);
+            }
+            Ok(proof.rerandomized_element)
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! `proptest::arbitrary::Arbitrary` implementations of the core types, behind the `test-arbitrary` feature.
+//!
+//! The generated values are structurally valid: trees and paths of consistent depths, layouts with distinct slots,
+//! policies accepted by `ValuePolicy::validate` on fields of 255 bits, limits letting something through,
+//! circuit descriptions accepted by `CircuitDescription::check`. Randomized tests then exercise what the code
+//! does with valid inputs, instead of spending their cases on inputs rejected up front.
+//!
+//! The strategies are drawn from ranges and lists ordered from the cheapest value, so a failing case
+//! shrinks towards shallow trees of small branching factors first, then towards small values of the other fields.
+
+use crate::circuit::{CircuitDescription, CircuitStep};
+use crate::convert::fe_from_le_bytes_reduced;
+use crate::curve_tree::{SelRerandParameters, SelectAndRerandomizePath};
+use crate::layout::{CommitmentLayout, ASSET, TAG, VALUE};
+use crate::limits::{DeserializeLimits, MAX_SUPPORTED_DEPTH};
+use crate::policy::ValuePolicy;
+use crate::tree_config::{level_multipliers, TreeConfig, CANDIDATE_BRANCHING_FACTORS};
+
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine, AffineRepr};
+use ark_ff::PrimeField;
+use proptest::prelude::*;
+use proptest::sample::subsequence;
+use std::sync::OnceLock;
+
+/// The names of the slots of generated layouts, the slots of this crate then placeholders.
+pub const SLOT_NAMES: [&str; 8] = [
+    VALUE, TAG, ASSET, "slot 3", "slot 4", "slot 5", "slot 6", "slot 7",
+];
+
+/// The most steps of a generated circuit description.
+pub const MAX_CIRCUIT_STEPS: usize = 8;
+
+/// A field element reduced from 64 uniform bytes, below the modulus, shrinking towards zero.
+pub fn scalar<F: PrimeField>() -> impl Strategy<Value = F> {
+    prop::array::uniform32(any::<u8>())
+        .prop_flat_map(|low| (Just(low), prop::array::uniform32(any::<u8>())))
+        .prop_map(|(low, high)| {
+            let mut bytes = [0u8; 64];
+            bytes[..32].copy_from_slice(&low);
+            bytes[32..].copy_from_slice(&high);
+            fe_from_le_bytes_reduced(&bytes)
+        })
+}
+
+/// A point of the prime order subgroup, a non-zero multiple of the generator shrinking towards the generator.
+pub fn point<P: SWCurveConfig>() -> impl Strategy<Value = Affine<P>> {
+    (1..=u64::MAX).prop_map(|k| (Affine::<P>::generator() * P::ScalarField::from(k)).into())
+}
+
+/// The multipliers of a level of the even and the odd proof, of pallas and vesta parameters from a fixed seed.
+fn pallas_vesta_level_multipliers() -> (usize, usize) {
+    static LEVELS: OnceLock<(usize, usize)> = OnceLock::new();
+    *LEVELS.get_or_init(|| {
+        let parameters =
+            SelRerandParameters::<ark_pallas::PallasConfig, ark_vesta::VestaConfig>::new(1, 1);
+        (
+            level_multipliers::<_, _, ark_vesta::VestaConfig, ark_pallas::Affine>(
+                &parameters.odd_parameters,
+            ),
+            level_multipliers::<_, _, ark_pallas::PallasConfig, ark_vesta::Affine>(
+                &parameters.even_parameters,
+            ),
+        )
+    })
+}
+
+impl Arbitrary for TreeConfig {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// A candidate branching factor and a depth up to `MAX_SUPPORTED_DEPTH`, with the costs of pallas and vesta trees.
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (1..=MAX_SUPPORTED_DEPTH, 0..CANDIDATE_BRANCHING_FACTORS.len())
+            .prop_map(|(depth, factor)| {
+                let (even_level, odd_level) = pallas_vesta_level_multipliers();
+                TreeConfig::with_level_multipliers::<ark_pallas::PallasConfig, ark_vesta::VestaConfig>(
+                    CANDIDATE_BRANCHING_FACTORS[factor],
+                    depth,
+                    even_level,
+                    odd_level,
+                )
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for CommitmentLayout {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// A layout of 1 to 8 coordinates holding distinct slots of `SLOT_NAMES` at distinct indices.
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (1..=SLOT_NAMES.len())
+            .prop_flat_map(|width| {
+                (1..=width).prop_flat_map(move |n_slots| {
+                    (
+                        subsequence(SLOT_NAMES.to_vec(), n_slots),
+                        subsequence((0..width).collect::<Vec<_>>(), n_slots).prop_shuffle(),
+                    )
+                })
+            })
+            .prop_map(|(names, indices)| {
+                let slots: Vec<_> = names.into_iter().zip(indices).collect();
+                CommitmentLayout::new(&slots).expect("the names and the indices are distinct")
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for ValuePolicy {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// Values of 1 to 64 bits balanced by 1 to 255 inputs and outputs, valid on fields of 255 bits.
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (1..=u8::MAX, 1..=u8::MAX, 1..=64u8)
+            .prop_map(|(max_inputs, max_outputs, bits)| ValuePolicy {
+                bits,
+                max_inputs,
+                max_outputs,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for DeserializeLimits {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// Limits of at least a level, two children, a step and the generators of a single multiplier,
+    /// and at most `DeserializeLimits::default()`.
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        let default = DeserializeLimits::default();
+        (
+            1..=default.max_depth,
+            2..=default.max_arity,
+            1..=default.max_circuit_steps,
+            1..=default.max_transaction_size,
+            1..=default.max_generators,
+        )
+            .prop_map(
+                |(
+                    max_depth,
+                    max_arity,
+                    max_circuit_steps,
+                    max_transaction_size,
+                    max_generators,
+                )| {
+                    DeserializeLimits {
+                        max_depth,
+                        max_arity,
+                        max_circuit_steps,
+                        max_transaction_size,
+                        max_generators,
+                    }
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for CircuitStep {
+    /// The branching factor of the select and rerandomize steps, any candidate branching factor if `None`.
+    type Parameters = Option<usize>;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(branching_factor: Option<usize>) -> Self::Strategy {
+        let branching_factors = match branching_factor {
+            Some(branching_factor) => vec![branching_factor],
+            None => CANDIDATE_BRANCHING_FACTORS.to_vec(),
+        };
+        prop_oneof![
+            (
+                1..=MAX_SUPPORTED_DEPTH,
+                prop::sample::select(branching_factors)
+            )
+                .prop_map(|(height, branching_factor)| {
+                    CircuitStep::SelectAndRerandomize {
+                        branching_factor,
+                        height,
+                    }
+                }),
+            (1..=64usize).prop_map(|bits| CircuitStep::RangeProof { bits }),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for CircuitDescription {
+    /// The branching factor of the select and rerandomize steps, as for `CircuitStep`.
+    type Parameters = Option<usize>;
+    type Strategy = BoxedStrategy<Self>;
+
+    /// Up to `MAX_CIRCUIT_STEPS` steps, within `DeserializeLimits::default()`.
+    fn arbitrary_with(branching_factor: Option<usize>) -> Self::Strategy {
+        prop::collection::vec(
+            CircuitStep::arbitrary_with(branching_factor),
+            0..=MAX_CIRCUIT_STEPS,
+        )
+        .prop_map(CircuitDescription::new)
+        .boxed()
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> Arbitrary
+    for SelectAndRerandomizePath<L, P0, P1>
+{
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// The skeleton of a path from a tree of depth 1 to `MAX_SUPPORTED_DEPTH`: as many commitments on each curve
+    /// as such a path has, of arbitrary points of the subgroups.
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (1..=MAX_SUPPORTED_DEPTH)
+            .prop_flat_map(|depth| {
+                (
+                    prop::collection::vec(point::<P0>(), depth - depth / 2),
+                    prop::collection::vec(point::<P1>(), depth / 2),
+                )
+            })
+            .prop_map(
+                |(even_commitments, odd_commitments)| SelectAndRerandomizePath {
+                    even_commitments,
+                    odd_commitments,
+                },
+            )
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::DeserializeWithLimits;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::TestRunner;
+
+    type PallasScalar = ark_pallas::Fr;
+    type Path = SelectAndRerandomizePath<4, ark_pallas::PallasConfig, ark_vesta::VestaConfig>;
+
+    proptest! {
+        #[test]
+        fn test_tree_configs_are_consistent(config in any::<TreeConfig>()) {
+            prop_assert!(CANDIDATE_BRANCHING_FACTORS.contains(&config.branching_factor));
+            prop_assert!(config.depth >= 1 && config.depth <= MAX_SUPPORTED_DEPTH);
+            let capacity = (config.branching_factor as u64).checked_pow(config.depth as u32);
+            prop_assert_eq!(config.capacity, capacity.unwrap_or(u64::MAX));
+            prop_assert!(config.padded_multipliers.0 >= config.multipliers.0);
+            prop_assert!(config.padded_multipliers.1 >= config.multipliers.1);
+        }
+
+        #[test]
+        fn test_layouts_have_distinct_slots(layout in any::<CommitmentLayout>()) {
+            let indices: Vec<_> = SLOT_NAMES.iter().filter_map(|name| layout.index(name).ok()).collect();
+            prop_assert!(!indices.is_empty());
+            prop_assert!(indices.iter().all(|index| *index < layout.width()));
+            let mut distinct = indices.clone();
+            distinct.sort();
+            distinct.dedup();
+            prop_assert_eq!(distinct.len(), indices.len());
+            prop_assert_eq!(&layout.merge(&layout).unwrap(), &layout);
+        }
+
+        #[test]
+        fn test_policies_are_valid(policy in any::<ValuePolicy>()) {
+            prop_assert!(policy.validate::<PallasScalar>().is_ok());
+        }
+
+        #[test]
+        fn test_descriptions_round_trip(
+            description in any_with::<CircuitDescription>(Some(4)),
+            limits in any::<DeserializeLimits>(),
+        ) {
+            prop_assert!(description.check::<4>().is_ok());
+            let mut bytes = Vec::new();
+            description.serialize_compressed(&mut bytes).unwrap();
+            prop_assert_eq!(
+                CircuitDescription::deserialize_compressed_with_limits(&bytes[..], &DeserializeLimits::default()).unwrap(),
+                description.clone()
+            );
+            // Under smaller limits, a description is either read back or rejected by a limit.
+            if let Ok(read) = CircuitDescription::deserialize_compressed_with_limits(&bytes[..], &limits) {
+                prop_assert_eq!(read, description);
+            }
+        }
+
+        #[test]
+        fn test_paths_round_trip(path in any::<Path>()) {
+            prop_assert_eq!(path.even_commitments.len(), path.depth() - path.depth() / 2);
+            let mut bytes = Vec::new();
+            path.serialize_compressed(&mut bytes).unwrap();
+            let read = Path::deserialize_compressed_with_limits(&bytes[..], &DeserializeLimits::default()).unwrap();
+            prop_assert_eq!(read.even_commitments, path.even_commitments);
+            prop_assert_eq!(read.odd_commitments, path.odd_commitments);
+        }
+
+        #[test]
+        fn test_scalars_round_trip(scalar in scalar::<PallasScalar>()) {
+            // The canonical encoding of a scalar below the modulus is read back.
+            let mut bytes = Vec::new();
+            scalar.serialize_compressed(&mut bytes).unwrap();
+            prop_assert_eq!(PallasScalar::deserialize_compressed(&bytes[..]).unwrap(), scalar);
+        }
+    }
+
+    #[test]
+    fn test_shrinks_depth_first() {
+        // Shrinking a deep configuration as far as it goes leads to a single level of the smallest branching factor.
+        let mut runner = TestRunner::deterministic();
+        let mut tree = any::<TreeConfig>().new_tree(&mut runner).unwrap();
+        while tree.simplify() {}
+        let config = tree.current();
+        assert_eq!((config.depth, config.branching_factor), (1, 2));
+
+        let mut tree = any::<Path>().new_tree(&mut runner).unwrap();
+        while tree.simplify() {}
+        assert_eq!(tree.current().depth(), 1);
+    }
+}
@@ -0,0 +1,377 @@
+//! Batches of items verified together, e.g. the serialized transactions received by a node,
+//! grouped by the context they are verified in, e.g. the parameters and trees of their format version.
+//!
+//! A `Batcher` groups the items as they arrive and hands out a batch as soon as its key has enough items,
+//! a `Router` holds one verification context per key, and `verify_batches` verifies batches in their contexts
+//! and reports, for each, how long it took and which items fail. Verifying a batch is cheaper than verifying
+//! its items one by one, see `coin::verify_transactions_batched`, but a failing batch does not say which item fails:
+//! the items of a failing batch are then verified one by one.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ark_serialize::SerializationError;
+
+use crate::error::Error;
+use crate::metrics::{self, Metrics, NoMetrics};
+use crate::version::FormatVersion;
+
+/// The format version of serialized data starting with a version byte, e.g. a `coin::SignedTx`,
+/// to route it to the verifiers of that version before deserializing it.
+pub fn version_of(bytes: &[u8]) -> Result<FormatVersion, Error> {
+    let byte = bytes
+        .first()
+        .ok_or(Error::Serialization(SerializationError::InvalidData))?;
+    FormatVersion::from_byte(*byte)
+}
+
+/// Items of the same key, with their positions in the input of the `Batcher`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Batch<K, T> {
+    pub key: K,
+    /// The position of each item among all the items pushed to the batcher, in the order of `items`.
+    pub indices: Vec<usize>,
+    pub items: Vec<T>,
+}
+
+impl<K, T> Batch<K, T> {
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// Groups items by key into batches of at most `max_batch_size` items.
+pub struct Batcher<K: Ord + Clone, T> {
+    max_batch_size: usize,
+    pending: BTreeMap<K, Batch<K, T>>,
+    received: usize,
+}
+
+impl<K: Ord + Clone, T> Batcher<K, T> {
+    /// Fails with `Error::ParameterMismatch` if `max_batch_size` is zero.
+    pub fn new(max_batch_size: usize) -> Result<Self, Error> {
+        if max_batch_size == 0 {
+            return Err(Error::parameter_mismatch("A batch holds at least one item"));
+        }
+        Ok(Self {
+            max_batch_size,
+            pending: BTreeMap::new(),
+            received: 0,
+        })
+    }
+
+    /// Adds the next item with its key, or the error of reading its key, e.g. from `version_of`.
+    /// Returns the batch of the key if the item fills it, or the index of the item with the error.
+    pub fn push(
+        &mut self,
+        key: Result<K, Error>,
+        item: T,
+    ) -> Result<Option<Batch<K, T>>, (usize, Error)> {
+        let index = self.received;
+        self.received += 1;
+        let key = key.map_err(|e| (index, e))?;
+        let batch = self.pending.entry(key.clone()).or_insert_with(|| Batch {
+            key: key.clone(),
+            indices: Vec::new(),
+            items: Vec::new(),
+        });
+        batch.indices.push(index);
+        batch.items.push(item);
+        if batch.len() < self.max_batch_size {
+            return Ok(None);
+        }
+        Ok(self.pending.remove(&key))
+    }
+
+    /// The number of items pushed so far, the index of the next one.
+    pub fn received(&self) -> usize {
+        self.received
+    }
+
+    /// The number of items waiting for their batch to fill.
+    pub fn pending(&self) -> usize {
+        self.pending.values().map(Batch::len).sum()
+    }
+
+    /// Hands out the batches which did not fill, e.g. at the end of the input or when waiting longer would delay them
+    /// too much, in the order of their keys.
+    pub fn flush(&mut self) -> Vec<Batch<K, T>> {
+        std::mem::take(&mut self.pending).into_values().collect()
+    }
+}
+
+/// The outcome of verifying a batch.
+#[derive(Debug)]
+pub struct BatchReport<K> {
+    pub key: K,
+    /// The indices of the items of the batch, see `Batch::indices`.
+    pub indices: Vec<usize>,
+    /// The result of verifying the batch as a whole.
+    pub result: Result<(), Error>,
+    /// The indices of the items which fail on their own, with their errors, if the batch failed.
+    pub failures: Vec<(usize, Error)>,
+    /// The time taken to verify the batch, and its items one by one if it failed.
+    pub elapsed: Duration,
+}
+
+impl<K> BatchReport<K> {
+    pub fn is_valid(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// The verification contexts of each key, e.g. the prepared verifiers of each format version.
+pub struct Router<K: Ord, C> {
+    contexts: BTreeMap<K, C>,
+    metrics: Arc<dyn Metrics>,
+}
+
+impl<K: Ord, C> Default for Router<K, C> {
+    fn default() -> Self {
+        Self {
+            contexts: BTreeMap::new(),
+            metrics: Arc::new(NoMetrics),
+        }
+    }
+}
+
+impl<K: Ord + Debug, C> Router<K, C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts the batches verified, and those failing by the kind of their error, in `metrics`,
+    /// see `metrics::BATCHES_PROCESSED`.
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Verifies the batches of `key` in `context`, replacing the previous context of the key, if any.
+    pub fn insert(&mut self, key: K, context: C) -> Option<C> {
+        self.contexts.insert(key, context)
+    }
+
+    pub fn context(&self, key: &K) -> Option<&C> {
+        self.contexts.get(key)
+    }
+
+    /// Verifies `batch` in the context of its key with `verify_batch`, then, if it fails, each of its items
+    /// with `verify_one`. A batch whose key has no context fails with `Error::ParameterMismatch`,
+    /// without verifying its items.
+    pub fn verify<T>(
+        &self,
+        batch: &Batch<K, T>,
+        verify_batch: impl Fn(&C, &[T]) -> Result<(), Error>,
+        verify_one: impl Fn(&C, &T) -> Result<(), Error>,
+    ) -> BatchReport<K>
+    where
+        K: Clone,
+    {
+        let start = Instant::now();
+        let (result, failures) = match self.contexts.get(&batch.key) {
+            None => (
+                Err(Error::parameter_mismatch(format!(
+                    "No verification context for {:?}",
+                    batch.key
+                ))),
+                Vec::new(),
+            ),
+            Some(context) => match verify_batch(context, &batch.items) {
+                Ok(()) => (Ok(()), Vec::new()),
+                Err(e) => (
+                    Err(e),
+                    batch
+                        .indices
+                        .iter()
+                        .zip(&batch.items)
+                        .filter_map(|(index, item)| {
+                            verify_one(context, item).err().map(|e| (*index, e))
+                        })
+                        .collect(),
+                ),
+            },
+        };
+        self.metrics.increment(metrics::BATCHES_PROCESSED, None, 1);
+        if let Err(e) = &result {
+            self.metrics
+                .increment(metrics::BATCH_FAILURES, Some(e.kind()), 1);
+        }
+        BatchReport {
+            key: batch.key.clone(),
+            indices: batch.indices.clone(),
+            result,
+            failures,
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+/// Verifies each of `batches` with `Router::verify`, in parallel with the `parallel` feature,
+/// and returns their reports in the order of the batches.
+pub fn verify_batches<K, C, T>(
+    router: &Router<K, C>,
+    batches: &[Batch<K, T>],
+    verify_batch: impl Fn(&C, &[T]) -> Result<(), Error> + Sync,
+    verify_one: impl Fn(&C, &T) -> Result<(), Error> + Sync,
+) -> Vec<BatchReport<K>>
+where
+    K: Ord + Clone + Debug + Send + Sync,
+    C: Sync,
+    T: Sync,
+{
+    let verify = |batch| router.verify(batch, &verify_batch, &verify_one);
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        batches.par_iter().map(verify).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    batches.iter().map(verify).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Items are a version byte and a payload, which the context of the version accepts unless it is 0xff.
+    fn verify_one(version: &u8, item: &[u8; 2]) -> Result<(), Error> {
+        if item[0] != *version {
+            return Err(Error::UnsupportedVersion { version: item[0] });
+        }
+        if item[1] == 0xff {
+            return Err(Error::InvalidSignature);
+        }
+        Ok(())
+    }
+
+    fn verify_batch(version: &u8, items: &[[u8; 2]]) -> Result<(), Error> {
+        items.iter().try_for_each(|item| verify_one(version, item))
+    }
+
+    fn key(item: &[u8; 2]) -> Result<u8, Error> {
+        match item[0] {
+            0 => Err(Error::Serialization(SerializationError::InvalidData)),
+            version => Ok(version),
+        }
+    }
+
+    #[test]
+    fn test_version_of() {
+        assert_eq!(
+            version_of(&[FormatVersion::CURRENT.byte(), 0]).unwrap(),
+            FormatVersion::CURRENT
+        );
+        assert!(matches!(
+            version_of(&[7]),
+            Err(Error::UnsupportedVersion { version: 7 })
+        ));
+        assert!(matches!(version_of(&[]), Err(Error::Serialization(_))));
+    }
+
+    #[test]
+    fn test_batches_fill_per_key() {
+        assert!(Batcher::<u8, [u8; 2]>::new(0).is_err());
+        let mut batcher = Batcher::new(2).unwrap();
+        assert_eq!(batcher.push(key(&[1, 0]), [1, 0]).unwrap(), None);
+        assert_eq!(batcher.push(key(&[2, 0]), [2, 0]).unwrap(), None);
+        // The second item of version 1 fills its batch, not that of version 2.
+        let batch = batcher.push(key(&[1, 1]), [1, 1]).unwrap().unwrap();
+        assert_eq!(batch.key, 1);
+        assert_eq!(batch.indices, vec![0, 2]);
+        assert_eq!(batch.items, vec![[1, 0], [1, 1]]);
+        assert_eq!(batcher.pending(), 1);
+
+        // An unreadable key is reported with the index of its item, which still counts.
+        assert!(matches!(
+            batcher.push(key(&[0, 0]), [0, 0]),
+            Err((3, Error::Serialization(_)))
+        ));
+        assert_eq!(batcher.received(), 4);
+        let rest = batcher.flush();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].indices, vec![1]);
+        assert_eq!(batcher.pending(), 0);
+        assert!(batcher.flush().is_empty());
+    }
+
+    #[test]
+    fn test_mixed_versions_routed_to_their_contexts() {
+        let items = [
+            [1, 0],
+            [2, 0],
+            [2, 0xff],
+            [3, 0],
+            [1, 0],
+            [0, 0],
+            [2, 0],
+            [1, 0xff],
+        ];
+        let mut batcher = Batcher::new(3).unwrap();
+        let mut batches = Vec::new();
+        let mut rejected = Vec::new();
+        for item in items {
+            match batcher.push(key(&item), item) {
+                Ok(Some(batch)) => batches.push(batch),
+                Ok(None) => {}
+                Err((index, _)) => rejected.push(index),
+            }
+        }
+        batches.extend(batcher.flush());
+        assert_eq!(rejected, vec![5]);
+
+        // Contexts for versions 1 and 2 only: a context verifying the items of another version fails them.
+        let counters = Arc::new(metrics::AtomicMetrics::new());
+        let mut router = Router::new().with_metrics(counters.clone());
+        router.insert(1u8, 1u8);
+        router.insert(2, 2);
+        let reports = verify_batches(&router, &batches, verify_batch, verify_one);
+        let report = |key: u8| reports.iter().find(|report| report.key == key).unwrap();
+
+        assert_eq!(report(2).indices, vec![1, 2, 6]);
+        assert!(!report(2).is_valid());
+        assert!(matches!(
+            report(2).failures.as_slice(),
+            [(2, Error::InvalidSignature)]
+        ));
+
+        assert_eq!(report(1).indices, vec![0, 4, 7]);
+        assert!(matches!(
+            report(1).failures.as_slice(),
+            [(7, Error::InvalidSignature)]
+        ));
+
+        // Version 3 has no context: its batch fails whole.
+        assert_eq!(report(3).indices, vec![3]);
+        assert!(matches!(
+            report(3).result,
+            Err(Error::ParameterMismatch { .. })
+        ));
+        assert!(report(3).failures.is_empty());
+        assert_eq!(counters.counter(metrics::BATCHES_PROCESSED, None), 3);
+        assert_eq!(
+            counters.counter(metrics::BATCH_FAILURES, Some("InvalidSignature")),
+            2
+        );
+        assert_eq!(
+            counters.counter(metrics::BATCH_FAILURES, Some("ParameterMismatch")),
+            1
+        );
+
+        // Valid batches report no failures.
+        let valid = [Batch {
+            key: 1u8,
+            indices: vec![0, 1],
+            items: vec![[1, 0], [1, 1]],
+        }];
+        let reports = verify_batches(&router, &valid, verify_batch, verify_one);
+        assert!(reports[0].is_valid());
+        assert!(reports[0].failures.is_empty());
+    }
+}
@@ -0,0 +1,248 @@
+use crate::ct::field_ct_eq;
+
+use ark_ff::{PrimeField, Zero};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+
+/// The blinding drawn when committing, before any other term.
+pub const INITIAL: &str = "initial";
+/// The offset added by the search for a permissible commitment.
+pub const PERMISSIBLE_OFFSET: &str = "permissible_offset";
+/// The rerandomization of a leaf by the select and rerandomize gadget, when spending.
+pub const SELECT_AND_RERANDOMIZE: &str = "select_and_rerandomize";
+/// The rerandomization of the receiver's public key, drawn by the sender.
+pub const PK_RERANDOMIZATION: &str = "pk_rerandomization";
+/// The single term of a deserialized accumulator, which only stores the total.
+pub const SERIALIZED_TOTAL: &str = "serialized_total";
+
+/// The scalars whose sum blinds a commitment, or rerandomizes a public key, each under the label of the step that added it.
+///
+/// A coin's commitment is blinded by the initial blinding plus the permissible offset, plus the select and rerandomize
+/// rerandomization once spent; the public key in its tag by the sender's rerandomization, on the field of the signature curve.
+/// Accumulating the terms rather than their sum lets a prover whose commitment does not match find the wrong term,
+/// see `first_mismatch`.
+///
+/// The terms are secret: equality compares the totals in constant time, and there is no `Debug`.
+/// Only the total is serialized, the terms are not needed to prove and a deserialized accumulator
+/// has the single term `SERIALIZED_TOTAL`.
+///
+/// The scalars are those of a field, or typed by the curve of the cycle they belong to, see `cycle`:
+/// the blinding of a coin commitment is a `BlindingAccumulator<EvenScalar<P0>>`, to which a scalar of
+/// another curve cannot be added.
+#[derive(Clone)]
+pub struct BlindingAccumulator<F: BlindingScalar> {
+    terms: Vec<(&'static str, F)>,
+}
+
+/// The scalars of a `BlindingAccumulator`: the elements of a field, or wrappers of them.
+pub trait BlindingScalar: Copy + Send + Sync {
+    type Field: PrimeField;
+
+    fn from_field(field: Self::Field) -> Self;
+
+    fn into_field(self) -> Self::Field;
+}
+
+impl<F: PrimeField> BlindingScalar for F {
+    type Field = F;
+
+    fn from_field(field: F) -> Self {
+        field
+    }
+
+    fn into_field(self) -> F {
+        self
+    }
+}
+
+impl<F: BlindingScalar> BlindingAccumulator<F> {
+    pub fn new() -> Self {
+        Self { terms: Vec::new() }
+    }
+
+    /// An accumulator of the single term `scalar`.
+    pub fn from_term(label: &'static str, scalar: F) -> Self {
+        let mut accumulator = Self::new();
+        accumulator.add_term(label, scalar);
+        accumulator
+    }
+
+    pub fn add_term(&mut self, label: &'static str, scalar: F) {
+        self.terms.push((label, scalar));
+    }
+
+    /// Like `add_term`, returning the accumulator.
+    pub fn with_term(mut self, label: &'static str, scalar: F) -> Self {
+        self.add_term(label, scalar);
+        self
+    }
+
+    /// The terms in the order they were added.
+    pub fn terms(&self) -> &[(&'static str, F)] {
+        &self.terms
+    }
+
+    /// The sum of the terms, the blinding to commit with.
+    #[must_use]
+    pub fn total(&self) -> F {
+        F::from_field(
+            self.terms
+                .iter()
+                .map(|(_, scalar)| scalar.into_field())
+                .sum(),
+        )
+    }
+
+    /// The label of the first term after which the commitment to the partial sum of the blindings,
+    /// computed by `commit`, is not the one `expected` after the term of that label.
+    /// Terms without an expected commitment are only summed.
+    /// Returns `None` if all the expected commitments match.
+    ///
+    /// Debugging a prover: the partial sums are recommitted, which is only worth it once the final commitment did not match.
+    pub fn first_mismatch<G: PartialEq>(
+        &self,
+        expected: &[(&'static str, G)],
+        commit: impl Fn(F) -> G,
+    ) -> Option<&'static str> {
+        let mut partial_sum = F::Field::zero();
+        for (label, scalar) in &self.terms {
+            partial_sum += scalar.into_field();
+            for (expected_label, commitment) in expected {
+                if expected_label == label && commit(F::from_field(partial_sum)) != *commitment {
+                    return Some(label);
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<F: BlindingScalar> Default for BlindingAccumulator<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compares the totals in constant time, whatever the terms.
+impl<F: BlindingScalar> PartialEq for BlindingAccumulator<F> {
+    fn eq(&self, other: &Self) -> bool {
+        field_ct_eq(&self.total().into_field(), &other.total().into_field()).into()
+    }
+}
+
+impl<F: BlindingScalar> CanonicalSerialize for BlindingAccumulator<F> {
+    fn serialized_size(&self, mode: Compress) -> usize {
+        self.total().into_field().serialized_size(mode)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.total()
+            .into_field()
+            .serialize_with_mode(writer, compress)
+    }
+}
+
+impl<F: BlindingScalar> Valid for BlindingAccumulator<F> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+impl<F: BlindingScalar> CanonicalDeserialize for BlindingAccumulator<F> {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        Ok(Self::from_term(
+            SERIALIZED_TOTAL,
+            F::from_field(F::Field::deserialize_with_mode(reader, compress, validate)?),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::UniformRand;
+
+    type PallasScalar = ark_pallas::Fr;
+
+    #[test]
+    fn test_first_mismatch() {
+        let mut rng = rand::thread_rng();
+        let initial = PallasScalar::rand(&mut rng);
+        let offset = PallasScalar::rand(&mut rng);
+        let rerandomization = PallasScalar::rand(&mut rng);
+        let accumulator = BlindingAccumulator::from_term(INITIAL, initial)
+            .with_term(PERMISSIBLE_OFFSET, offset)
+            .with_term(SELECT_AND_RERANDOMIZE, rerandomization);
+        assert_eq!(accumulator.total(), initial + offset + rerandomization);
+
+        // The commitment is the blinding itself, which is enough to check the partial sums.
+        let commit = |blinding: PallasScalar| blinding;
+        let leaf = initial + offset;
+        let rerandomized_leaf = leaf + rerandomization;
+        assert_eq!(
+            accumulator.first_mismatch(
+                &[
+                    (PERMISSIBLE_OFFSET, leaf),
+                    (SELECT_AND_RERANDOMIZE, rerandomized_leaf)
+                ],
+                commit
+            ),
+            None
+        );
+        assert_eq!(
+            accumulator.first_mismatch(
+                &[
+                    (PERMISSIBLE_OFFSET, leaf + PallasScalar::from(1u64)),
+                    (SELECT_AND_RERANDOMIZE, rerandomized_leaf)
+                ],
+                commit
+            ),
+            Some(PERMISSIBLE_OFFSET)
+        );
+
+        // A wrong rerandomization, the leaf still matches.
+        let wrong = BlindingAccumulator::from_term(INITIAL, initial)
+            .with_term(PERMISSIBLE_OFFSET, offset)
+            .with_term(SELECT_AND_RERANDOMIZE, offset);
+        assert_eq!(
+            wrong.first_mismatch(
+                &[
+                    (PERMISSIBLE_OFFSET, leaf),
+                    (SELECT_AND_RERANDOMIZE, rerandomized_leaf)
+                ],
+                commit
+            ),
+            Some(SELECT_AND_RERANDOMIZE)
+        );
+    }
+
+    #[test]
+    fn test_serialization_keeps_total() {
+        let mut rng = rand::thread_rng();
+        let accumulator = BlindingAccumulator::from_term(INITIAL, PallasScalar::rand(&mut rng))
+            .with_term(PERMISSIBLE_OFFSET, PallasScalar::rand(&mut rng));
+        let mut bytes = Vec::new();
+        accumulator.serialize_compressed(&mut bytes).unwrap();
+        let mut total_bytes = Vec::new();
+        accumulator
+            .total()
+            .serialize_compressed(&mut total_bytes)
+            .unwrap();
+        assert_eq!(bytes, total_bytes);
+
+        let decoded =
+            BlindingAccumulator::<PallasScalar>::deserialize_compressed(bytes.as_slice()).unwrap();
+        assert!(decoded == accumulator);
+        assert_eq!(decoded.terms().len(), 1);
+        assert_eq!(decoded.terms()[0].0, SERIALIZED_TOTAL);
+    }
+}
@@ -0,0 +1,201 @@
+//! A bloom filter over the leaves of a curve tree, keyed by their compressed encodings.
+//!
+//! A wallet rescanning the chain checks whether the commitments it is looking for are leaves of the tree.
+//! The filter ships with a tree snapshot, and `LeafBloom::maybe_contains` skips the commitments that are
+//! definitely not leaves before any expensive work, e.g. looking them up among the leaves or deriving their openings.
+//! It has false positives, at a rate set by its bits per leaf, and never false negatives.
+
+use crate::protocol;
+
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use sha3::{Digest, Sha3_256};
+
+// The most positions of a leaf in a deserialized filter.
+const MAX_HASHES: u32 = 64;
+
+/// A bloom filter of the leaves of a tree, see `CurveTree::build_bloom`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LeafBloom {
+    // The number of positions of each leaf.
+    hashes: u32,
+    // The bits of the filter, 64 to a word.
+    words: Vec<u64>,
+}
+
+impl LeafBloom {
+    /// An empty filter for `leaf_count` leaves of `bits_per_leaf` bits each, at least one,
+    /// with the number of positions per leaf that minimizes the false positive rate for that many leaves:
+    /// 10 bits per leaf give a rate under 1%.
+    pub fn new(leaf_count: usize, bits_per_leaf: usize) -> Self {
+        let bits_per_leaf = bits_per_leaf.max(1);
+        let bit_count = leaf_count.max(1) * bits_per_leaf;
+        let hashes = ((bits_per_leaf as f64) * std::f64::consts::LN_2)
+            .round()
+            .max(1.0) as u32;
+        Self {
+            hashes,
+            words: vec![0; bit_count.div_ceil(64)],
+        }
+    }
+
+    pub fn insert<P: SWCurveConfig>(&mut self, leaf: &Affine<P>) {
+        for position in self.positions(leaf) {
+            self.words[position / 64] |= 1 << (position % 64);
+        }
+    }
+
+    /// Whether `leaf` may have been inserted: always if it was, and for other points at the false positive rate.
+    pub fn maybe_contains<P: SWCurveConfig>(&self, leaf: &Affine<P>) -> bool {
+        self.positions(leaf)
+            .all(|position| self.words[position / 64] & (1 << (position % 64)) != 0)
+    }
+
+    /// The number of bits of the filter.
+    pub fn bit_count(&self) -> usize {
+        self.words.len() * 64
+    }
+
+    /// The number of positions of each leaf.
+    pub fn hashes(&self) -> u32 {
+        self.hashes
+    }
+
+    // The positions of `leaf` by double hashing: the first two words of the hash of its encoding,
+    // the second odd so that it steps through all the positions.
+    fn positions<P: SWCurveConfig>(&self, leaf: &Affine<P>) -> impl Iterator<Item = usize> {
+        let mut hasher = Sha3_256::new();
+        hasher.update(protocol::LEAF_BLOOM_DOMAIN);
+        let mut bytes = Vec::with_capacity(leaf.compressed_size());
+        leaf.serialize_compressed(&mut bytes)
+            .expect("serializing to a vector does not fail");
+        hasher.update(&bytes);
+        let hash = hasher.finalize();
+        let word = |i: usize| u64::from_le_bytes(hash[8 * i..8 * (i + 1)].try_into().unwrap());
+        let (first, step) = (word(0), word(1) | 1);
+        let bit_count = self.bit_count() as u64;
+        (0..self.hashes as u64)
+            .map(move |i| (first.wrapping_add(i.wrapping_mul(step)) % bit_count) as usize)
+    }
+}
+
+impl CanonicalSerialize for LeafBloom {
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.hashes.serialized_size(compress) + self.words.serialized_size(compress)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.hashes.serialize_with_mode(&mut writer, compress)?;
+        self.words.serialize_with_mode(&mut writer, compress)
+    }
+}
+
+impl Valid for LeafBloom {
+    fn check(&self) -> Result<(), SerializationError> {
+        // An empty filter has no position to set, and a leaf at least one.
+        // More positions than those of 64 bits per leaf would only make a sent filter slow to query.
+        if self.hashes == 0 || self.hashes > MAX_HASHES || self.words.is_empty() {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(())
+    }
+}
+
+impl CanonicalDeserialize for LeafBloom {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let hashes = u32::deserialize_with_mode(&mut reader, compress, validate)?;
+        let len = u64::deserialize_with_mode(&mut reader, compress, validate)?;
+        // The words are read one at a time, a forged length fails when the input runs out rather than allocating.
+        let mut words = Vec::new();
+        for _ in 0..len {
+            words.push(u64::deserialize_with_mode(&mut reader, compress, validate)?);
+        }
+        let bloom = Self { hashes, words };
+        if let Validate::Yes = validate {
+            bloom.check()?;
+        }
+        Ok(bloom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ec::{CurveGroup, Group};
+    use ark_std::UniformRand;
+
+    type PallasConfig = ark_pallas::PallasConfig;
+    type PallasP = ark_pallas::Projective;
+
+    // Every inserted point is found, whatever the points and the size of the filter,
+    // and the false positive rate is about that of the size.
+    #[test]
+    fn test_no_false_negatives() {
+        let mut rng = rand::thread_rng();
+        for (leaf_count, bits_per_leaf) in [(1, 1), (7, 3), (100, 10), (1000, 1), (1000, 16)] {
+            let leaves: Vec<Affine<PallasConfig>> = (0..leaf_count)
+                .map(|_| PallasP::rand(&mut rng).into_affine())
+                .collect();
+            let mut bloom = LeafBloom::new(leaf_count, bits_per_leaf);
+            for leaf in &leaves {
+                bloom.insert(leaf);
+            }
+            for leaf in &leaves {
+                assert!(bloom.maybe_contains(leaf));
+            }
+        }
+
+        let mut bloom = LeafBloom::new(1000, 10);
+        for _ in 0..1000 {
+            bloom.insert(&PallasP::rand(&mut rng).into_affine());
+        }
+        let false_positives = (0..10000)
+            .filter(|_| bloom.maybe_contains(&PallasP::rand(&mut rng).into_affine()))
+            .count();
+        assert!(false_positives < 300, "{} false positives", false_positives);
+        assert!(!LeafBloom::new(1000, 10).maybe_contains(&PallasP::generator().into_affine()));
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut rng = rand::thread_rng();
+        let leaves: Vec<Affine<PallasConfig>> = (0..50)
+            .map(|_| PallasP::rand(&mut rng).into_affine())
+            .collect();
+        let mut bloom = LeafBloom::new(leaves.len(), 8);
+        for leaf in &leaves {
+            bloom.insert(leaf);
+        }
+        let mut bytes = Vec::new();
+        bloom.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), bloom.compressed_size());
+        let decoded = LeafBloom::deserialize_compressed(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, bloom);
+        assert!(leaves.iter().all(|leaf| decoded.maybe_contains(leaf)));
+
+        // A filter without bits, one whose length prefix exceeds its words, and one of too many positions.
+        let mut empty = Vec::new();
+        8u32.serialize_compressed(&mut empty).unwrap();
+        0u64.serialize_compressed(&mut empty).unwrap();
+        assert!(LeafBloom::deserialize_compressed(empty.as_slice()).is_err());
+        let mut truncated = Vec::new();
+        8u32.serialize_compressed(&mut truncated).unwrap();
+        u64::MAX.serialize_compressed(&mut truncated).unwrap();
+        assert!(LeafBloom::deserialize_compressed(truncated.as_slice()).is_err());
+        let mut slow = Vec::new();
+        u32::MAX.serialize_compressed(&mut slow).unwrap();
+        vec![0u64; 4].serialize_compressed(&mut slow).unwrap();
+        assert!(LeafBloom::deserialize_compressed(slow.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,747 @@
+//! Serializable descriptions of the circuits proved with the relations in this crate.
+//!
+//! A `CircuitDescription` lists the steps of a fixed relation, e.g. membership in a tree with branching factor 256 and height 4
+//! followed by a 64-bit range proof, and both the prover and the verifier are built from it by `prove` and `verify`.
+//! Since neither side lays down constraints by hand, they cannot drift apart,
+//! and a verifier can check proofs for any description it loads.
+//! The hash of the description is absorbed into both transcripts, binding every proof to the exact circuit it was made for.
+
+use bulletproofs::r1cs::*;
+use bulletproofs::PrecomputedGens;
+
+#[cfg(feature = "prover")]
+use crate::convert::fe_from_u64;
+#[cfg(feature = "prover")]
+use crate::curve_tree::prove_pair;
+use crate::curve_tree::{CurveTree, SelRerandParameters, SelectAndRerandomizePath};
+use crate::cycle::EvenScalar;
+use crate::error::Error;
+use crate::limits::{
+    deserialize_with_default_limits, DeserializeLimits, DeserializeWithLimits, MAX_SUPPORTED_DEPTH,
+};
+use crate::metrics::{self, Metrics, NoMetrics};
+use crate::protocol;
+use crate::range_proof::range_proof;
+
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine, AffineRepr};
+use ark_ff::PrimeField;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use merlin::Transcript;
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+use std::sync::Arc;
+
+/// The version of the serialization and of the way circuits are laid out for a description.
+/// Descriptions of a different version are rejected when deserialized.
+pub const CIRCUIT_DESCRIPTION_VERSION: u8 = 1;
+
+/// One step of a circuit, laying down the constraints of a single relation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitStep {
+    /// A commitment is a rerandomization of a leaf of a Curve Tree with this shape.
+    /// Lays down constraints on both curves.
+    SelectAndRerandomize {
+        branching_factor: usize,
+        height: usize,
+    },
+    /// A value committed on the even curve is in the range [0, 2^bits).
+    RangeProof { bits: usize },
+}
+
+/// The private inputs of a step, in the same order as the steps of the description.
+pub enum StepWitness<'a, const L: usize, P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy> {
+    SelectAndRerandomize {
+        tree: &'a CurveTree<L, P0, P1>,
+        index: usize,
+    },
+    RangeProof {
+        value: u64,
+        blinding: P0::ScalarField,
+    },
+}
+
+/// The public inputs of a step, in the same order as the steps of the description.
+/// `prove` returns the statements of the steps it proved.
+pub enum StepStatement<const L: usize, P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy> {
+    /// The rerandomized path, its last commitment is the rerandomized leaf.
+    SelectAndRerandomize {
+        path: SelectAndRerandomizePath<L, P0, P1>,
+        /// The rerandomization scalar of the leaf, only known to the prover.
+        rerandomization: Option<EvenScalar<P0>>,
+    },
+    RangeProof {
+        commitment: Affine<P0>,
+    },
+}
+
+/// A proof of a whole circuit, made of one proof on each curve.
+pub struct CircuitProof<P0: SWCurveConfig, P1: SWCurveConfig> {
+    pub even_proof: R1CSProof<Affine<P0>>,
+    pub odd_proof: R1CSProof<Affine<P1>>,
+}
+
+/// A proof of a circuit with the statements of its steps, as returned by `CircuitDescription::prove`.
+pub type ProvenCircuit<const L: usize, P0, P1> =
+    (CircuitProof<P0, P1>, Vec<StepStatement<L, P0, P1>>);
+
+/// The verifiers of both curves, with the steps of a circuit laid down.
+type CircuitVerifiers<P0, P1> = (
+    Verifier<Transcript, Affine<P0>>,
+    Verifier<Transcript, Affine<P1>>,
+);
+
+/// The sequence of steps a circuit is built from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitDescription {
+    pub steps: Vec<CircuitStep>,
+}
+
+fn check_height(circuit_height: usize, tree_height: usize) -> Result<(), Error> {
+    if circuit_height != tree_height {
+        return Err(Error::parameter_mismatch(format!(
+            "The circuit is for trees of height {}, not {}",
+            circuit_height, tree_height
+        )));
+    }
+    Ok(())
+}
+
+impl CircuitDescription {
+    pub fn new(steps: Vec<CircuitStep>) -> Self {
+        Self { steps }
+    }
+
+    /// A hash of the canonical serialization of the description.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut bytes = Vec::with_capacity(self.compressed_size());
+        self.serialize_compressed(&mut bytes)
+            .expect("serializing to a vector does not fail");
+        let mut transcript = Transcript::new(protocol::CIRCUIT_DESCRIPTION);
+        transcript.append_message(protocol::DESCRIPTION, &bytes);
+        let mut hash = [0u8; 32];
+        transcript.challenge_bytes(protocol::DESCRIPTION_HASH, &mut hash);
+        hash
+    }
+
+    /// Absorbs the hash of the description into `transcript`.
+    pub fn bind(&self, transcript: &mut Transcript) {
+        transcript.append_message(protocol::CIRCUIT, &self.hash());
+    }
+
+    /// Checks that the steps can be laid out with trees of branching factor `L` and of height at most `MAX_SUPPORTED_DEPTH`.
+    pub fn check<const L: usize>(&self) -> Result<(), Error> {
+        for step in &self.steps {
+            match *step {
+                CircuitStep::SelectAndRerandomize {
+                    branching_factor,
+                    height,
+                } => {
+                    if branching_factor != L {
+                        return Err(Error::parameter_mismatch(format!(
+                            "The circuit has branching factor {}, the tree {}",
+                            branching_factor, L
+                        )));
+                    }
+                    if height == 0 {
+                        return Err(Error::parameter_mismatch("Trees have height at least 1"));
+                    }
+                    DeserializeLimits::check(
+                        height as u64,
+                        MAX_SUPPORTED_DEPTH,
+                        "the height of a circuit step",
+                    )?;
+                }
+                CircuitStep::RangeProof { bits } => {
+                    if bits == 0 || bits > 64 {
+                        return Err(Error::parameter_mismatch(format!(
+                            "Range proofs are over 1 to 64 bits, not {}",
+                            bits
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Lays down the steps of the circuit with `witnesses`, one for each step, and proves them.
+    /// Returns the proof and the statements the verifier needs, in the order of the steps.
+    #[cfg(feature = "prover")]
+    pub fn prove<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+        R: RngCore + CryptoRng,
+    >(
+        &self,
+        label: &'static [u8],
+        witnesses: &[StepWitness<L, P0, P1>],
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<ProvenCircuit<L, P0, P1>, Error> {
+        self.prove_with_transcripts(
+            Transcript::new(label),
+            Transcript::new(label),
+            witnesses,
+            parameters,
+            rng,
+        )
+    }
+
+    /// Like `prove`, from transcripts which may already hold messages, e.g. those of a `ProvingSession`.
+    #[cfg(feature = "prover")]
+    pub(crate) fn prove_with_transcripts<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+        R: RngCore + CryptoRng,
+    >(
+        &self,
+        mut even_transcript: Transcript,
+        mut odd_transcript: Transcript,
+        witnesses: &[StepWitness<L, P0, P1>],
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<ProvenCircuit<L, P0, P1>, Error> {
+        self.check::<L>()?;
+        if witnesses.len() != self.steps.len() {
+            return Err(Error::inconsistent_witness(format!(
+                "Expected {} step witnesses, got {}",
+                self.steps.len(),
+                witnesses.len()
+            )));
+        }
+
+        self.bind(&mut even_transcript);
+        self.bind(&mut odd_transcript);
+        let mut even_prover = Prover::new(&parameters.even_parameters.pc_gens, even_transcript);
+        let mut odd_prover = Prover::new(&parameters.odd_parameters.pc_gens, odd_transcript);
+
+        let mut statements = Vec::with_capacity(self.steps.len());
+        for (step, witness) in self.steps.iter().zip(witnesses) {
+            let statement = match (*step, witness) {
+                (
+                    CircuitStep::SelectAndRerandomize { height, .. },
+                    StepWitness::SelectAndRerandomize { tree, index },
+                ) => {
+                    check_height(height, tree.height())?;
+                    let (path, rerandomization) = tree.select_and_rerandomize_prover_gadget(
+                        *index,
+                        &mut even_prover,
+                        &mut odd_prover,
+                        parameters,
+                        rng,
+                    )?;
+                    StepStatement::SelectAndRerandomize {
+                        path,
+                        rerandomization: Some(rerandomization),
+                    }
+                }
+                (CircuitStep::RangeProof { bits }, StepWitness::RangeProof { value, blinding }) => {
+                    let (commitment, variable) = even_prover.commit(fe_from_u64(*value), *blinding);
+                    range_proof(&mut even_prover, variable.into(), Some(*value), bits)?;
+                    StepStatement::RangeProof { commitment }
+                }
+                _ => {
+                    return Err(Error::inconsistent_witness(format!(
+                        "Wrong witness for step {:?}",
+                        step
+                    )))
+                }
+            };
+            statements.push(statement);
+        }
+
+        let (even_proof, odd_proof) = prove_pair(even_prover, odd_prover, parameters)?;
+        Ok((
+            CircuitProof {
+                even_proof,
+                odd_proof,
+            },
+            statements,
+        ))
+    }
+
+    /// Lays down the steps of the circuit with `statements`, one for each step, and verifies `proof` against them.
+    /// `trees` holds the tree of each `SelectAndRerandomize` step, in order.
+    pub fn verify<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    >(
+        &self,
+        label: &'static [u8],
+        statements: Vec<StepStatement<L, P0, P1>>,
+        trees: &[&CurveTree<L, P0, P1>],
+        proof: &CircuitProof<P0, P1>,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<(), Error> {
+        self.verify_with_transcripts(
+            Transcript::new(label),
+            Transcript::new(label),
+            statements,
+            trees,
+            proof,
+            parameters,
+        )
+    }
+
+    /// Like `verify`, from transcripts which may already hold messages, e.g. those of a `VerificationSession`.
+    pub(crate) fn verify_with_transcripts<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    >(
+        &self,
+        even_transcript: Transcript,
+        odd_transcript: Transcript,
+        statements: Vec<StepStatement<L, P0, P1>>,
+        trees: &[&CurveTree<L, P0, P1>],
+        proof: &CircuitProof<P0, P1>,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<(), Error> {
+        let (even_verifier, odd_verifier) = self.verifiers(
+            even_transcript,
+            odd_transcript,
+            statements,
+            trees,
+            parameters,
+        )?;
+        even_verifier.verify(
+            &proof.even_proof,
+            &parameters.even_parameters.pc_gens,
+            &parameters.even_parameters.bp_gens,
+        )?;
+        odd_verifier.verify(
+            &proof.odd_proof,
+            &parameters.odd_parameters.pc_gens,
+            &parameters.odd_parameters.bp_gens,
+        )?;
+        Ok(())
+    }
+
+    /// Lays down the steps of the circuit with `statements` on a verifier for each curve, starting from the transcripts.
+    fn verifiers<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    >(
+        &self,
+        mut even_transcript: Transcript,
+        mut odd_transcript: Transcript,
+        statements: Vec<StepStatement<L, P0, P1>>,
+        trees: &[&CurveTree<L, P0, P1>],
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<CircuitVerifiers<P0, P1>, Error> {
+        self.check::<L>()?;
+        if statements.len() != self.steps.len() {
+            return Err(Error::parameter_mismatch(format!(
+                "Expected {} step statements, got {}",
+                self.steps.len(),
+                statements.len()
+            )));
+        }
+
+        self.bind(&mut even_transcript);
+        self.bind(&mut odd_transcript);
+        let mut even_verifier = Verifier::new(even_transcript);
+        let mut odd_verifier = Verifier::new(odd_transcript);
+
+        let mut trees = trees.iter();
+        for (step, statement) in self.steps.iter().zip(statements) {
+            match (*step, statement) {
+                (
+                    CircuitStep::SelectAndRerandomize { height, .. },
+                    StepStatement::SelectAndRerandomize { path, .. },
+                ) => {
+                    let tree = trees
+                        .next()
+                        .ok_or_else(|| Error::parameter_mismatch("Missing a tree"))?;
+                    check_height(height, tree.height())?;
+                    if !tree.is_valid_path(&path) {
+                        return Err(Error::InvalidPath);
+                    }
+                    let _rerandomized_leaf = tree.select_and_rerandomize_verifier_gadget(
+                        &mut even_verifier,
+                        &mut odd_verifier,
+                        path,
+                        parameters,
+                    )?;
+                }
+                (CircuitStep::RangeProof { bits }, StepStatement::RangeProof { commitment }) => {
+                    let variable = even_verifier.commit(commitment);
+                    range_proof(&mut even_verifier, variable.into(), None, bits)?;
+                }
+                (step, _) => {
+                    return Err(Error::parameter_mismatch(format!(
+                        "Wrong statement for step {:?}",
+                        step
+                    )))
+                }
+            }
+        }
+        if trees.next().is_some() {
+            return Err(Error::parameter_mismatch("Too many trees"));
+        }
+
+        Ok((even_verifier, odd_verifier))
+    }
+}
+
+/// A verifier of the proofs of a single circuit, with what only depends on the description computed once:
+/// the shapes of the paths and of the proofs on each curve, and the multiples of the generators a proof of
+/// that size is verified with.
+///
+/// Verifying a proof on its own is dominated by the multiscalar multiplication over the generators,
+/// which `batch_verify` amortizes over the proofs of a batch and the precomputed generators speed up for a single proof.
+/// The constraints are still laid down for each proof, since their constants and the transcripts depend on the path and the root.
+pub struct SelRerandVerificationContext<
+    'a,
+    const L: usize,
+    P0: SWCurveConfig + Copy,
+    P1: SWCurveConfig + Copy,
+> {
+    description: CircuitDescription,
+    parameters: &'a SelRerandParameters<P0, P1>,
+    /// The numbers of even and odd commitments of the path of each `SelectAndRerandomize` step.
+    path_lengths: Vec<(usize, usize)>,
+    even_gens: PrecomputedGens<Affine<P0>>,
+    odd_gens: PrecomputedGens<Affine<P1>>,
+    metrics: Arc<dyn Metrics>,
+}
+
+impl<
+        'a,
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    > SelRerandVerificationContext<'a, L, P0, P1>
+{
+    /// Lays down the circuit of `description` once with placeholder statements to learn its shape,
+    /// and precomputes the generators for proofs of its size on each curve.
+    pub fn new(
+        parameters: &'a SelRerandParameters<P0, P1>,
+        description: CircuitDescription,
+    ) -> Result<Self, Error> {
+        description.check::<L>()?;
+
+        let (leaf, _) = parameters.even_parameters.uh.permissible_commitment(
+            &parameters.even_parameters.pc_gens.B,
+            &parameters.even_parameters.pc_gens.B_blinding,
+        );
+        let mut trees = Vec::new();
+        let mut statements = Vec::with_capacity(description.steps.len());
+        let mut path_lengths = Vec::new();
+        for step in &description.steps {
+            match *step {
+                CircuitStep::SelectAndRerandomize { height, .. } => {
+                    let tree = CurveTree::<L, P0, P1>::from_set(&[leaf], parameters, Some(height))?;
+                    let (even_length, odd_length) = tree.path_lengths();
+                    statements.push(StepStatement::SelectAndRerandomize {
+                        path: SelectAndRerandomizePath {
+                            even_commitments: vec![Affine::generator(); even_length],
+                            odd_commitments: vec![Affine::generator(); odd_length],
+                        },
+                        rerandomization: None,
+                    });
+                    path_lengths.push((even_length, odd_length));
+                    trees.push(tree);
+                }
+                CircuitStep::RangeProof { .. } => statements.push(StepStatement::RangeProof {
+                    commitment: Affine::generator(),
+                }),
+            }
+        }
+        let trees: Vec<_> = trees.iter().collect();
+        let (even_verifier, odd_verifier) = description.verifiers(
+            Transcript::new(protocol::SHAPE),
+            Transcript::new(protocol::SHAPE),
+            statements,
+            &trees,
+            parameters,
+        )?;
+
+        Ok(Self {
+            even_gens: PrecomputedGens::new(
+                &parameters.even_parameters.pc_gens,
+                &parameters.even_parameters.bp_gens,
+                even_verifier.size().next_power_of_two(),
+            )?,
+            odd_gens: PrecomputedGens::new(
+                &parameters.odd_parameters.pc_gens,
+                &parameters.odd_parameters.bp_gens,
+                odd_verifier.size().next_power_of_two(),
+            )?,
+            description,
+            parameters,
+            path_lengths,
+            metrics: Arc::new(NoMetrics),
+        })
+    }
+
+    /// Counts the proofs verified and failing, by the kind of their error, in `metrics`,
+    /// and observes the size of the multiscalar multiplication of each, see `metrics::MSM_SIZE`.
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn description(&self) -> &CircuitDescription {
+        &self.description
+    }
+
+    /// Like `CircuitDescription::verify`, rejecting paths and proofs of another shape with `Error::ShapeMismatch`
+    /// before laying down any constraints.
+    pub fn verify(
+        &self,
+        label: &'static [u8],
+        statements: Vec<StepStatement<L, P0, P1>>,
+        trees: &[&CurveTree<L, P0, P1>],
+        proof: &CircuitProof<P0, P1>,
+    ) -> Result<(), Error> {
+        let result = self.verify_unmetered(label, statements, trees, proof);
+        metrics::count_result(
+            &*self.metrics,
+            metrics::PROOFS_VERIFIED,
+            metrics::PROOF_FAILURES,
+            &result,
+        );
+        result
+    }
+
+    fn verify_unmetered(
+        &self,
+        label: &'static [u8],
+        statements: Vec<StepStatement<L, P0, P1>>,
+        trees: &[&CurveTree<L, P0, P1>],
+        proof: &CircuitProof<P0, P1>,
+    ) -> Result<(), Error> {
+        let paths = statements.iter().filter_map(|statement| match statement {
+            StepStatement::SelectAndRerandomize { path, .. } => Some(path),
+            StepStatement::RangeProof { .. } => None,
+        });
+        for (path, &(even_length, odd_length)) in paths.zip(&self.path_lengths) {
+            if path.even_commitments.len() != even_length
+                || path.odd_commitments.len() != odd_length
+            {
+                return Err(Error::shape_mismatch(format!(
+                    "Expected a path of {} even and {} odd commitments, got {} and {}",
+                    even_length,
+                    odd_length,
+                    path.even_commitments.len(),
+                    path.odd_commitments.len()
+                )));
+            }
+        }
+        if proof.even_proof.padded_multipliers() != Some(self.even_gens.padded_n())
+            || proof.odd_proof.padded_multipliers() != Some(self.odd_gens.padded_n())
+        {
+            return Err(Error::shape_mismatch(format!(
+                "Expected proofs of {} even and {} odd padded multipliers",
+                self.even_gens.padded_n(),
+                self.odd_gens.padded_n()
+            )));
+        }
+
+        let (even_verifier, odd_verifier) = self.description.verifiers(
+            Transcript::new(label),
+            Transcript::new(label),
+            statements,
+            trees,
+            self.parameters,
+        )?;
+        for padded_n in [self.even_gens.padded_n(), self.odd_gens.padded_n()] {
+            self.metrics
+                .observe(metrics::MSM_SIZE, 2 + 2 * padded_n as u64);
+        }
+        even_verifier.verify_precomputed(&proof.even_proof, &self.even_gens)?;
+        odd_verifier.verify_precomputed(&proof.odd_proof, &self.odd_gens)?;
+        Ok(())
+    }
+}
+
+const SELECT_AND_RERANDOMIZE: u8 = 0;
+const RANGE_PROOF: u8 = 1;
+
+impl CanonicalSerialize for CircuitStep {
+    fn serialized_size(&self, compress: Compress) -> usize {
+        1 + match self {
+            Self::SelectAndRerandomize { .. } => 2 * 0u64.serialized_size(compress),
+            Self::RangeProof { .. } => 0u64.serialized_size(compress),
+        }
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        match *self {
+            Self::SelectAndRerandomize {
+                branching_factor,
+                height,
+            } => {
+                SELECT_AND_RERANDOMIZE.serialize_with_mode(&mut writer, compress)?;
+                (branching_factor as u64).serialize_with_mode(&mut writer, compress)?;
+                (height as u64).serialize_with_mode(&mut writer, compress)?;
+            }
+            Self::RangeProof { bits } => {
+                RANGE_PROOF.serialize_with_mode(&mut writer, compress)?;
+                (bits as u64).serialize_with_mode(&mut writer, compress)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Valid for CircuitStep {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl CanonicalDeserialize for CircuitStep {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        deserialize_with_default_limits(reader, compress, validate)
+    }
+}
+
+impl DeserializeWithLimits for CircuitStep {
+    fn deserialize_with_limits<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+        limits: &DeserializeLimits,
+    ) -> Result<Self, Error> {
+        let read_usize = |reader: &mut R| -> Result<usize, Error> {
+            usize::try_from(u64::deserialize_with_mode(reader, compress, validate)?)
+                .map_err(|_| SerializationError::InvalidData.into())
+        };
+        match u8::deserialize_with_mode(&mut reader, compress, validate)? {
+            SELECT_AND_RERANDOMIZE => Ok(Self::SelectAndRerandomize {
+                branching_factor: DeserializeLimits::check(
+                    u64::deserialize_with_mode(&mut reader, compress, validate)?,
+                    limits.max_arity,
+                    "the branching factor of a circuit step",
+                )?,
+                height: DeserializeLimits::check(
+                    u64::deserialize_with_mode(&mut reader, compress, validate)?,
+                    limits.max_depth,
+                    "the height of a circuit step",
+                )?,
+            }),
+            RANGE_PROOF => Ok(Self::RangeProof {
+                bits: read_usize(&mut reader)?,
+            }),
+            _ => Err(SerializationError::InvalidData.into()),
+        }
+    }
+}
+
+impl CanonicalSerialize for CircuitDescription {
+    fn serialized_size(&self, compress: Compress) -> usize {
+        CIRCUIT_DESCRIPTION_VERSION.serialized_size(compress) + self.steps.serialized_size(compress)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        CIRCUIT_DESCRIPTION_VERSION.serialize_with_mode(&mut writer, compress)?;
+        self.steps.serialize_with_mode(&mut writer, compress)
+    }
+}
+
+impl Valid for CircuitDescription {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl CanonicalDeserialize for CircuitDescription {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        deserialize_with_default_limits(reader, compress, validate)
+    }
+}
+
+impl DeserializeWithLimits for CircuitDescription {
+    fn deserialize_with_limits<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+        limits: &DeserializeLimits,
+    ) -> Result<Self, Error> {
+        if u8::deserialize_with_mode(&mut reader, compress, validate)?
+            != CIRCUIT_DESCRIPTION_VERSION
+        {
+            return Err(SerializationError::InvalidData.into());
+        }
+        let len = DeserializeLimits::check(
+            u64::deserialize_with_mode(&mut reader, compress, validate)?,
+            limits.max_circuit_steps,
+            "the number of steps of a circuit",
+        )?;
+        let steps = (0..len)
+            .map(|_| CircuitStep::deserialize_with_limits(&mut reader, compress, validate, limits))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { steps })
+    }
+}
+
+impl<P0: SWCurveConfig, P1: SWCurveConfig> CanonicalSerialize for CircuitProof<P0, P1> {
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.even_proof.serialized_size(compress) + self.odd_proof.serialized_size(compress)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.even_proof.serialize_with_mode(&mut writer, compress)?;
+        self.odd_proof.serialize_with_mode(&mut writer, compress)
+    }
+}
+
+impl<P0: SWCurveConfig, P1: SWCurveConfig> Valid for CircuitProof<P0, P1> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl<P0: SWCurveConfig, P1: SWCurveConfig> CanonicalDeserialize for CircuitProof<P0, P1> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        Ok(Self {
+            even_proof: R1CSProof::deserialize_with_mode(&mut reader, compress, validate)?,
+            odd_proof: R1CSProof::deserialize_with_mode(&mut reader, compress, validate)?,
+        })
+    }
+}
@@ -2,28 +2,77 @@ use ark_serialize::Compress;
 use ark_serialize::Valid;
 use bulletproofs::r1cs::*;
 use merlin::Transcript;
-use rand::Rng;
+use rand::{CryptoRng, RngCore};
 
+use crate::accumulator::Accumulator;
+use crate::blinding::{
+    BlindingAccumulator, INITIAL, PERMISSIBLE_OFFSET, PK_RERANDOMIZATION, SELECT_AND_RERANDOMIZE,
+};
+use crate::convert::{fe_from_u64, hash_to_field};
+use crate::ct::{field_ct_eq, point_ct_eq};
 use crate::curve_tree::*;
-use crate::range_proof::*;
+use crate::cycle::EvenScalar;
+use crate::equal_value::ValueOpening;
+use crate::error::Error;
+use crate::layout::{CommitmentLayout, TAG, VALUE};
+use crate::limits::{
+    deserialize_vec, deserialize_with_default_limits, DeserializeLimits, DeserializeWithLimits,
+};
+use crate::metrics::{self, Metrics, NoMetrics};
+use crate::opened::{enforce_tag, enforce_value_range, OpenedLeaf};
+use crate::policy::ValuePolicy;
+use crate::protocol;
+use crate::range_proof::range_proof;
 use crate::single_level_select_and_rerandomize::*;
+use crate::size::SizeReport;
+use crate::strictness::Strictness;
+use crate::version::FormatVersion;
+use crate::wallet::TagSet;
 
 use ark_crypto_primitives::{
     signature::schnorr::{Parameters, PublicKey, Schnorr, SecretKey, Signature},
     signature::*,
 };
-use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine, CurveGroup};
-use ark_ff::PrimeField;
+use ark_ec::{
+    models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine, AffineRepr, CurveConfig,
+    CurveGroup,
+};
+use ark_ff::{Field, PrimeField, Zero};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
 use ark_std::UniformRand;
 use blake2::Blake2s256 as Blake2s;
+use sha3::{Digest, Sha3_256};
 use std::marker::PhantomData;
-
+use subtle::ConstantTimeEq;
+
+/// A minted coin with its commitment and the variable of its value, as returned by `Coin::mint`.
+pub type MintOutput<P0, C> = (
+    Coin<P0, C>,
+    Affine<P0>,
+    Variable<<P0 as CurveConfig>::ScalarField>,
+);
+
+/// A coin with its commitment, as returned by `Coin::new_batch` for each output.
+pub type NewCoin<P0, C> = (Coin<P0, C>, Affine<P0>);
+
+/// A stable identifier of a coin, see `Coin::id`.
+pub type CoinId = [u8; 32];
+
+/// The rerandomized path to a spent coin and the opening of its rerandomized commitment, as returned by `Coin::prove_spend`.
+pub type SpendOutput<const L: usize, P0, P1> = (
+    SelectAndRerandomizePath<L, P0, P1>,
+    OpenedLeaf<<P0 as CurveConfig>::ScalarField>,
+);
+
+/// A coin of any value, zero included: pours balance with zero value outputs,
+/// and the commitment of a zero value coin still commits to its tag.
+/// The receiver's public key must not be the identity, for which anyone can sign.
+#[derive(Clone)]
 pub struct Coin<P0: SWCurveConfig + Clone, C: CurveGroup> {
     pub value: u64,
     pub tag: P0::ScalarField, // spending tag derived from the rerandomized public key
-    pub permissible_randomness: P0::ScalarField, // hiding and permissible randomness used to commit to `tag` and `value`
-    pub pk_randomness: C::ScalarField, // the randomness used to randomize the public key, needed for the receivers signature
+    pub commitment_blinding: BlindingAccumulator<EvenScalar<P0>>, // hiding and permissible randomness used to commit to `tag` and `value`
+    pub pk_randomness: BlindingAccumulator<C::ScalarField>, // the randomness used to randomize the public key, needed for the receivers signature
 }
 
 impl<
@@ -33,57 +82,178 @@ impl<
         C: CurveGroup,
     > Coin<P0, C>
 {
-    pub fn mint<R: Rng>(
+    /// Mints a coin of `value` to `pk`, proving its value in the range of `policy`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn mint<R: RngCore + CryptoRng>(
         value: u64,
         pk: &PublicKey<C>,
         parameters: &Parameters<C, Blake2s>,
         sr_parameters: &SingleLayerParameters<P0>,
+        layout: &CommitmentLayout,
+        policy: &ValuePolicy,
         rng: &mut R,
         prover: &mut Prover<Transcript, Affine<P0>>,
-    ) -> (Coin<P0, C>, Affine<P0>, Variable<P0::ScalarField>) {
-        let (coin, _) = Self::new(value, pk, parameters, sr_parameters, rng);
+    ) -> Result<MintOutput<P0, C>, Error> {
+        Self::mint_bounded(
+            value,
+            None,
+            pk,
+            parameters,
+            sr_parameters,
+            layout,
+            policy,
+            rng,
+            prover,
+        )
+    }
 
-        let (coin_commitment, variables) = prover.commit_vec(
-            &[P0::ScalarField::from(value), coin.tag],
-            coin.permissible_randomness,
-            &sr_parameters.bp_gens,
-        );
-        range_proof(prover, variables[0].into(), Some(value), 64).unwrap(); // todo what range do we want to enforce? Table of benchmarks for different powers?
+    /// Like `mint`, but if `max_value` is given the value is proven to be in [0, max_value)
+    /// rather than in the full range of `policy`, which must contain it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn mint_bounded<R: RngCore + CryptoRng>(
+        value: u64,
+        max_value: Option<u64>,
+        pk: &PublicKey<C>,
+        parameters: &Parameters<C, Blake2s>,
+        sr_parameters: &SingleLayerParameters<P0>,
+        layout: &CommitmentLayout,
+        policy: &ValuePolicy,
+        rng: &mut R,
+        prover: &mut Prover<Transcript, Affine<P0>>,
+    ) -> Result<MintOutput<P0, C>, Error> {
+        let (coin, _) = Self::new(value, pk, parameters, sr_parameters, layout, rng)?;
+        let (coin_commitment, value_variable) =
+            coin.opening()
+                .prove_mint(max_value, sr_parameters, layout, policy, prover)?;
+        Ok((coin, coin_commitment, value_variable))
+    }
 
-        (coin, coin_commitment, variables[0])
+    /// Like `mint`, for a deposit of `amount` from the transparent chain: the value of the coin is `amount`,
+    /// and the proof also shows it to be, binding the hidden value to the public amount.
+    /// The public key and the randomness of the commitment stay hidden. Verified by `verify_deposit_mint`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn deposit_mint<R: RngCore + CryptoRng>(
+        amount: u64,
+        pk: &PublicKey<C>,
+        parameters: &Parameters<C, Blake2s>,
+        sr_parameters: &SingleLayerParameters<P0>,
+        layout: &CommitmentLayout,
+        policy: &ValuePolicy,
+        rng: &mut R,
+        prover: &mut Prover<Transcript, Affine<P0>>,
+    ) -> Result<MintOutput<P0, C>, Error> {
+        let (coin, coin_commitment, value_variable) = Self::mint(
+            amount,
+            pk,
+            parameters,
+            sr_parameters,
+            layout,
+            policy,
+            rng,
+            prover,
+        )?;
+        constrain_public_amount(prover, value_variable, amount);
+        Ok((coin, coin_commitment, value_variable))
     }
 
-    pub fn new<R: Rng>(
+    pub fn new<R: RngCore + CryptoRng>(
         value: u64,
         pk: &PublicKey<C>,
         parameters: &Parameters<C, Blake2s>,
         sr_parameters: &SingleLayerParameters<P0>,
+        layout: &CommitmentLayout,
         rng: &mut R,
-    ) -> (Coin<P0, C>, Affine<P0>) {
+    ) -> Result<(Coin<P0, C>, Affine<P0>), Error> {
+        check_not_identity(pk)?;
+        // Always random, never zero or reused: the tag revealed by spending the coin is derived from the rerandomized key,
+        // a zero rerandomization would reveal the receiver's key and link all the coins minted to it.
         let pk_rerandomization = C::ScalarField::rand(rng);
         let randomized_pk = Self::rerandomized_pk(pk, &pk_rerandomization, parameters);
         let output_tag = Self::pk_to_scalar(&randomized_pk);
 
+        let initial_blinding = P0::ScalarField::rand(rng);
         let (coin_commitment, permissible_randomness) = sr_parameters.permissible_commitment(
-            &[P0::ScalarField::from(value), output_tag],
-            P0::ScalarField::rand(rng),
+            &layout.vector(&[(VALUE, fe_from_u64(value)), (TAG, output_tag)])?,
+            initial_blinding,
             0, // todo
-        );
+        )?;
 
-        (
+        Ok((
             Coin {
                 value,
                 tag: output_tag,
-                permissible_randomness,
-                pk_randomness: pk_rerandomization,
+                commitment_blinding: BlindingAccumulator::from_term(
+                    INITIAL,
+                    EvenScalar(initial_blinding),
+                )
+                .with_term(
+                    PERMISSIBLE_OFFSET,
+                    EvenScalar(permissible_randomness - initial_blinding),
+                ),
+                pk_randomness: BlindingAccumulator::from_term(
+                    PK_RERANDOMIZATION,
+                    pk_rerandomization,
+                ),
             },
             coin_commitment,
-        )
+        ))
+    }
+
+    /// Like `Coin::new` for each value and public key of `outputs`, in the same order,
+    /// for minting many coins at once: the permissible commitments are searched in batches,
+    /// in parallel with the `parallel` feature.
+    /// Draws the same randomness from `rng` as calling `Coin::new` for each output, and so returns the same coins.
+    pub fn new_batch<R: RngCore + CryptoRng>(
+        outputs: &[(u64, PublicKey<C>)],
+        parameters: &Parameters<C, Blake2s>,
+        sr_parameters: &SingleLayerParameters<P0>,
+        layout: &CommitmentLayout,
+        rng: &mut R,
+    ) -> Result<Vec<NewCoin<P0, C>>, Error> {
+        let mut coins = Vec::with_capacity(outputs.len());
+        let mut vectors = Vec::with_capacity(outputs.len());
+        for (value, pk) in outputs {
+            check_not_identity(pk)?;
+            let pk_rerandomization = C::ScalarField::rand(rng);
+            let randomized_pk = Self::rerandomized_pk(pk, &pk_rerandomization, parameters);
+            let output_tag = Self::pk_to_scalar(&randomized_pk);
+            let initial_blinding = P0::ScalarField::rand(rng);
+            vectors.push((
+                layout.vector(&[(VALUE, fe_from_u64(*value)), (TAG, output_tag)])?,
+                initial_blinding,
+            ));
+            coins.push(Coin {
+                value: *value,
+                tag: output_tag,
+                // the permissible offset is added once the commitment is found
+                commitment_blinding: BlindingAccumulator::from_term(
+                    INITIAL,
+                    EvenScalar(initial_blinding),
+                ),
+                pk_randomness: BlindingAccumulator::from_term(
+                    PK_RERANDOMIZATION,
+                    pk_rerandomization,
+                ),
+            });
+        }
+
+        let commitments = sr_parameters.blinded_permissible_commitments(&vectors, 0)?;
+        Ok(coins
+            .into_iter()
+            .zip(commitments)
+            .map(|(mut coin, (coin_commitment, permissible_randomness))| {
+                let offset = EvenScalar(permissible_randomness) - coin.commitment_blinding.total();
+                coin.commitment_blinding
+                    .add_term(PERMISSIBLE_OFFSET, offset);
+                (coin, coin_commitment)
+            })
+            .collect())
     }
 
-    fn pk_to_scalar(pk: &PublicKey<C>) -> P0::ScalarField {
+    pub(crate) fn pk_to_scalar(pk: &PublicKey<C>) -> P0::ScalarField {
         let mut pk_bytes = Vec::new();
-        pk.serialize_compressed(&mut pk_bytes).unwrap();
+        pk.serialize_compressed(&mut pk_bytes)
+            .expect("serializing to a vector does not fail");
         element_from_bytes_stat::<P0::ScalarField>(&pk_bytes)
     }
 
@@ -95,10 +265,168 @@ impl<
         let mut randomness = Vec::new();
         rerandomization
             .serialize_compressed(&mut randomness)
-            .unwrap();
-        Schnorr::randomize_public_key(parameters, pk, &randomness).unwrap()
+            .expect("serializing to a vector does not fail");
+        Schnorr::randomize_public_key(parameters, pk, &randomness)
+            .expect("randomizing a public key does not fail")
+    }
+
+    /// The opening of the coin's commitment, without the randomness of its public key.
+    #[must_use]
+    pub fn opening(&self) -> CoinOpening<P0> {
+        CoinOpening {
+            value: self.value,
+            tag: self.tag,
+            commitment_blinding: self.commitment_blinding.clone(),
+        }
+    }
+
+    /// The coin as the receiver spends it from the leaf its commitment was inserted as, see `open_permissible_leaf`:
+    /// the offset of the opening is added to the blinding, unless the commitment was its own leaf.
+    #[must_use]
+    pub fn with_permissible_opening(mut self, opening: &PermissibleOpening<F0>) -> Self {
+        if !opening.is_trivial() {
+            self.commitment_blinding
+                .add_term(PERMISSIBLE_OFFSET, EvenScalar(opening.offset));
+        }
+        self
+    }
+
+    /// The identifier of the coin minted with `commitment`, with which a wallet keys its coins:
+    /// the hash of the commitment and of the tag, which is derived from the rerandomized public key.
+    /// It is the same on every call, and for the same coin in every wallet.
+    pub fn id(&self, commitment: &Affine<P0>) -> CoinId {
+        let mut bytes = protocol::COIN_ID_DOMAIN.to_vec();
+        commitment
+            .serialize_compressed(&mut bytes)
+            .expect("serializing to a vector does not fail");
+        self.tag
+            .serialize_compressed(&mut bytes)
+            .expect("serializing to a vector does not fail");
+        let mut id = [0u8; 32];
+        id.copy_from_slice(&Sha3_256::digest(&bytes));
+        id
+    }
+
+    pub fn prove_spend<
+        const L: usize,
+        P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = P0::BaseField> + Copy,
+    >(
+        &self,
+        index: usize,
+        even_prover: &mut Prover<Transcript, Affine<P0>>,
+        odd_prover: &mut Prover<Transcript, Affine<P1>>,
+        parameters: &SelRerandParameters<P0, P1>,
+        layout: &CommitmentLayout,
+        curve_tree: &CurveTree<L, P0, P1>,
+    ) -> Result<SpendOutput<L, P0, P1>, Error> {
+        self.opening().prove_spend(
+            index,
+            even_prover,
+            odd_prover,
+            parameters,
+            layout,
+            curve_tree,
+        )
+    }
+
+    /// Like `prove_spend`, for a withdrawal of `amount` to the transparent chain, see `CoinOpening::prove_withdraw`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prove_withdraw<
+        const L: usize,
+        P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = P0::BaseField> + Copy,
+    >(
+        &self,
+        amount: u64,
+        index: usize,
+        even_prover: &mut Prover<Transcript, Affine<P0>>,
+        odd_prover: &mut Prover<Transcript, Affine<P1>>,
+        parameters: &SelRerandParameters<P0, P1>,
+        layout: &CommitmentLayout,
+        curve_tree: &CurveTree<L, P0, P1>,
+    ) -> Result<SelectAndRerandomizePath<L, P0, P1>, Error> {
+        self.opening().prove_withdraw(
+            amount,
+            index,
+            even_prover,
+            odd_prover,
+            parameters,
+            layout,
+            curve_tree,
+        )
+    }
+}
+
+/// The opening of a coin commitment, which is all a prover needs to mint or spend the coin.
+/// Unlike a `Coin` it does not hold the randomness of the public key, so it cannot be used to sign for the coin.
+#[derive(Clone)]
+pub struct CoinOpening<P0: SWCurveConfig> {
+    pub value: u64,
+    pub tag: P0::ScalarField,
+    pub commitment_blinding: BlindingAccumulator<EvenScalar<P0>>,
+}
+
+/// Compares the openings in constant time, as they are secret until spent.
+impl<P0: SWCurveConfig> PartialEq for CoinOpening<P0> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.value.ct_eq(&other.value)
+            & field_ct_eq(&self.tag, &other.tag)
+            & field_ct_eq(
+                &self.commitment_blinding.total().0,
+                &other.commitment_blinding.total().0,
+            ))
+        .into()
+    }
+}
+
+impl<
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+    > CoinOpening<P0>
+{
+    /// The opening of the coin commitment, for `prove_equal_value` with `ValueGenerators::layout`.
+    pub fn value_opening(&self, layout: &CommitmentLayout) -> Result<ValueOpening<F0>, Error> {
+        Ok(ValueOpening {
+            vector: layout.vector(&[(VALUE, fe_from_u64(self.value)), (TAG, self.tag)])?,
+            blinding: self.commitment_blinding.total().0,
+        })
+    }
+
+    /// Commits to the coin and proves its value in range as in `Coin::mint_bounded`.
+    /// Returns the coin commitment and the variable of its value.
+    pub fn prove_mint(
+        &self,
+        max_value: Option<u64>,
+        sr_parameters: &SingleLayerParameters<P0>,
+        layout: &CommitmentLayout,
+        policy: &ValuePolicy,
+        prover: &mut Prover<Transcript, Affine<P0>>,
+    ) -> Result<(Affine<P0>, Variable<F0>), Error> {
+        if let Some(max_value) = max_value {
+            if self.value >= max_value {
+                return Err(Error::inconsistent_witness(format!(
+                    "The value {} is not below the bound {}",
+                    self.value, max_value
+                )));
+            }
+        }
+        let vector = layout.vector(&[(VALUE, fe_from_u64(self.value)), (TAG, self.tag)])?;
+        layout.bind(prover.transcript());
+        let (coin_commitment, opened) = OpenedLeaf::commit_prover(
+            prover,
+            layout,
+            &vector,
+            self.commitment_blinding.total().0,
+            &sr_parameters.bp_gens,
+        )?;
+        enforce_value_range(prover, &opened, max_value, policy)?;
+        Ok((coin_commitment, opened.variable(VALUE)?))
     }
 
+    /// Proves that the coin is the leaf at `index` of `curve_tree`, as in `Coin::prove_spend`.
+    ///
+    /// The odd prover only carries the rerandomized path: the tag is a slot of `layout`, opened with the rest of
+    /// the leaf on the even curve, so every commitment of the relation goes through the layout.
     pub fn prove_spend<
         const L: usize,
         P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = P0::BaseField> + Copy,
@@ -108,221 +436,865 @@ impl<
         even_prover: &mut Prover<Transcript, Affine<P0>>,
         odd_prover: &mut Prover<Transcript, Affine<P1>>,
         parameters: &SelRerandParameters<P0, P1>,
+        layout: &CommitmentLayout,
+        curve_tree: &CurveTree<L, P0, P1>,
+    ) -> Result<SpendOutput<L, P0, P1>, Error> {
+        self.prove_spend_with_strictness(
+            index,
+            even_prover,
+            odd_prover,
+            parameters,
+            layout,
+            curve_tree,
+            Strictness::Checked,
+        )
+    }
+
+    /// Like `prove_spend`, checking that the coin is the leaf at `index` only if `strictness` is `Checked`.
+    /// If it is not, the check fails with `Error::InconsistentWitness`, naming the wrong term of the blinding
+    /// if there is one; with `Fast`, the proof is made and rejected by its verifiers.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prove_spend_with_strictness<
+        const L: usize,
+        P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = P0::BaseField> + Copy,
+    >(
+        &self,
+        index: usize,
+        even_prover: &mut Prover<Transcript, Affine<P0>>,
+        odd_prover: &mut Prover<Transcript, Affine<P1>>,
+        parameters: &SelRerandParameters<P0, P1>,
+        layout: &CommitmentLayout,
         curve_tree: &CurveTree<L, P0, P1>,
-    ) -> (
-        SelectAndRerandomizePath<L, P0, P1>,
-        Variable<P0::ScalarField>,
-    ) {
+        strictness: Strictness,
+    ) -> Result<SpendOutput<L, P0, P1>, Error> {
+        let vector = layout.vector(&[(VALUE, fe_from_u64(self.value)), (TAG, self.tag)])?;
+        layout.bind(even_prover.transcript());
         let (path, rerandomization) = curve_tree.select_and_rerandomize_prover_gadget(
             index,
             even_prover,
             odd_prover,
             parameters,
             &mut rand::thread_rng(),
-        );
+        )?;
 
-        let (rerandomized_point, variables) = even_prover.commit_vec(
-            &[P0::ScalarField::from(self.value), self.tag],
-            self.permissible_randomness + rerandomization,
+        let blinding = self
+            .commitment_blinding
+            .clone()
+            .with_term(SELECT_AND_RERANDOMIZE, rerandomization);
+        let (rerandomized_point, opened) = OpenedLeaf::commit_prover(
+            even_prover,
+            layout,
+            &vector,
+            blinding.total().0,
             &parameters.even_parameters.bp_gens,
-        );
-        assert_eq!(
-            path.even_commitments[path.even_commitments.len() - 1],
-            rerandomized_point
-        );
+        )?;
+        if strictness.is_checked()
+            && !bool::from(point_ct_eq(
+                &path.get_rerandomized_leaf(),
+                &rerandomized_point,
+            ))
+        {
+            // Tell a wrong coin from a wrong term of the blinding.
+            if let Some(label) = blinding.first_mismatch(
+                &[
+                    (PERMISSIBLE_OFFSET, curve_tree.leaf(index)),
+                    (SELECT_AND_RERANDOMIZE, Some(path.get_rerandomized_leaf())),
+                ],
+                |b| parameters.even_parameters.commit(&vector, b.0, 0).ok(),
+            ) {
+                return Err(Error::inconsistent_witness(format!(
+                    "The coin is not the leaf at index {}, the commitment after the blinding term {} does not match",
+                    index, label
+                )));
+            }
+            return Err(Error::inconsistent_witness(format!(
+                "The coin is not the leaf at index {}",
+                index
+            )));
+        }
+
+        enforce_tag(even_prover, &opened, self.tag)?;
 
-        even_prover.constrain(variables[1] - self.tag);
+        Ok((path, opened))
+    }
+
+    /// Proves that the coin is the leaf at `index` of `curve_tree`, as `prove_spend`,
+    /// and that its value is `amount`, withdrawn to the transparent chain. Verified by `verify_withdraw`.
+    /// Returns the rerandomized path to the coin.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prove_withdraw<
+        const L: usize,
+        P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = P0::BaseField> + Copy,
+    >(
+        &self,
+        amount: u64,
+        index: usize,
+        even_prover: &mut Prover<Transcript, Affine<P0>>,
+        odd_prover: &mut Prover<Transcript, Affine<P1>>,
+        parameters: &SelRerandParameters<P0, P1>,
+        layout: &CommitmentLayout,
+        curve_tree: &CurveTree<L, P0, P1>,
+    ) -> Result<SelectAndRerandomizePath<L, P0, P1>, Error> {
+        if self.value != amount {
+            return Err(Error::inconsistent_witness(format!(
+                "The value of the coin is not the withdrawn amount {}",
+                amount
+            )));
+        }
+        let (path, opened) = self.prove_spend(
+            index,
+            even_prover,
+            odd_prover,
+            parameters,
+            layout,
+            curve_tree,
+        )?;
+        constrain_public_amount(even_prover, opened.variable(VALUE)?, amount);
+        Ok(path)
+    }
 
-        (path, variables[0])
+    /// Proves that the coin is the element at `witness` of `accumulator`, outside of a pour.
+    /// Returns the proof and the blinding of the rerandomized coin commitment,
+    /// checked to open to the value and tag of the coin.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prove_membership<
+        A: Accumulator<Element = Affine<P0>, Blinding = EvenScalar<P0>>,
+        R: RngCore + CryptoRng,
+    >(
+        &self,
+        accumulator: &A,
+        witness: &A::Witness,
+        sr_parameters: &SingleLayerParameters<P0>,
+        layout: &CommitmentLayout,
+        parameters: &A::Parameters,
+        rng: &mut R,
+    ) -> Result<(A::Proof, EvenScalar<P0>), Error> {
+        let vector = layout.vector(&[(VALUE, fe_from_u64(self.value)), (TAG, self.tag)])?;
+        let (proof, blinding) = accumulator.prove_membership(
+            witness,
+            self.commitment_blinding.total(),
+            parameters,
+            rng,
+        )?;
+        if !bool::from(point_ct_eq(
+            &A::rerandomized_element(&proof),
+            &sr_parameters.commit(&vector, blinding.0, 0)?,
+        )) {
+            return Err(Error::inconsistent_witness(
+                "The coin is not the element of the witness",
+            ));
+        }
+        Ok((proof, blinding))
     }
 }
 
-pub fn verify_mint<P: SWCurveConfig>(
-    verifier: &mut Verifier<Transcript, Affine<P>>,
-    commitment: Affine<P>,
-) -> Variable<P::ScalarField> {
-    let variables = verifier.commit_vec(2, commitment);
-    range_proof(verifier, variables[0].into(), None, 64).unwrap(); // todo range?
-    variables[0]
+/// The opening of the fee commitment of a pour, `value * B + blinding * B_blinding` with the Pedersen generators
+/// of the even curve, see `PourRequest::new_with_fee`.
+#[derive(Clone, Copy)]
+pub struct FeeOpening<F: PrimeField> {
+    pub value: u64,
+    pub blinding: F,
 }
 
-pub fn element_from_bytes_stat<F: PrimeField>(bytes: &[u8]) -> F {
-    // for the purpose of hashing to a 256 bit prime field, provides statistical security of ... todo
-    extern crate crypto;
-    use crypto::digest::Digest;
-    use crypto::sha3::Sha3;
+impl<F: PrimeField> FeeOpening<F> {
+    /// The opening of its Pedersen commitment, for `prove_equal_value` with `ValueGenerators::pedersen`.
+    pub fn value_opening(&self) -> ValueOpening<F> {
+        ValueOpening::pedersen(self.value, self.blinding)
+    }
 
-    let mut sha = Sha3::sha3_512();
-    sha.input(bytes);
-    let mut buf = [0u8; 32];
-    sha.result(&mut buf);
-    F::from_le_bytes_mod_order(&buf)
-}
+    /// The fee commitment it opens.
+    pub fn commitment<P0: SWCurveConfig<ScalarField = F> + Copy>(
+        &self,
+        sr_parameters: &SingleLayerParameters<P0>,
+    ) -> Affine<P0> {
+        sr_parameters
+            .pc_gens
+            .commit(fe_from_u64(self.value), self.blinding)
+    }
 
-pub struct SpendingInfo<P: SWCurveConfig + Clone, C: CurveGroup> {
-    pub index: usize,
-    pub coin_aux: Coin<P, C>,
-    pub randomized_pk: PublicKey<C>,
-    pub sk: SecretKey<C>,
+    /// Checks that it opens `commitment`, else fails with `Error::FeeMismatch`,
+    /// e.g. for a node to check the opening of the aggregate fee commitment of a block a producer publishes.
+    pub fn verify<P0: SWCurveConfig<ScalarField = F> + Copy>(
+        &self,
+        commitment: &Affine<P0>,
+        sr_parameters: &SingleLayerParameters<P0>,
+    ) -> Result<(), Error> {
+        // The fee is opened to be published, comparing in variable time leaks nothing.
+        if self.commitment(sr_parameters) != *commitment {
+            return Err(Error::FeeMismatch);
+        }
+        Ok(())
+    }
 }
 
-pub fn prove_pour<
+/// A transaction paying a fee with the opening of its fee commitment, as `aggregate_fees` sums them.
+pub type FeePayment<P0, P1, C> = (
+    SignedTx<P0, P1, C>,
+    FeeOpening<<P0 as CurveConfig>::ScalarField>,
+);
+
+/// Sums the fee commitments of the transactions of a block, each with the opening its sender handed to the block producer.
+/// Returns the aggregate commitment, which a node recomputes from the transactions alone with `SignedTx::fee_commitment`,
+/// and its opening, the total fee and blinding the producer publishes to claim the fees, see `FeeOpening::verify`.
+///
+/// Fails with `Error::FeeMismatch` for an opening of another fee than its transaction commits to,
+/// e.g. of a sender lying about its fee, and with `Error::ParameterMismatch` for a transaction paying no fee.
+pub fn aggregate_fees<
     const L: usize,
     F0: PrimeField,
     F1: PrimeField,
     P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
     P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
     C: CurveGroup,
-    R: Rng,
 >(
-    mut even_prover: Prover<Transcript, Affine<P0>>,
-    mut odd_prover: Prover<Transcript, Affine<P1>>,
-    sr_parameters: &SelRerandParameters<P0, P1>,
-    curve_tree: &CurveTree<L, P0, P1>,
-    input_0: &SpendingInfo<P0, C>,
-    input_1: &SpendingInfo<P0, C>,
-    receiver_value_0: u64,
-    receiver_pk_0: PublicKey<C>,
-    receiver_value_1: u64,
-    receiver_pk_1: PublicKey<C>,
-    sig_parameters: &Parameters<C, Blake2s>,
-    rng: &mut R,
-) -> SignedTx<P0, P1, C> {
-    // mint coins
-    let (_, minted_coin_commitment_0, minted_amount_var_0) = Coin::<P0, C>::mint(
-        receiver_value_0,
-        &receiver_pk_0,
-        sig_parameters,
-        &sr_parameters.even_parameters,
-        rng,
-        &mut even_prover,
-    );
-    let (_, minted_coin_commitment_1, minted_amount_var_1) = Coin::<P0, C>::mint(
-        receiver_value_1,
-        &receiver_pk_1,
-        sig_parameters,
-        &sr_parameters.even_parameters,
-        rng,
-        &mut even_prover,
-    );
+    transactions: &[FeePayment<P0, P1, C>],
+    sr_parameters: &SingleLayerParameters<P0>,
+) -> Result<(Affine<P0>, FeeOpening<F0>), Error> {
+    let mut commitment = Affine::<P0>::zero().into_group();
+    let mut opening = FeeOpening {
+        value: 0,
+        blinding: F0::zero(),
+    };
+    for (tx, fee) in transactions {
+        let fee_commitment = tx
+            .fee_commitment::<L>()?
+            .ok_or_else(|| Error::parameter_mismatch("The transaction pays no fee"))?;
+        fee.verify(&fee_commitment, sr_parameters)?;
+        commitment += fee_commitment;
+        opening.value = opening.value.checked_add(fee.value).ok_or_else(|| {
+            Error::value_out_of_range("The total fee of the transactions exceeds 2^64")
+        })?;
+        opening.blinding += fee.blinding;
+    }
+    Ok((commitment.into_affine(), opening))
+}
 
-    // spend coins
-    let (path_0, spent_amount_var_0) = input_0.coin_aux.prove_spend(
-        input_0.index,
-        &mut even_prover,
-        &mut odd_prover,
-        sr_parameters,
-        curve_tree,
-    );
-    let (path_1, spent_amount_var_1) = input_1.coin_aux.prove_spend(
-        input_1.index,
-        &mut even_prover,
-        &mut odd_prover,
-        sr_parameters,
-        curve_tree,
-    );
+pub fn verify_mint<P: SWCurveConfig>(
+    verifier: &mut Verifier<Transcript, Affine<P>>,
+    commitment: Affine<P>,
+    layout: &CommitmentLayout,
+    policy: &ValuePolicy,
+) -> Result<Variable<P::ScalarField>, Error> {
+    verify_mint_bounded(verifier, commitment, layout, None, policy)
+}
 
-    // enforce equal amount spent and minted
-    even_prover.constrain(
-        minted_amount_var_0 + minted_amount_var_1 - spent_amount_var_0 - spent_amount_var_1,
-    );
+/// Verifier counterpart of `Coin::mint_bounded`, `max_value` and `policy` must match those used by the prover.
+pub fn verify_mint_bounded<P: SWCurveConfig>(
+    verifier: &mut Verifier<Transcript, Affine<P>>,
+    commitment: Affine<P>,
+    layout: &CommitmentLayout,
+    max_value: Option<u64>,
+    policy: &ValuePolicy,
+) -> Result<Variable<P::ScalarField>, Error> {
+    check_not_identity(&commitment)?;
+    layout.bind(verifier.transcript());
+    let opened = OpenedLeaf::commit_verifier(verifier, layout, commitment);
+    enforce_value_range(verifier, &opened, max_value, policy)?;
+    opened.variable(VALUE)
+}
 
-    // prove
-    #[cfg(not(feature = "parallel"))]
-    let (even_proof, odd_proof) = (
-        even_prover
-            .prove(&sr_parameters.even_parameters.bp_gens)
-            .unwrap(),
-        odd_prover
-            .prove(&sr_parameters.odd_parameters.bp_gens)
-            .unwrap(),
-    );
-    #[cfg(feature = "parallel")]
-    let (even_proof, odd_proof) = rayon::join(
-        || {
-            even_prover
-                .prove(&sr_parameters.even_parameters.bp_gens)
-                .unwrap()
-        },
-        || {
-            odd_prover
-                .prove(&sr_parameters.odd_parameters.bp_gens)
-                .unwrap()
-        },
-    );
+/// Verifier counterpart of `Coin::deposit_mint`, `amount` is read from the transparent chain.
+pub fn verify_deposit_mint<P: SWCurveConfig>(
+    verifier: &mut Verifier<Transcript, Affine<P>>,
+    commitment: Affine<P>,
+    layout: &CommitmentLayout,
+    amount: u64,
+    policy: &ValuePolicy,
+) -> Result<Variable<P::ScalarField>, Error> {
+    let value_variable = verify_mint(verifier, commitment, layout, policy)?;
+    constrain_public_amount(verifier, value_variable, amount);
+    Ok(value_variable)
+}
 
-    let proof = Pour::<L, P0, P1, C> {
-        even_proof,
-        odd_proof,
-        randomized_path_0: path_0,
-        randomized_path_1: path_1,
-        pk0: input_0.randomized_pk,
-        pk1: input_1.randomized_pk,
-        minted_coin_commitment_0,
-        minted_coin_commitment_1,
-    };
-    // double sign
-    let mut proof_bytes = Vec::with_capacity(proof.serialized_size(Compress::Yes));
-    proof.serialize_compressed(&mut proof_bytes).unwrap();
-    let sig_0 = Schnorr::sign(sig_parameters, &input_0.sk, proof_bytes.as_slice(), rng).unwrap();
-    let mut randomization_bytes = Vec::new();
-    input_0
-        .coin_aux
-        .pk_randomness
-        .serialize_compressed(&mut randomization_bytes)
-        .unwrap();
-    let sig_0 =
-        Schnorr::randomize_signature(sig_parameters, &sig_0, randomization_bytes.as_slice())
-            .unwrap();
+/// Binds `amount`, public on the transparent chain, to the transcript, and constrains `value` to equal it.
+fn constrain_public_amount<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    value: Variable<F>,
+    amount: u64,
+) {
+    cs.transcript().append_u64(protocol::PUBLIC_AMOUNT, amount);
+    let amount = cs.public_constant(fe_from_u64(amount));
+    cs.constrain(value - amount);
+}
 
-    let sig_1 = Schnorr::sign(sig_parameters, &input_1.sk, proof_bytes.as_slice(), rng).unwrap();
-    let mut randomization_bytes = Vec::new();
-    input_1
-        .coin_aux
-        .pk_randomness
-        .serialize_compressed(&mut randomization_bytes)
-        .unwrap();
-    let sig_1 =
-        Schnorr::randomize_signature(sig_parameters, &sig_1, randomization_bytes.as_slice())
-            .unwrap();
+/// Hashes `bytes` into `F` in the domain `protocol::HASH_TO_FIELD_DOMAIN`, see `convert::hash_to_field`:
+/// statistically close to uniform in fields of any size.
+pub fn element_from_bytes_stat<F: PrimeField>(bytes: &[u8]) -> F {
+    hash_to_field(protocol::HASH_TO_FIELD_DOMAIN, bytes)
+}
 
-    SignedTx::<P0, P1, _> {
-        signature_prover_response_0: sig_0.prover_response,
-        signature_verifier_challenge_0: sig_0.verifier_challenge,
-        signature_prover_response_1: sig_1.prover_response,
-        signature_verifier_challenge_1: sig_1.verifier_challenge,
-        pour_bytes: proof_bytes,
-        _pour_type: PhantomData,
+/// Rejects the identity as a public key, for which anyone can sign, or as a minted coin commitment, which is the empty node of a tree.
+/// A spending key drawn from `rng`, never zero: the public key of zero is the identity, for which anyone can sign.
+/// Coins are minted to its public key, see `public_key_from_secret`.
+#[must_use]
+pub fn generate_secret_key<C: CurveGroup, R: RngCore + CryptoRng>(rng: &mut R) -> SecretKey<C> {
+    loop {
+        let sk = C::ScalarField::rand(rng);
+        if !sk.is_zero() {
+            return SecretKey(sk);
+        }
     }
 }
 
-// todo do an n to m pour with arrays?
-#[derive(Clone)]
-pub struct Pour<
-    const L: usize,
-    P0: SWCurveConfig + Clone,
-    P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = P0::BaseField> + Clone,
-    C: CurveGroup,
-> {
-    pub even_proof: R1CSProof<Affine<P0>>,
-    pub odd_proof: R1CSProof<Affine<P1>>,
-    pub randomized_path_0: SelectAndRerandomizePath<L, P0, P1>,
-    pub randomized_path_1: SelectAndRerandomizePath<L, P0, P1>,
-    pub pk0: PublicKey<C>,
-    pub pk1: PublicKey<C>,
-    pub minted_coin_commitment_0: Affine<P0>,
-    pub minted_coin_commitment_1: Affine<P0>,
+/// The public key of `sk`, as `Schnorr::keygen` derives it.
+/// Fails with `Error::IdentityPoint` for the zero key, to which `Coin::new` would refuse to mint.
+pub fn public_key_from_secret<C: CurveGroup>(
+    sk: &SecretKey<C>,
+    parameters: &Parameters<C, Blake2s>,
+) -> Result<PublicKey<C>, Error> {
+    let pk = (parameters.generator * sk.0).into_affine();
+    check_not_identity(&pk)?;
+    Ok(pk)
 }
 
-impl<
-        const L: usize,
-        P0: SWCurveConfig + Clone,
-        P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = P0::BaseField> + Clone,
-        C: CurveGroup,
-    > CanonicalSerialize for Pour<L, P0, P1, C>
-{
-    fn serialized_size(&self, compress: Compress) -> usize {
-        self.even_proof.serialized_size(compress)
+fn check_not_identity<A: AffineRepr>(point: &A) -> Result<(), Error> {
+    // The points checked are public, comparing them in variable time leaks nothing.
+    if point.is_zero() {
+        return Err(Error::IdentityPoint);
+    }
+    Ok(())
+}
+
+/// Rejects the tags of a pour if they are equal, which they are when the same coin is spent twice.
+/// Distinct coins only have equal tags with negligible probability, such a pour is rejected all the same.
+fn check_distinct_tags<F: PrimeField>(tags: [F; 2]) -> Result<(), Error> {
+    // The tags are revealed by the pour, comparing them in variable time leaks nothing more.
+    if tags[0] == tags[1] {
+        return Err(Error::DuplicateTag);
+    }
+    Ok(())
+}
+
+pub struct SpendingInfo<P: SWCurveConfig + Clone, C: CurveGroup> {
+    /// The leaf of the coin, refreshed with `CurveTree::refresh_ref` when the tree is rebuilt.
+    pub leaf: LeafRef,
+    pub coin_aux: Coin<P, C>,
+    pub randomized_pk: PublicKey<C>,
+    pub sk: SecretKey<C>,
+}
+
+impl<P: SWCurveConfig + Clone, C: CurveGroup> SpendingInfo<P, C> {
+    /// What a prover needs to spend the coin, without the spending key.
+    #[must_use]
+    pub fn request(&self) -> SpendRequest<P, C> {
+        SpendRequest {
+            leaf: self.leaf,
+            coin: CoinOpening {
+                value: self.coin_aux.value,
+                tag: self.coin_aux.tag,
+                commitment_blinding: self.coin_aux.commitment_blinding.clone(),
+            },
+            randomized_pk: self.randomized_pk,
+        }
+    }
+}
+
+/// The part of a `SpendingInfo` a prover needs to spend the coin, without the spending key.
+#[derive(Clone)]
+pub struct SpendRequest<P: SWCurveConfig, C: CurveGroup> {
+    pub leaf: LeafRef,
+    pub coin: CoinOpening<P>,
+    pub randomized_pk: PublicKey<C>,
+}
+
+impl<P: SWCurveConfig, C: CurveGroup> PartialEq for SpendRequest<P, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.leaf == other.leaf
+            && self.coin == other.coin
+            && self.randomized_pk == other.randomized_pk
+    }
+}
+
+/// The witness of a pour without the spending keys, with which an untrusted prover proves a pour
+/// for the device holding the keys.
+///
+/// The device creates the request with `PourRequest::new`, the prover proves it with `PourRequest::prove`,
+/// and the device signs the returned pour with `PourRequest::sign`, which checks that the pour spends and mints
+/// the requested coins. Only the request and the unsigned pour are exchanged, both serializable.
+///
+/// The prover learns:
+/// - the indices of the spent coins, and so which leaves of the tree are spent, since it proves the paths to them,
+/// - the values and tags of the spent coins,
+/// - the values, tags and commitment randomness of the minted coins,
+/// - the rerandomized public keys of the spent coins, which are public in the pour anyway.
+///
+/// It does not learn the spending keys or the randomness of the public keys, so it can neither sign the pour
+/// nor link the spent or minted coins to the long-term public keys of their owners. It cannot redirect the minted coins,
+/// as the device only signs a pour with the requested minted coin commitments.
+/// Hiding the spent leaves from the prover would need proving over secret shared witnesses, which this does not do.
+#[derive(Clone)]
+pub struct PourRequest<P0: SWCurveConfig, C: CurveGroup> {
+    pub inputs: [SpendRequest<P0, C>; 2],
+    pub outputs: [CoinOpening<P0>; 2],
+    pub output_commitments: [Affine<P0>; 2],
+    /// The opening and the commitment of the fee, if the pour pays one, see `PourRequest::new_with_fee`.
+    pub fee: Option<(FeeOpening<P0::ScalarField>, Affine<P0>)>,
+}
+
+impl<
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+        C: CurveGroup,
+    > PourRequest<P0, C>
+{
+    /// Mints the coins of the receivers and requests a pour spending `input_0` and `input_1` into them.
+    /// Returns the request and the minted coins, which the receivers need to spend them.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new<R: RngCore + CryptoRng>(
+        input_0: &SpendingInfo<P0, C>,
+        input_1: &SpendingInfo<P0, C>,
+        receiver_value_0: u64,
+        receiver_pk_0: PublicKey<C>,
+        receiver_value_1: u64,
+        receiver_pk_1: PublicKey<C>,
+        sig_parameters: &Parameters<C, Blake2s>,
+        sr_parameters: &SingleLayerParameters<P0>,
+        layout: &CommitmentLayout,
+        rng: &mut R,
+    ) -> Result<(Self, [Coin<P0, C>; 2]), Error> {
+        Self::build(
+            [input_0, input_1],
+            [
+                (receiver_value_0, receiver_pk_0),
+                (receiver_value_1, receiver_pk_1),
+            ],
+            None,
+            sig_parameters,
+            sr_parameters,
+            layout,
+            rng,
+        )
+    }
+
+    /// Like `new`, for a pour that also pays `fee`, which stays hidden: the pour publishes a commitment to it
+    /// with the Pedersen generators of the even curve, proves it in range, and balances the spent values
+    /// against the minted values and the fee. The opening of the commitment is in `fee`,
+    /// for the sender to hand to the block producer claiming the fees, see `aggregate_fees`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_fee<R: RngCore + CryptoRng>(
+        input_0: &SpendingInfo<P0, C>,
+        input_1: &SpendingInfo<P0, C>,
+        receiver_value_0: u64,
+        receiver_pk_0: PublicKey<C>,
+        receiver_value_1: u64,
+        receiver_pk_1: PublicKey<C>,
+        fee: u64,
+        sig_parameters: &Parameters<C, Blake2s>,
+        sr_parameters: &SingleLayerParameters<P0>,
+        layout: &CommitmentLayout,
+        rng: &mut R,
+    ) -> Result<(Self, [Coin<P0, C>; 2]), Error> {
+        Self::build(
+            [input_0, input_1],
+            [
+                (receiver_value_0, receiver_pk_0),
+                (receiver_value_1, receiver_pk_1),
+            ],
+            Some(fee),
+            sig_parameters,
+            sr_parameters,
+            layout,
+            rng,
+        )
+    }
+
+    fn build<R: RngCore + CryptoRng>(
+        [input_0, input_1]: [&SpendingInfo<P0, C>; 2],
+        [(receiver_value_0, receiver_pk_0), (receiver_value_1, receiver_pk_1)]: [(u64, PublicKey<C>);
+            2],
+        fee: Option<u64>,
+        sig_parameters: &Parameters<C, Blake2s>,
+        sr_parameters: &SingleLayerParameters<P0>,
+        layout: &CommitmentLayout,
+        rng: &mut R,
+    ) -> Result<(Self, [Coin<P0, C>; 2]), Error> {
+        let spent = input_0.coin_aux.value as u128 + input_1.coin_aux.value as u128;
+        let minted = receiver_value_0 as u128 + receiver_value_1 as u128;
+        let paid = fee.unwrap_or(0) as u128;
+        if spent != minted + paid {
+            return Err(Error::inconsistent_witness(format!(
+                "The pour spends {} but mints {} and pays a fee of {}",
+                spent, minted, paid
+            )));
+        }
+
+        check_distinct_tags([
+            Coin::<P0, C>::pk_to_scalar(&input_0.randomized_pk),
+            Coin::<P0, C>::pk_to_scalar(&input_1.randomized_pk),
+        ])?;
+
+        let (minted_coin_0, minted_coin_commitment_0) = Coin::<P0, C>::new(
+            receiver_value_0,
+            &receiver_pk_0,
+            sig_parameters,
+            sr_parameters,
+            layout,
+            rng,
+        )?;
+        let (minted_coin_1, minted_coin_commitment_1) = Coin::<P0, C>::new(
+            receiver_value_1,
+            &receiver_pk_1,
+            sig_parameters,
+            sr_parameters,
+            layout,
+            rng,
+        )?;
+
+        let fee = fee.map(|value| {
+            let opening = FeeOpening {
+                value,
+                blinding: P0::ScalarField::rand(rng),
+            };
+            (opening, opening.commitment(sr_parameters))
+        });
+
+        let request = Self {
+            inputs: [input_0.request(), input_1.request()],
+            outputs: [minted_coin_0.opening(), minted_coin_1.opening()],
+            output_commitments: [minted_coin_commitment_0, minted_coin_commitment_1],
+            fee,
+        };
+        Ok((request, [minted_coin_0, minted_coin_1]))
+    }
+
+    /// Proves the pour of the request, without signing it.
+    pub fn prove<const L: usize, P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy>(
+        &self,
+        even_prover: Prover<Transcript, Affine<P0>>,
+        odd_prover: Prover<Transcript, Affine<P1>>,
+        sr_parameters: &SelRerandParameters<P0, P1>,
+        layout: &CommitmentLayout,
+        curve_tree: &CurveTree<L, P0, P1>,
+    ) -> Result<Pour<L, P0, P1, C>, Error> {
+        self.prove_with_strictness(
+            even_prover,
+            odd_prover,
+            sr_parameters,
+            layout,
+            curve_tree,
+            Strictness::Checked,
+        )
+    }
+
+    /// Like `prove`, checking that the minted coins, the spent coins and the fee open the commitments of the request
+    /// only if `strictness` is `Checked`, see `CoinOpening::prove_spend_with_strictness`.
+    /// The policy of the values is enforced either way.
+    pub fn prove_with_strictness<
+        const L: usize,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+    >(
+        &self,
+        mut even_prover: Prover<Transcript, Affine<P0>>,
+        mut odd_prover: Prover<Transcript, Affine<P1>>,
+        sr_parameters: &SelRerandParameters<P0, P1>,
+        layout: &CommitmentLayout,
+        curve_tree: &CurveTree<L, P0, P1>,
+        strictness: Strictness,
+    ) -> Result<Pour<L, P0, P1, C>, Error> {
+        check_distinct_tags([
+            Coin::<P0, C>::pk_to_scalar(&self.inputs[0].randomized_pk),
+            Coin::<P0, C>::pk_to_scalar(&self.inputs[1].randomized_pk),
+        ])?;
+        let policy = &sr_parameters.value_policy;
+        policy.check_counts(2, 2 + self.fee.iter().count())?;
+
+        // mint coins
+        let (minted_coin_commitment_0, minted_amount_var_0) = self.outputs[0].prove_mint(
+            None,
+            &sr_parameters.even_parameters,
+            layout,
+            policy,
+            &mut even_prover,
+        )?;
+        let (minted_coin_commitment_1, minted_amount_var_1) = self.outputs[1].prove_mint(
+            None,
+            &sr_parameters.even_parameters,
+            layout,
+            policy,
+            &mut even_prover,
+        )?;
+        if strictness.is_checked()
+            && [minted_coin_commitment_0, minted_coin_commitment_1] != self.output_commitments
+        {
+            return Err(Error::inconsistent_witness(
+                "The minted coins do not open the requested commitments",
+            ));
+        }
+
+        // spend coins
+        let (path_0, spent_0) = self.inputs[0].coin.prove_spend_with_strictness(
+            curve_tree.resolve_ref(&self.inputs[0].leaf)?,
+            &mut even_prover,
+            &mut odd_prover,
+            sr_parameters,
+            layout,
+            curve_tree,
+            strictness,
+        )?;
+        let (path_1, spent_1) = self.inputs[1].coin.prove_spend_with_strictness(
+            curve_tree.resolve_ref(&self.inputs[1].leaf)?,
+            &mut even_prover,
+            &mut odd_prover,
+            sr_parameters,
+            layout,
+            curve_tree,
+            strictness,
+        )?;
+
+        // commit to the fee
+        let fee = match &self.fee {
+            Some((opening, commitment)) => {
+                let (fee_commitment, fee_var) =
+                    even_prover.commit(fe_from_u64(opening.value), opening.blinding);
+                if strictness.is_checked() && fee_commitment != *commitment {
+                    return Err(Error::inconsistent_witness(
+                        "The fee does not open the requested commitment",
+                    ));
+                }
+                policy.check_value(opening.value)?;
+                range_proof(
+                    &mut even_prover,
+                    fee_var.into(),
+                    Some(opening.value),
+                    policy.bits as usize,
+                )?;
+                Some((fee_commitment, fee_var))
+            }
+            None => None,
+        };
+
+        // enforce equal amount spent, and minted or paid as fee
+        let mut balance = minted_amount_var_0 + minted_amount_var_1
+            - spent_0.variable(VALUE)?
+            - spent_1.variable(VALUE)?;
+        if let Some((_, fee_var)) = fee {
+            balance = balance + fee_var;
+        }
+        even_prover.constrain(balance);
+
+        // prove, on both curves at once with the `parallel` feature
+        let (even_proof, odd_proof) = prove_pair(even_prover, odd_prover, sr_parameters)?;
+
+        Ok(Pour::<L, P0, P1, C> {
+            tags: [
+                Coin::<P0, C>::pk_to_scalar(&self.inputs[0].randomized_pk),
+                Coin::<P0, C>::pk_to_scalar(&self.inputs[1].randomized_pk),
+            ],
+            even_proof,
+            odd_proof,
+            randomized_path_0: path_0,
+            randomized_path_1: path_1,
+            pk0: self.inputs[0].randomized_pk,
+            pk1: self.inputs[1].randomized_pk,
+            minted_coin_commitment_0,
+            minted_coin_commitment_1,
+            fee_commitment: fee.map(|(commitment, _)| commitment),
+            parameters_fingerprint: sr_parameters.fingerprint(),
+        })
+    }
+
+    /// Signs `pour` with the keys of the spent coins, after checking that it spends and mints the requested coins.
+    /// The proofs of the pour are not verified, a pour with invalid proofs is rejected by the verifiers of the transaction.
+    pub fn sign<
+        const L: usize,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+        R: RngCore + CryptoRng,
+    >(
+        &self,
+        pour: &Pour<L, P0, P1, C>,
+        input_0: &SpendingInfo<P0, C>,
+        input_1: &SpendingInfo<P0, C>,
+        sig_parameters: &Parameters<C, Blake2s>,
+        rng: &mut R,
+    ) -> Result<SignedTx<P0, P1, C>, Error> {
+        if [input_0.request(), input_1.request()] != self.inputs {
+            return Err(Error::inconsistent_witness(
+                "The spending infos are not those of the request",
+            ));
+        }
+        if [pour.pk0, pour.pk1] != [self.inputs[0].randomized_pk, self.inputs[1].randomized_pk]
+            || [pour.minted_coin_commitment_0, pour.minted_coin_commitment_1]
+                != self.output_commitments
+            || pour.fee_commitment != self.fee.map(|(_, commitment)| commitment)
+        {
+            return Err(Error::parameter_mismatch(
+                "The pour does not spend and mint the requested coins",
+            ));
+        }
+
+        // double sign, with the spending keys, then randomize each signature by the whole rerandomization of the key,
+        // the total of `pk_randomness`, to verify against `randomized_pk`
+        let mut proof_bytes = Vec::with_capacity(pour.serialized_size(Compress::Yes));
+        pour.serialize_compressed(&mut proof_bytes)
+            .expect("serializing to a vector does not fail");
+        let sig_0 = Schnorr::sign(sig_parameters, &input_0.sk, proof_bytes.as_slice(), rng)
+            .expect("signing does not fail");
+        let mut randomization_bytes = Vec::new();
+        input_0
+            .coin_aux
+            .pk_randomness
+            .total()
+            .serialize_compressed(&mut randomization_bytes)
+            .expect("serializing to a vector does not fail");
+        let sig_0 =
+            Schnorr::randomize_signature(sig_parameters, &sig_0, randomization_bytes.as_slice())
+                .expect("randomizing a signature does not fail");
+
+        let sig_1 = Schnorr::sign(sig_parameters, &input_1.sk, proof_bytes.as_slice(), rng)
+            .expect("signing does not fail");
+        let mut randomization_bytes = Vec::new();
+        input_1
+            .coin_aux
+            .pk_randomness
+            .total()
+            .serialize_compressed(&mut randomization_bytes)
+            .expect("serializing to a vector does not fail");
+        let sig_1 =
+            Schnorr::randomize_signature(sig_parameters, &sig_1, randomization_bytes.as_slice())
+                .expect("randomizing a signature does not fail");
+
+        Ok(SignedTx::<P0, P1, _> {
+            signature_prover_response_0: sig_0.prover_response,
+            signature_verifier_challenge_0: sig_0.verifier_challenge,
+            signature_prover_response_1: sig_1.prover_response,
+            signature_verifier_challenge_1: sig_1.verifier_challenge,
+            pour_bytes: proof_bytes,
+            _pour_type: PhantomData,
+        })
+    }
+}
+
+pub fn prove_pour<
+    const L: usize,
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+    C: CurveGroup,
+    R: RngCore + CryptoRng,
+>(
+    even_prover: Prover<Transcript, Affine<P0>>,
+    odd_prover: Prover<Transcript, Affine<P1>>,
+    sr_parameters: &SelRerandParameters<P0, P1>,
+    layout: &CommitmentLayout,
+    curve_tree: &CurveTree<L, P0, P1>,
+    input_0: &SpendingInfo<P0, C>,
+    input_1: &SpendingInfo<P0, C>,
+    receiver_value_0: u64,
+    receiver_pk_0: PublicKey<C>,
+    receiver_value_1: u64,
+    receiver_pk_1: PublicKey<C>,
+    sig_parameters: &Parameters<C, Blake2s>,
+    rng: &mut R,
+) -> Result<SignedTx<P0, P1, C>, Error> {
+    Ok(prove_pour_with_outputs(
+        even_prover,
+        odd_prover,
+        sr_parameters,
+        layout,
+        curve_tree,
+        input_0,
+        input_1,
+        receiver_value_0,
+        receiver_pk_0,
+        receiver_value_1,
+        receiver_pk_1,
+        sig_parameters,
+        rng,
+    )?
+    .0)
+}
+
+/// A signed pour with the coins it mints, as returned by `prove_pour_with_outputs`.
+pub type PourWithOutputs<P0, P1, C> = (SignedTx<P0, P1, C>, [Coin<P0, C>; 2]);
+
+/// Like `prove_pour`, but also returns the minted coins, which the receivers need to spend them.
+#[allow(clippy::too_many_arguments)]
+pub fn prove_pour_with_outputs<
+    const L: usize,
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+    C: CurveGroup,
+    R: RngCore + CryptoRng,
+>(
+    even_prover: Prover<Transcript, Affine<P0>>,
+    odd_prover: Prover<Transcript, Affine<P1>>,
+    sr_parameters: &SelRerandParameters<P0, P1>,
+    layout: &CommitmentLayout,
+    curve_tree: &CurveTree<L, P0, P1>,
+    input_0: &SpendingInfo<P0, C>,
+    input_1: &SpendingInfo<P0, C>,
+    receiver_value_0: u64,
+    receiver_pk_0: PublicKey<C>,
+    receiver_value_1: u64,
+    receiver_pk_1: PublicKey<C>,
+    sig_parameters: &Parameters<C, Blake2s>,
+    rng: &mut R,
+) -> Result<PourWithOutputs<P0, P1, C>, Error> {
+    let (request, minted_coins) = PourRequest::new(
+        input_0,
+        input_1,
+        receiver_value_0,
+        receiver_pk_0,
+        receiver_value_1,
+        receiver_pk_1,
+        sig_parameters,
+        &sr_parameters.even_parameters,
+        layout,
+        rng,
+    )?;
+    let pour = request.prove(even_prover, odd_prover, sr_parameters, layout, curve_tree)?;
+    let tx = request.sign(&pour, input_0, input_1, sig_parameters, rng)?;
+    Ok((tx, minted_coins))
+}
+
+/// The verifiers of the even and odd proof of a pour, as returned by `Pour::verifiers`.
+pub type PourVerifiers<P0, P1> = (
+    Verifier<Transcript, Affine<P0>>,
+    Verifier<Transcript, Affine<P1>>,
+);
+
+/// The verification tuples of the even and odd proof of a pour, to be batch verified.
+pub type PourVerificationTuples<P0, P1> =
+    (VerificationTuple<Affine<P0>>, VerificationTuple<Affine<P1>>);
+
+// todo do an n to m pour with arrays?
+#[derive(Clone)]
+pub struct Pour<
+    const L: usize,
+    P0: SWCurveConfig + Clone,
+    P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = P0::BaseField> + Clone,
+    C: CurveGroup,
+> {
+    /// The spending tags of the two spent coins, serialized first for an application to index a transaction by them
+    /// without deserializing its proofs. The verifiers check that they are those the proofs enforce.
+    pub tags: [P0::ScalarField; 2],
+    pub even_proof: R1CSProof<Affine<P0>>,
+    pub odd_proof: R1CSProof<Affine<P1>>,
+    pub randomized_path_0: SelectAndRerandomizePath<L, P0, P1>,
+    pub randomized_path_1: SelectAndRerandomizePath<L, P0, P1>,
+    pub pk0: PublicKey<C>,
+    pub pk1: PublicKey<C>,
+    pub minted_coin_commitment_0: Affine<P0>,
+    pub minted_coin_commitment_1: Affine<P0>,
+    /// The commitment to the fee of the pour, if it pays one, see `PourRequest::new_with_fee`.
+    pub fee_commitment: Option<Affine<P0>>,
+    pub parameters_fingerprint: ParametersFingerprint,
+}
+
+impl<
+        const L: usize,
+        P0: SWCurveConfig + Clone,
+        P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = P0::BaseField> + Clone,
+        C: CurveGroup,
+    > CanonicalSerialize for Pour<L, P0, P1, C>
+{
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.tags.serialized_size(compress)
+            + self.even_proof.serialized_size(compress)
             + self.odd_proof.serialized_size(compress)
             + self.randomized_path_0.serialized_size(compress)
             + self.randomized_path_1.serialized_size(compress)
@@ -330,6 +1302,8 @@ impl<
             + self.pk1.serialized_size(compress)
             + self.minted_coin_commitment_0.serialized_size(compress)
             + self.minted_coin_commitment_1.serialized_size(compress)
+            + self.fee_commitment.serialized_size(compress)
+            + self.parameters_fingerprint.serialized_size(compress)
     }
 
     fn serialize_with_mode<W: Write>(
@@ -337,6 +1311,7 @@ impl<
         mut writer: W,
         compress: Compress,
     ) -> Result<(), SerializationError> {
+        self.tags.serialize_with_mode(&mut writer, compress)?;
         self.even_proof.serialize_with_mode(&mut writer, compress)?;
         self.odd_proof.serialize_with_mode(&mut writer, compress)?;
         self.randomized_path_0
@@ -349,6 +1324,10 @@ impl<
             .serialize_with_mode(&mut writer, compress)?;
         self.minted_coin_commitment_1
             .serialize_with_mode(&mut writer, compress)?;
+        self.fee_commitment
+            .serialize_with_mode(&mut writer, compress)?;
+        self.parameters_fingerprint
+            .serialize_with_mode(&mut writer, compress)?;
         Ok(())
     }
 }
@@ -372,11 +1351,29 @@ impl<
     > CanonicalDeserialize for Pour<L, P0, P1, C>
 {
     fn deserialize_with_mode<R: Read>(
-        mut reader: R,
+        reader: R,
         compress: Compress,
         validate: ark_serialize::Validate,
     ) -> Result<Self, SerializationError> {
+        deserialize_with_default_limits(reader, compress, validate)
+    }
+}
+
+impl<
+        const L: usize,
+        P0: SWCurveConfig + Clone,
+        P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = P0::BaseField> + Clone,
+        C: CurveGroup,
+    > DeserializeWithLimits for Pour<L, P0, P1, C>
+{
+    fn deserialize_with_limits<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: ark_serialize::Validate,
+        limits: &DeserializeLimits,
+    ) -> Result<Self, Error> {
         Ok(Self {
+            tags: <[P0::ScalarField; 2]>::deserialize_with_mode(&mut reader, compress, validate)?,
             even_proof: R1CSProof::<Affine<P0>>::deserialize_with_mode(
                 &mut reader,
                 compress,
@@ -387,15 +1384,17 @@ impl<
                 compress,
                 validate,
             )?,
-            randomized_path_0: SelectAndRerandomizePath::<L, P0, P1>::deserialize_with_mode(
+            randomized_path_0: SelectAndRerandomizePath::<L, P0, P1>::deserialize_with_limits(
                 &mut reader,
                 compress,
                 validate,
+                limits,
             )?,
-            randomized_path_1: SelectAndRerandomizePath::<L, P0, P1>::deserialize_with_mode(
+            randomized_path_1: SelectAndRerandomizePath::<L, P0, P1>::deserialize_with_limits(
                 &mut reader,
                 compress,
                 validate,
+                limits,
             )?,
             pk0: PublicKey::<C>::deserialize_with_mode(&mut reader, compress, validate)?,
             pk1: PublicKey::<C>::deserialize_with_mode(&mut reader, compress, validate)?,
@@ -409,6 +1408,16 @@ impl<
                 compress,
                 validate,
             )?,
+            fee_commitment: Option::<Affine<P0>>::deserialize_with_mode(
+                &mut reader,
+                compress,
+                validate,
+            )?,
+            parameters_fingerprint: ParametersFingerprint::deserialize_with_mode(
+                &mut reader,
+                compress,
+                validate,
+            )?,
         })
     }
 }
@@ -422,82 +1431,140 @@ impl<
         C: CurveGroup,
     > Pour<L, P0, P1, C>
 {
-    // verification
-    pub fn even_verification_gadget(
+    /// The spending tags of the two spent coins, which a verifier records to reject double spends.
+    pub fn tags(&self) -> [P0::ScalarField; 2] {
+        self.tags
+    }
+
+    /// The commitment to the fee of the pour, none if it pays no fee, see `aggregate_fees`.
+    pub fn fee_commitment(&self) -> Option<Affine<P0>> {
+        self.fee_commitment
+    }
+
+    /// The bytes of the tags, of each proof and of its inner product proof rounds, of the path commitments,
+    /// public keys, output commitments and fee commitment, see `SizeReport`.
+    pub fn size_report(&self) -> SizeReport {
+        let mut report = SizeReport::new();
+        report.add_serialized("tags", &self.tags);
+        report.add_proof("even proof", "even proof rounds", &self.even_proof);
+        report.add_proof("odd proof", "odd proof rounds", &self.odd_proof);
+        report.add_serialized("path commitments", &self.randomized_path_0);
+        report.add_serialized("path commitments", &self.randomized_path_1);
+        report.add_serialized("public keys", &self.pk0);
+        report.add_serialized("public keys", &self.pk1);
+        report.add_serialized("output commitments", &self.minted_coin_commitment_0);
+        report.add_serialized("output commitments", &self.minted_coin_commitment_1);
+        report.add_serialized("fee commitment", &self.fee_commitment);
+        report.add_serialized("parameters fingerprint", &self.parameters_fingerprint);
+        report
+    }
+
+    /// Checks that the published tags are those the proofs enforce, the tags of the rerandomized public keys,
+    /// else fails with `Error::TagMismatch`.
+    fn check_published_tags(&self) -> Result<(), Error> {
+        // The tags and keys are public, comparing them in variable time leaks nothing.
+        if self.tags
+            != [
+                Coin::<P0, C>::pk_to_scalar(&self.pk0),
+                Coin::<P0, C>::pk_to_scalar(&self.pk1),
+            ]
+        {
+            return Err(Error::TagMismatch);
+        }
+        Ok(())
+    }
+
+    /// Builds the constraint systems of the pour, in which each spend binds the two transcripts.
+    /// Rejects a pour spending the same tag twice with `Error::DuplicateTag`, whatever the tags already spent,
+    /// a pour publishing other tags than those its proofs enforce with `Error::TagMismatch`,
+    /// and a pour proven with other parameters with `Error::ParameterFingerprintMismatch`.
+    pub fn verifiers(
         &self,
         ro_domain: &'static [u8],
         sr_parameters: &SelRerandParameters<P0, P1>,
+        layout: &CommitmentLayout,
         spend_commitments_0: &SelectAndRerandomizePath<L, P0, P1>,
         spend_commitments_1: &SelectAndRerandomizePath<L, P0, P1>,
         curve_tree: &CurveTree<L, P0, P1>,
-    ) -> VerificationTuple<Affine<P0>> {
+    ) -> Result<PourVerifiers<P0, P1>, Error> {
+        sr_parameters.check_fingerprint(&self.parameters_fingerprint)?;
+        check_not_identity(&self.pk0)?;
+        check_not_identity(&self.pk1)?;
+        self.check_published_tags()?;
+        check_distinct_tags(self.tags())?;
+        let policy = &sr_parameters.value_policy;
+        policy.check_counts(2, 2 + self.fee_commitment.iter().count())?;
         let mut even_verifier = Verifier::new(Transcript::new(ro_domain));
+        let mut odd_verifier = Verifier::new(Transcript::new(ro_domain));
         // mint
-        let minted_amount_var_0 = verify_mint(&mut even_verifier, self.minted_coin_commitment_0);
-        let minted_amount_var_1 = verify_mint(&mut even_verifier, self.minted_coin_commitment_1);
+        let minted_amount_var_0 = verify_mint(
+            &mut even_verifier,
+            self.minted_coin_commitment_0,
+            layout,
+            policy,
+        )?;
+        let minted_amount_var_1 = verify_mint(
+            &mut even_verifier,
+            self.minted_coin_commitment_1,
+            layout,
+            policy,
+        )?;
 
         // spend
-        let spent_amount_var_0 = verify_spend_even::<L, _, _, _, _, C>(
+        let spent_0 = verify_spend::<L, _, _, _, _, C>(
             &mut even_verifier,
+            &mut odd_verifier,
             spend_commitments_0,
             sr_parameters,
+            layout,
             &self.pk0,
             curve_tree,
-        );
-        let spent_amount_var_1 = verify_spend_even::<L, _, _, _, _, C>(
+        )?;
+        let spent_1 = verify_spend::<L, _, _, _, _, C>(
             &mut even_verifier,
+            &mut odd_verifier,
             spend_commitments_1,
             sr_parameters,
+            layout,
             &self.pk1,
             curve_tree,
-        );
+        )?;
+
+        // fee
+        let fee_var = match self.fee_commitment {
+            Some(commitment) => {
+                let fee_var = even_verifier.commit(commitment);
+                range_proof(
+                    &mut even_verifier,
+                    fee_var.into(),
+                    None,
+                    policy.bits as usize,
+                )?;
+                Some(fee_var)
+            }
+            None => None,
+        };
 
         // balance
-        even_verifier.constrain(
-            minted_amount_var_0 + minted_amount_var_1 - spent_amount_var_0 - spent_amount_var_1,
-        );
+        let mut balance = minted_amount_var_0 + minted_amount_var_1
+            - spent_0.variable(VALUE)?
+            - spent_1.variable(VALUE)?;
+        if let Some(fee_var) = fee_var {
+            balance = balance + fee_var;
+        }
+        even_verifier.constrain(balance);
 
-        even_verifier
-            .verification_scalars_and_points(&self.even_proof)
-            .unwrap()
+        Ok((even_verifier, odd_verifier))
     }
 
     // verification
-    pub fn odd_verification_gadget(
-        &self,
+    pub fn verification_gadget(
+        self,
         ro_domain: &'static [u8],
         sr_parameters: &SelRerandParameters<P0, P1>,
-        spend_commitments_0: &SelectAndRerandomizePath<L, P0, P1>,
-        spend_commitments_1: &SelectAndRerandomizePath<L, P0, P1>,
+        layout: &CommitmentLayout,
         curve_tree: &CurveTree<L, P0, P1>,
-    ) -> VerificationTuple<Affine<P1>> {
-        let mut odd_verifier = Verifier::new(Transcript::new(ro_domain));
-        // spend
-        verify_spend_odd(
-            &mut odd_verifier,
-            spend_commitments_0,
-            sr_parameters,
-            curve_tree,
-        );
-        verify_spend_odd(
-            &mut odd_verifier,
-            spend_commitments_1,
-            sr_parameters,
-            curve_tree,
-        );
-
-        odd_verifier
-            .verification_scalars_and_points(&self.odd_proof)
-            .unwrap()
-    }
-
-    // verification
-    pub fn verification_gadget(
-        self,
-        ro_domain: &'static [u8],
-        sr_parameters: &SelRerandParameters<P0, P1>,
-        curve_tree: &CurveTree<L, P0, P1>,
-    ) -> (VerificationTuple<Affine<P0>>, VerificationTuple<Affine<P1>>) {
+    ) -> Result<PourVerificationTuples<P0, P1>, Error> {
         #[cfg(feature = "parallel")]
         let (spend_commitments_0, spend_commitments_1) = {
             // todo this might not be worth the overhead
@@ -525,57 +1592,332 @@ impl<
                 ),
             )
         };
+        let (spend_commitments_0, spend_commitments_1) =
+            (spend_commitments_0?, spend_commitments_1?);
 
-        #[cfg(feature = "parallel")]
-        let (even_vt, odd_vt) = {
-            rayon::join(
-                || {
-                    self.even_verification_gadget(
-                        ro_domain,
-                        sr_parameters,
-                        &spend_commitments_0,
-                        &spend_commitments_1,
-                        curve_tree,
-                    )
-                },
-                || {
-                    self.odd_verification_gadget(
-                        ro_domain,
-                        sr_parameters,
-                        &spend_commitments_0,
-                        &spend_commitments_1,
-                        curve_tree,
-                    )
-                },
-            )
-        };
-        #[cfg(not(feature = "parallel"))]
-        let (even_vt, odd_vt) = {
-            (
-                self.even_verification_gadget(
-                    ro_domain,
-                    sr_parameters,
-                    &spend_commitments_0,
-                    &spend_commitments_1,
-                    curve_tree,
-                ),
-                self.odd_verification_gadget(
-                    ro_domain,
-                    sr_parameters,
-                    &spend_commitments_0,
-                    &spend_commitments_1,
-                    curve_tree,
-                ),
-            )
-        };
+        // The verifiers hold deferred constraints, which cannot be sent between threads.
+        let (even_verifier, odd_verifier) = self.verifiers(
+            ro_domain,
+            sr_parameters,
+            layout,
+            &spend_commitments_0,
+            &spend_commitments_1,
+            curve_tree,
+        )?;
+        let even_vt = even_verifier.verification_scalars_and_points(&self.even_proof)?;
+        let odd_vt = odd_verifier.verification_scalars_and_points(&self.odd_proof)?;
 
         // todo check signatures
 
-        (even_vt, odd_vt)
+        Ok((even_vt, odd_vt))
+    }
+}
+
+/// The leaf a minted coin commitment is inserted in a tree as, what nodes insert and gossip.
+///
+/// The commitments of `Coin::new` are permissible, and are their own leaves.
+/// A verified pour may still mint others, which would not fit in a tree: their leaf is the first permissible commitment
+/// adding the blinding generator, as `SingleLayerParameters::permissible_commitment` searches.
+/// The offset of the blinding it adds is only needed by the receiver of the coin, see `PermissibleOpening`.
+pub struct PermissibleLeaf<P: SWCurveConfig> {
+    pub permissible_coin: Affine<P>,
+}
+
+// Derived, these would require the curve configurations to be `Copy`, `Debug` and `Eq`.
+impl<P: SWCurveConfig> Clone for PermissibleLeaf<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P: SWCurveConfig> Copy for PermissibleLeaf<P> {}
+
+impl<P: SWCurveConfig> PartialEq for PermissibleLeaf<P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.permissible_coin == other.permissible_coin
+    }
+}
+
+impl<P: SWCurveConfig> Eq for PermissibleLeaf<P> {}
+
+impl<P: SWCurveConfig> std::fmt::Debug for PermissibleLeaf<P> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("PermissibleLeaf")
+            .field("permissible_coin", &self.permissible_coin)
+            .finish()
+    }
+}
+
+/// The offset the search of a `PermissibleLeaf` added to the blinding of a minted coin commitment,
+/// which the receiver of the coin adds to its blinding to spend it, see `Coin::with_permissible_opening`.
+///
+/// The offset is secret-adjacent: it is not published, there is no `Debug`, and nodes never compute it.
+#[derive(Clone, Copy)]
+pub struct PermissibleOpening<F: PrimeField> {
+    offset: F,
+}
+
+impl<F: PrimeField> PermissibleOpening<F> {
+    /// Whether the commitment was permissible, its own leaf: the offset is zero.
+    pub fn is_trivial(&self) -> bool {
+        self.offset.is_zero()
+    }
+}
+
+/// The leaf of the minted coin `commitment`, as a node inserts it, see `PermissibleLeaf`.
+/// The offset of the search is dropped here: nodes never hold a `PermissibleOpening`.
+pub fn permissible_leaf<P: SWCurveConfig + Copy>(
+    commitment: &Affine<P>,
+    sr_parameters: &SingleLayerParameters<P>,
+) -> PermissibleLeaf<P> {
+    let (permissible_coin, _) = sr_parameters
+        .uh
+        .permissible_commitment(commitment, &sr_parameters.pc_gens.B_blinding);
+    PermissibleLeaf { permissible_coin }
+}
+
+/// The leaf of the minted coin `commitment` with the offset of its blinding, as the receiver of the coin opens it.
+/// The search only depends on the commitment, so the receiver recomputes the offset the node found.
+pub fn open_permissible_leaf<P: SWCurveConfig + Copy>(
+    commitment: &Affine<P>,
+    sr_parameters: &SingleLayerParameters<P>,
+) -> (PermissibleLeaf<P>, PermissibleOpening<P::ScalarField>) {
+    let (permissible_coin, offset) = sr_parameters
+        .uh
+        .permissible_commitment(commitment, &sr_parameters.pc_gens.B_blinding);
+    (
+        PermissibleLeaf { permissible_coin },
+        PermissibleOpening { offset },
+    )
+}
+
+/// Checks that `leaf` is the leaf of the minted coin `commitment`, see `permissible_leaf`, else fails with `Error::LeafMismatch`.
+/// The leaf is not published with the transaction: the search is deterministic, so every node inserts the same leaf
+/// for the same commitment, and a light client given the leaves by a node checks them against the verified pour.
+pub fn verify_inserted_leaf<P: SWCurveConfig + Copy>(
+    commitment: &Affine<P>,
+    leaf: &Affine<P>,
+    sr_parameters: &SingleLayerParameters<P>,
+) -> Result<(), Error> {
+    if permissible_leaf(commitment, sr_parameters).permissible_coin != *leaf {
+        return Err(Error::LeafMismatch);
+    }
+    Ok(())
+}
+
+/// Verifies the signed pour `tx` against `curve_tree`, then inserts the leaves of its minted coins,
+/// see `SignedTx::outputs_for_insertion`, and records its spending tags in `tags`.
+/// Returns the indices of the inserted leaves.
+///
+/// Either the whole transaction is applied or nothing is: the transaction is first prechecked, see `SignedTx::precheck`,
+/// so a pour spending a tag of `tags` is rejected with `Error::SpentTag`, and the tree and the tags are only changed
+/// once the proofs and signatures are verified and the tree with the new leaves is built.
+pub fn apply_transaction<
+    const L: usize,
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    C: CurveGroup,
+>(
+    curve_tree: &mut CurveTree<L, P0, P1>,
+    tags: &mut TagSet<F0>,
+    tx: &SignedTx<P0, P1, C>,
+    ro_domain: &'static [u8],
+    sr_parameters: &SelRerandParameters<P0, P1>,
+    layout: &CommitmentLayout,
+    sig_parameters: &Parameters<C, Blake2s>,
+) -> Result<Vec<usize>, Error> {
+    apply_transaction_with_metrics(
+        curve_tree,
+        tags,
+        tx,
+        ro_domain,
+        sr_parameters,
+        layout,
+        sig_parameters,
+        &NoMetrics,
+    )
+}
+
+/// Like `apply_transaction`, counting the transactions applied and those rejected, by the kind of their error,
+/// in `metrics`, see `metrics::TRANSACTIONS_APPLIED`, and the insertion of the leaves, see `CurveTree::insert_with_metrics`.
+#[allow(clippy::too_many_arguments)]
+pub fn apply_transaction_with_metrics<
+    const L: usize,
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    C: CurveGroup,
+>(
+    curve_tree: &mut CurveTree<L, P0, P1>,
+    tags: &mut TagSet<F0>,
+    tx: &SignedTx<P0, P1, C>,
+    ro_domain: &'static [u8],
+    sr_parameters: &SelRerandParameters<P0, P1>,
+    layout: &CommitmentLayout,
+    sig_parameters: &Parameters<C, Blake2s>,
+    metrics: &dyn Metrics,
+) -> Result<Vec<usize>, Error> {
+    let mut apply = || {
+        tx.precheck::<L>(tags, &DeserializeLimits::default())?;
+        let leaves = tx.outputs_for_insertion::<L>(&sr_parameters.even_parameters)?;
+
+        let spent = tx.verify(ro_domain, sr_parameters, layout, curve_tree, sig_parameters)?;
+
+        // Leaves the tree as it was if building the new one fails.
+        let indices = curve_tree.insert_with_metrics(&leaves, sr_parameters, metrics)?;
+        tags.extend(spent.map(|spend| spend.tag));
+        Ok(indices)
+    };
+    let result = apply();
+    metrics::count_result(
+        metrics,
+        metrics::TRANSACTIONS_APPLIED,
+        metrics::TRANSACTION_FAILURES,
+        &result,
+    );
+    result
+}
+
+/// A public key, the message it signed and its signature, as verified by `verify_signatures_batch`.
+pub type SignatureItem<'a, C> = (PublicKey<C>, &'a [u8], Signature<C>);
+
+/// Verifies many Schnorr signatures at once, e.g. the ownership signatures of the transactions of a block,
+/// and fails with `Error::InvalidSignatureInBatch` at the first that does not verify.
+///
+/// The signatures carry their challenge and not the commitment of the signer, so the commitment of each is computed
+/// to be hashed: unlike signatures carrying their commitment, they cannot be folded into a single multiscalar
+/// multiplication with random weights. The batch shares the conversion of the commitments to affine coordinates,
+/// with a single field inversion, and computes them on all threads with the `parallel` feature.
+/// Each signature is then checked against its own hash, so the failing one is known without bisecting the batch.
+pub fn verify_signatures_batch<C: CurveGroup>(
+    sig_parameters: &Parameters<C, Blake2s>,
+    items: &[SignatureItem<C>],
+) -> Result<(), Error> {
+    let commitment = |(pk, _, signature): &SignatureItem<C>| {
+        sig_parameters.generator * signature.prover_response + *pk * signature.verifier_challenge
+    };
+    #[cfg(feature = "parallel")]
+    let commitments: Vec<C> = {
+        use rayon::prelude::*;
+        items.par_iter().map(commitment).collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let commitments: Vec<C> = items.iter().map(commitment).collect();
+
+    for (index, ((_, message, signature), commitment)) in items
+        .iter()
+        .zip(C::normalize_batch(&commitments))
+        .enumerate()
+    {
+        // The challenge as `Schnorr::verify` derives it from the commitment.
+        let mut hash_input = Vec::new();
+        sig_parameters.salt.serialize_compressed(&mut hash_input)?;
+        commitment.serialize_compressed(&mut hash_input)?;
+        message.serialize_compressed(&mut hash_input)?;
+        let challenge =
+            C::ScalarField::from_random_bytes(&<Blake2s as blake2::Digest>::digest(&hash_input));
+        if challenge != Some(signature.verifier_challenge) {
+            return Err(Error::InvalidSignatureInBatch { index });
+        }
+    }
+    Ok(())
+}
+
+/// Verifies the signatures and the proofs of `txs`, e.g. the transactions of a block, as `SignedTx::verify` does
+/// for each, with all the signatures in a single `verify_signatures_batch` and the proofs of each curve
+/// in a single `batch_verify`. It does not check the spent tags, see `SignedTx::precheck`.
+///
+/// Fails with `Error::InvalidSignatureInBatch` if a signature does not verify, the signatures of the transaction `i`
+/// being those of index `2 * i` and `2 * i + 1`, and as `batch_verify` if a proof of either curve does not.
+pub fn verify_transactions_batched<
+    const L: usize,
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    C: CurveGroup,
+>(
+    txs: &[SignedTx<P0, P1, C>],
+    ro_domain: &'static [u8],
+    sr_parameters: &SelRerandParameters<P0, P1>,
+    layout: &CommitmentLayout,
+    curve_tree: &CurveTree<L, P0, P1>,
+    sig_parameters: &Parameters<C, Blake2s>,
+) -> Result<(), Error> {
+    let pours = txs
+        .iter()
+        .map(|tx| tx.pour::<L>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let signatures: Vec<_> = txs
+        .iter()
+        .zip(&pours)
+        .flat_map(|(tx, pour)| tx.signature_items(&pour.pk0, &pour.pk1))
+        .collect();
+    verify_signatures_batch(sig_parameters, &signatures)?;
+
+    let (mut even_vts, mut odd_vts) =
+        (Vec::with_capacity(txs.len()), Vec::with_capacity(txs.len()));
+    for pour in pours {
+        let (even_vt, odd_vt) =
+            pour.verification_gadget(ro_domain, sr_parameters, layout, curve_tree)?;
+        even_vts.push(even_vt);
+        odd_vts.push(odd_vt);
+    }
+    batch_verify(
+        even_vts,
+        &sr_parameters.even_parameters.pc_gens,
+        &sr_parameters.even_parameters.bp_gens,
+    )?;
+    batch_verify(
+        odd_vts,
+        &sr_parameters.odd_parameters.pc_gens,
+        &sr_parameters.odd_parameters.bp_gens,
+    )?;
+    Ok(())
+}
+
+/// A spent input of a pour as its proofs certify it, for an application checking the ownership signature
+/// of the input itself, e.g. the consensus of another codebase.
+/// Only `SignedTx::verify` returns one, once the proofs of the pour verified. It serializes for the application,
+/// and does not deserialize: bytes read back certify nothing.
+#[derive(Clone)]
+pub struct VerifiedSpend<P0: SWCurveConfig, C: CurveGroup> {
+    rerandomized_pk: PublicKey<C>,
+    tag: P0::ScalarField,
+    value_commitment: Affine<P0>,
+}
+
+impl<P0: SWCurveConfig, C: CurveGroup> VerifiedSpend<P0, C> {
+    /// The rerandomized public key, against which the signature of the input verifies.
+    pub fn rerandomized_pk(&self) -> PublicKey<C> {
+        self.rerandomized_pk
+    }
+
+    /// The spending tag of the coin, the hash of the rerandomized public key.
+    pub fn tag(&self) -> P0::ScalarField {
+        self.tag
+    }
+
+    /// The rerandomized commitment of the coin, hiding its value, e.g. for an `EqualValueProof`.
+    pub fn value_commitment(&self) -> Affine<P0> {
+        self.value_commitment
+    }
+}
+
+impl<P0: SWCurveConfig, C: CurveGroup> PartialEq for VerifiedSpend<P0, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.rerandomized_pk == other.rerandomized_pk
+            && self.tag == other.tag
+            && self.value_commitment == other.value_commitment
     }
 }
 
-fn verify_spend_even<
+/// Verifier counterpart of `Coin::prove_spend`, returning the opening of the rerandomized coin commitment.
+/// It only adds the constraints of the spend: nothing is certified before the proofs of the verifiers verify.
+pub fn verify_spend<
     const L: usize,
     F0: PrimeField,
     F1: PrimeField,
@@ -584,35 +1926,69 @@ fn verify_spend_even<
     C: CurveGroup,
 >(
     even_verifier: &mut Verifier<Transcript, Affine<P0>>,
+    odd_verifier: &mut Verifier<Transcript, Affine<P1>>,
     commitments: &SelectAndRerandomizePath<L, P0, P1>,
     sr_parameters: &SelRerandParameters<P0, P1>,
+    layout: &CommitmentLayout,
     pk: &PublicKey<C>,
     curve_tree: &CurveTree<L, P0, P1>,
-) -> Variable<P0::ScalarField> {
-    commitments.even_verifier_gadget(even_verifier, sr_parameters, curve_tree);
-    let vars = even_verifier.commit_vec(L, commitments.get_rerandomized_leaf());
+) -> Result<OpenedLeaf<F0>, Error> {
+    layout.bind(even_verifier.transcript());
+    // mirrors the bindings of `CurveTree::select_and_rerandomize_prover_gadget`
+    sr_parameters.bind(even_verifier.transcript(), odd_verifier.transcript());
+    commitments.even_verifier_gadget(even_verifier, sr_parameters, curve_tree)?;
+    commitments.odd_verifier_gadget(odd_verifier, sr_parameters, curve_tree)?;
+    bind_transcripts(even_verifier.transcript(), odd_verifier.transcript());
+    let opened =
+        OpenedLeaf::commit_verifier(even_verifier, layout, commitments.get_rerandomized_leaf());
 
     // enforce equality of tag with hash of public key
-    even_verifier.constrain(vars[1] - Coin::<P0, C>::pk_to_scalar(pk));
+    let tag = Coin::<P0, C>::pk_to_scalar(pk);
+    enforce_tag(even_verifier, &opened, tag)?;
 
-    // return value to constrain spending balance
-    vars[0]
+    // return the opening to constrain spending balance
+    Ok(opened)
 }
 
-fn verify_spend_odd<
+/// Verifier counterpart of `Coin::prove_withdraw`: `path` is the rerandomized path of the prover,
+/// `pk` the rerandomized public key revealing the tag of the coin, and `amount` is read from the transparent chain.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_withdraw<
     const L: usize,
-    F: PrimeField,
-    P0: SWCurveConfig<BaseField = F> + Copy,
-    P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = F> + Copy,
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+    C: CurveGroup,
 >(
+    even_verifier: &mut Verifier<Transcript, Affine<P0>>,
     odd_verifier: &mut Verifier<Transcript, Affine<P1>>,
-    commitments: &SelectAndRerandomizePath<L, P0, P1>,
+    path: SelectAndRerandomizePath<L, P0, P1>,
     sr_parameters: &SelRerandParameters<P0, P1>,
+    layout: &CommitmentLayout,
+    pk: &PublicKey<C>,
     curve_tree: &CurveTree<L, P0, P1>,
-) {
-    commitments.odd_verifier_gadget(odd_verifier, sr_parameters, curve_tree);
+    amount: u64,
+) -> Result<(), Error> {
+    check_not_identity(pk)?;
+    let commitments = curve_tree.select_and_rerandomize_verification_commitments(path)?;
+    let opened = verify_spend::<L, _, _, _, _, C>(
+        even_verifier,
+        odd_verifier,
+        &commitments,
+        sr_parameters,
+        layout,
+        pk,
+        curve_tree,
+    )?;
+    constrain_public_amount(even_verifier, opened.variable(VALUE)?, amount);
+    Ok(())
 }
 
+/// A pour with the signatures of its two inputs.
+///
+/// Serialized after a version byte of `FormatVersion`: the proofs of the pour absorb the version
+/// through the parameter binding of their paths, see `SelRerandParameters::bind`.
 #[derive(Clone)]
 pub struct SignedTx<
     P0: SWCurveConfig + Copy,
@@ -635,37 +2011,210 @@ impl<
         C: CurveGroup,
     > SignedTx<P0, P1, C>
 {
+    /// Deserializes the signed pour, e.g. to read its tags and minted coin commitments.
+    pub fn pour<const L: usize>(&self) -> Result<Pour<L, P0, P1, C>, Error> {
+        self.pour_with_limits(&DeserializeLimits::default())
+    }
+
+    /// The published spending tags of the pour, read from the front of the signed bytes without deserializing the proofs,
+    /// e.g. to index the transactions of a mempool by the coins they spend.
+    /// They are only known to be those the proofs enforce once the transaction is verified.
+    pub fn tags(&self) -> Result<[F0; 2], Error> {
+        Ok(<[F0; 2]>::deserialize_compressed(
+            self.pour_bytes.as_slice(),
+        )?)
+    }
+
+    /// The commitment to the fee of the pour, none if it pays no fee, which a node sums over a block with `aggregate_fees`.
+    pub fn fee_commitment<const L: usize>(&self) -> Result<Option<Affine<P0>>, Error> {
+        Ok(self.pour::<L>()?.fee_commitment())
+    }
+
+    /// The bytes of the signatures and of the components of the pour, see `Pour::size_report`.
+    /// The length prefix of the signed pour is counted with the signatures.
+    pub fn size_report<const L: usize>(&self) -> Result<SizeReport, Error> {
+        let mut report = SizeReport::new();
+        report.add(
+            "signatures",
+            self.serialized_size(Compress::Yes) - self.pour_bytes.len(),
+        );
+        for (component, bytes) in self.pour::<L>()?.size_report().components() {
+            report.add(component, *bytes);
+        }
+        Ok(report)
+    }
+
+    /// Like `pour`, under the limits of the application.
+    pub fn pour_with_limits<const L: usize>(
+        &self,
+        limits: &DeserializeLimits,
+    ) -> Result<Pour<L, P0, P1, C>, Error> {
+        Pour::<L, P0, P1, C>::deserialize_compressed_with_limits(self.pour_bytes.as_slice(), limits)
+    }
+
+    /// The checks of the transaction that need neither the tree nor any multiscalar multiplication,
+    /// for a node to reject malformed transactions and double spends before verifying their proofs and signatures:
+    /// - the signed bytes are a single pour, under `limits`,
+    /// - its points are on their curves, as deserializing checks, and its public keys and minted coin commitments are not the identity,
+    /// - its two rerandomized paths have the shape of paths of the same tree,
+    /// - its published tags are those of its public keys, else it fails with `Error::TagMismatch`,
+    /// - its tags are distinct and not in `tags`, else it fails with `Error::DuplicateTag` or `Error::SpentTag`.
+    ///
+    /// `verification_gadget` does not know the spent tags and does not call it, `apply_transaction` does.
+    pub fn precheck<const L: usize>(
+        &self,
+        tags: &TagSet<F0>,
+        limits: &DeserializeLimits,
+    ) -> Result<(), Error> {
+        DeserializeLimits::check(
+            self.pour_bytes.len() as u64,
+            limits.max_transaction_size,
+            "the size of the pour of a transaction",
+        )?;
+        let mut reader = self.pour_bytes.as_slice();
+        let pour = Pour::<L, P0, P1, C>::deserialize_compressed_with_limits(&mut reader, limits)?;
+        if !reader.is_empty() {
+            return Err(Error::Serialization(SerializationError::InvalidData));
+        }
+
+        check_not_identity(&pour.pk0)?;
+        check_not_identity(&pour.pk1)?;
+        check_not_identity(&pour.minted_coin_commitment_0)?;
+        check_not_identity(&pour.minted_coin_commitment_1)?;
+
+        // The leaf is the last even commitment, and the commitments alternate between the curves.
+        let path_0 = &pour.randomized_path_0;
+        let path_1 = &pour.randomized_path_1;
+        let (even_length, odd_length) =
+            (path_0.even_commitments.len(), path_0.odd_commitments.len());
+        if even_length == 0
+            || even_length.abs_diff(odd_length) > 1
+            || path_1.even_commitments.len() != even_length
+            || path_1.odd_commitments.len() != odd_length
+        {
+            return Err(Error::InvalidPath);
+        }
+
+        pour.check_published_tags()?;
+        let spent = pour.tags();
+        check_distinct_tags(spent)?;
+        if spent.iter().any(|tag| tags.contains(tag)) {
+            return Err(Error::SpentTag);
+        }
+        Ok(())
+    }
+
+    /// Verifies the signatures and lays down the constraints of the pour.
+    /// It does not check the spent tags, see `precheck`.
     pub fn verification_gadget<const L: usize>(
         self,
         ro_domain: &'static [u8],
         sr_parameters: &SelRerandParameters<P0, P1>,
+        layout: &CommitmentLayout,
         curve_tree: &CurveTree<L, P0, P1>,
         sig_parameters: &Parameters<C, Blake2s>,
-    ) -> (VerificationTuple<Affine<P0>>, VerificationTuple<Affine<P1>>) {
-        let pour =
-            Pour::<L, P0, P1, C>::deserialize_compressed(self.pour_bytes.as_slice()).unwrap();
+    ) -> Result<PourVerificationTuples<P0, P1>, Error> {
+        let pour = self.pour::<L>()?;
         let pk0 = pour.pk0;
         let pk1 = pour.pk1;
         #[cfg(feature = "parallel")]
-        let (_, vts) = rayon::join(
+        let (signatures, vts) = rayon::join(
             || self.verify_signatures(sig_parameters, &pk0, &pk1),
-            || pour.verification_gadget(ro_domain, sr_parameters, curve_tree),
+            || pour.verification_gadget(ro_domain, sr_parameters, layout, curve_tree),
         );
         #[cfg(not(feature = "parallel"))]
-        let vts = {
-            self.verify_signatures(sig_parameters, &pk0, &pk1);
-            pour.verification_gadget(ro_domain, sr_parameters, curve_tree)
-        };
+        let (signatures, vts) = (
+            self.verify_signatures(sig_parameters, &pk0, &pk1),
+            pour.verification_gadget(ro_domain, sr_parameters, layout, curve_tree),
+        );
+        signatures?;
         vts
     }
 
+    /// Verifies the signatures and the proofs of the pour, and returns its two spent inputs as the proofs certify them,
+    /// e.g. for an application checking the signatures of the inputs itself against their rerandomized public keys.
+    /// It does not check the spent tags, see `precheck`.
+    pub fn verify<const L: usize>(
+        &self,
+        ro_domain: &'static [u8],
+        sr_parameters: &SelRerandParameters<P0, P1>,
+        layout: &CommitmentLayout,
+        curve_tree: &CurveTree<L, P0, P1>,
+        sig_parameters: &Parameters<C, Blake2s>,
+    ) -> Result<[VerifiedSpend<P0, C>; 2], Error> {
+        let pour = self.pour::<L>()?;
+        let (even_vt, odd_vt) = self.clone().verification_gadget(
+            ro_domain,
+            sr_parameters,
+            layout,
+            curve_tree,
+            sig_parameters,
+        )?;
+        batch_verify(
+            vec![even_vt],
+            &sr_parameters.even_parameters.pc_gens,
+            &sr_parameters.even_parameters.bp_gens,
+        )?;
+        batch_verify(
+            vec![odd_vt],
+            &sr_parameters.odd_parameters.pc_gens,
+            &sr_parameters.odd_parameters.bp_gens,
+        )?;
+        Ok([
+            (&pour.pk0, pour.tags[0], &pour.randomized_path_0),
+            (&pour.pk1, pour.tags[1], &pour.randomized_path_1),
+        ]
+        .map(|(pk, tag, path)| VerifiedSpend {
+            rerandomized_pk: *pk,
+            tag,
+            value_commitment: path.get_rerandomized_leaf(),
+        }))
+    }
+
+    /// The leaves a node inserts into the tree for the coins minted by the pour, in order, see `permissible_leaf`.
+    /// They are the minted coin commitments the proof commits to, unless a prover minted non permissible ones.
+    pub fn outputs_for_insertion<const L: usize>(
+        &self,
+        sr_parameters: &SingleLayerParameters<P0>,
+    ) -> Result<Vec<Affine<P0>>, Error> {
+        let pour = self.pour::<L>()?;
+        Ok(
+            [pour.minted_coin_commitment_0, pour.minted_coin_commitment_1]
+                .iter()
+                .map(|commitment| permissible_leaf(commitment, sr_parameters).permissible_coin)
+                .collect(),
+        )
+    }
+
+    // The signatures of the inputs of public keys `pk0` and `pk1`, for `verify_signatures_batch`.
+    fn signature_items(&self, pk0: &PublicKey<C>, pk1: &PublicKey<C>) -> [SignatureItem<'_, C>; 2] {
+        [
+            (
+                *pk0,
+                self.pour_bytes.as_slice(),
+                Signature {
+                    verifier_challenge: self.signature_verifier_challenge_0,
+                    prover_response: self.signature_prover_response_0,
+                },
+            ),
+            (
+                *pk1,
+                self.pour_bytes.as_slice(),
+                Signature {
+                    verifier_challenge: self.signature_verifier_challenge_1,
+                    prover_response: self.signature_prover_response_1,
+                },
+            ),
+        ]
+    }
+
     pub fn verify_signatures(
         &self,
         sig_parameters: &Parameters<C, Blake2s>,
         pk0: &PublicKey<C>,
         pk1: &PublicKey<C>,
-    ) {
-        Schnorr::verify(
+    ) -> Result<(), Error> {
+        let valid_0 = Schnorr::verify(
             sig_parameters,
             pk0,
             self.pour_bytes.as_slice(),
@@ -674,8 +2223,8 @@ impl<
                 prover_response: self.signature_prover_response_0,
             },
         )
-        .unwrap();
-        Schnorr::verify(
+        .map_err(|_| Error::InvalidSignature)?;
+        let valid_1 = Schnorr::verify(
             sig_parameters,
             pk1,
             self.pour_bytes.as_slice(),
@@ -684,7 +2233,12 @@ impl<
                 prover_response: self.signature_prover_response_1,
             },
         )
-        .unwrap();
+        .map_err(|_| Error::InvalidSignature)?;
+        if valid_0 && valid_1 {
+            Ok(())
+        } else {
+            Err(Error::InvalidSignature)
+        }
     }
 }
 
@@ -695,7 +2249,7 @@ impl<
     > CanonicalSerialize for SignedTx<P0, P1, C>
 {
     fn serialized_size(&self, mode: Compress) -> usize {
-        self.pour_bytes.serialized_size(mode)
+        1 + self.pour_bytes.serialized_size(mode)
             + self.signature_prover_response_0.serialized_size(mode)
             + self.signature_verifier_challenge_0.serialized_size(mode)
             + self.signature_prover_response_1.serialized_size(mode)
@@ -707,6 +2261,9 @@ impl<
         mut writer: W,
         compress: ark_serialize::Compress,
     ) -> Result<(), SerializationError> {
+        FormatVersion::CURRENT
+            .byte()
+            .serialize_with_mode(&mut writer, compress)?;
         self.signature_prover_response_0
             .serialize_with_mode(&mut writer, compress)?;
         self.signature_verifier_challenge_0
@@ -737,10 +2294,29 @@ impl<
     > CanonicalDeserialize for SignedTx<P0, P1, C>
 {
     fn deserialize_with_mode<R: Read>(
-        mut reader: R,
+        reader: R,
         compress: ark_serialize::Compress,
         validate: ark_serialize::Validate,
     ) -> Result<Self, SerializationError> {
+        deserialize_with_default_limits(reader, compress, validate)
+    }
+}
+
+impl<
+        P0: SWCurveConfig + Copy,
+        P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = P0::BaseField> + Copy,
+        C: CurveGroup,
+    > DeserializeWithLimits for SignedTx<P0, P1, C>
+{
+    /// Checks the version byte, then caps the pour bytes at `limits.max_transaction_size`,
+    /// the pour itself is deserialized from them by `pour_with_limits`.
+    fn deserialize_with_limits<R: Read>(
+        mut reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+        limits: &DeserializeLimits,
+    ) -> Result<Self, Error> {
+        FormatVersion::from_byte(u8::deserialize_with_mode(&mut reader, compress, validate)?)?;
         Ok(Self {
             signature_prover_response_0: C::ScalarField::deserialize_with_mode(
                 &mut reader,
@@ -762,33 +2338,1005 @@ impl<
                 compress,
                 validate,
             )?,
-            pour_bytes: Vec::<u8>::deserialize_with_mode(&mut reader, compress, validate)?,
+            pour_bytes: deserialize_vec(
+                &mut reader,
+                compress,
+                validate,
+                limits.max_transaction_size,
+                "the size of the pour of a transaction",
+            )?,
             _pour_type: PhantomData,
         })
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use merlin::Transcript;
-    type PallasParameters = ark_pallas::PallasConfig;
-    type VestaParameters = ark_vesta::VestaConfig;
-    type PallasP = ark_pallas::Projective;
+impl<P0: SWCurveConfig + Clone, C: CurveGroup> CanonicalSerialize for Coin<P0, C> {
+    fn serialized_size(&self, mode: Compress) -> usize {
+        self.value.serialized_size(mode)
+            + self.tag.serialized_size(mode)
+            + self.commitment_blinding.serialized_size(mode)
+            + self.pk_randomness.serialized_size(mode)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), SerializationError> {
+        self.value.serialize_with_mode(&mut writer, compress)?;
+        self.tag.serialize_with_mode(&mut writer, compress)?;
+        self.commitment_blinding
+            .serialize_with_mode(&mut writer, compress)?;
+        self.pk_randomness
+            .serialize_with_mode(&mut writer, compress)?;
+        Ok(())
+    }
+}
+
+impl<P0: SWCurveConfig + Clone, C: CurveGroup> Valid for Coin<P0, C> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+impl<P0: SWCurveConfig + Clone, C: CurveGroup> CanonicalDeserialize for Coin<P0, C> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, SerializationError> {
+        Ok(Self {
+            value: u64::deserialize_with_mode(&mut reader, compress, validate)?,
+            tag: P0::ScalarField::deserialize_with_mode(&mut reader, compress, validate)?,
+            commitment_blinding: BlindingAccumulator::deserialize_with_mode(
+                &mut reader,
+                compress,
+                validate,
+            )?,
+            pk_randomness: BlindingAccumulator::deserialize_with_mode(
+                &mut reader,
+                compress,
+                validate,
+            )?,
+        })
+    }
+}
+
+impl<P0: SWCurveConfig> CanonicalSerialize for CoinOpening<P0> {
+    fn serialized_size(&self, mode: Compress) -> usize {
+        self.value.serialized_size(mode)
+            + self.tag.serialized_size(mode)
+            + self.commitment_blinding.serialized_size(mode)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), SerializationError> {
+        self.value.serialize_with_mode(&mut writer, compress)?;
+        self.tag.serialize_with_mode(&mut writer, compress)?;
+        self.commitment_blinding
+            .serialize_with_mode(&mut writer, compress)?;
+        Ok(())
+    }
+}
+
+impl<P0: SWCurveConfig> Valid for CoinOpening<P0> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+impl<P0: SWCurveConfig> CanonicalDeserialize for CoinOpening<P0> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, SerializationError> {
+        Ok(Self {
+            value: u64::deserialize_with_mode(&mut reader, compress, validate)?,
+            tag: P0::ScalarField::deserialize_with_mode(&mut reader, compress, validate)?,
+            commitment_blinding: BlindingAccumulator::deserialize_with_mode(
+                &mut reader,
+                compress,
+                validate,
+            )?,
+        })
+    }
+}
+
+impl<F: PrimeField> CanonicalSerialize for FeeOpening<F> {
+    fn serialized_size(&self, mode: Compress) -> usize {
+        self.value.serialized_size(mode) + self.blinding.serialized_size(mode)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), SerializationError> {
+        self.value.serialize_with_mode(&mut writer, compress)?;
+        self.blinding.serialize_with_mode(&mut writer, compress)?;
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> Valid for FeeOpening<F> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+impl<F: PrimeField> CanonicalDeserialize for FeeOpening<F> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, SerializationError> {
+        Ok(Self {
+            value: u64::deserialize_with_mode(&mut reader, compress, validate)?,
+            blinding: F::deserialize_with_mode(&mut reader, compress, validate)?,
+        })
+    }
+}
+
+impl<P: SWCurveConfig, C: CurveGroup> CanonicalSerialize for SpendRequest<P, C> {
+    fn serialized_size(&self, mode: Compress) -> usize {
+        self.leaf.serialized_size(mode)
+            + self.coin.serialized_size(mode)
+            + self.randomized_pk.serialized_size(mode)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), SerializationError> {
+        self.leaf.serialize_with_mode(&mut writer, compress)?;
+        self.coin.serialize_with_mode(&mut writer, compress)?;
+        self.randomized_pk
+            .serialize_with_mode(&mut writer, compress)?;
+        Ok(())
+    }
+}
+
+impl<P: SWCurveConfig, C: CurveGroup> Valid for SpendRequest<P, C> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+impl<P: SWCurveConfig, C: CurveGroup> CanonicalDeserialize for SpendRequest<P, C> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, SerializationError> {
+        Ok(Self {
+            leaf: LeafRef::deserialize_with_mode(&mut reader, compress, validate)?,
+            coin: CoinOpening::deserialize_with_mode(&mut reader, compress, validate)?,
+            randomized_pk: PublicKey::<C>::deserialize_with_mode(&mut reader, compress, validate)?,
+        })
+    }
+}
+
+impl<P0: SWCurveConfig, C: CurveGroup> CanonicalSerialize for VerifiedSpend<P0, C> {
+    fn serialized_size(&self, mode: Compress) -> usize {
+        self.rerandomized_pk.serialized_size(mode)
+            + self.tag.serialized_size(mode)
+            + self.value_commitment.serialized_size(mode)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), SerializationError> {
+        self.rerandomized_pk
+            .serialize_with_mode(&mut writer, compress)?;
+        self.tag.serialize_with_mode(&mut writer, compress)?;
+        self.value_commitment
+            .serialize_with_mode(&mut writer, compress)?;
+        Ok(())
+    }
+}
+
+impl<P0: SWCurveConfig, C: CurveGroup> CanonicalSerialize for PourRequest<P0, C> {
+    fn serialized_size(&self, mode: Compress) -> usize {
+        self.inputs[0].serialized_size(mode)
+            + self.inputs[1].serialized_size(mode)
+            + self.outputs[0].serialized_size(mode)
+            + self.outputs[1].serialized_size(mode)
+            + self.output_commitments[0].serialized_size(mode)
+            + self.output_commitments[1].serialized_size(mode)
+            + self.fee.serialized_size(mode)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), SerializationError> {
+        for input in &self.inputs {
+            input.serialize_with_mode(&mut writer, compress)?;
+        }
+        for output in &self.outputs {
+            output.serialize_with_mode(&mut writer, compress)?;
+        }
+        for commitment in &self.output_commitments {
+            commitment.serialize_with_mode(&mut writer, compress)?;
+        }
+        self.fee.serialize_with_mode(&mut writer, compress)?;
+        Ok(())
+    }
+}
+
+impl<P0: SWCurveConfig, C: CurveGroup> Valid for PourRequest<P0, C> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+impl<P0: SWCurveConfig, C: CurveGroup> CanonicalDeserialize for PourRequest<P0, C> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, SerializationError> {
+        let mut input = || SpendRequest::deserialize_with_mode(&mut reader, compress, validate);
+        let inputs = [input()?, input()?];
+        let mut output = || CoinOpening::deserialize_with_mode(&mut reader, compress, validate);
+        let outputs = [output()?, output()?];
+        let mut commitment =
+            || Affine::<P0>::deserialize_with_mode(&mut reader, compress, validate);
+        let output_commitments = [commitment()?, commitment()?];
+        let fee = Option::<(FeeOpening<P0::ScalarField>, Affine<P0>)>::deserialize_with_mode(
+            &mut reader,
+            compress,
+            validate,
+        )?;
+        Ok(Self {
+            inputs,
+            outputs,
+            output_commitments,
+            fee,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use merlin::Transcript;
+    type PallasParameters = ark_pallas::PallasConfig;
+    type VestaParameters = ark_vesta::VestaConfig;
+    type PallasP = ark_pallas::Projective;
+    type PallasScalar = ark_pallas::Fr;
+
+    #[test]
+    fn test_schnorr() {
+        let mut rng = rand::thread_rng();
+        let parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let (pk, sk) = Schnorr::keygen(&parameters, &mut rng).unwrap();
+        let msg = b"msg";
+        let signature = Schnorr::sign(&parameters, &sk, msg.as_slice(), &mut rng).unwrap();
+        let randomness = [42u8];
+        let randomized_sig =
+            Schnorr::randomize_signature(&parameters, &signature, &randomness).unwrap();
+        let randomized_pk = Schnorr::randomize_public_key(&parameters, &pk, &randomness).unwrap();
+        let res = Schnorr::verify(&parameters, &randomized_pk, msg, &randomized_sig).unwrap();
+        assert_eq!(res, true);
+    }
+
+    #[test]
+    fn test_verify_signatures_batch() {
+        let mut rng = rand::thread_rng();
+        let parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let messages: Vec<[u8; 8]> = (0..300u64).map(u64::to_le_bytes).collect();
+        let items: Vec<SignatureItem<PallasP>> = messages
+            .iter()
+            .map(|msg| {
+                let (pk, sk) = Schnorr::keygen(&parameters, &mut rng).unwrap();
+                let signature = Schnorr::sign(&parameters, &sk, msg, &mut rng).unwrap();
+                (pk, msg.as_slice(), signature)
+            })
+            .collect();
+        assert!(items.iter().all(|(pk, msg, signature)| Schnorr::verify(
+            &parameters,
+            pk,
+            msg,
+            signature
+        )
+        .unwrap()));
+        assert!(verify_signatures_batch(&parameters, &items).is_ok());
+        assert!(verify_signatures_batch(&parameters, &[]).is_ok());
+
+        // Exactly one invalid signature: a forged response, a message signed by another key, another message.
+        let mut forged = items.clone();
+        forged[137].2.prover_response += PallasScalar::from(1u64);
+        let mut swapped = items.clone();
+        swapped[0].0 = items[1].0;
+        let mut other_message = items.clone();
+        other_message[299].1 = b"another";
+        for (batch, index) in [(forged, 137), (swapped, 0), (other_message, 299)] {
+            assert!(matches!(
+                verify_signatures_batch(&parameters, &batch),
+                Err(Error::InvalidSignatureInBatch { index: i }) if i == index
+            ));
+        }
+    }
+
+    #[test]
+    fn test_mint_bounded_rejects_value_out_of_range() {
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 8, 1 << 8);
+        let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
+        let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+        let mut prover: Prover<_, Affine<PallasParameters>> =
+            Prover::new(&sr_params.even_parameters.pc_gens, Transcript::new(b"mint"));
+
+        let minted = Coin::<PallasParameters, PallasP>::mint_bounded(
+            100,
+            Some(100),
+            &pk,
+            &schnorr_parameters,
+            &sr_params.even_parameters,
+            &layout,
+            &sr_params.value_policy,
+            &mut rng,
+            &mut prover,
+        );
+        assert!(matches!(minted, Err(Error::InconsistentWitness { .. })));
+    }
+
+    #[test]
+    fn test_equal_value_of_coins() {
+        use crate::equal_value::{prove_equal_value, ValueGenerators};
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 8, 1 << 8);
+        let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
+        let coins = ValueGenerators::layout(&sr_params.even_parameters, &layout).unwrap();
+        let mut mint = |value| {
+            let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+            let (coin, commitment) = Coin::<PallasParameters, PallasP>::new(
+                value,
+                &pk,
+                &schnorr_parameters,
+                &sr_params.even_parameters,
+                &layout,
+                &mut rng,
+            )
+            .unwrap();
+            (commitment, coin.opening().value_opening(&layout).unwrap())
+        };
+        // A coin being spent and a coin someone else minted for a swap, then one of another value.
+        let (mine, my_opening) = mint(42);
+        let (theirs, their_opening) = mint(42);
+        let (other, other_opening) = mint(41);
+
+        let mut rng = rand::thread_rng();
+        let proof = prove_equal_value(
+            &coins,
+            &mine,
+            &my_opening,
+            &coins,
+            &theirs,
+            &their_opening,
+            &mut rng,
+        )
+        .unwrap();
+        proof.verify(&coins, &mine, &coins, &theirs).unwrap();
+        assert!(proof.verify(&coins, &mine, &coins, &other).is_err());
+        assert!(matches!(
+            prove_equal_value(
+                &coins,
+                &mine,
+                &my_opening,
+                &coins,
+                &other,
+                &other_opening,
+                &mut rng
+            ),
+            Err(Error::InconsistentWitness { .. })
+        ));
+
+        // The value of a coin and the fee commitment of a pour.
+        let fee = FeeOpening {
+            value: 42,
+            blinding: PallasScalar::rand(&mut rng),
+        };
+        let fee_commitment = fee.commitment(&sr_params.even_parameters);
+        let pedersen = ValueGenerators::pedersen(&sr_params.even_parameters);
+        let proof = prove_equal_value(
+            &coins,
+            &mine,
+            &my_opening,
+            &pedersen,
+            &fee_commitment,
+            &fee.value_opening(),
+            &mut rng,
+        )
+        .unwrap();
+        proof
+            .verify(&coins, &mine, &pedersen, &fee_commitment)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_prove_membership_with_mock_accumulator() {
+        use crate::accumulator::mock::MockAccumulator;
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 8, 1 << 8);
+        let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
+        let (coins, commitments): (Vec<_>, Vec<_>) = (0..3u64)
+            .map(|value| {
+                let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+                Coin::<PallasParameters, PallasP>::new(
+                    value,
+                    &pk,
+                    &schnorr_parameters,
+                    &sr_params.even_parameters,
+                    &layout,
+                    &mut rng,
+                )
+                .unwrap()
+            })
+            .unzip();
+
+        let mut accumulator = MockAccumulator { elements: vec![] };
+        let witnesses = accumulator
+            .insert(&commitments, &sr_params.even_parameters)
+            .unwrap();
+        assert_eq!(witnesses, vec![0, 1, 2]);
+        assert_eq!(accumulator.root(), 3);
+
+        let opening = coins[1].opening();
+        let (proof, blinding) = opening
+            .prove_membership(
+                &accumulator,
+                &witnesses[1],
+                &sr_params.even_parameters,
+                &layout,
+                &sr_params.even_parameters,
+                &mut rng,
+            )
+            .unwrap();
+        let rerandomized_commitment = accumulator
+            .verify_membership(&proof, &sr_params.even_parameters)
+            .unwrap();
+        assert_eq!(
+            rerandomized_commitment,
+            sr_params
+                .even_parameters
+                .commit(
+                    &layout
+                        .vector(&[(VALUE, ark_pallas::Fr::from(1u64)), (TAG, opening.tag)])
+                        .unwrap(),
+                    blinding.0,
+                    0
+                )
+                .unwrap()
+        );
+
+        // The witness of another coin does not open to the coin.
+        assert!(matches!(
+            opening.prove_membership(
+                &accumulator,
+                &witnesses[2],
+                &sr_params.even_parameters,
+                &layout,
+                &sr_params.even_parameters,
+                &mut rng,
+            ),
+            Err(Error::InconsistentWitness { .. })
+        ));
+    }
+
+    #[test]
+    fn test_new_batch_matches_new() {
+        use rand::{rngs::StdRng, SeedableRng};
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 8, 1 << 8);
+        let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
+        let outputs: Vec<_> = (0..100u64)
+            .map(|value| {
+                let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+                (value, pk)
+            })
+            .collect();
+
+        let mut sequential_rng = StdRng::seed_from_u64(0);
+        let batch = Coin::<PallasParameters, PallasP>::new_batch(
+            &outputs,
+            &schnorr_parameters,
+            &sr_params.even_parameters,
+            &layout,
+            &mut StdRng::seed_from_u64(0),
+        )
+        .unwrap();
+        assert_eq!(batch.len(), outputs.len());
+        for ((value, pk), (coin, commitment)) in outputs.iter().zip(batch) {
+            let (expected_coin, expected_commitment) = Coin::<PallasParameters, PallasP>::new(
+                *value,
+                pk,
+                &schnorr_parameters,
+                &sr_params.even_parameters,
+                &layout,
+                &mut sequential_rng,
+            )
+            .unwrap();
+            assert!(coin.opening() == expected_coin.opening());
+            assert!(coin.commitment_blinding.terms() == expected_coin.commitment_blinding.terms());
+            assert_eq!(
+                coin.pk_randomness.terms(),
+                expected_coin.pk_randomness.terms()
+            );
+            assert_eq!(commitment, expected_commitment);
+        }
+    }
+
+    #[test]
+    fn test_identity_points_rejected() {
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 8, 1 << 8);
+        let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
+        let identity = Affine::<PallasParameters>::zero();
+
+        // A coin for the identity could be spent by anyone.
+        assert!(matches!(
+            Coin::<PallasParameters, PallasP>::new(
+                1,
+                &identity,
+                &schnorr_parameters,
+                &sr_params.even_parameters,
+                &layout,
+                &mut rng,
+            ),
+            Err(Error::IdentityPoint)
+        ));
+        let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+        assert!(matches!(
+            Coin::<PallasParameters, PallasP>::new_batch(
+                &[(1, pk), (2, identity)],
+                &schnorr_parameters,
+                &sr_params.even_parameters,
+                &layout,
+                &mut rng,
+            ),
+            Err(Error::IdentityPoint)
+        ));
+
+        let mut verifier = Verifier::new(Transcript::new(b"mint"));
+        assert!(matches!(
+            verify_mint(&mut verifier, identity, &layout, &sr_params.value_policy),
+            Err(Error::IdentityPoint)
+        ));
+    }
+
+    #[test]
+    fn test_mint_zero_value() {
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 8, 1 << 8);
+        let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
+        let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+        let mut prover: Prover<_, Affine<PallasParameters>> =
+            Prover::new(&sr_params.even_parameters.pc_gens, Transcript::new(b"mint"));
+        let (_, commitment, _) = Coin::<PallasParameters, PallasP>::mint(
+            0,
+            &pk,
+            &schnorr_parameters,
+            &sr_params.even_parameters,
+            &layout,
+            &sr_params.value_policy,
+            &mut rng,
+            &mut prover,
+        )
+        .unwrap();
+        let proof = prover.prove(&sr_params.even_parameters.bp_gens).unwrap();
+
+        let mut verifier = Verifier::new(Transcript::new(b"mint"));
+        verify_mint(&mut verifier, commitment, &layout, &sr_params.value_policy).unwrap();
+        verifier
+            .verify(
+                &proof,
+                &sr_params.even_parameters.pc_gens,
+                &sr_params.even_parameters.bp_gens,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_deposit_mint() {
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 8, 1 << 8);
+        let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
+        let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+
+        // The extremes of the range proof of the value.
+        for amount in [0, 19, u64::MAX] {
+            let mut prover: Prover<_, Affine<PallasParameters>> = Prover::new(
+                &sr_params.even_parameters.pc_gens,
+                Transcript::new(b"deposit"),
+            );
+            let (coin, commitment, _) = Coin::<PallasParameters, PallasP>::deposit_mint(
+                amount,
+                &pk,
+                &schnorr_parameters,
+                &sr_params.even_parameters,
+                &layout,
+                &sr_params.value_policy,
+                &mut rng,
+                &mut prover,
+            )
+            .unwrap();
+            assert_eq!(coin.value, amount);
+            let proof = prover.prove(&sr_params.even_parameters.bp_gens).unwrap();
+
+            let verify = |amount: u64| {
+                let mut verifier = Verifier::new(Transcript::new(b"deposit"));
+                verify_deposit_mint(
+                    &mut verifier,
+                    commitment,
+                    &layout,
+                    amount,
+                    &sr_params.value_policy,
+                )?;
+                verifier.verify(
+                    &proof,
+                    &sr_params.even_parameters.pc_gens,
+                    &sr_params.even_parameters.bp_gens,
+                )?;
+                Ok::<_, Error>(())
+            };
+            verify(amount).unwrap();
+            assert!(verify(amount.wrapping_add(1)).is_err());
+            assert!(verify(amount.wrapping_sub(1)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_commitment_blinding_terms() {
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 11, 1 << 11);
+        let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
+        let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+        let (coin, commitment) = Coin::<PallasParameters, PallasP>::new(
+            19,
+            &pk,
+            &schnorr_parameters,
+            &sr_params.even_parameters,
+            &layout,
+            &mut rng,
+        )
+        .unwrap();
+
+        // The terms sum to the blinding of the permissible commitment of the initial blinding.
+        let terms = coin.commitment_blinding.terms();
+        assert_eq!(
+            terms.iter().map(|(label, _)| *label).collect::<Vec<_>>(),
+            [INITIAL, PERMISSIBLE_OFFSET]
+        );
+        let vector = layout
+            .vector(&[(VALUE, PallasScalar::from(19u64)), (TAG, coin.tag)])
+            .unwrap();
+        let (expected_commitment, expected_blinding) = sr_params
+            .even_parameters
+            .permissible_commitment(&vector, terms[0].1 .0, 0)
+            .unwrap();
+        assert_eq!(commitment, expected_commitment);
+        assert_eq!(coin.commitment_blinding.total().0, expected_blinding);
+        assert_eq!(coin.pk_randomness.terms()[0].0, PK_RERANDOMIZATION);
+
+        let curve_tree = CurveTree::<32, PallasParameters, VestaParameters>::from_set(
+            &[commitment],
+            &sr_params,
+            Some(2),
+        )
+        .unwrap();
+        let mut wrong = coin.opening();
+        wrong
+            .commitment_blinding
+            .add_term(PERMISSIBLE_OFFSET, EvenScalar(PallasScalar::from(1u64)));
+        let result = wrong.prove_spend(
+            0,
+            &mut Prover::new(
+                &sr_params.even_parameters.pc_gens,
+                Transcript::new(b"spend"),
+            ),
+            &mut Prover::new(&sr_params.odd_parameters.pc_gens, Transcript::new(b"spend")),
+            &sr_params,
+            &layout,
+            &curve_tree,
+        );
+        match result {
+            Err(Error::InconsistentWitness { description }) => {
+                assert!(description.contains(PERMISSIBLE_OFFSET));
+            }
+            _ => panic!("spending with a wrong blinding term succeeded"),
+        }
+    }
+
+    #[test]
+    fn test_spend_strictness() {
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 11, 1 << 11);
+        let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
+        let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+        let (coin, commitment) = Coin::<PallasParameters, PallasP>::new(
+            19,
+            &pk,
+            &schnorr_parameters,
+            &sr_params.even_parameters,
+            &layout,
+            &mut rng,
+        )
+        .unwrap();
+        let curve_tree = CurveTree::<32, PallasParameters, VestaParameters>::from_set(
+            &[commitment],
+            &sr_params,
+            Some(2),
+        )
+        .unwrap();
+        let randomized_pk = Coin::<PallasParameters, PallasP>::rerandomized_pk(
+            &pk,
+            &coin.pk_randomness.total(),
+            &schnorr_parameters,
+        );
+        // A corrupted witness: the coin claims a value it was not minted with.
+        let mut corrupted = coin.opening();
+        corrupted.value = 20;
+
+        let prove = |opening: &CoinOpening<PallasParameters>, strictness: Strictness| {
+            let mut even_prover = Prover::new(
+                &sr_params.even_parameters.pc_gens,
+                Transcript::new(b"spend"),
+            );
+            let mut odd_prover =
+                Prover::new(&sr_params.odd_parameters.pc_gens, Transcript::new(b"spend"));
+            let (path, _) = opening.prove_spend_with_strictness(
+                0,
+                &mut even_prover,
+                &mut odd_prover,
+                &sr_params,
+                &layout,
+                &curve_tree,
+                strictness,
+            )?;
+            let even_proof = even_prover.prove(&sr_params.even_parameters.bp_gens)?;
+            let odd_proof = odd_prover.prove(&sr_params.odd_parameters.bp_gens)?;
+            Ok::<_, Error>((path, even_proof, odd_proof))
+        };
+        let verify = |path: SelectAndRerandomizePath<32, PallasParameters, VestaParameters>,
+                      even_proof: R1CSProof<Affine<PallasParameters>>,
+                      odd_proof: R1CSProof<Affine<VestaParameters>>| {
+            let mut even_verifier = Verifier::new(Transcript::new(b"spend"));
+            let mut odd_verifier = Verifier::new(Transcript::new(b"spend"));
+            verify_spend::<32, _, _, _, _, PallasP>(
+                &mut even_verifier,
+                &mut odd_verifier,
+                &curve_tree.select_and_rerandomize_verification_commitments(path)?,
+                &sr_params,
+                &layout,
+                &randomized_pk,
+                &curve_tree,
+            )?;
+            even_verifier.verify(
+                &even_proof,
+                &sr_params.even_parameters.pc_gens,
+                &sr_params.even_parameters.bp_gens,
+            )?;
+            odd_verifier.verify(
+                &odd_proof,
+                &sr_params.odd_parameters.pc_gens,
+                &sr_params.odd_parameters.bp_gens,
+            )?;
+            Ok::<_, Error>(())
+        };
+
+        for strictness in [Strictness::Checked, Strictness::Fast] {
+            let (path, even_proof, odd_proof) = prove(&coin.opening(), strictness).unwrap();
+            verify(path, even_proof, odd_proof).unwrap();
+        }
+        assert!(matches!(
+            prove(&corrupted, Strictness::Checked),
+            Err(Error::InconsistentWitness { .. })
+        ));
+        let (path, even_proof, odd_proof) = prove(&corrupted, Strictness::Fast).unwrap();
+        assert!(verify(path, even_proof, odd_proof).is_err());
+    }
+
+    #[test]
+    fn test_permissible_leaf_and_opening() {
+        // The leaf is shared by nodes, the opening is not.
+        fn public<T: Clone + Copy + std::fmt::Debug + Eq + Send + Sync>() {}
+        public::<PermissibleLeaf<PallasParameters>>();
+
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 4, 1 << 4);
+        let parameters = &sr_params.even_parameters;
+        let vector = [PallasScalar::from(30u64), PallasScalar::rand(&mut rng)];
+        let (blinding, commitment) = loop {
+            let blinding = PallasScalar::rand(&mut rng);
+            let commitment = parameters.commit(&vector, blinding, 0).unwrap();
+            if !open_permissible_leaf(&commitment, parameters)
+                .1
+                .is_trivial()
+            {
+                break (blinding, commitment);
+            }
+        };
+        let leaf = permissible_leaf(&commitment, parameters);
+        let (opened_leaf, opening) = open_permissible_leaf(&commitment, parameters);
+        assert_eq!(opened_leaf, leaf);
+        assert_ne!(leaf.permissible_coin, commitment);
+        assert_eq!(
+            parameters
+                .commit(&vector, blinding + opening.offset, 0)
+                .unwrap(),
+            leaf.permissible_coin
+        );
+        verify_inserted_leaf(&commitment, &leaf.permissible_coin, parameters).unwrap();
+
+        // A permissible commitment is its own leaf.
+        let (own_leaf, own_opening) = open_permissible_leaf(&leaf.permissible_coin, parameters);
+        assert_eq!(own_leaf, leaf);
+        assert!(own_opening.is_trivial());
+    }
+
+    #[test]
+    fn test_withdraw() {
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 11, 1 << 11);
+        let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
+        let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+        let (coin, commitment) = Coin::<PallasParameters, PallasP>::new(
+            19,
+            &pk,
+            &schnorr_parameters,
+            &sr_params.even_parameters,
+            &layout,
+            &mut rng,
+        )
+        .unwrap();
+        let curve_tree = CurveTree::<32, PallasParameters, VestaParameters>::from_set(
+            &[commitment],
+            &sr_params,
+            Some(2),
+        )
+        .unwrap();
+        let randomized_pk = Coin::<PallasParameters, PallasP>::rerandomized_pk(
+            &pk,
+            &coin.pk_randomness.total(),
+            &schnorr_parameters,
+        );
+        let provers = || {
+            (
+                Prover::new(
+                    &sr_params.even_parameters.pc_gens,
+                    Transcript::new(b"withdraw"),
+                ),
+                Prover::new(
+                    &sr_params.odd_parameters.pc_gens,
+                    Transcript::new(b"withdraw"),
+                ),
+            )
+        };
+
+        let (mut even_prover, mut odd_prover) = provers();
+        assert!(matches!(
+            coin.prove_withdraw(
+                20,
+                0,
+                &mut even_prover,
+                &mut odd_prover,
+                &sr_params,
+                &layout,
+                &curve_tree
+            ),
+            Err(Error::InconsistentWitness { .. })
+        ));
+
+        let (mut even_prover, mut odd_prover) = provers();
+        let path = coin
+            .prove_withdraw(
+                19,
+                0,
+                &mut even_prover,
+                &mut odd_prover,
+                &sr_params,
+                &layout,
+                &curve_tree,
+            )
+            .unwrap();
+        let even_proof = even_prover
+            .prove(&sr_params.even_parameters.bp_gens)
+            .unwrap();
+        let odd_proof = odd_prover.prove(&sr_params.odd_parameters.bp_gens).unwrap();
+
+        let verify = |amount: u64| {
+            let mut even_verifier = Verifier::new(Transcript::new(b"withdraw"));
+            let mut odd_verifier = Verifier::new(Transcript::new(b"withdraw"));
+            verify_withdraw::<32, _, _, _, _, PallasP>(
+                &mut even_verifier,
+                &mut odd_verifier,
+                path.clone(),
+                &sr_params,
+                &layout,
+                &randomized_pk,
+                &curve_tree,
+                amount,
+            )?;
+            even_verifier.verify(
+                &even_proof,
+                &sr_params.even_parameters.pc_gens,
+                &sr_params.even_parameters.bp_gens,
+            )?;
+            odd_verifier.verify(
+                &odd_proof,
+                &sr_params.odd_parameters.pc_gens,
+                &sr_params.odd_parameters.bp_gens,
+            )?;
+            Ok::<_, Error>(())
+        };
+        verify(19).unwrap();
+        assert!(verify(20).is_err());
+        assert!(verify(0).is_err());
+    }
 
     #[test]
-    fn test_schnorr() {
+    fn test_mint_layout_mismatch() {
         let mut rng = rand::thread_rng();
-        let parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
-        let (pk, sk) = Schnorr::keygen(&parameters, &mut rng).unwrap();
-        let msg = b"msg";
-        let signature = Schnorr::sign(&parameters, &sk, msg.as_slice(), &mut rng).unwrap();
-        let randomness = [42u8];
-        let randomized_sig =
-            Schnorr::randomize_signature(&parameters, &signature, &randomness).unwrap();
-        let randomized_pk = Schnorr::randomize_public_key(&parameters, &pk, &randomness).unwrap();
-        let res = Schnorr::verify(&parameters, &randomized_pk, msg, &randomized_sig).unwrap();
-        assert_eq!(res, true);
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 8, 1 << 8);
+        let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
+        let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+        let mut prover: Prover<_, Affine<PallasParameters>> =
+            Prover::new(&sr_params.even_parameters.pc_gens, Transcript::new(b"mint"));
+        let (_, commitment, _) = Coin::<PallasParameters, PallasP>::mint(
+            100,
+            &pk,
+            &schnorr_parameters,
+            &sr_params.even_parameters,
+            &layout,
+            &sr_params.value_policy,
+            &mut rng,
+            &mut prover,
+        )
+        .unwrap();
+        let proof = prover.prove(&sr_params.even_parameters.bp_gens).unwrap();
+
+        let verify = |layout: &CommitmentLayout| {
+            let mut verifier = Verifier::new(Transcript::new(b"mint"));
+            verify_mint(&mut verifier, commitment, layout, &sr_params.value_policy)?;
+            verifier.verify(
+                &proof,
+                &sr_params.even_parameters.pc_gens,
+                &sr_params.even_parameters.bp_gens,
+            )?;
+            Ok::<_, Error>(())
+        };
+        verify(&layout).unwrap();
+        // The value read from the tag slot, under another transcript domain.
+        let swapped = CommitmentLayout::new(&[(TAG, 0), (VALUE, 1)]).unwrap();
+        assert!(verify(&swapped).is_err());
     }
 
     #[test]
@@ -799,7 +3347,6 @@ mod tests {
         let sr_params = SelRerandParameters::<PallasParameters, VestaParameters>::new(
             generators_length,
             generators_length,
-            &mut rng,
         );
 
         let pallas_transcript = Transcript::new(b"select_and_rerandomize");
@@ -811,6 +3358,7 @@ mod tests {
             Prover::new(&sr_params.odd_parameters.pc_gens, vesta_transcript);
 
         let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
         let (pk, _sk) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
 
         let (coin_aux, coin) = Coin::<PallasParameters, PallasP>::new(
@@ -818,11 +3366,13 @@ mod tests {
             &pk,
             &schnorr_parameters,
             &sr_params.even_parameters,
+            &layout,
             &mut rng,
-        );
+        )
+        .unwrap();
         let rerandomized_pk = Coin::<PallasParameters, PallasP>::rerandomized_pk(
             &pk,
-            &coin_aux.pk_randomness,
+            &coin_aux.pk_randomness.total(),
             &schnorr_parameters,
         );
         // Curve tree with two coins
@@ -831,15 +3381,19 @@ mod tests {
             &set,
             &sr_params,
             Some(4),
-        );
+        )
+        .unwrap();
 
-        let (path, _) = coin_aux.prove_spend(
-            0,
-            &mut pallas_prover,
-            &mut vesta_prover,
-            &sr_params,
-            &curve_tree,
-        );
+        let (path, _) = coin_aux
+            .prove_spend(
+                0,
+                &mut pallas_prover,
+                &mut vesta_prover,
+                &sr_params,
+                &layout,
+                &curve_tree,
+            )
+            .unwrap();
 
         let pallas_proof = pallas_prover
             .prove(&sr_params.even_parameters.bp_gens)
@@ -854,15 +3408,19 @@ mod tests {
             let vesta_transcript = Transcript::new(b"select_and_rerandomize");
             let mut vesta_verifier = Verifier::new(vesta_transcript);
 
-            let commitments = curve_tree.select_and_rerandomize_verification_commitments(path);
-            verify_spend_odd(&mut vesta_verifier, &commitments, &sr_params, &curve_tree);
-            verify_spend_even::<256, _, _, _, _, PallasP>(
+            let commitments = curve_tree
+                .select_and_rerandomize_verification_commitments(path)
+                .unwrap();
+            verify_spend::<256, _, _, _, _, PallasP>(
                 &mut pallas_verifier,
+                &mut vesta_verifier,
                 &commitments,
                 &sr_params,
+                &layout,
                 &rerandomized_pk,
                 &curve_tree,
-            );
+            )
+            .unwrap();
 
             vesta_verifier
                 .verify(
@@ -881,6 +3439,245 @@ mod tests {
         }
     }
 
+    // A generated key, its public key, a coin minted to it, spent and signed for, on the cycle of `P0` and `P1`.
+    fn check_generated_key_spend<
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+        C: CurveGroup + std::hash::Hash,
+    >()
+    where
+        C::ScalarField: PrimeField,
+    {
+        let mut rng = rand::thread_rng();
+        let sr_params = SelRerandParameters::<P0, P1>::new(1 << 12, 1 << 12);
+        let schnorr_parameters = Schnorr::<C, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
+        let sk = generate_secret_key::<C, _>(&mut rng);
+        let pk = public_key_from_secret(&sk, &schnorr_parameters).unwrap();
+        assert_eq!(pk, (schnorr_parameters.generator * sk.0).into_affine());
+
+        let (coin_aux, coin) = Coin::<P0, C>::new(
+            19,
+            &pk,
+            &schnorr_parameters,
+            &sr_params.even_parameters,
+            &layout,
+            &mut rng,
+        )
+        .unwrap();
+        let rerandomized_pk = Coin::<P0, C>::rerandomized_pk(
+            &pk,
+            &coin_aux.pk_randomness.total(),
+            &schnorr_parameters,
+        );
+        let curve_tree = CurveTree::<32, P0, P1>::from_set(&[coin], &sr_params, Some(2)).unwrap();
+
+        let mut even_prover =
+            Prover::new(&sr_params.even_parameters.pc_gens, Transcript::new(b"keys"));
+        let mut odd_prover =
+            Prover::new(&sr_params.odd_parameters.pc_gens, Transcript::new(b"keys"));
+        let (path, _) = coin_aux
+            .prove_spend(
+                0,
+                &mut even_prover,
+                &mut odd_prover,
+                &sr_params,
+                &layout,
+                &curve_tree,
+            )
+            .unwrap();
+        let even_proof = even_prover
+            .prove(&sr_params.even_parameters.bp_gens)
+            .unwrap();
+        let odd_proof = odd_prover.prove(&sr_params.odd_parameters.bp_gens).unwrap();
+
+        let mut even_verifier = Verifier::new(Transcript::new(b"keys"));
+        let mut odd_verifier = Verifier::new(Transcript::new(b"keys"));
+        let commitments = curve_tree
+            .select_and_rerandomize_verification_commitments(path)
+            .unwrap();
+        verify_spend::<32, _, _, _, _, C>(
+            &mut even_verifier,
+            &mut odd_verifier,
+            &commitments,
+            &sr_params,
+            &layout,
+            &rerandomized_pk,
+            &curve_tree,
+        )
+        .unwrap();
+        odd_verifier
+            .verify(
+                &odd_proof,
+                &sr_params.odd_parameters.pc_gens,
+                &sr_params.odd_parameters.bp_gens,
+            )
+            .unwrap();
+        even_verifier
+            .verify(
+                &even_proof,
+                &sr_params.even_parameters.pc_gens,
+                &sr_params.even_parameters.bp_gens,
+            )
+            .unwrap();
+
+        // The key signs for the rerandomized key once its signature is randomized by the whole rerandomization.
+        let signature = Schnorr::sign(&schnorr_parameters, &sk, b"spend", &mut rng).unwrap();
+        let mut randomization_bytes = Vec::new();
+        coin_aux
+            .pk_randomness
+            .total()
+            .serialize_compressed(&mut randomization_bytes)
+            .unwrap();
+        let signature =
+            Schnorr::randomize_signature(&schnorr_parameters, &signature, &randomization_bytes)
+                .unwrap();
+        assert!(
+            Schnorr::verify(&schnorr_parameters, &rerandomized_pk, b"spend", &signature).unwrap()
+        );
+        assert!(!Schnorr::verify(&schnorr_parameters, &pk, b"spend", &signature).unwrap());
+    }
+
+    #[test]
+    fn test_generated_key_spend_pallas_vesta() {
+        check_generated_key_spend::<_, _, PallasParameters, VestaParameters, PallasP>();
+    }
+
+    #[test]
+    fn test_generated_key_spend_secp_secq() {
+        check_generated_key_spend::<
+            _,
+            _,
+            ark_secp256k1::Config,
+            ark_secq256k1::Config,
+            ark_secp256k1::Projective,
+        >();
+    }
+
+    #[test]
+    fn test_public_key_of_zero_key_is_an_error() {
+        let mut rng = rand::thread_rng();
+        let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        assert!(matches!(
+            public_key_from_secret(
+                &SecretKey::<PallasP>(PallasScalar::zero()),
+                &schnorr_parameters
+            ),
+            Err(Error::IdentityPoint)
+        ));
+    }
+
+    // Sub-relations of other modules on the rerandomized coin commitment of a spend, which opens it once.
+    #[test]
+    fn test_spend_composed_sub_relations() {
+        use crate::layout::ASSET;
+        use crate::opened::enforce_asset;
+
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 12, 1 << 12);
+        let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin().with_trailing(&[ASSET]).unwrap();
+        let (pk, _sk) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+        let (coin_aux, coin) = Coin::<PallasParameters, PallasP>::new(
+            19,
+            &pk,
+            &schnorr_parameters,
+            &sr_params.even_parameters,
+            &layout,
+            &mut rng,
+        )
+        .unwrap();
+        let rerandomized_pk = Coin::<PallasParameters, PallasP>::rerandomized_pk(
+            &pk,
+            &coin_aux.pk_randomness.total(),
+            &schnorr_parameters,
+        );
+        let curve_tree = CurveTree::<32, PallasParameters, VestaParameters>::from_set(
+            &[coin],
+            &sr_params,
+            Some(2),
+        )
+        .unwrap();
+        let asset = PallasScalar::from(0u64);
+
+        // The spend enforces the tag, the range of the value and the asset are enforced on its opening.
+        let mut even_prover = Prover::new(
+            &sr_params.even_parameters.pc_gens,
+            Transcript::new(b"composed"),
+        );
+        let mut odd_prover = Prover::new(
+            &sr_params.odd_parameters.pc_gens,
+            Transcript::new(b"composed"),
+        );
+        let (path, opened) = coin_aux
+            .prove_spend(
+                0,
+                &mut even_prover,
+                &mut odd_prover,
+                &sr_params,
+                &layout,
+                &curve_tree,
+            )
+            .unwrap();
+        assert_eq!(
+            opened.value(VALUE).unwrap(),
+            Some(PallasScalar::from(19u64))
+        );
+        enforce_value_range(
+            &mut even_prover,
+            &opened,
+            Some(100),
+            &sr_params.value_policy,
+        )
+        .unwrap();
+        enforce_asset(&mut even_prover, &opened, asset).unwrap();
+        let even_proof = even_prover
+            .prove(&sr_params.even_parameters.bp_gens)
+            .unwrap();
+        let odd_proof = odd_prover.prove(&sr_params.odd_parameters.bp_gens).unwrap();
+
+        let verify = |max_value, asset| -> Result<(), Error> {
+            let mut even_verifier = Verifier::new(Transcript::new(b"composed"));
+            let mut odd_verifier = Verifier::new(Transcript::new(b"composed"));
+            let commitments =
+                curve_tree.select_and_rerandomize_verification_commitments(path.clone())?;
+            let opened = verify_spend::<32, _, _, _, _, PallasP>(
+                &mut even_verifier,
+                &mut odd_verifier,
+                &commitments,
+                &sr_params,
+                &layout,
+                &rerandomized_pk,
+                &curve_tree,
+            )?;
+            enforce_value_range(
+                &mut even_verifier,
+                &opened,
+                max_value,
+                &sr_params.value_policy,
+            )?;
+            enforce_asset(&mut even_verifier, &opened, asset)?;
+            odd_verifier.verify(
+                &odd_proof,
+                &sr_params.odd_parameters.pc_gens,
+                &sr_params.odd_parameters.bp_gens,
+            )?;
+            even_verifier.verify(
+                &even_proof,
+                &sr_params.even_parameters.pc_gens,
+                &sr_params.even_parameters.bp_gens,
+            )?;
+            Ok(())
+        };
+        verify(Some(100), asset).unwrap();
+        // Another asset, or another bound on the value, is another relation.
+        assert!(verify(Some(100), PallasScalar::from(1u64)).is_err());
+        assert!(verify(Some(1000), asset).is_err());
+    }
+
     #[test]
     pub fn test_pour() {
         let mut rng = rand::thread_rng();
@@ -889,7 +3686,6 @@ mod tests {
         let sr_params = SelRerandParameters::<PallasParameters, VestaParameters>::new(
             generators_length,
             generators_length,
-            &mut rng,
         );
 
         let pallas_transcript = Transcript::new(b"select_and_rerandomize");
@@ -901,6 +3697,7 @@ mod tests {
             Prover::new(&sr_params.odd_parameters.pc_gens, vesta_transcript);
 
         let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
         let (pk, sk) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
 
         let (coin_aux_0, coin_0) = Coin::<PallasParameters, PallasP>::new(
@@ -908,41 +3705,46 @@ mod tests {
             &pk,
             &schnorr_parameters,
             &sr_params.even_parameters,
+            &layout,
             &mut rng,
-        );
+        )
+        .unwrap();
         let (coin_aux_1, coin_1) = Coin::<PallasParameters, PallasP>::new(
             23,
             &pk,
             &schnorr_parameters,
             &sr_params.even_parameters,
+            &layout,
             &mut rng,
-        );
+        )
+        .unwrap();
         // Curve tree with two coins
         let set = vec![coin_0, coin_1];
         let curve_tree = CurveTree::<256, PallasParameters, VestaParameters>::from_set(
             &set,
             &sr_params,
             Some(4),
-        );
+        )
+        .unwrap();
         let randomized_pk_0 = Coin::<PallasParameters, PallasP>::rerandomized_pk(
             &pk,
-            &coin_aux_0.pk_randomness,
+            &coin_aux_0.pk_randomness.total(),
             &schnorr_parameters,
         );
         let input0 = SpendingInfo {
             coin_aux: coin_aux_0,
-            index: 0,
+            leaf: curve_tree.leaf_ref(0).unwrap(),
             randomized_pk: randomized_pk_0,
             sk: sk.clone(),
         };
         let randomized_pk_1 = Coin::<PallasParameters, PallasP>::rerandomized_pk(
             &pk,
-            &coin_aux_1.pk_randomness,
+            &coin_aux_1.pk_randomness.total(),
             &schnorr_parameters,
         );
         let input1 = SpendingInfo {
             coin_aux: coin_aux_1,
-            index: 1,
+            leaf: curve_tree.leaf_ref(1).unwrap(),
             randomized_pk: randomized_pk_1,
             sk: sk,
         };
@@ -950,10 +3752,59 @@ mod tests {
         let receiver_pk_0 = pk;
         let receiver_pk_1 = pk;
 
+        // 11 + 32 is not 19 + 23
+        let unbalanced = prove_pour(
+            Prover::new(
+                &sr_params.even_parameters.pc_gens,
+                Transcript::new(b"select_and_rerandomize"),
+            ),
+            Prover::new(
+                &sr_params.odd_parameters.pc_gens,
+                Transcript::new(b"select_and_rerandomize"),
+            ),
+            &sr_params,
+            &layout,
+            &curve_tree,
+            &input0,
+            &input1,
+            11,
+            receiver_pk_0,
+            32,
+            receiver_pk_1,
+            &schnorr_parameters,
+            &mut rng,
+        );
+        assert!(matches!(unbalanced, Err(Error::InconsistentWitness { .. })));
+
+        // The same coin cannot be spent twice in a pour.
+        let double_spend = prove_pour(
+            Prover::new(
+                &sr_params.even_parameters.pc_gens,
+                Transcript::new(b"select_and_rerandomize"),
+            ),
+            Prover::new(
+                &sr_params.odd_parameters.pc_gens,
+                Transcript::new(b"select_and_rerandomize"),
+            ),
+            &sr_params,
+            &layout,
+            &curve_tree,
+            &input0,
+            &input0,
+            11,
+            receiver_pk_0,
+            27,
+            receiver_pk_1,
+            &schnorr_parameters,
+            &mut rng,
+        );
+        assert!(matches!(double_spend, Err(Error::DuplicateTag)));
+
         let proof = prove_pour(
             pallas_prover,
             vesta_prover,
             &sr_params,
+            &layout,
             &curve_tree,
             &input0,
             &input1,
@@ -963,15 +3814,206 @@ mod tests {
             receiver_pk_1,
             &schnorr_parameters,
             &mut rng,
-        );
+        )
+        .unwrap();
 
-        {
-            let (pallas_vt, vesta_vt) = proof.verification_gadget(
+        // Nor can a verifier be made to accept a pour spending the same tag twice.
+        let mut duplicate = proof.pour::<256>().unwrap();
+        duplicate.pk1 = duplicate.pk0;
+        duplicate.tags[1] = duplicate.tags[0];
+        assert!(matches!(
+            duplicate.verification_gadget(
+                b"select_and_rerandomize",
+                &sr_params,
+                &layout,
+                &curve_tree
+            ),
+            Err(Error::DuplicateTag)
+        ));
+
+        // Anyone can sign for the identity as a rerandomized public key.
+        let mut identity = proof.pour::<256>().unwrap();
+        identity.pk0 = Affine::<PallasParameters>::zero();
+        assert!(matches!(
+            identity.verification_gadget(
+                b"select_and_rerandomize",
+                &sr_params,
+                &layout,
+                &curve_tree
+            ),
+            Err(Error::IdentityPoint)
+        ));
+
+        // A published tag is that of the proofs, an application indexing the pour by it sees the spent coin.
+        let pour = proof.pour::<256>().unwrap();
+        assert_eq!(proof.tags().unwrap(), pour.tags());
+
+        // The components of the size reports sum to the serialized sizes.
+        let report = pour.size_report();
+        assert_eq!(report.total(), pour.compressed_size());
+        let tx_report = proof.size_report::<256>().unwrap();
+        assert_eq!(tx_report.total(), proof.compressed_size());
+        assert_eq!(
+            tx_report.get("even proof rounds"),
+            report.get("even proof rounds")
+        );
+        assert_eq!(
+            tx_report.get("signatures").unwrap() + report.total(),
+            tx_report.total()
+        );
+        let mut mismatched = pour.clone();
+        mismatched.tags[0] = ark_pallas::Fr::rand(&mut rng);
+        assert!(matches!(
+            mismatched.clone().verification_gadget(
+                b"select_and_rerandomize",
+                &sr_params,
+                &layout,
+                &curve_tree
+            ),
+            Err(Error::TagMismatch)
+        ));
+
+        // A node rejects all of these before deserializing the tree paths into verifiers.
+        let limits = DeserializeLimits::default();
+        let mut tags = TagSet::new();
+        proof.precheck::<256>(&tags, &limits).unwrap();
+        let with_pour = |pour: &Pour<256, PallasParameters, VestaParameters, PallasP>| {
+            let mut tx = proof.clone();
+            tx.pour_bytes.clear();
+            pour.serialize_compressed(&mut tx.pour_bytes).unwrap();
+            tx
+        };
+        let mut duplicate = proof.pour::<256>().unwrap();
+        duplicate.pk1 = duplicate.pk0;
+        duplicate.tags[1] = duplicate.tags[0];
+        assert!(matches!(
+            with_pour(&duplicate).precheck::<256>(&tags, &limits),
+            Err(Error::DuplicateTag)
+        ));
+        let mut identity = proof.pour::<256>().unwrap();
+        identity.pk0 = Affine::<PallasParameters>::zero();
+        assert!(matches!(
+            with_pour(&identity).precheck::<256>(&tags, &limits),
+            Err(Error::IdentityPoint)
+        ));
+        assert!(matches!(
+            with_pour(&mismatched).precheck::<256>(&tags, &limits),
+            Err(Error::TagMismatch)
+        ));
+        let mut short_path = proof.pour::<256>().unwrap();
+        short_path.randomized_path_1.odd_commitments.pop();
+        assert!(matches!(
+            with_pour(&short_path).precheck::<256>(&tags, &limits),
+            Err(Error::InvalidPath)
+        ));
+        assert!(matches!(
+            short_path.verification_gadget(
+                b"select_and_rerandomize",
+                &sr_params,
+                &layout,
+                &curve_tree
+            ),
+            Err(Error::InvalidPath)
+        ));
+
+        // A pour proven with generators of another capacity.
+        let other_params = SelRerandParameters::<PallasParameters, VestaParameters>::new(
+            generators_length << 1,
+            generators_length,
+        );
+        let pour = proof.pour::<256>().unwrap();
+        assert_eq!(pour.parameters_fingerprint, sr_params.fingerprint());
+        assert!(matches!(
+            pour.verification_gadget(
+                b"select_and_rerandomize",
+                &other_params,
+                &layout,
+                &curve_tree
+            ),
+            Err(Error::ParameterFingerprintMismatch)
+        ));
+
+        // A public key which is not a point.
+        let mut pk_bytes = Vec::new();
+        proof
+            .pour::<256>()
+            .unwrap()
+            .pk0
+            .serialize_compressed(&mut pk_bytes)
+            .unwrap();
+        let mut malformed = proof.clone();
+        let offset = malformed
+            .pour_bytes
+            .windows(pk_bytes.len())
+            .position(|window| window == pk_bytes.as_slice())
+            .unwrap();
+        malformed.pour_bytes[offset..offset + pk_bytes.len()].fill(0xff);
+        assert!(matches!(
+            malformed.precheck::<256>(&tags, &limits),
+            Err(Error::Serialization(_))
+        ));
+
+        // Trailing bytes, which the signatures cover but the pour does not.
+        let mut trailing = proof.clone();
+        trailing.pour_bytes.push(0);
+        assert!(matches!(
+            trailing.precheck::<256>(&tags, &limits),
+            Err(Error::Serialization(_))
+        ));
+
+        // Tags already spent on chain, which only the node knows.
+        tags.extend(proof.pour::<256>().unwrap().tags());
+        assert!(matches!(
+            proof.precheck::<256>(&tags, &limits),
+            Err(Error::SpentTag)
+        ));
+
+        let mut forged = proof.clone();
+        forged.signature_prover_response_0 += ark_pallas::Fr::from(1u64);
+        assert!(matches!(
+            forged.clone().verification_gadget::<256>(
                 b"select_and_rerandomize",
                 &sr_params,
+                &layout,
                 &curve_tree,
                 &schnorr_parameters,
-            );
+            ),
+            Err(Error::InvalidSignature)
+        ));
+
+        // The transactions of a block, verified together.
+        let block = [proof.clone(), proof.clone(), forged];
+        assert!(verify_transactions_batched(
+            &block[..2],
+            b"select_and_rerandomize",
+            &sr_params,
+            &layout,
+            &curve_tree,
+            &schnorr_parameters,
+        )
+        .is_ok());
+        assert!(matches!(
+            verify_transactions_batched(
+                &block,
+                b"select_and_rerandomize",
+                &sr_params,
+                &layout,
+                &curve_tree,
+                &schnorr_parameters,
+            ),
+            Err(Error::InvalidSignatureInBatch { index: 4 })
+        ));
+
+        {
+            let (pallas_vt, vesta_vt) = proof
+                .verification_gadget(
+                    b"select_and_rerandomize",
+                    &sr_params,
+                    &layout,
+                    &curve_tree,
+                    &schnorr_parameters,
+                )
+                .unwrap();
 
             batch_verify(
                 vec![pallas_vt],
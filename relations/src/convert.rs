@@ -0,0 +1,239 @@
+use crate::error::Error;
+
+use ark_ec::short_weierstrass::SWCurveConfig;
+use ark_ff::{BigInteger, Field, PrimeField};
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use sha3::Shake256;
+use std::cmp::Ordering;
+
+// Bytes are little-endian throughout the crate, as arkworks serializes field elements
+// and as the length prefixes and public amounts are encoded: the first byte is the least significant.
+
+/// The field element of a `u64`, e.g. a value, an amount or a counter committed to in a relation.
+pub fn fe_from_u64<F: Field>(value: u64) -> F {
+    F::from(value)
+}
+
+/// The `u64` of a field element, e.g. a committed value read back from a witness or an opening.
+/// Fails with `Error::ValueOutOfRange` if the element is not below 2^64, rather than truncating it.
+pub fn u64_from_fe<F: PrimeField>(fe: &F) -> Result<u64, Error> {
+    let bigint = fe.into_bigint();
+    let limbs = bigint.as_ref();
+    if limbs[1..].iter().any(|limb| *limb != 0) {
+        return Err(Error::value_out_of_range(format!(
+            "{} does not fit in 64 bits",
+            bigint
+        )));
+    }
+    Ok(limbs[0])
+}
+
+/// The field element of the little-endian integer `bytes`, reduced modulo the order of the field.
+/// Hashes and challenges are mapped to the field with it, from at least as many bytes as the modulus
+/// for their distribution to be close to uniform.
+pub fn fe_from_le_bytes_reduced<F: PrimeField>(bytes: &[u8]) -> F {
+    F::from_le_bytes_mod_order(bytes)
+}
+
+/// The number of bytes `hash_to_field` reduces into `F`: 128 bits more than the modulus, rounded up to whole bytes.
+pub fn hash_to_field_length<F: PrimeField>() -> usize {
+    (F::MODULUS_BIT_SIZE as usize + 128).div_ceil(8)
+}
+
+/// Hashes `bytes` in `domain` into the field `F`: SHAKE-256 of the length-prefixed domain followed by the bytes,
+/// squeezed to `hash_to_field_length::<F>()` bytes and reduced, whose distance from uniform is below 2^-128
+/// whatever the size of the field, of 64 bits or of 384.
+pub fn hash_to_field<F: PrimeField>(domain: &[u8], bytes: &[u8]) -> F {
+    let mut shake = Shake256::default();
+    shake.update((domain.len() as u64).to_le_bytes());
+    shake.update(domain);
+    shake.update(bytes);
+    let mut wide = vec![0u8; hash_to_field_length::<F>()];
+    shake.finalize_xof().read(&mut wide);
+    fe_from_le_bytes_reduced(&wide)
+}
+
+/// The element of the field `D` of the same integer as `fe`, e.g. an x-coordinate of one curve of a cycle
+/// committed to as a scalar of the other. Fails with `Error::ValueOutOfRange` if the integer is not below
+/// the modulus of `D`, rather than reducing it: a reduced coordinate would open to another point.
+pub fn fe_from_fe_checked<S: PrimeField, D: PrimeField>(fe: &S) -> Result<D, Error> {
+    let bytes = fe.into_bigint().to_bytes_le();
+    if !le_bytes_below(&bytes, &D::MODULUS.to_bytes_le()) {
+        return Err(Error::value_out_of_range(format!(
+            "{} is not below the modulus of the destination field",
+            fe.into_bigint()
+        )));
+    }
+    Ok(D::from_le_bytes_mod_order(&bytes))
+}
+
+/// An x-coordinate of the even curve of a cycle as a scalar of the odd curve, see `fe_from_fe_checked`.
+/// The relations of the crate require `P0::BaseField = P1::ScalarField`, for which it never fails.
+pub fn even_base_to_odd_scalar<P0: SWCurveConfig, P1: SWCurveConfig>(
+    x: &P0::BaseField,
+) -> Result<P1::ScalarField, Error>
+where
+    P0::BaseField: PrimeField,
+{
+    fe_from_fe_checked(x)
+}
+
+/// An x-coordinate of the odd curve of a cycle as a scalar of the even curve, see `fe_from_fe_checked`.
+pub fn odd_base_to_even_scalar<P0: SWCurveConfig, P1: SWCurveConfig>(
+    x: &P1::BaseField,
+) -> Result<P0::ScalarField, Error>
+where
+    P1::BaseField: PrimeField,
+{
+    fe_from_fe_checked(x)
+}
+
+// Whether the little-endian integer `value` is below `bound`, of any lengths.
+fn le_bytes_below(value: &[u8], bound: &[u8]) -> bool {
+    let byte = |bytes: &[u8], i: usize| bytes.get(i).copied().unwrap_or(0);
+    for i in (0..value.len().max(bound.len())).rev() {
+        match byte(value, i).cmp(&byte(bound, i)) {
+            Ordering::Less => return true,
+            Ordering::Greater => return false,
+            Ordering::Equal => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::One;
+    use ark_pallas::Fr as PallasScalar;
+
+    #[test]
+    fn test_u64_round_trip_straddling_2_64() {
+        for value in [0, 1, u64::MAX - 1, u64::MAX] {
+            let fe: PallasScalar = fe_from_u64(value);
+            assert_eq!(u64_from_fe(&fe).unwrap(), value);
+        }
+        // 2^64, 2^64 + 1 and the field's -1 have higher limbs set.
+        let two_64 = fe_from_u64::<PallasScalar>(u64::MAX) + PallasScalar::one();
+        for fe in [two_64, two_64 + PallasScalar::one(), -PallasScalar::one()] {
+            assert!(matches!(
+                u64_from_fe(&fe),
+                Err(Error::ValueOutOfRange { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_le_bytes_reduced_near_modulus() {
+        let modulus = PallasScalar::MODULUS.to_bytes_le();
+        assert_eq!(
+            fe_from_le_bytes_reduced::<PallasScalar>(&modulus),
+            PallasScalar::from(0u64)
+        );
+
+        // The modulus minus and plus one, the bytes are little-endian.
+        let mut below = modulus.clone();
+        below[0] -= 1;
+        assert_eq!(
+            fe_from_le_bytes_reduced::<PallasScalar>(&below),
+            -PallasScalar::one()
+        );
+        let mut above = modulus.clone();
+        above[0] += 1;
+        assert_eq!(
+            fe_from_le_bytes_reduced::<PallasScalar>(&above),
+            PallasScalar::one()
+        );
+
+        // Longer than the modulus, as a wide hash: 2^256 + 5.
+        let mut wide = [0u8; 64];
+        wide[0] = 5;
+        wide[32] = 1;
+        assert_eq!(
+            fe_from_le_bytes_reduced::<PallasScalar>(&wide),
+            PallasScalar::from(2u64).pow([256]) + PallasScalar::from(5u64)
+        );
+        // Little-endian, not big-endian.
+        assert_eq!(
+            fe_from_le_bytes_reduced::<PallasScalar>(&[1, 0]),
+            fe_from_u64(1)
+        );
+    }
+
+    #[test]
+    fn test_cycle_crossings_near_moduli() {
+        type PallasBase = ark_pallas::Fq;
+        type VestaScalar = ark_vesta::Fr;
+        type SecpBase = ark_secp256k1::Fq;
+        type SecpScalar = ark_secp256k1::Fr;
+
+        // On the exact cycles the crossings are the identity, up to the largest coordinate.
+        for x in [PallasBase::from(0u64), -PallasBase::one()] {
+            let scalar: VestaScalar =
+                even_base_to_odd_scalar::<ark_pallas::PallasConfig, ark_vesta::VestaConfig>(&x)
+                    .unwrap();
+            assert_eq!(scalar, x);
+            assert_eq!(
+                odd_base_to_even_scalar::<ark_vesta::VestaConfig, ark_pallas::PallasConfig>(&x)
+                    .unwrap(),
+                x
+            );
+        }
+
+        // Between the fields of one curve, whose moduli differ: the base field of secp256k1 is the larger.
+        let below = fe_from_le_bytes_reduced::<SecpBase>(&SecpScalar::MODULUS.to_bytes_le())
+            - SecpBase::one();
+        assert_eq!(
+            fe_from_fe_checked::<_, SecpScalar>(&below).unwrap(),
+            -SecpScalar::one()
+        );
+        for above in [
+            below + SecpBase::one(),
+            below + SecpBase::from(2u64),
+            -SecpBase::one(),
+        ] {
+            assert!(matches!(
+                fe_from_fe_checked::<_, SecpScalar>(&above),
+                Err(Error::ValueOutOfRange { .. })
+            ));
+        }
+        // The other way, every scalar is below the modulus of the base field.
+        assert_eq!(
+            fe_from_fe_checked::<_, SecpBase>(&-SecpScalar::one()).unwrap(),
+            below
+        );
+    }
+
+    use crate::test_fields::{Bls381Base, Goldilocks};
+
+    #[test]
+    fn test_hash_to_field_known_answers() {
+        assert_eq!(hash_to_field_length::<Goldilocks>(), 24);
+        assert_eq!(hash_to_field_length::<PallasScalar>(), 48);
+        assert_eq!(hash_to_field_length::<Bls381Base>(), 64);
+
+        // Pinned: changing them changes the public key scalars of coins and the leaves of data.
+        assert_eq!(
+            hash_to_field::<Goldilocks>(b"test", b"abc").to_string(),
+            "5922295309783628419"
+        );
+        assert_eq!(
+            hash_to_field::<PallasScalar>(b"test", b"abc").to_string(),
+            "21045134083238636195132773351642538831845564902802179221081954455984922135621"
+        );
+        assert_eq!(
+            hash_to_field::<Bls381Base>(b"test", b"abc").to_string(),
+            "3857980145773030733979540007110821516775305705044088999074203773269451451944778366800604328448454575630803421203762"
+        );
+
+        // The domain is length-prefixed, apart from the bytes.
+        assert_ne!(
+            hash_to_field::<PallasScalar>(b"ab", b"c"),
+            hash_to_field::<PallasScalar>(b"a", b"bc")
+        );
+        assert_ne!(
+            hash_to_field::<PallasScalar>(b"test", b"abc"),
+            hash_to_field::<PallasScalar>(b"other", b"abc")
+        );
+    }
+}
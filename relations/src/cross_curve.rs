@@ -0,0 +1,243 @@
+use bulletproofs::r1cs::*;
+
+use crate::curve::{curve_check, PointRepresentation};
+use crate::error::Error;
+use crate::nonzero::enforce_nonzero;
+use crate::protocol;
+use crate::rerandomize::re_randomize;
+use crate::single_level_select_and_rerandomize::SingleLayerParameters;
+
+#[cfg(feature = "prover")]
+use ark_ec::CurveGroup;
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine, AffineRepr};
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use merlin::Transcript;
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+
+/// Enforces that `x` is the affine x-coordinate of a point of `C2`, the other curve of the cycle,
+/// of which `rerandomized` is a rerandomization: `rerandomized = (x, y) + r * B_blinding`,
+/// with the blinding generator of `parameters` and the point's y-coordinate allocated as a witness.
+///
+/// The constraints are over the base field of `C2`, i.e. in the proof of the curve whose scalar field it is,
+/// where `x` is typically a committed variable or a slot of a vector commitment, e.g. of a key in a registry of keys.
+/// `x` is constrained not to be zero: the identity has no affine coordinates, it is represented by zeros,
+/// and an unset slot of a vector commitment is zero, which is so never linked to a point.
+///
+/// `witness` is the point and its rerandomization for the prover, and `None` for the verifier.
+pub fn committed_x_gadget<
+    F: PrimeField,
+    S: PrimeField,
+    C2: SWCurveConfig<BaseField = F, ScalarField = S> + Copy,
+    Cs: ConstraintSystem<F>,
+>(
+    cs: &mut Cs,
+    parameters: &SingleLayerParameters<C2>,
+    x: LinearCombination<F>,
+    rerandomized: &Affine<C2>,
+    witness: Option<(Affine<C2>, S)>,
+) -> Result<(), R1CSError> {
+    let mut bytes = Vec::new();
+    rerandomized
+        .serialize_compressed(&mut bytes)
+        .expect("serializing to a vector does not fail");
+    cs.transcript()
+        .append_message(protocol::COMMITTED_X_POINT, &bytes);
+
+    let point = witness.map(|(point, _)| point);
+    enforce_nonzero(cs, x.clone(), point.map(|point| point.x))?;
+    let y = cs.allocate(point.map(|point| point.y))?;
+    curve_check(cs, x.clone(), y.into(), C2::COEFF_A, C2::COEFF_B);
+    let rerandomized_x = cs.public_constant(rerandomized.x);
+    let rerandomized_y = cs.public_constant(rerandomized.y);
+    re_randomize(
+        cs,
+        &parameters.tables,
+        PointRepresentation {
+            x,
+            y: y.into(),
+            witness: point,
+        },
+        rerandomized_x,
+        rerandomized_y,
+        witness.map(|(_, rerandomization)| rerandomization),
+    );
+    Ok(())
+}
+
+/// Proves that `x`, a variable of `prover`, is the affine x-coordinate of `point`, a point of the other curve,
+/// without revealing it: the point is rerandomized with a fresh scalar. Verified by `verify_committed_x`.
+/// Returns the rerandomized point, which the verifier needs, and the rerandomization.
+///
+/// Fails with `Error::IdentityPoint` for the identity and with `Error::InconsistentWitness` for a point of x-coordinate
+/// zero, which `committed_x_gadget` rejects.
+/// Whether `x` is the x-coordinate of `point` is not checked, the proof does not verify if it is not.
+#[cfg(feature = "prover")]
+pub fn prove_committed_x<
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0>,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+    R: RngCore + CryptoRng,
+>(
+    prover: &mut Prover<Transcript, Affine<P0>>,
+    parameters: &SingleLayerParameters<P1>,
+    x: LinearCombination<F0>,
+    point: &Affine<P1>,
+    rng: &mut R,
+) -> Result<(Affine<P1>, F1), Error> {
+    // The point is secret, but which of these exceptional points it is not leaks nothing about it.
+    if point.is_zero() {
+        return Err(Error::IdentityPoint);
+    }
+    if point.x.is_zero() {
+        return Err(Error::inconsistent_witness(
+            "The point has the x-coordinate zero, which is not linked",
+        ));
+    }
+    let rerandomization = F1::rand(rng);
+    let rerandomized =
+        (parameters.pc_gens.commit_blinding_only(rerandomization) + point).into_affine();
+    if rerandomized == Affine::<P1>::zero() {
+        // Only happens with negligible probability.
+        return Err(Error::IdentityPoint);
+    }
+    committed_x_gadget(
+        prover,
+        parameters,
+        x,
+        &rerandomized,
+        Some((*point, rerandomization)),
+    )?;
+    Ok((rerandomized, rerandomization))
+}
+
+/// Verifies that `x`, a variable of `verifier`, is the affine x-coordinate of the point of which `rerandomized`,
+/// returned by `prove_committed_x`, is a rerandomization, once the proof of `verifier` verifies.
+/// Rejects the identity as `rerandomized` with `Error::IdentityPoint`, it has no affine coordinates.
+pub fn verify_committed_x<
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0>,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+>(
+    verifier: &mut Verifier<Transcript, Affine<P0>>,
+    parameters: &SingleLayerParameters<P1>,
+    x: LinearCombination<F0>,
+    rerandomized: &Affine<P1>,
+) -> Result<(), Error> {
+    // The rerandomized point is public.
+    if rerandomized.is_zero() {
+        return Err(Error::IdentityPoint);
+    }
+    committed_x_gadget(verifier, parameters, x, rerandomized, None)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve_tree::SelRerandParameters;
+    use ark_ff::{Field, Zero};
+    use ark_std::UniformRand;
+
+    type PallasParameters = ark_pallas::PallasConfig;
+    type VestaParameters = ark_vesta::VestaConfig;
+    type PallasA = ark_pallas::Affine;
+    type PallasScalar = ark_pallas::Fr;
+    type VestaA = ark_vesta::Affine;
+
+    const LABEL: &[u8] = b"committed_x";
+
+    // Commits to `committed` and another value as a registry entry, and proves that the first slot is the x-coordinate
+    // of `point`, then verifies.
+    fn prove_and_verify(
+        parameters: &SelRerandParameters<PallasParameters, VestaParameters>,
+        committed: PallasScalar,
+        point: &VestaA,
+        tamper: impl Fn(VestaA) -> VestaA,
+    ) -> Result<(), Error> {
+        let mut rng = rand::thread_rng();
+        let even = &parameters.even_parameters;
+        let odd = &parameters.odd_parameters;
+        let mut prover: Prover<_, PallasA> = Prover::new(&even.pc_gens, Transcript::new(LABEL));
+        let (entry, variables) = prover.commit_vec(
+            &[committed, PallasScalar::from(7u64)],
+            PallasScalar::rand(&mut rng),
+            &even.bp_gens,
+        );
+        let (rerandomized, _) =
+            prove_committed_x(&mut prover, odd, variables[0].into(), point, &mut rng)?;
+        let proof = prover.prove(&even.bp_gens)?;
+
+        let mut verifier = Verifier::<_, PallasA>::new(Transcript::new(LABEL));
+        let variables = verifier.commit_vec(2, entry);
+        verify_committed_x(
+            &mut verifier,
+            odd,
+            variables[0].into(),
+            &tamper(rerandomized),
+        )?;
+        verifier.verify(&proof, &even.pc_gens, &even.bp_gens)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_committed_x() {
+        let mut rng = rand::thread_rng();
+        let parameters =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 11, 1 << 11);
+        let point = VestaA::rand(&mut rng);
+        prove_and_verify(&parameters, point.x, &point, |p| p).unwrap();
+
+        // The x-coordinate of another point.
+        let other = VestaA::rand(&mut rng);
+        assert!(matches!(
+            prove_and_verify(&parameters, other.x, &point, |p| p),
+            Err(Error::R1CS(R1CSError::VerificationError))
+        ));
+        // Another rerandomized point.
+        assert!(matches!(
+            prove_and_verify(&parameters, point.x, &point, |p| (p + VestaA::generator())
+                .into_affine()),
+            Err(Error::R1CS(R1CSError::VerificationError))
+        ));
+
+        // The identity, as the point and as the rerandomized point.
+        assert!(matches!(
+            prove_and_verify(&parameters, PallasScalar::zero(), &VestaA::zero(), |p| p),
+            Err(Error::IdentityPoint)
+        ));
+        assert!(matches!(
+            prove_and_verify(&parameters, point.x, &point, |_| VestaA::zero()),
+            Err(Error::IdentityPoint)
+        ));
+    }
+
+    #[test]
+    fn test_committed_x_zero() {
+        let mut rng = rand::thread_rng();
+        let parameters =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 11, 1 << 11);
+        // Vesta has no point of x-coordinate zero, its constant 5 is not a square: zero is only the identity's.
+        assert!(VestaParameters::COEFF_B.sqrt().is_none());
+
+        // A prover claiming a zero slot with the witness of a point does not convince the verifier.
+        let point = VestaA::rand(&mut rng);
+        let even = &parameters.even_parameters;
+        let odd = &parameters.odd_parameters;
+        let r = ark_vesta::Fr::rand(&mut rng);
+        let rerandomized = (odd.pc_gens.B_blinding * r + point).into_affine();
+        let mut prover: Prover<_, PallasA> = Prover::new(&even.pc_gens, Transcript::new(LABEL));
+        let (commitment, x) = prover.commit(PallasScalar::zero(), PallasScalar::rand(&mut rng));
+        committed_x_gadget(&mut prover, odd, x.into(), &rerandomized, Some((point, r))).unwrap();
+        let proof = prover.prove(&even.bp_gens).unwrap();
+        let mut verifier = Verifier::<_, PallasA>::new(Transcript::new(LABEL));
+        let x = verifier.commit(commitment);
+        verify_committed_x(&mut verifier, odd, x.into(), &rerandomized).unwrap();
+        assert!(verifier
+            .verify(&proof, &even.pc_gens, &even.bp_gens)
+            .is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use subtle::{Choice, ConstantTimeEq};
+
+/// Compares the uncompressed canonical serializations of `a` and `b` without branching on their bytes,
+/// for values derived from secrets such as coin openings, tags before they are spent and commitments recomputed from openings.
+///
+/// Only the bytes are compared in constant time: the serializations have the same length for field elements
+/// and points of the same curve, but converting a field element out of Montgomery form is only as constant time
+/// as `ark_ff` makes it.
+///
+/// Comparisons of public values remain `==`, which returns at the first differing limb:
+/// the tags of a pour, which the verifier reads, the roots, paths and proofs, and the minted coin commitments of a pour.
+pub fn ct_eq<T: CanonicalSerialize>(a: &T, b: &T) -> Choice {
+    let mut a_bytes = Vec::with_capacity(a.uncompressed_size());
+    a.serialize_uncompressed(&mut a_bytes)
+        .expect("serializing to a vector does not fail");
+    let mut b_bytes = Vec::with_capacity(b.uncompressed_size());
+    b.serialize_uncompressed(&mut b_bytes)
+        .expect("serializing to a vector does not fail");
+    a_bytes.as_slice().ct_eq(b_bytes.as_slice())
+}
+
+/// `ct_eq` for field elements.
+pub fn field_ct_eq<F: PrimeField>(a: &F, b: &F) -> Choice {
+    ct_eq(a, b)
+}
+
+/// `ct_eq` for affine points, including the point at infinity.
+pub fn point_ct_eq<P: SWCurveConfig>(a: &Affine<P>, b: &Affine<P>) -> Choice {
+    ct_eq(a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ec::AffineRepr;
+    use ark_std::{UniformRand, Zero};
+
+    type PallasA = ark_pallas::Affine;
+    type PallasScalar = ark_pallas::Fr;
+
+    #[test]
+    fn test_ct_eq_agrees_with_eq() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let a = PallasScalar::rand(&mut rng);
+            let b = PallasScalar::rand(&mut rng);
+            assert_eq!(bool::from(field_ct_eq(&a, &b)), a == b);
+            assert!(bool::from(field_ct_eq(&a, &a)));
+            assert!(!bool::from(field_ct_eq(
+                &a,
+                &(a + PallasScalar::from(1u64))
+            )));
+
+            let p = PallasA::rand(&mut rng);
+            let q = PallasA::rand(&mut rng);
+            assert_eq!(bool::from(point_ct_eq(&p, &q)), p == q);
+            assert!(bool::from(point_ct_eq(&p, &p)));
+            // Same x-coordinate, opposite y-coordinate.
+            assert!(!bool::from(point_ct_eq(&p, &-p)));
+        }
+        let zero = PallasA::zero();
+        assert!(bool::from(point_ct_eq(&zero, &zero)));
+        assert!(!bool::from(point_ct_eq(&zero, &PallasA::generator())));
+        assert!(bool::from(field_ct_eq(
+            &PallasScalar::zero(),
+            &PallasScalar::zero()
+        )));
+    }
+}
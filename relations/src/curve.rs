@@ -1,6 +1,8 @@
 use ark_ec::{AffineRepr, CurveGroup};
-use ark_ff::Field;
+use ark_ff::{Field, PrimeField};
 use bulletproofs::r1cs::*;
+
+use crate::inversion::ct_inverse;
 use std::marker::PhantomData;
 
 pub fn curve_check<F: Field, Cs: ConstraintSystem<F>>(
@@ -35,7 +37,7 @@ pub struct PointRepresentation<F: Field, C: AffineRepr<BaseField = F>> {
 /// Given variables representing the coordinates of two points returns two variables representing the sum.
 /// The prover must additionally provide the two summands as input and gets the sum as output.
 pub fn incomplete_curve_addition_helper<
-    F: Field,
+    F: PrimeField,
     Cs: ConstraintSystem<F>,
     P: AffineRepr<BaseField = F>,
 >(
@@ -47,7 +49,7 @@ pub fn incomplete_curve_addition_helper<
         (Some(left), Some(right)) => {
             let out = (left + right).into_affine();
             let delta = (*right.y().unwrap() - *left.y().unwrap())
-                / (*right.x().unwrap() - *left.x().unwrap());
+                * ct_inverse(&(*right.x().unwrap() - *left.x().unwrap())).unwrap();
             (Some(out), Some(delta))
         }
         _ => (None, None),
@@ -77,7 +79,7 @@ pub fn incomplete_curve_addition_helper<
 /// and checks that the result is not point at inf.
 /// The prover must additionally provide the two summands as input and gets the sum as output.
 pub fn checked_curve_addition_helper<
-    F: Field,
+    F: PrimeField,
     Cs: ConstraintSystem<F>,
     P: AffineRepr<BaseField = F>,
 >(
@@ -88,10 +90,10 @@ pub fn checked_curve_addition_helper<
     let (out_witness, delta, x_l_minus_x_r_inv) = match (left.witness, right.witness) {
         (Some(left), Some(right)) => {
             let out = (left + right).into_affine();
-            let delta = (*right.y().unwrap() - left.y().unwrap())
-                / (*right.x().unwrap() - left.x().unwrap());
             assert_ne!(left.x().unwrap(), right.x().unwrap());
-            let x_l_minus_x_r_inv = F::one() / (*left.x().unwrap() - right.x().unwrap());
+            let delta = (*right.y().unwrap() - left.y().unwrap())
+                * ct_inverse(&(*right.x().unwrap() - left.x().unwrap())).unwrap();
+            let x_l_minus_x_r_inv = ct_inverse(&(*left.x().unwrap() - right.x().unwrap())).unwrap();
             (Some(out), Some(delta), Some(x_l_minus_x_r_inv))
         }
         _ => (None, None, None),
@@ -137,13 +139,11 @@ pub fn incomplete_curve_addition<F: Field, Cs: ConstraintSystem<F>>(
 ) {
     let delta_lc: LinearCombination<F> = cs.allocate(prms.delta).unwrap().into();
     // delta * (x_r - x_l) = y_r - y_l
-    let (_, _, delta_x_r_x_l) = cs.multiply(delta_lc.clone(), prms.x_r.clone() - prms.x_l.clone());
-    cs.constrain(
-        LinearCombination::<F>::from(delta_x_r_x_l) - (prms.y_r.clone() - prms.y_l.clone()),
-    );
+    let (_, _, delta_x_r_x_l) = cs.multiply(delta_lc.clone(), &prms.x_r - &prms.x_l);
+    cs.constrain(LinearCombination::<F>::from(delta_x_r_x_l) - (&prms.y_r - &prms.y_l));
 
     // delta * (x_o - x_l) = - y_o - y_l
-    let (_, _, delta_x_o_x_l) = cs.multiply(delta_lc.clone(), prms.x_o.clone() - prms.x_l.clone());
+    let (_, _, delta_x_o_x_l) = cs.multiply(delta_lc.clone(), &prms.x_o - &prms.x_l);
     cs.constrain(
         LinearCombination::<F>::from(delta_x_o_x_l) - (-prms.y_o.clone() - prms.y_l.clone()),
     );
@@ -163,11 +163,7 @@ pub fn checked_curve_addition<F: Field, Cs: ConstraintSystem<F>>(
     x_l_minus_x_r_inv: Option<F>,
 ) {
     let x_l_minus_x_r_inv_lc: LinearCombination<F> = cs.allocate(x_l_minus_x_r_inv).unwrap().into();
-    not_zero(
-        cs,
-        prms.x_l.clone() - prms.x_r.clone(),
-        x_l_minus_x_r_inv_lc,
-    );
+    not_zero(cs, &prms.x_l - &prms.x_r, x_l_minus_x_r_inv_lc);
     incomplete_curve_addition(cs, prms);
     // todo check on curve?
 }
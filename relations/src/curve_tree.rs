@@ -1,16 +1,49 @@
 use bulletproofs::r1cs::*;
 
+use crate::bloom::LeafBloom;
+use crate::convert::u64_from_fe;
+use crate::cycle::{EvenPoint, EvenScalar, OddPoint, OddScalar};
+use crate::error::Error;
+use crate::events::{Event, SharedEventSink};
+use crate::limits::{
+    deserialize_vec, deserialize_with_default_limits, DeserializeLimits, DeserializeWithLimits,
+    MAX_SUPPORTED_DEPTH,
+};
+#[cfg(feature = "prover")]
+use crate::lookup::ct_indicator;
+use crate::metrics::{self, Metrics};
+use crate::policy::ValuePolicy;
+use crate::protocol;
 use crate::single_level_select_and_rerandomize::*;
+use crate::size::SizeReport;
+use crate::version::FormatVersion;
 
-use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine, CurveGroup};
-use ark_ff::PrimeField;
+use ark_ec::{
+    models::short_weierstrass::SWCurveConfig,
+    short_weierstrass::{Affine, SWFlags},
+};
+use ark_ff::{Field, PrimeField};
 use ark_serialize::{
-    CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Valid, Write,
+    CanonicalDeserialize, CanonicalDeserializeWithFlags, CanonicalSerialize, Compress, Read,
+    SerializationError, Valid, Validate, Write,
 };
 use ark_std::Zero;
 use merlin::Transcript;
-use rand::Rng;
-use std::{borrow::BorrowMut, ops::Mul};
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+use sha3::{Digest, Sha3_256};
+#[cfg(feature = "prover")]
+use std::sync::Mutex;
+use std::{
+    borrow::BorrowMut,
+    collections::{BTreeSet, HashMap, VecDeque},
+    iter,
+    ops::Range,
+    sync::Arc,
+};
+
+/// The number of previous roots a tree keeps the number of leaves of, unless set by `CurveTree::set_root_history_window`.
+pub const DEFAULT_ROOT_HISTORY_WINDOW: usize = 1 << 10;
 
 pub enum CurveTree<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
     Even(CurveTreeNode<L, P0, P1>),
@@ -25,101 +58,176 @@ impl<
         P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
     > CurveTree<L, P0, P1>
 {
-    /// Build a curve tree from a set of commitments assumed to be permissible
+    /// Like `from_set`, keeping the commitments of the nodes as `storage` says.
+    /// The tree has the same root and the same witnesses with either storage.
+    pub fn from_set_with_storage(
+        set: &[Affine<P0>],
+        parameters: &SelRerandParameters<P0, P1>,
+        height: Option<usize>,
+        storage: PointStorage,
+    ) -> Result<Self, Error> {
+        match storage {
+            PointStorage::Affine => Self::from_set(set, parameters, height),
+            PointStorage::Compressed => {
+                Self::from_set_compressed(set, parameters)?.increase_height(height, parameters)
+            }
+        }
+    }
+
+    // Builds the levels below the root as compressed levels, combining them as `from_set` combines forests.
+    fn from_set_compressed(
+        set: &[Affine<P0>],
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<Self, Error> {
+        if set.is_empty() {
+            return Err(Error::parameter_mismatch(
+                "The curve tree must have at least one leaf",
+            ));
+        }
+        let leaf_index = leaf_index(set);
+        let mut even_level = CompressedLevel::<L, P0, P1>::leaves(set);
+        while even_level.len() > 1 {
+            let odd_level =
+                CompressedLevel::<L, P1, P0>::combine(even_level, &parameters.odd_parameters)?;
+            if odd_level.len() == 1 {
+                return Ok(Self::Odd(odd_level.into_root(set.len())).with_leaf_index(leaf_index));
+            }
+            even_level =
+                CompressedLevel::<L, P0, P1>::combine(odd_level, &parameters.even_parameters)?;
+        }
+        Ok(Self::Even(even_level.into_root(set.len())).with_leaf_index(leaf_index))
+    }
+
+    /// Build a curve tree from a set of commitments assumed to be permissible.
+    /// A commitment repeated in the set is a leaf at each of its positions, see `leaf_indices_of`.
+    ///
+    /// The construction is deterministic: the inner nodes commit to the x-coordinates of their children
+    /// without blinding, and the search of a permissible commitment starts from zero, so nodes building
+    /// from the same leaves in the same order, with parameters of the same fingerprint, get the same root,
+    /// whatever their storage or whether the leaves were inserted at once or over time.
+    /// The inner nodes are not secret, anyone can recompute them from the leaves: what hides the path
+    /// of a membership proof is the rerandomization chosen by the prover.
     pub fn from_set(
         set: &[Affine<P0>],
         parameters: &SelRerandParameters<P0, P1>,
         height: Option<usize>, // resulting curve tree will have height at least `height`
-    ) -> Self {
+    ) -> Result<Self, Error> {
         if set.is_empty() {
-            panic!("The curve tree must have at least one leaf.")
+            return Err(Error::parameter_mismatch(
+                "The curve tree must have at least one leaf",
+            ));
         }
+        let leaf_index = leaf_index(set);
         // Convert each commitment to a leaf.
-        let mut forest_length = set.len();
-        let mut next_forest_length = (forest_length + L - 1) / L;
-        let mut even_forest = Vec::with_capacity(forest_length);
-        for leaf in set {
-            even_forest.push(CurveTreeNode::<L, P0, P1>::leaf(*leaf));
-        }
-        while forest_length > 1 {
+        let mut even_forest: Vec<_> = set
+            .iter()
+            .map(|leaf| CurveTreeNode::<L, P0, P1>::leaf(*leaf))
+            .collect();
+        while even_forest.len() > 1 {
             // Combine forest of trees with even roots, into a forest of trees with odd roots.
-            let mut odd_forest = Vec::with_capacity(next_forest_length);
-            for i in 0..next_forest_length {
-                let mut chunk = Vec::new();
-                for (j, tree) in even_forest.iter().enumerate().take((i + 1) * L).skip(i * L) {
-                    if j >= forest_length {
-                        break;
-                    }
-                    chunk.push(tree.clone());
-                }
-                odd_forest.push(CurveTreeNode::<L, P1, P0>::combine(
-                    chunk,
-                    &parameters.odd_parameters,
-                ));
-            }
-            forest_length = next_forest_length;
-            next_forest_length = (next_forest_length + L - 1) / L;
-
-            if forest_length == 1 {
-                return Self::Odd(odd_forest[0].clone()).increase_height(height, parameters);
+            let mut odd_forest = CurveTreeNode::<L, P1, P0>::combine_forest(
+                even_forest,
+                &parameters.odd_parameters,
+            )?;
+            if odd_forest.len() == 1 {
+                return Self::Odd(odd_forest.pop().unwrap())
+                    .with_leaf_index(leaf_index)
+                    .increase_height(height, parameters);
             }
 
             // Combine forest of trees with odd roots, into a forest of trees with even roots.
-            even_forest = Vec::with_capacity(next_forest_length);
-            for i in 0..next_forest_length {
-                let mut chunk = Vec::new();
-                for (j, tree) in odd_forest.iter().enumerate().take((i + 1) * L).skip(i * L) {
-                    if j >= forest_length {
-                        break;
-                    }
-                    chunk.push(tree.clone());
-                }
-                even_forest.push(CurveTreeNode::<L, P0, P1>::combine(
-                    chunk,
-                    &parameters.even_parameters,
-                ));
-            }
-            forest_length = next_forest_length;
-            next_forest_length = (next_forest_length + L - 1) / L;
+            even_forest = CurveTreeNode::<L, P0, P1>::combine_forest(
+                odd_forest,
+                &parameters.even_parameters,
+            )?;
         }
-        Self::Even(even_forest[0].clone()).increase_height(height, parameters)
+        Self::Even(even_forest.pop().unwrap())
+            .with_leaf_index(leaf_index)
+            .increase_height(height, parameters)
     }
 
+    /// Adds roots above the tree until it has height `height`.
+    /// Fails with `Error::LimitExceeded` for a height above `MAX_SUPPORTED_DEPTH`, or at which the tree
+    /// has more than `usize::MAX` positions, e.g. above 7 for branching factor 256, before adding any root.
     pub fn increase_height(
         self,
         height: Option<usize>,
         parameters: &SelRerandParameters<P0, P1>,
-    ) -> Self {
+    ) -> Result<Self, Error> {
         match height {
-            None => self,
+            None => Ok(self),
             Some(height) => {
+                DeserializeLimits::check(
+                    height as u64,
+                    MAX_SUPPORTED_DEPTH,
+                    "the height of the tree",
+                )?;
+                if L.checked_pow(height as u32).is_none() {
+                    return Err(Error::limit_exceeded(format!(
+                        "A tree of branching factor {} and height {} has more positions than a usize counts",
+                        L, height
+                    )));
+                }
                 let mut res = self;
+                // The index and the event sink move to the new root.
+                let leaf_index = res.take_leaf_index();
+                let event_sink = res.take_event_sink();
+                let root_history = res.take_root_history();
                 while res.height() < height {
                     match res {
                         Self::Even(ct) => {
                             res = Self::Odd(CurveTreeNode::<L, P1, P0>::combine(
                                 vec![ct],
                                 &parameters.odd_parameters,
-                            ));
+                            )?);
                         }
                         Self::Odd(ct) => {
                             res = Self::Even(CurveTreeNode::<L, P0, P1>::combine(
                                 vec![ct],
                                 &parameters.even_parameters,
-                            ));
+                            )?);
                         }
                     }
                 }
-                res
+                let res = match leaf_index {
+                    Some(leaf_index) => res.with_leaf_index(leaf_index),
+                    None => res,
+                };
+                Ok(res
+                    .with_event_sink(event_sink)
+                    .with_root_history(root_history))
             }
         }
     }
 
     /// Produce a witness of the path to the commitment at `index` including siblings and randomness.
+    ///
+    /// The selected child is read by touching every child at each level, and the rerandomization
+    /// windows are read by touching every table entry, so the prover's arithmetic does not depend on `index`.
+    /// Remaining leaks: descending into the selected child is an index-dependent memory access,
+    /// and the number of attempts in the permissible search depends on the committed values.
+    #[cfg(feature = "prover")]
     pub fn select_and_rerandomize_prover_witness(
         &self,
         index: usize,
-    ) -> CurveTreeWitnessPath<L, P0, P1> {
+    ) -> Result<CurveTreeWitnessPath<L, P0, P1>, Error> {
+        let leaves = self.leaf_count();
+        if index >= leaves {
+            return Err(Error::inconsistent_witness(format!(
+                "Leaf index {} out of range, the tree has {} leaves",
+                index, leaves
+            )));
+        }
+        if self.is_pruned(index) {
+            return Err(Error::PrunedLeaf { index });
+        }
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!(
+            "tree_traversal",
+            height = self.height(),
+            branching_factor = L
+        )
+        .entered();
         // todo capacity
         let mut even_nodes: Vec<CurveTreeWitness<L, P0, P1>> = Vec::new();
         let mut odd_nodes: Vec<CurveTreeWitness<L, P1, P0>> = Vec::new();
@@ -133,73 +241,241 @@ impl<
             }
         }
 
-        CurveTreeWitnessPath {
+        Ok(CurveTreeWitnessPath {
             even_nodes,
             odd_nodes,
-        }
+        })
     }
 
     /// Commits to the root and rerandomizations of the path to the leaf specified by `index`
     /// and proves the Select and rerandomize relation for each level.
     /// Returns the rerandomized commitments on the path to (and including) the selected leaf and the rerandomization scalar of the selected leaf.
-    pub fn select_and_rerandomize_prover_gadget<R: Rng>(
+    ///
+    /// Binds the two transcripts to the parameters, see `SelRerandParameters::bind`,
+    /// and to each other afterwards, see [`bind_transcripts`].
+    #[cfg(feature = "prover")]
+    pub fn select_and_rerandomize_prover_gadget<R: RngCore + CryptoRng>(
         &self,
         index: usize,
         even_prover: &mut Prover<Transcript, Affine<P0>>,
         odd_prover: &mut Prover<Transcript, Affine<P1>>,
         parameters: &SelRerandParameters<P0, P1>,
         rng: &mut R,
-    ) -> (SelectAndRerandomizePath<L, P0, P1>, P0::ScalarField) {
-        let witness = self.select_and_rerandomize_prover_witness(index);
-        witness.select_and_rerandomize_prover_gadget(even_prover, odd_prover, parameters, rng)
+    ) -> Result<(SelectAndRerandomizePath<L, P0, P1>, EvenScalar<P0>), Error> {
+        let witness = self.select_and_rerandomize_prover_witness(index)?;
+        parameters.bind(even_prover.transcript(), odd_prover.transcript());
+        let result = witness.select_and_rerandomize_prover_gadget(
+            even_prover,
+            odd_prover,
+            parameters,
+            rng,
+        )?;
+        bind_transcripts(even_prover.transcript(), odd_prover.transcript());
+        Ok(result)
+    }
+
+    /// Proves that the leaf at `index` is in the tree, revealing only a rerandomization of it.
+    ///
+    /// Returns the proof and the blinding of the rerandomized leaf, `leaf_blinding` plus the rerandomization,
+    /// for callers who prove further statements about the rerandomized leaf.
+    ///
+    /// ```
+    /// # use ark_pallas::{Fr, PallasConfig};
+    /// # use ark_std::UniformRand;
+    /// # use ark_vesta::VestaConfig;
+    /// # use relations::curve_tree::{CurveTree, SelRerandParameters};
+    /// # use relations::cycle::EvenScalar;
+    /// let mut rng = rand::thread_rng();
+    /// let parameters = SelRerandParameters::<PallasConfig, VestaConfig>::new(1 << 11, 1 << 11);
+    /// let value = [Fr::from(42u64)];
+    /// let (leaf, blinding) = parameters.even_parameters.permissible_commitment(&value, Fr::rand(&mut rng), 0)?;
+    /// let tree = CurveTree::<32, PallasConfig, VestaConfig>::from_set(&[leaf], &parameters, Some(2))?;
+    ///
+    /// let (proof, blinding) = tree.prove_membership(0, EvenScalar(blinding), &parameters, &mut rng)?;
+    /// let rerandomized_leaf = proof.verify(&tree, &parameters)?;
+    /// assert_eq!(rerandomized_leaf, parameters.even_parameters.commit(&value, blinding.0, 0)?);
+    /// # Ok::<(), relations::Error>(())
+    /// ```
+    #[cfg(feature = "prover")]
+    pub fn prove_membership<R: RngCore + CryptoRng>(
+        &self,
+        index: usize,
+        leaf_blinding: EvenScalar<P0>,
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<(MembershipProof<L, P0, P1>, EvenScalar<P0>), Error> {
+        let witness = self.select_and_rerandomize_prover_witness(index)?;
+        self.prove_witness_membership(&witness, leaf_blinding, parameters, rng)
+    }
+
+    /// Prepares proving the membership of the leaf at `index` again and again, e.g. by a wallet authenticating
+    /// with the same coin: the path to the leaf, its siblings and the permissible offsets of its ancestors are read
+    /// from the tree once, see `prove_membership_prepared`.
+    #[cfg(feature = "prover")]
+    pub fn prepare_membership(
+        &self,
+        index: usize,
+        leaf_blinding: EvenScalar<P0>,
+    ) -> Result<PreparedMembershipWitness<L, P0, P1>, Error> {
+        Ok(PreparedMembershipWitness {
+            witness: self.select_and_rerandomize_prover_witness(index)?,
+            leaf_blinding,
+            root: self.root(),
+        })
+    }
+
+    /// Like `prove_membership` for the leaf `prepared` was prepared for, with fresh rerandomizations:
+    /// the proofs are unlinkable, and only skip reading the path from the tree.
+    /// Fails with `Error::InconsistentWitness` if the root of the tree changed since, the path being stale.
+    #[cfg(feature = "prover")]
+    pub fn prove_membership_prepared<R: RngCore + CryptoRng>(
+        &self,
+        prepared: &PreparedMembershipWitness<L, P0, P1>,
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<(MembershipProof<L, P0, P1>, EvenScalar<P0>), Error> {
+        if !prepared.is_valid_for(self) {
+            return Err(Error::inconsistent_witness(
+                "The membership witness was prepared for another root",
+            ));
+        }
+        self.prove_witness_membership(&prepared.witness, prepared.leaf_blinding, parameters, rng)
+    }
+
+    #[cfg(feature = "prover")]
+    fn prove_witness_membership<R: RngCore + CryptoRng>(
+        &self,
+        witness: &CurveTreeWitnessPath<L, P0, P1>,
+        leaf_blinding: EvenScalar<P0>,
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<(MembershipProof<L, P0, P1>, EvenScalar<P0>), Error> {
+        let mut even_prover = Prover::new(
+            &parameters.even_parameters.pc_gens,
+            Transcript::new(protocol::MEMBERSHIP),
+        );
+        let mut odd_prover = Prover::new(
+            &parameters.odd_parameters.pc_gens,
+            Transcript::new(protocol::MEMBERSHIP),
+        );
+        let leaf_count = self.leaf_count() as u64;
+        bind_leaf_count(
+            even_prover.transcript(),
+            odd_prover.transcript(),
+            leaf_count,
+        );
+        parameters.bind(even_prover.transcript(), odd_prover.transcript());
+        let (path, rerandomization) = witness.select_and_rerandomize_prover_gadget(
+            &mut even_prover,
+            &mut odd_prover,
+            parameters,
+            rng,
+        )?;
+        bind_transcripts(even_prover.transcript(), odd_prover.transcript());
+        let (even_proof, odd_proof) = prove_pair(even_prover, odd_prover, parameters)?;
+
+        let proof = MembershipProof {
+            path,
+            even_proof,
+            odd_proof,
+            parameters_fingerprint: parameters.fingerprint(),
+            leaf_count,
+        };
+        Ok((proof, leaf_blinding + rerandomization))
+    }
+
+    /// Checks that `randomized_path` has one rerandomized commitment for each level below the root.
+    /// The verifier gadget rejects paths of the wrong shape with `Error::InvalidPath`,
+    /// this allows rejecting them before laying down any constraints.
+    pub fn is_valid_path(&self, randomized_path: &SelectAndRerandomizePath<L, P0, P1>) -> bool {
+        let (even_length, odd_length) = self.path_lengths();
+        randomized_path.even_commitments.len() == even_length
+            && randomized_path.odd_commitments.len() == odd_length
+    }
+
+    /// The numbers of even and odd rerandomized commitments of a path of this tree.
+    pub fn path_lengths(&self) -> (usize, usize) {
+        let height = self.height();
+        match self {
+            Self::Even(_) => (height / 2, height - height / 2),
+            Self::Odd(_) => (height - height / 2, height / 2),
+        }
     }
 
     pub fn select_and_rerandomize_verification_commitments(
         &self,
         mut randomized_path: SelectAndRerandomizePath<L, P0, P1>,
-    ) -> SelectAndRerandomizePath<L, P0, P1> {
+    ) -> Result<SelectAndRerandomizePath<L, P0, P1>, Error> {
+        // A shorter path with the right parity would otherwise select an inner node.
+        if !self.is_valid_path(&randomized_path) {
+            return Err(Error::InvalidPath);
+        }
         let (even_commitments, odd_commitments) = match self {
             // todo we are committing to public values in the first iteration.
             // could allocate variables for each entry instead of using the vector commitment machinery needed for the next levels
             Self::Odd(ct) => {
-                assert_eq!(
-                    randomized_path.even_commitments.len(),
-                    randomized_path.odd_commitments.len() + 1
-                );
                 let mut odd_commitments_with_root = vec![ct.parent_commitment];
                 odd_commitments_with_root.append(&mut randomized_path.odd_commitments);
                 (randomized_path.even_commitments, odd_commitments_with_root)
             }
             Self::Even(ct) => {
-                assert_eq!(
-                    randomized_path.even_commitments.len(),
-                    randomized_path.odd_commitments.len()
-                );
                 let mut even_commitments_with_root = vec![ct.parent_commitment];
                 even_commitments_with_root.append(&mut randomized_path.even_commitments);
                 (even_commitments_with_root, randomized_path.odd_commitments)
             }
         };
 
-        SelectAndRerandomizePath {
+        Ok(SelectAndRerandomizePath {
             even_commitments,
             odd_commitments,
-        }
+        })
     }
 
+    /// Verifier counterpart of `select_and_rerandomize_prover_gadget`, including the binding of the transcripts.
+    /// The path starts at the root of this tree, the prover's path does not include a root to trust.
     pub fn select_and_rerandomize_verifier_gadget<T: BorrowMut<Transcript>>(
         &self,
         even_verifier: &mut Verifier<T, Affine<P0>>,
         odd_verifier: &mut Verifier<T, Affine<P1>>,
         randomized_path: SelectAndRerandomizePath<L, P0, P1>,
         parameters: &SelRerandParameters<P0, P1>,
-    ) -> Affine<P0> {
-        let commitments = self.select_and_rerandomize_verification_commitments(randomized_path);
+    ) -> Result<Affine<P0>, Error> {
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!(
+            "select_and_rerandomize_verifier",
+            height = self.height(),
+            branching_factor = L
+        )
+        .entered();
+        let commitments = self.select_and_rerandomize_verification_commitments(randomized_path)?;
+
+        parameters.bind(even_verifier.transcript(), odd_verifier.transcript());
+        commitments.even_verifier_gadget(even_verifier, parameters, self)?;
+        commitments.odd_verifier_gadget(odd_verifier, parameters, self)?;
+        bind_transcripts(even_verifier.transcript(), odd_verifier.transcript());
+
+        Ok(commitments.get_rerandomized_leaf())
+    }
+
+    /// Like `select_and_rerandomize_verifier_gadget`, without laying down the constraints: commits the path and
+    /// supplies its public constants, in the order the gadget does, to verifiers of the statement of a proof checked
+    /// with `Verifier::verify_ref` against the constraints of verifiers the gadget was run on, with any path of this tree.
+    /// Returns the rerandomized leaf.
+    pub fn select_and_rerandomize_verifier_statement<T: BorrowMut<Transcript>>(
+        &self,
+        even_verifier: &mut Verifier<T, Affine<P0>>,
+        odd_verifier: &mut Verifier<T, Affine<P1>>,
+        randomized_path: SelectAndRerandomizePath<L, P0, P1>,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<Affine<P0>, Error> {
+        let commitments = self.select_and_rerandomize_verification_commitments(randomized_path)?;
 
-        commitments.even_verifier_gadget(even_verifier, parameters, self);
-        commitments.odd_verifier_gadget(odd_verifier, parameters, self);
+        parameters.bind(even_verifier.transcript(), odd_verifier.transcript());
+        commitments.even_verifier_statement(even_verifier, self)?;
+        commitments.odd_verifier_statement(odd_verifier, self)?;
+        bind_transcripts(even_verifier.transcript(), odd_verifier.transcript());
 
-        commitments.get_rerandomized_leaf()
+        Ok(commitments.get_rerandomized_leaf())
     }
 
     pub fn height(&self) -> usize {
@@ -209,222 +485,1707 @@ impl<
         }
     }
 
-    pub fn root_node(&self) -> Affine<P0> {
+    pub fn root_node(&self) -> Result<Affine<P0>, Error> {
         match self {
-            Self::Even(ct) => ct.parent_commitment,
-            Self::Odd(_ct) => {panic!("Odd roots not yet supported.")}
+            Self::Even(ct) => Ok(ct.parent_commitment),
+            Self::Odd(_ct) => Err(Error::parameter_mismatch("Odd roots are not yet supported")),
         }
     }
-    //todo add a function to add a single/several commitments
-}
 
-// todo don't include the root
-#[derive(Clone)]
-pub struct SelectAndRerandomizePath<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
-    pub even_commitments: Vec<Affine<P0>>,
-    pub odd_commitments: Vec<Affine<P1>>,
-}
+    /// The leaves of the tree but the pruned ones, from left to right, see `leaf` for the leaf at an index.
+    pub fn leaves(&self) -> Vec<Affine<P0>> {
+        let leaf_index = self.leaf_index();
+        self.stored_leaves()
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !leaf_index.pruned.contains(index))
+            .map(|(_, leaf)| leaf.expect("the leaves not pruned are kept"))
+            .collect()
+    }
 
-impl<
-        const L: usize,
-        F: PrimeField,
-        P0: SWCurveConfig<BaseField = F> + Copy + Send,
-        P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = F> + Copy + Send,
-    > SelectAndRerandomizePath<L, P0, P1>
-{
-    /// Get the public rerandomization of the selected commitment
-    pub fn get_rerandomized_leaf(&self) -> Affine<P0> {
-        *self.even_commitments.last().unwrap()
+    /// The leaf at `index`, none if it is out of range or was pruned, see `prune`.
+    pub fn leaf(&self, index: usize) -> Option<Affine<P0>> {
+        if index >= self.leaf_count() || self.is_pruned(index) {
+            return None;
+        }
+        let (mut even_leaf, mut odd_leaf) = (None, None);
+        match self {
+            Self::Even(ct) => ct.stored_leaf(index, &mut even_leaf, &mut odd_leaf),
+            Self::Odd(ct) => ct.stored_leaf(index, &mut odd_leaf, &mut even_leaf),
+        }
+        even_leaf
     }
 
-    pub fn even_verifier_gadget<T: BorrowMut<Transcript>>(
-        &self,
-        even_verifier: &mut Verifier<T, Affine<P0>>,
-        parameters: &SelRerandParameters<P0, P1>,
-        ct: &CurveTree<L, P0, P1>,
-    ) {
-        // Determine the parity of the root:
-        let root_is_odd = self.even_commitments.len() == self.odd_commitments.len();
-        if !root_is_odd {
-            assert!(self.even_commitments.len() == self.odd_commitments.len() + 1);
+    // The leaves of the tree by index, none for those `prune` dropped.
+    fn stored_leaves(&self) -> Vec<Option<Affine<P0>>> {
+        let mut even_leaves = Vec::with_capacity(self.leaf_count());
+        let mut odd_leaves = Vec::new();
+        match self {
+            Self::Even(ct) => ct.collect_leaves(&mut even_leaves, &mut odd_leaves),
+            Self::Odd(ct) => ct.collect_leaves(&mut odd_leaves, &mut even_leaves),
         }
+        even_leaves
+    }
 
-        // The last even commitment is skipped as it is the leaf and as such not a parent in the select and rerandomize relation.
-        for parent_index in 0..self.even_commitments.len() - 1 {
-            let odd_index = if root_is_odd {
-                parent_index + 1
-            } else {
-                parent_index
-            };
-            let variables = if parent_index == 0 && !root_is_odd {
-                let children = match &ct {
-                    CurveTree::Even(root) => {
-                        if let Some(children) = &root.children {
-                            x_coordinates(children)
-                        } else {
-                            panic!()
-                        }
-                    }
-                    _ => panic!(),
-                };
-                children.map(constant).to_vec()
-            } else {
-                let variables = even_verifier.commit_vec(L, self.even_commitments[parent_index]);
-                variables
-                    .iter()
-                    .map(|v| LinearCombination::<P0::ScalarField>::from(*v))
-                    .collect()
-            };
-            single_level_select_and_rerandomize(
-                even_verifier,
-                &parameters.odd_parameters,
-                &self.odd_commitments[odd_index],
-                variables,
-                None,
-                None,
-            );
+    /// A bloom filter of the leaves of the tree of `bits_per_leaf` bits per leaf, see `LeafBloom`,
+    /// for a wallet to skip the commitments that are not leaves before looking them up.
+    pub fn build_bloom(&self, bits_per_leaf: usize) -> LeafBloom {
+        let leaves = self.leaves();
+        let mut bloom = LeafBloom::new(leaves.len(), bits_per_leaf);
+        for leaf in &leaves {
+            bloom.insert(leaf);
         }
+        bloom
     }
 
-    pub fn odd_verifier_gadget<T: BorrowMut<Transcript>>(
-        &self,
-        odd_verifier: &mut Verifier<T, Affine<P1>>,
-        parameters: &SelRerandParameters<P0, P1>,
-        ct: &CurveTree<L, P0, P1>,
-    ) {
-        // Determine the parity of the root:
-        let root_is_odd = self.even_commitments.len() == self.odd_commitments.len();
-        if !root_is_odd {
-            assert!(self.even_commitments.len() == self.odd_commitments.len() + 1);
+    /// How the tree keeps the commitments of its nodes, as given to `from_set_with_storage`.
+    pub fn storage(&self) -> PointStorage {
+        match self {
+            Self::Even(ct) => ct.storage(),
+            Self::Odd(ct) => ct.storage(),
         }
-        for parent_index in 0..self.odd_commitments.len() {
-            let even_index = if root_is_odd {
-                parent_index
-            } else {
-                parent_index + 1
-            };
-            let variables = if parent_index == 0 && root_is_odd {
-                let children = match &ct {
-                    CurveTree::Odd(root) => {
-                        if let Some(children) = &root.children {
-                            x_coordinates(children)
-                        } else {
-                            panic!()
-                        }
-                    }
-                    _ => panic!(),
-                };
-                children.map(|c| constant(c)).to_vec()
-            } else {
-                let variables = odd_verifier.commit_vec(L, self.odd_commitments[parent_index]);
-                variables
-                    .iter()
-                    .map(|v| LinearCombination::<P1::ScalarField>::from(*v))
-                    .collect()
-            };
-            single_level_select_and_rerandomize(
-                odd_verifier,
-                &parameters.even_parameters,
-                &self.even_commitments[even_index],
-                variables,
-                None,
-                None,
-            );
+    }
+
+    /// The number of leaves, the size of the set a membership proof hides the leaf in.
+    pub fn leaf_count(&self) -> usize {
+        match self {
+            Self::Even(ct) => ct.elements,
+            Self::Odd(ct) => ct.elements,
         }
     }
-}
 
-impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> CanonicalSerialize
-    for SelectAndRerandomizePath<L, P0, P1>
-{
-    fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
-        self.even_commitments.serialized_size(compress)
-            + self.odd_commitments.serialized_size(compress)
+    /// The root of the tree, on the curve its parity says.
+    pub fn root(&self) -> CyclePoint<P0, P1> {
+        match self {
+            Self::Even(ct) => CyclePoint::Even(EvenPoint(ct.parent_commitment)),
+            Self::Odd(ct) => CyclePoint::Odd(OddPoint(ct.parent_commitment)),
+        }
     }
 
-    fn serialize_with_mode<W: Write>(
-        &self,
-        mut writer: W,
-        compress: ark_serialize::Compress,
-    ) -> Result<(), SerializationError> {
-        self.even_commitments
-            .serialize_with_mode(&mut writer, compress)?;
-        self.odd_commitments
-            .serialize_with_mode(&mut writer, compress)?;
-        Ok(())
+    /// The indices of `leaf` in the tree, in increasing order, none if it is not a leaf.
+    /// Looked up in an index of the leaves kept by the root, extended when leaves are inserted.
+    ///
+    /// A commitment inserted more than once, e.g. the same key in two registries, is a leaf at each of its indices:
+    /// membership can be proven and attested for each of them, and a membership proof does not tell them apart.
+    pub fn leaf_indices_of(&self, leaf: &Affine<P0>) -> Vec<usize> {
+        self.leaf_index().indices_of(&encoding(leaf))
     }
-}
 
-impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> Valid
-    for SelectAndRerandomizePath<L, P0, P1>
-{
-    fn check(&self) -> Result<(), SerializationError> {
-        Ok(())
+    /// The epoch of the positions of the leaves, see `LeafRef`: zero for a tree built with `from_set`,
+    /// incremented by `rebuild` and `compact`, which move leaves, and kept by inserting and pruning, which do not.
+    pub fn root_epoch(&self) -> u64 {
+        self.leaf_index().epoch
     }
-}
-impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> CanonicalDeserialize
-    for SelectAndRerandomizePath<L, P0, P1>
-{
-    fn deserialize_with_mode<R: Read>(
-        mut reader: R,
-        compress: ark_serialize::Compress,
-        validate: ark_serialize::Validate,
-    ) -> Result<Self, SerializationError> {
-        Ok(Self {
-            even_commitments: Vec::<Affine<P0>>::deserialize_with_mode(
-                &mut reader,
-                compress,
-                validate,
-            )?,
-            odd_commitments: Vec::<Affine<P1>>::deserialize_with_mode(
-                &mut reader,
-                compress,
-                validate,
-            )?,
+
+    /// A reference to the leaf at `index` in the current epoch of the tree, for a wallet to keep with the coin.
+    pub fn leaf_ref(&self, index: usize) -> Result<LeafRef, Error> {
+        let leaves = self.leaf_count();
+        if index >= leaves {
+            return Err(Error::parameter_mismatch(format!(
+                "Leaf index {} out of range, the tree has {} leaves",
+                index, leaves
+            )));
+        }
+        Ok(LeafRef {
+            index,
+            root_epoch: self.root_epoch(),
         })
     }
-}
-
-/// A witness of a Curve Tree path including siblings of randomness.
-/// Contains all the information needed to prove the select and rerandomize relation.
-#[derive(Clone)]
-pub struct CurveTreeWitnessPath<const L: usize, P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy>
-{
-    // list of internal even nodes
-    pub even_nodes: Vec<CurveTreeWitness<L, P0, P1>>,
-    // list of internal odd nodes
-    pub odd_nodes: Vec<CurveTreeWitness<L, P1, P0>>,
-}
 
-impl<
-        const L: usize,
-        F0: PrimeField,
-        F1: PrimeField,
-        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
-        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
-    > CurveTreeWitnessPath<L, P0, P1>
-{
-    fn root_is_even(&self) -> bool {
-        if self.even_nodes.len() == self.odd_nodes.len() {
-            return true;
-        }
-        if self.even_nodes.len() + 1 == self.odd_nodes.len() {
-            return false;
+    /// The index `leaf` refers to, checking that it was taken in the current epoch of the tree:
+    /// fails with `Error::StaleLeafRef` otherwise, as the leaves moved since, see `refresh_ref`.
+    /// The witnesses of the spends of a pour are requested with it, see `SpendingInfo`.
+    pub fn resolve_ref(&self, leaf: &LeafRef) -> Result<usize, Error> {
+        let current_epoch = self.root_epoch();
+        if leaf.root_epoch != current_epoch {
+            return Err(Error::StaleLeafRef {
+                index: leaf.index,
+                root_epoch: leaf.root_epoch,
+                current_epoch,
+            });
         }
-        panic!("Invalid witness path");
+        Ok(leaf.index)
     }
-    /// Commits to the root and rerandomizations of the path to the leaf specified by `index`
-    /// and proves the Select and rerandomize relation for each level.
-    /// Returns the rerandomized commitments on the path to (and including) the selected leaf
-    /// and the rerandomization scalar of the selected leaf.
-    pub fn select_and_rerandomize_prover_gadget<R: Rng>(
+
+    /// Like `select_and_rerandomize_prover_witness`, for the leaf `leaf` refers to, see `resolve_ref`.
+    #[cfg(feature = "prover")]
+    pub fn prover_witness_for_ref(
         &self,
-        even_prover: &mut Prover<Transcript, Affine<P0>>,
-        odd_prover: &mut Prover<Transcript, Affine<P1>>,
+        leaf: &LeafRef,
+    ) -> Result<CurveTreeWitnessPath<L, P0, P1>, Error> {
+        self.select_and_rerandomize_prover_witness(self.resolve_ref(leaf)?)
+    }
+
+    /// The reference to `leaf` in the current epoch of the tree, found by its value: `old_ref` if it is still current
+    /// and `leaf` is at its index, and the first index of `leaf` otherwise, e.g. after the tree was compacted.
+    /// Fails with `Error::InconsistentWitness` if `leaf` is not a leaf of the tree, or was pruned.
+    pub fn refresh_ref(&self, old_ref: &LeafRef, leaf: &Affine<P0>) -> Result<LeafRef, Error> {
+        let indices = self.leaf_indices_of(leaf);
+        let index = match self.resolve_ref(old_ref) {
+            Ok(index) if indices.contains(&index) => index,
+            _ => *indices.first().ok_or_else(|| {
+                Error::inconsistent_witness("The leaf is not an unpruned leaf of the tree")
+            })?,
+        };
+        Ok(LeafRef {
+            index,
+            root_epoch: self.root_epoch(),
+        })
+    }
+
+    /// Replaces the leaves of the tree with `set`, e.g. those of the chain after a reorganization,
+    /// keeping the storage, at least the height and the root history, and moves to the next epoch:
+    /// the references to leaves taken before fail with `Error::StaleLeafRef`, see `refresh_ref`.
+    ///
+    /// Fails with `Error::ParameterMismatch` if the tree records its events, see `set_event_sink`:
+    /// its log of inserts would no longer replay it.
+    pub fn rebuild(
+        &mut self,
+        set: &[Affine<P0>],
         parameters: &SelRerandParameters<P0, P1>,
-        rng: &mut R,
-    ) -> (SelectAndRerandomizePath<L, P0, P1>, P0::ScalarField) {
-        // for each even internal node, there must be a rerandomization of a commitment in the odd curve
+    ) -> Result<(), Error> {
+        let has_event_sink = match self {
+            Self::Even(ct) => ct.event_sink.is_some(),
+            Self::Odd(ct) => ct.event_sink.is_some(),
+        };
+        if has_event_sink {
+            return Err(Error::parameter_mismatch(
+                "A tree recording its events cannot be rebuilt",
+            ));
+        }
+        let epoch = self.root_epoch() + 1;
+        let tree =
+            Self::from_set_with_storage(set, parameters, Some(self.height()), self.storage())?
+                .with_root_epoch(epoch);
+        let root_history = self.take_root_history_with_root();
+        *self = tree.with_root_history(root_history);
+        Ok(())
+    }
+
+    /// Rebuilds the tree without its pruned leaves, reclaiming the memory `prune` keeps, see `rebuild`.
+    /// The leaves after a pruned leaf move to lower indices.
+    pub fn compact(&mut self, parameters: &SelRerandParameters<P0, P1>) -> Result<(), Error> {
+        self.rebuild(&self.leaves(), parameters)
+    }
+
+    /// Prunes the leaves at `indices`, e.g. those of coins whose spending tags are public:
+    /// witnesses, membership proofs and attestations for them fail with `Error::PrunedLeaf`,
+    /// and `leaf_indices_of` no longer finds them.
+    ///
+    /// No commitment changes, the root included, and the other leaves are proven as before:
+    /// a pruned leaf is still a sibling in the witnesses of the leaves next to it, and stays pruned when leaves are inserted.
+    ///
+    /// The nodes below a full node all of whose leaves are pruned are dropped, with either storage: no witness
+    /// goes through them, and no leaf is inserted below a full node. The node keeps its commitment, a sibling
+    /// in the witnesses of the other leaves. The pruned leaves next to leaves which are not pruned are kept,
+    /// and the root keeps its children: `compact` reclaims the rest of the memory, moving the leaves.
+    pub fn prune(&mut self, indices: &[usize]) -> Result<(), Error> {
+        let leaves = self.leaf_count();
+        if let Some(index) = indices.iter().find(|index| **index >= leaves) {
+            return Err(Error::parameter_mismatch(format!(
+                "Leaf index {} out of range, the tree has {} leaves",
+                index, leaves
+            )));
+        }
+        if indices.is_empty() {
+            return Ok(());
+        }
+        let set = self.stored_leaves();
+        let leaf_index = match self {
+            Self::Even(ct) => ct.leaf_index.as_mut(),
+            Self::Odd(ct) => ct.leaf_index.as_mut(),
+        }
+        .expect("the root of a tree keeps the index of its leaves");
+        let leaf_index = Arc::make_mut(leaf_index);
+        for index in indices {
+            // A leaf dropped was pruned before.
+            if let Some(leaf) = &set[*index] {
+                leaf_index.prune(&encoding(leaf), *index);
+            }
+        }
+        let collapsed = self.collapsed_nodes();
+        match self {
+            Self::Even(ct) => ct.drop_descendants(&collapsed),
+            Self::Odd(ct) => ct.drop_descendants(&collapsed),
+        }
+        Ok(())
+    }
+
+    // The full nodes below the root all of whose leaves are pruned, but those below another such node,
+    // by height and position among the nodes of their height.
+    fn collapsed_nodes(&self) -> Vec<(usize, usize)> {
+        // The positions of such nodes at each height, the pruned leaves at height 0.
+        let mut levels: Vec<Vec<usize>> = vec![self.leaf_index().pruned.iter().copied().collect()];
+        for _ in 1..self.height() {
+            let below = levels.last().expect("the leaves");
+            let mut level = Vec::new();
+            for children in below.chunk_by(|a, b| a / L == b / L) {
+                if children.len() == L {
+                    level.push(children[0] / L);
+                }
+            }
+            levels.push(level);
+        }
+        let mut collapsed = Vec::new();
+        for height in 1..levels.len() {
+            for &position in &levels[height] {
+                let is_below = levels
+                    .get(height + 1)
+                    .is_some_and(|above| above.binary_search(&(position / L)).is_ok());
+                if !is_below {
+                    collapsed.push((height, position));
+                }
+            }
+        }
+        collapsed
+    }
+
+    /// Whether the leaf at `index` was pruned, see `prune`.
+    pub fn is_pruned(&self, index: usize) -> bool {
+        self.leaf_index().pruned.contains(&index)
+    }
+
+    /// The indices of the pruned leaves, in increasing order.
+    pub fn pruned_indices(&self) -> Vec<usize> {
+        self.leaf_index().pruned.iter().copied().collect()
+    }
+
+    fn leaf_index(&self) -> &LeafIndex {
+        match self {
+            Self::Even(ct) => ct.leaf_index.as_ref(),
+            Self::Odd(ct) => ct.leaf_index.as_ref(),
+        }
+        .expect("the root of a tree keeps the index of its leaves")
+    }
+
+    /// A transparent attestation that the leaf at `index` is in the tree, verified with `verify_attestation`.
+    /// Unlike a membership proof it reveals the leaf, its index and the nodes above it, and is checked without R1CS.
+    pub fn inclusion_attestation(
+        &self,
+        index: usize,
+    ) -> Result<InclusionAttestation<L, P0, P1>, Error> {
+        let leaves = self.leaf_count();
+        if index >= leaves {
+            return Err(Error::parameter_mismatch(format!(
+                "Leaf index {} out of range, the tree has {} leaves",
+                index, leaves
+            )));
+        }
+        if self.is_pruned(index) {
+            return Err(Error::PrunedLeaf { index });
+        }
+        let mut even_levels = Vec::with_capacity(self.height() / 2 + 1);
+        let mut odd_levels = Vec::with_capacity(self.height() / 2 + 1);
+        match self {
+            Self::Even(ct) => ct.attestation_levels(index, &mut even_levels, &mut odd_levels),
+            Self::Odd(ct) => ct.attestation_levels(index, &mut odd_levels, &mut even_levels),
+        }
+        Ok(InclusionAttestation {
+            index,
+            even_levels,
+            odd_levels,
+        })
+    }
+
+    fn with_root_epoch(mut self, epoch: u64) -> Self {
+        if let Some(leaf_index) = match &mut self {
+            Self::Even(ct) => ct.leaf_index.as_mut(),
+            Self::Odd(ct) => ct.leaf_index.as_mut(),
+        } {
+            Arc::make_mut(leaf_index).epoch = epoch;
+        }
+        self
+    }
+
+    fn with_leaf_index(mut self, leaf_index: Arc<LeafIndex>) -> Self {
+        match &mut self {
+            Self::Even(ct) => ct.leaf_index = Some(leaf_index),
+            Self::Odd(ct) => ct.leaf_index = Some(leaf_index),
+        }
+        self
+    }
+
+    fn take_leaf_index(&mut self) -> Option<Arc<LeafIndex>> {
+        match self {
+            Self::Even(ct) => ct.leaf_index.take(),
+            Self::Odd(ct) => ct.leaf_index.take(),
+        }
+    }
+
+    /// Records the leaves inserted into the tree from now on in `sink`, as `Event::Insert`.
+    /// The leaves already in the tree are recorded first, so that the log replays from an empty tree.
+    ///
+    /// Fails with `Error::ParameterMismatch` if `prune` dropped leaves of the tree: the log would not replay it.
+    pub fn set_event_sink(&mut self, sink: SharedEventSink) -> Result<(), Error> {
+        let leaves = self.stored_leaves();
+        if leaves.iter().any(Option::is_none) {
+            return Err(Error::parameter_mismatch(
+                "A tree whose pruned leaves were dropped cannot record its events",
+            ));
+        }
+        for (index, leaf) in leaves.iter().flatten().enumerate() {
+            sink.record(&Event::insert(index, leaf));
+        }
+        match self {
+            Self::Even(ct) => ct.event_sink = Some(sink),
+            Self::Odd(ct) => ct.event_sink = Some(sink),
+        }
+        Ok(())
+    }
+
+    pub(crate) fn with_event_sink(mut self, event_sink: Option<SharedEventSink>) -> Self {
+        match &mut self {
+            Self::Even(ct) => ct.event_sink = event_sink,
+            Self::Odd(ct) => ct.event_sink = event_sink,
+        }
+        self
+    }
+
+    pub(crate) fn take_event_sink(&mut self) -> Option<SharedEventSink> {
+        match self {
+            Self::Even(ct) => ct.event_sink.take(),
+            Self::Odd(ct) => ct.event_sink.take(),
+        }
+    }
+
+    /// The number of leaves under `root`, the current root of the tree or one it had before leaves were inserted,
+    /// e.g. to display the size of the set a membership proof made against that root hides the leaf in.
+    /// `None` for a root the tree never had, or had before it was built again with `from_set`,
+    /// or that is older than the window of previous roots kept, see `set_root_history_window`.
+    pub fn leaf_count_at_root(&self, root: &CyclePoint<P0, P1>) -> Option<u64> {
+        let key = root_encoding(root);
+        if key == root_encoding(&self.root()) {
+            return Some(self.leaf_count() as u64);
+        }
+        match self {
+            Self::Even(ct) => ct.root_history.as_ref(),
+            Self::Odd(ct) => ct.root_history.as_ref(),
+        }?
+        .leaf_counts
+        .get(&key)
+        .copied()
+    }
+
+    /// Keeps the number of leaves of the `window` most recent previous roots only, forgetting the older ones now
+    /// and as leaves are inserted, so that the history of a long-lived tree takes bounded memory.
+    /// The window is `DEFAULT_ROOT_HISTORY_WINDOW` roots until set, and a window of 0 keeps no previous root.
+    pub fn set_root_history_window(&mut self, window: usize) {
+        let mut root_history = self.take_root_history().unwrap_or_default();
+        root_history.window = window;
+        root_history.truncate();
+        match self {
+            Self::Even(ct) => ct.root_history = Some(root_history),
+            Self::Odd(ct) => ct.root_history = Some(root_history),
+        }
+    }
+
+    pub(crate) fn with_root_history(mut self, root_history: Option<RootHistory>) -> Self {
+        match &mut self {
+            Self::Even(ct) => ct.root_history = root_history,
+            Self::Odd(ct) => ct.root_history = root_history,
+        }
+        self
+    }
+
+    fn take_root_history(&mut self) -> Option<RootHistory> {
+        match self {
+            Self::Even(ct) => ct.root_history.take(),
+            Self::Odd(ct) => ct.root_history.take(),
+        }
+    }
+
+    /// The previous roots of the tree and its current root, for the tree built by inserting leaves into it.
+    pub(crate) fn take_root_history_with_root(&mut self) -> Option<RootHistory> {
+        let mut root_history = self.take_root_history().unwrap_or_default();
+        root_history.record(root_encoding(&self.root()), self.leaf_count() as u64);
+        Some(root_history)
+    }
+
+    /// The tree of the leaves of this one followed by `leaves`, with the same storage, at least the same height,
+    /// the same pruned leaves and epoch, but without the event sink and root history, see `replace_with_inserted`,
+    /// and the attempts of the permissible search of the nodes committed.
+    ///
+    /// Only the nodes on the paths of the new leaves are committed, the other nodes are kept:
+    /// the tree grows to the height `from_set` would build it at, and has the root `from_set` would give it.
+    pub(crate) fn with_inserted(
+        &self,
+        leaves: &[Affine<P0>],
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<(Self, u64), Error> {
+        let storage = self.storage();
+        let first = self.leaf_count();
+        let mut tree = match self {
+            Self::Even(ct) => Self::Even(ct.clone()),
+            Self::Odd(ct) => Self::Odd(ct.clone()),
+        };
+        tree.take_event_sink();
+        tree.take_root_history();
+        if leaves.is_empty() {
+            return Ok((tree, 0));
+        }
+        let mut leaf_index = tree
+            .take_leaf_index()
+            .expect("the root of a tree keeps the index of its leaves");
+        let total = first + leaves.len();
+        let mut height = tree.height();
+        while L
+            .checked_pow(height as u32)
+            .is_some_and(|capacity| capacity < total)
+        {
+            height += 1;
+        }
+        let (tree, attempts) = match tree.increase_height(Some(height), parameters)? {
+            Self::Even(ct) => {
+                let (ct, attempts) = ct.with_appended(
+                    NewLeaves::Own(leaves),
+                    storage,
+                    &parameters.even_parameters,
+                    &parameters.odd_parameters,
+                )?;
+                (Self::Even(ct), attempts)
+            }
+            Self::Odd(ct) => {
+                let (ct, attempts) = ct.with_appended(
+                    NewLeaves::Other(leaves),
+                    storage,
+                    &parameters.odd_parameters,
+                    &parameters.even_parameters,
+                )?;
+                (Self::Odd(ct), attempts)
+            }
+        };
+        Arc::make_mut(&mut leaf_index).extend(first, leaves);
+        Ok((tree.with_leaf_index(leaf_index), attempts))
+    }
+
+    /// Replaces the tree with `tree`, built from it by `with_inserted`, moving the event sink and the root history over
+    /// and recording the inserted leaves. It does not fail, so that several trees can be replaced at once.
+    pub(crate) fn replace_with_inserted(&mut self, tree: Self) {
+        let first = self.leaf_count();
+        let event_sink = self.take_event_sink();
+        let root_history = self.take_root_history_with_root();
+        if let Some(sink) = &event_sink {
+            for (index, leaf) in tree.stored_leaves().iter().enumerate().skip(first) {
+                sink.record(&Event::insert(
+                    index,
+                    leaf.as_ref().expect("the leaves inserted are kept"),
+                ));
+            }
+        }
+        *self = tree
+            .with_event_sink(event_sink)
+            .with_root_history(root_history);
+    }
+
+    /// Inserts `leaves` as `Accumulator::insert` does, counting them in `metrics`, see `metrics::LEAVES_INSERTED`,
+    /// with the attempts of the permissible search of the inner nodes committed on their paths.
+    /// The other inner nodes are kept as they were, their attempts are not counted again.
+    pub fn insert_with_metrics(
+        &mut self,
+        leaves: &[Affine<P0>],
+        parameters: &SelRerandParameters<P0, P1>,
+        metrics: &dyn Metrics,
+    ) -> Result<Vec<usize>, Error> {
+        let first = self.leaf_count();
+        let (tree, attempts) = self.with_inserted(leaves, parameters)?;
+        metrics.increment(metrics::LEAVES_INSERTED, None, leaves.len() as u64);
+        metrics.increment(metrics::PERMISSIBLE_ATTEMPTS, None, attempts);
+        self.replace_with_inserted(tree);
+        Ok((first..self.leaf_count()).collect())
+    }
+}
+
+// The attempts of the permissible search of an inner node whose commitment was blinded `offset` times,
+// see `UniversalHash::permissible_commitment`.
+fn permissible_attempts<F: PrimeField>(offset: &F) -> u64 {
+    u64_from_fe(offset).expect("the offset of a permissible search is small") + 1
+}
+
+// The positions of the leaves of a tree by their compressed encoding, but for the pruned leaves.
+// The later positions of repeated leaves are apart, so that distinct leaves take a single position each.
+#[derive(Clone)]
+struct LeafIndex {
+    first: HashMap<Vec<u8>, usize>,
+    // The later positions of the repeated leaves, by their first position.
+    repeated: HashMap<usize, Vec<usize>>,
+    // The positions of the pruned leaves, see `CurveTree::prune`.
+    pruned: BTreeSet<usize>,
+    // The epoch of the positions, see `CurveTree::root_epoch`.
+    epoch: u64,
+}
+
+impl LeafIndex {
+    fn indices_of(&self, encoding: &[u8]) -> Vec<usize> {
+        match self.first.get(encoding) {
+            None => Vec::new(),
+            Some(first) => iter::once(*first)
+                .chain(self.repeated.get(first).into_iter().flatten().copied())
+                .collect(),
+        }
+    }
+
+    // Adds `leaves`, at the positions from `first`.
+    fn extend<P: SWCurveConfig>(&mut self, first: usize, leaves: &[Affine<P>]) {
+        for (index, leaf) in (first..).zip(leaves) {
+            let first_index = *self.first.entry(encoding(leaf)).or_insert(index);
+            if first_index != index {
+                self.repeated.entry(first_index).or_default().push(index);
+            }
+        }
+    }
+
+    // Removes the leaf of `encoding` at `index` from the index, the next position of a repeated leaf becoming its first.
+    fn prune(&mut self, encoding: &[u8], index: usize) {
+        if !self.pruned.insert(index) {
+            return;
+        }
+        match self.first.get(encoding).copied() {
+            Some(first) if first == index => match self.repeated.remove(&first) {
+                Some(mut later) => {
+                    let next = later.remove(0);
+                    self.first.insert(encoding.to_vec(), next);
+                    if !later.is_empty() {
+                        self.repeated.insert(next, later);
+                    }
+                }
+                None => {
+                    self.first.remove(encoding);
+                }
+            },
+            Some(first) => {
+                if let Some(later) = self.repeated.get_mut(&first) {
+                    later.retain(|position| *position != index);
+                    if later.is_empty() {
+                        self.repeated.remove(&first);
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+fn encoding<P: SWCurveConfig>(point: &Affine<P>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(point.compressed_size());
+    point
+        .serialize_compressed(&mut bytes)
+        .expect("serializing to a vector does not fail");
+    bytes
+}
+
+// The number of leaves under the most recent previous roots of a tree, by the curve and compressed encoding of the root,
+// and those roots from the oldest, at most `window` of them.
+#[derive(Clone)]
+pub(crate) struct RootHistory {
+    leaf_counts: HashMap<Vec<u8>, u64>,
+    roots: VecDeque<Vec<u8>>,
+    window: usize,
+}
+
+impl Default for RootHistory {
+    fn default() -> Self {
+        Self {
+            leaf_counts: HashMap::new(),
+            roots: VecDeque::new(),
+            window: DEFAULT_ROOT_HISTORY_WINDOW,
+        }
+    }
+}
+
+impl RootHistory {
+    fn record(&mut self, root: Vec<u8>, leaf_count: u64) {
+        // A root is recorded again when no leaf was inserted, it keeps its place in the window.
+        if self.leaf_counts.insert(root.clone(), leaf_count).is_none() {
+            self.roots.push_back(root);
+        }
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        while self.roots.len() > self.window {
+            if let Some(oldest) = self.roots.pop_front() {
+                self.leaf_counts.remove(&oldest);
+            }
+        }
+    }
+}
+
+pub(crate) fn root_encoding<P0: SWCurveConfig, P1: SWCurveConfig>(
+    root: &CyclePoint<P0, P1>,
+) -> Vec<u8> {
+    let (curve, point) = match root {
+        CyclePoint::Even(root) => (0, encoding(&root.0)),
+        CyclePoint::Odd(root) => (1, encoding(&root.0)),
+    };
+    iter::once(curve).chain(point).collect()
+}
+
+fn leaf_index<P: SWCurveConfig>(set: &[Affine<P>]) -> Arc<LeafIndex> {
+    let mut leaf_index = LeafIndex {
+        first: HashMap::with_capacity(set.len()),
+        repeated: HashMap::new(),
+        pruned: BTreeSet::new(),
+        epoch: 0,
+    };
+    leaf_index.extend(0, set);
+    Arc::new(leaf_index)
+}
+
+/// A leaf of a tree by its index, in the epoch of the tree the index was taken in, see `CurveTree::leaf_ref`.
+///
+/// Rebuilding or compacting a tree moves its leaves, so an index kept by a wallet may point at another leaf,
+/// and proving would either fail late, when the coin does not open the leaf, or prove the membership of the leaf
+/// of someone else. Rebuilding moves the tree to the next epoch, and the tree refuses the references of other epochs
+/// with `Error::StaleLeafRef`, until refreshed with `CurveTree::refresh_ref`.
+/// Epochs are counted per tree: a reference to a leaf of another tree is not caught.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LeafRef {
+    pub index: usize,
+    pub root_epoch: u64,
+}
+
+impl CanonicalSerialize for LeafRef {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.index.serialize_with_mode(&mut writer, compress)?;
+        self.root_epoch.serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.index.serialized_size(compress) + self.root_epoch.serialized_size(compress)
+    }
+}
+
+impl Valid for LeafRef {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl CanonicalDeserialize for LeafRef {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        Ok(Self {
+            index: usize::deserialize_with_mode(&mut reader, compress, validate)?,
+            root_epoch: u64::deserialize_with_mode(&mut reader, compress, validate)?,
+        })
+    }
+}
+
+/// A point on either curve of the cycle, such as the root of a tree or the rerandomized leaf of a `LeafCurveTree`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CyclePoint<P0: SWCurveConfig, P1: SWCurveConfig> {
+    Even(EvenPoint<P0>),
+    Odd(OddPoint<P1>),
+}
+
+impl<P0: SWCurveConfig, P1: SWCurveConfig> CyclePoint<P0, P1> {
+    /// The same point, with the curves swapped.
+    pub fn swapped(self) -> CyclePoint<P1, P0> {
+        match self {
+            Self::Even(point) => CyclePoint::Odd(point.swapped()),
+            Self::Odd(point) => CyclePoint::Even(point.swapped()),
+        }
+    }
+}
+
+/// A scalar of either curve of the cycle, such as the blinding of a leaf of a `LeafCurveTree`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CycleScalar<P0: SWCurveConfig, P1: SWCurveConfig> {
+    Even(EvenScalar<P0>),
+    Odd(OddScalar<P1>),
+}
+
+/// A curve tree with its leaves on either curve of the cycle: `Even` trees have their leaves on `P0`, `Odd` trees on `P1`.
+/// The curves of the levels above alternate as in `CurveTree`, so choosing the curve of the leaves
+/// chooses the curve of the root for a given height.
+/// Both parities use the same `SelRerandParameters`, the odd trees through `SelRerandParameters::swapped`.
+pub enum LeafCurveTree<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
+    Even(CurveTree<L, P0, P1>),
+    Odd(CurveTree<L, P1, P0>),
+}
+
+/// A rerandomized path of a `LeafCurveTree`, of the parity of the tree.
+#[derive(Clone)]
+pub enum LeafCurvePath<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
+    Even(SelectAndRerandomizePath<L, P0, P1>),
+    Odd(SelectAndRerandomizePath<L, P1, P0>),
+}
+
+/// A membership proof of a `LeafCurveTree`, of the parity of the tree.
+#[derive(Clone)]
+pub enum LeafCurveMembershipProof<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
+    Even(MembershipProof<L, P0, P1>),
+    Odd(MembershipProof<L, P1, P0>),
+}
+
+impl<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    > LeafCurveTree<L, P0, P1>
+{
+    /// Builds a tree with its leaves on `P0`, see `CurveTree::from_set`.
+    pub fn from_even_set(
+        set: &[Affine<P0>],
+        parameters: &SelRerandParameters<P0, P1>,
+        height: Option<usize>,
+    ) -> Result<Self, Error> {
+        Ok(Self::Even(CurveTree::from_set(set, parameters, height)?))
+    }
+
+    /// Builds a tree with its leaves on `P1`, see `CurveTree::from_set`.
+    pub fn from_odd_set(
+        set: &[Affine<P1>],
+        parameters: &SelRerandParameters<P0, P1>,
+        height: Option<usize>,
+    ) -> Result<Self, Error> {
+        Ok(Self::Odd(CurveTree::from_set(
+            set,
+            &parameters.swapped(),
+            height,
+        )?))
+    }
+
+    pub fn height(&self) -> usize {
+        match self {
+            Self::Even(tree) => tree.height(),
+            Self::Odd(tree) => tree.height(),
+        }
+    }
+
+    pub fn root(&self) -> CyclePoint<P0, P1> {
+        match self {
+            Self::Even(tree) => tree.root(),
+            Self::Odd(tree) => tree.root().swapped(),
+        }
+    }
+
+    /// Like `CurveTree::select_and_rerandomize_prover_gadget`, the odd trees proving their leaf level with `odd_prover`.
+    /// Returns the path and the rerandomization scalar of the selected leaf, on the curve of the leaves.
+    #[cfg(feature = "prover")]
+    #[allow(clippy::type_complexity)]
+    pub fn select_and_rerandomize_prover_gadget<R: RngCore + CryptoRng>(
+        &self,
+        index: usize,
+        even_prover: &mut Prover<Transcript, Affine<P0>>,
+        odd_prover: &mut Prover<Transcript, Affine<P1>>,
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<(LeafCurvePath<L, P0, P1>, CycleScalar<P0, P1>), Error> {
+        match self {
+            Self::Even(tree) => {
+                let (path, rerandomization) = tree.select_and_rerandomize_prover_gadget(
+                    index,
+                    even_prover,
+                    odd_prover,
+                    parameters,
+                    rng,
+                )?;
+                Ok((
+                    LeafCurvePath::Even(path),
+                    CycleScalar::Even(rerandomization),
+                ))
+            }
+            Self::Odd(tree) => {
+                let (path, rerandomization) = tree.select_and_rerandomize_prover_gadget(
+                    index,
+                    odd_prover,
+                    even_prover,
+                    &parameters.swapped(),
+                    rng,
+                )?;
+                Ok((
+                    LeafCurvePath::Odd(path),
+                    CycleScalar::Odd(rerandomization.swapped()),
+                ))
+            }
+        }
+    }
+
+    /// Verifier counterpart of `select_and_rerandomize_prover_gadget`.
+    /// Returns the rerandomized leaf, on the curve of the leaves.
+    /// Fails with `Error::InvalidPath` for a path of the other parity.
+    pub fn select_and_rerandomize_verifier_gadget<T: BorrowMut<Transcript>>(
+        &self,
+        even_verifier: &mut Verifier<T, Affine<P0>>,
+        odd_verifier: &mut Verifier<T, Affine<P1>>,
+        randomized_path: LeafCurvePath<L, P0, P1>,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<CyclePoint<P0, P1>, Error> {
+        match (self, randomized_path) {
+            (Self::Even(tree), LeafCurvePath::Even(path)) => Ok(CyclePoint::Even(EvenPoint(
+                tree.select_and_rerandomize_verifier_gadget(
+                    even_verifier,
+                    odd_verifier,
+                    path,
+                    parameters,
+                )?,
+            ))),
+            (Self::Odd(tree), LeafCurvePath::Odd(path)) => Ok(CyclePoint::Odd(OddPoint(
+                tree.select_and_rerandomize_verifier_gadget(
+                    odd_verifier,
+                    even_verifier,
+                    path,
+                    &parameters.swapped(),
+                )?,
+            ))),
+            _ => Err(Error::InvalidPath),
+        }
+    }
+
+    /// Like `CurveTree::prove_membership`, with `leaf_blinding` on the curve of the leaves.
+    #[cfg(feature = "prover")]
+    #[allow(clippy::type_complexity)]
+    pub fn prove_membership<R: RngCore + CryptoRng>(
+        &self,
+        index: usize,
+        leaf_blinding: CycleScalar<P0, P1>,
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<(LeafCurveMembershipProof<L, P0, P1>, CycleScalar<P0, P1>), Error> {
+        match (self, leaf_blinding) {
+            (Self::Even(tree), CycleScalar::Even(leaf_blinding)) => {
+                let (proof, blinding) =
+                    tree.prove_membership(index, leaf_blinding, parameters, rng)?;
+                Ok((
+                    LeafCurveMembershipProof::Even(proof),
+                    CycleScalar::Even(blinding),
+                ))
+            }
+            (Self::Odd(tree), CycleScalar::Odd(leaf_blinding)) => {
+                let (proof, blinding) = tree.prove_membership(
+                    index,
+                    leaf_blinding.swapped(),
+                    &parameters.swapped(),
+                    rng,
+                )?;
+                Ok((
+                    LeafCurveMembershipProof::Odd(proof),
+                    CycleScalar::Odd(blinding.swapped()),
+                ))
+            }
+            _ => Err(Error::parameter_mismatch(
+                "The blinding of a leaf is on the curve of the leaves",
+            )),
+        }
+    }
+}
+
+impl<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    > LeafCurveMembershipProof<L, P0, P1>
+{
+    /// Verifies the proof against the tree, see `MembershipProof::verify`,
+    /// and returns the rerandomized leaf, on the curve of the leaves.
+    /// Fails with `Error::InvalidPath` for a tree of the other parity.
+    pub fn verify(
+        &self,
+        tree: &LeafCurveTree<L, P0, P1>,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<CyclePoint<P0, P1>, Error> {
+        match (self, tree) {
+            (Self::Even(proof), LeafCurveTree::Even(tree)) => {
+                Ok(CyclePoint::Even(EvenPoint(proof.verify(tree, parameters)?)))
+            }
+            (Self::Odd(proof), LeafCurveTree::Odd(tree)) => Ok(CyclePoint::Odd(OddPoint(
+                proof.verify(tree, &parameters.swapped())?,
+            ))),
+            _ => Err(Error::InvalidPath),
+        }
+    }
+}
+
+// todo don't include the root
+#[derive(Clone)]
+pub struct SelectAndRerandomizePath<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
+    pub even_commitments: Vec<Affine<P0>>,
+    pub odd_commitments: Vec<Affine<P1>>,
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> std::fmt::Debug
+    for SelectAndRerandomizePath<L, P0, P1>
+{
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("SelectAndRerandomizePath")
+            .field("even_commitments", &self.even_commitments)
+            .field("odd_commitments", &self.odd_commitments)
+            .finish()
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> SelectAndRerandomizePath<L, P0, P1> {
+    /// The number of levels the path goes down, the height of the tree of a path from a prover.
+    pub fn depth(&self) -> usize {
+        self.even_commitments.len() + self.odd_commitments.len()
+    }
+
+    /// Fails with `Error::LimitExceeded` if the path is deeper than `limits.max_depth`,
+    /// which deserializing a path checks, for paths built otherwise to be rejected before any verification work.
+    pub fn validate(&self, limits: &DeserializeLimits) -> Result<(), Error> {
+        DeserializeLimits::check(self.depth() as u64, limits.max_depth, "the depth of a path")?;
+        Ok(())
+    }
+}
+
+impl<
+        const L: usize,
+        F: PrimeField,
+        P0: SWCurveConfig<BaseField = F> + Copy + Send,
+        P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = F> + Copy + Send,
+    > SelectAndRerandomizePath<L, P0, P1>
+{
+    /// Get the public rerandomization of the selected commitment
+    pub fn get_rerandomized_leaf(&self) -> Affine<P0> {
+        *self.even_commitments.last().unwrap()
+    }
+
+    /// Checks that the path has the shape of a path of `ct` and starts at its root,
+    /// as the paths returned by `CurveTree::select_and_rerandomize_verification_commitments` do.
+    /// The gadgets lay down one level for each commitment, this bounds them by the height of the tree
+    /// before any constraint, whatever the length of the path.
+    fn check_starts_at_root(&self, ct: &CurveTree<L, P0, P1>) -> Result<(), Error> {
+        let even_length = self.even_commitments.len();
+        let odd_length = self.odd_commitments.len();
+        let (even_path_length, odd_path_length) = ct.path_lengths();
+        let starts_at_root = match ct {
+            CurveTree::Even(root) => {
+                even_length == even_path_length + 1
+                    && odd_length == odd_path_length
+                    && self.even_commitments[0] == root.parent_commitment
+            }
+            CurveTree::Odd(root) => {
+                even_length == even_path_length
+                    && odd_length == odd_path_length + 1
+                    && self.odd_commitments[0] == root.parent_commitment
+            }
+        };
+        if !starts_at_root {
+            return Err(Error::InvalidPath);
+        }
+        Ok(())
+    }
+
+    pub fn even_verifier_gadget<T: BorrowMut<Transcript>>(
+        &self,
+        even_verifier: &mut Verifier<T, Affine<P0>>,
+        parameters: &SelRerandParameters<P0, P1>,
+        ct: &CurveTree<L, P0, P1>,
+    ) -> Result<(), Error> {
+        self.check_starts_at_root(ct)?;
+        let root_is_odd = matches!(ct, CurveTree::Odd(_));
+
+        // The children of a level, reused across the levels.
+        let mut children = Vec::with_capacity(L);
+        // The last even commitment is skipped as it is the leaf and as such not a parent in the select and rerandomize relation.
+        for parent_index in 0..self.even_commitments.len() - 1 {
+            let odd_index = if root_is_odd {
+                parent_index + 1
+            } else {
+                parent_index
+            };
+            children.clear();
+            if parent_index == 0 && !root_is_odd {
+                let x_coordinates = match &ct {
+                    // a single leaf is not the parent of a path
+                    CurveTree::Even(root) => {
+                        root.children_x_coordinates().ok_or(Error::InvalidPath)?
+                    }
+                    CurveTree::Odd(_) => unreachable!("the root is even"),
+                };
+                children.extend(
+                    x_coordinates
+                        .into_iter()
+                        .map(|x| even_verifier.public_constant(x)),
+                );
+            } else {
+                let variables = even_verifier.commit_vec(L, self.even_commitments[parent_index]);
+                children.extend(variables.into_iter().map(LinearCombination::from));
+            }
+            single_level_select_and_rerandomize(
+                even_verifier,
+                &parameters.odd_parameters,
+                &self.odd_commitments[odd_index],
+                &children,
+                None,
+                None,
+            );
+        }
+        Ok(())
+    }
+
+    pub fn odd_verifier_gadget<T: BorrowMut<Transcript>>(
+        &self,
+        odd_verifier: &mut Verifier<T, Affine<P1>>,
+        parameters: &SelRerandParameters<P0, P1>,
+        ct: &CurveTree<L, P0, P1>,
+    ) -> Result<(), Error> {
+        self.check_starts_at_root(ct)?;
+        let root_is_odd = matches!(ct, CurveTree::Odd(_));
+
+        // The children of a level, reused across the levels.
+        let mut children = Vec::with_capacity(L);
+        for parent_index in 0..self.odd_commitments.len() {
+            let even_index = if root_is_odd {
+                parent_index
+            } else {
+                parent_index + 1
+            };
+            children.clear();
+            if parent_index == 0 && root_is_odd {
+                let x_coordinates = match &ct {
+                    // a single leaf is not the parent of a path
+                    CurveTree::Odd(root) => {
+                        root.children_x_coordinates().ok_or(Error::InvalidPath)?
+                    }
+                    CurveTree::Even(_) => unreachable!("the root is odd"),
+                };
+                children.extend(
+                    x_coordinates
+                        .into_iter()
+                        .map(|x| odd_verifier.public_constant(x)),
+                );
+            } else {
+                let variables = odd_verifier.commit_vec(L, self.odd_commitments[parent_index]);
+                children.extend(variables.into_iter().map(LinearCombination::from));
+            }
+            single_level_select_and_rerandomize(
+                odd_verifier,
+                &parameters.even_parameters,
+                &self.even_commitments[even_index],
+                &children,
+                None,
+                None,
+            );
+        }
+        Ok(())
+    }
+
+    /// The statement of `even_verifier_gadget`, see `CurveTree::select_and_rerandomize_verifier_statement`.
+    pub fn even_verifier_statement<T: BorrowMut<Transcript>>(
+        &self,
+        even_verifier: &mut Verifier<T, Affine<P0>>,
+        ct: &CurveTree<L, P0, P1>,
+    ) -> Result<(), Error> {
+        self.check_starts_at_root(ct)?;
+        let root_is_odd = matches!(ct, CurveTree::Odd(_));
+        for parent_index in 0..self.even_commitments.len() - 1 {
+            let odd_index = if root_is_odd {
+                parent_index + 1
+            } else {
+                parent_index
+            };
+            if parent_index == 0 && !root_is_odd {
+                let x_coordinates = match &ct {
+                    CurveTree::Even(root) => {
+                        root.children_x_coordinates().ok_or(Error::InvalidPath)?
+                    }
+                    CurveTree::Odd(_) => unreachable!("the root is even"),
+                };
+                for x in x_coordinates {
+                    even_verifier.public_constant(x);
+                }
+            } else {
+                even_verifier.commit_vec(L, self.even_commitments[parent_index]);
+            }
+            single_level_select_and_rerandomize_statement(
+                even_verifier,
+                &self.odd_commitments[odd_index],
+            );
+        }
+        Ok(())
+    }
+
+    /// The statement of `odd_verifier_gadget`, see `CurveTree::select_and_rerandomize_verifier_statement`.
+    pub fn odd_verifier_statement<T: BorrowMut<Transcript>>(
+        &self,
+        odd_verifier: &mut Verifier<T, Affine<P1>>,
+        ct: &CurveTree<L, P0, P1>,
+    ) -> Result<(), Error> {
+        self.check_starts_at_root(ct)?;
+        let root_is_odd = matches!(ct, CurveTree::Odd(_));
+        for parent_index in 0..self.odd_commitments.len() {
+            let even_index = if root_is_odd {
+                parent_index
+            } else {
+                parent_index + 1
+            };
+            if parent_index == 0 && root_is_odd {
+                let x_coordinates = match &ct {
+                    CurveTree::Odd(root) => {
+                        root.children_x_coordinates().ok_or(Error::InvalidPath)?
+                    }
+                    CurveTree::Even(_) => unreachable!("the root is odd"),
+                };
+                for x in x_coordinates {
+                    odd_verifier.public_constant(x);
+                }
+            } else {
+                odd_verifier.commit_vec(L, self.odd_commitments[parent_index]);
+            }
+            single_level_select_and_rerandomize_statement(
+                odd_verifier,
+                &self.even_commitments[even_index],
+            );
+        }
+        Ok(())
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> CanonicalSerialize
+    for SelectAndRerandomizePath<L, P0, P1>
+{
+    fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        1 + self.even_commitments.serialized_size(compress)
+            + self.odd_commitments.serialized_size(compress)
+    }
+
+    /// Writes the version byte of `FormatVersion::CURRENT` first.
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), SerializationError> {
+        FormatVersion::CURRENT
+            .byte()
+            .serialize_with_mode(&mut writer, compress)?;
+        self.even_commitments
+            .serialize_with_mode(&mut writer, compress)?;
+        self.odd_commitments
+            .serialize_with_mode(&mut writer, compress)?;
+        Ok(())
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> Valid
+    for SelectAndRerandomizePath<L, P0, P1>
+{
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> CanonicalDeserialize
+    for SelectAndRerandomizePath<L, P0, P1>
+{
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, SerializationError> {
+        deserialize_with_default_limits(reader, compress, validate)
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> DeserializeWithLimits
+    for SelectAndRerandomizePath<L, P0, P1>
+{
+    fn deserialize_with_limits<R: Read>(
+        mut reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+        limits: &DeserializeLimits,
+    ) -> Result<Self, Error> {
+        FormatVersion::from_byte(u8::deserialize_with_mode(&mut reader, compress, validate)?)?;
+        let path = Self {
+            even_commitments: deserialize_vec(
+                &mut reader,
+                compress,
+                validate,
+                limits.max_depth,
+                "the number of even commitments of a path",
+            )?,
+            odd_commitments: deserialize_vec(
+                &mut reader,
+                compress,
+                validate,
+                limits.max_depth,
+                "the number of odd commitments of a path",
+            )?,
+        };
+        path.validate(limits)?;
+        Ok(path)
+    }
+}
+
+/// Binds the transcripts of the two curves to each other: a challenge squeezed from the even transcript
+/// is appended to the odd one, then a challenge squeezed from the odd transcript is appended to the even one.
+///
+/// Without it, nothing ties an even proof to the odd proof of the same session,
+/// so proofs of two sessions with the same path could be mixed.
+/// The prover and the verifier must bind at the same point of their constraint systems.
+pub fn bind_transcripts(even_transcript: &mut Transcript, odd_transcript: &mut Transcript) {
+    let mut challenge = [0u8; 32];
+    even_transcript.challenge_bytes(protocol::EVEN_TRANSCRIPT_BINDING, &mut challenge);
+    odd_transcript.append_message(protocol::EVEN_TRANSCRIPT_BINDING, &challenge);
+    odd_transcript.challenge_bytes(protocol::ODD_TRANSCRIPT_BINDING, &mut challenge);
+    even_transcript.append_message(protocol::ODD_TRANSCRIPT_BINDING, &challenge);
+}
+
+/// The proofs of the even and the odd constraint systems of a statement.
+pub type ProofPair<P0, P1> = (R1CSProof<Affine<P0>>, R1CSProof<Affine<P1>>);
+
+/// Proves the constraint systems of both curves, once they are laid down and bound to each other, see `bind_transcripts`.
+///
+/// Once bound, each proof only depends on its own transcript and witness. With the `parallel` feature the two are
+/// computed on separate rayon tasks, roughly halving the latency of proving on several cores,
+/// and are the same as computed one after the other, see `prove_pair_with_rng`.
+#[cfg(feature = "prover")]
+pub fn prove_pair<
+    T0: BorrowMut<Transcript>,
+    T1: BorrowMut<Transcript>,
+    P0: SWCurveConfig + Copy,
+    P1: SWCurveConfig + Copy,
+>(
+    even_prover: Prover<T0, Affine<P0>>,
+    odd_prover: Prover<T1, Affine<P1>>,
+    parameters: &SelRerandParameters<P0, P1>,
+) -> Result<ProofPair<P0, P1>, Error> {
+    #[cfg(not(feature = "parallel"))]
+    let (even_proof, odd_proof) = (
+        even_prover.prove(&parameters.even_parameters.bp_gens),
+        odd_prover.prove(&parameters.odd_parameters.bp_gens),
+    );
+    #[cfg(feature = "parallel")]
+    let (even_proof, odd_proof) = rayon::join(
+        || even_prover.prove(&parameters.even_parameters.bp_gens),
+        || odd_prover.prove(&parameters.odd_parameters.bp_gens),
+    );
+    Ok((even_proof?, odd_proof?))
+}
+
+/// Like `prove_pair`, drawing the blindings of the even proof from `even_rng` and those of the odd proof from `odd_rng`.
+/// The proofs are then reproducible, byte for byte those of `Prover::prove_with_rng` called with the same rngs
+/// one proof after the other.
+#[cfg(feature = "prover")]
+pub fn prove_pair_with_rng<
+    T0: BorrowMut<Transcript>,
+    T1: BorrowMut<Transcript>,
+    P0: SWCurveConfig + Copy,
+    P1: SWCurveConfig + Copy,
+    R0: RngCore + CryptoRng + Send,
+    R1: RngCore + CryptoRng + Send,
+>(
+    even_prover: Prover<T0, Affine<P0>>,
+    odd_prover: Prover<T1, Affine<P1>>,
+    parameters: &SelRerandParameters<P0, P1>,
+    even_rng: &mut R0,
+    odd_rng: &mut R1,
+) -> Result<ProofPair<P0, P1>, Error> {
+    #[cfg(not(feature = "parallel"))]
+    let (even_proof, odd_proof) = (
+        even_prover.prove_with_rng(&parameters.even_parameters.bp_gens, even_rng),
+        odd_prover.prove_with_rng(&parameters.odd_parameters.bp_gens, odd_rng),
+    );
+    #[cfg(feature = "parallel")]
+    let (even_proof, odd_proof) = rayon::join(
+        || even_prover.prove_with_rng(&parameters.even_parameters.bp_gens, even_rng),
+        || odd_prover.prove_with_rng(&parameters.odd_parameters.bp_gens, odd_rng),
+    );
+    Ok((even_proof?, odd_proof?))
+}
+
+/// Appends the number of leaves of the tree to both transcripts of a membership proof, before its gadgets,
+/// so that the proof commits to the size of the set it hides the leaf in, see `MembershipProof::leaf_count`.
+pub fn bind_leaf_count(
+    even_transcript: &mut Transcript,
+    odd_transcript: &mut Transcript,
+    leaf_count: u64,
+) {
+    even_transcript.append_u64(protocol::LEAF_COUNT, leaf_count);
+    odd_transcript.append_u64(protocol::LEAF_COUNT, leaf_count);
+}
+
+/// The children of a node on the path of an `InclusionAttestation`, on the curve `P1`,
+/// and the blinding offset that made the commitment of the node, on the curve `P0`, permissible.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttestationLevel<P0: SWCurveConfig, P1: SWCurveConfig> {
+    /// The commitments of the children, from the left, without the empty ones.
+    pub children: Vec<Affine<P1>>,
+    pub blinding_offset: P0::ScalarField,
+}
+
+impl<P0: SWCurveConfig + Copy, P1: SWCurveConfig<BaseField = P0::ScalarField>>
+    AttestationLevel<P0, P1>
+{
+    // Checks that `parent` commits to the children, and returns the child at `child_index`.
+    fn child<const L: usize>(
+        &self,
+        parent: &Affine<P0>,
+        child_index: usize,
+        parameters: &SingleLayerParameters<P0>,
+    ) -> Result<Affine<P1>, Error> {
+        // The identity has the x-coordinate of an empty child.
+        if self.children.len() > L || self.children.iter().any(|child| child.infinity) {
+            return Err(Error::InvalidPath);
+        }
+        let mut x_coordinates = [P0::ScalarField::zero(); L];
+        for (x, child) in x_coordinates.iter_mut().zip(&self.children) {
+            *x = child.x;
+        }
+        if parameters.commit(&x_coordinates, self.blinding_offset, 0)? != *parent {
+            return Err(Error::InvalidPath);
+        }
+        self.children
+            .get(child_index)
+            .copied()
+            .ok_or(Error::InvalidPath)
+    }
+}
+
+/// A transparent attestation that a leaf is at `index` in a curve tree, made by `CurveTree::inclusion_attestation`:
+/// the children and blinding offset of each node on the path from the root to the leaf.
+/// It reveals the leaf and its position, e.g. for an explorer to show where an output was inserted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InclusionAttestation<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
+    pub index: usize,
+    /// The levels of the even nodes on the path, from the root.
+    pub even_levels: Vec<AttestationLevel<P0, P1>>,
+    /// The levels of the odd nodes on the path, from the root.
+    pub odd_levels: Vec<AttestationLevel<P1, P0>>,
+}
+
+/// Verifies that `leaf` is at the index of `attestation` in the tree of root `root`,
+/// recomputing the commitments of the nodes on the path, without R1CS.
+/// Fails with `Error::InvalidPath` if it is not.
+pub fn verify_attestation<
+    const L: usize,
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+>(
+    root: &CyclePoint<P0, P1>,
+    leaf: &Affine<P0>,
+    attestation: &InclusionAttestation<L, P0, P1>,
+    parameters: &SelRerandParameters<P0, P1>,
+) -> Result<(), Error> {
+    let height = attestation.even_levels.len() + attestation.odd_levels.len();
+    // The child index at each level, from the leaf, for an index below the capacity of the tree.
+    let mut index = attestation.index;
+    let mut child_indices = Vec::with_capacity(height);
+    for _ in 0..height {
+        child_indices.push(index % L);
+        index /= L;
+    }
+    if index != 0 {
+        return Err(Error::InvalidPath);
+    }
+
+    let mut even_levels = attestation.even_levels.iter();
+    let mut odd_levels = attestation.odd_levels.iter();
+    let mut node = *root;
+    for child_index in child_indices.into_iter().rev() {
+        node = match node {
+            CyclePoint::Even(parent) => CyclePoint::Odd(OddPoint(
+                even_levels.next().ok_or(Error::InvalidPath)?.child::<L>(
+                    &parent.0,
+                    child_index,
+                    &parameters.even_parameters,
+                )?,
+            )),
+            CyclePoint::Odd(parent) => CyclePoint::Even(EvenPoint(
+                odd_levels.next().ok_or(Error::InvalidPath)?.child::<L>(
+                    &parent.0,
+                    child_index,
+                    &parameters.odd_parameters,
+                )?,
+            )),
+        };
+    }
+    match node {
+        CyclePoint::Even(point) if point.0 == *leaf => Ok(()),
+        _ => Err(Error::InvalidPath),
+    }
+}
+
+/// A proof that a public commitment is a rerandomization of a leaf of a curve tree,
+/// produced by `CurveTree::prove_membership`.
+#[derive(Clone)]
+pub struct MembershipProof<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
+    pub path: SelectAndRerandomizePath<L, P0, P1>,
+    pub even_proof: R1CSProof<Affine<P0>>,
+    pub odd_proof: R1CSProof<Affine<P1>>,
+    pub parameters_fingerprint: ParametersFingerprint,
+    /// The number of leaves of the tree the proof was made against, bound into its transcripts, see `bind_leaf_count`:
+    /// the leaf is hidden among that many.
+    pub leaf_count: u64,
+}
+
+impl<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    > MembershipProof<L, P0, P1>
+{
+    /// The rerandomized leaf, which the proof shows to be a rerandomization of a leaf.
+    pub fn rerandomized_leaf(&self) -> Affine<P0> {
+        self.path.get_rerandomized_leaf()
+    }
+
+    /// The bytes of the path commitments, of each proof and of its inner product proof rounds, see `SizeReport`.
+    pub fn size_report(&self) -> SizeReport {
+        let mut report = SizeReport::new();
+        report.add_serialized("path commitments", &self.path);
+        report.add_proof("even proof", "even proof rounds", &self.even_proof);
+        report.add_proof("odd proof", "odd proof rounds", &self.odd_proof);
+        report.add_serialized("parameters fingerprint", &self.parameters_fingerprint);
+        report.add_serialized("leaf count", &self.leaf_count);
+        report
+    }
+
+    /// Verifies the proof against the tree, of which only the root and its children are read,
+    /// and returns the rerandomized leaf.
+    /// Fails with `Error::InvalidPath` if the proof claims another number of leaves than the tree has.
+    pub fn verify(
+        &self,
+        tree: &CurveTree<L, P0, P1>,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<Affine<P0>, Error> {
+        parameters.check_fingerprint(&self.parameters_fingerprint)?;
+        if !tree.is_valid_path(&self.path) {
+            return Err(Error::InvalidPath);
+        }
+        if self.leaf_count != tree.leaf_count() as u64 {
+            return Err(Error::InvalidPath);
+        }
+        let mut even_verifier = Verifier::new(Transcript::new(protocol::MEMBERSHIP));
+        let mut odd_verifier = Verifier::new(Transcript::new(protocol::MEMBERSHIP));
+        bind_leaf_count(
+            even_verifier.transcript(),
+            odd_verifier.transcript(),
+            self.leaf_count,
+        );
+        let rerandomized_leaf = tree.select_and_rerandomize_verifier_gadget(
+            &mut even_verifier,
+            &mut odd_verifier,
+            self.path.clone(),
+            parameters,
+        )?;
+        even_verifier.verify(
+            &self.even_proof,
+            &parameters.even_parameters.pc_gens,
+            &parameters.even_parameters.bp_gens,
+        )?;
+        odd_verifier.verify(
+            &self.odd_proof,
+            &parameters.odd_parameters.pc_gens,
+            &parameters.odd_parameters.bp_gens,
+        )?;
+        Ok(rerandomized_leaf)
+    }
+
+    /// Verifies the proof against one of `trees`, e.g. the trees of the recent roots a verifier accepts proofs against,
+    /// and returns the index of that tree in `trees` with the rerandomized leaf.
+    /// The number of leaves the proof claims declares the tree it was made against in the clear,
+    /// so the proof is verified once, against the last of the trees with that number of leaves, rather than against each.
+    /// Trees rather than roots are taken as the children of the root are read, see `verify`.
+    /// Fails with `Error::InvalidPath` if no tree has that number of leaves, e.g. the root left the window.
+    pub fn verify_against_roots(
+        &self,
+        trees: &[&CurveTree<L, P0, P1>],
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<(usize, Affine<P0>), Error> {
+        let index = trees
+            .iter()
+            .rposition(|tree| tree.leaf_count() as u64 == self.leaf_count)
+            .ok_or(Error::InvalidPath)?;
+        Ok((index, self.verify(trees[index], parameters)?))
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> CanonicalSerialize
+    for MembershipProof<L, P0, P1>
+{
+    fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        self.path.serialized_size(compress)
+            + self.even_proof.serialized_size(compress)
+            + self.odd_proof.serialized_size(compress)
+            + self.parameters_fingerprint.serialized_size(compress)
+            + self.leaf_count.serialized_size(compress)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), SerializationError> {
+        self.path.serialize_with_mode(&mut writer, compress)?;
+        self.even_proof.serialize_with_mode(&mut writer, compress)?;
+        self.odd_proof.serialize_with_mode(&mut writer, compress)?;
+        self.parameters_fingerprint
+            .serialize_with_mode(&mut writer, compress)?;
+        self.leaf_count.serialize_with_mode(&mut writer, compress)?;
+        Ok(())
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> Valid for MembershipProof<L, P0, P1> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> CanonicalDeserialize
+    for MembershipProof<L, P0, P1>
+{
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, SerializationError> {
+        deserialize_with_default_limits(reader, compress, validate)
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> DeserializeWithLimits
+    for MembershipProof<L, P0, P1>
+{
+    fn deserialize_with_limits<R: Read>(
+        mut reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+        limits: &DeserializeLimits,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            path: SelectAndRerandomizePath::<L, P0, P1>::deserialize_with_limits(
+                &mut reader,
+                compress,
+                validate,
+                limits,
+            )?,
+            even_proof: R1CSProof::<Affine<P0>>::deserialize_with_mode(
+                &mut reader,
+                compress,
+                validate,
+            )?,
+            odd_proof: R1CSProof::<Affine<P1>>::deserialize_with_mode(
+                &mut reader,
+                compress,
+                validate,
+            )?,
+            parameters_fingerprint: ParametersFingerprint::deserialize_with_mode(
+                &mut reader,
+                compress,
+                validate,
+            )?,
+            leaf_count: u64::deserialize_with_mode(&mut reader, compress, validate)?,
+        })
+    }
+}
+
+/// The witness of the membership of a leaf in a tree of a given root, returned by `CurveTree::prepare_membership`.
+///
+/// The witness is secret: there is no `Debug` and it is not serializable.
+#[cfg(feature = "prover")]
+#[derive(Clone)]
+pub struct PreparedMembershipWitness<
+    const L: usize,
+    P0: SWCurveConfig + Copy,
+    P1: SWCurveConfig + Copy,
+> {
+    witness: CurveTreeWitnessPath<L, P0, P1>,
+    leaf_blinding: EvenScalar<P0>,
+    root: CyclePoint<P0, P1>,
+}
+
+#[cfg(feature = "prover")]
+impl<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+    > PreparedMembershipWitness<L, P0, P1>
+{
+    /// The root of the tree the witness was prepared for.
+    pub fn root(&self) -> CyclePoint<P0, P1> {
+        self.root
+    }
+
+    /// Whether the witness is still that of `tree`, whose root changes with every insertion.
+    pub fn is_valid_for(&self, tree: &CurveTree<L, P0, P1>) -> bool {
+        match (self.root, tree.root()) {
+            (CyclePoint::Even(prepared), CyclePoint::Even(root)) => prepared == root,
+            (CyclePoint::Odd(prepared), CyclePoint::Odd(root)) => prepared == root,
+            _ => false,
+        }
+    }
+}
+
+/// A witness of a Curve Tree path including siblings of randomness.
+/// Contains all the information needed to prove the select and rerandomize relation.
+#[cfg(feature = "prover")]
+#[derive(Clone)]
+pub struct CurveTreeWitnessPath<const L: usize, P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy>
+{
+    // list of internal even nodes
+    pub even_nodes: Vec<CurveTreeWitness<L, P0, P1>>,
+    // list of internal odd nodes
+    pub odd_nodes: Vec<CurveTreeWitness<L, P1, P0>>,
+}
+
+#[cfg(feature = "prover")]
+impl<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+    > CurveTreeWitnessPath<L, P0, P1>
+{
+    fn root_is_even(&self) -> Result<bool, Error> {
+        if self.even_nodes.len() == self.odd_nodes.len() {
+            return Ok(true);
+        }
+        if self.even_nodes.len() + 1 == self.odd_nodes.len() {
+            return Ok(false);
+        }
+        Err(Error::inconsistent_witness(format!(
+            "A path has as many even as odd levels, or one odd level more, not {} and {}",
+            self.even_nodes.len(),
+            self.odd_nodes.len()
+        )))
+    }
+    /// Commits to the root and rerandomizations of the path to the leaf specified by `index`
+    /// and proves the Select and rerandomize relation for each level.
+    /// Returns the rerandomized commitments on the path to (and including) the selected leaf
+    /// and the rerandomization scalar of the selected leaf.
+    /// Unlike `CurveTree::select_and_rerandomize_prover_gadget`, it leaves binding the transcripts to the caller.
+    #[cfg(feature = "prover")]
+    pub fn select_and_rerandomize_prover_gadget<R: RngCore + CryptoRng>(
+        &self,
+        even_prover: &mut Prover<Transcript, Affine<P0>>,
+        odd_prover: &mut Prover<Transcript, Affine<P1>>,
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<(SelectAndRerandomizePath<L, P0, P1>, EvenScalar<P0>), Error> {
+        let root_is_even = self.root_is_even()?;
+        if self.odd_nodes.is_empty() {
+            return Err(Error::inconsistent_witness(
+                "A path ends in a leaf below an odd level",
+            ));
+        }
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!(
+            "select_and_rerandomize_prover",
+            even_levels = self.even_nodes.len(),
+            odd_levels = self.odd_nodes.len()
+        )
+        .entered();
+        // for each even internal node, there must be a rerandomization of a commitment in the odd curve
         let even_length = self.even_nodes.len();
         let mut odd_rerandomization_scalars: Vec<P1::ScalarField> = Vec::with_capacity(even_length);
         let mut odd_rerandomized_commitments: Vec<Affine<P1>> = Vec::with_capacity(even_length);
@@ -439,9 +2200,7 @@ impl<
             let blinding = parameters
                 .odd_parameters
                 .pc_gens
-                .B_blinding
-                .mul(rerandomization)
-                .into_affine();
+                .commit_blinding_only(rerandomization);
             odd_rerandomized_commitments.push((even.child_witness + blinding).into());
         }
 
@@ -451,15 +2210,15 @@ impl<
             let blinding = parameters
                 .even_parameters
                 .pc_gens
-                .B_blinding
-                .mul(rerandomization)
-                .into_affine();
+                .commit_blinding_only(rerandomization);
             even_rerandomized_commitments.push((odd.child_witness + blinding).into());
         }
 
         let prove_even = |prover: &mut Prover<Transcript, Affine<P0>>| {
+            // The children of a level, reused across the levels.
+            let mut children = Vec::with_capacity(L);
             for i in 0..even_length {
-                let parent_rerandomization = if self.root_is_even() {
+                let parent_rerandomization = if root_is_even {
                     if i == 0 {
                         // the parent is the the root and thus not rerandomized
                         F0::zero()
@@ -471,6 +2230,7 @@ impl<
                 };
                 self.even_nodes[i].single_level_select_and_rerandomize_prover_gadget(
                     prover,
+                    &mut children,
                     &parameters.even_parameters,
                     &parameters.odd_parameters,
                     parent_rerandomization,
@@ -481,8 +2241,9 @@ impl<
         #[cfg(not(feature = "parallel"))]
         prove_even(even_prover);
         let prove_odd = |prover: &mut Prover<Transcript, Affine<P1>>| {
+            let mut children = Vec::with_capacity(L);
             for i in 0..odd_length {
-                let parent_rerandomization = if !self.root_is_even() {
+                let parent_rerandomization = if !root_is_even {
                     if i == 0 {
                         // the parent is the the root and thus not rerandomized
                         F1::zero()
@@ -494,6 +2255,7 @@ impl<
                 };
                 self.odd_nodes[i].single_level_select_and_rerandomize_prover_gadget(
                     prover,
+                    &mut children,
                     &parameters.odd_parameters,
                     &parameters.even_parameters,
                     parent_rerandomization,
@@ -507,18 +2269,19 @@ impl<
         #[cfg(feature = "parallel")]
         rayon::join(|| prove_even(even_prover), || prove_odd(odd_prover));
 
-        (
+        Ok((
             SelectAndRerandomizePath {
                 even_commitments: even_rerandomized_commitments,
                 odd_commitments: odd_rerandomized_commitments,
             },
-            *even_rerandomization_scalars.last().unwrap(),
-        )
+            EvenScalar(*even_rerandomization_scalars.last().unwrap()),
+        ))
     }
 }
 
 /// A witness of a Curve Tree path including siblings of randomness.
 /// Contains the information needed to prove the single level select and rerandomize relation.
+#[cfg(feature = "prover")]
 #[derive(Copy, Clone)]
 pub struct CurveTreeWitness<const L: usize, P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy> {
     randomness: P0::ScalarField,
@@ -526,6 +2289,7 @@ pub struct CurveTreeWitness<const L: usize, P0: SWCurveConfig + Copy, P1: SWCurv
     child_witness: Affine<P1>,
 }
 
+#[cfg(feature = "prover")]
 impl<
         const L: usize,
         F: PrimeField,
@@ -533,36 +2297,40 @@ impl<
         P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = F> + Copy,
     > CurveTreeWitness<L, P0, P1>
 {
+    /// `children` is a scratch buffer for the children of the level, which callers proving several levels reuse
+    /// rather than allocating it at each level.
+    #[cfg(feature = "prover")]
     pub fn single_level_select_and_rerandomize_prover_gadget(
         &self,
         prover: &mut Prover<Transcript, Affine<P0>>,
+        children: &mut Vec<LinearCombination<P0::ScalarField>>,
         even_parameters: &SingleLayerParameters<P0>,
         odd_parameters: &SingleLayerParameters<P1>,
         parent_rerandomization_scalar: P0::ScalarField,
         child_rerandomization_scalar: P1::ScalarField,
     ) {
-        let children_vars = if parent_rerandomization_scalar.is_zero() {
-            self.siblings.map(constant).to_vec()
+        children.clear();
+        if parent_rerandomization_scalar.is_zero() {
+            children.extend(self.siblings.iter().copied().map(constant));
         } else {
             let (_, children_vars) = prover.commit_vec(
                 &self.siblings,
                 self.randomness + parent_rerandomization_scalar,
                 &even_parameters.bp_gens,
             );
-            children_vars
-                .iter()
-                .map(|var| LinearCombination::<P0::ScalarField>::from(*var))
-                .collect()
-        };
+            children.extend(children_vars.into_iter().map(LinearCombination::from));
+        }
         let child_commitment = self.child_witness;
-        let blinding = odd_parameters.pc_gens.B_blinding * child_rerandomization_scalar;
-        let rerandomized_child = child_commitment + blinding.into_affine();
+        let blinding = odd_parameters
+            .pc_gens
+            .commit_blinding_only(child_rerandomization_scalar);
+        let rerandomized_child = child_commitment + blinding;
 
         single_level_select_and_rerandomize(
             prover,
             odd_parameters,
             &rerandomized_child.into(),
-            children_vars,
+            children,
             Some(child_commitment),
             Some(child_rerandomization_scalar),
         );
@@ -571,28 +2339,143 @@ impl<
 
 type Children<const L: usize, P0, P1> = [Option<CurveTreeNode<L, P1, P0>>; L];
 
-// map L children to their x-coordinate with 0 representing the empty node.
-fn x_coordinates<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig>(
+// The commitments of L children, with none for the empty nodes.
+fn commitments<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig>(
     children: &Children<L, P0, P1>,
-) -> [P1::BaseField; L] {
+) -> [Option<&Affine<P1>>; L] {
     children
-        .iter()
-        .map(|opt| match opt {
-            None => P1::BaseField::zero(),
-            Some(child) => child.parent_commitment.x,
-        })
-        .collect::<Vec<_>>()
-        .try_into()
-        .unwrap()
+        .each_ref()
+        .map(|child| child.as_ref().map(|child| &child.parent_commitment))
+}
+
+// Leaves appended below a node, on the curve of the node or on the other curve, see `CurveTreeNode::insert`.
+#[derive(Clone, Copy)]
+enum NewLeaves<'a, P0: SWCurveConfig, P1: SWCurveConfig> {
+    Own(&'a [Affine<P0>]),
+    Other(&'a [Affine<P1>]),
+}
+
+impl<'a, P0: SWCurveConfig, P1: SWCurveConfig> NewLeaves<'a, P0, P1> {
+    fn len(&self) -> usize {
+        match self {
+            Self::Own(leaves) => leaves.len(),
+            Self::Other(leaves) => leaves.len(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The leaves as appended below the children of the node.
+    fn swap(self) -> NewLeaves<'a, P1, P0> {
+        match self {
+            Self::Own(leaves) => NewLeaves::Other(leaves),
+            Self::Other(leaves) => NewLeaves::Own(leaves),
+        }
+    }
+
+    fn split_at(self, mid: usize) -> (Self, Self) {
+        match self {
+            Self::Own(leaves) => {
+                let (left, right) = leaves.split_at(mid);
+                (Self::Own(left), Self::Own(right))
+            }
+            Self::Other(leaves) => {
+                let (left, right) = leaves.split_at(mid);
+                (Self::Other(left), Self::Other(right))
+            }
+        }
+    }
+}
+
+// map L children to their x-coordinate with 0 representing the empty node.
+fn x_coordinates<const L: usize, P: SWCurveConfig>(
+    children: [Option<&Affine<P>>; L],
+) -> [P::BaseField; L] {
+    children.map(|child| match child {
+        None => P::BaseField::zero(),
+        Some(commitment) => commitment.x,
+    })
+}
+
+// Reads the commitment of the child at `child_index` touching every child,
+// so that the arithmetic does not depend on the (secret) index.
+#[cfg(feature = "prover")]
+fn ct_child_commitment<const L: usize, P: SWCurveConfig>(
+    children: [Option<&Affine<P>>; L],
+    child_index: usize,
+) -> Affine<P> {
+    let mut x = P::BaseField::zero();
+    let mut y = P::BaseField::zero();
+    for (j, child) in children.iter().enumerate() {
+        let indicator: P::BaseField = ct_indicator(j, child_index);
+        if let Some(commitment) = child {
+            x += commitment.x * indicator;
+            y += commitment.y * indicator;
+        }
+    }
+    Affine::new_unchecked(x, y)
+}
+
+/// How a curve tree keeps the commitments of its nodes in memory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PointStorage {
+    /// Each node holds its commitment as an affine point.
+    #[default]
+    Affine,
+    /// The nodes below the root are held in one array of compressed commitments per level,
+    /// a quarter of the memory of the affine nodes for 2^18 leaves and a branching factor of 256.
+    /// The children of the nodes on the path of a proof are decompressed when the proof is made,
+    /// a square root per child, each level caching those of the last path proven.
+    /// See the `tree_storage` benchmark for the memory and the time of both storages.
+    Compressed,
+}
+
+// The nodes below a node.
+enum Descendants<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
+    Leaf,
+    Nodes(Box<Children<L, P0, P1>>),
+    // The level of the children, and the levels below it.
+    Compressed(Box<CompressedLevel<L, P1, P0>>),
+    // The descendants of a full node all of whose leaves are pruned, dropped by `CurveTree::prune`.
+    Pruned,
+}
+
+// The children are cloned straight into the box, see `combine_forest`.
+impl<const L: usize, P0: SWCurveConfig + Clone, P1: SWCurveConfig + Clone> Clone
+    for Descendants<L, P0, P1>
+{
+    fn clone(&self) -> Self {
+        match self {
+            Self::Leaf => Self::Leaf,
+            Self::Nodes(children) => Self::Nodes(
+                children
+                    .iter()
+                    .cloned()
+                    .collect::<Box<[_]>>()
+                    .try_into()
+                    .unwrap_or_else(|_| unreachable!("exactly L children")),
+            ),
+            Self::Compressed(level) => Self::Compressed(level.clone()),
+            Self::Pruned => Self::Pruned,
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct CurveTreeNode<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
     parent_commitment: Affine<P0>,
     randomness: P0::ScalarField,
-    children: Option<Box<Children<L, P0, P1>>>,
+    children: Descendants<L, P0, P1>,
     height: usize,
     elements: usize,
+    // The index of the leaves of the tree, kept by its root only.
+    leaf_index: Option<Arc<LeafIndex>>,
+    // The sink of the events of the tree, kept by its root only.
+    event_sink: Option<SharedEventSink>,
+    // The number of leaves under the previous roots of the tree, kept by its root only.
+    root_history: Option<RootHistory>,
 }
 
 impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> std::fmt::Debug
@@ -614,9 +2497,12 @@ impl<
         Self {
             parent_commitment: commitment,
             randomness: P0::ScalarField::zero(),
-            children: None,
+            children: Descendants::Leaf,
             height: 0,
             elements: 1,
+            leaf_index: None,
+            event_sink: None,
+            root_history: None,
         }
     }
 
@@ -626,31 +2512,161 @@ impl<
         (index % capacity) / child_capacity
     }
 
+    // The x-coordinates the commitment of the node commits to, none for a leaf or a node whose descendants were dropped.
+    fn children_x_coordinates(&self) -> Option<[P1::BaseField; L]> {
+        match &self.children {
+            Descendants::Leaf | Descendants::Pruned => None,
+            Descendants::Nodes(children) => Some(x_coordinates(commitments(children))),
+            Descendants::Compressed(level) => Some(level.x_coordinates(0)),
+        }
+    }
+
+    // Adds the leaves below the node, on the curve of the node to `leaves` and on the other curve to `other_leaves`,
+    // none for those dropped.
+    fn collect_leaves(
+        &self,
+        leaves: &mut Vec<Option<Affine<P0>>>,
+        other_leaves: &mut Vec<Option<Affine<P1>>>,
+    ) {
+        match &self.children {
+            Descendants::Leaf => leaves.push(Some(self.parent_commitment)),
+            Descendants::Nodes(children) => {
+                for child in children.iter().flatten() {
+                    child.collect_leaves(other_leaves, leaves);
+                }
+            }
+            Descendants::Compressed(level) => level.collect_leaves(other_leaves, leaves),
+            Descendants::Pruned if self.height.is_multiple_of(2) => {
+                leaves.extend(iter::repeat_n(None, self.elements))
+            }
+            Descendants::Pruned => other_leaves.extend(iter::repeat_n(None, self.elements)),
+        }
+    }
+
+    // Sets the leaf at `index` below the node, to `leaf` if it is on the curve of the node and to `other_leaf` otherwise,
+    // unless it was dropped.
+    fn stored_leaf(
+        &self,
+        index: usize,
+        leaf: &mut Option<Affine<P0>>,
+        other_leaf: &mut Option<Affine<P1>>,
+    ) {
+        match &self.children {
+            Descendants::Leaf => *leaf = Some(self.parent_commitment),
+            Descendants::Nodes(children) => {
+                if let Some(child) = &children[self.child_index(index)] {
+                    child.stored_leaf(index, other_leaf, leaf);
+                }
+            }
+            Descendants::Compressed(level) => {
+                level.stored_leaf(index % L.pow(self.height as u32), other_leaf, leaf)
+            }
+            Descendants::Pruned => {}
+        }
+    }
+
+    // The attempts of the permissible search of the node and of the nodes below it.
+    fn permissible_attempts(&self) -> u64 {
+        match &self.children {
+            Descendants::Leaf => 0,
+            Descendants::Pruned => permissible_attempts(&self.randomness),
+            Descendants::Nodes(children) => {
+                permissible_attempts(&self.randomness)
+                    + children
+                        .iter()
+                        .flatten()
+                        .map(|child| child.permissible_attempts())
+                        .sum::<u64>()
+            }
+            Descendants::Compressed(level) => {
+                permissible_attempts(&self.randomness) + level.permissible_attempts()
+            }
+        }
+    }
+
+    fn storage(&self) -> PointStorage {
+        match &self.children {
+            Descendants::Leaf | Descendants::Pruned => PointStorage::Affine,
+            Descendants::Nodes(children) => match &children[0] {
+                Some(child) => child.storage(),
+                None => PointStorage::Affine,
+            },
+            Descendants::Compressed(_) => PointStorage::Compressed,
+        }
+    }
+
+    #[cfg(feature = "prover")]
     fn select_and_rerandomize_prover_witness(
         &self,
         index: usize,
         even_nodes: &mut Vec<CurveTreeWitness<L, P0, P1>>,
         odd_nodes: &mut Vec<CurveTreeWitness<L, P1, P0>>,
     ) {
-        if let Some(children) = &self.children {
-            let child_index = self.child_index(index);
-            let child = match &children[self.child_index(index)] {
-                None => panic!(
-                    "Child index out of bounds. Height: {}, Index: {}, Local index: {}",
-                    self.height, index, child_index
-                ),
-                Some(child) => child,
-            };
-            let siblings = x_coordinates(children);
+        match &self.children {
+            Descendants::Leaf => {}
+            Descendants::Pruned => unreachable!("the leaf at the index is not pruned"),
+            Descendants::Nodes(children) => {
+                let child_index = self.child_index(index);
+                let child = match &children[child_index] {
+                    // the leaves are filled from the left and the index is below their number
+                    None => unreachable!(
+                        "Child index out of bounds. Height: {}, Index: {}, Local index: {}",
+                        self.height, index, child_index
+                    ),
+                    Some(child) => child,
+                };
 
-            even_nodes.push(CurveTreeWitness {
-                randomness: self.randomness,
-                siblings,
-                child_witness: child.parent_commitment,
-            });
+                even_nodes.push(CurveTreeWitness {
+                    randomness: self.randomness,
+                    siblings: x_coordinates(commitments(children)),
+                    child_witness: ct_child_commitment(commitments(children), child_index),
+                });
+
+                // recursively add the remaining path
+                child.select_and_rerandomize_prover_witness(index, odd_nodes, even_nodes);
+            }
+            Descendants::Compressed(level) => level.select_and_rerandomize_prover_witness(
+                0,
+                self.randomness,
+                index % L.pow(self.height as u32),
+                even_nodes,
+                odd_nodes,
+            ),
+        }
+    }
 
-            // recursively add the remaining path
-            child.select_and_rerandomize_prover_witness(index, odd_nodes, even_nodes);
+    // Adds the children and the randomness of the node, and of the nodes below it on the path to the leaf at `index`.
+    fn attestation_levels(
+        &self,
+        index: usize,
+        even_levels: &mut Vec<AttestationLevel<P0, P1>>,
+        odd_levels: &mut Vec<AttestationLevel<P1, P0>>,
+    ) {
+        match &self.children {
+            Descendants::Leaf => {}
+            Descendants::Pruned => unreachable!("the leaf at the index is not pruned"),
+            Descendants::Nodes(children) => {
+                even_levels.push(AttestationLevel {
+                    children: children
+                        .iter()
+                        .flatten()
+                        .map(|child| child.parent_commitment)
+                        .collect(),
+                    blinding_offset: self.randomness,
+                });
+                // the leaves are filled from the left and the index is below their number
+                let child = children[self.child_index(index)]
+                    .as_ref()
+                    .expect("the index is of a leaf");
+                child.attestation_levels(index, odd_levels, even_levels);
+            }
+            Descendants::Compressed(level) => level.attestation_levels(
+                0,
+                self.randomness,
+                index % L.pow(self.height as u32),
+                even_levels,
+                odd_levels,
+            ),
         }
     }
 
@@ -660,60 +2676,894 @@ impl<
     fn combine(
         children: Vec<CurveTreeNode<L, P1, P0>>,
         parameters: &SingleLayerParameters<P0>,
-    ) -> Self {
-        if children.len() > L {
-            panic!(
-                "Cannot combine more than the branching factor: {} into one node.",
-                L
-            )
-        };
+    ) -> Result<Self, Error> {
+        // only called with the single root of a tree
+        assert!(
+            children.len() <= L,
+            "Cannot combine more than the branching factor: {} into one node.",
+            L
+        );
+        Ok(Self::combine_forest(children, parameters)?.pop().unwrap())
+    }
 
-        let mut elements = 0;
-        let mut cs: Vec<Option<CurveTreeNode<L, P1, P0>>> = Vec::with_capacity(L);
-        for c in children {
-            elements += c.elements;
-            cs.push(Some(c));
+    // Combine a forest of level d nodes into level d+1 nodes, each of up to L consecutive nodes.
+    // The permissible commitments of the new nodes are searched in a batch,
+    // which takes a field inversion per round of the search instead of one per node and attempt.
+    fn combine_forest(
+        forest: Vec<CurveTreeNode<L, P1, P0>>,
+        parameters: &SingleLayerParameters<P0>,
+    ) -> Result<Vec<Self>, Error> {
+        let mut forest = forest.into_iter().peekable();
+        let mut nodes = Vec::new();
+        while forest.peek().is_some() {
+            let mut elements = 0;
+            let mut cs: Vec<Option<CurveTreeNode<L, P1, P0>>> = Vec::with_capacity(L);
+            for c in forest.by_ref().take(L) {
+                elements += c.elements;
+                cs.push(Some(c));
+            }
+            // Let the rest of the children be dummy elements.
+            while cs.len() < L {
+                cs.push(None)
+            }
+            // Boxed straight from the vector: an array of L nodes is too large for the stack of small threads.
+            let children: Box<Children<L, P0, P1>> = cs
+                .into_boxed_slice()
+                .try_into()
+                .unwrap_or_else(|_| unreachable!("exactly L children"));
+            nodes.push((children, elements));
         }
-        // Let the rest of the children be dummy elements.
-        while cs.len() < L {
-            cs.push(None)
+
+        // commit to the children's x-coordinates with randomness zero, then increment randomness to find permissible points.
+        let x_coordinates: Vec<_> = nodes
+            .iter()
+            .map(|(children, _)| x_coordinates(commitments(children)))
+            .collect();
+        let commitments = parameters.permissible_commitments(&x_coordinates, 0)?; // todo index
+
+        Ok(nodes
+            .into_iter()
+            .zip(commitments)
+            .map(|((children, elements), (c, r))| {
+                let height = if let Some(c) = &children[0] {
+                    c.height + 1
+                } else {
+                    1
+                };
+                Self {
+                    parent_commitment: c,
+                    randomness: r,
+                    children: Descendants::Nodes(children),
+                    height,
+                    elements,
+                    leaf_index: None,
+                    event_sink: None,
+                    root_history: None,
+                }
+            })
+            .collect())
+    }
+
+    // The nodes of height `height` above `leaves`, each above up to L^height consecutive leaves,
+    // built a level at a time as `combine_forest` builds them.
+    fn forest(
+        height: usize,
+        leaves: NewLeaves<'_, P0, P1>,
+        parameters: &SingleLayerParameters<P0>,
+        child_parameters: &SingleLayerParameters<P1>,
+    ) -> Result<Vec<Self>, Error> {
+        if height == 0 {
+            return match leaves {
+                NewLeaves::Own(leaves) => Ok(leaves.iter().map(|leaf| Self::leaf(*leaf)).collect()),
+                NewLeaves::Other(_) => {
+                    unreachable!("the leaves are on the curve of the nodes of height 0")
+                }
+            };
         }
-        let children: [Option<CurveTreeNode<L, P1, P0>>; L] = cs.try_into().unwrap();
-        let height = if let Some(c) = &children[0] {
-            c.height + 1
-        } else {
-            1
+        let children = CurveTreeNode::<L, P1, P0>::forest(
+            height - 1,
+            leaves.swap(),
+            child_parameters,
+            parameters,
+        )?;
+        Self::combine_forest(children, parameters)
+    }
+
+    // Commits the node to the x-coordinates of its children as `combine_forest` does,
+    // returning the attempts of the permissible search.
+    fn commit(&mut self, parameters: &SingleLayerParameters<P0>) -> Result<u64, Error> {
+        let x_coordinates = self
+            .children_x_coordinates()
+            .expect("an inner node has children");
+        let (commitment, randomness) = parameters
+            .permissible_commitments(&[x_coordinates], 0)? // todo index
+            .pop()
+            .expect("a commitment per vector");
+        self.parent_commitment = commitment;
+        self.randomness = randomness;
+        Ok(permissible_attempts(&randomness))
+    }
+
+    // Appends `leaves` below the node, which has room for them: the children the leaves are appended to and the node
+    // are committed again, the children of the remaining leaves are built with `forest`, the other children are kept.
+    // Returns the attempts of the permissible search of the nodes committed.
+    fn insert(
+        &mut self,
+        leaves: NewLeaves<'_, P0, P1>,
+        parameters: &SingleLayerParameters<P0>,
+        child_parameters: &SingleLayerParameters<P1>,
+    ) -> Result<u64, Error> {
+        if leaves.is_empty() {
+            return Ok(0);
+        }
+        let mut attempts = 0;
+        match &mut self.children {
+            // a full node
+            Descendants::Leaf | Descendants::Pruned => {
+                unreachable!("the node has room for the leaves")
+            }
+            Descendants::Nodes(children) => {
+                let child_capacity = L.pow((self.height - 1) as u32);
+                let mut position = self.elements / child_capacity;
+                let mut rest = leaves.swap();
+                let filled = self.elements % child_capacity;
+                if filled != 0 {
+                    let (head, tail) = rest.split_at(rest.len().min(child_capacity - filled));
+                    // the leaves are filled from the left
+                    let child = children[position]
+                        .as_mut()
+                        .expect("a partially filled child");
+                    attempts += child.insert(head, child_parameters, parameters)?;
+                    rest = tail;
+                    position += 1;
+                }
+                let new_children = CurveTreeNode::<L, P1, P0>::forest(
+                    self.height - 1,
+                    rest,
+                    child_parameters,
+                    parameters,
+                )?;
+                for child in new_children {
+                    attempts += child.permissible_attempts();
+                    children[position] = Some(child);
+                    position += 1;
+                }
+            }
+            Descendants::Compressed(level) => {
+                attempts +=
+                    level.append(self.elements, leaves.swap(), child_parameters, parameters)?;
+            }
+        }
+        self.elements += leaves.len();
+        Ok(attempts + self.commit(parameters)?)
+    }
+
+    // Appends `leaves` below the root of a tree, which has room for them, as `insert`, keeping `storage`:
+    // with `PointStorage::Compressed`, the roots added above the compressed levels by `CurveTree::increase_height`
+    // become compressed levels too.
+    fn with_appended(
+        mut self,
+        leaves: NewLeaves<'_, P0, P1>,
+        storage: PointStorage,
+        parameters: &SingleLayerParameters<P0>,
+        child_parameters: &SingleLayerParameters<P1>,
+    ) -> Result<(Self, u64), Error> {
+        match storage {
+            PointStorage::Affine => {
+                let attempts = self.insert(leaves, parameters, child_parameters)?;
+                Ok((self, attempts))
+            }
+            PointStorage::Compressed => {
+                let first = self.elements;
+                let mut level = self.into_level();
+                let attempts = level.append(first, leaves, parameters, child_parameters)?;
+                Ok((level.into_root(first + leaves.len()), attempts))
+            }
+        }
+    }
+
+    // The level of the node alone, for a node with compressed storage, or above a single such node.
+    fn into_level(self) -> CompressedLevel<L, P0, P1> {
+        let children = match self.children {
+            Descendants::Leaf => None,
+            Descendants::Compressed(level) => Some(level),
+            Descendants::Pruned => unreachable!("the levels of a compressed tree drop their nodes"),
+            // A root added by `CurveTree::increase_height`, of a single child.
+            Descendants::Nodes(mut children) => {
+                let child = children[0].take().expect("a child");
+                debug_assert!(children.iter().all(Option::is_none));
+                Some(Box::new(child.into_level()))
+            }
         };
-        // commit to the children's x-coordinates with randomness zero, then increment randomness to find permissible point.
-        let (c, r) = parameters.permissible_commitment(
-            &x_coordinates(&children),
-            P0::ScalarField::zero(),
-            0,
-        ); // todo index
+        CompressedLevel {
+            commitments: CompressedLevel::<L, P0, P1>::compress(iter::once(self.parent_commitment)),
+            randomness: match children {
+                None => Vec::new(),
+                Some(_) => vec![self.randomness],
+            },
+            children,
+            height: self.height,
+            dropped: Vec::new(),
+            #[cfg(feature = "prover")]
+            cache: Mutex::new(None),
+        }
+    }
+
+    // Drops the descendants of the nodes at `collapsed` below the node, by height and position among the nodes
+    // of their height, keeping the commitments of those nodes, see `CurveTree::prune`.
+    fn drop_descendants(&mut self, collapsed: &[(usize, usize)]) {
+        if self.height == 0 {
+            return;
+        }
+        // The nodes below each child.
+        let mut below = vec![Vec::new(); L];
+        for &(height, position) in collapsed {
+            if height < self.height {
+                below[self.child_index(position * L.pow(height as u32))].push((height, position));
+            }
+        }
+        match &mut self.children {
+            Descendants::Leaf | Descendants::Pruned => {}
+            Descendants::Compressed(level) => level.drop_descendants(collapsed),
+            Descendants::Nodes(children) => {
+                for (child, collapsed) in children.iter_mut().zip(below) {
+                    let Some(child) = child else { continue };
+                    let is_collapsed = collapsed.iter().any(|(height, _)| *height == child.height);
+                    // The levels of a compressed node drop the nodes below it instead.
+                    if is_collapsed && !matches!(child.children, Descendants::Compressed(_)) {
+                        child.children = Descendants::Pruned;
+                    } else if !collapsed.is_empty() {
+                        child.drop_descendants(&collapsed);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The decompressed commitments of the children of a node, with none for the empty nodes.
+#[cfg(feature = "prover")]
+type DecompressedChildren<const L: usize, P> = [Option<Affine<P>>; L];
+
+// The nodes of one level of a tree with `PointStorage::Compressed`, from left to right.
+// The children of the node at position `p` of the level above are those at positions `p * L..(p + 1) * L`.
+struct CompressedLevel<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
+    // The concatenated compressed commitments of the nodes.
+    commitments: Vec<u8>,
+    // The randomness of the commitments, empty for the leaves.
+    randomness: Vec<P0::ScalarField>,
+    // The level below, none for the leaves.
+    children: Option<Box<CompressedLevel<L, P1, P0>>>,
+    height: usize,
+    // The ranges of positions whose nodes were dropped, below nodes all of whose leaves are pruned, see `CurveTree::prune`,
+    // in increasing order, each with the number of positions dropped before it. The other nodes are stored without gaps.
+    dropped: Vec<(Range<usize>, usize)>,
+    // The position of the last parent whose children were decompressed, and its children.
+    #[cfg(feature = "prover")]
+    cache: Mutex<Option<(usize, DecompressedChildren<L, P0>)>>,
+}
+
+// The cache is not cloned, the clone decompresses its children again.
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> Clone for CompressedLevel<L, P0, P1> {
+    fn clone(&self) -> Self {
+        Self {
+            commitments: self.commitments.clone(),
+            randomness: self.randomness.clone(),
+            children: self.children.clone(),
+            height: self.height,
+            dropped: self.dropped.clone(),
+            #[cfg(feature = "prover")]
+            cache: Mutex::new(None),
+        }
+    }
+}
+
+impl<
+        const L: usize,
+        P0: SWCurveConfig + Copy,
+        P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = P0::BaseField> + Copy,
+    > CompressedLevel<L, P0, P1>
+{
+    fn point_size() -> usize {
+        Affine::<P0>::identity().compressed_size()
+    }
+
+    fn compress(points: impl ExactSizeIterator<Item = Affine<P0>>) -> Vec<u8> {
+        let mut commitments = Vec::with_capacity(points.len() * Self::point_size());
+        for point in points {
+            point
+                .serialize_compressed(&mut commitments)
+                .expect("serializing to a vector does not fail");
+        }
+        commitments
+    }
+
+    // The commitments are assumed to be permissible
+    fn leaves(set: &[Affine<P0>]) -> Self {
         Self {
-            parent_commitment: c,
-            randomness: r,
+            commitments: Self::compress(set.iter().copied()),
+            randomness: Vec::new(),
+            children: None,
+            height: 0,
+            dropped: Vec::new(),
+            #[cfg(feature = "prover")]
+            cache: Mutex::new(None),
+        }
+    }
+
+    // Combine the nodes of `children` into nodes of up to L consecutive nodes, as `CurveTreeNode::combine_forest`.
+    fn combine(
+        children: CompressedLevel<L, P1, P0>,
+        parameters: &SingleLayerParameters<P0>,
+    ) -> Result<Self, Error> {
+        let x_coordinates: Vec<_> = (0..children.len().div_ceil(L))
+            .map(|parent| children.x_coordinates(parent))
+            .collect();
+        let (commitments, randomness): (Vec<_>, Vec<_>) = parameters
+            .permissible_commitments(&x_coordinates, 0)? // todo index
+            .into_iter()
+            .unzip();
+        Ok(Self {
+            commitments: Self::compress(commitments.into_iter()),
+            randomness,
+            height: children.height + 1,
             children: Some(Box::new(children)),
-            height,
+            dropped: Vec::new(),
+            #[cfg(feature = "prover")]
+            cache: Mutex::new(None),
+        })
+    }
+
+    // Appends `leaves` below the level, whose nodes are above `first` leaves, as `CurveTreeNode::insert`:
+    // the nodes from that of the first new leaf are committed again, the nodes before it are kept.
+    // Returns the attempts of the permissible search of the nodes committed.
+    fn append(
+        &mut self,
+        first: usize,
+        leaves: NewLeaves<'_, P0, P1>,
+        parameters: &SingleLayerParameters<P0>,
+        child_parameters: &SingleLayerParameters<P1>,
+    ) -> Result<u64, Error> {
+        // The children of the last parent change.
+        #[cfg(feature = "prover")]
+        {
+            *self.cache.get_mut().unwrap_or_else(|e| e.into_inner()) = None;
+        }
+        let children = match &mut self.children {
+            None => {
+                match leaves {
+                    NewLeaves::Own(leaves) => self
+                        .commitments
+                        .extend(Self::compress(leaves.iter().copied())),
+                    NewLeaves::Other(_) => {
+                        unreachable!("the leaves are on the curve of the nodes of height 0")
+                    }
+                }
+                return Ok(0);
+            }
+            Some(children) => children,
+        };
+        let mut attempts = children.append(first, leaves.swap(), child_parameters, parameters)?;
+        let start = first / L.pow(self.height as u32);
+        let x_coordinates: Vec<_> = (start..children.len().div_ceil(L))
+            .map(|parent| children.x_coordinates(parent))
+            .collect();
+        let (commitments, randomness): (Vec<_>, Vec<_>) = parameters
+            .permissible_commitments(&x_coordinates, 0)? // todo index
+            .into_iter()
+            .unzip();
+        attempts += randomness.iter().map(permissible_attempts).sum::<u64>();
+        // The nodes from `start` are not dropped, the nodes above them have room for leaves.
+        self.commitments
+            .truncate(self.index(start) * Self::point_size());
+        self.commitments
+            .extend(Self::compress(commitments.into_iter()));
+        self.randomness.truncate(self.index(start));
+        self.randomness.extend(randomness);
+        Ok(attempts)
+    }
+
+    // The root of a tree of `elements` leaves, for a level of a single node.
+    fn into_root(self, elements: usize) -> CurveTreeNode<L, P0, P1> {
+        CurveTreeNode {
+            parent_commitment: self.point(0),
+            randomness: self.randomness.first().copied().unwrap_or_else(Zero::zero),
+            children: match self.children {
+                None => Descendants::Leaf,
+                Some(children) => Descendants::Compressed(children),
+            },
+            height: self.height,
             elements,
+            leaf_index: None,
+            event_sink: None,
+            root_history: None,
+        }
+    }
+
+    // The number of positions of the level, those of the dropped nodes included.
+    fn len(&self) -> usize {
+        self.commitments.len() / Self::point_size() + self.dropped_count()
+    }
+
+    fn dropped_count(&self) -> usize {
+        self.dropped
+            .last()
+            .map_or(0, |(range, before)| before + range.len())
+    }
+
+    fn is_dropped(&self, position: usize) -> bool {
+        let next = self
+            .dropped
+            .partition_point(|(range, _)| range.end <= position);
+        self.dropped
+            .get(next)
+            .is_some_and(|(range, _)| range.contains(&position))
+    }
+
+    // The index among the nodes stored of the node at `position`, which is not dropped.
+    fn index(&self, position: usize) -> usize {
+        let next = self
+            .dropped
+            .partition_point(|(range, _)| range.end <= position);
+        position
+            - self
+                .dropped
+                .get(next)
+                .map_or_else(|| self.dropped_count(), |(_, before)| *before)
+    }
+
+    fn encoding(&self, position: usize) -> &[u8] {
+        let size = Self::point_size();
+        let index = self.index(position);
+        &self.commitments[index * size..(index + 1) * size]
+    }
+
+    fn point(&self, position: usize) -> Affine<P0> {
+        // The encodings are of points of the curve, written by `compress`.
+        Affine::deserialize_with_mode(self.encoding(position), Compress::Yes, Validate::No)
+            .expect("the encodings are of points")
+    }
+
+    // Adds the leaves below the level, as `CurveTreeNode::collect_leaves`.
+    fn collect_leaves(
+        &self,
+        leaves: &mut Vec<Option<Affine<P0>>>,
+        other_leaves: &mut Vec<Option<Affine<P1>>>,
+    ) {
+        match &self.children {
+            None => leaves.extend(
+                (0..self.len())
+                    .map(|position| (!self.is_dropped(position)).then(|| self.point(position))),
+            ),
+            Some(level) => level.collect_leaves(other_leaves, leaves),
+        }
+    }
+
+    // Sets the leaf at `index` below the level, as `CurveTreeNode::stored_leaf`.
+    fn stored_leaf(
+        &self,
+        index: usize,
+        leaf: &mut Option<Affine<P0>>,
+        other_leaf: &mut Option<Affine<P1>>,
+    ) {
+        match &self.children {
+            None if !self.is_dropped(index) => *leaf = Some(self.point(index)),
+            None => {}
+            Some(level) => level.stored_leaf(index, other_leaf, leaf),
+        }
+    }
+
+    // Drops the nodes of the level and of the levels below it which are below the nodes at `collapsed`,
+    // as `CurveTreeNode::drop_descendants`, storing the other nodes again without gaps.
+    fn drop_descendants(&mut self, collapsed: &[(usize, usize)]) {
+        let mut ranges: Vec<Range<usize>> = self
+            .dropped
+            .iter()
+            .map(|(range, _)| range.clone())
+            .chain(
+                collapsed
+                    .iter()
+                    .filter(|(height, _)| *height > self.height)
+                    .map(|&(height, position)| {
+                        let span = L.pow((height - self.height) as u32);
+                        position * span..(position + 1) * span
+                    }),
+            )
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        if !merged
+            .iter()
+            .eq(self.dropped.iter().map(|(range, _)| range))
+        {
+            let len = self.len();
+            let kept = len - merged.iter().map(Range::len).sum::<usize>();
+            let mut commitments = Vec::with_capacity(kept * Self::point_size());
+            let mut randomness = Vec::with_capacity(kept.min(self.randomness.len()));
+            let mut next = 0;
+            for range in merged.iter().chain(iter::once(&(len..len))) {
+                for position in next..range.start {
+                    commitments.extend_from_slice(self.encoding(position));
+                    if let Some(r) = self.randomness.get(self.index(position)) {
+                        randomness.push(*r);
+                    }
+                }
+                next = range.end;
+            }
+            let mut before = 0;
+            self.dropped = merged
+                .into_iter()
+                .map(|range| {
+                    let entry = (range.clone(), before);
+                    before += range.len();
+                    entry
+                })
+                .collect();
+            self.commitments = commitments;
+            self.randomness = randomness;
+        }
+        if let Some(level) = &mut self.children {
+            level.drop_descendants(collapsed);
+        }
+    }
+
+    // The attempts of the permissible search of the nodes of the level and of the levels below it.
+    fn permissible_attempts(&self) -> u64 {
+        self.randomness
+            .iter()
+            .map(permissible_attempts)
+            .sum::<u64>()
+            + self
+                .children
+                .as_ref()
+                .map_or(0, |level| level.permissible_attempts())
+    }
+
+    fn children(&self, parent: usize) -> std::ops::Range<usize> {
+        parent * L..self.len().min((parent + 1) * L)
+    }
+
+    // The x-coordinates of the children of the node at `parent` in the level above,
+    // read from the encodings without decompressing them.
+    fn x_coordinates(&self, parent: usize) -> [P0::BaseField; L] {
+        let mut x_coordinates = [P0::BaseField::zero(); L];
+        for (x, position) in x_coordinates.iter_mut().zip(self.children(parent)) {
+            // The flags of the identity come with an x-coordinate of zero.
+            (*x, _) = P0::BaseField::deserialize_with_flags::<_, SWFlags>(self.encoding(position))
+                .expect("the encodings are of points");
+        }
+        x_coordinates
+    }
+
+    // The children of the node at `parent` in the level above, decompressed once for consecutive proofs through the node.
+    #[cfg(feature = "prover")]
+    fn decompressed_children(&self, parent: usize) -> DecompressedChildren<L, P0> {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        match &*cache {
+            Some((cached, children)) if *cached == parent => *children,
+            _ => {
+                let mut children = [None; L];
+                for (child, position) in children.iter_mut().zip(self.children(parent)) {
+                    *child = Some(self.point(position));
+                }
+                *cache = Some((parent, children));
+                children
+            }
+        }
+    }
+
+    // Adds the witness of the node at `parent` in the level above, whose randomness is `parent_randomness`,
+    // and of the nodes below it on the path to the leaf at `index`.
+    #[cfg(feature = "prover")]
+    fn select_and_rerandomize_prover_witness(
+        &self,
+        parent: usize,
+        parent_randomness: P1::ScalarField,
+        index: usize,
+        parent_nodes: &mut Vec<CurveTreeWitness<L, P1, P0>>,
+        nodes: &mut Vec<CurveTreeWitness<L, P0, P1>>,
+    ) {
+        let position = index / L.pow(self.height as u32);
+        // the leaves are filled from the left and the index is below their number
+        assert!(
+            self.children(parent).contains(&position),
+            "Child index out of bounds. Height: {}, Index: {}, Position: {}",
+            self.height + 1,
+            index,
+            position
+        );
+        let children = self.decompressed_children(parent);
+        let children = children.each_ref().map(Option::as_ref);
+        parent_nodes.push(CurveTreeWitness {
+            randomness: parent_randomness,
+            siblings: x_coordinates(children),
+            child_witness: ct_child_commitment(children, position - parent * L),
+        });
+
+        // recursively add the remaining path
+        if let Some(level) = &self.children {
+            level.select_and_rerandomize_prover_witness(
+                position,
+                self.randomness[self.index(position)],
+                index,
+                nodes,
+                parent_nodes,
+            );
+        }
+    }
+
+    // Adds the level of the node at `parent` in the level above, whose randomness is `parent_randomness`,
+    // and of the nodes below it on the path to the leaf at `index`, as `CurveTreeNode::attestation_levels`.
+    fn attestation_levels(
+        &self,
+        parent: usize,
+        parent_randomness: P1::ScalarField,
+        index: usize,
+        parent_levels: &mut Vec<AttestationLevel<P1, P0>>,
+        levels: &mut Vec<AttestationLevel<P0, P1>>,
+    ) {
+        parent_levels.push(AttestationLevel {
+            children: self
+                .children(parent)
+                .map(|position| self.point(position))
+                .collect(),
+            blinding_offset: parent_randomness,
+        });
+        if let Some(level) = &self.children {
+            let position = index / L.pow(self.height as u32);
+            level.attestation_levels(
+                position,
+                self.randomness[self.index(position)],
+                index,
+                levels,
+                parent_levels,
+            );
         }
     }
 }
 
+/// The hash of the parameters of a proof, see `SelRerandParameters::fingerprint`.
+pub type ParametersFingerprint = [u8; 32];
+
+/// The parameters of both curves, shared so that `swapped` does not copy the generators.
 pub struct SelRerandParameters<P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy> {
-    pub even_parameters: SingleLayerParameters<P0>,
-    pub odd_parameters: SingleLayerParameters<P1>,
+    pub even_parameters: Arc<SingleLayerParameters<P0>>,
+    pub odd_parameters: Arc<SingleLayerParameters<P1>>,
+    /// The range of the values proven with the parameters, `ValuePolicy::default()` unless set by `with_value_policy`.
+    pub value_policy: ValuePolicy,
 }
 
 impl<P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy> SelRerandParameters<P0, P1> {
-    pub fn new<R: Rng>(
+    #[cfg(feature = "prover")]
+    pub fn new(even_generators_length: usize, odd_generators_length: usize) -> Self {
+        Self::for_version(
+            even_generators_length,
+            odd_generators_length,
+            FormatVersion::CURRENT,
+        )
+    }
+
+    /// The parameters of `version`, e.g. to verify the proofs of an earlier version,
+    /// which differ in their Pedersen generators, see `FormatVersion::pedersen_gens`.
+    #[cfg(feature = "prover")]
+    pub fn for_version(
         even_generators_length: usize,
         odd_generators_length: usize,
-        rng: &mut R,
+        version: FormatVersion,
     ) -> Self {
+        let parameters = SelRerandParameters {
+            even_parameters: Arc::new(SingleLayerParameters::<P0>::for_version::<P1>(
+                even_generators_length,
+                version,
+            )),
+            odd_parameters: Arc::new(SingleLayerParameters::<P1>::for_version::<P0>(
+                odd_generators_length,
+                version,
+            )),
+            value_policy: ValuePolicy::default(),
+        };
+        debug_assert!(parameters.self_check().is_ok());
+        parameters
+    }
+
+    /// The same generators proving values under `policy`, shared as in `swapped`, with another fingerprint.
+    /// Fails with `Error::ParameterMismatch` if the balances of the policy may overflow the scalar field of `P0`,
+    /// see `ValuePolicy::validate`.
+    pub fn with_value_policy(&self, policy: ValuePolicy) -> Result<Self, Error> {
+        policy.validate::<P0::ScalarField>()?;
+        Ok(Self {
+            even_parameters: self.even_parameters.clone(),
+            odd_parameters: self.odd_parameters.clone(),
+            value_policy: policy,
+        })
+    }
+
+    /// The same parameters with the curves swapped, for trees with their leaves on `P1`.
+    pub fn swapped(&self) -> SelRerandParameters<P1, P0> {
         SelRerandParameters {
-            even_parameters: SingleLayerParameters::<P0>::new::<_, P1>(even_generators_length, rng),
-            odd_parameters: SingleLayerParameters::<P1>::new::<_, P0>(odd_generators_length, rng),
+            even_parameters: self.odd_parameters.clone(),
+            odd_parameters: self.even_parameters.clone(),
+            value_policy: self.value_policy,
+        }
+    }
+
+    /// The hash of what the proofs made with the parameters of both curves depend on.
+    ///
+    /// The select and rerandomize gadgets absorb it in both transcripts, see `bind`, so proofs only verify
+    /// with the parameters they were made with. Membership proofs and pours also carry it,
+    /// and are rejected with `Error::ParameterFingerprintMismatch` before being verified with other parameters,
+    /// e.g. generators of another capacity or another `ValuePolicy`.
+    pub fn fingerprint(&self) -> ParametersFingerprint {
+        let mut bytes = protocol::PARAMETERS_FINGERPRINT_DOMAIN.to_vec();
+        self.even_parameters.append_fingerprint_bytes(&mut bytes);
+        self.odd_parameters.append_fingerprint_bytes(&mut bytes);
+        // The parameters of the default policy keep the fingerprint they had before policies,
+        // so the proofs made with them, e.g. the checked-in test vectors, still verify.
+        if self.value_policy != ValuePolicy::default() {
+            self.value_policy.append_fingerprint_bytes(&mut bytes);
+        }
+        let mut fingerprint = [0u8; 32];
+        fingerprint.copy_from_slice(&Sha3_256::digest(&bytes));
+        fingerprint
+    }
+
+    /// The format version of the proofs made with the parameters: the version whose Pedersen generators
+    /// both curves have, see `FormatVersion::pedersen_gens`, else `FormatVersion::CURRENT`.
+    /// Parameters of an earlier version, e.g. read from the bytes written by an earlier build,
+    /// thus keep verifying the proofs made with them.
+    pub fn format_version(&self) -> FormatVersion {
+        FormatVersion::ALL
+            .into_iter()
+            .find(|version| {
+                let even = version.pedersen_gens::<Affine<P0>>();
+                let odd = version.pedersen_gens::<Affine<P1>>();
+                self.even_parameters.pc_gens.B == even.B
+                    && self.even_parameters.pc_gens.B_blinding == even.B_blinding
+                    && self.odd_parameters.pc_gens.B == odd.B
+                    && self.odd_parameters.pc_gens.B_blinding == odd.B_blinding
+            })
+            .unwrap_or(FormatVersion::CURRENT)
+    }
+
+    /// Appends the fingerprint of the parameters and their format version, see `format_version`
+    /// and `FormatVersion::bind`, to both transcripts.
+    /// The prover and the verifier must bind at the same point of their constraint systems.
+    pub fn bind(&self, even_transcript: &mut Transcript, odd_transcript: &mut Transcript) {
+        let fingerprint = self.fingerprint();
+        even_transcript.append_message(protocol::PARAMETERS_FINGERPRINT, &fingerprint);
+        odd_transcript.append_message(protocol::PARAMETERS_FINGERPRINT, &fingerprint);
+        self.format_version().bind(even_transcript, odd_transcript);
+    }
+
+    /// Fails with `Error::ParameterFingerprintMismatch` if `fingerprint`, carried by a proof,
+    /// is not the fingerprint of these parameters.
+    pub fn check_fingerprint(&self, fingerprint: &ParametersFingerprint) -> Result<(), Error> {
+        if *fingerprint != self.fingerprint() {
+            return Err(Error::ParameterFingerprintMismatch);
+        }
+        Ok(())
+    }
+
+    /// Checks the invariants the proofs rely on and the types cannot express, e.g. for parameters read at startup.
+    ///
+    /// Fails with `Error::ParameterMismatch` if the curves do not form a cycle, the base field of each
+    /// being the scalar field of the other, if the parameters of either curve break the invariants of
+    /// `SingleLayerParameters::self_check`, if a base appears twice, see `assert_independent_bases`,
+    /// or if the value policy is invalid, see `ValuePolicy::validate`. The deserialization of parameters runs it, and so do the constructors in debug builds.
+    pub fn self_check(&self) -> Result<(), Error> {
+        if P0::BaseField::extension_degree() != 1
+            || P1::BaseField::extension_degree() != 1
+            || P0::BaseField::characteristic() != P1::ScalarField::characteristic()
+            || P1::BaseField::characteristic() != P0::ScalarField::characteristic()
+        {
+            return Err(Error::parameter_mismatch(
+                "the curves of the parameters do not form a cycle",
+            ));
         }
+        self.even_parameters.self_check()?;
+        self.odd_parameters.self_check()?;
+        assert_independent_bases(self)?;
+        self.value_policy.validate::<P0::ScalarField>()
+    }
+}
+
+/// Fails with `Error::ParameterMismatch` if a base of either curve appears twice among the Pedersen generators
+/// and the generators of the first party, comparing their compressed encodings.
+///
+/// Commitments mix the Pedersen generators with the vector generators, e.g. a coin commits to its value and
+/// public key with G generators and is blinded with `B_blinding`, and a fee commits to its value with `B`.
+/// Binding relies on no relation between them being known: a base appearing twice is one. All the generators up to the capacity are compared,
+/// which covers those of any `CommitmentLayout` and of the nodes of trees. `PedersenGens::default` draws its
+/// generators from a domain apart from the vector generators, so this only fails for parameters built or read otherwise.
+pub fn assert_independent_bases<P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy>(
+    parameters: &SelRerandParameters<P0, P1>,
+) -> Result<(), Error> {
+    parameters.even_parameters.check_distinct_bases()?;
+    parameters.odd_parameters.check_distinct_bases()
+}
+
+/// The Pedersen generators, the capacity of the generators and the constants of the universal hash of each curve,
+/// then the value policy. The generators and the tables of the rerandomization are derived again when reading.
+impl<P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy> CanonicalSerialize
+    for SelRerandParameters<P0, P1>
+{
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.even_parameters
+            .serialize_layer(&mut writer, compress)?;
+        self.odd_parameters.serialize_layer(&mut writer, compress)?;
+        let policy = &self.value_policy;
+        [policy.bits, policy.max_inputs, policy.max_outputs].serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.even_parameters.layer_size(compress) + self.odd_parameters.layer_size(compress) + 3
+    }
+}
+
+impl<P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy> Valid for SelRerandParameters<P0, P1> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.self_check()
+            .map_err(|_| SerializationError::InvalidData)
+    }
+}
+
+impl<P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy> CanonicalDeserialize
+    for SelRerandParameters<P0, P1>
+{
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        deserialize_with_default_limits(reader, compress, validate)
+    }
+}
+
+impl<P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy> DeserializeWithLimits
+    for SelRerandParameters<P0, P1>
+{
+    /// Fails with `Error::LimitExceeded` if the capacity of the generators of either curve exceeds
+    /// `limits.max_generators`, and as `self_check` for parameters breaking its invariants, whatever `validate`.
+    fn deserialize_with_limits<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+        limits: &DeserializeLimits,
+    ) -> Result<Self, Error> {
+        let even_parameters = SingleLayerParameters::<P0>::deserialize_layer(
+            &mut reader,
+            compress,
+            validate,
+            limits.max_generators,
+        )?;
+        let odd_parameters = SingleLayerParameters::<P1>::deserialize_layer(
+            &mut reader,
+            compress,
+            validate,
+            limits.max_generators,
+        )?;
+        let [bits, max_inputs, max_outputs] =
+            <[u8; 3]>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let parameters = SelRerandParameters {
+            even_parameters: Arc::new(even_parameters),
+            odd_parameters: Arc::new(odd_parameters),
+            value_policy: ValuePolicy {
+                bits,
+                max_inputs,
+                max_outputs,
+            },
+        };
+        parameters.self_check()?;
+        Ok(parameters)
     }
 }
@@ -0,0 +1,225 @@
+//! Scalars and points typed by the curve of the cycle they belong to.
+//!
+//! The scalars of the even curve and of the signature curve of the payments are often elements of the same field,
+//! as are the coordinates of one curve and the scalars of the other: a scalar of one curve type-checks wherever a scalar
+//! of another is expected, e.g. the randomness of a public key added to the blinding of a coin commitment.
+//! `EvenScalar`, `OddScalar`, `EvenPoint` and `OddPoint` tell them apart, and the cycle is only crossed by the named
+//! conversions: the coordinates of a point of one curve are scalars of the other.
+//!
+//! The roots of the trees, `CyclePoint`, and the blindings the tree and coin provers take and return are typed,
+//! the gadgets of the levels take the fields themselves and the wrappers are unwrapped at their boundary.
+//! The trees whose leaves are on the odd curve are trees of the swapped cycle, see `LeafCurveTree`:
+//! their scalars and points change parity by the `swapped` conversions.
+
+use crate::blinding::BlindingScalar;
+use crate::convert::{even_base_to_odd_scalar, fe_from_fe_checked, odd_base_to_even_scalar};
+use crate::ct::{field_ct_eq, point_ct_eq};
+use crate::error::Error;
+
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ff::PrimeField;
+use std::ops::{Add, Neg, Sub};
+
+macro_rules! typed_scalar {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        /// Equality is in constant time and there is no `Debug`: the scalar may be secret.
+        #[repr(transparent)]
+        pub struct $name<P: SWCurveConfig>(pub P::ScalarField);
+
+        impl<P: SWCurveConfig> Clone for $name<P> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<P: SWCurveConfig> Copy for $name<P> {}
+
+        impl<P: SWCurveConfig> PartialEq for $name<P> {
+            fn eq(&self, other: &Self) -> bool {
+                field_ct_eq(&self.0, &other.0).into()
+            }
+        }
+
+        impl<P: SWCurveConfig> Eq for $name<P> {}
+
+        impl<P: SWCurveConfig> Add for $name<P> {
+            type Output = Self;
+
+            fn add(self, other: Self) -> Self {
+                Self(self.0 + other.0)
+            }
+        }
+
+        impl<P: SWCurveConfig> Sub for $name<P> {
+            type Output = Self;
+
+            fn sub(self, other: Self) -> Self {
+                Self(self.0 - other.0)
+            }
+        }
+
+        impl<P: SWCurveConfig> Neg for $name<P> {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                Self(-self.0)
+            }
+        }
+
+        impl<P: SWCurveConfig> BlindingScalar for $name<P> {
+            type Field = P::ScalarField;
+
+            fn from_field(field: P::ScalarField) -> Self {
+                Self(field)
+            }
+
+            fn into_field(self) -> P::ScalarField {
+                self.0
+            }
+        }
+    };
+}
+
+macro_rules! typed_point {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[repr(transparent)]
+        pub struct $name<P: SWCurveConfig>(pub Affine<P>);
+
+        impl<P: SWCurveConfig> Clone for $name<P> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<P: SWCurveConfig> Copy for $name<P> {}
+
+        /// Compares in constant time, the point may be derived from secrets.
+        impl<P: SWCurveConfig> PartialEq for $name<P> {
+            fn eq(&self, other: &Self) -> bool {
+                point_ct_eq(&self.0, &other.0).into()
+            }
+        }
+
+        impl<P: SWCurveConfig> Eq for $name<P> {}
+
+        impl<P: SWCurveConfig> std::fmt::Debug for $name<P> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_tuple(stringify!($name)).field(&self.0).finish()
+            }
+        }
+    };
+}
+
+typed_scalar!(
+    /// A scalar of the even curve of a cycle, e.g. a committed value or the blinding of a coin commitment.
+    EvenScalar
+);
+typed_scalar!(
+    /// A scalar of the odd curve of a cycle.
+    OddScalar
+);
+typed_point!(
+    /// A point of the even curve of a cycle, e.g. a coin commitment or a leaf of a curve tree.
+    EvenPoint
+);
+typed_point!(
+    /// A point of the odd curve of a cycle, e.g. a commitment to the children of an odd layer.
+    OddPoint
+);
+
+macro_rules! swapped {
+    ($even:ident, $odd:ident) => {
+        impl<P: SWCurveConfig> $even<P> {
+            /// The same element, of the odd curve of the cycle with its curves swapped.
+            pub fn swapped(self) -> $odd<P> {
+                $odd(self.0)
+            }
+        }
+
+        impl<P: SWCurveConfig> $odd<P> {
+            /// The same element, of the even curve of the cycle with its curves swapped.
+            pub fn swapped(self) -> $even<P> {
+                $even(self.0)
+            }
+        }
+    };
+}
+
+swapped!(EvenScalar, OddScalar);
+swapped!(EvenPoint, OddPoint);
+
+impl<P0: SWCurveConfig> EvenScalar<P0> {
+    /// The scalar of the odd curve of the same integer, see `fe_from_fe_checked`.
+    pub fn to_odd<P1: SWCurveConfig>(self) -> Result<OddScalar<P1>, Error> {
+        fe_from_fe_checked(&self.0).map(OddScalar)
+    }
+}
+
+impl<P1: SWCurveConfig> OddScalar<P1> {
+    /// The scalar of the even curve of the same integer, see `fe_from_fe_checked`.
+    pub fn to_even<P0: SWCurveConfig>(self) -> Result<EvenScalar<P0>, Error> {
+        fe_from_fe_checked(&self.0).map(EvenScalar)
+    }
+}
+
+impl<P0: SWCurveConfig> EvenPoint<P0>
+where
+    P0::BaseField: PrimeField,
+{
+    /// The x-coordinate as a scalar of the odd curve, the value committed to by the layer above the point.
+    pub fn x<P1: SWCurveConfig>(&self) -> Result<OddScalar<P1>, Error> {
+        even_base_to_odd_scalar::<P0, P1>(&self.0.x).map(OddScalar)
+    }
+}
+
+impl<P1: SWCurveConfig> OddPoint<P1>
+where
+    P1::BaseField: PrimeField,
+{
+    /// The x-coordinate as a scalar of the even curve.
+    pub fn x<P0: SWCurveConfig>(&self) -> Result<EvenScalar<P0>, Error> {
+        odd_base_to_even_scalar::<P0, P1>(&self.0.x).map(EvenScalar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blinding::{BlindingAccumulator, INITIAL, PERMISSIBLE_OFFSET};
+    use ark_ec::AffineRepr;
+    use ark_pallas::{Affine as PallasAffine, PallasConfig};
+    use ark_std::UniformRand;
+    use ark_vesta::VestaConfig;
+
+    #[test]
+    fn test_crossing_the_cycle() {
+        let mut rng = rand::thread_rng();
+        let point = EvenPoint(PallasAffine::rand(&mut rng));
+        let x: OddScalar<VestaConfig> = point.x().unwrap();
+        assert_eq!(x.0, *point.0.x().unwrap());
+
+        let scalar = EvenScalar::<PallasConfig>(ark_pallas::Fr::from(5u64));
+        let odd: OddScalar<VestaConfig> = scalar.to_odd().unwrap();
+        assert!(odd.to_even::<PallasConfig>().unwrap() == scalar);
+        // The modulus of the base field of Pallas is below that of its scalar field.
+        assert!((-scalar).to_odd::<VestaConfig>().is_err());
+
+        // The trees of the swapped cycle, with their leaves on the odd curve, relabel without converting.
+        assert!(scalar.swapped().swapped() == scalar);
+        assert_eq!(point.swapped().0, point.0);
+    }
+
+    // A scalar of the odd curve cannot be added to the blinding of an even commitment without a named conversion.
+    #[test]
+    fn test_typed_blinding() {
+        let mut rng = rand::thread_rng();
+        let initial = EvenScalar::<PallasConfig>(ark_pallas::Fr::rand(&mut rng));
+        let offset = EvenScalar(ark_pallas::Fr::rand(&mut rng));
+        let accumulator =
+            BlindingAccumulator::from_term(INITIAL, initial).with_term(PERMISSIBLE_OFFSET, offset);
+        assert!(accumulator.total() == initial + offset);
+        assert!(accumulator.total() - offset == initial);
+    }
+}
@@ -0,0 +1,249 @@
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use sha3::{Digest, Sha3_256};
+use std::fmt;
+use std::str::FromStr;
+
+/// Number of bytes of the hash of an encoding printed as its fingerprint.
+pub const FINGERPRINT_BYTES: usize = 8;
+
+/// Errors of decoding a hex encoded value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodingError {
+    /// Hex encodes a byte with two digits.
+    OddLength,
+    /// The byte at `index` is not a hex digit.
+    InvalidCharacter { index: usize },
+    /// The bytes are not the compressed encoding of a value of the type, e.g. not a point on the curve.
+    InvalidEncoding,
+    /// The bytes continue after the encoding of the value.
+    TrailingBytes,
+}
+
+impl fmt::Display for DecodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OddLength => write!(f, "odd number of hex digits"),
+            Self::InvalidCharacter { index } => write!(f, "invalid hex digit at {}", index),
+            Self::InvalidEncoding => write!(f, "invalid encoding"),
+            Self::TrailingBytes => write!(f, "trailing bytes after the encoding"),
+        }
+    }
+}
+
+impl std::error::Error for DecodingError {}
+
+/// Lower case hex of the compressed encoding of `t`.
+pub fn to_hex<T: CanonicalSerialize>(t: &T) -> String {
+    let mut bytes = Vec::with_capacity(t.compressed_size());
+    t.serialize_compressed(&mut bytes)
+        .expect("serializing to a vector does not fail");
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decodes a value from the hex of its compressed encoding, as produced by [`to_hex`].
+/// Both upper and lower case digits are accepted, points are checked to be on the curve,
+/// and encodings other than the one [`to_hex`] produces, e.g. with unused bits set, are rejected.
+pub fn from_hex<T: CanonicalSerialize + CanonicalDeserialize>(s: &str) -> Result<T, DecodingError> {
+    let bytes = decode_hex(s)?;
+    let mut reader = bytes.as_slice();
+    let t = T::deserialize_compressed(&mut reader).map_err(|_| DecodingError::InvalidEncoding)?;
+    if !reader.is_empty() {
+        return Err(DecodingError::TrailingBytes);
+    }
+    if to_hex(&t) != s.to_ascii_lowercase() {
+        return Err(DecodingError::InvalidEncoding);
+    }
+    Ok(t)
+}
+
+pub(crate) fn decode_hex(s: &str) -> Result<Vec<u8>, DecodingError> {
+    if !s.len().is_multiple_of(2) {
+        return Err(DecodingError::OddLength);
+    }
+    let digit = |index: usize| {
+        (s.as_bytes()[index] as char)
+            .to_digit(16)
+            .map(|d| d as u8)
+            .ok_or(DecodingError::InvalidCharacter { index })
+    };
+    (0..s.len())
+        .step_by(2)
+        .map(|i| Ok((digit(i)? << 4) | digit(i + 1)?))
+        .collect()
+}
+
+/// A short identifier of `t`: the hex of the first [`FINGERPRINT_BYTES`] bytes of the hash of its encoding.
+pub fn fingerprint<T: CanonicalSerialize>(t: &T) -> String {
+    let mut bytes = Vec::with_capacity(t.compressed_size());
+    t.serialize_compressed(&mut bytes)
+        .expect("serializing to a vector does not fail");
+    Sha3_256::digest(&bytes)[..FINGERPRINT_BYTES]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Defines a wrapper giving a public value the textual representations used by tooling:
+/// `Display` prints the fingerprint, or the full hex with the alternate flag `{:#}`,
+/// `Debug` prints the fingerprint with the name of the wrapper, and `FromStr` parses the full hex.
+macro_rules! identifier {
+    ($(#[$doc:meta])* $name:ident<$param:ident: $bound:path>($inner:ty)) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $name<$param: $bound>(pub $inner);
+
+        impl<$param: $bound> $name<$param> {
+            /// Lower case hex of the compressed encoding.
+            pub fn to_hex(&self) -> String {
+                to_hex(&self.0)
+            }
+
+            /// Decodes the hex of the compressed encoding.
+            pub fn from_hex(s: &str) -> Result<Self, DecodingError> {
+                from_hex(s).map(Self)
+            }
+
+            /// The short identifier printed by `Display`.
+            pub fn fingerprint(&self) -> String {
+                fingerprint(&self.0)
+            }
+        }
+
+        impl<$param: $bound> From<$inner> for $name<$param> {
+            fn from(inner: $inner) -> Self {
+                Self(inner)
+            }
+        }
+
+        impl<$param: $bound> fmt::Display for $name<$param> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                if f.alternate() {
+                    write!(f, "{}", self.to_hex())
+                } else {
+                    write!(f, "{}", self.fingerprint())
+                }
+            }
+        }
+
+        impl<$param: $bound> fmt::Debug for $name<$param> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), self.fingerprint())
+            }
+        }
+
+        impl<$param: $bound> FromStr for $name<$param> {
+            type Err = DecodingError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::from_hex(s)
+            }
+        }
+    };
+}
+
+identifier!(
+    /// The root of a curve tree.
+    Root<P: SWCurveConfig>(Affine<P>)
+);
+identifier!(
+    /// A commitment, such as a leaf of a curve tree or a rerandomized leaf.
+    Commitment<P: SWCurveConfig>(Affine<P>)
+);
+identifier!(
+    /// A spending tag, revealed when a coin is spent.
+    Tag<F: PrimeField>(F)
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::UniformRand;
+
+    type PallasConfig = ark_pallas::PallasConfig;
+    type PallasA = ark_pallas::Affine;
+    type PallasScalar = ark_pallas::Fr;
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = rand::thread_rng();
+        let root = Root::<PallasConfig>(PallasA::rand(&mut rng));
+        let tag = Tag(PallasScalar::rand(&mut rng));
+
+        assert_eq!(root.to_hex().len(), 2 * root.0.compressed_size());
+        assert_eq!(root.to_hex().parse(), Ok(root));
+        assert_eq!(format!("{:#}", root).parse(), Ok(root));
+        assert_eq!(Root::from_hex(&root.to_hex().to_uppercase()), Ok(root));
+        assert_eq!(Tag::from_hex(&tag.to_hex()), Ok(tag));
+        assert_eq!(from_hex::<PallasA>(&to_hex(&root.0)), Ok(root.0));
+
+        assert_eq!(format!("{}", root).len(), 2 * FINGERPRINT_BYTES);
+        assert_eq!(format!("{}", root), root.fingerprint());
+        assert_eq!(
+            format!("{:?}", Commitment(root.0)),
+            format!("Commitment({})", root.fingerprint())
+        );
+        assert_ne!(
+            root.fingerprint(),
+            Root::<PallasConfig>(PallasA::rand(&mut rng)).fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_malformed_hex() {
+        let mut rng = rand::thread_rng();
+        let hex = Commitment::<PallasConfig>(PallasA::rand(&mut rng)).to_hex();
+        let parse = |s: &str| s.parse::<Commitment<PallasConfig>>();
+
+        assert_eq!(parse(&hex[1..]), Err(DecodingError::OddLength));
+        assert_eq!(parse("0"), Err(DecodingError::OddLength));
+        assert_eq!(parse(""), Err(DecodingError::InvalidEncoding));
+        assert_eq!(
+            parse(&hex[..hex.len() - 2]),
+            Err(DecodingError::InvalidEncoding)
+        );
+        assert_eq!(
+            parse(&format!("{}00", hex)),
+            Err(DecodingError::TrailingBytes)
+        );
+        for (index, bad) in [(0, "g"), (17, "z"), (63, " "), (40, "é")] {
+            let mut s = hex.clone();
+            s.replace_range(index..index + 1, bad);
+            if s.len().is_multiple_of(2) {
+                assert_eq!(parse(&s), Err(DecodingError::InvalidCharacter { index }));
+            } else {
+                assert_eq!(parse(&s), Err(DecodingError::OddLength));
+            }
+        }
+
+        // x-coordinates of which no point is on the curve
+        let mut invalid = 0;
+        for x in 0..32u8 {
+            let mut s = format!("{:02x}", x);
+            s.push_str(&"00".repeat(hex.len() / 2 - 1));
+            if let Err(e) = parse(&s) {
+                assert_eq!(e, DecodingError::InvalidEncoding);
+                invalid += 1;
+            }
+        }
+        assert!(invalid > 0);
+
+        // flipped digits almost never give a point
+        let mut flipped = 0;
+        for index in 0..hex.len() {
+            let mut s = hex.clone().into_bytes();
+            s[index] = if s[index] == b'0' { b'1' } else { b'0' };
+            let s = String::from_utf8(s).unwrap();
+            match parse(&s) {
+                Ok(c) => assert_ne!(c.to_hex(), hex),
+                Err(e) => {
+                    // also covers the unused bits of the flags
+                    assert_eq!(e, DecodingError::InvalidEncoding);
+                    flipped += 1;
+                }
+            }
+        }
+        assert!(flipped > 0);
+    }
+}
@@ -0,0 +1,469 @@
+use bulletproofs::r1cs::R1CSError;
+
+use crate::convert::{fe_from_le_bytes_reduced, fe_from_u64};
+use crate::error::Error;
+use crate::layout::{CommitmentLayout, VALUE};
+use crate::limits::{
+    deserialize_vec, deserialize_with_default_limits, DeserializeLimits, DeserializeWithLimits,
+};
+use crate::protocol;
+use crate::single_level_select_and_rerandomize::SingleLayerParameters;
+
+#[cfg(feature = "prover")]
+use ark_ec::CurveGroup;
+use ark_ec::{
+    models::short_weierstrass::SWCurveConfig,
+    short_weierstrass::{Affine, Projective},
+};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use ark_serialize::{Compress, Valid, Validate};
+#[cfg(feature = "prover")]
+use ark_std::UniformRand;
+use merlin::Transcript;
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+
+/// The generators of a commitment to a value: the value is the coordinate at `value_index` of the vector committed
+/// to with `generators`, the other coordinates, e.g. the tag of a coin, are hidden as well,
+/// and the blinding is on the blinding generator.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ValueGenerators<P: SWCurveConfig> {
+    generators: Vec<Affine<P>>,
+    value_index: usize,
+    blinding: Affine<P>,
+}
+
+impl<P: SWCurveConfig + Copy> ValueGenerators<P> {
+    /// The generators of the Pedersen commitments to a value alone, `pc_gens.commit(value, blinding)`,
+    /// e.g. the fee commitments of pours.
+    pub fn pedersen(parameters: &SingleLayerParameters<P>) -> Self {
+        Self {
+            generators: vec![parameters.pc_gens.B],
+            value_index: 0,
+            blinding: parameters.pc_gens.B_blinding,
+        }
+    }
+
+    /// The generators of the vector commitments of `layout` as `SingleLayerParameters::commit` makes them,
+    /// e.g. coin commitments, of which the value is the `VALUE` slot.
+    pub fn layout(
+        parameters: &SingleLayerParameters<P>,
+        layout: &CommitmentLayout,
+    ) -> Result<Self, Error> {
+        let value_index = layout.index(VALUE)?;
+        if layout.width() > parameters.bp_gens.gens_capacity {
+            return Err(Error::parameter_mismatch(format!(
+                "Not enough generators to commit to the vector: {} needed, {} available",
+                layout.width(),
+                parameters.bp_gens.gens_capacity
+            )));
+        }
+        Ok(Self {
+            generators: parameters.bp_gens.share(0).G_slice(layout.width()).to_vec(),
+            value_index,
+            blinding: parameters.pc_gens.B_blinding,
+        })
+    }
+
+    // The commitment to `vector` and `blinding`, of vectors of the length of the generators.
+    fn commit(&self, vector: &[P::ScalarField], blinding: P::ScalarField) -> Projective<P> {
+        self.generators
+            .iter()
+            .zip(vector)
+            .fold(self.blinding * blinding, |sum, (generator, coordinate)| {
+                sum + *generator * coordinate
+            })
+    }
+}
+
+/// The opening of a commitment made with `ValueGenerators`: the committed vector, whose coordinate
+/// at the value index of the generators is the value, and the blinding.
+#[derive(Clone)]
+pub struct ValueOpening<F: PrimeField> {
+    pub vector: Vec<F>,
+    pub blinding: F,
+}
+
+impl<F: PrimeField> ValueOpening<F> {
+    /// The opening of a Pedersen commitment to `value`, see `ValueGenerators::pedersen`.
+    pub fn pedersen(value: u64, blinding: F) -> Self {
+        Self {
+            vector: vec![fe_from_u64(value)],
+            blinding,
+        }
+    }
+}
+
+/// A proof that two commitments hide the same value, possibly with different blindings and generators,
+/// e.g. a coin being spent and a coin someone else minted, or a coin and a Pedersen commitment to its value.
+///
+/// A Schnorr proof of knowledge of the openings of both commitments, with a single nonce and response
+/// for the value coordinate of both, which reveals nothing else about the openings.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EqualValueProof<P: SWCurveConfig> {
+    nonce_commitments: [Affine<P>; 2],
+    value_response: P::ScalarField,
+    // The responses of the coordinates of each opening but the value, then of its blinding.
+    responses: [Vec<P::ScalarField>; 2],
+}
+
+fn append<T: CanonicalSerialize>(transcript: &mut Transcript, label: &'static [u8], value: &T) {
+    let mut bytes = Vec::with_capacity(value.compressed_size());
+    value
+        .serialize_compressed(&mut bytes)
+        .expect("serializing to a vector does not fail");
+    transcript.append_message(label, &bytes);
+}
+
+// Absorbs the statement and the nonce commitments, and returns the challenge.
+fn equal_value_challenge<P: SWCurveConfig>(
+    statements: [(&ValueGenerators<P>, &Affine<P>); 2],
+    nonce_commitments: &[Affine<P>; 2],
+) -> P::ScalarField {
+    let mut transcript = Transcript::new(protocol::EQUAL_VALUE);
+    for ((generators, commitment), nonce_commitment) in statements.iter().zip(nonce_commitments) {
+        let generators_label = protocol::EQUAL_VALUE_GENERATORS;
+        append(&mut transcript, generators_label, &generators.generators);
+        append(
+            &mut transcript,
+            generators_label,
+            &(generators.value_index as u64),
+        );
+        append(&mut transcript, generators_label, &generators.blinding);
+        append(
+            &mut transcript,
+            protocol::EQUAL_VALUE_COMMITMENT,
+            *commitment,
+        );
+        append(
+            &mut transcript,
+            protocol::EQUAL_VALUE_NONCE_COMMITMENT,
+            nonce_commitment,
+        );
+    }
+    let mut challenge = [0u8; 64];
+    transcript.challenge_bytes(protocol::EQUAL_VALUE_CHALLENGE, &mut challenge);
+    fe_from_le_bytes_reduced(&challenge)
+}
+
+/// Proves that `commitment_a`, opened by `opening_a` with `generators_a`, hides the same value as `commitment_b`,
+/// opened by `opening_b` with `generators_b`.
+/// Fails with `Error::InconsistentWitness` if an opening does not open its commitment, or the values differ.
+#[cfg(feature = "prover")]
+pub fn prove_equal_value<P: SWCurveConfig + Copy, R: RngCore + CryptoRng>(
+    generators_a: &ValueGenerators<P>,
+    commitment_a: &Affine<P>,
+    opening_a: &ValueOpening<P::ScalarField>,
+    generators_b: &ValueGenerators<P>,
+    commitment_b: &Affine<P>,
+    opening_b: &ValueOpening<P::ScalarField>,
+    rng: &mut R,
+) -> Result<EqualValueProof<P>, Error> {
+    let statements = [
+        (generators_a, commitment_a, opening_a),
+        (generators_b, commitment_b, opening_b),
+    ];
+    for (generators, commitment, opening) in statements {
+        if opening.vector.len() != generators.generators.len()
+            || generators.commit(&opening.vector, opening.blinding) != *commitment
+        {
+            return Err(Error::inconsistent_witness(
+                "The opening does not open the commitment",
+            ));
+        }
+    }
+    if opening_a.vector[generators_a.value_index] != opening_b.vector[generators_b.value_index] {
+        return Err(Error::inconsistent_witness(
+            "The commitments do not hide the same value",
+        ));
+    }
+
+    let value_nonce = P::ScalarField::rand(rng);
+    let nonces = statements.map(|(generators, _, opening)| {
+        let mut vector: Vec<_> = (0..opening.vector.len())
+            .map(|_| P::ScalarField::rand(rng))
+            .collect();
+        vector[generators.value_index] = value_nonce;
+        (vector, P::ScalarField::rand(rng))
+    });
+    let nonce_commitments = [0, 1].map(|side| {
+        let (vector, blinding) = &nonces[side];
+        statements[side].0.commit(vector, *blinding).into_affine()
+    });
+    let challenge = equal_value_challenge(
+        [(generators_a, commitment_a), (generators_b, commitment_b)],
+        &nonce_commitments,
+    );
+    let responses = [0, 1].map(|side| {
+        let (generators, _, opening) = statements[side];
+        let (vector, blinding) = &nonces[side];
+        vector
+            .iter()
+            .zip(&opening.vector)
+            .enumerate()
+            .filter(|(index, _)| *index != generators.value_index)
+            .map(|(_, (nonce, coordinate))| *nonce + challenge * coordinate)
+            .chain([*blinding + challenge * opening.blinding])
+            .collect()
+    });
+    Ok(EqualValueProof {
+        nonce_commitments,
+        value_response: value_nonce + challenge * opening_a.vector[generators_a.value_index],
+        responses,
+    })
+}
+
+impl<P: SWCurveConfig + Copy> EqualValueProof<P> {
+    /// Verifies that `commitment_a`, made with `generators_a`, and `commitment_b`, made with `generators_b`,
+    /// hide the same value.
+    pub fn verify(
+        &self,
+        generators_a: &ValueGenerators<P>,
+        commitment_a: &Affine<P>,
+        generators_b: &ValueGenerators<P>,
+        commitment_b: &Affine<P>,
+    ) -> Result<(), Error> {
+        let statements = [(generators_a, commitment_a), (generators_b, commitment_b)];
+        for ((generators, _), responses) in statements.iter().zip(&self.responses) {
+            if responses.len() != generators.generators.len() {
+                return Err(Error::shape_mismatch(format!(
+                    "The proof has {} responses for a commitment to {} coordinates",
+                    responses.len(),
+                    generators.generators.len()
+                )));
+            }
+        }
+        let challenge = equal_value_challenge(statements, &self.nonce_commitments);
+        for (side, (generators, commitment)) in statements.into_iter().enumerate() {
+            let responses = &self.responses[side];
+            let (blinding, others) = responses.split_last().expect("there is a value coordinate");
+            let mut others = others.iter();
+            let vector: Vec<_> = (0..generators.generators.len())
+                .map(|index| match index == generators.value_index {
+                    true => self.value_response,
+                    false => *others.next().expect("a response per coordinate"),
+                })
+                .collect();
+            if generators.commit(&vector, *blinding)
+                != self.nonce_commitments[side] + *commitment * challenge
+            {
+                return Err(Error::R1CS(R1CSError::VerificationError));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<P: SWCurveConfig> CanonicalSerialize for EqualValueProof<P> {
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.nonce_commitments.serialized_size(compress)
+            + self.value_response.serialized_size(compress)
+            + self.responses[0].serialized_size(compress)
+            + self.responses[1].serialized_size(compress)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.nonce_commitments
+            .serialize_with_mode(&mut writer, compress)?;
+        self.value_response
+            .serialize_with_mode(&mut writer, compress)?;
+        self.responses[0].serialize_with_mode(&mut writer, compress)?;
+        self.responses[1].serialize_with_mode(&mut writer, compress)?;
+        Ok(())
+    }
+}
+
+impl<P: SWCurveConfig> Valid for EqualValueProof<P> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl<P: SWCurveConfig> CanonicalDeserialize for EqualValueProof<P> {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        deserialize_with_default_limits(reader, compress, validate)
+    }
+}
+
+impl<P: SWCurveConfig> DeserializeWithLimits for EqualValueProof<P> {
+    /// Caps the responses of each commitment at `limits.max_arity`.
+    fn deserialize_with_limits<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+        limits: &DeserializeLimits,
+    ) -> Result<Self, Error> {
+        let nonce_commitments =
+            <[Affine<P>; 2]>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let value_response =
+            P::ScalarField::deserialize_with_mode(&mut reader, compress, validate)?;
+        let mut responses = || {
+            deserialize_vec(
+                &mut reader,
+                compress,
+                validate,
+                limits.max_arity,
+                "the number of responses of an equal value proof",
+            )
+        };
+        let responses = [responses()?, responses()?];
+        Ok(Self {
+            nonce_commitments,
+            value_response,
+            responses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve_tree::SelRerandParameters;
+    use crate::layout::TAG;
+
+    type PallasParameters = ark_pallas::PallasConfig;
+    type VestaParameters = ark_vesta::VestaConfig;
+    type PallasScalar = ark_pallas::Fr;
+
+    #[test]
+    fn test_equal_value() {
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 4, 1 << 4);
+        let parameters = &sr_params.even_parameters;
+        let layout = CommitmentLayout::coin();
+        let pedersen = ValueGenerators::pedersen(parameters);
+        let coins = ValueGenerators::layout(parameters, &layout).unwrap();
+
+        let coin = |value: u64, rng: &mut rand::rngs::ThreadRng| {
+            let opening = ValueOpening {
+                vector: layout
+                    .vector(&[(VALUE, fe_from_u64(value)), (TAG, PallasScalar::rand(rng))])
+                    .unwrap(),
+                blinding: PallasScalar::rand(rng),
+            };
+            let commitment = parameters
+                .commit(&opening.vector, opening.blinding, 0)
+                .unwrap();
+            (commitment, opening)
+        };
+        let raw = |value: u64, rng: &mut rand::rngs::ThreadRng| {
+            let opening = ValueOpening::pedersen(value, PallasScalar::rand(rng));
+            let commitment = parameters
+                .pc_gens
+                .commit(opening.vector[0], opening.blinding);
+            (commitment, opening)
+        };
+
+        // A coin and someone else's coin of the same value, with other tags and blindings.
+        let (mine, my_opening) = coin(42, &mut rng);
+        let (theirs, their_opening) = coin(42, &mut rng);
+        let proof = prove_equal_value(
+            &coins,
+            &mine,
+            &my_opening,
+            &coins,
+            &theirs,
+            &their_opening,
+            &mut rng,
+        )
+        .unwrap();
+        proof.verify(&coins, &mine, &coins, &theirs).unwrap();
+        assert!(proof.verify(&coins, &theirs, &coins, &mine).is_err());
+
+        // A coin and a Pedersen commitment to its value.
+        let (value, value_opening) = raw(42, &mut rng);
+        let proof = prove_equal_value(
+            &coins,
+            &mine,
+            &my_opening,
+            &pedersen,
+            &value,
+            &value_opening,
+            &mut rng,
+        )
+        .unwrap();
+        proof.verify(&coins, &mine, &pedersen, &value).unwrap();
+        assert!(proof.verify(&coins, &mine, &coins, &value).is_err());
+
+        // The same commitment twice.
+        let proof = prove_equal_value(
+            &coins,
+            &mine,
+            &my_opening,
+            &coins,
+            &mine,
+            &my_opening,
+            &mut rng,
+        )
+        .unwrap();
+        proof.verify(&coins, &mine, &coins, &mine).unwrap();
+
+        // Different values are refused by the prover, and a proof of other commitments does not verify.
+        let (other, other_opening) = raw(43, &mut rng);
+        assert!(matches!(
+            prove_equal_value(
+                &coins,
+                &mine,
+                &my_opening,
+                &pedersen,
+                &other,
+                &other_opening,
+                &mut rng,
+            ),
+            Err(Error::InconsistentWitness { .. })
+        ));
+        assert!(matches!(
+            prove_equal_value(
+                &coins,
+                &theirs,
+                &my_opening,
+                &pedersen,
+                &value,
+                &value_opening,
+                &mut rng,
+            ),
+            Err(Error::InconsistentWitness { .. })
+        ));
+        let proof = prove_equal_value(
+            &coins,
+            &mine,
+            &my_opening,
+            &pedersen,
+            &value,
+            &value_opening,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(matches!(
+            proof.verify(&coins, &mine, &pedersen, &other),
+            Err(Error::R1CS(R1CSError::VerificationError))
+        ));
+
+        // Serialized, the proof is two points, a scalar and, for a coin and a value, two and one scalars.
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        let (point, scalar) = (mine.compressed_size(), 32);
+        assert_eq!(
+            bytes.len(),
+            2 * point + scalar + (8 + 2 * scalar) + (8 + scalar)
+        );
+        let decoded =
+            EqualValueProof::<PallasParameters>::deserialize_compressed(&bytes[..]).unwrap();
+        assert!(decoded == proof);
+        decoded.verify(&coins, &mine, &pedersen, &value).unwrap();
+        assert!(matches!(
+            decoded.verify(&coins, &mine, &coins, &theirs),
+            Err(Error::ShapeMismatch { .. })
+        ));
+    }
+}
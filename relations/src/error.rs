@@ -0,0 +1,224 @@
+use ark_serialize::SerializationError;
+use bulletproofs::r1cs::R1CSError;
+use std::fmt;
+
+/// Errors of building, proving and verifying the relations of this crate.
+#[derive(Debug)]
+pub enum Error {
+    /// Laying down constraints, proving or verifying failed, e.g. a proof does not verify.
+    R1CS(R1CSError),
+    /// A value could not be serialized or deserialized.
+    Serialization(SerializationError),
+    /// The parameters or public inputs do not fit the statement,
+    /// e.g. there are too few generators to commit to a vector.
+    ParameterMismatch { description: String },
+    /// The witness does not fit the statement, e.g. it selects a leaf that is not in the tree.
+    InconsistentWitness { description: String },
+    /// A path or proof does not have the shape of the circuit it is verified for,
+    /// e.g. it was made for a tree of another height.
+    ShapeMismatch { description: String },
+    /// A rerandomized path does not have the shape of a path of the tree, or does not start at its root,
+    /// or a membership proof claims another number of leaves than the tree has.
+    InvalidPath,
+    /// A signature does not verify.
+    InvalidSignature,
+    /// The signature of a batch at `index` does not verify, see `coin::verify_signatures_batch`.
+    InvalidSignatureInBatch { index: usize },
+    /// A transaction spends two coins with the same spending tag.
+    DuplicateTag,
+    /// A transaction spends a coin whose spending tag was already revealed on chain.
+    SpentTag,
+    /// A transaction publishes other spending tags than those its proofs enforce.
+    TagMismatch,
+    /// A leaf inserted for a minted coin is not the permissible leaf of its commitment, see `coin::verify_inserted_leaf`.
+    LeafMismatch,
+    /// A point is not permissible where a leaf or a child of a tree is expected, see `permissible::assert_permissible`.
+    NotPermissible,
+    /// A public key or a commitment is the identity:
+    /// anyone can sign for the identity as a public key, and it is the empty node as a commitment.
+    IdentityPoint,
+    /// A proof was made with other parameters than those it is verified with,
+    /// as its `ParametersFingerprint` shows before it is verified.
+    ParameterFingerprintMismatch,
+    /// A length prefix of serialized data exceeds its `DeserializeLimits`.
+    LimitExceeded { description: String },
+    /// An event log is not a log of the changes of a tree and a tag set,
+    /// or does not reconstruct the state it is checked against.
+    InvalidEventLog { description: String },
+    /// A field element does not fit the integer it is read back as, e.g. a committed value above 2^64.
+    ValueOutOfRange { description: String },
+    /// A witness or attestation was requested for a leaf pruned from the tree, see `CurveTree::prune`.
+    PrunedLeaf { index: usize },
+    /// The opening of a fee handed to a block producer does not open the fee commitment of its transaction,
+    /// see `coin::aggregate_fees`.
+    FeeMismatch,
+    /// Serialized data is of a format version this build does not know, see `version::FormatVersion`.
+    UnsupportedVersion { version: u8 },
+    /// A pool state does not have the digest it is checked against, see `pool::verify_transition`.
+    StateMismatch,
+    /// An encrypted backup does not decrypt with the recovery key, or was altered, see `WalletStore::import_encrypted`.
+    InvalidBackup,
+    /// A stream of `streaming::ProofWriter` has its frames out of order, or does not match its integrity hash.
+    CorruptedStream { description: String },
+    /// A `curve_tree::LeafRef` to the leaf at `index` was taken in the epoch `root_epoch` of a tree now in
+    /// `current_epoch`, whose leaves moved since, see `CurveTree::refresh_ref`.
+    StaleLeafRef {
+        index: usize,
+        root_epoch: u64,
+        current_epoch: u64,
+    },
+}
+
+impl Error {
+    pub(crate) fn parameter_mismatch(description: impl Into<String>) -> Self {
+        Self::ParameterMismatch {
+            description: description.into(),
+        }
+    }
+
+    pub(crate) fn shape_mismatch(description: impl Into<String>) -> Self {
+        Self::ShapeMismatch {
+            description: description.into(),
+        }
+    }
+
+    pub(crate) fn limit_exceeded(description: impl Into<String>) -> Self {
+        Self::LimitExceeded {
+            description: description.into(),
+        }
+    }
+
+    pub(crate) fn invalid_event_log(description: impl Into<String>) -> Self {
+        Self::InvalidEventLog {
+            description: description.into(),
+        }
+    }
+
+    pub(crate) fn value_out_of_range(description: impl Into<String>) -> Self {
+        Self::ValueOutOfRange {
+            description: description.into(),
+        }
+    }
+
+    pub(crate) fn corrupted_stream(description: impl Into<String>) -> Self {
+        Self::CorruptedStream {
+            description: description.into(),
+        }
+    }
+
+    pub(crate) fn inconsistent_witness(description: impl Into<String>) -> Self {
+        Self::InconsistentWitness {
+            description: description.into(),
+        }
+    }
+
+    /// The name of the variant, without its fields, e.g. to count failures by kind, see `metrics::Metrics`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::R1CS(_) => "R1CS",
+            Self::Serialization(_) => "Serialization",
+            Self::ParameterMismatch { .. } => "ParameterMismatch",
+            Self::InconsistentWitness { .. } => "InconsistentWitness",
+            Self::ShapeMismatch { .. } => "ShapeMismatch",
+            Self::InvalidPath => "InvalidPath",
+            Self::InvalidSignature => "InvalidSignature",
+            Self::InvalidSignatureInBatch { .. } => "InvalidSignatureInBatch",
+            Self::DuplicateTag => "DuplicateTag",
+            Self::SpentTag => "SpentTag",
+            Self::TagMismatch => "TagMismatch",
+            Self::LeafMismatch => "LeafMismatch",
+            Self::NotPermissible => "NotPermissible",
+            Self::IdentityPoint => "IdentityPoint",
+            Self::ParameterFingerprintMismatch => "ParameterFingerprintMismatch",
+            Self::LimitExceeded { .. } => "LimitExceeded",
+            Self::InvalidEventLog { .. } => "InvalidEventLog",
+            Self::ValueOutOfRange { .. } => "ValueOutOfRange",
+            Self::PrunedLeaf { .. } => "PrunedLeaf",
+            Self::FeeMismatch => "FeeMismatch",
+            Self::UnsupportedVersion { .. } => "UnsupportedVersion",
+            Self::StateMismatch => "StateMismatch",
+            Self::InvalidBackup => "InvalidBackup",
+            Self::CorruptedStream { .. } => "CorruptedStream",
+            Self::StaleLeafRef { .. } => "StaleLeafRef",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::R1CS(e) => write!(f, "{}", e),
+            Self::Serialization(e) => write!(f, "serialization error: {}", e),
+            Self::ParameterMismatch { description } => {
+                write!(f, "parameter mismatch: {}", description)
+            }
+            Self::InconsistentWitness { description } => {
+                write!(f, "inconsistent witness: {}", description)
+            }
+            Self::ShapeMismatch { description } => write!(f, "shape mismatch: {}", description),
+            Self::InvalidPath => write!(f, "the path is not a path of the tree"),
+            Self::InvalidSignature => write!(f, "invalid signature"),
+            Self::InvalidSignatureInBatch { index } => {
+                write!(f, "invalid signature at index {} of the batch", index)
+            }
+            Self::DuplicateTag => write!(f, "the transaction spends the same tag twice"),
+            Self::SpentTag => write!(f, "the transaction spends an already spent tag"),
+            Self::TagMismatch => write!(f, "the published tags are not those of the proofs"),
+            Self::LeafMismatch => write!(f, "the leaf is not that of the minted coin commitment"),
+            Self::NotPermissible => write!(f, "the point is not permissible"),
+            Self::IdentityPoint => write!(f, "a public key or commitment is the identity"),
+            Self::ParameterFingerprintMismatch => write!(f, "parameter fingerprint mismatch"),
+            Self::LimitExceeded { description } => write!(f, "limit exceeded: {}", description),
+            Self::InvalidEventLog { description } => {
+                write!(f, "invalid event log: {}", description)
+            }
+            Self::ValueOutOfRange { description } => {
+                write!(f, "value out of range: {}", description)
+            }
+            Self::PrunedLeaf { index } => write!(f, "the leaf at index {} was pruned", index),
+            Self::FeeMismatch => write!(f, "the opening does not open the fee commitment"),
+            Self::UnsupportedVersion { version } => {
+                write!(f, "unsupported format version {}", version)
+            }
+            Self::StateMismatch => write!(f, "the pool state does not have the expected digest"),
+            Self::InvalidBackup => write!(
+                f,
+                "the backup does not decrypt with the recovery key or was altered"
+            ),
+            Self::CorruptedStream { description } => {
+                write!(f, "corrupted proof stream: {}", description)
+            }
+            Self::StaleLeafRef {
+                index,
+                root_epoch,
+                current_epoch,
+            } => write!(
+                f,
+                "the reference to the leaf at index {} of epoch {} is stale, the tree is in epoch {}",
+                index, root_epoch, current_epoch
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::R1CS(e) => Some(e),
+            Self::Serialization(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<R1CSError> for Error {
+    fn from(e: R1CSError) -> Self {
+        Self::R1CS(e)
+    }
+}
+
+impl From<SerializationError> for Error {
+    fn from(e: SerializationError) -> Self {
+        Self::Serialization(e)
+    }
+}
@@ -0,0 +1,273 @@
+use crate::curve_tree::{CurveTree, CyclePoint, PointStorage, SelRerandParameters};
+use crate::error::Error;
+use crate::protocol;
+
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use sha3::{Digest, Sha3_256};
+use std::collections::HashSet;
+use std::io::{ErrorKind, Read, Write};
+use std::sync::{Arc, Mutex};
+
+/// The largest record of an event log, far above the encoding of a point or a scalar.
+pub const MAX_EVENT_SIZE: usize = 1 << 10;
+
+const INSERT: u8 = 0;
+const TAG_ADDED: u8 = 1;
+
+/// A change of the state of a node, recorded in the order it happened to debug a divergence of states.
+/// The points and scalars are compressed, so that one log holds the events of trees and tag sets of any curve.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A leaf inserted into a curve tree at `index`.
+    Insert { index: u64, leaf: Vec<u8> },
+    /// A spending tag added to a tag set.
+    TagAdded { tag: Vec<u8> },
+}
+
+impl Event {
+    pub fn insert<P: SWCurveConfig>(index: usize, leaf: &Affine<P>) -> Self {
+        Self::Insert {
+            index: index as u64,
+            leaf: compressed(leaf),
+        }
+    }
+
+    pub fn tag_added<F: PrimeField>(tag: &F) -> Self {
+        Self::TagAdded {
+            tag: compressed(tag),
+        }
+    }
+
+    /// Writes the event as a record: its length as a little-endian `u64`, then the kind of the event,
+    /// the index of an insert as a little-endian `u64`, and the compressed leaf or tag.
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let mut record = Vec::new();
+        match self {
+            Self::Insert { index, leaf } => {
+                record.push(INSERT);
+                record.extend_from_slice(&index.to_le_bytes());
+                record.extend_from_slice(leaf);
+            }
+            Self::TagAdded { tag } => {
+                record.push(TAG_ADDED);
+                record.extend_from_slice(tag);
+            }
+        }
+        writer
+            .write_all(&(record.len() as u64).to_le_bytes())
+            .and_then(|_| writer.write_all(&record))
+            .map_err(|e| Error::Serialization(SerializationError::IoError(e)))
+    }
+
+    /// Reads a record written by `write`, none at the end of the log.
+    /// A record longer than `MAX_EVENT_SIZE` fails with `Error::LimitExceeded` before it is read.
+    pub fn read<R: Read>(mut reader: R) -> Result<Option<Self>, Error> {
+        let mut length = [0u8; 8];
+        let mut filled = 0;
+        while filled < length.len() {
+            match reader.read(&mut length[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(Error::invalid_event_log("The log ends within a record")),
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(Error::Serialization(SerializationError::IoError(e))),
+            }
+        }
+        let length = u64::from_le_bytes(length);
+        if length > MAX_EVENT_SIZE as u64 {
+            return Err(Error::limit_exceeded(format!(
+                "The event record is {} bytes, the limit is {}",
+                length, MAX_EVENT_SIZE
+            )));
+        }
+        let mut record = vec![0u8; length as usize];
+        reader.read_exact(&mut record).map_err(|e| match e.kind() {
+            ErrorKind::UnexpectedEof => Error::invalid_event_log("The log ends within a record"),
+            _ => Error::Serialization(SerializationError::IoError(e)),
+        })?;
+        match record.split_first() {
+            Some((&INSERT, rest)) if rest.len() >= 8 => {
+                let (index, leaf) = rest.split_at(8);
+                Ok(Some(Self::Insert {
+                    index: u64::from_le_bytes(index.try_into().expect("eight bytes")),
+                    leaf: leaf.to_vec(),
+                }))
+            }
+            Some((&TAG_ADDED, tag)) => Ok(Some(Self::TagAdded { tag: tag.to_vec() })),
+            _ => Err(Error::invalid_event_log("A record is of no known event")),
+        }
+    }
+}
+
+fn compressed<T: CanonicalSerialize>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(value.compressed_size());
+    value
+        .serialize_compressed(&mut bytes)
+        .expect("serializing to a vector does not fail");
+    bytes
+}
+
+/// Receives the events of the curve trees and tag sets it is set on, see `CurveTree::set_event_sink`
+/// and `TagSet::set_event_sink`.
+///
+/// Recording does not fail the change it records: a sink that cannot record keeps the error for its owner.
+pub trait EventSink {
+    fn record(&mut self, event: &Event);
+}
+
+/// An `EventSink` shared by the trees and tag sets of a node, so that their events are in one log in the order they happened.
+#[derive(Clone)]
+pub struct SharedEventSink(Arc<Mutex<dyn EventSink + Send>>);
+
+impl SharedEventSink {
+    pub fn new<S: EventSink + Send + 'static>(sink: S) -> Self {
+        Self(Arc::new(Mutex::new(sink)))
+    }
+
+    pub fn record(&self, event: &Event) {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record(event)
+    }
+}
+
+/// Shares a sink its owner keeps a handle to, e.g. to read the log it wrote.
+impl<S: EventSink + Send + 'static> From<Arc<Mutex<S>>> for SharedEventSink {
+    fn from(sink: Arc<Mutex<S>>) -> Self {
+        Self(sink)
+    }
+}
+
+impl std::fmt::Debug for SharedEventSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedEventSink")
+    }
+}
+
+/// An `EventSink` writing the records of the events, see `Event::write`, replayed by `replay`.
+/// After the first error it records nothing more, `finish` returns the error.
+pub struct EventWriter<W: Write> {
+    writer: W,
+    error: Option<Error>,
+}
+
+impl<W: Write> EventWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            error: None,
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// The writer, or the first error writing to it.
+    pub fn finish(mut self) -> Result<W, Error> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(self.writer),
+        }
+    }
+}
+
+impl<W: Write> EventSink for EventWriter<W> {
+    fn record(&mut self, event: &Event) {
+        if self.error.is_none() {
+            if let Err(error) = event.write(&mut self.writer) {
+                self.error = Some(error);
+            }
+        }
+    }
+}
+
+/// The digest of a set of spending tags, whatever the order they were added in, see `TagSet::digest`.
+pub fn tags_digest<'a, F: PrimeField>(tags: impl IntoIterator<Item = &'a F>) -> [u8; 32] {
+    let mut encodings: Vec<_> = tags.into_iter().map(compressed).collect();
+    encodings.sort_unstable();
+    let mut hasher = Sha3_256::new();
+    hasher.update(protocol::TAGS_DIGEST_DOMAIN);
+    hasher.update((encodings.len() as u64).to_le_bytes());
+    for encoding in encodings {
+        hasher.update(encoding);
+    }
+    hasher.finalize().into()
+}
+
+/// The leaves of a curve tree and the spending tags recorded in an event log, in the order they were recorded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayedState<P: SWCurveConfig> {
+    pub leaves: Vec<Affine<P>>,
+    pub tags: Vec<P::ScalarField>,
+}
+
+/// Reads the events of a log written by `EventWriter` for a tree of leaves on `P` and its tag set.
+///
+/// Fails with `Error::InvalidEventLog` if the leaves are not inserted at consecutive indices from zero,
+/// as they are in a tree, or if a tag is added twice, which a tag set does not record.
+pub fn replay<P: SWCurveConfig, R: Read>(mut reader: R) -> Result<ReplayedState<P>, Error> {
+    let mut leaves = Vec::new();
+    let mut tags = Vec::new();
+    let mut seen_tags = HashSet::new();
+    while let Some(event) = Event::read(&mut reader)? {
+        match event {
+            Event::Insert { index, leaf } => {
+                if index != leaves.len() as u64 {
+                    return Err(Error::invalid_event_log(format!(
+                        "A leaf is inserted at index {} after {} leaves",
+                        index,
+                        leaves.len()
+                    )));
+                }
+                leaves.push(Affine::<P>::deserialize_compressed(leaf.as_slice())?);
+            }
+            Event::TagAdded { tag } => {
+                if !seen_tags.insert(tag.clone()) {
+                    return Err(Error::invalid_event_log("A tag is added twice"));
+                }
+                tags.push(P::ScalarField::deserialize_compressed(tag.as_slice())?);
+            }
+        }
+    }
+    Ok(ReplayedState { leaves, tags })
+}
+
+impl<F0: PrimeField, P0: SWCurveConfig<ScalarField = F0> + Copy + Send> ReplayedState<P0> {
+    /// Builds the tree of the replayed leaves, as `CurveTree::from_set_with_storage`,
+    /// and checks that its root is `root` and that the replayed tags have the digest `tags_digest`.
+    /// Fails with `Error::InvalidEventLog` if either differs: the log does not reconstruct the state.
+    pub fn check<const L: usize, F1: PrimeField, P1>(
+        &self,
+        root: &CyclePoint<P0, P1>,
+        tags_digest: &[u8; 32],
+        parameters: &SelRerandParameters<P0, P1>,
+        height: Option<usize>,
+        storage: PointStorage,
+    ) -> Result<CurveTree<L, P0, P1>, Error>
+    where
+        P0: SWCurveConfig<BaseField = F1>,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    {
+        let tree = CurveTree::from_set_with_storage(&self.leaves, parameters, height, storage)?;
+        let same_root = match (tree.root(), root) {
+            (CyclePoint::Even(a), CyclePoint::Even(b)) => a == *b,
+            (CyclePoint::Odd(a), CyclePoint::Odd(b)) => a == *b,
+            _ => false,
+        };
+        if !same_root {
+            return Err(Error::invalid_event_log(
+                "The replayed leaves have another root",
+            ));
+        }
+        if self::tags_digest(&self.tags) != *tags_digest {
+            return Err(Error::invalid_event_log(
+                "The replayed tags have another digest",
+            ));
+        }
+        Ok(tree)
+    }
+}
@@ -0,0 +1,138 @@
+use ark_ec::short_weierstrass::{Affine, Projective, SWCurveConfig};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_std::Zero;
+
+/// Zero has no inverse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ZeroInverse {
+    /// Position of the zero in the inverted slice, zero when inverting a single element.
+    pub index: usize,
+}
+
+impl std::fmt::Display for ZeroInverse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot invert zero at index {}", self.index)
+    }
+}
+
+impl std::error::Error for ZeroInverse {}
+
+/// Inverts a public `x` with the inversion of the field, whose running time depends on `x`.
+pub fn inverse<F: Field>(x: &F) -> Result<F, ZeroInverse> {
+    x.inverse().ok_or(ZeroInverse { index: 0 })
+}
+
+/// Inverts a secret `x` as \\(x^{p-2}\\).
+///
+/// The exponentiation performs the same sequence of field operations for every non-zero `x`,
+/// only whether `x` is zero is revealed.
+pub fn ct_inverse<F: PrimeField>(x: &F) -> Result<F, ZeroInverse> {
+    if x.is_zero() {
+        return Err(ZeroInverse { index: 0 });
+    }
+    let mut exponent = F::MODULUS;
+    exponent.sub_with_borrow(&F::BigInt::from(2u64));
+    Ok(x.pow(exponent))
+}
+
+/// Replaces every element of `v` by its inverse with Montgomery's trick,
+/// which takes a single inversion and three multiplications per element.
+///
+/// Returns the position of the first zero without modifying `v` if there is one.
+/// Variable time, only for public values.
+pub fn batch_inverse<F: Field>(v: &mut [F]) -> Result<(), ZeroInverse> {
+    if let Some(index) = v.iter().position(Zero::is_zero) {
+        return Err(ZeroInverse { index });
+    }
+    // prefixes[i] is the product of the elements before i
+    let mut prefixes = Vec::with_capacity(v.len());
+    let mut product = F::one();
+    for x in v.iter() {
+        prefixes.push(product);
+        product *= x;
+    }
+    // Going backwards, product_inv is the inverse of the product of the elements up to i.
+    let mut product_inv = product.inverse().expect("the elements are non-zero");
+    for (x, prefix) in v.iter_mut().zip(prefixes).rev() {
+        let x_inv = product_inv * prefix;
+        product_inv *= *x;
+        *x = x_inv;
+    }
+    Ok(())
+}
+
+/// Converts the points to affine coordinates with a single inversion, see [`batch_inverse`].
+pub fn batch_into_affine<P: SWCurveConfig>(points: &[Projective<P>]) -> Vec<Affine<P>> {
+    let mut z_invs: Vec<P::BaseField> = points
+        .iter()
+        .filter(|point| !point.z.is_zero())
+        .map(|point| point.z)
+        .collect();
+    batch_inverse(&mut z_invs).expect("points at infinity are skipped");
+    let mut z_invs = z_invs.into_iter();
+    points
+        .iter()
+        .map(|point| {
+            if point.z.is_zero() {
+                return Affine::zero();
+            }
+            // Jacobian coordinates: (x, y) = (X / Z^2, Y / Z^3)
+            let z_inv = z_invs.next().unwrap();
+            let z_inv_squared = z_inv.square();
+            Affine::new_unchecked(point.x * z_inv_squared, point.y * z_inv_squared * z_inv)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ec::CurveGroup;
+    use ark_std::{One, UniformRand};
+
+    type PallasP = ark_pallas::Projective;
+    type PallasBase = ark_pallas::Fq;
+
+    #[test]
+    fn test_inversions_agree() {
+        let mut rng = rand::thread_rng();
+        let mut v: Vec<PallasBase> = (0..33).map(|_| PallasBase::rand(&mut rng)).collect();
+        let expected: Vec<PallasBase> = v.iter().map(|x| x.inverse().unwrap()).collect();
+
+        for (x, x_inv) in v.iter().zip(expected.iter()) {
+            assert_eq!(inverse(x).unwrap(), *x_inv);
+            assert_eq!(ct_inverse(x).unwrap(), *x_inv);
+        }
+        assert_eq!(ct_inverse(&PallasBase::one()).unwrap(), PallasBase::one());
+
+        batch_inverse(&mut v).unwrap();
+        assert_eq!(v, expected);
+
+        let mut empty: Vec<PallasBase> = Vec::new();
+        assert!(batch_inverse(&mut empty).is_ok());
+    }
+
+    #[test]
+    fn test_zero_is_an_error() {
+        let mut rng = rand::thread_rng();
+        let zero = PallasBase::zero();
+        assert_eq!(inverse(&zero), Err(ZeroInverse { index: 0 }));
+        assert_eq!(ct_inverse(&zero), Err(ZeroInverse { index: 0 }));
+
+        let mut v: Vec<PallasBase> = (0..5).map(|_| PallasBase::rand(&mut rng)).collect();
+        v[3] = zero;
+        let before = v.clone();
+        assert_eq!(batch_inverse(&mut v), Err(ZeroInverse { index: 3 }));
+        assert_eq!(v, before);
+    }
+
+    #[test]
+    fn test_batch_into_affine() {
+        let mut rng = rand::thread_rng();
+        let mut points: Vec<PallasP> = (0..9).map(|_| PallasP::rand(&mut rng)).collect();
+        points[4] = PallasP::zero();
+        let expected: Vec<_> = points.iter().map(|p| p.into_affine()).collect();
+        assert_eq!(batch_into_affine(&points), expected);
+    }
+}
@@ -0,0 +1,379 @@
+#[cfg(feature = "prover")]
+use bulletproofs::r1cs::Prover;
+use bulletproofs::r1cs::{ConstraintSystem, Variable, Verifier};
+use bulletproofs::BulletproofGens;
+
+use crate::error::Error;
+use crate::protocol;
+
+use ark_ec::AffineRepr;
+use ark_ff::Field;
+use merlin::Transcript;
+
+/// Slot of the value of a coin.
+pub const VALUE: &str = "value";
+/// Slot of the spending tag of a coin, derived from its rerandomized public key.
+pub const TAG: &str = "tag";
+/// Slot of the asset type of a coin, for relations over several assets.
+pub const ASSET: &str = "asset";
+/// Slot of the hash of the application data of a leaf, see `leaf_data`.
+pub const DATA_HASH: &str = "data_hash";
+/// Slot of the x-coordinate of the public key of a registry entry, a point of the other curve of the cycle, see `registry`.
+pub const KEY_X: &str = "key_x";
+/// Slot of the attribute of a registry entry, see `registry`.
+pub const ATTRIBUTE: &str = "attribute";
+
+/// The named slots of a vector commitment: which value each coordinate, and so each generator, holds.
+///
+/// The layout is declared once and passed to every relation committing to or opening such vectors,
+/// which look up coordinates and generators by slot name instead of by raw index.
+/// Relations sharing commitments must agree on the layout, as checked by `merge`,
+/// and each binds the layout to its transcript, so proofs made for one layout do not verify for another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitmentLayout {
+    // The name of the slot at each coordinate, if any.
+    slots: Vec<Option<&'static str>>,
+}
+
+impl CommitmentLayout {
+    /// A layout with a slot named `name` at each `(name, index)` of `slots`,
+    /// rejecting names or indices given twice.
+    pub fn new(slots: &[(&'static str, usize)]) -> Result<Self, Error> {
+        let mut layout = Self { slots: Vec::new() };
+        for &(name, index) in slots {
+            layout.insert(name, index)?;
+        }
+        Ok(layout)
+    }
+
+    /// This layout with the slots `names` appended after its last coordinate, in order,
+    /// e.g. a later version of a layout carrying more data in each commitment.
+    pub fn with_trailing(&self, names: &[&'static str]) -> Result<Self, Error> {
+        let mut layout = self.clone();
+        for (offset, name) in names.iter().enumerate() {
+            layout.insert(name, self.width() + offset)?;
+        }
+        Ok(layout)
+    }
+
+    /// The layout of coin commitments: the value, then the tag.
+    pub fn coin() -> Self {
+        Self::new(&[(VALUE, 0), (TAG, 1)]).expect("the slots are distinct")
+    }
+
+    fn insert(&mut self, name: &'static str, index: usize) -> Result<(), Error> {
+        if let Ok(existing) = self.index(name) {
+            if existing != index {
+                return Err(Error::parameter_mismatch(format!(
+                    "The slot {} is at index {}, not {}",
+                    name, existing, index
+                )));
+            }
+        }
+        if index >= self.slots.len() {
+            self.slots.resize(index + 1, None);
+        }
+        match self.slots[index] {
+            Some(existing) if existing != name => Err(Error::parameter_mismatch(format!(
+                "The index {} holds the slot {}, not {}",
+                index, existing, name
+            ))),
+            _ => {
+                self.slots[index] = Some(name);
+                Ok(())
+            }
+        }
+    }
+
+    /// The layout with the slots of both layouts, for relations opening the same commitments.
+    /// Fails if the layouts put a slot at different indices, or different slots at the same index.
+    pub fn merge(&self, other: &Self) -> Result<Self, Error> {
+        let mut merged = self.clone();
+        for (index, name) in other.slots.iter().enumerate() {
+            if let Some(name) = name {
+                merged.insert(name, index)?;
+            }
+        }
+        Ok(merged)
+    }
+
+    /// The mask of the slots `constrained` of this layout, for a relation written against this layout
+    /// to open commitments of layouts extending it, see `SlotMask`.
+    pub fn mask(&self, constrained: &[&'static str]) -> Result<SlotMask, Error> {
+        let slots = constrained
+            .iter()
+            .map(|name| Ok((*name, self.index(name)?)))
+            .collect::<Result<_, Error>>()?;
+        Ok(SlotMask { slots })
+    }
+
+    /// The number of coordinates of the committed vectors.
+    pub fn width(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// The coordinate of the slot `name`.
+    pub fn index(&self, name: &str) -> Result<usize, Error> {
+        self.slots
+            .iter()
+            .position(|slot| *slot == Some(name))
+            .ok_or_else(|| Error::parameter_mismatch(format!("The layout has no slot {}", name)))
+    }
+
+    /// The vector with `values` at their slots and zero at the others.
+    pub fn vector<F: Field>(&self, values: &[(&str, F)]) -> Result<Vec<F>, Error> {
+        let mut vector = vec![F::zero(); self.width()];
+        for &(name, value) in values {
+            vector[self.index(name)?] = value;
+        }
+        Ok(vector)
+    }
+
+    /// The generator multiplying the slot `name` in the commitments of `bp_gens`.
+    pub fn generator<C: AffineRepr>(
+        &self,
+        name: &str,
+        bp_gens: &BulletproofGens<C>,
+    ) -> Result<C, Error> {
+        let index = self.index(name)?;
+        if self.width() > bp_gens.gens_capacity {
+            return Err(Error::parameter_mismatch(format!(
+                "Not enough generators for the layout: {} needed, {} available",
+                self.width(),
+                bp_gens.gens_capacity
+            )));
+        }
+        Ok(bp_gens.share(0).G_slice(self.width())[index])
+    }
+
+    /// Absorbs the layout into `transcript`, so that the proofs of different layouts are in different domains.
+    pub fn bind(&self, transcript: &mut Transcript) {
+        transcript.append_u64(protocol::LAYOUT_WIDTH, self.width() as u64);
+        for (index, name) in self.slots.iter().enumerate() {
+            if let Some(name) = name {
+                transcript.append_u64(protocol::LAYOUT_INDEX, index as u64);
+                transcript.append_message(protocol::LAYOUT_SLOT, name.as_bytes());
+            }
+        }
+    }
+}
+
+/// A commitment and the variables of the masked slots, as returned by `SlotMask::commit_vec_masked_prover`.
+pub type MaskedCommitment<C> = (C, Vec<Variable<<C as AffineRepr>::ScalarField>>);
+
+/// The slots a relation constrains, at their indices in the layout the relation was written against.
+///
+/// Commitments of any layout keeping those slots at those indices, e.g. with extra trailing slots, are opened through the mask:
+/// every coordinate is allocated, so the commitment still binds the slots the relation does not mention
+/// and the prover cannot swap them, but only the variables of the masked slots are returned to be constrained.
+/// The layout of the commitment, with all its slot names, is bound to the transcript as by `CommitmentLayout::bind`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotMask {
+    slots: Vec<(&'static str, usize)>,
+}
+
+impl SlotMask {
+    /// Fails with `Error::ParameterMismatch` if `layout` does not hold each masked slot at its index,
+    /// e.g. a layout in which a constrained slot moved.
+    pub fn check(&self, layout: &CommitmentLayout) -> Result<(), Error> {
+        for &(name, index) in &self.slots {
+            if layout.slots.get(index) != Some(&Some(name)) {
+                return Err(Error::parameter_mismatch(format!(
+                    "The relation constrains the slot {} at index {}, the layout of the commitment does not",
+                    name, index
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Binds `layout` to the transcript of `prover` and commits to `vector`, laid out as `layout`.
+    /// Returns the commitment and the variables of the masked slots, in the order of the mask.
+    #[cfg(feature = "prover")]
+    pub fn commit_vec_masked_prover<C: AffineRepr>(
+        &self,
+        prover: &mut Prover<Transcript, C>,
+        layout: &CommitmentLayout,
+        vector: &[C::ScalarField],
+        blinding: C::ScalarField,
+        bp_gens: &BulletproofGens<C>,
+    ) -> Result<MaskedCommitment<C>, Error> {
+        self.check(layout)?;
+        if vector.len() != layout.width() {
+            return Err(Error::inconsistent_witness(format!(
+                "The vector has {} coordinates, the layout {}",
+                vector.len(),
+                layout.width()
+            )));
+        }
+        layout.bind(prover.transcript());
+        let (commitment, variables) = prover.commit_vec(vector, blinding, bp_gens);
+        Ok((commitment, self.select(&variables)))
+    }
+
+    /// Verifier counterpart of `commit_vec_masked_prover`, with the layout of the commitment.
+    pub fn commit_vec_masked_verifier<C: AffineRepr>(
+        &self,
+        verifier: &mut Verifier<Transcript, C>,
+        layout: &CommitmentLayout,
+        commitment: C,
+    ) -> Result<Vec<Variable<C::ScalarField>>, Error> {
+        self.check(layout)?;
+        layout.bind(verifier.transcript());
+        let variables = verifier.commit_vec(layout.width(), commitment);
+        Ok(self.select(&variables))
+    }
+
+    fn select<F: Field>(&self, variables: &[Variable<F>]) -> Vec<Variable<F>> {
+        self.slots
+            .iter()
+            .map(|&(_, index)| variables[index])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::{UniformRand, Zero};
+
+    type PallasA = ark_pallas::Affine;
+    type PallasScalar = ark_pallas::Fr;
+
+    fn challenge(layout: &CommitmentLayout) -> [u8; 32] {
+        let mut transcript = Transcript::new(b"layout test");
+        layout.bind(&mut transcript);
+        let mut challenge = [0u8; 32];
+        transcript.challenge_bytes(b"challenge", &mut challenge);
+        challenge
+    }
+
+    #[test]
+    fn test_layout() {
+        let layout = CommitmentLayout::coin();
+        assert_eq!(layout.width(), 2);
+        assert_eq!(layout.index(VALUE).unwrap(), 0);
+        assert_eq!(layout.index(TAG).unwrap(), 1);
+        assert!(matches!(
+            layout.index(ASSET),
+            Err(Error::ParameterMismatch { .. })
+        ));
+
+        let mut rng = rand::thread_rng();
+        let value = PallasScalar::rand(&mut rng);
+        assert_eq!(
+            layout.vector(&[(TAG, value)]).unwrap(),
+            vec![PallasScalar::zero(), value]
+        );
+
+        let bp_gens = BulletproofGens::<PallasA>::new(4, 1);
+        let pc_gens = bulletproofs::PedersenGens::<PallasA>::default();
+        let tag_only = bp_gens.share(0).commit_vec(
+            &pc_gens,
+            &layout.vector(&[(TAG, PallasScalar::from(1u64))]).unwrap(),
+            PallasScalar::zero(),
+        );
+        assert_eq!(layout.generator(TAG, &bp_gens).unwrap(), tag_only);
+        assert!(layout
+            .generator(TAG, &BulletproofGens::<PallasA>::new(1, 1))
+            .is_err());
+
+        // Another layout is another transcript domain.
+        let swapped = CommitmentLayout::new(&[(TAG, 0), (VALUE, 1)]).unwrap();
+        assert_eq!(challenge(&layout), challenge(&CommitmentLayout::coin()));
+        assert_ne!(challenge(&layout), challenge(&swapped));
+    }
+
+    #[test]
+    fn test_conflicting_layouts() {
+        assert!(matches!(
+            CommitmentLayout::new(&[(VALUE, 0), (VALUE, 1)]),
+            Err(Error::ParameterMismatch { .. })
+        ));
+        assert!(matches!(
+            CommitmentLayout::new(&[(VALUE, 0), (TAG, 0)]),
+            Err(Error::ParameterMismatch { .. })
+        ));
+
+        // A relation reading the asset type where the coin relations keep the tag.
+        let assets = CommitmentLayout::new(&[(VALUE, 0), (ASSET, 1)]).unwrap();
+        assert!(matches!(
+            CommitmentLayout::coin().merge(&assets),
+            Err(Error::ParameterMismatch { .. })
+        ));
+        // One reading the asset type in a slot of its own.
+        let assets = CommitmentLayout::new(&[(VALUE, 0), (ASSET, 2)]).unwrap();
+        let merged = CommitmentLayout::coin().merge(&assets).unwrap();
+        assert_eq!(merged.width(), 3);
+        assert_eq!(merged.index(TAG).unwrap(), 1);
+        assert_eq!(merged.index(ASSET).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_masked_slots() {
+        use bulletproofs::r1cs::R1CSProof;
+        use bulletproofs::PedersenGens;
+
+        let mut rng = rand::thread_rng();
+        let pc_gens = PedersenGens::<PallasA>::default();
+        let bp_gens = BulletproofGens::<PallasA>::new(8, 1);
+        // A relation written against the coin layout, proving the value of a commitment.
+        let mask = CommitmentLayout::coin().mask(&[VALUE]).unwrap();
+        let value = PallasScalar::from(5u64);
+
+        let prove = |layout: &CommitmentLayout, vector: &[PallasScalar]| {
+            let mut prover = Prover::new(&pc_gens, Transcript::new(b"masked"));
+            let (commitment, variables) = mask
+                .commit_vec_masked_prover(
+                    &mut prover,
+                    layout,
+                    vector,
+                    PallasScalar::rand(&mut rand::thread_rng()),
+                    &bp_gens,
+                )
+                .unwrap();
+            prover.constrain(variables[0] - value);
+            (commitment, prover.prove(&bp_gens).unwrap())
+        };
+        let verify = |layout: &CommitmentLayout, commitment, proof: &R1CSProof<PallasA>| {
+            let mut verifier = Verifier::new(Transcript::new(b"masked"));
+            let variables = mask.commit_vec_masked_verifier(&mut verifier, layout, commitment)?;
+            verifier.constrain(variables[0] - value);
+            verifier.verify(proof, &pc_gens, &bp_gens)?;
+            Ok::<_, Error>(())
+        };
+
+        // Commitments of a later layout with trailing slots still verify, the asset is bound but not constrained.
+        let v2 = CommitmentLayout::coin().with_trailing(&[ASSET]).unwrap();
+        assert_eq!(v2.index(ASSET).unwrap(), 2);
+        let asset = PallasScalar::rand(&mut rng);
+        let vector = v2
+            .vector(&[
+                (VALUE, value),
+                (TAG, PallasScalar::rand(&mut rng)),
+                (ASSET, asset),
+            ])
+            .unwrap();
+        let (commitment, proof) = prove(&v2, &vector);
+        verify(&v2, commitment, &proof).unwrap();
+        // Read with the coin layout, the commitment is opened on fewer coordinates, in another domain.
+        assert!(verify(&CommitmentLayout::coin(), commitment, &proof).is_err());
+        // Another asset is another commitment, for which the proof does not verify.
+        let other_asset = (commitment.into_group()
+            + v2.generator(ASSET, &bp_gens).unwrap() * PallasScalar::from(1u64))
+        .into();
+        assert!(verify(&v2, other_asset, &proof).is_err());
+
+        // A layout in which the value moved is rejected before any commitment.
+        let moved = CommitmentLayout::new(&[(TAG, 0), (VALUE, 1), (ASSET, 2)]).unwrap();
+        assert!(matches!(
+            mask.check(&moved),
+            Err(Error::ParameterMismatch { .. })
+        ));
+        assert!(matches!(
+            verify(&moved, commitment, &proof),
+            Err(Error::ParameterMismatch { .. })
+        ));
+        assert!(CommitmentLayout::coin().with_trailing(&[VALUE]).is_err());
+    }
+}
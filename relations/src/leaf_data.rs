@@ -0,0 +1,191 @@
+//! Leaves committing to the hash of application data, e.g. certificates or credentials, instead of to curve points.
+//!
+//! The blob is hashed into the scalar field of the even curve by a `LeafHasher`, committed to in the `DATA_HASH` slot
+//! of `data_layout` with a fresh blinding, and made permissible to be inserted into a tree. The owner keeps the
+//! `LeafWitness` of the insertion: with it, a membership proof opens the rerandomized leaf into its hash slot, which
+//! further constraints relate to a committed or public value without revealing the leaf.
+
+#[cfg(feature = "prover")]
+use bulletproofs::r1cs::Prover;
+use bulletproofs::r1cs::{ConstraintSystem, Verifier};
+
+#[cfg(feature = "prover")]
+use crate::accumulator::Accumulator;
+use crate::convert::hash_to_field;
+use crate::curve_tree::CurveTree;
+#[cfg(feature = "prover")]
+use crate::curve_tree::SelRerandParameters;
+use crate::cycle::EvenScalar;
+#[cfg(feature = "prover")]
+use crate::error::Error;
+use crate::layout::{CommitmentLayout, DATA_HASH};
+use crate::opened::OpenedLeaf;
+use crate::protocol;
+
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine};
+use ark_ff::PrimeField;
+use merlin::Transcript;
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+
+/// Hashes application data into a field element committed to by a leaf.
+///
+/// Different applications should hash in different domains, so that a blob of one is never the leaf of another.
+pub trait LeafHasher<F: PrimeField> {
+    fn hash(&self, blob: &[u8]) -> F;
+}
+
+/// SHAKE-256 of the length-prefixed domain followed by the blob, reduced into the field by `convert::hash_to_field`,
+/// which squeezes 128 bits more than the modulus for the reduction to be statistically close to uniform.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sha3LeafHasher {
+    domain: &'static [u8],
+}
+
+impl Sha3LeafHasher {
+    /// A hasher in the domain of an application, e.g. `application_label!("my_registry", "credential")`.
+    pub fn new(domain: &'static [u8]) -> Self {
+        Self { domain }
+    }
+}
+
+impl Default for Sha3LeafHasher {
+    /// The hasher in the domain `protocol::LEAF_DATA_DOMAIN`.
+    fn default() -> Self {
+        Self::new(protocol::LEAF_DATA_DOMAIN)
+    }
+}
+
+impl<F: PrimeField> LeafHasher<F> for Sha3LeafHasher {
+    fn hash(&self, blob: &[u8]) -> F {
+        hash_to_field(self.domain, blob)
+    }
+}
+
+/// The layout of the leaves of data: the hash of the data, alone.
+pub fn data_layout() -> CommitmentLayout {
+    CommitmentLayout::new(&[(DATA_HASH, 0)]).expect("a single slot")
+}
+
+/// What the owner of a leaf of data keeps to prove the membership of the leaf and open it, see `CurveTree::insert_data`.
+/// There is no `Debug`: the blinding is secret.
+#[derive(Clone)]
+pub struct LeafWitness<P: SWCurveConfig> {
+    /// The position of the leaf in the tree.
+    pub index: usize,
+    /// The hash of the data, committed to in the `DATA_HASH` slot of `data_layout`.
+    pub data_hash: P::ScalarField,
+    /// The blinding of the leaf, the fresh blinding plus the offset of the permissible search.
+    pub blinding: EvenScalar<P>,
+}
+
+impl<P: SWCurveConfig + Copy> LeafWitness<P> {
+    /// The vector committed to by the leaf, laid out as `data_layout`.
+    pub fn vector(&self) -> Vec<P::ScalarField> {
+        data_layout()
+            .vector(&[(DATA_HASH, self.data_hash)])
+            .expect("the layout has the slot")
+    }
+
+    /// Opens the rerandomized leaf of a membership proof of this leaf in `prover`, whose transcript the layout
+    /// is bound to first, where `rerandomization` is that returned by the membership gadget.
+    /// Returns the rerandomized leaf and its opening, whose `DATA_HASH` variable further constraints use.
+    #[cfg(feature = "prover")]
+    pub fn open_prover(
+        &self,
+        prover: &mut Prover<Transcript, Affine<P>>,
+        rerandomization: EvenScalar<P>,
+        parameters: &SelRerandParameters<P, impl SWCurveConfig + Copy>,
+    ) -> Result<(Affine<P>, OpenedLeaf<P::ScalarField>), Error> {
+        let layout = data_layout();
+        layout.bind(prover.transcript());
+        OpenedLeaf::commit_prover(
+            prover,
+            &layout,
+            &self.vector(),
+            (self.blinding + rerandomization).0,
+            &parameters.even_parameters.bp_gens,
+        )
+    }
+}
+
+/// Verifier counterpart of `LeafWitness::open_prover`, opening the rerandomized leaf `leaf`
+/// returned by the verifier's membership gadget.
+pub fn open_data_leaf_verifier<P: SWCurveConfig>(
+    verifier: &mut Verifier<Transcript, Affine<P>>,
+    leaf: Affine<P>,
+) -> OpenedLeaf<P::ScalarField> {
+    let layout = data_layout();
+    layout.bind(verifier.transcript());
+    OpenedLeaf::commit_verifier(verifier, &layout, leaf)
+}
+
+impl<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    > CurveTree<L, P0, P1>
+{
+    /// Inserts a leaf committing to the hash of `blob` by `Sha3LeafHasher::default()`, see `insert_data_with`.
+    #[cfg(feature = "prover")]
+    pub fn insert_data<R: RngCore + CryptoRng>(
+        &mut self,
+        blob: &[u8],
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<(usize, LeafWitness<P0>), Error> {
+        self.insert_data_with(&Sha3LeafHasher::default(), blob, parameters, rng)
+    }
+
+    /// Hashes `blob` with `hasher`, commits to the hash laid out as `data_layout` with a fresh blinding,
+    /// makes the commitment permissible and inserts it as a leaf, as `Accumulator::insert`.
+    /// Returns the index of the leaf and the witness its owner proves membership and opens the leaf with.
+    #[cfg(feature = "prover")]
+    pub fn insert_data_with<H: LeafHasher<F0>, R: RngCore + CryptoRng>(
+        &mut self,
+        hasher: &H,
+        blob: &[u8],
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<(usize, LeafWitness<P0>), Error> {
+        let data_hash = hasher.hash(blob);
+        let vector = data_layout().vector(&[(DATA_HASH, data_hash)])?;
+        let (leaf, blinding) =
+            parameters
+                .even_parameters
+                .permissible_commitment(&vector, F0::rand(rng), 0)?;
+        let index = self.insert(&[leaf], parameters)?[0];
+        Ok((
+            index,
+            LeafWitness {
+                index,
+                data_hash,
+                blinding: EvenScalar(blinding),
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type PallasScalar = ark_pallas::Fr;
+
+    #[test]
+    fn test_hasher_domains() {
+        let hasher = Sha3LeafHasher::default();
+        let hash = |hasher: &Sha3LeafHasher, blob: &[u8]| -> PallasScalar { hasher.hash(blob) };
+        assert_eq!(hash(&hasher, b"blob"), hash(&hasher, b"blob"));
+        assert_ne!(hash(&hasher, b"blob"), hash(&hasher, b"blob "));
+        let other = Sha3LeafHasher::new(crate::application_label!("test", "credential"));
+        assert_ne!(hash(&hasher, b"blob"), hash(&other, b"blob"));
+        // The length prefix keeps the domain and the blob apart.
+        assert_ne!(
+            hash(&Sha3LeafHasher::new(b"ab"), b"c"),
+            hash(&Sha3LeafHasher::new(b"a"), b"bc")
+        );
+    }
+}
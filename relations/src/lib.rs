@@ -1,3 +1,7 @@
+// Errors of the relations and of the proofs built from them
+pub mod error;
+pub use error::Error;
+
 // 3-bit lookups
 pub mod lookup;
 
@@ -22,8 +26,129 @@ pub mod single_level_select_and_rerandomize;
 // Prove that a commitment is a rerandomization of a commitment contained in a Curve Tree
 pub mod curve_tree;
 
+// Bloom filters over the leaves of curve trees, to skip the commitments that are not leaves when scanning
+pub mod bloom;
+
+// Branching factors and depths of curve trees for a number of leaves, from the costs of their membership proofs
+pub mod tree_config;
+
+// Accumulators: sets of commitments with membership proofs revealing rerandomized elements
+pub mod accumulator;
+
+// Curve trees, paths and membership proofs of a depth fixed at compile time
+pub mod shape;
+
+// Prove that rerandomized commitments, e.g. the leaves of membership proofs in two trees, hide the same commitment
+pub mod linking;
+
+// Prove that a committed variable is the x-coordinate of a rerandomized point of the other curve of the cycle
+pub mod cross_curve;
+
+// Prove that two commitments, e.g. of coins or of bare values, hide the same value under different blindings
+pub mod equal_value;
+
 // Prove that a committed variable is in the range [0, 2^k)
 pub mod range_proof;
 
+// The range of coin values and the most values balanced by a pour, carried by the parameters of the proofs
+pub mod policy;
+
+// Prove that a committed variable is not zero
+pub mod nonzero;
+
+// Whether provers check their witnesses against their statements, failing with typed errors, or prove them as they are
+pub mod strictness;
+pub use strictness::Strictness;
+
+// Constant time equality of field elements and points derived from secrets
+pub mod ct;
+
+// The labelled terms whose sum blinds a commitment, to find the wrong one when a prover's commitment does not match
+pub mod blinding;
+
+// Scalars and points typed by the curve of the cycle they belong to, crossing it by named conversions only
+pub mod cycle;
+
+// Field inversions: batched for public values and constant time for secret ones
+pub mod inversion;
+
+// Conversions between integers, little-endian bytes and field elements
+pub mod convert;
+
+// Hex encodings and short fingerprints of roots, tags and commitments for logs, configs and CLIs
+pub mod encoding;
+
+// Logs of the leaves inserted into curve trees and the tags added to tag sets, replayed to debug diverging states
+pub mod events;
+
+// Caps on the length prefixes of serialized paths, proofs, circuits and transactions
+pub mod limits;
+pub use limits::MAX_SUPPORTED_DEPTH;
+
+// Leaves committing to the hash of application data, and the witnesses their owners open them with
+pub mod leaf_data;
+
+// Registries of keys with attributes, and proofs that the attribute of a key in a registry satisfies a predicate
+pub mod registry;
+
+// Membership proofs streamed component by component in framed, hashed streams, verified as they arrive
+pub mod streaming;
+
+// Named slots of vector commitments shared by relations, and their generators
+pub mod layout;
+
+// Byte counts of the components of serialized proofs and transactions, for tuning their parameters
+pub mod size;
+
+// Commitments opened once into the variables of their slots, shared by the sub-relations on them
+pub mod opened;
+
+// Serializable circuit descriptions from which provers and verifiers are built
+pub mod circuit;
+
+// Sessions of several proofs made or verified with the same parameters, forked from one transcript
+pub mod session;
+
+// The versioned labels of the transcripts and hashes of the protocol, and of applications built on it
+pub mod protocol;
+
+// The versions of the serialized formats of proofs, paths and transactions, and the circuits each implies
+pub mod version;
+
+// Counters and histograms of verification, application and insertion, for monitoring nodes
+pub mod metrics;
+
+// Batches of transactions or proofs grouped by the context they are verified in, e.g. their format version
+pub mod batching;
+
 // Anonymous payments using Curve Trees and rerandomizable signatures
+#[cfg(feature = "payments")]
 pub mod coin;
+
+// Wallet storage of received coins keyed by their ids, and of whether they were spent on chain
+#[cfg(feature = "payments")]
+pub mod wallet;
+
+// The leaves and tags of a block staged against several curve trees and the tag set, committed at once or not at all
+#[cfg(feature = "payments")]
+pub mod staging;
+
+// A digest of the root of the tree, the spent tags and the parameters of a pool, updated as transactions are applied
+#[cfg(feature = "payments")]
+pub mod pool;
+
+// Prime fields of 64 and 381 bits for the unit tests of field-size dependent code
+#[cfg(test)]
+mod test_fields;
+
+// Known-answer test vectors of minted coins and their membership proofs, for other implementations
+#[cfg(feature = "test_vectors")]
+pub mod test_vectors;
+
+// Structurally valid arbitrary trees, layouts, policies, limits, circuits and paths for randomized tests
+#[cfg(feature = "test-arbitrary")]
+pub mod arbitrary;
+
+// Browser bindings for wallet operations
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,114 @@
+use crate::error::Error;
+
+use ark_serialize::{CanonicalDeserialize, Compress, Read, SerializationError, Validate};
+
+/// The largest height of the trees this crate builds, and of the paths and circuit steps it verifies,
+/// unless an application lowers it in its `DeserializeLimits`.
+/// A tree of branching factor 2 and this height already has 2^32 leaves.
+pub const MAX_SUPPORTED_DEPTH: usize = 32;
+
+/// Caps on the length prefixes and sizes read when deserializing paths, proofs, circuits, transactions and parameters.
+///
+/// The prefixes are controlled by whoever sent the bytes. Without caps, a single prefix would have
+/// the deserializer read elements until the input runs out, before any of them is validated.
+/// The `CanonicalDeserialize` implementations apply `DeserializeLimits::default()`,
+/// `DeserializeWithLimits` takes the limits of the application, e.g. the height of its tree.
+///
+/// The limits of the `R1CSProof`s inside do not depend on the application, bulletproofs applies its own.
+/// A pour always has two inputs and two outputs, so transactions are only capped in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeserializeLimits {
+    /// The largest height of a tree: of a circuit step, and the most commitments on either curve of a path.
+    pub max_depth: usize,
+    /// The largest branching factor of a circuit step, the number of children a vector commitment commits to.
+    pub max_arity: usize,
+    /// The most steps of a circuit description.
+    pub max_circuit_steps: usize,
+    /// The largest serialized pour of a signed transaction, in bytes.
+    pub max_transaction_size: usize,
+    /// The largest capacity of the generators of either curve of serialized parameters, derived when reading them.
+    pub max_generators: usize,
+}
+
+impl Default for DeserializeLimits {
+    /// Limits well above the trees, circuits and transactions of the benchmarks.
+    fn default() -> Self {
+        Self {
+            max_depth: MAX_SUPPORTED_DEPTH,
+            max_arity: 1 << 16,
+            max_circuit_steps: 1 << 10,
+            max_transaction_size: 1 << 20,
+            max_generators: 1 << 20,
+        }
+    }
+}
+
+impl DeserializeLimits {
+    /// Fails with `Error::LimitExceeded` if `value`, the `what` of the data, exceeds `max`.
+    pub(crate) fn check(value: u64, max: usize, what: &str) -> Result<usize, Error> {
+        if value > max as u64 {
+            return Err(Error::limit_exceeded(format!(
+                "{} is {}, the limit is {}",
+                what, value, max
+            )));
+        }
+        Ok(value as usize)
+    }
+}
+
+/// Deserialization under explicit `DeserializeLimits`, failing fast with `Error::LimitExceeded`.
+pub trait DeserializeWithLimits: Sized {
+    fn deserialize_with_limits<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+        limits: &DeserializeLimits,
+    ) -> Result<Self, Error>;
+
+    fn deserialize_compressed_with_limits<R: Read>(
+        reader: R,
+        limits: &DeserializeLimits,
+    ) -> Result<Self, Error> {
+        Self::deserialize_with_limits(reader, Compress::Yes, Validate::Yes, limits)
+    }
+}
+
+/// Deserializes a vector like `Vec::<T>::deserialize_with_mode`,
+/// but checks its length prefix against `max` before reading any element.
+pub(crate) fn deserialize_vec<T: CanonicalDeserialize, R: Read>(
+    mut reader: R,
+    compress: Compress,
+    validate: Validate,
+    max: usize,
+    what: &str,
+) -> Result<Vec<T>, Error> {
+    let len = u64::deserialize_with_mode(&mut reader, compress, validate)?;
+    let len = DeserializeLimits::check(len, max, what)?;
+    let mut values = Vec::with_capacity(len);
+    for _ in 0..len {
+        values.push(T::deserialize_with_mode(
+            &mut reader,
+            compress,
+            Validate::No,
+        )?);
+    }
+    if let Validate::Yes = validate {
+        T::batch_check(values.iter())?;
+    }
+    Ok(values)
+}
+
+/// Deserializes under the default limits, for the `CanonicalDeserialize` implementations,
+/// which can only fail with a `SerializationError`: exceeding a limit is `InvalidData`.
+pub(crate) fn deserialize_with_default_limits<T: DeserializeWithLimits, R: Read>(
+    reader: R,
+    compress: Compress,
+    validate: Validate,
+) -> Result<T, SerializationError> {
+    T::deserialize_with_limits(reader, compress, validate, &DeserializeLimits::default()).map_err(
+        |e| match e {
+            Error::Serialization(e) => e,
+            _ => SerializationError::InvalidData,
+        },
+    )
+}
@@ -0,0 +1,369 @@
+use bulletproofs::r1cs::R1CSError;
+
+use crate::convert::fe_from_le_bytes_reduced;
+use crate::curve_tree::*;
+#[cfg(feature = "prover")]
+use crate::cycle::EvenScalar;
+use crate::error::Error;
+use crate::limits::{deserialize_with_default_limits, DeserializeLimits, DeserializeWithLimits};
+use crate::protocol;
+use crate::single_level_select_and_rerandomize::*;
+
+#[cfg(feature = "prover")]
+use ark_ec::CurveGroup;
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine, AffineRepr};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use ark_serialize::{Compress, Valid, Validate};
+#[cfg(feature = "prover")]
+use ark_std::UniformRand;
+use merlin::Transcript;
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+
+/// A proof that two commitments are rerandomizations of the same commitment, i.e. that they differ by a known multiple
+/// of the blinding generator, such as the rerandomized leaves of membership proofs of the same leaf in two trees.
+///
+/// A Schnorr proof of knowledge of the difference of the blindings, the discrete logarithm of the difference
+/// of the commitments to the blinding generator, which reveals nothing about the commitments' common opening.
+#[derive(Clone, Copy)]
+pub struct SameLeafProof<P: SWCurveConfig> {
+    nonce_commitment: Affine<P>,
+    response: P::ScalarField,
+}
+
+// Absorbs the statement and the nonce commitment, and returns the challenge.
+fn same_leaf_challenge<P: SWCurveConfig>(
+    first: &Affine<P>,
+    second: &Affine<P>,
+    nonce_commitment: &Affine<P>,
+) -> P::ScalarField {
+    let mut transcript = Transcript::new(protocol::SAME_LEAF);
+    for (label, point) in [
+        (protocol::SAME_LEAF_FIRST, first),
+        (protocol::SAME_LEAF_SECOND, second),
+        (protocol::SAME_LEAF_NONCE_COMMITMENT, nonce_commitment),
+    ] {
+        let mut bytes = Vec::with_capacity(point.compressed_size());
+        point
+            .serialize_compressed(&mut bytes)
+            .expect("serializing to a vector does not fail");
+        transcript.append_message(label, &bytes);
+    }
+    let mut challenge = [0u8; 64];
+    transcript.challenge_bytes(protocol::SAME_LEAF_CHALLENGE, &mut challenge);
+    fe_from_le_bytes_reduced(&challenge)
+}
+
+/// Proves that `first` and `second`, with blindings `first_blinding` and `second_blinding`,
+/// are rerandomizations of the same commitment, e.g. the rerandomized leaves and the blindings returned by
+/// `CurveTree::prove_membership` for the same leaf in two trees.
+#[cfg(feature = "prover")]
+pub fn prove_same_leaf<P: SWCurveConfig + Copy, R: RngCore + CryptoRng>(
+    parameters: &SingleLayerParameters<P>,
+    first: &Affine<P>,
+    first_blinding: P::ScalarField,
+    second: &Affine<P>,
+    second_blinding: P::ScalarField,
+    rng: &mut R,
+) -> Result<SameLeafProof<P>, Error> {
+    let generator = parameters.pc_gens.B_blinding;
+    let delta = first_blinding - second_blinding;
+    if (first.into_group() - second) != generator * delta {
+        return Err(Error::inconsistent_witness(
+            "The commitments do not differ by the difference of their blindings",
+        ));
+    }
+    let nonce = P::ScalarField::rand(rng);
+    let nonce_commitment = (generator * nonce).into_affine();
+    let challenge = same_leaf_challenge(first, second, &nonce_commitment);
+    Ok(SameLeafProof {
+        nonce_commitment,
+        response: nonce + challenge * delta,
+    })
+}
+
+impl<P: SWCurveConfig + Copy> SameLeafProof<P> {
+    /// Verifies that `first` and `second` are rerandomizations of the same commitment.
+    pub fn verify(
+        &self,
+        parameters: &SingleLayerParameters<P>,
+        first: &Affine<P>,
+        second: &Affine<P>,
+    ) -> Result<(), Error> {
+        let challenge = same_leaf_challenge(first, second, &self.nonce_commitment);
+        let difference = first.into_group() - second;
+        if parameters.pc_gens.B_blinding * self.response
+            != self.nonce_commitment + difference * challenge
+        {
+            return Err(Error::R1CS(R1CSError::VerificationError));
+        }
+        Ok(())
+    }
+}
+
+impl<P: SWCurveConfig> CanonicalSerialize for SameLeafProof<P> {
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.nonce_commitment.serialized_size(compress) + self.response.serialized_size(compress)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.nonce_commitment
+            .serialize_with_mode(&mut writer, compress)?;
+        self.response.serialize_with_mode(&mut writer, compress)?;
+        Ok(())
+    }
+}
+
+impl<P: SWCurveConfig> Valid for SameLeafProof<P> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl<P: SWCurveConfig> CanonicalDeserialize for SameLeafProof<P> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        Ok(Self {
+            nonce_commitment: Affine::<P>::deserialize_with_mode(&mut reader, compress, validate)?,
+            response: P::ScalarField::deserialize_with_mode(&mut reader, compress, validate)?,
+        })
+    }
+}
+
+/// A proof that a leaf of a new tree is a leaf of an old tree, revealing neither:
+/// membership proofs in both trees and a proof that their rerandomized leaves hide the same leaf.
+/// Produced by `MigrationProof::prove` when moving a coin from a retiring tree to a new one.
+#[derive(Clone)]
+pub struct MigrationProof<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
+    pub old_membership: MembershipProof<L, P0, P1>,
+    pub new_membership: MembershipProof<L, P0, P1>,
+    pub same_leaf: SameLeafProof<P0>,
+}
+
+impl<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    > MigrationProof<L, P0, P1>
+{
+    /// Proves that the leaf at `new_index` of `new_tree` is the leaf at `old_index` of `old_tree`,
+    /// both with blinding `leaf_blinding`.
+    /// Returns the proof and the blinding of the rerandomized leaf of the new tree.
+    #[cfg(feature = "prover")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn prove<R: RngCore + CryptoRng>(
+        old_tree: &CurveTree<L, P0, P1>,
+        old_index: usize,
+        new_tree: &CurveTree<L, P0, P1>,
+        new_index: usize,
+        leaf_blinding: EvenScalar<P0>,
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<(Self, EvenScalar<P0>), Error> {
+        let (old_membership, old_blinding) =
+            old_tree.prove_membership(old_index, leaf_blinding, parameters, rng)?;
+        let (new_membership, new_blinding) =
+            new_tree.prove_membership(new_index, leaf_blinding, parameters, rng)?;
+        let same_leaf = prove_same_leaf(
+            &parameters.even_parameters,
+            &old_membership.rerandomized_leaf(),
+            old_blinding.0,
+            &new_membership.rerandomized_leaf(),
+            new_blinding.0,
+            rng,
+        )?;
+        Ok((
+            Self {
+                old_membership,
+                new_membership,
+                same_leaf,
+            },
+            new_blinding,
+        ))
+    }
+
+    /// Verifies both membership proofs and that their rerandomized leaves hide the same leaf,
+    /// and returns the rerandomized leaf of the new tree.
+    pub fn verify(
+        &self,
+        old_tree: &CurveTree<L, P0, P1>,
+        new_tree: &CurveTree<L, P0, P1>,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<Affine<P0>, Error> {
+        let old_leaf = self.old_membership.verify(old_tree, parameters)?;
+        let new_leaf = self.new_membership.verify(new_tree, parameters)?;
+        self.same_leaf
+            .verify(&parameters.even_parameters, &old_leaf, &new_leaf)?;
+        Ok(new_leaf)
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> CanonicalSerialize
+    for MigrationProof<L, P0, P1>
+{
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.old_membership.serialized_size(compress)
+            + self.new_membership.serialized_size(compress)
+            + self.same_leaf.serialized_size(compress)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.old_membership
+            .serialize_with_mode(&mut writer, compress)?;
+        self.new_membership
+            .serialize_with_mode(&mut writer, compress)?;
+        self.same_leaf.serialize_with_mode(&mut writer, compress)?;
+        Ok(())
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> Valid for MigrationProof<L, P0, P1> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> CanonicalDeserialize
+    for MigrationProof<L, P0, P1>
+{
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        deserialize_with_default_limits(reader, compress, validate)
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> DeserializeWithLimits
+    for MigrationProof<L, P0, P1>
+{
+    fn deserialize_with_limits<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+        limits: &DeserializeLimits,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            old_membership: MembershipProof::<L, P0, P1>::deserialize_with_limits(
+                &mut reader,
+                compress,
+                validate,
+                limits,
+            )?,
+            new_membership: MembershipProof::<L, P0, P1>::deserialize_with_limits(
+                &mut reader,
+                compress,
+                validate,
+                limits,
+            )?,
+            same_leaf: SameLeafProof::<P0>::deserialize_with_mode(&mut reader, compress, validate)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type PallasParameters = ark_pallas::PallasConfig;
+    type VestaParameters = ark_vesta::VestaConfig;
+    type PallasScalar = ark_pallas::Fr;
+
+    #[test]
+    fn test_migration() {
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 11, 1 << 11);
+        let values: Vec<_> = (0..4u64).map(PallasScalar::from).collect();
+        let (leaves, blindings): (Vec<_>, Vec<_>) = values
+            .iter()
+            .map(|v| {
+                sr_params
+                    .even_parameters
+                    .permissible_commitment(&[*v], PallasScalar::rand(&mut rng), 0)
+                    .unwrap()
+            })
+            .unzip();
+        // The coin at index 1 of the old tree moves to index 0 of the new tree.
+        let old_tree = CurveTree::<32, PallasParameters, VestaParameters>::from_set(
+            &leaves,
+            &sr_params,
+            Some(2),
+        )
+        .unwrap();
+        let new_tree = CurveTree::<32, PallasParameters, VestaParameters>::from_set(
+            &[leaves[1], leaves[3]],
+            &sr_params,
+            Some(2),
+        )
+        .unwrap();
+
+        let (proof, blinding) = MigrationProof::prove(
+            &old_tree,
+            1,
+            &new_tree,
+            0,
+            EvenScalar(blindings[1]),
+            &sr_params,
+            &mut rng,
+        )
+        .unwrap();
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        let proof =
+            MigrationProof::<32, PallasParameters, VestaParameters>::deserialize_compressed(
+                bytes.as_slice(),
+            )
+            .unwrap();
+        let new_leaf = proof.verify(&old_tree, &new_tree, &sr_params).unwrap();
+        assert_eq!(
+            new_leaf,
+            sr_params
+                .even_parameters
+                .commit(&[values[1]], blinding.0, 0)
+                .unwrap()
+        );
+        // The trees are bound to their own membership proofs.
+        assert!(proof.verify(&new_tree, &old_tree, &sr_params).is_err());
+
+        // Membership proofs of two different leaves cannot be linked.
+        let (same_proof, same_blinding) = new_tree
+            .prove_membership(0, EvenScalar(blindings[1]), &sr_params, &mut rng)
+            .unwrap();
+        let (other_proof, other_blinding) = new_tree
+            .prove_membership(1, EvenScalar(blindings[3]), &sr_params, &mut rng)
+            .unwrap();
+        assert!(matches!(
+            prove_same_leaf(
+                &sr_params.even_parameters,
+                &same_proof.rerandomized_leaf(),
+                same_blinding.0,
+                &other_proof.rerandomized_leaf(),
+                other_blinding.0,
+                &mut rng,
+            ),
+            Err(Error::InconsistentWitness { .. })
+        ));
+        let mut mixed = proof.clone();
+        mixed.new_membership = other_proof;
+        assert!(matches!(
+            mixed.verify(&old_tree, &new_tree, &sr_params),
+            Err(Error::R1CS(R1CSError::VerificationError))
+        ));
+    }
+}
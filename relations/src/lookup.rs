@@ -1,6 +1,8 @@
 use bulletproofs::r1cs::*;
 
 use ark_ff::Field;
+use std::marker::PhantomData;
+use subtle::ConstantTimeEq;
 
 const WINDOW_SIZE: usize = 3;
 pub const WINDOW_ELEMS: usize = 1 << WINDOW_SIZE;
@@ -48,38 +50,60 @@ fn bit<F: Field, Cs: ConstraintSystem<F>>(
 fn single_membership<F: Field, Cs: ConstraintSystem<F>>(
     cs: &mut Cs,
     u: &[F; WINDOW_ELEMS],
-    sa: LinearCombination<F>, // product
-    s0: LinearCombination<F>, // bit
-    s1: LinearCombination<F>, // bit
-    s2: LinearCombination<F>, // bit
+    sa: Variable<F>, // product
+    s0: Variable<F>, // bit
+    s1: Variable<F>, // bit
+    s2: Variable<F>, // bit
 ) -> LinearCombination<F> {
-    // left side
-    let (_, _, left): (Variable<F>, Variable<F>, Variable<F>) = cs.multiply(s0, {
-        let f = -(sa.clone() * u[0]) + (s2.clone() * u[0]) + (s1.clone() * u[0]) - u[0]
-            + (sa.clone() * u[2]);
-        let f = f - (s1.clone() * u[2]) + (sa.clone() * u[4])
-            - (s2.clone() * u[4])
-            - (sa.clone() * u[6]);
-        let f = f + (sa.clone() * u[1]) - (s2.clone() * u[1]) - (s1.clone() * u[1]) + u[1]
-            - (sa.clone() * u[3]);
-        f + (s1.clone() * u[3]) - (sa.clone() * u[5]) + (s2.clone() * u[5]) + (sa.clone() * u[7])
-    });
-
-    // right side
-    let right = -(sa.clone() * u[0]) + (s2.clone() * u[0]) + (s1.clone() * u[0]) - u[0]
-        + (sa.clone() * u[2]);
-    let right = right - (s1 * u[2]) + (sa.clone() * u[4]) - (s2 * u[4]) - (sa * u[6]);
+    // The terms of each side are summed per variable, so that each side is built in a single allocation.
+    let side = |a: F, c2: F, c1: F, c: F| -> LinearCombination<F> {
+        [(sa, a), (s2, c2), (s1, c1), (Variable::One(PhantomData), c)]
+            .into_iter()
+            .collect()
+    };
+    // right side: (1 - s1 - s2 + sa) u_0 + (s1 - sa) u_2 + (s2 - sa) u_4 + sa u_6, negated
+    let right_a = -u[0] + u[2] + u[4] - u[6];
+    let right = side(right_a, u[0] - u[4], u[0] - u[2], -u[0]);
+
+    // left side: s0 times the difference of the odd and even entries
+    let (_, _, left): (Variable<F>, Variable<F>, Variable<F>) = cs.multiply(
+        s0.into(),
+        side(
+            right_a + u[1] - u[3] - u[5] + u[7],
+            u[0] - u[4] - u[1] + u[5],
+            u[0] - u[2] - u[1] + u[3],
+            -u[0] + u[1],
+        ),
+    );
 
     // sum is the element
     left - right
 }
 
+// Returns one if j == index and zero otherwise, without branching on either.
+pub(crate) fn ct_indicator<F: Field>(j: usize, index: usize) -> F {
+    F::from((j as u64).ct_eq(&(index as u64)).unwrap_u8() as u64)
+}
+
 impl<const N: usize, F: Field> Lookup3Bit<N, F> {
     fn lookup(&self, index: usize) -> [F; N] {
         assert!(index < WINDOW_ELEMS);
         let val: Vec<_> = (0..N).map(|i| self.elems[i][index]).collect();
         val.try_into().unwrap()
     }
+
+    /// Returns the same entries as a lookup at `index`, but reads every entry of the table
+    /// so that the memory access pattern does not depend on the (secret) index.
+    pub fn ct_lookup(&self, index: usize) -> [F; N] {
+        let mut val = [F::zero(); N];
+        for j in 0..WINDOW_ELEMS {
+            let indicator: F = ct_indicator(j, index);
+            for (v, elems) in val.iter_mut().zip(self.elems.iter()) {
+                *v += elems[j] * indicator;
+            }
+        }
+        val
+    }
 }
 
 // The witness (provided when proving/None when verifying) is the secret index
@@ -100,14 +124,7 @@ pub fn lookup<const N: usize, F: Field, Cs: ConstraintSystem<F>>(
     // enforce membership
     let mut res: Vec<LinearCombination<_>> = Vec::with_capacity(N);
     for i in 0..N {
-        res.push(single_membership(
-            cs,
-            &table.elems[i],
-            ba.into(),
-            b0.into(),
-            b1.into(),
-            b2.into(),
-        ));
+        res.push(single_membership(cs, &table.elems[i], ba, b0, b1, b2));
     }
 
     Ok(res.try_into().unwrap())
@@ -157,6 +174,9 @@ mod tests {
         assert_eq!(l3b.lookup(0), [u[0], v[0]]);
         assert_eq!(l3b.lookup(1), [u[1], v[1]]);
         assert_eq!(l3b.lookup(7), [u[7], v[7]]);
+        for index in 0..WINDOW_ELEMS {
+            assert_eq!(l3b.ct_lookup(index), l3b.lookup(index));
+        }
 
         let pc_gens = PedersenGens::<C>::default();
         let bp_gens = BulletproofGens::<C>::new(1024, 1);
@@ -0,0 +1,283 @@
+//! Counters and histograms of the verification path, for the monitoring of a node, e.g. scraped by Prometheus.
+//!
+//! The verification context, the batch router, transaction application and tree insertion take an optional
+//! `Metrics`, which they tell what they verified, inserted and rejected. The metrics are names of this module,
+//! a failure is counted under the variant of its error, see `Error::kind`. Nothing depends on a metrics crate:
+//! `AtomicMetrics` keeps the counters in memory and renders them in the text format of Prometheus,
+//! and a node already exporting metrics implements `Metrics` over its own registry.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use crate::error::Error;
+
+/// Counter of the proofs which verify, see `circuit::SelRerandVerificationContext::with_metrics`.
+pub const PROOFS_VERIFIED: &str = "proofs_verified";
+/// Counter of the proofs which do not verify, labelled by the kind of their error.
+pub const PROOF_FAILURES: &str = "proof_failures";
+/// Histogram of the points of the generators in the multiscalar multiplication verifying a proof on one curve,
+/// 2 + 2n for a proof of n padded multipliers, to which the few dozen points of the proof add.
+pub const MSM_SIZE: &str = "msm_size";
+/// Counter of the batches verified by a `batching::Router`, whether they verify or not.
+pub const BATCHES_PROCESSED: &str = "batches_processed";
+/// Counter of the batches which do not verify as a whole, labelled by the kind of their error.
+pub const BATCH_FAILURES: &str = "batch_failures";
+/// Counter of the transactions applied, see `coin::apply_transaction_with_metrics`.
+pub const TRANSACTIONS_APPLIED: &str = "transactions_applied";
+/// Counter of the transactions rejected, labelled by the kind of their error.
+pub const TRANSACTION_FAILURES: &str = "transaction_failures";
+/// Counter of the leaves inserted into curve trees, see `CurveTree::insert_with_metrics`.
+pub const LEAVES_INSERTED: &str = "leaves_inserted";
+/// Counter of the attempts of the permissible search of the nodes committed by insertions, those on the paths of the new leaves.
+pub const PERMISSIBLE_ATTEMPTS: &str = "permissible_attempts";
+
+/// Receives the counters and observations of the code paths it is given to. Both methods do nothing by default.
+///
+/// Called on the verification path, the methods should be cheap and must not block for long:
+/// a verifier on many threads calls them concurrently.
+pub trait Metrics: Send + Sync {
+    /// Adds `by` to `counter`, under `label` if any, e.g. the kind of an error.
+    fn increment(&self, _counter: &'static str, _label: Option<&'static str>, _by: u64) {}
+
+    /// Records `value` in `histogram`.
+    fn observe(&self, _histogram: &'static str, _value: u64) {}
+}
+
+/// The metrics of code paths given none: nothing is recorded.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoMetrics;
+
+impl Metrics for NoMetrics {}
+
+/// Counts `result` under `ok` if it is a success, and under `failures` labelled by the kind of its error otherwise.
+pub(crate) fn count_result<T>(
+    metrics: &dyn Metrics,
+    ok: &'static str,
+    failures: &'static str,
+    result: &Result<T, Error>,
+) {
+    match result {
+        Ok(_) => metrics.increment(ok, None, 1),
+        Err(e) => metrics.increment(failures, Some(e.kind()), 1),
+    }
+}
+
+/// The number of observations of a histogram and their sum.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HistogramSummary {
+    pub count: u64,
+    pub sum: u64,
+}
+
+impl HistogramSummary {
+    /// The mean of the observations, none before the first.
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+}
+
+type CounterKey = (&'static str, Option<&'static str>);
+
+/// `Metrics` kept in memory in atomic counters, shared by the threads of a node, e.g. behind an `Arc`.
+/// A counter or histogram exists from its first increment or observation.
+#[derive(Debug, Default)]
+pub struct AtomicMetrics {
+    counters: RwLock<BTreeMap<CounterKey, AtomicU64>>,
+    // The number of observations and their sum.
+    histograms: RwLock<BTreeMap<&'static str, (AtomicU64, AtomicU64)>>,
+}
+
+impl AtomicMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of `counter` under `label`, zero if it was never incremented.
+    pub fn counter(&self, counter: &'static str, label: Option<&'static str>) -> u64 {
+        self.counters
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&(counter, label))
+            .map_or(0, |value| value.load(Ordering::Relaxed))
+    }
+
+    /// The sum of `counter` under all its labels and none.
+    pub fn total(&self, counter: &'static str) -> u64 {
+        self.counters
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .range((counter, None)..)
+            .take_while(|((name, _), _)| *name == counter)
+            .map(|(_, value)| value.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    pub fn histogram(&self, histogram: &'static str) -> HistogramSummary {
+        self.histograms
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(histogram)
+            .map_or_else(HistogramSummary::default, |(count, sum)| HistogramSummary {
+                count: count.load(Ordering::Relaxed),
+                sum: sum.load(Ordering::Relaxed),
+            })
+    }
+
+    /// The counters and histograms in the text format of Prometheus, each name prefixed with `prefix`:
+    /// a counter per name and label, the label as `kind`, and the `_count` and `_sum` of each histogram.
+    pub fn render(&self, prefix: &str) -> String {
+        let mut text = String::new();
+        let counters = self.counters.read().unwrap_or_else(|e| e.into_inner());
+        let mut previous = None;
+        for ((name, label), value) in counters.iter() {
+            if previous != Some(*name) {
+                writeln!(text, "# TYPE {prefix}{name} counter").expect("writing to a string");
+                previous = Some(*name);
+            }
+            let value = value.load(Ordering::Relaxed);
+            match label {
+                None => writeln!(text, "{prefix}{name} {value}"),
+                Some(label) => writeln!(text, "{prefix}{name}{{kind=\"{label}\"}} {value}"),
+            }
+            .expect("writing to a string");
+        }
+        let histograms = self.histograms.read().unwrap_or_else(|e| e.into_inner());
+        for (name, (count, sum)) in histograms.iter() {
+            writeln!(
+                text,
+                "# TYPE {prefix}{name} summary\n{prefix}{name}_count {}\n{prefix}{name}_sum {}",
+                count.load(Ordering::Relaxed),
+                sum.load(Ordering::Relaxed)
+            )
+            .expect("writing to a string");
+        }
+        text
+    }
+}
+
+impl Metrics for AtomicMetrics {
+    fn increment(&self, counter: &'static str, label: Option<&'static str>, by: u64) {
+        let key = (counter, label);
+        if let Some(value) = self
+            .counters
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&key)
+        {
+            value.fetch_add(by, Ordering::Relaxed);
+            return;
+        }
+        self.counters
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key)
+            .or_default()
+            .fetch_add(by, Ordering::Relaxed);
+    }
+
+    fn observe(&self, histogram: &'static str, value: u64) {
+        let add = |(count, sum): &(AtomicU64, AtomicU64)| {
+            count.fetch_add(1, Ordering::Relaxed);
+            sum.fetch_add(value, Ordering::Relaxed);
+        };
+        if let Some(entry) = self
+            .histograms
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(histogram)
+        {
+            return add(entry);
+        }
+        add(self
+            .histograms
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(histogram)
+            .or_default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atomic_metrics() {
+        let metrics = AtomicMetrics::new();
+        assert_eq!(metrics.counter(PROOFS_VERIFIED, None), 0);
+        assert_eq!(metrics.histogram(MSM_SIZE).mean(), None);
+
+        NoMetrics.increment(PROOFS_VERIFIED, None, 1);
+        metrics.increment(PROOFS_VERIFIED, None, 2);
+        metrics.increment(PROOFS_VERIFIED, None, 1);
+        count_result(&metrics, PROOFS_VERIFIED, PROOF_FAILURES, &Ok(()));
+        count_result::<()>(
+            &metrics,
+            PROOFS_VERIFIED,
+            PROOF_FAILURES,
+            &Err(Error::InvalidSignature),
+        );
+        count_result::<()>(
+            &metrics,
+            PROOFS_VERIFIED,
+            PROOF_FAILURES,
+            &Err(Error::InvalidPath),
+        );
+        count_result::<()>(
+            &metrics,
+            PROOFS_VERIFIED,
+            PROOF_FAILURES,
+            &Err(Error::InvalidPath),
+        );
+        assert_eq!(metrics.counter(PROOFS_VERIFIED, None), 4);
+        assert_eq!(metrics.counter(PROOF_FAILURES, Some("InvalidPath")), 2);
+        assert_eq!(metrics.counter(PROOF_FAILURES, None), 0);
+        assert_eq!(metrics.total(PROOF_FAILURES), 3);
+        assert_eq!(metrics.total(PROOFS_VERIFIED), 4);
+
+        metrics.observe(MSM_SIZE, 10);
+        metrics.observe(MSM_SIZE, 20);
+        assert_eq!(
+            metrics.histogram(MSM_SIZE),
+            HistogramSummary { count: 2, sum: 30 }
+        );
+        assert_eq!(metrics.histogram(MSM_SIZE).mean(), Some(15.0));
+
+        assert_eq!(
+            metrics.render("curve_trees_"),
+            "# TYPE curve_trees_proof_failures counter\n\
+             curve_trees_proof_failures{kind=\"InvalidPath\"} 2\n\
+             curve_trees_proof_failures{kind=\"InvalidSignature\"} 1\n\
+             # TYPE curve_trees_proofs_verified counter\n\
+             curve_trees_proofs_verified 4\n\
+             # TYPE curve_trees_msm_size summary\n\
+             curve_trees_msm_size_count 2\n\
+             curve_trees_msm_size_sum 30\n"
+        );
+    }
+
+    #[test]
+    fn test_atomic_metrics_concurrent() {
+        let metrics = AtomicMetrics::new();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for i in 0..1000 {
+                        metrics.increment(BATCHES_PROCESSED, None, 1);
+                        metrics.observe(MSM_SIZE, i);
+                    }
+                });
+            }
+        });
+        assert_eq!(metrics.counter(BATCHES_PROCESSED, None), 4000);
+        assert_eq!(
+            metrics.histogram(MSM_SIZE),
+            HistogramSummary {
+                count: 4000,
+                sum: 4 * 999 * 1000 / 2
+            }
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use bulletproofs::r1cs::*;
+
+use crate::inversion::ct_inverse;
+use crate::range_proof::range_proof;
+
+use ark_ff::PrimeField;
+
+/// Enforces that the quantity of v is not zero,
+/// by having the prover supply w such that v * w = 1.
+/// A zero assignment for v is not rejected here, instead the resulting proof does not verify.
+pub fn enforce_nonzero<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    v: LinearCombination<F>,
+    v_assignment: Option<F>,
+) -> Result<(), R1CSError> {
+    // The value is secret, so is its inverse.
+    let (a, _, o) = cs.allocate_multiplier(
+        v_assignment.map(|q| (q, ct_inverse(&q).unwrap_or_else(|_| F::zero()))),
+    )?;
+
+    // Enforce a = v
+    cs.constrain(a - v);
+
+    // Enforce v * w = 1
+    cs.constrain(o - constant(1u64));
+
+    Ok(())
+}
+
+/// Enforces that the quantity of v is in the range [1, 2^n).
+pub fn enforce_positive<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    v: LinearCombination<F>,
+    v_assignment: Option<u64>,
+    n: usize,
+) -> Result<(), R1CSError> {
+    range_proof(cs, v.clone(), v_assignment, n)?;
+    enforce_nonzero(cs, v, v_assignment.map(F::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ark_ec::AffineRepr;
+    use ark_std::UniformRand;
+    use bulletproofs::{BulletproofGens, PedersenGens};
+    use merlin::Transcript;
+
+    type VestaA = ark_vesta::Affine;
+    type VestaScalar = <VestaA as AffineRepr>::ScalarField;
+
+    fn prove_and_verify_nonzero(value: VestaScalar) -> Result<(), R1CSError> {
+        let mut rng = rand::thread_rng();
+        let pg = PedersenGens::default();
+        let bpg = BulletproofGens::new(8, 1);
+        let (proof, comm) = {
+            let mut transcript = Transcript::new(b"nonzero");
+            let mut prover: Prover<_, VestaA> = Prover::new(&pg, &mut transcript);
+            let (comm, var) = prover.commit(value, VestaScalar::rand(&mut rng));
+            enforce_nonzero(&mut prover, var.into(), Some(value))?;
+            (prover.prove(&bpg)?, comm)
+        };
+
+        let mut transcript = Transcript::new(b"nonzero");
+        let mut verifier = Verifier::new(&mut transcript);
+        let var = verifier.commit(comm);
+        enforce_nonzero(&mut verifier, var.into(), None)?;
+        verifier.verify(&proof, &pg, &bpg)
+    }
+
+    fn prove_and_verify_positive(value: u64, n: usize) -> Result<(), R1CSError> {
+        let mut rng = rand::thread_rng();
+        let pg = PedersenGens::default();
+        let bpg = BulletproofGens::new(128, 1);
+        let (proof, comm) = {
+            let mut transcript = Transcript::new(b"positive");
+            let mut prover: Prover<_, VestaA> = Prover::new(&pg, &mut transcript);
+            let (comm, var) = prover.commit(VestaScalar::from(value), VestaScalar::rand(&mut rng));
+            enforce_positive(&mut prover, var.into(), Some(value), n)?;
+            (prover.prove(&bpg)?, comm)
+        };
+
+        let mut transcript = Transcript::new(b"positive");
+        let mut verifier = Verifier::new(&mut transcript);
+        let var = verifier.commit(comm);
+        enforce_positive(&mut verifier, var.into(), None, n)?;
+        verifier.verify(&proof, &pg, &bpg)
+    }
+
+    #[test]
+    fn test_enforce_nonzero() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(prove_and_verify_nonzero(VestaScalar::from(1u64)), Ok(()));
+        assert_eq!(
+            prove_and_verify_nonzero(VestaScalar::rand(&mut rng)),
+            Ok(())
+        );
+        assert_eq!(
+            prove_and_verify_nonzero(VestaScalar::from(0u64)),
+            Err(R1CSError::VerificationError)
+        );
+    }
+
+    #[test]
+    fn test_enforce_positive() {
+        assert_eq!(prove_and_verify_positive(1, 64), Ok(()));
+        assert_eq!(prove_and_verify_positive(u64::MAX, 64), Ok(()));
+        assert_eq!(
+            prove_and_verify_positive(0, 64),
+            Err(R1CSError::VerificationError)
+        );
+        assert_eq!(
+            prove_and_verify_positive(256, 8),
+            Err(R1CSError::VerificationError)
+        );
+    }
+}
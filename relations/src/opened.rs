@@ -0,0 +1,157 @@
+//! Commitments opened once into the variables of their slots, and the sub-relations on those slots.
+//!
+//! A relation consuming a commitment, e.g. the rerandomized leaf of a membership proof, opens it into an `OpenedLeaf`
+//! and hands it to every sub-relation on the committed values: `enforce_value_range`, `enforce_tag`, `enforce_asset`.
+//! Sub-relations written by different modules compose on the same variables instead of each committing again,
+//! so the composition is explicit in the code and the commitment has a single opening in the proof.
+
+use bulletproofs::r1cs::{ConstraintSystem, Variable, Verifier};
+#[cfg(feature = "prover")]
+use bulletproofs::{r1cs::Prover, BulletproofGens};
+
+use crate::convert::u64_from_fe;
+use crate::error::Error;
+use crate::layout::{CommitmentLayout, ASSET, TAG, VALUE};
+use crate::policy::ValuePolicy;
+use crate::range_proof::{range_proof, range_proof_upper_bound};
+
+use ark_ec::AffineRepr;
+use ark_ff::{Field, PrimeField};
+use merlin::Transcript;
+
+/// The variables of a commitment opened in a constraint system, by slot of its layout,
+/// and on the prover's side the committed values.
+///
+/// The handle is not `Clone`: it is made once per commitment and lent to the sub-relations.
+#[derive(Debug)]
+pub struct OpenedLeaf<F: Field> {
+    layout: CommitmentLayout,
+    variables: Vec<Variable<F>>,
+    values: Option<Vec<F>>,
+}
+
+impl<F: Field> OpenedLeaf<F> {
+    /// Commits to `vector`, laid out as `layout`, and returns the commitment and its opening.
+    /// The layout is bound to the transcript by the caller, see `CommitmentLayout::bind`,
+    /// e.g. before the membership gadget whose rerandomized leaf is opened.
+    #[cfg(feature = "prover")]
+    pub fn commit_prover<C: AffineRepr<ScalarField = F>>(
+        prover: &mut Prover<Transcript, C>,
+        layout: &CommitmentLayout,
+        vector: &[F],
+        blinding: F,
+        bp_gens: &BulletproofGens<C>,
+    ) -> Result<(C, Self), Error> {
+        if vector.len() != layout.width() {
+            return Err(Error::inconsistent_witness(format!(
+                "The vector has {} coordinates, the layout {}",
+                vector.len(),
+                layout.width()
+            )));
+        }
+        let (commitment, variables) = prover.commit_vec(vector, blinding, bp_gens);
+        Ok((
+            commitment,
+            Self {
+                layout: layout.clone(),
+                variables,
+                values: Some(vector.to_vec()),
+            },
+        ))
+    }
+
+    /// Verifier counterpart of `commit_prover`.
+    pub fn commit_verifier<C: AffineRepr<ScalarField = F>>(
+        verifier: &mut Verifier<Transcript, C>,
+        layout: &CommitmentLayout,
+        commitment: C,
+    ) -> Self {
+        Self {
+            layout: layout.clone(),
+            variables: verifier.commit_vec(layout.width(), commitment),
+            values: None,
+        }
+    }
+
+    pub fn layout(&self) -> &CommitmentLayout {
+        &self.layout
+    }
+
+    /// The variable of the slot `name`.
+    pub fn variable(&self, name: &str) -> Result<Variable<F>, Error> {
+        Ok(self.variables[self.layout.index(name)?])
+    }
+
+    /// The committed value of the slot `name`, known to the prover only.
+    pub fn value(&self, name: &str) -> Result<Option<F>, Error> {
+        let index = self.layout.index(name)?;
+        Ok(self.values.as_ref().map(|values| values[index]))
+    }
+
+    // Constrains the slot `name` to the public `value`.
+    fn enforce_public<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        name: &str,
+        value: F,
+    ) -> Result<(), Error> {
+        cs.constrain(self.variable(name)? - value);
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> OpenedLeaf<F> {
+    /// The committed value of the slot `name` as an integer, known to the prover only,
+    /// e.g. the assignment of a range proof on the slot or on its difference with a public bound.
+    /// Fails with `Error::ValueOutOfRange` if the value does not fit in 64 bits.
+    pub fn value_u64(&self, name: &str) -> Result<Option<u64>, Error> {
+        self.value(name)?
+            .map(|value| u64_from_fe(&value))
+            .transpose()
+    }
+}
+
+/// Enforces that the value is in [0, max_value) if a bound is given, and otherwise in [0, 2^bits) of the `policy`.
+/// Fails with `Error::ParameterMismatch` for a bound above the range of the policy,
+/// and with `Error::ValueOutOfRange` on the prover's side if the committed value is not in the range of the policy.
+pub fn enforce_value_range<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    opened: &OpenedLeaf<F>,
+    max_value: Option<u64>,
+    policy: &ValuePolicy,
+) -> Result<(), Error> {
+    let value = opened.variable(VALUE)?;
+    let assignment = opened.value_u64(VALUE)?;
+    if let Some(assignment) = assignment {
+        policy.check_value(assignment)?;
+    }
+    match max_value {
+        Some(m) if m.saturating_sub(1) > policy.max_value() => {
+            return Err(Error::parameter_mismatch(format!(
+                "The bound {} is above the range of the {} bits of the policy",
+                m, policy.bits
+            )))
+        }
+        Some(m) => range_proof_upper_bound(cs, value.into(), assignment, m)?,
+        None => range_proof(cs, value.into(), assignment, policy.bits as usize)?,
+    }
+    Ok(())
+}
+
+/// Enforces that the spending tag is `tag`, revealed to the verifier.
+pub fn enforce_tag<F: Field, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    opened: &OpenedLeaf<F>,
+    tag: F,
+) -> Result<(), Error> {
+    opened.enforce_public(cs, TAG, tag)
+}
+
+/// Enforces that the asset type is `asset`, e.g. of a pool accepting a single asset.
+pub fn enforce_asset<F: Field, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    opened: &OpenedLeaf<F>,
+    asset: F,
+) -> Result<(), Error> {
+    opened.enforce_public(cs, ASSET, asset)
+}
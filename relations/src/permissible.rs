@@ -1,10 +1,14 @@
 use bulletproofs::r1cs::*;
 
+use crate::convert::fe_from_u64;
 use crate::curve::*;
+use crate::error::Error;
+use crate::single_level_select_and_rerandomize::SingleLayerParameters;
 
-use ark_ec::models::short_weierstrass::{Affine, SWCurveConfig};
+use crate::inversion::batch_into_affine;
+
+use ark_ec::models::short_weierstrass::{Affine, Projective, SWCurveConfig};
 use ark_ff::Field;
-use ark_std::rand::Rng;
 
 #[derive(Clone, Copy, Debug)]
 pub struct UniversalHash<F: Field> {
@@ -16,31 +20,112 @@ pub struct UniversalHash<F: Field> {
 }
 
 impl<F: Field> UniversalHash<F> {
-    pub fn new<R: Rng>(rng: &mut R, a: F, b: F) -> Self {
+    pub fn new(a: F, b: F) -> Self {
         Self {
             // using constants (arbitrary) for the parameters
             // of the UH, to allow deterministic reproduction
             // of the same tree.
-            // TODO: remove rng argument.
             alpha: F::try_from(42u64).unwrap(),
-           beta: F::try_from(690u64).unwrap(),
+            beta: F::try_from(690u64).unwrap(),
             a,
             b,
         }
     }
+    /// The constants of the hash and the coefficients of the curve equation.
+    pub fn constants(&self) -> [F; 4] {
+        [self.alpha, self.beta, self.a, self.b]
+    }
+
+    /// The hash of the constants and curve coefficients returned by `constants`.
+    pub(crate) fn from_constants([alpha, beta, a, b]: [F; 4]) -> Self {
+        Self { alpha, beta, a, b }
+    }
+
     /// Given a commitment c, blinded using h, returns c' and r s.t. c' = c+h*r and c' is a permissible point
+    /// The running time depends on r, i.e. the number of attempts, which is not constant time.
     pub fn permissible_commitment<S: Field, C: SWCurveConfig<BaseField = F, ScalarField = S>>(
         &self,
         c: &Affine<C>,
         h: &Affine<C>,
     ) -> (Affine<C>, C::ScalarField) {
+        #[cfg(feature = "trace")]
+        let span =
+            tracing::info_span!("permissible_search", attempts = tracing::field::Empty).entered();
         let mut r = 0u64;
         let mut c_prime = *c;
         while !self.is_permissible(c_prime) {
             c_prime = (c_prime + h).into();
             r += 1;
         }
-        (c_prime, C::ScalarField::from(r))
+        #[cfg(feature = "trace")]
+        span.record("attempts", r + 1);
+        (c_prime, fe_from_u64(r))
+    }
+
+    /// Searches permissible commitments like `permissible_commitment` for many commitments at once.
+    /// Each round of the search converts the remaining candidates to affine coordinates with a single
+    /// field inversion, instead of one inversion per candidate.
+    ///
+    /// With the `parallel` feature the commitments are split in a chunk per thread, each searched in batches.
+    /// The result is that of `permissible_commitment` for each commitment, in the same order.
+    pub fn permissible_commitments<S: Field, C: SWCurveConfig<BaseField = F, ScalarField = S>>(
+        &self,
+        cs: &[Projective<C>],
+        h: &Affine<C>,
+    ) -> Vec<(Affine<C>, C::ScalarField)> {
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!("permissible_search", commitments = cs.len()).entered();
+        #[cfg(not(feature = "parallel"))]
+        let found = self.batched_permissible_commitments(cs, h);
+        #[cfg(feature = "parallel")]
+        let found = {
+            use rayon::prelude::*;
+            let chunk_size = cs.len().div_ceil(rayon::current_num_threads()).max(1);
+            cs.par_chunks(chunk_size)
+                .flat_map_iter(|chunk| self.batched_permissible_commitments(chunk, h))
+                .collect()
+        };
+        found
+    }
+
+    fn batched_permissible_commitments<
+        S: Field,
+        C: SWCurveConfig<BaseField = F, ScalarField = S>,
+    >(
+        &self,
+        cs: &[Projective<C>],
+        h: &Affine<C>,
+    ) -> Vec<(Affine<C>, C::ScalarField)> {
+        #[cfg(feature = "trace")]
+        let span = tracing::info_span!(
+            "permissible_search_batch",
+            commitments = cs.len(),
+            rounds = tracing::field::Empty
+        )
+        .entered();
+        let mut found = vec![None; cs.len()];
+        // indices of the commitments still searched for, and their current candidates
+        let mut pending: Vec<usize> = (0..cs.len()).collect();
+        let mut candidates = cs.to_vec();
+        let mut r = 0u64;
+        while !pending.is_empty() {
+            let mut next_pending = Vec::new();
+            let mut next_candidates = Vec::new();
+            for (i, c_prime) in pending.into_iter().zip(batch_into_affine(&candidates)) {
+                if self.is_permissible(c_prime) {
+                    found[i] = Some((c_prime, fe_from_u64(r)));
+                } else {
+                    next_pending.push(i);
+                    next_candidates.push(c_prime + h);
+                }
+            }
+            pending = next_pending;
+            candidates = next_candidates;
+            r += 1;
+        }
+        #[cfg(feature = "trace")]
+        span.record("rounds", r);
+        found.into_iter().map(Option::unwrap).collect()
     }
 
     pub fn witness(&self, y: F) -> F {
@@ -49,10 +134,14 @@ impl<F: Field> UniversalHash<F> {
             .expect("point must be permissible")
     }
 
+    /// Whether the hash of the y-coordinate of `point` is a quadratic residue and that of its negation is not,
+    /// which makes the x-coordinate of a permissible point determine the point. The identity is not permissible.
+    ///
+    /// Both symbols are computed whatever the first one is, see `is_permissible`.
     pub fn is_permissible<C: SWCurveConfig<BaseField = F>>(&self, point: Affine<C>) -> bool {
         let hash_of_y_is_qr = self.universal_hash_to_bit(point.y);
         let hash_of_neg_y_is_not_qr = !self.universal_hash_to_bit(-point.y);
-        hash_of_y_is_qr && hash_of_neg_y_is_not_qr
+        !point.infinity & hash_of_y_is_qr & hash_of_neg_y_is_not_qr
     }
 
     /// returns true iff v*alpha+beta is a quadratic residue
@@ -85,10 +174,35 @@ impl<F: Field> UniversalHash<F> {
     }
 }
 
+/// Whether `point` is permissible for the parameters of its curve, the predicate every leaf and every child
+/// of a curve tree satisfies, checked with public data only, e.g. by a verifier or an indexer validating the leaf
+/// a transaction declares it inserts.
+///
+/// The same predicate is the one `UniversalHash::permissible_commitment` searches for. The search leaks how many
+/// attempts it took, this check does not: it evaluates both quadratic residuosity symbols of the point without
+/// short-circuiting, so the time it takes does not depend on which of them fails.
+pub fn is_permissible<P: SWCurveConfig + Copy>(
+    point: &Affine<P>,
+    parameters: &SingleLayerParameters<P>,
+) -> bool {
+    parameters.uh.is_permissible(*point)
+}
+
+/// Fails with `Error::NotPermissible` if `point` is not permissible, see `is_permissible`.
+pub fn assert_permissible<P: SWCurveConfig + Copy>(
+    point: &Affine<P>,
+    parameters: &SingleLayerParameters<P>,
+) -> Result<(), Error> {
+    if !is_permissible(point, parameters) {
+        return Err(Error::NotPermissible);
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ark_ec::AffineRepr;
+    use ark_ec::{AffineRepr, CurveGroup};
     use ark_std::UniformRand;
     use bulletproofs::{BulletproofGens, PedersenGens};
     use merlin::Transcript;
@@ -104,7 +218,6 @@ mod tests {
         let c = PallasA::rand(&mut rng);
         let h = PallasA::rand(&mut rng);
         let uh = UniversalHash::<PallasBase>::new(
-            &mut rng,
             ark_pallas::PallasConfig::COEFF_A,
             ark_pallas::PallasConfig::COEFF_B,
         );
@@ -134,4 +247,77 @@ mod tests {
 
         verifier.verify(&proof, &pc_gens, &bp_gens).unwrap();
     }
+
+    #[test]
+    fn test_permissible_commitments_batched() {
+        let mut rng = rand::thread_rng();
+        let h = PallasA::rand(&mut rng);
+        let uh = UniversalHash::<PallasBase>::new(
+            ark_pallas::PallasConfig::COEFF_A,
+            ark_pallas::PallasConfig::COEFF_B,
+        );
+        let cs: Vec<_> = (0..100)
+            .map(|_| ark_pallas::Projective::rand(&mut rng))
+            .collect();
+
+        #[cfg(not(feature = "parallel"))]
+        let batched = uh.permissible_commitments(&cs, &h);
+        // Several chunks, whatever the number of cores.
+        #[cfg(feature = "parallel")]
+        let batched = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap()
+            .install(|| uh.permissible_commitments(&cs, &h));
+        assert_eq!(batched.len(), cs.len());
+        for (c, found) in cs.iter().zip(batched) {
+            assert_eq!(uh.permissible_commitment(&(*c).into(), &h), found);
+        }
+    }
+
+    #[test]
+    fn test_is_permissible_pinned() {
+        // The multiples k * G of the generators that are permissible, for k from 1 to 16. A change of these sets
+        // is a change of which leaves are valid, i.e. a breaking change of the protocol.
+        const EVEN_PERMISSIBLE: [u64; 5] = [4, 8, 10, 11, 13];
+        const ODD_PERMISSIBLE: [u64; 4] = [6, 7, 8, 9];
+        let even =
+            SingleLayerParameters::<ark_pallas::PallasConfig>::new::<ark_vesta::VestaConfig>(1);
+        let odd =
+            SingleLayerParameters::<ark_vesta::VestaConfig>::new::<ark_pallas::PallasConfig>(1);
+        for k in 1..=16u64 {
+            let even_point: PallasA = (PallasA::generator() * ark_pallas::Fr::from(k)).into();
+            assert_eq!(
+                is_permissible(&even_point, &even),
+                EVEN_PERMISSIBLE.contains(&k)
+            );
+            assert_eq!(
+                assert_permissible(&even_point, &even).is_ok(),
+                EVEN_PERMISSIBLE.contains(&k)
+            );
+            let odd_point: VestaA = (VestaA::generator() * ark_vesta::Fr::from(k)).into();
+            assert_eq!(
+                is_permissible(&odd_point, &odd),
+                ODD_PERMISSIBLE.contains(&k)
+            );
+            // Of a point and its negation, sharing their x-coordinate, at most one is permissible.
+            assert!(!(is_permissible(&even_point, &even) && is_permissible(&-even_point, &even)));
+            assert!(!(is_permissible(&odd_point, &odd) && is_permissible(&-odd_point, &odd)));
+        }
+        assert!(matches!(
+            assert_permissible(&PallasA::generator(), &even),
+            Err(Error::NotPermissible)
+        ));
+        assert!(!is_permissible(&PallasA::zero(), &even));
+
+        // The search finds what the predicate accepts.
+        let (found, _) = even
+            .uh
+            .permissible_commitment(&PallasA::generator(), &PallasA::generator());
+        assert_eq!(
+            found,
+            (PallasA::generator() * ark_pallas::Fr::from(4u64)).into_affine()
+        );
+        assert_permissible(&found, &even).unwrap();
+    }
 }
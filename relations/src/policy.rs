@@ -0,0 +1,213 @@
+use crate::error::Error;
+
+use ark_ff::PrimeField;
+
+/// The bits kept free between the largest sum of a balance and the modulus of the field, see `check_soundness`.
+pub const SOUNDNESS_MARGIN_BITS: u32 = 8;
+
+/// Fails with `Error::ParameterMismatch` if the sums of the balances of `policy` come within
+/// `SOUNDNESS_MARGIN_BITS` of the modulus of `F`: the sum of the outputs of a pour minus that of its inputs
+/// is constrained to zero in `F`, which means that the integers balance only if the difference cannot wrap around
+/// the modulus. A difference of sums of `worst_case_sum_bits` bits does not wrap in a field of one bit more,
+/// the margin keeps policies widened later, e.g. of more bits or outputs, from reaching the modulus unnoticed.
+///
+/// The parameters check their policy when built with `SelRerandParameters::with_value_policy`,
+/// by `SelRerandParameters::self_check` and when they are deserialized.
+pub fn check_soundness<F: PrimeField>(policy: &ValuePolicy) -> Result<(), Error> {
+    let sum_bits = policy.worst_case_sum_bits();
+    if sum_bits + SOUNDNESS_MARGIN_BITS >= F::MODULUS_BIT_SIZE {
+        return Err(Error::parameter_mismatch(format!(
+            "Sums of {} values of {} bits have up to {} bits, within {} bits of the modulus of a field of {} bits",
+            policy.max_inputs.max(policy.max_outputs),
+            policy.bits,
+            sum_bits,
+            SOUNDNESS_MARGIN_BITS,
+            F::MODULUS_BIT_SIZE
+        )));
+    }
+    Ok(())
+}
+
+/// The range of the values of coins and fees, and the most values on either side of a balance,
+/// shared by the mints, spends and pours proven with the same `SelRerandParameters`.
+///
+/// Every value is proven in [0, 2^bits) by its own range proof, and a pour constrains the sum of its outputs,
+/// the fee included, minus the sum of its inputs to zero. The sums must not wrap around the modulus of the field
+/// for the constraint to mean that the integers balance, which `validate` checks of the bits and the counts.
+///
+/// The policy is absorbed into the fingerprint of the parameters, so proofs made under one policy
+/// are rejected under another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValuePolicy {
+    /// The number of bits of a value, at most 64.
+    pub bits: u8,
+    /// The most values subtracted in a balance, the inputs of a pour.
+    pub max_inputs: u8,
+    /// The most values added in a balance, the outputs of a pour and its fee.
+    pub max_outputs: u8,
+}
+
+impl Default for ValuePolicy {
+    /// Values of 64 bits, and the two inputs, two outputs and fee of a pour.
+    fn default() -> Self {
+        Self {
+            bits: 64,
+            max_inputs: 2,
+            max_outputs: 3,
+        }
+    }
+}
+
+impl ValuePolicy {
+    /// Fails with `Error::ParameterMismatch` if the values are not of 1 to 64 bits, if a balance has no inputs
+    /// or outputs, or if the sums of its balances come within the margin of the modulus of `F`, see `check_soundness`.
+    pub fn validate<F: PrimeField>(&self) -> Result<(), Error> {
+        if self.bits == 0 || self.bits > 64 {
+            return Err(Error::parameter_mismatch(format!(
+                "Values of {} bits, a policy has values of 1 to 64 bits",
+                self.bits
+            )));
+        }
+        if self.max_inputs == 0 || self.max_outputs == 0 {
+            return Err(Error::parameter_mismatch(
+                "A policy balances at least one input and one output",
+            ));
+        }
+        check_soundness::<F>(self)
+    }
+
+    /// The most bits of a sum of the values of one side of a balance: a sum of n values below 2^bits
+    /// is below 2^(bits + ceil(log2(n))), where n is the larger of `max_inputs` and `max_outputs`.
+    /// The difference of the sides of a balance is above -2^sum_bits and below 2^sum_bits.
+    pub fn worst_case_sum_bits(&self) -> u32 {
+        let count = self.max_inputs.max(self.max_outputs).max(1) as u32;
+        self.bits as u32 + (u32::BITS - (count - 1).leading_zeros())
+    }
+
+    /// The largest value of the policy, 2^bits - 1.
+    pub fn max_value(&self) -> u64 {
+        u64::MAX >> (64 - self.bits as u32)
+    }
+
+    /// Fails with `Error::ValueOutOfRange` if `value` is above `max_value`, before it is proven in range.
+    pub fn check_value(&self, value: u64) -> Result<(), Error> {
+        if value > self.max_value() {
+            return Err(Error::value_out_of_range(format!(
+                "{} does not fit in the {} bits of the policy",
+                value, self.bits
+            )));
+        }
+        Ok(())
+    }
+
+    /// Fails with `Error::LimitExceeded` if a balance of `inputs` values against `outputs` exceeds the counts of the policy.
+    pub fn check_counts(&self, inputs: usize, outputs: usize) -> Result<(), Error> {
+        if inputs > self.max_inputs as usize || outputs > self.max_outputs as usize {
+            return Err(Error::limit_exceeded(format!(
+                "A balance of {} inputs and {} outputs, the policy allows {} and {}",
+                inputs, outputs, self.max_inputs, self.max_outputs
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn append_fingerprint_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.extend([self.bits, self.max_inputs, self.max_outputs]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fields::Goldilocks;
+    use ark_pallas::Fr as PallasScalar;
+
+    fn policy(bits: u8, max_inputs: u8, max_outputs: u8) -> ValuePolicy {
+        ValuePolicy {
+            bits,
+            max_inputs,
+            max_outputs,
+        }
+    }
+
+    #[test]
+    fn test_validate_value_policy() {
+        ValuePolicy::default().validate::<PallasScalar>().unwrap();
+        // Sums of 255 values of 64 bits are far below the modulus of 255 bits.
+        policy(64, u8::MAX, u8::MAX)
+            .validate::<PallasScalar>()
+            .unwrap();
+        for invalid in [
+            policy(0, 2, 3),
+            policy(65, 2, 3),
+            policy(32, 0, 3),
+            policy(32, 2, 0),
+        ] {
+            assert!(matches!(
+                invalid.validate::<PallasScalar>(),
+                Err(Error::ParameterMismatch { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_worst_case_sum_bits() {
+        assert_eq!(policy(64, 1, 1).worst_case_sum_bits(), 64);
+        assert_eq!(policy(64, 2, 1).worst_case_sum_bits(), 65);
+        assert_eq!(ValuePolicy::default().worst_case_sum_bits(), 66);
+        assert_eq!(policy(32, 4, 2).worst_case_sum_bits(), 34);
+        assert_eq!(policy(32, 2, 5).worst_case_sum_bits(), 35);
+        assert_eq!(policy(64, u8::MAX, u8::MAX).worst_case_sum_bits(), 72);
+    }
+
+    #[test]
+    fn test_check_soundness_boundary() {
+        // Sums of up to 64 - 1 - 8 = 55 bits are accepted in a field of 64 bits.
+        let limit = Goldilocks::MODULUS_BIT_SIZE - 1 - SOUNDNESS_MARGIN_BITS;
+        assert_eq!(limit, 55);
+        for (accepted, rejected) in [
+            // One bit more per value.
+            (policy(54, 2, 2), policy(55, 2, 2)),
+            (policy(55, 1, 1), policy(56, 1, 1)),
+            // One more output, or input, past a power of two.
+            (policy(53, 2, 4), policy(53, 2, 5)),
+            (policy(53, 4, 3), policy(53, 5, 3)),
+            (policy(47, u8::MAX, 1), policy(48, u8::MAX, 1)),
+        ] {
+            assert_eq!(accepted.worst_case_sum_bits(), limit);
+            check_soundness::<Goldilocks>(&accepted).unwrap();
+            accepted.validate::<Goldilocks>().unwrap();
+            assert_eq!(rejected.worst_case_sum_bits(), limit + 1);
+            for result in [
+                check_soundness::<Goldilocks>(&rejected),
+                rejected.validate::<Goldilocks>(),
+            ] {
+                assert!(matches!(result, Err(Error::ParameterMismatch { .. })));
+            }
+        }
+        // The default policy needs a larger field than that of 64 bits, but not than that of Pallas.
+        assert!(check_soundness::<Goldilocks>(&ValuePolicy::default()).is_err());
+        check_soundness::<PallasScalar>(&ValuePolicy::default()).unwrap();
+        check_soundness::<PallasScalar>(&policy(64, u8::MAX, u8::MAX)).unwrap();
+    }
+
+    #[test]
+    fn test_value_policy_range() {
+        let policy = ValuePolicy {
+            bits: 32,
+            ..ValuePolicy::default()
+        };
+        assert_eq!(policy.max_value(), (1 << 32) - 1);
+        assert_eq!(ValuePolicy::default().max_value(), u64::MAX);
+        policy.check_value(policy.max_value()).unwrap();
+        assert!(matches!(
+            policy.check_value(1 << 32),
+            Err(Error::ValueOutOfRange { .. })
+        ));
+        policy.check_counts(2, 3).unwrap();
+        assert!(matches!(
+            policy.check_counts(3, 2),
+            Err(Error::LimitExceeded { .. })
+        ));
+    }
+}
@@ -0,0 +1,267 @@
+//! A compact commitment to the state of a shielded pool, for consensus and light clients.
+//!
+//! A `PoolState` holds the root of the tree of coins, its number of leaves, the fingerprint of the parameters
+//! the pool is verified with, and a hash chain over the spending tags in the order they were revealed.
+//! Its `digest` authenticates the whole state in 32 bytes, and applying a transaction folds its tags into the chain
+//! and takes the new root of the tree: the state is updated in constant time, whatever the number of tags revealed.
+//!
+//! The chain makes the digest depend on the order of the tags, unlike `TagSet::digest`, so that states which revealed
+//! the same tags in other blocks or in another order differ: consensus commits to the history, not only to the set.
+
+use crate::coin::{apply_transaction, SignedTx};
+use crate::curve_tree::{
+    root_encoding, CurveTree, CyclePoint, ParametersFingerprint, SelRerandParameters,
+};
+use crate::cycle::{EvenPoint, OddPoint};
+use crate::error::Error;
+use crate::layout::CommitmentLayout;
+use crate::limits::DeserializeLimits;
+use crate::protocol;
+use crate::wallet::TagSet;
+
+use ark_crypto_primitives::signature::schnorr::Parameters;
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine, CurveGroup};
+use ark_ff::PrimeField;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use blake2::Blake2s256 as Blake2s;
+use sha3::{Digest, Sha3_256};
+
+/// The commitment to a `PoolState`, see `PoolState::digest`.
+pub type PoolDigest = [u8; 32];
+
+/// The number of coins a pour mints, and of leaves it inserts into the tree.
+const LEAVES_PER_POUR: u64 = 2;
+
+/// The state of a shielded pool a `PoolDigest` commits to, see the module documentation.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PoolState<P0: SWCurveConfig, P1: SWCurveConfig> {
+    root: CyclePoint<P0, P1>,
+    leaf_count: u64,
+    tag_count: u64,
+    tag_chain: [u8; 32],
+    fingerprint: ParametersFingerprint,
+}
+
+impl<
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    > PoolState<P0, P1>
+{
+    /// The state of a pool with the leaves of `tree`, no tag revealed yet, verified with `parameters`.
+    pub fn new<const L: usize>(
+        tree: &CurveTree<L, P0, P1>,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Self {
+        Self {
+            root: tree.root(),
+            leaf_count: tree.leaf_count() as u64,
+            tag_count: 0,
+            tag_chain: [0u8; 32],
+            fingerprint: parameters.fingerprint(),
+        }
+    }
+
+    pub fn root(&self) -> CyclePoint<P0, P1> {
+        self.root
+    }
+
+    pub fn leaf_count(&self) -> u64 {
+        self.leaf_count
+    }
+
+    pub fn tag_count(&self) -> u64 {
+        self.tag_count
+    }
+
+    pub fn fingerprint(&self) -> ParametersFingerprint {
+        self.fingerprint
+    }
+
+    /// The hash of the parameters fingerprint, the root and number of leaves of the tree, and the number and chain of the tags.
+    pub fn digest(&self) -> PoolDigest {
+        let mut hasher = Sha3_256::new();
+        hasher.update(protocol::POOL_STATE_DOMAIN);
+        hasher.update(self.fingerprint);
+        hasher.update(root_encoding(&self.root));
+        hasher.update(self.leaf_count.to_le_bytes());
+        hasher.update(self.tag_count.to_le_bytes());
+        hasher.update(self.tag_chain);
+        hasher.finalize().into()
+    }
+
+    /// Folds a revealed spending tag into the chain of tags, e.g. for a node revealing the tags of a `TreeTransaction`.
+    /// The state does not know the tags already revealed: the caller checks the tag is not spent, as the tag set does.
+    pub fn reveal_tag(&mut self, tag: &F0) {
+        let mut encoding = Vec::with_capacity(tag.compressed_size());
+        tag.serialize_compressed(&mut encoding)
+            .expect("serializing to a vector does not fail");
+        let mut hasher = Sha3_256::new();
+        hasher.update(protocol::POOL_TAGS_DOMAIN);
+        hasher.update(self.tag_chain);
+        hasher.update(encoding);
+        self.tag_chain = hasher.finalize().into();
+        self.tag_count += 1;
+    }
+
+    /// Takes the root and the number of leaves of `tree`, once leaves were inserted into it.
+    pub fn update_tree<const L: usize>(&mut self, tree: &CurveTree<L, P0, P1>) {
+        self.root = tree.root();
+        self.leaf_count = tree.leaf_count() as u64;
+    }
+
+    /// Applies `tx` to the tree and the tag set, as `coin::apply_transaction`, and to the state:
+    /// its tags are folded in the order the pour publishes them, and the state takes the new root of the tree.
+    ///
+    /// Fails with `Error::ParameterFingerprintMismatch`, applying nothing, if the state is of a pool verified
+    /// with other parameters than `sr_parameters`. The state changes only if the transaction is applied.
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply_transaction<const L: usize, C: CurveGroup>(
+        &mut self,
+        curve_tree: &mut CurveTree<L, P0, P1>,
+        tags: &mut TagSet<F0>,
+        tx: &SignedTx<P0, P1, C>,
+        ro_domain: &'static [u8],
+        sr_parameters: &SelRerandParameters<P0, P1>,
+        layout: &CommitmentLayout,
+        sig_parameters: &Parameters<C, Blake2s>,
+    ) -> Result<Vec<usize>, Error> {
+        sr_parameters.check_fingerprint(&self.fingerprint)?;
+        let indices = apply_transaction(
+            curve_tree,
+            tags,
+            tx,
+            ro_domain,
+            sr_parameters,
+            layout,
+            sig_parameters,
+        )?;
+        for tag in tx.tags()? {
+            self.reveal_tag(&tag);
+        }
+        self.update_tree(curve_tree);
+        Ok(indices)
+    }
+}
+
+/// Checks, for a light client holding `old`, that applying `block_txs` in order leads to a state of digest `new_digest`
+/// whose tree has the root `new_root`, and returns that state.
+///
+/// The light client has no tree: it checks every transaction as `SignedTx::precheck` does against the tags of
+/// the block, and takes the new root from the full nodes, which verify the proofs against the tree and compute it.
+/// What it checks is that `new_digest` commits to `new_root`, to the leaves minted and the tags revealed by the block,
+/// in order, and to the state it held.
+/// Fails with `Error::StateMismatch` if `old` does not have the digest `old_digest`, or the new state not `new_digest`,
+/// and as `precheck` for a malformed transaction or a tag spent twice in the block.
+pub fn verify_transition<const L: usize, F0, F1, P0, P1, C>(
+    old: &PoolState<P0, P1>,
+    old_digest: &PoolDigest,
+    block_txs: &[SignedTx<P0, P1, C>],
+    new_root: CyclePoint<P0, P1>,
+    new_digest: &PoolDigest,
+) -> Result<PoolState<P0, P1>, Error>
+where
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    C: CurveGroup,
+{
+    if old.digest() != *old_digest {
+        return Err(Error::StateMismatch);
+    }
+    let limits = DeserializeLimits::default();
+    let mut block_tags = TagSet::new();
+    let mut state = *old;
+    for tx in block_txs {
+        tx.precheck::<L>(&block_tags, &limits)?;
+        for tag in tx.tags()? {
+            block_tags.insert(tag);
+            state.reveal_tag(&tag);
+        }
+        state.leaf_count += LEAVES_PER_POUR;
+    }
+    state.root = new_root;
+    if state.digest() != *new_digest {
+        return Err(Error::StateMismatch);
+    }
+    Ok(state)
+}
+
+impl<P0: SWCurveConfig, P1: SWCurveConfig> CanonicalSerialize for PoolState<P0, P1> {
+    fn serialized_size(&self, compress: Compress) -> usize {
+        let root = match &self.root {
+            CyclePoint::Even(root) => root.0.serialized_size(compress),
+            CyclePoint::Odd(root) => root.0.serialized_size(compress),
+        };
+        1 + root
+            + self.leaf_count.serialized_size(compress)
+            + self.tag_count.serialized_size(compress)
+            + self.tag_chain.serialized_size(compress)
+            + self.fingerprint.serialized_size(compress)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        match &self.root {
+            CyclePoint::Even(root) => {
+                0u8.serialize_with_mode(&mut writer, compress)?;
+                root.0.serialize_with_mode(&mut writer, compress)?;
+            }
+            CyclePoint::Odd(root) => {
+                1u8.serialize_with_mode(&mut writer, compress)?;
+                root.0.serialize_with_mode(&mut writer, compress)?;
+            }
+        }
+        self.leaf_count.serialize_with_mode(&mut writer, compress)?;
+        self.tag_count.serialize_with_mode(&mut writer, compress)?;
+        self.tag_chain.serialize_with_mode(&mut writer, compress)?;
+        self.fingerprint.serialize_with_mode(&mut writer, compress)
+    }
+}
+
+impl<P0: SWCurveConfig, P1: SWCurveConfig> Valid for PoolState<P0, P1> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl<P0: SWCurveConfig, P1: SWCurveConfig> CanonicalDeserialize for PoolState<P0, P1> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let root = match u8::deserialize_with_mode(&mut reader, compress, validate)? {
+            0 => CyclePoint::Even(EvenPoint(Affine::deserialize_with_mode(
+                &mut reader,
+                compress,
+                validate,
+            )?)),
+            1 => CyclePoint::Odd(OddPoint(Affine::deserialize_with_mode(
+                &mut reader,
+                compress,
+                validate,
+            )?)),
+            _ => return Err(SerializationError::InvalidData),
+        };
+        Ok(Self {
+            root,
+            leaf_count: u64::deserialize_with_mode(&mut reader, compress, validate)?,
+            tag_count: u64::deserialize_with_mode(&mut reader, compress, validate)?,
+            tag_chain: <[u8; 32]>::deserialize_with_mode(&mut reader, compress, validate)?,
+            fingerprint: ParametersFingerprint::deserialize_with_mode(
+                &mut reader,
+                compress,
+                validate,
+            )?,
+        })
+    }
+}
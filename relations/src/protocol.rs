@@ -0,0 +1,232 @@
+// The labels of the transcripts and hashes of the protocol, each prefixed with the protocol and its version,
+// so that the Fiat–Shamir surface is audited in one place and bumping `PROTOCOL_VERSION` changes every one of them.
+//
+// The labels of the bulletproofs crate are not here: that crate separates its own sub-protocols,
+// e.g. with the `r1cs v1` domain separator, within the transcripts created with the labels below.
+
+/// The version of the protocol, as a literal for `concat!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __protocol_version {
+    () => {
+        "2"
+    };
+}
+
+/// The version of the protocol, part of every label.
+/// Bumping it changes every transcript, so proofs of different versions never verify against each other.
+pub const PROTOCOL_VERSION: &str = __protocol_version!();
+
+/// The prefix of every label: the protocol and its version.
+pub const PROTOCOL_PREFIX: &str = concat!("curve_trees/v", __protocol_version!(), "/");
+
+macro_rules! label {
+    ($name:literal) => {
+        concat!("curve_trees/v", __protocol_version!(), "/", $name).as_bytes()
+    };
+}
+
+/// A label of an application built on the protocol, e.g. `application_label!("my_wallet", "mint")`
+/// for the transcripts of the mints of a wallet.
+///
+/// The label is in the application's own domain under `PROTOCOL_PREFIX`, so it extends the labels of the protocol
+/// and never equals one of them, whatever the application and the name.
+#[macro_export]
+macro_rules! application_label {
+    ($application:literal, $name:literal) => {
+        concat!(
+            "curve_trees/v",
+            $crate::__protocol_version!(),
+            "/application/",
+            $application,
+            "/",
+            $name
+        )
+        .as_bytes()
+    };
+}
+
+// Transcripts
+
+/// Transcript of the proofs of `CurveTree::prove_membership`.
+pub const MEMBERSHIP: &[u8] = label!("membership");
+/// Transcript of the verifiers built to check that a shape is consistent, see `shape` and `circuit`.
+pub const SHAPE: &[u8] = label!("shape");
+/// Transcript of the hash of a circuit description.
+pub const CIRCUIT_DESCRIPTION: &[u8] = label!("circuit_description");
+/// Transcript of the proofs of `prove_same_leaf`.
+pub const SAME_LEAF: &[u8] = label!("same_leaf");
+/// Transcript of the proofs of `prove_equal_value`.
+pub const EQUAL_VALUE: &[u8] = label!("equal_value");
+/// Transcript of a `ProvingSession` or `VerificationSession`, forked into the transcripts of its proofs.
+pub const SESSION: &[u8] = label!("session");
+/// Transcript of the mints of the browser bindings.
+pub const WASM_MINT: &[u8] = label!("wasm_mint");
+/// Transcript of the pours of the browser bindings.
+pub const WASM_SPEND: &[u8] = label!("wasm_spend");
+/// Transcript of the proofs of `registry::prove_attribute`.
+pub const REGISTRY_ATTRIBUTE: &[u8] = label!("registry_attribute");
+
+// Hash domains
+
+/// Domain of the hash of `Coin::id`.
+pub const COIN_ID_DOMAIN: &[u8] = label!("coin_id");
+/// Domain of the hash of `SelRerandParameters::fingerprint`.
+pub const PARAMETERS_FINGERPRINT_DOMAIN: &[u8] = label!("parameters_fingerprint_domain");
+/// Domain of the hash of `events::tags_digest`.
+pub const TAGS_DIGEST_DOMAIN: &[u8] = label!("tags_digest");
+/// Domain of the hash of the positions of a leaf in a `LeafBloom`.
+pub const LEAF_BLOOM_DOMAIN: &[u8] = label!("leaf_bloom");
+/// Domain of the hash of `pool::PoolState::digest`.
+pub const POOL_STATE_DOMAIN: &[u8] = label!("pool_state");
+/// Domain of the hash chain of the tags of a `pool::PoolState`.
+pub const POOL_TAGS_DOMAIN: &[u8] = label!("pool_tags");
+/// Domain of the hash of the data of the leaves of `CurveTree::insert_data`.
+pub const LEAF_DATA_DOMAIN: &[u8] = label!("leaf_data");
+/// Domain of the HKDF deriving the AEAD keys of the encrypted backups of `WalletStore::export_encrypted`.
+pub const WALLET_BACKUP_KEY_DOMAIN: &[u8] = label!("wallet_backup_key");
+/// Domain of `coin::element_from_bytes_stat`, hashing public keys into the scalars committed to by coins.
+pub const HASH_TO_FIELD_DOMAIN: &[u8] = label!("hash_to_field");
+/// Domain of the integrity hash closing the streams of `streaming::ProofWriter`.
+pub const PROOF_STREAM_DOMAIN: &[u8] = label!("proof_stream");
+
+// Messages and challenges
+
+/// The serialized description, in the transcript of its hash.
+pub const DESCRIPTION: &[u8] = label!("description");
+/// The hash of a circuit description, squeezed from its transcript.
+pub const DESCRIPTION_HASH: &[u8] = label!("description_hash");
+/// The hash of a circuit description, absorbed by the transcripts of its proofs.
+pub const CIRCUIT: &[u8] = label!("circuit");
+/// The width of a commitment layout.
+pub const LAYOUT_WIDTH: &[u8] = label!("layout_width");
+/// The index of a named slot of a commitment layout.
+pub const LAYOUT_INDEX: &[u8] = label!("layout_index");
+/// The name of a slot of a commitment layout.
+pub const LAYOUT_SLOT: &[u8] = label!("layout_slot");
+/// The public amount of a deposit or a withdrawal.
+pub const PUBLIC_AMOUNT: &[u8] = label!("public_amount");
+/// The predicate of a registry attribute proof, see `AttributePredicate::bind`.
+pub const ATTRIBUTE_PREDICATE: &[u8] = label!("attribute_predicate");
+/// The rerandomized point of `committed_x_gadget`.
+pub const COMMITTED_X_POINT: &[u8] = label!("committed_x_point");
+/// The rerandomized child of a select and rerandomize gadget.
+pub const RERANDOMIZED_CHILD: &[u8] = label!("rerandomized_child");
+/// The challenge squeezed from the even transcript and appended to the odd one, see `bind_transcripts`.
+pub const EVEN_TRANSCRIPT_BINDING: &[u8] = label!("even_transcript_binding");
+/// The challenge squeezed from the odd transcript and appended to the even one, see `bind_transcripts`.
+pub const ODD_TRANSCRIPT_BINDING: &[u8] = label!("odd_transcript_binding");
+/// The fingerprint of the parameters, see `SelRerandParameters::bind`.
+pub const PARAMETERS_FINGERPRINT: &[u8] = label!("parameters_fingerprint");
+/// The number of leaves of the tree of a membership proof, see `bind_leaf_count`.
+pub const LEAF_COUNT: &[u8] = label!("leaf_count");
+/// The label of a proof forked from a session, see `ProvingSession::new_prover`.
+pub const SESSION_FORK: &[u8] = label!("session_fork");
+/// The format version of the paths and transactions of a proof, see `FormatVersion::bind`.
+pub const FORMAT_VERSION: &[u8] = label!("format_version");
+/// The first commitment of a same leaf proof.
+pub const SAME_LEAF_FIRST: &[u8] = label!("same_leaf_first");
+/// The second commitment of a same leaf proof.
+pub const SAME_LEAF_SECOND: &[u8] = label!("same_leaf_second");
+/// The nonce commitment of a same leaf proof.
+pub const SAME_LEAF_NONCE_COMMITMENT: &[u8] = label!("same_leaf_nonce_commitment");
+/// The challenge of a same leaf proof.
+pub const SAME_LEAF_CHALLENGE: &[u8] = label!("same_leaf_challenge");
+/// The generators of a commitment of an equal value proof.
+pub const EQUAL_VALUE_GENERATORS: &[u8] = label!("equal_value_generators");
+/// A commitment of an equal value proof.
+pub const EQUAL_VALUE_COMMITMENT: &[u8] = label!("equal_value_commitment");
+/// A nonce commitment of an equal value proof.
+pub const EQUAL_VALUE_NONCE_COMMITMENT: &[u8] = label!("equal_value_nonce_commitment");
+/// The challenge of an equal value proof.
+pub const EQUAL_VALUE_CHALLENGE: &[u8] = label!("equal_value_challenge");
+
+/// Every label of the protocol, in the order they are declared.
+pub const LABELS: &[&[u8]] = &[
+    MEMBERSHIP,
+    SHAPE,
+    CIRCUIT_DESCRIPTION,
+    SAME_LEAF,
+    EQUAL_VALUE,
+    SESSION,
+    WASM_MINT,
+    WASM_SPEND,
+    REGISTRY_ATTRIBUTE,
+    COIN_ID_DOMAIN,
+    PARAMETERS_FINGERPRINT_DOMAIN,
+    TAGS_DIGEST_DOMAIN,
+    LEAF_BLOOM_DOMAIN,
+    POOL_STATE_DOMAIN,
+    POOL_TAGS_DOMAIN,
+    LEAF_DATA_DOMAIN,
+    WALLET_BACKUP_KEY_DOMAIN,
+    HASH_TO_FIELD_DOMAIN,
+    PROOF_STREAM_DOMAIN,
+    DESCRIPTION,
+    DESCRIPTION_HASH,
+    CIRCUIT,
+    LAYOUT_WIDTH,
+    LAYOUT_INDEX,
+    LAYOUT_SLOT,
+    PUBLIC_AMOUNT,
+    ATTRIBUTE_PREDICATE,
+    COMMITTED_X_POINT,
+    RERANDOMIZED_CHILD,
+    EVEN_TRANSCRIPT_BINDING,
+    ODD_TRANSCRIPT_BINDING,
+    PARAMETERS_FINGERPRINT,
+    LEAF_COUNT,
+    SESSION_FORK,
+    FORMAT_VERSION,
+    SAME_LEAF_FIRST,
+    SAME_LEAF_SECOND,
+    SAME_LEAF_NONCE_COMMITMENT,
+    SAME_LEAF_CHALLENGE,
+    EQUAL_VALUE_GENERATORS,
+    EQUAL_VALUE_COMMITMENT,
+    EQUAL_VALUE_NONCE_COMMITMENT,
+    EQUAL_VALUE_CHALLENGE,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha3::{Digest, Sha3_256};
+    use std::collections::HashSet;
+
+    // Changing, adding or removing a label changes this digest: update it only as part of a reviewed protocol change,
+    // bumping `PROTOCOL_VERSION` if the proofs of the previous labels are out there.
+    const LABELS_DIGEST: &str = "518cc7ee9b50cd0f46888a83079e3be0dc30e6a956bb889883267cc00f898a6b";
+
+    #[test]
+    fn test_labels_digest() {
+        let mut hasher = Sha3_256::new();
+        for label in LABELS {
+            hasher.update((label.len() as u64).to_le_bytes());
+            hasher.update(label);
+        }
+        let digest: String = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        assert_eq!(digest, LABELS_DIGEST);
+    }
+
+    #[test]
+    fn test_labels_are_distinct_and_versioned() {
+        let labels: HashSet<_> = LABELS.iter().collect();
+        assert_eq!(labels.len(), LABELS.len());
+        for label in LABELS {
+            assert!(label.starts_with(PROTOCOL_PREFIX.as_bytes()));
+        }
+        assert_eq!(
+            PROTOCOL_PREFIX,
+            format!("curve_trees/v{}/", PROTOCOL_VERSION)
+        );
+
+        let application = application_label!("wallet", "membership");
+        assert_eq!(application, b"curve_trees/v2/application/wallet/membership");
+        assert!(!LABELS.contains(&application));
+    }
+}
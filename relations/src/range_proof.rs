@@ -1,19 +1,148 @@
 use ark_ff::fields::Field;
 use bulletproofs::r1cs::*;
 
+use crate::convert::fe_from_u64;
+
 /// Enforces that the quantity of v is in the range [0, 2^n).
 pub fn range_proof<F: Field, CS: ConstraintSystem<F>>(
     cs: &mut CS,
-    mut v: LinearCombination<F>,
+    v: LinearCombination<F>,
+    v_assignment: Option<u64>,
+    n: usize,
+) -> Result<(), R1CSError> {
+    range_proof_with_bits(cs, v, v_assignment, n)?;
+    Ok(())
+}
+
+/// Enforces that the quantity of v is in the range [0, 2^n)
+/// and returns the allocated bit variables in little-endian order,
+/// so that callers can reuse them in further constraints without decomposing v again.
+pub fn range_proof_with_bits<F: Field, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    v: LinearCombination<F>,
+    v_assignment: Option<u64>,
+    n: usize,
+) -> Result<Vec<Variable<F>>, R1CSError> {
+    bit_decomposition(cs, v, v_assignment, &powers_of_two(n))
+}
+
+/// Enforces that the quantity of every element of vs is in the range [0, 2^n).
+/// The elements can be the variables returned by `commit_vec` or arbitrary linear combinations.
+/// The powers of two are computed once and shared by all the decompositions,
+/// see `range_proof_cost` for the number of multipliers and constraints this lays down.
+pub fn range_proof_vec<F: Field, CS: ConstraintSystem<F>, V: Clone + Into<LinearCombination<F>>>(
+    cs: &mut CS,
+    vs: &[V],
+    v_assignments: Option<&[u64]>,
+    n: usize,
+) -> Result<(), R1CSError> {
+    if let Some(assignments) = v_assignments {
+        if assignments.len() != vs.len() {
+            return Err(R1CSError::GadgetError {
+                description: format!(
+                    "Expected {} range proof assignments, got {}",
+                    vs.len(),
+                    assignments.len()
+                ),
+            });
+        }
+    }
+    let powers = powers_of_two(n);
+    for (i, v) in vs.iter().enumerate() {
+        bit_decomposition(cs, v.clone().into(), v_assignments.map(|a| a[i]), &powers)?;
+    }
+    Ok(())
+}
+
+/// The cost of proving that each of `values` committed quantities is in the range [0, 2^n).
+/// Every bit costs one multiplier and two linear constraints,
+/// and every value costs one additional linear constraint for the recomposition.
+pub fn range_proof_cost(values: usize, n: usize) -> Metrics {
+    Metrics {
+        multipliers: values * n,
+        constraints: values * (2 * n + 1),
+        phase_one_constraints: values * (2 * n + 1),
+        phase_two_constraints: 0,
+    }
+}
+
+/// Enforces that the quantity of v is in the range [0, m) for an arbitrary public bound m,
+/// by proving that both v and m - 1 - v are in the range [0, 2^n) with n = ceil(log2(m)).
+pub fn range_proof_upper_bound<F: Field, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    v: LinearCombination<F>,
     v_assignment: Option<u64>,
+    m: u64,
+) -> Result<(), R1CSError> {
+    if m == 0 {
+        return Err(R1CSError::GadgetError {
+            description: "The range [0, 0) is empty".to_string(),
+        });
+    }
+    let n = (64 - (m - 1).leading_zeros()) as usize;
+    let powers = powers_of_two(n);
+    bit_decomposition(cs, v.clone(), v_assignment, &powers)?;
+    // An assignment v >= m wraps around here and the resulting proof does not verify.
+    bit_decomposition(
+        cs,
+        constant(m - 1) - v,
+        v_assignment.map(|q| (m - 1).wrapping_sub(q)),
+        &powers,
+    )?;
+    Ok(())
+}
+
+/// Enforces that the signed quantity of v is in the range [-2^(n-1), 2^(n-1)),
+/// by proving that v + 2^(n-1) is in the range [0, 2^n). Requires 1 <= n <= 64.
+pub fn signed_range_proof<F: Field, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    v: LinearCombination<F>,
+    v_assignment: Option<i64>,
     n: usize,
 ) -> Result<(), R1CSError> {
+    if n == 0 || n > 64 {
+        return Err(R1CSError::GadgetError {
+            description: format!("Signed range proof over {} bits is not supported", n),
+        });
+    }
+    let offset: u64 = 1 << (n - 1);
+    // An out of range witness wraps around here and the resulting proof does not verify.
+    let shifted_assignment = v_assignment.map(|q| (q as i128 + offset as i128) as u64);
+    range_proof(cs, v + constant(offset), shifted_assignment, n)
+}
+
+/// Maps a signed integer into the field, sending negative x to the additive inverse of |x|.
+pub fn field_from_i64<F: Field>(x: i64) -> F {
+    if x < 0 {
+        -fe_from_u64::<F>(x.unsigned_abs())
+    } else {
+        fe_from_u64(x as u64)
+    }
+}
+
+// 2^0, 2^1, ..., 2^(n-1)
+fn powers_of_two<F: Field>(n: usize) -> Vec<F> {
+    let mut powers = Vec::with_capacity(n);
     let mut exp_2 = F::one();
-    for i in 0..n {
+    for _ in 0..n {
+        powers.push(exp_2);
+        exp_2 = exp_2 + exp_2;
+    }
+    powers
+}
+
+fn bit_decomposition<F: Field, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    mut v: LinearCombination<F>,
+    v_assignment: Option<u64>,
+    powers: &[F],
+) -> Result<Vec<Variable<F>>, R1CSError> {
+    let mut bits = Vec::with_capacity(powers.len());
+    for (i, exp_2) in powers.iter().enumerate() {
         // Create low-level variables and add them to constraints
         let (a, b, o) = cs.allocate_multiplier(v_assignment.map(|q| {
             let bit: u64 = (q >> i) & 1;
-            ((1 - bit).into(), bit.into())
+            (fe_from_u64(1 - bit), fe_from_u64(bit))
         }))?;
 
         // Enforce a * b = 0, so one of (a,b) is zero
@@ -25,13 +154,175 @@ pub fn range_proof<F: Field, CS: ConstraintSystem<F>>(
         // Add `-b_i*2^i` to the linear combination
         // in order to form the following constraint by the end of the loop:
         // v = Sum(b_i * 2^i, i = 0..n-1)
-        v = v - b * exp_2;
-
-        exp_2 = exp_2 + exp_2;
+        v = v - b * *exp_2;
+        bits.push(b);
     }
 
     // Enforce that v = Sum(b_i * 2^i, i = 0..n-1)
     cs.constrain(v);
 
-    Ok(())
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ark_ec::AffineRepr;
+    use ark_std::UniformRand;
+    use bulletproofs::{BulletproofGens, PedersenGens};
+    use merlin::Transcript;
+
+    type VestaA = ark_vesta::Affine;
+    type VestaScalar = <VestaA as AffineRepr>::ScalarField;
+
+    fn prove_and_verify_range_vec(values: &[u64], n: usize) -> Result<(), R1CSError> {
+        let mut rng = rand::thread_rng();
+        let pg = PedersenGens::default();
+        let bpg = BulletproofGens::new(1024, 1);
+        let (proof, comm) = {
+            let scalars: Vec<_> = values.iter().map(|v| VestaScalar::from(*v)).collect();
+            let mut transcript = Transcript::new(b"range_proof_vec");
+            let mut prover: Prover<_, VestaA> = Prover::new(&pg, &mut transcript);
+            let (comm, vars) =
+                prover.commit_vec(scalars.as_slice(), VestaScalar::rand(&mut rng), &bpg);
+            range_proof_vec(&mut prover, &vars, Some(values), n)?;
+            assert_eq!(
+                prover.metrics().multipliers,
+                range_proof_cost(values.len(), n).multipliers
+            );
+            (prover.prove(&bpg)?, comm)
+        };
+
+        let mut transcript = Transcript::new(b"range_proof_vec");
+        let mut verifier = Verifier::new(&mut transcript);
+        let vars = verifier.commit_vec(values.len(), comm);
+        range_proof_vec(&mut verifier, &vars, None, n)?;
+        verifier.verify(&proof, &pg, &bpg)
+    }
+
+    #[test]
+    fn test_range_proof_vec() {
+        let values = [0, 1, 2, 3, 5, 8, 13, u32::MAX as u64];
+        assert_eq!(prove_and_verify_range_vec(&values, 32), Ok(()));
+    }
+
+    #[test]
+    fn test_range_proof_vec_single_bad_element() {
+        let values = [0, 1, 2, 3, 1 << 32, 8, 13, 21];
+        assert_eq!(
+            prove_and_verify_range_vec(&values, 32),
+            Err(R1CSError::VerificationError)
+        );
+    }
+
+    fn prove_and_verify_high_bit(value: u64, n: usize) -> Result<(), R1CSError> {
+        let mut rng = rand::thread_rng();
+        let pg = PedersenGens::default();
+        let bpg = BulletproofGens::new(128, 1);
+        let (proof, comm) = {
+            let mut transcript = Transcript::new(b"range_proof_with_bits");
+            let mut prover: Prover<_, VestaA> = Prover::new(&pg, &mut transcript);
+            let (comm, var) = prover.commit(VestaScalar::from(value), VestaScalar::rand(&mut rng));
+            let bits = range_proof_with_bits(&mut prover, var.into(), Some(value), n)?;
+            // Reuse the most significant bit to enforce v >= 2^(n-1)
+            prover.constrain(bits[n - 1] - constant(1u64));
+            (prover.prove(&bpg)?, comm)
+        };
+
+        let mut transcript = Transcript::new(b"range_proof_with_bits");
+        let mut verifier = Verifier::new(&mut transcript);
+        let var = verifier.commit(comm);
+        let bits = range_proof_with_bits(&mut verifier, var.into(), None, n)?;
+        verifier.constrain(bits[n - 1] - constant(1u64));
+        verifier.verify(&proof, &pg, &bpg)
+    }
+
+    #[test]
+    fn test_range_proof_with_bits() {
+        assert_eq!(prove_and_verify_high_bit(128, 8), Ok(()));
+        assert_eq!(prove_and_verify_high_bit(255, 8), Ok(()));
+        assert_eq!(
+            prove_and_verify_high_bit(127, 8),
+            Err(R1CSError::VerificationError)
+        );
+    }
+
+    fn prove_and_verify_upper_bound(value: u64, m: u64) -> Result<(), R1CSError> {
+        let mut rng = rand::thread_rng();
+        let pg = PedersenGens::default();
+        let bpg = BulletproofGens::new(128, 1);
+        let (proof, comm) = {
+            let mut transcript = Transcript::new(b"range_proof_upper_bound");
+            let mut prover: Prover<_, VestaA> = Prover::new(&pg, &mut transcript);
+            let (comm, var) = prover.commit(VestaScalar::from(value), VestaScalar::rand(&mut rng));
+            range_proof_upper_bound(&mut prover, var.into(), Some(value), m)?;
+            (prover.prove(&bpg)?, comm)
+        };
+
+        let mut transcript = Transcript::new(b"range_proof_upper_bound");
+        let mut verifier = Verifier::new(&mut transcript);
+        let var = verifier.commit(comm);
+        range_proof_upper_bound(&mut verifier, var.into(), None, m)?;
+        verifier.verify(&proof, &pg, &bpg)
+    }
+
+    #[test]
+    fn test_range_proof_upper_bound() {
+        let max_supply = 21_000_000 * 100_000_000;
+        assert_eq!(prove_and_verify_upper_bound(0, max_supply), Ok(()));
+        assert_eq!(
+            prove_and_verify_upper_bound(max_supply - 1, max_supply),
+            Ok(())
+        );
+        assert_eq!(
+            prove_and_verify_upper_bound(max_supply, max_supply),
+            Err(R1CSError::VerificationError)
+        );
+        assert_eq!(prove_and_verify_upper_bound(0, 1), Ok(()));
+        assert_eq!(
+            prove_and_verify_upper_bound(1, 1),
+            Err(R1CSError::VerificationError)
+        );
+    }
+
+    fn prove_and_verify_signed_range(value: i64, n: usize) -> Result<(), R1CSError> {
+        let mut rng = rand::thread_rng();
+        let pg = PedersenGens::default();
+        let bpg = BulletproofGens::new(128, 1);
+        let (proof, comm) = {
+            let mut transcript = Transcript::new(b"signed_range_proof");
+            let mut prover: Prover<_, VestaA> = Prover::new(&pg, &mut transcript);
+            let (comm, var) = prover.commit(field_from_i64(value), VestaScalar::rand(&mut rng));
+            signed_range_proof(&mut prover, var.into(), Some(value), n)?;
+            (prover.prove(&bpg)?, comm)
+        };
+
+        let mut transcript = Transcript::new(b"signed_range_proof");
+        let mut verifier = Verifier::new(&mut transcript);
+        let var = verifier.commit(comm);
+        signed_range_proof(&mut verifier, var.into(), None, n)?;
+        verifier.verify(&proof, &pg, &bpg)
+    }
+
+    #[test]
+    fn test_signed_range_proof() {
+        for value in [i64::MIN, -1, 0, i64::MAX] {
+            assert_eq!(prove_and_verify_signed_range(value, 64), Ok(()));
+        }
+        assert_eq!(prove_and_verify_signed_range(-128, 8), Ok(()));
+        assert_eq!(prove_and_verify_signed_range(127, 8), Ok(()));
+    }
+
+    #[test]
+    fn test_signed_range_proof_out_of_range() {
+        assert_eq!(
+            prove_and_verify_signed_range(128, 8),
+            Err(R1CSError::VerificationError)
+        );
+        assert_eq!(
+            prove_and_verify_signed_range(-129, 8),
+            Err(R1CSError::VerificationError)
+        );
+    }
 }
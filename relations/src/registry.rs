@@ -0,0 +1,417 @@
+//! Registries: curve trees whose leaves commit to the x-coordinate of a public key and an attribute of its owner,
+//! e.g. an age or a score, with proofs that the key of the prover is in the registry and that its attribute satisfies
+//! a public predicate, revealing neither the key nor the attribute.
+//!
+//! The key is a point of the odd curve, whose x-coordinate is a scalar of the even curve the leaves are on.
+//! A proof composes the membership gadget of the tree with the opening of the rerandomized leaf, see `OpenedLeaf`,
+//! and the range proofs of `AttributePredicate` on the `ATTRIBUTE` slot of `registry_layout`.
+
+use bulletproofs::r1cs::{ConstraintSystem, Verifier};
+#[cfg(feature = "prover")]
+use bulletproofs::r1cs::{Prover, R1CSProof};
+
+use crate::convert::fe_from_u64;
+#[cfg(feature = "prover")]
+use crate::ct::point_ct_eq;
+#[cfg(feature = "prover")]
+use crate::curve_tree::prove_pair;
+use crate::curve_tree::{bind_leaf_count, CurveTree, MembershipProof, SelRerandParameters};
+use crate::cycle::EvenScalar;
+use crate::error::Error;
+use crate::layout::{CommitmentLayout, ATTRIBUTE, KEY_X};
+use crate::limits::{deserialize_with_default_limits, DeserializeLimits, DeserializeWithLimits};
+use crate::opened::OpenedLeaf;
+use crate::protocol;
+use crate::range_proof::{range_proof, range_proof_upper_bound};
+
+use ark_ec::models::short_weierstrass::SWCurveConfig;
+#[cfg(feature = "prover")]
+use ark_ec::{short_weierstrass::Affine, AffineRepr};
+use ark_ff::PrimeField;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Valid, Write,
+};
+use merlin::Transcript;
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+
+/// The layout of the leaves of a registry: the x-coordinate of the key, then the attribute.
+pub fn registry_layout() -> CommitmentLayout {
+    CommitmentLayout::new(&[(KEY_X, 0), (ATTRIBUTE, 1)]).expect("the slots are distinct")
+}
+
+/// A public statement about the attribute of a registry entry, bounds included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributePredicate {
+    /// The attribute is in `[min, max]`.
+    Range { min: u64, max: u64 },
+    /// The attribute is at least the bound, e.g. an age of at least 18.
+    GreaterEq(u64),
+    /// The attribute is the value.
+    Equals(u64),
+}
+
+impl AttributePredicate {
+    /// Fails with `Error::ParameterMismatch` for an empty range.
+    pub fn validate(&self) -> Result<(), Error> {
+        match *self {
+            Self::Range { min, max } if min > max => Err(Error::parameter_mismatch(format!(
+                "The range [{}, {}] of the predicate is empty",
+                min, max
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether `attribute` satisfies the predicate.
+    pub fn holds(&self, attribute: u64) -> bool {
+        match *self {
+            Self::Range { min, max } => (min..=max).contains(&attribute),
+            Self::GreaterEq(min) => attribute >= min,
+            Self::Equals(value) => attribute == value,
+        }
+    }
+
+    /// Appends the predicate to the transcript of the even proof, which lays down its constraints,
+    /// so that a proof of one predicate does not verify for another with the same number of multipliers.
+    pub fn bind(&self, transcript: &mut Transcript) {
+        let (variant, first, second) = match *self {
+            Self::Range { min, max } => (0u8, min, max),
+            Self::GreaterEq(min) => (1, min, 0),
+            Self::Equals(value) => (2, value, 0),
+        };
+        let mut bytes = vec![variant];
+        bytes.extend_from_slice(&first.to_le_bytes());
+        bytes.extend_from_slice(&second.to_le_bytes());
+        transcript.append_message(protocol::ATTRIBUTE_PREDICATE, &bytes);
+    }
+
+    /// Enforces the predicate on the `ATTRIBUTE` slot of `opened`: a range proof of the attribute minus the lower bound,
+    /// of 64 bits or below the width of the range, or the equality of the attribute with the public value.
+    /// On the prover's side, an attribute which does not satisfy the predicate makes a proof which does not verify.
+    pub fn enforce<F: PrimeField, CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        opened: &OpenedLeaf<F>,
+    ) -> Result<(), Error> {
+        self.validate()?;
+        let attribute = opened.variable(ATTRIBUTE)?;
+        let assignment = opened.value_u64(ATTRIBUTE)?;
+        match *self {
+            Self::Range { min, max } => {
+                let shifted = attribute - cs.public_constant(fe_from_u64(min));
+                let shifted_assignment = assignment.map(|a| a.wrapping_sub(min));
+                match (max - min).checked_add(1) {
+                    Some(width) => range_proof_upper_bound(cs, shifted, shifted_assignment, width)?,
+                    None => range_proof(cs, shifted, shifted_assignment, 64)?,
+                }
+            }
+            Self::GreaterEq(min) => {
+                let shifted = attribute - cs.public_constant(fe_from_u64(min));
+                range_proof(cs, shifted, assignment.map(|a| a.wrapping_sub(min)), 64)?
+            }
+            Self::Equals(value) => {
+                let value = cs.public_constant(fe_from_u64(value));
+                cs.constrain(attribute - value)
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What the owner of a registry entry keeps to prove its attribute, see `registry_entry`.
+/// There is no `Debug`: the blinding is secret.
+#[derive(Clone)]
+pub struct RegistryWitness<P: SWCurveConfig> {
+    /// The x-coordinate of the key of the entry.
+    pub key_x: P::ScalarField,
+    pub attribute: u64,
+    /// The blinding of the leaf, the fresh blinding plus the offset of the permissible search.
+    pub blinding: EvenScalar<P>,
+}
+
+impl<P: SWCurveConfig> RegistryWitness<P> {
+    /// The vector committed to by the leaf, laid out as `registry_layout`.
+    pub fn vector(&self) -> Vec<P::ScalarField> {
+        registry_layout()
+            .vector(&[
+                (KEY_X, self.key_x),
+                (ATTRIBUTE, fe_from_u64(self.attribute)),
+            ])
+            .expect("the layout has the slots")
+    }
+}
+
+/// The permissible leaf of the entry of `key` with `attribute`, to be inserted into a registry,
+/// and the witness its owner proves the attribute with.
+/// Fails with `Error::IdentityPoint` if the key is the identity, which has no x-coordinate.
+#[cfg(feature = "prover")]
+pub fn registry_entry<
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+    R: RngCore + CryptoRng,
+>(
+    key: &Affine<P1>,
+    attribute: u64,
+    parameters: &SelRerandParameters<P0, P1>,
+    rng: &mut R,
+) -> Result<(Affine<P0>, RegistryWitness<P0>), Error> {
+    let (key_x, _) = key.xy().ok_or(Error::IdentityPoint)?;
+    let mut witness = RegistryWitness {
+        key_x: *key_x,
+        attribute,
+        blinding: EvenScalar(F0::zero()),
+    };
+    let (leaf, blinding) =
+        parameters
+            .even_parameters
+            .permissible_commitment(&witness.vector(), F0::rand(rng), 0)?;
+    witness.blinding = EvenScalar(blinding);
+    Ok((leaf, witness))
+}
+
+/// A proof that a leaf of a registry commits to an attribute satisfying a predicate, see `prove_attribute`.
+///
+/// The membership proof is made with the transcripts of `protocol::REGISTRY_ATTRIBUTE`, which also carry
+/// the predicate and its constraints: it is verified by `verify_attribute`, not by `MembershipProof::verify`.
+#[derive(Clone)]
+pub struct AttributeProof<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
+    pub membership: MembershipProof<L, P0, P1>,
+}
+
+/// Proves that the leaf at `index` of `tree`, opened by `witness`, is in the tree and commits to an attribute
+/// satisfying `predicate`, revealing neither the leaf nor the attribute.
+///
+/// Fails with `Error::InconsistentWitness` if the attribute does not satisfy the predicate,
+/// or if the witness does not open the leaf, and with `Error::ParameterMismatch` if the predicate is empty.
+#[cfg(feature = "prover")]
+pub fn prove_attribute<
+    const L: usize,
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+    R: RngCore + CryptoRng,
+>(
+    tree: &CurveTree<L, P0, P1>,
+    index: usize,
+    witness: &RegistryWitness<P0>,
+    predicate: AttributePredicate,
+    parameters: &SelRerandParameters<P0, P1>,
+    rng: &mut R,
+) -> Result<AttributeProof<L, P0, P1>, Error> {
+    predicate.validate()?;
+    if !predicate.holds(witness.attribute) {
+        return Err(Error::inconsistent_witness(format!(
+            "The attribute does not satisfy the predicate {:?}",
+            predicate
+        )));
+    }
+    let mut even_prover = Prover::new(
+        &parameters.even_parameters.pc_gens,
+        Transcript::new(protocol::REGISTRY_ATTRIBUTE),
+    );
+    let mut odd_prover = Prover::new(
+        &parameters.odd_parameters.pc_gens,
+        Transcript::new(protocol::REGISTRY_ATTRIBUTE),
+    );
+    let leaf_count = tree.leaf_count() as u64;
+    bind_leaf_count(
+        even_prover.transcript(),
+        odd_prover.transcript(),
+        leaf_count,
+    );
+    predicate.bind(even_prover.transcript());
+    let (path, rerandomization) = tree.select_and_rerandomize_prover_gadget(
+        index,
+        &mut even_prover,
+        &mut odd_prover,
+        parameters,
+        rng,
+    )?;
+
+    let layout = registry_layout();
+    layout.bind(even_prover.transcript());
+    let (rerandomized_leaf, opened) = OpenedLeaf::commit_prover(
+        &mut even_prover,
+        &layout,
+        &witness.vector(),
+        (witness.blinding + rerandomization).0,
+        &parameters.even_parameters.bp_gens,
+    )?;
+    if !bool::from(point_ct_eq(
+        &path.get_rerandomized_leaf(),
+        &rerandomized_leaf,
+    )) {
+        return Err(Error::inconsistent_witness(format!(
+            "The witness does not open the leaf at index {}",
+            index
+        )));
+    }
+    predicate.enforce(&mut even_prover, &opened)?;
+
+    let (even_proof, odd_proof): (R1CSProof<Affine<P0>>, R1CSProof<Affine<P1>>) =
+        prove_pair(even_prover, odd_prover, parameters)?;
+    Ok(AttributeProof {
+        membership: MembershipProof {
+            path,
+            even_proof,
+            odd_proof,
+            parameters_fingerprint: parameters.fingerprint(),
+            leaf_count,
+        },
+    })
+}
+
+/// Verifies that `proof` proves that a leaf of `tree` commits to an attribute satisfying `predicate`.
+pub fn verify_attribute<
+    const L: usize,
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+>(
+    tree: &CurveTree<L, P0, P1>,
+    proof: &AttributeProof<L, P0, P1>,
+    predicate: AttributePredicate,
+    parameters: &SelRerandParameters<P0, P1>,
+) -> Result<(), Error> {
+    let proof = &proof.membership;
+    predicate.validate()?;
+    parameters.check_fingerprint(&proof.parameters_fingerprint)?;
+    if !tree.is_valid_path(&proof.path) || proof.leaf_count != tree.leaf_count() as u64 {
+        return Err(Error::InvalidPath);
+    }
+    let mut even_verifier = Verifier::new(Transcript::new(protocol::REGISTRY_ATTRIBUTE));
+    let mut odd_verifier = Verifier::new(Transcript::new(protocol::REGISTRY_ATTRIBUTE));
+    bind_leaf_count(
+        even_verifier.transcript(),
+        odd_verifier.transcript(),
+        proof.leaf_count,
+    );
+    predicate.bind(even_verifier.transcript());
+    let rerandomized_leaf = tree.select_and_rerandomize_verifier_gadget(
+        &mut even_verifier,
+        &mut odd_verifier,
+        proof.path.clone(),
+        parameters,
+    )?;
+
+    let layout = registry_layout();
+    layout.bind(even_verifier.transcript());
+    let opened = OpenedLeaf::commit_verifier(&mut even_verifier, &layout, rerandomized_leaf);
+    predicate.enforce(&mut even_verifier, &opened)?;
+
+    even_verifier.verify(
+        &proof.even_proof,
+        &parameters.even_parameters.pc_gens,
+        &parameters.even_parameters.bp_gens,
+    )?;
+    odd_verifier.verify(
+        &proof.odd_proof,
+        &parameters.odd_parameters.pc_gens,
+        &parameters.odd_parameters.bp_gens,
+    )?;
+    Ok(())
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> CanonicalSerialize
+    for AttributeProof<L, P0, P1>
+{
+    fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        self.membership.serialized_size(compress)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), SerializationError> {
+        self.membership.serialize_with_mode(writer, compress)
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> Valid for AttributeProof<L, P0, P1> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> CanonicalDeserialize
+    for AttributeProof<L, P0, P1>
+{
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, SerializationError> {
+        deserialize_with_default_limits(reader, compress, validate)
+    }
+}
+
+impl<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> DeserializeWithLimits
+    for AttributeProof<L, P0, P1>
+{
+    fn deserialize_with_limits<R: Read>(
+        reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+        limits: &DeserializeLimits,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            membership: MembershipProof::deserialize_with_limits(
+                reader, compress, validate, limits,
+            )?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_predicates() {
+        let range = AttributePredicate::Range { min: 18, max: 65 };
+        assert!(range.holds(18) && range.holds(65) && range.holds(40));
+        assert!(!range.holds(17) && !range.holds(66));
+        assert!(AttributePredicate::GreaterEq(18).holds(u64::MAX));
+        assert!(!AttributePredicate::GreaterEq(18).holds(0));
+        assert!(AttributePredicate::Equals(7).holds(7));
+        assert!(!AttributePredicate::Equals(7).holds(8));
+        assert!(AttributePredicate::Range {
+            min: 0,
+            max: u64::MAX
+        }
+        .validate()
+        .is_ok());
+        assert!(matches!(
+            AttributePredicate::Range { min: 2, max: 1 }.validate(),
+            Err(Error::ParameterMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_predicates_bind_distinct_transcripts() {
+        let challenge = |predicate: AttributePredicate| {
+            let mut transcript = Transcript::new(b"test");
+            predicate.bind(&mut transcript);
+            let mut bytes = [0u8; 32];
+            transcript.challenge_bytes(b"challenge", &mut bytes);
+            bytes
+        };
+        let predicates = [
+            AttributePredicate::Range { min: 18, max: 18 },
+            AttributePredicate::GreaterEq(18),
+            AttributePredicate::Equals(18),
+            AttributePredicate::Range { min: 18, max: 65 },
+        ];
+        for (i, first) in predicates.iter().enumerate() {
+            for second in &predicates[i + 1..] {
+                assert_ne!(challenge(*first), challenge(*second));
+            }
+        }
+    }
+}
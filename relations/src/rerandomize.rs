@@ -1,6 +1,7 @@
 use bulletproofs::r1cs::*;
 
 use crate::curve::*;
+use crate::inversion::ct_inverse;
 use crate::lookup::*;
 
 use ark_ec::{
@@ -48,7 +49,7 @@ pub fn build_tables<C: AffineRepr>(h: C) -> Vec<Lookup3Bit<2, C::BaseField>> {
 }
 
 pub fn re_randomize<
-    F: Field,
+    F: PrimeField,
     S: PrimeField,
     P: SWCurveConfig<BaseField = F, ScalarField = S>,
     Cs: ConstraintSystem<F>,
@@ -82,15 +83,12 @@ pub fn re_randomize<
             None => (None, None, None, None, None),
             Some(random_bits) => {
                 let bi = (i - 1) * 3;
-                let mut index: usize = usize::from(bi < lambda && random_bits[bi]);
-                if bi + 1 < lambda && random_bits[bi + 1] {
-                    index += 2;
-                };
-                if bi + 2 < lambda && random_bits[bi + 2] {
-                    index += 4;
-                };
-                let x_i_lookup = table.elems[0][index];
-                let y_i_lookup = table.elems[1][index];
+                // The window is assembled without branching on the secret bits
+                // and the table is read in full, see `Lookup3Bit::ct_lookup`.
+                let index: usize = usize::from(bi < lambda && random_bits[bi])
+                    + 2 * usize::from(bi + 1 < lambda && random_bits[bi + 1])
+                    + 4 * usize::from(bi + 2 < lambda && random_bits[bi + 2]);
+                let [x_i_lookup, y_i_lookup] = table.ct_lookup(index);
                 let x_left = if i == 1 {
                     F::zero()
                 } else {
@@ -104,14 +102,15 @@ pub fn re_randomize<
                 let x_right = x_i_lookup;
                 let y_right = y_i_lookup;
                 // compute slope delta
+                // The accumulated blinding is secret, so are the differences inverted.
                 let delta = if i != 1 {
-                    Some((y_right - y_left) / (x_right - x_left))
+                    Some((y_right - y_left) * ct_inverse(&(x_right - x_left)).unwrap())
                 } else {
                     None
                 };
                 let x_left_minus_x_right_inv = if i == m {
                     // compute x_l-x_r inverse for checked addition
-                    Some(F::one() / (x_left - x_right))
+                    Some(ct_inverse(&(x_left - x_right)).unwrap())
                 } else {
                     None
                 };
@@ -136,9 +135,10 @@ pub fn re_randomize<
         if i > 1 {
             // Enforce addition constraint:
             // R_i = R_i-1 + (x_i, y_i)
+            // The accumulator of the previous window is not used after this addition.
             let prms = CurveAddition {
-                x_l: acc_i_minus_1_x_lc.clone(),
-                y_l: acc_i_minus_1_y_lc.clone(),
+                x_l: acc_i_minus_1_x_lc,
+                y_l: acc_i_minus_1_y_lc,
                 x_r: x_table,
                 y_r: y_table,
                 x_o: acc_i_x_lc.clone(),
@@ -164,8 +164,8 @@ pub fn re_randomize<
             let y_left = commitment.y().unwrap();
             let x_right = blinding_accumulator.x().unwrap();
             let y_right = blinding_accumulator.y().unwrap();
-            let delta = (*y_right - y_left) / (*x_right - x_left);
-            (Some(delta), Some(F::one() / (*x_left - x_right)))
+            let delta = (*y_right - y_left) * ct_inverse(&(*x_right - x_left)).unwrap();
+            (Some(delta), Some(ct_inverse(&(*x_left - x_right)).unwrap()))
         }
         _ => (None, None),
     };
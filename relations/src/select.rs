@@ -3,20 +3,40 @@ use bulletproofs::r1cs::*;
 use ark_ff::Field;
 
 /// Prove that a commitment x is one of the values committed to in vector commitment xs.
+/// No position is bound: for a value repeated in xs, the constraint holds for each of its positions alike.
 pub fn select<F: Field, Cs: ConstraintSystem<F>>(
     cs: &mut Cs,
-    x: LinearCombination<F>,
-    xs: Vec<LinearCombination<F>>,
+    x: &LinearCombination<F>,
+    xs: &[LinearCombination<F>],
 ) {
-    assert!(!xs.is_empty());
+    select_multi(cs, x, &[xs]);
+}
+
+/// Prove that a commitment x is one of the values committed to in any of the vector commitments xss.
+/// The running product is carried across chunk boundaries,
+/// so the cost is the same as a single `select` over the concatenation of the chunks.
+///
+/// The linear combinations are borrowed, each difference is built in a single allocation.
+pub fn select_multi<F: Field, Cs: ConstraintSystem<F>>(
+    cs: &mut Cs,
+    x: &LinearCombination<F>,
+    xss: &[&[LinearCombination<F>]],
+) {
+    assert!(xss.iter().any(|xs| !xs.is_empty()));
 
     // (x_1 - x) * (x_2 - x) * ... * (x_n - x) = 0
-    let mut product: LinearCombination<F> = xs[0].clone();
-    for xi in xs.iter() {
-        let (_, _, next_product) = cs.multiply(product, xi.clone() - x.clone());
-        product = next_product.into();
+    let mut product: Option<LinearCombination<F>> = None;
+    for xi in xss.iter().copied().flatten() {
+        let difference = xi - x;
+        product = Some(match product {
+            None => difference,
+            Some(product) => {
+                let (_, _, next_product) = cs.multiply(product, difference);
+                next_product.into()
+            }
+        });
     }
-    cs.constrain(product);
+    cs.constrain(product.unwrap());
 }
 
 #[cfg(test)]
@@ -55,11 +75,8 @@ mod tests {
             let blinding_x = PallasBase::rand(&mut rng);
             let (x_comm, x_var) = prover.commit(x, blinding_x);
 
-            select(
-                &mut prover,
-                x_var.into(),
-                xs_vars.into_iter().map(|v| v.into()).collect(),
-            );
+            let xs_lcs: Vec<_> = xs_vars.into_iter().map(|v| v.into()).collect();
+            select(&mut prover, &x_var.into(), &xs_lcs);
 
             let proof = prover.prove(&bpg).unwrap();
             (proof, xs_comm, x_comm)
@@ -71,13 +88,75 @@ mod tests {
         let xs_vars = verifier.commit_vec(256, xs_comm);
         let x_var = verifier.commit(x_comm);
 
-        select(
-            &mut verifier,
-            x_var.into(),
-            xs_vars.into_iter().map(|v| v.into()).collect(),
-        );
+        let xs_lcs: Vec<_> = xs_vars.into_iter().map(|v| v.into()).collect();
+        select(&mut verifier, &x_var.into(), &xs_lcs);
 
         let res = verifier.verify(&proof, &pg, &bpg);
         assert_eq!(res, Ok(()))
     }
+
+    fn prove_and_verify_select_multi(chunk_sizes: &[usize], index: usize) -> Result<(), R1CSError> {
+        let mut rng = rand::thread_rng();
+        let pg = PedersenGens::default();
+        let bpg = BulletproofGens::new(256, 1);
+        let (proof, xs_comms, x_comm) = {
+            let xss: Vec<Vec<_>> = chunk_sizes
+                .iter()
+                .map(|size| {
+                    iter::from_fn(|| Some(VestaScalar::rand(&mut rng)))
+                        .take(*size)
+                        .collect()
+                })
+                .collect();
+            let x = xss.concat()[index];
+
+            let mut transcript = Transcript::new(b"select_multi");
+            let mut prover: Prover<_, VestaA> = Prover::new(&pg, &mut transcript);
+            let (xs_comms, xss_vars): (Vec<_>, Vec<_>) = xss
+                .iter()
+                .map(|xs| prover.commit_vec(xs.as_slice(), PallasBase::rand(&mut rng), &bpg))
+                .unzip();
+            let (x_comm, x_var) = prover.commit(x, PallasBase::rand(&mut rng));
+
+            let xss_lcs: Vec<Vec<_>> = xss_vars
+                .into_iter()
+                .map(|xs_vars| xs_vars.into_iter().map(|v| v.into()).collect())
+                .collect();
+            let xss_lcs: Vec<&[_]> = xss_lcs.iter().map(Vec::as_slice).collect();
+            select_multi(&mut prover, &x_var.into(), &xss_lcs);
+            // one multiplier per element, but the first
+            let total: usize = chunk_sizes.iter().sum();
+            assert_eq!(prover.metrics().multipliers, total - 1);
+
+            (prover.prove(&bpg)?, xs_comms, x_comm)
+        };
+
+        let mut transcript = Transcript::new(b"select_multi");
+        let mut verifier = Verifier::new(&mut transcript);
+
+        let xss_vars: Vec<_> = chunk_sizes
+            .iter()
+            .zip(xs_comms)
+            .map(|(size, comm)| verifier.commit_vec(*size, comm))
+            .collect();
+        let x_var = verifier.commit(x_comm);
+
+        let xss_lcs: Vec<Vec<_>> = xss_vars
+            .into_iter()
+            .map(|xs_vars| xs_vars.into_iter().map(|v| v.into()).collect())
+            .collect();
+        let xss_lcs: Vec<&[_]> = xss_lcs.iter().map(Vec::as_slice).collect();
+        select_multi(&mut verifier, &x_var.into(), &xss_lcs);
+
+        verifier.verify(&proof, &pg, &bpg)
+    }
+
+    #[test]
+    fn test_select_multi() {
+        let chunk_sizes = [32, 16, 32];
+        // first, middle and last chunk
+        for index in [0, 40, 79] {
+            assert_eq!(prove_and_verify_select_multi(&chunk_sizes, index), Ok(()));
+        }
+    }
 }
@@ -0,0 +1,196 @@
+//! Sessions of several proofs made or verified with the same parameters, e.g. a proof of reserves and then a payment.
+//!
+//! A session absorbs the fingerprint of its parameters once into a base transcript, and each proof starts from a
+//! clone of it with its own label appended: forking is a copy of the transcript state, and the proofs of distinct
+//! labels are independent, so a verifier checks them in any order and one invalid proof leaves the others valid.
+//! The generators are borrowed from the parameters by every fork, never copied.
+
+#[cfg(feature = "prover")]
+use bulletproofs::r1cs::Prover;
+use bulletproofs::r1cs::Verifier;
+
+use crate::circuit::{CircuitDescription, CircuitProof, StepStatement};
+#[cfg(feature = "prover")]
+use crate::circuit::{ProvenCircuit, StepWitness};
+use crate::curve_tree::{CurveTree, ParametersFingerprint, SelRerandParameters};
+use crate::error::Error;
+use crate::protocol;
+
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine};
+use ark_ff::PrimeField;
+use merlin::Transcript;
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "prover")]
+use std::collections::HashSet;
+
+/// The provers of both curves of a proof forked from a `ProvingSession`.
+#[cfg(feature = "prover")]
+pub type SessionProvers<'a, P0, P1> = (
+    Prover<'a, Transcript, Affine<P0>>,
+    Prover<'a, Transcript, Affine<P1>>,
+);
+
+/// The verifiers of both curves of a proof forked from a `VerificationSession`.
+pub type SessionVerifiers<P0, P1> = (
+    Verifier<Transcript, Affine<P0>>,
+    Verifier<Transcript, Affine<P1>>,
+);
+
+// The transcript every proof of a session is forked from.
+fn base_transcript(fingerprint: &ParametersFingerprint) -> Transcript {
+    let mut transcript = Transcript::new(protocol::SESSION);
+    transcript.append_message(protocol::PARAMETERS_FINGERPRINT, fingerprint);
+    transcript
+}
+
+// The transcripts of both curves of the proof labelled `label`.
+fn fork(base: &Transcript, label: &'static [u8]) -> (Transcript, Transcript) {
+    let mut transcript = base.clone();
+    transcript.append_message(protocol::SESSION_FORK, label);
+    (transcript.clone(), transcript)
+}
+
+/// Makes several proofs with the same parameters, see the module documentation.
+/// Each proof is labelled, and a label is used for a single proof of the session.
+#[cfg(feature = "prover")]
+pub struct ProvingSession<'a, P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy> {
+    parameters: &'a SelRerandParameters<P0, P1>,
+    fingerprint: ParametersFingerprint,
+    transcript: Transcript,
+    labels: HashSet<&'static [u8]>,
+}
+
+#[cfg(feature = "prover")]
+impl<
+        'a,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    > ProvingSession<'a, P0, P1>
+{
+    pub fn new(parameters: &'a SelRerandParameters<P0, P1>) -> Self {
+        let fingerprint = parameters.fingerprint();
+        Self {
+            parameters,
+            fingerprint,
+            transcript: base_transcript(&fingerprint),
+            labels: HashSet::new(),
+        }
+    }
+
+    pub fn parameters(&self) -> &'a SelRerandParameters<P0, P1> {
+        self.parameters
+    }
+
+    /// The fingerprint of the parameters, computed once for the session.
+    pub fn fingerprint(&self) -> &ParametersFingerprint {
+        &self.fingerprint
+    }
+
+    /// The provers of both curves of the proof labelled `label`, verified with `VerificationSession::new_verifier`.
+    /// Fails with `Error::ParameterMismatch` if the session already forked a proof with this label.
+    pub fn new_prover(
+        &mut self,
+        label: &'static [u8],
+    ) -> Result<SessionProvers<'a, P0, P1>, Error> {
+        let (even_transcript, odd_transcript) = self.fork(label)?;
+        Ok((
+            Prover::new(&self.parameters.even_parameters.pc_gens, even_transcript),
+            Prover::new(&self.parameters.odd_parameters.pc_gens, odd_transcript),
+        ))
+    }
+
+    /// Like `CircuitDescription::prove`, for the proof labelled `label`, verified with `VerificationSession::verify`.
+    pub fn prove<const L: usize, R: RngCore + CryptoRng>(
+        &mut self,
+        label: &'static [u8],
+        description: &CircuitDescription,
+        witnesses: &[StepWitness<L, P0, P1>],
+        rng: &mut R,
+    ) -> Result<ProvenCircuit<L, P0, P1>, Error> {
+        let (even_transcript, odd_transcript) = self.fork(label)?;
+        description.prove_with_transcripts(
+            even_transcript,
+            odd_transcript,
+            witnesses,
+            self.parameters,
+            rng,
+        )
+    }
+
+    fn fork(&mut self, label: &'static [u8]) -> Result<(Transcript, Transcript), Error> {
+        if !self.labels.insert(label) {
+            return Err(Error::parameter_mismatch(format!(
+                "The session already has a proof labelled {:?}",
+                String::from_utf8_lossy(label)
+            )));
+        }
+        Ok(fork(&self.transcript, label))
+    }
+}
+
+/// Verifies the proofs of a `ProvingSession` made with the same parameters, in any order.
+pub struct VerificationSession<'a, P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy> {
+    parameters: &'a SelRerandParameters<P0, P1>,
+    fingerprint: ParametersFingerprint,
+    transcript: Transcript,
+}
+
+impl<
+        'a,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    > VerificationSession<'a, P0, P1>
+{
+    pub fn new(parameters: &'a SelRerandParameters<P0, P1>) -> Self {
+        let fingerprint = parameters.fingerprint();
+        Self {
+            parameters,
+            fingerprint,
+            transcript: base_transcript(&fingerprint),
+        }
+    }
+
+    pub fn parameters(&self) -> &'a SelRerandParameters<P0, P1> {
+        self.parameters
+    }
+
+    /// The fingerprint of the parameters, computed once for the session.
+    pub fn fingerprint(&self) -> &ParametersFingerprint {
+        &self.fingerprint
+    }
+
+    /// The verifiers of both curves of the proof labelled `label`, see `ProvingSession::new_prover`.
+    /// A label may be verified more than once, e.g. a proof received again.
+    pub fn new_verifier(&self, label: &'static [u8]) -> SessionVerifiers<P0, P1> {
+        let (even_transcript, odd_transcript) = fork(&self.transcript, label);
+        (
+            Verifier::new(even_transcript),
+            Verifier::new(odd_transcript),
+        )
+    }
+
+    /// Like `CircuitDescription::verify`, for the proof labelled `label` of `ProvingSession::prove`.
+    pub fn verify<const L: usize>(
+        &self,
+        label: &'static [u8],
+        description: &CircuitDescription,
+        statements: Vec<StepStatement<L, P0, P1>>,
+        trees: &[&CurveTree<L, P0, P1>],
+        proof: &CircuitProof<P0, P1>,
+    ) -> Result<(), Error> {
+        let (even_transcript, odd_transcript) = fork(&self.transcript, label);
+        description.verify_with_transcripts(
+            even_transcript,
+            odd_transcript,
+            statements,
+            trees,
+            proof,
+            self.parameters,
+        )
+    }
+}
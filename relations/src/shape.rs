@@ -0,0 +1,337 @@
+use bulletproofs::r1cs::*;
+
+use crate::curve_tree::*;
+#[cfg(feature = "prover")]
+use crate::cycle::EvenScalar;
+use crate::error::Error;
+use crate::limits::{DeserializeLimits, DeserializeWithLimits};
+use crate::protocol;
+
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine, AffineRepr};
+use ark_ff::PrimeField;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Valid, Write,
+};
+use merlin::Transcript;
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+use std::borrow::BorrowMut;
+
+/// The shape of curve trees with branching factor `L` and depth `D`, the height of the tree.
+///
+/// Trees, paths and proofs typed with a shape only convert to and from the dynamic types of `curve_tree`
+/// through `TryFrom`, which checks the shape, so that code mixing shapes does not compile
+/// instead of failing verification.
+/// Shapes of depth 0 or branching factor below 2 are rejected when the shape is used:
+///
+/// ```compile_fail
+/// let _ = relations::shape::TreeShape::<32, 0>::PATH_LENGTHS;
+/// ```
+pub struct TreeShape<const L: usize, const D: usize>;
+
+impl<const L: usize, const D: usize> TreeShape<L, D> {
+    const CHECK: () = {
+        assert!(L >= 2, "curve trees have a branching factor of at least 2");
+        assert!(D >= 1, "curve trees have a depth of at least 1");
+    };
+
+    /// Whether the root of the trees is on the even curve, as for the trees of even depth.
+    pub const ROOT_IS_EVEN: bool = {
+        let () = Self::CHECK;
+        D.is_multiple_of(2)
+    };
+
+    /// The numbers of even and odd rerandomized commitments of a path, as `CurveTree::path_lengths`.
+    pub const PATH_LENGTHS: (usize, usize) = {
+        let () = Self::CHECK;
+        (D - D / 2, D / 2)
+    };
+
+    fn check_path<P0: SWCurveConfig, P1: SWCurveConfig>(
+        path: &SelectAndRerandomizePath<L, P0, P1>,
+    ) -> Result<(), Error> {
+        let (even_length, odd_length) = Self::PATH_LENGTHS;
+        if path.even_commitments.len() != even_length || path.odd_commitments.len() != odd_length {
+            return Err(Error::shape_mismatch(format!(
+                "Expected a path of depth {} with {} even and {} odd commitments, got {} and {}",
+                D,
+                even_length,
+                odd_length,
+                path.even_commitments.len(),
+                path.odd_commitments.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// The numbers of multipliers of the even and odd proofs of a membership proof of this shape,
+    /// rounded up to the next power of two as the proofs are.
+    /// Lays down the verifier's constraints for a placeholder tree of this shape.
+    pub fn padded_multipliers<
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    >(
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<(usize, usize), Error> {
+        let (leaf, _) = parameters.even_parameters.uh.permissible_commitment(
+            &parameters.even_parameters.pc_gens.B,
+            &parameters.even_parameters.pc_gens.B_blinding,
+        );
+        let tree = CurveTree::<L, P0, P1>::from_set(&[leaf], parameters, Some(D))?;
+        let (even_length, odd_length) = Self::PATH_LENGTHS;
+        let path = SelectAndRerandomizePath {
+            even_commitments: vec![Affine::generator(); even_length],
+            odd_commitments: vec![Affine::generator(); odd_length],
+        };
+        let mut even_verifier = Verifier::new(Transcript::new(protocol::SHAPE));
+        let mut odd_verifier = Verifier::new(Transcript::new(protocol::SHAPE));
+        let _rerandomized_leaf = tree.select_and_rerandomize_verifier_gadget(
+            &mut even_verifier,
+            &mut odd_verifier,
+            path,
+            parameters,
+        )?;
+        Ok((
+            even_verifier.size().next_power_of_two(),
+            odd_verifier.size().next_power_of_two(),
+        ))
+    }
+}
+
+/// A curve tree of depth `D`.
+pub struct TypedCurveTree<const L: usize, const D: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
+    tree: CurveTree<L, P0, P1>,
+}
+
+impl<
+        const L: usize,
+        const D: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    > TypedCurveTree<L, D, P0, P1>
+{
+    /// Like `CurveTree::from_set` with height `D`, failing if the set does not fit in a tree of depth `D`.
+    pub fn from_set(
+        set: &[Affine<P0>],
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<Self, Error> {
+        CurveTree::from_set(set, parameters, Some(D))?.try_into()
+    }
+
+    pub fn tree(&self) -> &CurveTree<L, P0, P1> {
+        &self.tree
+    }
+
+    pub fn into_inner(self) -> CurveTree<L, P0, P1> {
+        self.tree
+    }
+
+    /// Like `CurveTree::select_and_rerandomize_prover_gadget`, returning a path of this shape.
+    #[cfg(feature = "prover")]
+    pub fn select_and_rerandomize_prover_gadget<R: RngCore + CryptoRng>(
+        &self,
+        index: usize,
+        even_prover: &mut Prover<Transcript, Affine<P0>>,
+        odd_prover: &mut Prover<Transcript, Affine<P1>>,
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<(TypedPath<L, D, P0, P1>, EvenScalar<P0>), Error> {
+        let (path, rerandomization) = self.tree.select_and_rerandomize_prover_gadget(
+            index,
+            even_prover,
+            odd_prover,
+            parameters,
+            rng,
+        )?;
+        Ok((path.try_into()?, rerandomization))
+    }
+
+    /// Like `CurveTree::select_and_rerandomize_verifier_gadget`, for a path of this shape.
+    pub fn select_and_rerandomize_verifier_gadget<T: BorrowMut<Transcript>>(
+        &self,
+        even_verifier: &mut Verifier<T, Affine<P0>>,
+        odd_verifier: &mut Verifier<T, Affine<P1>>,
+        randomized_path: TypedPath<L, D, P0, P1>,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<Affine<P0>, Error> {
+        self.tree.select_and_rerandomize_verifier_gadget(
+            even_verifier,
+            odd_verifier,
+            randomized_path.path,
+            parameters,
+        )
+    }
+
+    /// Like `CurveTree::prove_membership`, returning a proof of this shape.
+    #[cfg(feature = "prover")]
+    pub fn prove_membership<R: RngCore + CryptoRng>(
+        &self,
+        index: usize,
+        leaf_blinding: EvenScalar<P0>,
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<(TypedMembershipProof<L, D, P0, P1>, EvenScalar<P0>), Error> {
+        let (proof, blinding) =
+            self.tree
+                .prove_membership(index, leaf_blinding, parameters, rng)?;
+        Ok((proof.try_into()?, blinding))
+    }
+}
+
+impl<
+        const L: usize,
+        const D: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    > TryFrom<CurveTree<L, P0, P1>> for TypedCurveTree<L, D, P0, P1>
+{
+    type Error = Error;
+
+    fn try_from(tree: CurveTree<L, P0, P1>) -> Result<Self, Error> {
+        let () = TreeShape::<L, D>::CHECK;
+        if tree.height() != D {
+            return Err(Error::shape_mismatch(format!(
+                "Expected a tree of depth {}, got {}",
+                D,
+                tree.height()
+            )));
+        }
+        Ok(Self { tree })
+    }
+}
+
+/// A rerandomized path of a tree of depth `D`.
+#[derive(Clone)]
+pub struct TypedPath<const L: usize, const D: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
+    path: SelectAndRerandomizePath<L, P0, P1>,
+}
+
+impl<const L: usize, const D: usize, P0: SWCurveConfig, P1: SWCurveConfig> TypedPath<L, D, P0, P1> {
+    pub fn path(&self) -> &SelectAndRerandomizePath<L, P0, P1> {
+        &self.path
+    }
+
+    pub fn into_inner(self) -> SelectAndRerandomizePath<L, P0, P1> {
+        self.path
+    }
+}
+
+impl<const L: usize, const D: usize, P0: SWCurveConfig, P1: SWCurveConfig>
+    TryFrom<SelectAndRerandomizePath<L, P0, P1>> for TypedPath<L, D, P0, P1>
+{
+    type Error = Error;
+
+    fn try_from(path: SelectAndRerandomizePath<L, P0, P1>) -> Result<Self, Error> {
+        TreeShape::<L, D>::check_path(&path)?;
+        Ok(Self { path })
+    }
+}
+
+/// A membership proof for a tree of depth `D`.
+#[derive(Clone)]
+pub struct TypedMembershipProof<
+    const L: usize,
+    const D: usize,
+    P0: SWCurveConfig,
+    P1: SWCurveConfig,
+> {
+    proof: MembershipProof<L, P0, P1>,
+}
+
+impl<
+        const L: usize,
+        const D: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    > TypedMembershipProof<L, D, P0, P1>
+{
+    pub fn proof(&self) -> &MembershipProof<L, P0, P1> {
+        &self.proof
+    }
+
+    pub fn into_inner(self) -> MembershipProof<L, P0, P1> {
+        self.proof
+    }
+
+    /// Like `MembershipProof::verify`, against a tree of this shape.
+    pub fn verify(
+        &self,
+        tree: &TypedCurveTree<L, D, P0, P1>,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<Affine<P0>, Error> {
+        self.proof.verify(&tree.tree, parameters)
+    }
+}
+
+impl<const L: usize, const D: usize, P0: SWCurveConfig, P1: SWCurveConfig>
+    TryFrom<MembershipProof<L, P0, P1>> for TypedMembershipProof<L, D, P0, P1>
+{
+    type Error = Error;
+
+    fn try_from(proof: MembershipProof<L, P0, P1>) -> Result<Self, Error> {
+        TreeShape::<L, D>::check_path(&proof.path)?;
+        Ok(Self { proof })
+    }
+}
+
+impl<const L: usize, const D: usize, P0: SWCurveConfig, P1: SWCurveConfig> CanonicalSerialize
+    for TypedMembershipProof<L, D, P0, P1>
+{
+    fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        self.proof.serialized_size(compress)
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), SerializationError> {
+        self.proof.serialize_with_mode(writer, compress)
+    }
+}
+
+impl<const L: usize, const D: usize, P0: SWCurveConfig, P1: SWCurveConfig> Valid
+    for TypedMembershipProof<L, D, P0, P1>
+{
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+impl<const L: usize, const D: usize, P0: SWCurveConfig, P1: SWCurveConfig> CanonicalDeserialize
+    for TypedMembershipProof<L, D, P0, P1>
+{
+    /// Deserializes a membership proof, failing with `SerializationError::InvalidData`
+    /// if its path is not of this shape.
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, SerializationError> {
+        MembershipProof::deserialize_with_mode(reader, compress, validate)?
+            .try_into()
+            .map_err(|_| SerializationError::InvalidData)
+    }
+}
+
+impl<const L: usize, const D: usize, P0: SWCurveConfig, P1: SWCurveConfig> DeserializeWithLimits
+    for TypedMembershipProof<L, D, P0, P1>
+{
+    /// Like `MembershipProof::deserialize_with_limits`, failing with `Error::ShapeMismatch`
+    /// if its path is not of this shape.
+    fn deserialize_with_limits<R: Read>(
+        reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+        limits: &DeserializeLimits,
+    ) -> Result<Self, Error> {
+        MembershipProof::deserialize_with_limits(reader, compress, validate, limits)?.try_into()
+    }
+}
@@ -1,19 +1,28 @@
-use ark_serialize::CanonicalSerialize;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Validate, Write,
+};
 use bulletproofs::r1cs::*;
 use bulletproofs::{BulletproofGens, PedersenGens};
 
 use crate::curve::{checked_curve_addition_helper, PointRepresentation};
+use crate::error::Error;
+use crate::limits::DeserializeLimits;
 use crate::lookup::*;
 use crate::permissible::*;
+use crate::protocol;
 use crate::rerandomize::*;
 use crate::select::*;
+#[cfg(feature = "prover")]
+use crate::version::FormatVersion;
 
 use ark_ec::{
-    models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine, AffineRepr, CurveGroup,
-    VariableBaseMSM,
+    models::short_weierstrass::SWCurveConfig,
+    short_weierstrass::{Affine, Projective},
+    AffineRepr, CurveGroup, VariableBaseMSM,
 };
-use ark_ff::{Field, PrimeField};
-use rand::Rng;
+use ark_ff::{PrimeField, Zero};
+use merlin::Transcript;
+use std::collections::HashMap;
 use std::iter;
 use std::marker::PhantomData;
 
@@ -25,45 +34,234 @@ pub struct SingleLayerParameters<P: SWCurveConfig + Copy> {
 }
 
 impl<P: SWCurveConfig + Copy> SingleLayerParameters<P> {
-    pub fn new<R: Rng, P1: SWCurveConfig>(generators_length: usize, rng: &mut R) -> Self {
-        let pc_gens = PedersenGens::<Affine<P>>::default();
+    #[cfg(feature = "prover")]
+    pub fn new<P1: SWCurveConfig>(generators_length: usize) -> Self {
+        Self::for_version::<P1>(generators_length, FormatVersion::CURRENT)
+    }
+
+    /// The parameters with the Pedersen generators of `version`, see `FormatVersion::pedersen_gens`.
+    #[cfg(feature = "prover")]
+    pub fn for_version<P1: SWCurveConfig>(
+        generators_length: usize,
+        version: FormatVersion,
+    ) -> Self {
+        let pc_gens = version.pedersen_gens::<Affine<P>>();
         let tables = build_tables(pc_gens.B_blinding);
 
         SingleLayerParameters {
             bp_gens: BulletproofGens::<Affine<P>>::new(generators_length, 1),
             pc_gens,
-            uh: UniversalHash::new(rng, P::COEFF_A, P::COEFF_B),
+            uh: UniversalHash::new(P::COEFF_A, P::COEFF_B),
             tables,
         }
     }
 
+    /// Appends what the proofs made with these parameters depend on to `bytes`, see `SelRerandParameters::fingerprint`:
+    /// the Pedersen generators, the capacities of the bulletproofs generators and the first of them,
+    /// which is derived from their label, and the constants of the universal hash.
+    pub(crate) fn append_fingerprint_bytes(&self, bytes: &mut Vec<u8>) {
+        self.pc_gens
+            .B
+            .serialize_compressed(&mut *bytes)
+            .expect("serializing to a vector does not fail");
+        self.pc_gens
+            .B_blinding
+            .serialize_compressed(&mut *bytes)
+            .expect("serializing to a vector does not fail");
+        (self.bp_gens.gens_capacity as u64)
+            .serialize_compressed(&mut *bytes)
+            .expect("serializing to a vector does not fail");
+        (self.bp_gens.party_capacity as u64)
+            .serialize_compressed(&mut *bytes)
+            .expect("serializing to a vector does not fail");
+        self.bp_gens
+            .share(0)
+            .G_slice(1)
+            .serialize_compressed(&mut *bytes)
+            .expect("serializing to a vector does not fail");
+        self.uh
+            .constants()
+            .serialize_compressed(&mut *bytes)
+            .expect("serializing to a vector does not fail");
+    }
+
+    /// Fails with `Error::ParameterMismatch` if the parameters break an invariant the gadgets rely on:
+    /// the generators of the first party do not cover the declared capacity, the Pedersen generators are
+    /// not independent points of the prime-order subgroup, as far as a relation of small multiples shows,
+    /// or the universal hash is not over the coefficients of the curve or has a zero multiplier.
+    pub(crate) fn self_check(&self) -> Result<(), Error> {
+        let gens = &self.bp_gens;
+        if gens.party_capacity == 0 || gens.gens_capacity == 0 {
+            return Err(Error::parameter_mismatch(format!(
+                "the parameters have {} parties of {} generators",
+                gens.party_capacity, gens.gens_capacity
+            )));
+        }
+        if gens.share(0).G(gens.gens_capacity).count() != gens.gens_capacity {
+            return Err(Error::parameter_mismatch(format!(
+                "fewer generators than the declared capacity of {}",
+                gens.gens_capacity
+            )));
+        }
+
+        let pc_gens = &self.pc_gens;
+        for (point, name) in [(pc_gens.B, "B"), (pc_gens.B_blinding, "B_blinding")] {
+            if point.is_zero()
+                || !point.is_on_curve()
+                || !point.is_in_correct_subgroup_assuming_on_curve()
+            {
+                return Err(Error::parameter_mismatch(format!(
+                    "the Pedersen generator {} is not a point of the prime-order subgroup",
+                    name
+                )));
+            }
+        }
+        if let Some(k) = small_relation(pc_gens.B, pc_gens.B_blinding)
+            .or_else(|| small_relation(pc_gens.B_blinding, pc_gens.B))
+        {
+            return Err(Error::parameter_mismatch(format!(
+                "the Pedersen generators are related by a factor of {}",
+                k
+            )));
+        }
+
+        let [alpha, _, a, b] = self.uh.constants();
+        if a != P::COEFF_A || b != P::COEFF_B {
+            return Err(Error::parameter_mismatch(
+                "the universal hash is not over the coefficients of the curve",
+            ));
+        }
+        if alpha.is_zero() {
+            return Err(Error::parameter_mismatch(
+                "the universal hash has a zero multiplier",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fails with `Error::ParameterMismatch` if a base appears twice among `B`, `B_blinding` and the G and H generators
+    /// of the first party up to the capacity, comparing their compressed encodings.
+    pub(crate) fn check_distinct_bases(&self) -> Result<(), Error> {
+        let share = self.bp_gens.share(0);
+        let capacity = self.bp_gens.gens_capacity;
+        let named = [
+            (String::from("B"), self.pc_gens.B),
+            (String::from("B_blinding"), self.pc_gens.B_blinding),
+        ];
+        let generators = share
+            .G(capacity)
+            .enumerate()
+            .map(|(i, g)| (format!("G[{}]", i), *g))
+            .chain(
+                share
+                    .H(capacity)
+                    .enumerate()
+                    .map(|(i, h)| (format!("H[{}]", i), *h)),
+            );
+        let mut seen = HashMap::with_capacity(2 + 2 * capacity);
+        for (name, base) in named.into_iter().chain(generators) {
+            let mut encoding = Vec::with_capacity(base.compressed_size());
+            base.serialize_compressed(&mut encoding)
+                .expect("serializing to a vector does not fail");
+            if let Some(first) = seen.insert(encoding, name.clone()) {
+                return Err(Error::parameter_mismatch(format!(
+                    "the base {} is also {}",
+                    name, first
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes what the parameters are rebuilt from by `deserialize_layer`: the Pedersen generators,
+    /// the capacity of the generators of the single party and the constants of the universal hash.
+    pub(crate) fn serialize_layer<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.pc_gens.B.serialize_with_mode(&mut writer, compress)?;
+        self.pc_gens
+            .B_blinding
+            .serialize_with_mode(&mut writer, compress)?;
+        (self.bp_gens.gens_capacity as u64).serialize_with_mode(&mut writer, compress)?;
+        self.uh
+            .constants()
+            .serialize_with_mode(&mut writer, compress)
+    }
+
+    pub(crate) fn layer_size(&self, compress: Compress) -> usize {
+        self.pc_gens.B.serialized_size(compress)
+            + self.pc_gens.B_blinding.serialized_size(compress)
+            + 0u64.serialized_size(compress)
+            + self.uh.constants().serialized_size(compress)
+    }
+
+    /// Reads the parameters written by `serialize_layer`, deriving the generators and the tables of the
+    /// rerandomization again. Fails with `Error::LimitExceeded` if the capacity exceeds `max_generators`.
+    pub(crate) fn deserialize_layer<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+        max_generators: usize,
+    ) -> Result<Self, Error> {
+        let pc_gens = PedersenGens {
+            B: Affine::<P>::deserialize_with_mode(&mut reader, compress, validate)?,
+            B_blinding: Affine::<P>::deserialize_with_mode(&mut reader, compress, validate)?,
+        };
+        let gens_capacity = DeserializeLimits::check(
+            u64::deserialize_with_mode(&mut reader, compress, validate)?,
+            max_generators,
+            "the capacity of the generators",
+        )?;
+        let constants =
+            <[P::BaseField; 4]>::deserialize_with_mode(&mut reader, compress, validate)?;
+        Ok(SingleLayerParameters {
+            bp_gens: BulletproofGens::<Affine<P>>::new(gens_capacity, 1),
+            tables: build_tables(pc_gens.B_blinding),
+            pc_gens,
+            uh: UniversalHash::from_constants(constants),
+        })
+    }
+
     pub fn commit(
         &self,
         v: &[P::ScalarField],
         v_blinding: P::ScalarField,
         generator_set_index: usize,
-    ) -> Affine<P> {
-        let gens = self
+    ) -> Result<Affine<P>, Error> {
+        Ok(self
+            .commit_projective(v, v_blinding, generator_set_index)?
+            .into_affine())
+    }
+
+    fn commit_projective(
+        &self,
+        v: &[P::ScalarField],
+        v_blinding: P::ScalarField,
+        generator_set_index: usize,
+    ) -> Result<Projective<P>, Error> {
+        if v.len() * (generator_set_index + 1) > self.bp_gens.gens_capacity {
+            return Err(Error::parameter_mismatch(format!(
+                "Not enough generators to commit to the vector: {} needed, {} available",
+                v.len() * (generator_set_index + 1),
+                self.bp_gens.gens_capacity
+            )));
+        }
+        let gens = &self
             .bp_gens
             .share(0)
-            .G(v.len() * (generator_set_index + 1))
-            .skip(v.len() * generator_set_index);
-
-        let generators: Vec<_> = iter::once(&self.pc_gens.B_blinding)
-            .chain(gens)
-            .copied()
-            .collect::<Vec<_>>();
-
-        let scalars: Vec<P::ScalarField> = iter::once(&v_blinding)
-            .chain(v.iter())
-            .map(|s| {
-                let s: P::ScalarField = *s;
-                s
-            })
-            .collect();
+            .G_slice(v.len() * (generator_set_index + 1))[v.len() * generator_set_index..];
+
+        // Padding and sparse nodes commit to mostly zero x-coordinates, skip them before the MSM.
+        let (generators, scalars): (Vec<Affine<P>>, Vec<P::ScalarField>) =
+            iter::once((self.pc_gens.B_blinding, v_blinding))
+                .chain(gens.iter().copied().zip(v.iter().copied()))
+                .filter(|(_, s)| !s.is_zero())
+                .unzip();
 
         let comm = <Affine<P> as AffineRepr>::Group::msm(generators.as_slice(), scalars.as_slice());
-        comm.unwrap().into_affine()
+        Ok(comm.expect("as many generators as scalars"))
     }
 
     pub fn permissible_commitment(
@@ -71,26 +269,83 @@ impl<P: SWCurveConfig + Copy> SingleLayerParameters<P> {
         v: &[P::ScalarField],
         v_blinding: P::ScalarField,
         generator_set_index: usize,
-    ) -> (Affine<P>, P::ScalarField) {
-        let commitment = self.commit(v, v_blinding, generator_set_index);
+    ) -> Result<(Affine<P>, P::ScalarField), Error> {
+        let commitment = self.commit(v, v_blinding, generator_set_index)?;
         let (permissible_commitment, offset) = self
             .uh
             .permissible_commitment(&commitment, &self.pc_gens.B_blinding);
-        (permissible_commitment, v_blinding + offset)
+        Ok((permissible_commitment, v_blinding + offset))
+    }
+
+    /// Like `permissible_commitment` with zero blinding for each of the vectors `vs`,
+    /// converting the commitments to affine coordinates in batches.
+    #[allow(clippy::type_complexity)]
+    pub fn permissible_commitments<V: AsRef<[P::ScalarField]>>(
+        &self,
+        vs: &[V],
+        generator_set_index: usize,
+    ) -> Result<Vec<(Affine<P>, P::ScalarField)>, Error> {
+        let commitments = vs
+            .iter()
+            .map(|v| {
+                self.commit_projective(v.as_ref(), P::ScalarField::zero(), generator_set_index)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self
+            .uh
+            .permissible_commitments(&commitments, &self.pc_gens.B_blinding))
+    }
+
+    /// Like `permissible_commitment` for each vector and blinding of `vs`, in the same order,
+    /// searching the permissible commitments in batches as `permissible_commitments`.
+    #[allow(clippy::type_complexity)]
+    pub fn blinded_permissible_commitments<V: AsRef<[P::ScalarField]>>(
+        &self,
+        vs: &[(V, P::ScalarField)],
+        generator_set_index: usize,
+    ) -> Result<Vec<(Affine<P>, P::ScalarField)>, Error> {
+        let commitments = vs
+            .iter()
+            .map(|(v, v_blinding)| {
+                self.commit_projective(v.as_ref(), *v_blinding, generator_set_index)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self
+            .uh
+            .permissible_commitments(&commitments, &self.pc_gens.B_blinding)
+            .into_iter()
+            .zip(vs)
+            .map(|((commitment, offset), (_, v_blinding))| (commitment, *v_blinding + offset))
+            .collect())
+    }
+}
+
+/// The largest factor `k` of the relations `point = ±k * base` `SingleLayerParameters::self_check` looks for.
+const SMALL_RELATION_BOUND: u64 = 256;
+
+// The smallest `k` of at most `SMALL_RELATION_BOUND` such that `point = ±k * base`, if any.
+fn small_relation<P: SWCurveConfig>(base: Affine<P>, point: Affine<P>) -> Option<u64> {
+    let mut multiple = Projective::<P>::zero();
+    for k in 1..=SMALL_RELATION_BOUND {
+        multiple += base;
+        if multiple == point || multiple == -point {
+            return Some(k);
+        }
     }
+    None
 }
 
 /// Circuit for the single level version of the select and rerandomize relation.
 pub fn single_level_select_and_rerandomize<
     Fb: PrimeField,
-    Fs: Field,
+    Fs: PrimeField,
     C2: SWCurveConfig<BaseField = Fs, ScalarField = Fb> + Copy,
     Cs: ConstraintSystem<Fs>,
 >(
     cs: &mut Cs, // Prover or verifier
     parameters: &SingleLayerParameters<C2>,
     rerandomized: &Affine<C2>, // The public rerandomization of the selected child
-    children: Vec<LinearCombination<Fs>>, // Variables representing members of the (parent) vector commitment
+    children: &[LinearCombination<Fs>], // Variables representing members of the (parent) vector commitment
     selected_witness: Option<Affine<C2>>, // Witness of the commitment being selected and rerandomized
     randomness_offset: Option<Fb>, // The scalar used for randomizing, i.e. selected_witness * randomness_offset = rerandomized
 ) {
@@ -98,41 +353,170 @@ pub fn single_level_select_and_rerandomize<
     {
         // TODO: clean this up. The transcript in CS should be restricted restricted to `ProtocolTranscript'
         let mut bytes = Vec::new();
-        if let Err(e) = rerandomized.serialize_compressed(&mut bytes) {
-            panic!("{}", e)
-        }
+        rerandomized
+            .serialize_compressed(&mut bytes)
+            .expect("serializing to a vector does not fail");
         cs.transcript()
-            .append_message(b"rerandomized_child", &bytes);
+            .append_message(protocol::RERANDOMIZED_CHILD, &bytes);
     }
 
     let x_var = cs.allocate(selected_witness.map(|xy| xy.x)).unwrap();
     let y_var = cs.allocate(selected_witness.map(|xy| xy.y)).unwrap();
     // Show that the parent is committed to the child's x-coordinate
-    select(cs, x_var.into(), children);
-    // Proof that the child is a permissible point
+    select(cs, &x_var.into(), children);
+    // Show that `rerandomized` is a rerandomization of the permissible child of that x-coordinate
+    enforce_rerandomization(
+        cs,
+        x_var,
+        y_var,
+        rerandomized,
+        selected_witness.zip(randomness_offset),
+        parameters,
+    );
+}
+
+/// Enforces that `rerandomized` is the permissible point of x-coordinate `original_x` shifted by a multiple
+/// of the blinding generator only: `rerandomized = (x, y) + r * B_blinding`.
+///
+/// This is what every level of a curve tree proves of the child its parent commits to, see
+/// `single_level_select_and_rerandomize`. `original_y` is allocated by the caller and constrained here
+/// to the permissible y-coordinate of `original_x`, so that the x-coordinate alone determines the point.
+/// `witness` is the original point and the blinding `r` for the prover, and `None` for the verifier.
+///
+/// The blinding must not be zero: the blinding is accumulated as a point, and the identity has no affine coordinates.
+/// `prove_rerandomization` rejects it, callers of the gadget draw it at random.
+pub fn enforce_rerandomization<
+    F: PrimeField,
+    S: PrimeField,
+    C2: SWCurveConfig<BaseField = F, ScalarField = S> + Copy,
+    Cs: ConstraintSystem<F>,
+>(
+    cs: &mut Cs,
+    original_x: Variable<F>,
+    original_y: Variable<F>,
+    rerandomized: &Affine<C2>,
+    witness: Option<(Affine<C2>, S)>,
+    parameters: &SingleLayerParameters<C2>,
+) {
+    let original = witness.map(|(original, _)| original);
     parameters
         .uh
-        .permissible_gadget(cs, x_var.into(), selected_witness.map(|xy| xy.y), y_var);
-    // Show that `rerandomized` is a rerandomization of the selected child
+        .permissible_gadget(cs, original_x.into(), original.map(|xy| xy.y), original_y);
+    let rerandomized_x = cs.public_constant(rerandomized.x);
+    let rerandomized_y = cs.public_constant(rerandomized.y);
     re_randomize(
         cs,
         &parameters.tables,
         PointRepresentation {
-            x: x_var.into(),
-            y: y_var.into(),
-            witness: selected_witness,
+            x: original_x.into(),
+            y: original_y.into(),
+            witness: original,
         },
-        constant(rerandomized.x),
-        constant(rerandomized.y),
-        randomness_offset,
+        rerandomized_x,
+        rerandomized_y,
+        witness.map(|(_, blinding)| blinding),
     );
 }
 
+/// Proves that `original_x`, a variable of `prover`, is the x-coordinate of the permissible point `original`
+/// of the other curve, of which the returned point is the rerandomization by `blinding`, as `enforce_rerandomization`.
+/// The rerandomized point is appended to the transcript. Verified by `verify_rerandomization`.
+///
+/// Fails with `Error::InconsistentWitness` for a point which is not permissible or a zero blinding.
+#[cfg(feature = "prover")]
+pub fn prove_rerandomization<
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0>,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+>(
+    prover: &mut Prover<Transcript, Affine<P0>>,
+    original_x: Variable<F0>,
+    original: &Affine<P1>,
+    blinding: F1,
+    parameters: &SingleLayerParameters<P1>,
+) -> Result<Affine<P1>, Error> {
+    if !is_permissible(original, parameters) {
+        return Err(Error::inconsistent_witness("The point is not permissible"));
+    }
+    if blinding.is_zero() {
+        return Err(Error::inconsistent_witness(
+            "A zero blinding does not rerandomize",
+        ));
+    }
+    let rerandomized = (parameters.pc_gens.commit_blinding_only(blinding) + original).into_affine();
+    append_rerandomized(prover.transcript(), &rerandomized);
+    let original_y = prover.allocate(Some(original.y))?;
+    enforce_rerandomization(
+        prover,
+        original_x,
+        original_y,
+        &rerandomized,
+        Some((*original, blinding)),
+        parameters,
+    );
+    Ok(rerandomized)
+}
+
+/// Verifies that `original_x`, a variable of `verifier`, is the x-coordinate of the permissible point
+/// of which `rerandomized`, returned by `prove_rerandomization`, is a rerandomization, once the proof verifies.
+/// Rejects the identity as `rerandomized` with `Error::IdentityPoint`, it has no affine coordinates.
+pub fn verify_rerandomization<
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0>,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+>(
+    verifier: &mut Verifier<Transcript, Affine<P0>>,
+    original_x: Variable<F0>,
+    rerandomized: &Affine<P1>,
+    parameters: &SingleLayerParameters<P1>,
+) -> Result<(), Error> {
+    if rerandomized.is_zero() {
+        return Err(Error::IdentityPoint);
+    }
+    append_rerandomized(verifier.transcript(), rerandomized);
+    let original_y = verifier.allocate(None)?;
+    enforce_rerandomization(
+        verifier,
+        original_x,
+        original_y,
+        rerandomized,
+        None,
+        parameters,
+    );
+    Ok(())
+}
+
+/// The statement of `single_level_select_and_rerandomize` on a verifier which lays down no constraints,
+/// checked with `Verifier::verify_ref` against the constraints of a verifier the gadget was run on:
+/// appends `rerandomized` to the transcript and supplies its coordinates as public constants, as the gadget does.
+pub fn single_level_select_and_rerandomize_statement<
+    F: PrimeField,
+    C2: SWCurveConfig<BaseField = F>,
+    Cs: ConstraintSystem<F>,
+>(
+    cs: &mut Cs,
+    rerandomized: &Affine<C2>,
+) {
+    append_rerandomized(cs.transcript(), rerandomized);
+    cs.public_constant(rerandomized.x);
+    cs.public_constant(rerandomized.y);
+}
+
+fn append_rerandomized<C: CanonicalSerialize>(transcript: &mut Transcript, rerandomized: &C) {
+    let mut bytes = Vec::new();
+    rerandomized
+        .serialize_compressed(&mut bytes)
+        .expect("serializing to a vector does not fail");
+    transcript.append_message(protocol::RERANDOMIZED_CHILD, &bytes);
+}
+
 /// Circuit for the single level version of the batched select and rerandomize relation.
 /// Facilitates showing M instances of the select and rerandomize relation with only a single rerandomization.
 pub fn single_level_batched_select_and_rerandomize<
     Fb: PrimeField,
-    Fs: Field,
+    Fs: PrimeField,
     C2: SWCurveConfig<BaseField = Fs, ScalarField = Fb> + Copy,
     Cs: ConstraintSystem<Fs>,
     const M: usize, // The number of parallel selections
@@ -151,6 +535,7 @@ pub fn single_level_batched_select_and_rerandomize<
         witness: selected_witnesses.map(|_| (Affine::<C2>::zero())),
     };
     // Split the variables of the vector commitments into chunks corresponding to the M parents.
+    let children: Vec<LinearCombination<Fs>> = children.into_iter().map(|v| v.into()).collect();
     let chunks = children.chunks_exact(children.len() / M);
     for (i, chunk) in chunks.enumerate() {
         let ith_selected_witness = selected_witnesses.map(|xy| *xy[i]);
@@ -162,14 +547,7 @@ pub fn single_level_batched_select_and_rerandomize<
             witness: ith_selected_witness,
         };
         // Show that the parent is committed to the ith child's x-coordinate
-        select(
-            cs,
-            x_var.into(),
-            chunk
-                .iter()
-                .map(|v| LinearCombination::<Fs>::from(*v))
-                .collect(),
-        );
+        select(cs, &x_var.into(), chunk);
         // Proof that the child is a permissible point
         parameters.uh.permissible_gadget(
             cs,
@@ -187,12 +565,160 @@ pub fn single_level_batched_select_and_rerandomize<
         }
     }
     // Show that `rerandomized`, is a rerandomization of sum of the selected children
+    let rerandomized_x = cs.public_constant(rerandomized.x);
+    let rerandomized_y = cs.public_constant(rerandomized.y);
     re_randomize(
         cs,
         &parameters.tables,
         sum_of_selected,
-        constant(rerandomized.x),
-        constant(rerandomized.y),
+        rerandomized_x,
+        rerandomized_y,
         randomness_offset,
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve_tree::SelRerandParameters;
+    use ark_std::UniformRand;
+
+    type PallasParameters = ark_pallas::PallasConfig;
+    type VestaParameters = ark_vesta::VestaConfig;
+    type PallasA = ark_pallas::Affine;
+    type PallasScalar = ark_pallas::Fr;
+    type VestaA = ark_vesta::Affine;
+    type VestaScalar = ark_vesta::Fr;
+
+    const LABEL: &[u8] = b"rerandomization";
+
+    // Commits to `committed` and proves it the x-coordinate of `original` rerandomized by `blinding`,
+    // then verifies against the rerandomized point mapped by `tamper`.
+    fn prove_and_verify(
+        parameters: &SingleLayerParameters<VestaParameters>,
+        pc_gens: &PedersenGens<PallasA>,
+        bp_gens: &BulletproofGens<PallasA>,
+        committed: PallasScalar,
+        original: &VestaA,
+        blinding: VestaScalar,
+        tamper: impl Fn(VestaA) -> VestaA,
+    ) -> Result<(), Error> {
+        let mut rng = rand::thread_rng();
+        let mut prover: Prover<_, PallasA> = Prover::new(pc_gens, Transcript::new(LABEL));
+        let (commitment, x) = prover.commit(committed, PallasScalar::rand(&mut rng));
+        let rerandomized = prove_rerandomization(&mut prover, x, original, blinding, parameters)?;
+        assert!(
+            rerandomized == (parameters.pc_gens.B_blinding * blinding + original).into_affine()
+        );
+        let proof = prover.prove(bp_gens)?;
+
+        let mut verifier = Verifier::<_, PallasA>::new(Transcript::new(LABEL));
+        let x = verifier.commit(commitment);
+        verify_rerandomization(&mut verifier, x, &tamper(rerandomized), parameters)?;
+        verifier.verify(&proof, pc_gens, bp_gens)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rerandomization() {
+        let mut rng = rand::thread_rng();
+        let parameters =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 11, 1 << 11);
+        let (even, odd) = (&parameters.even_parameters, &parameters.odd_parameters);
+        let permissible = |rng: &mut rand::rngs::ThreadRng| {
+            let (point, _): (VestaA, VestaScalar) = odd
+                .uh
+                .permissible_commitment(&VestaA::rand(rng), &odd.pc_gens.B_blinding);
+            point
+        };
+        let check = |committed, original: &VestaA, blinding, tamper: fn(VestaA) -> VestaA| {
+            prove_and_verify(
+                odd,
+                &even.pc_gens,
+                &even.bp_gens,
+                committed,
+                original,
+                blinding,
+                tamper,
+            )
+        };
+
+        for _ in 0..4 {
+            let original = permissible(&mut rng);
+            check(original.x, &original, VestaScalar::rand(&mut rng), |p| p).unwrap();
+        }
+
+        let original = permissible(&mut rng);
+        let blinding = VestaScalar::rand(&mut rng);
+        // The x-coordinate of another point.
+        let other = permissible(&mut rng);
+        assert!(matches!(
+            check(other.x, &original, blinding, |p| p),
+            Err(Error::R1CS(R1CSError::VerificationError))
+        ));
+        // Another rerandomized point, e.g. of another blinding.
+        assert!(matches!(
+            check(original.x, &original, blinding, |p| (p
+                + ark_vesta::Affine::generator())
+            .into_affine()),
+            Err(Error::R1CS(R1CSError::VerificationError))
+        ));
+        // The identity as the rerandomized point.
+        assert!(matches!(
+            check(original.x, &original, blinding, |_| VestaA::zero()),
+            Err(Error::IdentityPoint)
+        ));
+
+        // A zero blinding leaves the point as it is, which the accumulated blinding cannot represent.
+        assert!(matches!(
+            check(original.x, &original, VestaScalar::zero(), |p| p),
+            Err(Error::InconsistentWitness { .. })
+        ));
+        // The negation of a permissible point is not permissible.
+        assert!(matches!(
+            check(original.x, &-original, blinding, |p| p),
+            Err(Error::InconsistentWitness { .. })
+        ));
+    }
+
+    // A prover of the gadget rerandomizing with another blinding than the rerandomized point was made with.
+    #[test]
+    fn test_rerandomization_wrong_blinding() {
+        let mut rng = rand::thread_rng();
+        let parameters =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 11, 1 << 11);
+        let (even, odd) = (&parameters.even_parameters, &parameters.odd_parameters);
+        let (original, _): (VestaA, VestaScalar) = odd
+            .uh
+            .permissible_commitment(&VestaA::rand(&mut rng), &odd.pc_gens.B_blinding);
+        let blinding = VestaScalar::rand(&mut rng);
+        let rerandomized = (odd.pc_gens.B_blinding * blinding + original).into_affine();
+
+        let prove = |blinding: VestaScalar| {
+            let mut prover: Prover<_, PallasA> = Prover::new(&even.pc_gens, Transcript::new(LABEL));
+            let x = prover.allocate(Some(original.x)).unwrap();
+            let y = prover.allocate(Some(original.y)).unwrap();
+            enforce_rerandomization(
+                &mut prover,
+                x,
+                y,
+                &rerandomized,
+                Some((original, blinding)),
+                odd,
+            );
+            prover.prove(&even.bp_gens).unwrap()
+        };
+        let verify = |proof| {
+            let mut verifier = Verifier::<_, PallasA>::new(Transcript::new(LABEL));
+            let x = verifier.allocate(None).unwrap();
+            let y = verifier.allocate(None).unwrap();
+            enforce_rerandomization(&mut verifier, x, y, &rerandomized, None, odd);
+            verifier.verify(&proof, &even.pc_gens, &even.bp_gens)
+        };
+        verify(prove(blinding)).unwrap();
+        assert!(matches!(
+            verify(prove(blinding + VestaScalar::from(1u64))),
+            Err(R1CSError::VerificationError)
+        ));
+    }
+}
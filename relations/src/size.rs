@@ -0,0 +1,121 @@
+//! Byte counts of the components of serialized proofs and transactions, e.g. to compare the range proof bits,
+//! branching factors and depths of a deployment by where their bytes go.
+//!
+//! The counts are those of the compressed canonical serialization, the bytes sent over the wire:
+//! the components of a report sum to the serialized size of what it reports on.
+
+use ark_ec::AffineRepr;
+use ark_serialize::{CanonicalSerialize, Compress};
+use bulletproofs::r1cs::R1CSProof;
+use std::{fmt, iter};
+
+/// The bytes of each component of a serialized value, in the order they are serialized.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SizeReport {
+    components: Vec<(&'static str, usize)>,
+}
+
+impl SizeReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the `bytes` of `component` to the report, to the bytes already counted for it if any.
+    pub fn add(&mut self, component: &'static str, bytes: usize) {
+        match self
+            .components
+            .iter_mut()
+            .find(|(name, _)| *name == component)
+        {
+            Some((_, counted)) => *counted += bytes,
+            None => self.components.push((component, bytes)),
+        }
+    }
+
+    /// Adds the compressed size of `value` to `component`.
+    pub fn add_serialized<T: CanonicalSerialize>(&mut self, component: &'static str, value: &T) {
+        self.add(component, value.serialized_size(Compress::Yes));
+    }
+
+    /// Adds the inner product proof of `proof` to `rounds`, which grows with the logarithm of the number of
+    /// multipliers, and the rest of the proof, of a size independent of the circuit, to `component`.
+    pub fn add_proof<C: AffineRepr>(
+        &mut self,
+        component: &'static str,
+        rounds: &'static str,
+        proof: &R1CSProof<C>,
+    ) {
+        let ipp_size = proof.inner_product_proof_size(Compress::Yes);
+        self.add(component, proof.serialized_size(Compress::Yes) - ipp_size);
+        self.add(rounds, ipp_size);
+    }
+
+    /// The components and their bytes, in the order they were first added.
+    pub fn components(&self) -> &[(&'static str, usize)] {
+        &self.components
+    }
+
+    /// The bytes of `component`, if it is in the report.
+    pub fn get(&self, component: &str) -> Option<usize> {
+        self.components
+            .iter()
+            .find(|(name, _)| *name == component)
+            .map(|(_, bytes)| *bytes)
+    }
+
+    pub fn total(&self) -> usize {
+        self.components.iter().map(|(_, bytes)| bytes).sum()
+    }
+}
+
+// A table of a line per component, and of the total.
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .components
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(iter::once("total".len()))
+            .max()
+            .unwrap_or_default();
+        let total = self.total();
+        for (name, bytes) in &self.components {
+            writeln!(
+                f,
+                "{:<width$}  {:>8} bytes  {:>5.1}%",
+                name,
+                bytes,
+                100.0 * *bytes as f64 / total.max(1) as f64,
+                width = width
+            )?;
+        }
+        write!(f, "{:<width$}  {:>8} bytes", "total", total, width = width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_report_table() {
+        let mut report = SizeReport::new();
+        report.add("tags", 64);
+        report.add("output commitments", 32);
+        report.add("tags", 0);
+        report.add("output commitments", 32);
+        assert_eq!(
+            report.components(),
+            &[("tags", 64), ("output commitments", 64)]
+        );
+        assert_eq!(report.get("tags"), Some(64));
+        assert_eq!(report.get("odd proof"), None);
+        assert_eq!(report.total(), 128);
+        assert_eq!(
+            report.to_string(),
+            "tags                      64 bytes   50.0%\n\
+             output commitments        64 bytes   50.0%\n\
+             total                    128 bytes"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! Blocks applied to several curve trees and the tag set at once, or not at all.
+//!
+//! A node tracking e.g. a tree of coins and a registry of keys stages the leaves and the spending tags of a block
+//! in a `TreeTransaction`, which builds the trees they make without touching the trees it stages against.
+//! Consensus compares the staged roots with those the block claims, then the transaction is committed,
+//! replacing every tree and revealing every tag, or dropped, leaving all of them as they were,
+//! e.g. when a pour halfway through the block spends a tag already spent.
+
+use crate::curve_tree::{CurveTree, CyclePoint, SelRerandParameters};
+use crate::error::Error;
+use crate::wallet::TagSet;
+
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine};
+use ark_ff::PrimeField;
+
+// A tree of a transaction with the leaves staged for it.
+struct StagedTree<'a, const L: usize, P0: SWCurveConfig, P1: SWCurveConfig> {
+    tree: &'a mut CurveTree<L, P0, P1>,
+    leaves: Vec<Affine<P0>>,
+    // The tree with the staged leaves, dropped when more leaves are staged.
+    staged: Option<CurveTree<L, P0, P1>>,
+}
+
+/// The leaves and tags of a block, staged against curve trees and a tag set, see the module documentation.
+/// Dropping the transaction aborts it.
+pub struct TreeTransaction<'a, const L: usize, P0: SWCurveConfig + Copy, P1: SWCurveConfig + Copy> {
+    trees: Vec<StagedTree<'a, L, P0, P1>>,
+    tags: &'a mut TagSet<P0::ScalarField>,
+    staged_tags: Vec<P0::ScalarField>,
+    parameters: &'a SelRerandParameters<P0, P1>,
+}
+
+impl<
+        'a,
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    > TreeTransaction<'a, L, P0, P1>
+{
+    /// A transaction staging nothing yet against `trees`, referred to by their position, and `tags`.
+    pub fn new(
+        trees: Vec<&'a mut CurveTree<L, P0, P1>>,
+        tags: &'a mut TagSet<F0>,
+        parameters: &'a SelRerandParameters<P0, P1>,
+    ) -> Self {
+        Self {
+            trees: trees
+                .into_iter()
+                .map(|tree| StagedTree {
+                    tree,
+                    leaves: Vec::new(),
+                    staged: None,
+                })
+                .collect(),
+            tags,
+            staged_tags: Vec::new(),
+            parameters,
+        }
+    }
+
+    /// Stages `leaves` for insertion into the tree at `tree`, after those already staged for it,
+    /// and returns the indices they will have.
+    pub fn insert(&mut self, tree: usize, leaves: &[Affine<P0>]) -> Result<Vec<usize>, Error> {
+        let count = self.trees.len();
+        let staged = self.trees.get_mut(tree).ok_or_else(|| {
+            Error::parameter_mismatch(format!(
+                "Tree {} out of range, the transaction has {} trees",
+                tree, count
+            ))
+        })?;
+        let first = staged.tree.leaf_count() + staged.leaves.len();
+        staged.leaves.extend_from_slice(leaves);
+        staged.staged = None;
+        Ok((first..first + leaves.len()).collect())
+    }
+
+    /// Stages a spending tag to reveal.
+    /// Fails with `Error::SpentTag` for a tag of the tag set, and `Error::DuplicateTag` for one already staged,
+    /// staging nothing.
+    pub fn reveal_tag(&mut self, tag: F0) -> Result<(), Error> {
+        if self.tags.contains(&tag) {
+            return Err(Error::SpentTag);
+        }
+        if self.staged_tags.contains(&tag) {
+            return Err(Error::DuplicateTag);
+        }
+        self.staged_tags.push(tag);
+        Ok(())
+    }
+
+    /// The roots the trees will have once the transaction is committed, in the order of the trees.
+    /// The trees with staged leaves are built, but none of the trees of the transaction changes.
+    pub fn staged_roots(&mut self) -> Result<Vec<CyclePoint<P0, P1>>, Error> {
+        self.build()?;
+        Ok(self
+            .trees
+            .iter()
+            .map(|staged| match &staged.staged {
+                Some(tree) => tree.root(),
+                None => staged.tree.root(),
+            })
+            .collect())
+    }
+
+    /// Inserts the staged leaves into the trees and reveals the staged tags, and returns the roots of the trees.
+    ///
+    /// The trees with staged leaves are all built before any is replaced: if building one fails,
+    /// the error is returned with every tree and the tag set as they were.
+    pub fn commit(mut self) -> Result<Vec<CyclePoint<P0, P1>>, Error> {
+        self.build()?;
+        for staged in &mut self.trees {
+            if let Some(tree) = staged.staged.take() {
+                staged.tree.replace_with_inserted(tree);
+            }
+        }
+        self.tags.extend(self.staged_tags.drain(..));
+        Ok(self.trees.iter().map(|staged| staged.tree.root()).collect())
+    }
+
+    /// Drops the staged leaves and tags, leaving the trees and the tag set as they were.
+    pub fn abort(self) {}
+
+    // Builds the trees with staged leaves not built yet.
+    fn build(&mut self) -> Result<(), Error> {
+        for staged in &mut self.trees {
+            if staged.staged.is_none() && !staged.leaves.is_empty() {
+                let (tree, _) = staged.tree.with_inserted(&staged.leaves, self.parameters)?;
+                staged.staged = Some(tree);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,388 @@
+//! Streams of membership proofs sent component by component, for provers on constrained links.
+//!
+//! A `ProofWriter` writes each component of a proof, the rerandomized commitments of its path, the commitments
+//! of each phase of its R1CS proofs, each round of their inner product proofs and their final scalars,
+//! as a frame of its own: a kind byte, the length of the payload as a little-endian `u32`, and the payload,
+//! serialized compressed. The last frame is the SHA3-256 of every byte before it, in the domain
+//! `protocol::PROOF_STREAM_DOMAIN`. A `ProofReader` reads the frames in the same order, and
+//! `verify_membership_streamed` lays down the constraints of the path before the proofs arrive.
+//!
+//! The in-memory serialization of `MembershipProof` is unchanged: streams are a separate format.
+
+use std::io::{Read, Write};
+
+use bulletproofs::r1cs::{ConstraintSystem, ProofComponent, R1CSProof, Verifier};
+use merlin::Transcript;
+
+use crate::curve_tree::{
+    bind_leaf_count, CurveTree, MembershipProof, ParametersFingerprint, SelRerandParameters,
+    SelectAndRerandomizePath,
+};
+use crate::error::Error;
+use crate::limits::DeserializeLimits;
+use crate::protocol;
+
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine, AffineRepr};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use sha3::{Digest, Sha3_256};
+
+/// The largest payload of a frame: the commitments to the coefficients of t(x) are the largest component.
+pub const MAX_FRAME_LENGTH: usize = 1 << 10;
+/// The most rounds of an inner product proof read from a stream, that of a circuit of 2^64 multipliers.
+const MAX_INNER_PRODUCT_ROUNDS: usize = 64;
+
+// The kinds of frames, the first byte of each.
+const HEADER: u8 = 0;
+const EVEN_COMMITMENT: u8 = 1;
+const ODD_COMMITMENT: u8 = 2;
+const PATH_END: u8 = 3;
+const VERSION: u8 = 4;
+const PHASE_ONE_COMMITMENTS: u8 = 5;
+const PHASE_TWO_COMMITMENTS: u8 = 6;
+const T_COMMITMENTS: u8 = 7;
+const SCALARS: u8 = 8;
+const INNER_PRODUCT_ROUND: u8 = 9;
+const INNER_PRODUCT_SCALARS: u8 = 10;
+const END: u8 = 0xff;
+
+fn stream_hasher() -> Sha3_256 {
+    let mut hasher = Sha3_256::new();
+    hasher.update((protocol::PROOF_STREAM_DOMAIN.len() as u64).to_le_bytes());
+    hasher.update(protocol::PROOF_STREAM_DOMAIN);
+    hasher
+}
+
+/// Writes proofs to `W` one frame per component, as they are handed to it.
+/// Nothing is buffered: each frame is written to `W` when it is complete.
+pub struct ProofWriter<W: Write> {
+    writer: W,
+    hasher: Sha3_256,
+}
+
+impl<W: Write> ProofWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            hasher: stream_hasher(),
+        }
+    }
+
+    fn write_frame(&mut self, kind: u8, payload: &impl CanonicalSerialize) -> Result<(), Error> {
+        let mut bytes = Vec::with_capacity(payload.compressed_size());
+        payload.serialize_compressed(&mut bytes)?;
+        debug_assert!(bytes.len() <= MAX_FRAME_LENGTH);
+        let mut frame = vec![kind];
+        frame.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&bytes);
+        self.hasher.update(&frame);
+        self.writer
+            .write_all(&frame)
+            .map_err(SerializationError::from)?;
+        Ok(())
+    }
+
+    /// Writes the parameters fingerprint and the number of leaves a membership proof was made with,
+    /// which the verifier checks before reading anything else.
+    pub fn write_header(
+        &mut self,
+        parameters_fingerprint: &ParametersFingerprint,
+        leaf_count: u64,
+    ) -> Result<(), Error> {
+        self.write_frame(HEADER, &(*parameters_fingerprint, leaf_count))
+    }
+
+    /// Writes the rerandomized commitments of a path, one frame each.
+    pub fn write_path<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig>(
+        &mut self,
+        path: &SelectAndRerandomizePath<L, P0, P1>,
+    ) -> Result<(), Error> {
+        for commitment in &path.even_commitments {
+            self.write_frame(EVEN_COMMITMENT, commitment)?;
+        }
+        for commitment in &path.odd_commitments {
+            self.write_frame(ODD_COMMITMENT, commitment)?;
+        }
+        self.write_frame(PATH_END, &())
+    }
+
+    /// Writes the components of an R1CS proof, one frame each.
+    pub fn write_r1cs_proof<C: AffineRepr>(&mut self, proof: &R1CSProof<C>) -> Result<(), Error> {
+        for component in proof.components() {
+            match component {
+                ProofComponent::Version(version) => self.write_frame(VERSION, &version)?,
+                ProofComponent::PhaseOneCommitments(commitments) => {
+                    self.write_frame(PHASE_ONE_COMMITMENTS, &commitments.to_vec())?
+                }
+                ProofComponent::PhaseTwoCommitments(commitments) => self.write_frame(
+                    PHASE_TWO_COMMITMENTS,
+                    &commitments.map(|commitments| commitments.to_vec()),
+                )?,
+                ProofComponent::T(commitments) => self.write_frame(T_COMMITMENTS, &commitments)?,
+                ProofComponent::Scalars(scalars) => self.write_frame(SCALARS, &scalars.to_vec())?,
+                ProofComponent::InnerProductRound(l, r) => {
+                    self.write_frame(INNER_PRODUCT_ROUND, &(l, r))?
+                }
+                ProofComponent::InnerProductScalars(a, b) => {
+                    self.write_frame(INNER_PRODUCT_SCALARS, &(a, b))?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a membership proof: its header, its path, and its even and odd proofs.
+    pub fn write_membership_proof<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig>(
+        &mut self,
+        proof: &MembershipProof<L, P0, P1>,
+    ) -> Result<(), Error> {
+        self.write_header(&proof.parameters_fingerprint, proof.leaf_count)?;
+        self.write_path(&proof.path)?;
+        self.write_r1cs_proof(&proof.even_proof)?;
+        self.write_r1cs_proof(&proof.odd_proof)
+    }
+
+    /// Writes the integrity hash of the stream and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W, Error> {
+        let digest: [u8; 32] = self.hasher.clone().finalize().into();
+        self.write_frame(END, &digest)?;
+        self.writer.flush().map_err(SerializationError::from)?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads the frames of a `ProofWriter` from `R` as they arrive, in the order they were written.
+///
+/// Frames out of order and payloads with trailing bytes fail with `Error::CorruptedStream`,
+/// a stream cut short with `Error::Serialization`, and lengths above their limits with `Error::LimitExceeded`.
+/// The integrity hash is only checked by `finish`: a proof read from a stream is not to be trusted before.
+pub struct ProofReader<R: Read> {
+    reader: R,
+    hasher: Sha3_256,
+    limits: DeserializeLimits,
+}
+
+impl<R: Read> ProofReader<R> {
+    /// A reader under `DeserializeLimits::default()`.
+    pub fn new(reader: R) -> Self {
+        Self::with_limits(reader, DeserializeLimits::default())
+    }
+
+    /// A reader of paths of at most `limits.max_depth` commitments on either curve.
+    pub fn with_limits(reader: R, limits: DeserializeLimits) -> Self {
+        Self {
+            reader,
+            hasher: stream_hasher(),
+            limits,
+        }
+    }
+
+    fn read_exact(&mut self, bytes: &mut [u8]) -> Result<(), Error> {
+        self.reader
+            .read_exact(bytes)
+            .map_err(SerializationError::from)?;
+        self.hasher.update(&*bytes);
+        Ok(())
+    }
+
+    /// Reads the next frame and returns its kind and payload.
+    fn read_frame(&mut self) -> Result<(u8, Vec<u8>), Error> {
+        let mut header = [0u8; 5];
+        self.read_exact(&mut header)?;
+        let length = u32::from_le_bytes(header[1..].try_into().expect("four bytes"));
+        let length = DeserializeLimits::check(length as u64, MAX_FRAME_LENGTH, "frame length")?;
+        let mut payload = vec![0u8; length];
+        self.read_exact(&mut payload)?;
+        Ok((header[0], payload))
+    }
+
+    /// Reads the next frame, which must be of `kind`, and deserializes its payload, which must be consumed whole.
+    fn read_payload<T: CanonicalDeserialize>(&mut self, kind: u8) -> Result<T, Error> {
+        let (actual, payload) = self.read_frame()?;
+        if actual != kind {
+            return Err(unexpected_frame(kind, actual));
+        }
+        decode(&payload)
+    }
+
+    /// Reads the parameters fingerprint and the number of leaves of a membership proof.
+    pub fn read_header(&mut self) -> Result<(ParametersFingerprint, u64), Error> {
+        self.read_payload(HEADER)
+    }
+
+    /// Reads the rerandomized commitments of a path, at most `max_depth` on either curve.
+    pub fn read_path<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig>(
+        &mut self,
+    ) -> Result<SelectAndRerandomizePath<L, P0, P1>, Error> {
+        let mut path = SelectAndRerandomizePath {
+            even_commitments: Vec::new(),
+            odd_commitments: Vec::new(),
+        };
+        loop {
+            let (kind, payload) = self.read_frame()?;
+            match kind {
+                EVEN_COMMITMENT if path.odd_commitments.is_empty() => {
+                    path.even_commitments.push(decode(&payload)?);
+                    DeserializeLimits::check(
+                        path.even_commitments.len() as u64,
+                        self.limits.max_depth,
+                        "even path length",
+                    )?;
+                }
+                ODD_COMMITMENT => {
+                    path.odd_commitments.push(decode(&payload)?);
+                    DeserializeLimits::check(
+                        path.odd_commitments.len() as u64,
+                        self.limits.max_depth,
+                        "odd path length",
+                    )?;
+                }
+                PATH_END => {
+                    decode::<()>(&payload)?;
+                    return Ok(path);
+                }
+                _ => return Err(unexpected_frame(PATH_END, kind)),
+            }
+        }
+    }
+
+    /// Reads the components of an R1CS proof and reassembles it.
+    pub fn read_r1cs_proof<C: AffineRepr>(&mut self) -> Result<R1CSProof<C>, Error> {
+        let mut components = vec![
+            ProofComponent::Version(self.read_payload(VERSION)?),
+            ProofComponent::PhaseOneCommitments(three(self.read_payload(PHASE_ONE_COMMITMENTS)?)?),
+            ProofComponent::PhaseTwoCommitments(
+                self.read_payload::<Option<Vec<C>>>(PHASE_TWO_COMMITMENTS)?
+                    .map(three)
+                    .transpose()?,
+            ),
+            ProofComponent::T(self.read_payload(T_COMMITMENTS)?),
+            ProofComponent::Scalars(three(self.read_payload(SCALARS)?)?),
+        ];
+        let mut rounds = 0;
+        loop {
+            let (kind, payload) = self.read_frame()?;
+            match kind {
+                INNER_PRODUCT_ROUND => {
+                    rounds += 1;
+                    DeserializeLimits::check(
+                        rounds,
+                        MAX_INNER_PRODUCT_ROUNDS,
+                        "inner product rounds",
+                    )?;
+                    let (l, r) = decode(&payload)?;
+                    components.push(ProofComponent::InnerProductRound(l, r));
+                }
+                INNER_PRODUCT_SCALARS => {
+                    let (a, b) = decode(&payload)?;
+                    components.push(ProofComponent::InnerProductScalars(a, b));
+                    break;
+                }
+                _ => return Err(unexpected_frame(INNER_PRODUCT_SCALARS, kind)),
+            }
+        }
+        R1CSProof::from_components(components)
+            .map_err(|_| Error::corrupted_stream("the components do not make an R1CS proof"))
+    }
+
+    /// Reads a membership proof written by `ProofWriter::write_membership_proof`.
+    pub fn read_membership_proof<const L: usize, P0: SWCurveConfig, P1: SWCurveConfig>(
+        &mut self,
+    ) -> Result<MembershipProof<L, P0, P1>, Error> {
+        let (parameters_fingerprint, leaf_count) = self.read_header()?;
+        Ok(MembershipProof {
+            path: self.read_path()?,
+            even_proof: self.read_r1cs_proof()?,
+            odd_proof: self.read_r1cs_proof()?,
+            parameters_fingerprint,
+            leaf_count,
+        })
+    }
+
+    /// Reads the integrity hash and checks it against the frames read,
+    /// failing with `Error::CorruptedStream` if they differ. Returns the underlying reader.
+    pub fn finish(mut self) -> Result<R, Error> {
+        let expected: [u8; 32] = self.hasher.clone().finalize().into();
+        let digest: [u8; 32] = self.read_payload(END)?;
+        if digest != expected {
+            return Err(Error::corrupted_stream(
+                "the integrity hash does not match the frames",
+            ));
+        }
+        Ok(self.reader)
+    }
+}
+
+fn decode<T: CanonicalDeserialize>(mut payload: &[u8]) -> Result<T, Error> {
+    let value = T::deserialize_compressed(&mut payload)?;
+    if !payload.is_empty() {
+        return Err(Error::corrupted_stream(format!(
+            "{} trailing bytes in a frame",
+            payload.len()
+        )));
+    }
+    Ok(value)
+}
+
+fn three<T>(values: Vec<T>) -> Result<[T; 3], Error> {
+    values
+        .try_into()
+        .map_err(|_| Error::corrupted_stream("a frame does not have three elements"))
+}
+
+fn unexpected_frame(expected: u8, actual: u8) -> Error {
+    Error::corrupted_stream(format!(
+        "expected a frame of kind {}, read one of kind {}",
+        expected, actual
+    ))
+}
+
+/// Verifies a membership proof read from `reader` against `tree` as `MembershipProof::verify`,
+/// checking the header and laying down the constraints of the path before the R1CS proofs are read,
+/// and the integrity hash, which ends the stream, before they are verified. Returns the rerandomized leaf.
+pub fn verify_membership_streamed<
+    const L: usize,
+    F0: PrimeField,
+    F1: PrimeField,
+    P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+    P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    R: Read,
+>(
+    mut reader: ProofReader<R>,
+    tree: &CurveTree<L, P0, P1>,
+    parameters: &SelRerandParameters<P0, P1>,
+) -> Result<Affine<P0>, Error> {
+    let (parameters_fingerprint, leaf_count) = reader.read_header()?;
+    parameters.check_fingerprint(&parameters_fingerprint)?;
+    if leaf_count != tree.leaf_count() as u64 {
+        return Err(Error::InvalidPath);
+    }
+    let path = reader.read_path()?;
+    let mut even_verifier = Verifier::new(Transcript::new(protocol::MEMBERSHIP));
+    let mut odd_verifier = Verifier::new(Transcript::new(protocol::MEMBERSHIP));
+    bind_leaf_count(
+        even_verifier.transcript(),
+        odd_verifier.transcript(),
+        leaf_count,
+    );
+    let rerandomized_leaf = tree.select_and_rerandomize_verifier_gadget(
+        &mut even_verifier,
+        &mut odd_verifier,
+        path,
+        parameters,
+    )?;
+    let even_proof = reader.read_r1cs_proof()?;
+    let odd_proof = reader.read_r1cs_proof()?;
+    reader.finish()?;
+    even_verifier.verify(
+        &even_proof,
+        &parameters.even_parameters.pc_gens,
+        &parameters.even_parameters.bp_gens,
+    )?;
+    odd_verifier.verify(
+        &odd_proof,
+        &parameters.odd_parameters.pc_gens,
+        &parameters.odd_parameters.bp_gens,
+    )?;
+    Ok(rerandomized_leaf)
+}
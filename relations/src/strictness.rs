@@ -0,0 +1,22 @@
+/// Whether a prover checks that its witness is consistent with the statement before proving it.
+///
+/// An inconsistent witness, e.g. a coin which is not the leaf it is said to be, or a blinding missing a term,
+/// makes an invalid proof, which verifiers reject. `Checked` finds it before proving and fails with
+/// `Error::InconsistentWitness`, naming the wrong term of the blinding if there is one, in release builds as well.
+/// `Fast` skips the checks, for applications which validate their inputs upstream, and only learn of
+/// an inconsistent witness from a rejected proof. See the `Spend_strictness` benchmark for what the checks cost.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Check the witness, and fail with a typed error if it is inconsistent.
+    #[default]
+    Checked,
+    /// Prove whatever the witness is.
+    Fast,
+}
+
+impl Strictness {
+    /// Whether the consistency checks are run.
+    pub fn is_checked(self) -> bool {
+        self == Self::Checked
+    }
+}
@@ -0,0 +1,19 @@
+//! Prime fields of other sizes than those of the curves, for the tests of what depends on the size of a field:
+//! Goldilocks, of 64 bits, and the base field of BLS12-381, of 381 bits.
+
+// The derive of ark-ff 0.4 implements the configs inside anonymous consts.
+#![allow(non_local_definitions)]
+
+use ark_ff::{Fp384, Fp64, MontBackend, MontConfig};
+
+#[derive(MontConfig)]
+#[modulus = "18446744069414584321"]
+#[generator = "7"]
+pub struct GoldilocksConfig;
+pub type Goldilocks = Fp64<MontBackend<GoldilocksConfig, 1>>;
+
+#[derive(MontConfig)]
+#[modulus = "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559787"]
+#[generator = "2"]
+pub struct Bls381BaseConfig;
+pub type Bls381Base = Fp384<MontBackend<Bls381BaseConfig, 6>>;
@@ -0,0 +1,379 @@
+use bulletproofs::r1cs::*;
+
+use crate::coin::Coin;
+use crate::curve_tree::*;
+use crate::encoding::{decode_hex, from_hex, to_hex};
+use crate::error::Error;
+use crate::layout::CommitmentLayout;
+use crate::limits::{DeserializeLimits, DeserializeWithLimits};
+use crate::protocol;
+use crate::version::FormatVersion;
+
+use ark_crypto_primitives::signature::{schnorr::Schnorr, SignatureScheme};
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine, CurveGroup};
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalDeserialize;
+use blake2::Blake2s256 as Blake2s;
+use merlin::Transcript;
+use rand::{rngs::StdRng, SeedableRng};
+use std::collections::HashMap;
+
+/// A known-answer test vector of a membership proof of a minted coin, every value hex encoded as by `to_hex`.
+///
+/// The parameters are `SelRerandParameters::new` with `generators_length` generators on both curves,
+/// those of the version of the path when verifying, see `SelRerandParameters::for_version`.
+/// Everything else is drawn from a `StdRng` seeded with `seed`, in this order: the Schnorr parameters,
+/// a key pair and a coin (`Coin::new`) for each value, the rerandomizations of the path,
+/// and the blindings of the even and then the odd proof (`Prover::prove_with_rng`).
+/// The coins use `CommitmentLayout::coin`, the proofs the transcripts of `CurveTree::prove_membership`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestVector {
+    pub name: String,
+    pub seed: u64,
+    pub branching_factor: usize,
+    pub height: usize,
+    pub generators_length: usize,
+    pub values: Vec<u64>,
+    pub public_keys: Vec<String>,
+    pub pk_randomness: Vec<String>,
+    pub tags: Vec<String>,
+    pub blindings: Vec<String>,
+    pub leaves: Vec<String>,
+    // "even" or "odd", the curve of the root
+    pub root_curve: String,
+    pub root: String,
+    pub index: usize,
+    pub spending_tag: String,
+    pub path: String,
+    pub rerandomized_leaf: String,
+    pub rerandomized_blinding: String,
+    pub even_proof: String,
+    pub odd_proof: String,
+}
+
+impl TestVector {
+    /// Mints a coin of each of `values`, builds a tree of `height` from their commitments
+    /// and proves the membership of the coin at `index`.
+    pub fn generate<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+        C: CurveGroup,
+    >(
+        name: &str,
+        seed: u64,
+        height: usize,
+        generators_length: usize,
+        values: &[u64],
+        index: usize,
+    ) -> Result<Self, Error> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let parameters = SelRerandParameters::<P0, P1>::new(generators_length, generators_length);
+        let schnorr_parameters =
+            Schnorr::<C, Blake2s>::setup(&mut rng).expect("the Schnorr setup does not fail");
+        let layout = CommitmentLayout::coin();
+
+        let mut public_keys = Vec::with_capacity(values.len());
+        let mut coins = Vec::with_capacity(values.len());
+        let mut leaves = Vec::with_capacity(values.len());
+        for value in values {
+            let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng)
+                .expect("the Schnorr key generation does not fail");
+            let (coin, leaf) = Coin::<P0, C>::new(
+                *value,
+                &pk,
+                &schnorr_parameters,
+                &parameters.even_parameters,
+                &layout,
+                &mut rng,
+            )?;
+            public_keys.push(pk);
+            coins.push(coin);
+            leaves.push(leaf);
+        }
+        let coin = coins.get(index).ok_or_else(|| {
+            Error::inconsistent_witness(format!(
+                "Leaf index {} out of range, the tree has {} leaves",
+                index,
+                coins.len()
+            ))
+        })?;
+
+        let tree = CurveTree::<L, P0, P1>::from_set(&leaves, &parameters, Some(height))?;
+        let mut even_prover = Prover::new(
+            &parameters.even_parameters.pc_gens,
+            Transcript::new(protocol::MEMBERSHIP),
+        );
+        let mut odd_prover = Prover::new(
+            &parameters.odd_parameters.pc_gens,
+            Transcript::new(protocol::MEMBERSHIP),
+        );
+        let leaf_count = tree.leaf_count() as u64;
+        bind_leaf_count(
+            even_prover.transcript(),
+            odd_prover.transcript(),
+            leaf_count,
+        );
+        let (path, rerandomization) = tree.select_and_rerandomize_prover_gadget(
+            index,
+            &mut even_prover,
+            &mut odd_prover,
+            &parameters,
+            &mut rng,
+        )?;
+        let even_proof =
+            even_prover.prove_with_rng(&parameters.even_parameters.bp_gens, &mut rng)?;
+        let odd_proof = odd_prover.prove_with_rng(&parameters.odd_parameters.bp_gens, &mut rng)?;
+        let proof = MembershipProof {
+            path,
+            even_proof,
+            odd_proof,
+            parameters_fingerprint: parameters.fingerprint(),
+            leaf_count,
+        };
+        // The vectors must verify, or the other implementation would be checked against a wrong answer.
+        let rerandomized_leaf = proof.verify(&tree, &parameters)?;
+
+        let (root_curve, root) = match tree.root() {
+            CyclePoint::Even(root) => ("even", to_hex(&root.0)),
+            CyclePoint::Odd(root) => ("odd", to_hex(&root.0)),
+        };
+        Ok(Self {
+            name: name.to_string(),
+            seed,
+            branching_factor: L,
+            height: tree.height(),
+            generators_length,
+            values: values.to_vec(),
+            public_keys: public_keys.iter().map(to_hex).collect(),
+            pk_randomness: coins.iter().map(|c| to_hex(&c.pk_randomness)).collect(),
+            tags: coins.iter().map(|c| to_hex(&c.tag)).collect(),
+            blindings: coins
+                .iter()
+                .map(|c| to_hex(&c.commitment_blinding))
+                .collect(),
+            leaves: leaves.iter().map(to_hex::<Affine<P0>>).collect(),
+            root_curve: root_curve.to_string(),
+            root,
+            index,
+            spending_tag: to_hex(&coin.tag),
+            path: to_hex(&proof.path),
+            rerandomized_leaf: to_hex(&rerandomized_leaf),
+            rerandomized_blinding: to_hex(&(coin.commitment_blinding.total() + rerandomization).0),
+            even_proof: to_hex(&proof.even_proof),
+            odd_proof: to_hex(&proof.odd_proof),
+        })
+    }
+
+    /// The vector as a JSON object, with the fields in the order of the struct, one per line.
+    pub fn to_json(&self) -> String {
+        fn string(s: &str) -> String {
+            format!("\"{}\"", s)
+        }
+        fn list(items: Vec<String>) -> String {
+            if items.is_empty() {
+                return "[]".to_string();
+            }
+            format!("[\n    {}\n  ]", items.join(",\n    "))
+        }
+        fn strings(items: &[String]) -> String {
+            list(items.iter().map(|s| string(s)).collect())
+        }
+        let fields = [
+            ("name", string(&self.name)),
+            ("seed", self.seed.to_string()),
+            ("branching_factor", self.branching_factor.to_string()),
+            ("height", self.height.to_string()),
+            ("generators_length", self.generators_length.to_string()),
+            (
+                "values",
+                list(self.values.iter().map(|v| v.to_string()).collect()),
+            ),
+            ("public_keys", strings(&self.public_keys)),
+            ("pk_randomness", strings(&self.pk_randomness)),
+            ("tags", strings(&self.tags)),
+            ("blindings", strings(&self.blindings)),
+            ("leaves", strings(&self.leaves)),
+            ("root_curve", string(&self.root_curve)),
+            ("root", string(&self.root)),
+            ("index", self.index.to_string()),
+            ("spending_tag", string(&self.spending_tag)),
+            ("path", string(&self.path)),
+            ("rerandomized_leaf", string(&self.rerandomized_leaf)),
+            ("rerandomized_blinding", string(&self.rerandomized_blinding)),
+            ("even_proof", string(&self.even_proof)),
+            ("odd_proof", string(&self.odd_proof)),
+        ];
+        let fields: Vec<_> = fields
+            .iter()
+            .map(|(key, value)| format!("  \"{}\": {}", key, value))
+            .collect();
+        format!("{{\n{}\n}}\n", fields.join(",\n"))
+    }
+
+    /// Reads a vector back from its JSON, as written by `to_json`.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let invalid =
+            |what: &str| Error::parameter_mismatch(format!("invalid test vector: {}", what));
+        // The values are strings without whitespace, numbers or lists of them, so the JSON of `to_json`
+        // splits into fields at the commas outside of lists once its whitespace is removed.
+        let json: String = json.split_whitespace().collect();
+        let mut rest = json
+            .strip_prefix('{')
+            .and_then(|json| json.strip_suffix('}'))
+            .ok_or_else(|| invalid("not an object"))?;
+        let mut fields = HashMap::new();
+        while !rest.is_empty() {
+            let (key, value) = rest
+                .strip_prefix('"')
+                .and_then(|field| field.split_once("\":"))
+                .ok_or_else(|| invalid("a field is not a key and a value"))?;
+            let end = if value.starts_with('[') {
+                value
+                    .find(']')
+                    .ok_or_else(|| invalid("unterminated list"))?
+                    + 1
+            } else {
+                value.find(',').unwrap_or(value.len())
+            };
+            fields.insert(key, &value[..end]);
+            rest = value[end..].strip_prefix(',').unwrap_or(&value[end..]);
+        }
+
+        let field = |key: &str| {
+            fields
+                .get(key)
+                .copied()
+                .ok_or_else(|| invalid(&format!("missing {}", key)))
+        };
+        let string = |key: &str| -> Result<String, Error> {
+            field(key)?
+                .strip_prefix('"')
+                .and_then(|s| s.strip_suffix('"'))
+                .map(str::to_string)
+                .ok_or_else(|| invalid(&format!("{} is not a string", key)))
+        };
+        let number = |key: &str| -> Result<u64, Error> {
+            field(key)?
+                .parse()
+                .map_err(|_| invalid(&format!("{} is not a number", key)))
+        };
+        let items = |key: &str| -> Result<Vec<&str>, Error> {
+            let list = field(key)?
+                .strip_prefix('[')
+                .and_then(|list| list.strip_suffix(']'))
+                .ok_or_else(|| invalid(&format!("{} is not a list", key)))?;
+            Ok(list.split(',').filter(|item| !item.is_empty()).collect())
+        };
+        let strings = |key: &str| -> Result<Vec<String>, Error> {
+            items(key)?
+                .into_iter()
+                .map(|item| {
+                    item.strip_prefix('"')
+                        .and_then(|s| s.strip_suffix('"'))
+                        .map(str::to_string)
+                        .ok_or_else(|| invalid(&format!("{} is not a list of strings", key)))
+                })
+                .collect()
+        };
+        Ok(Self {
+            name: string("name")?,
+            seed: number("seed")?,
+            branching_factor: number("branching_factor")? as usize,
+            height: number("height")? as usize,
+            generators_length: number("generators_length")? as usize,
+            values: items("values")?
+                .into_iter()
+                .map(|value| value.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid("values is not a list of numbers"))?,
+            public_keys: strings("public_keys")?,
+            pk_randomness: strings("pk_randomness")?,
+            tags: strings("tags")?,
+            blindings: strings("blindings")?,
+            leaves: strings("leaves")?,
+            root_curve: string("root_curve")?,
+            root: string("root")?,
+            index: number("index")? as usize,
+            spending_tag: string("spending_tag")?,
+            path: string("path")?,
+            rerandomized_leaf: string("rerandomized_leaf")?,
+            rerandomized_blinding: string("rerandomized_blinding")?,
+            even_proof: string("even_proof")?,
+            odd_proof: string("odd_proof")?,
+        })
+    }
+
+    /// Verifies the membership proof of the vector against the tree of its leaves, with the parameters of its generators length
+    /// and of the version of its path, and checks the root and the rerandomized leaf.
+    ///
+    /// The path and the proofs are read with their version bytes: a vector of an unknown version fails with
+    /// `Error::UnsupportedVersion` for the path, and `R1CSError::UnsupportedVersion` for the proofs.
+    pub fn verify<
+        const L: usize,
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy + Send,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy + Send,
+    >(
+        &self,
+    ) -> Result<(), Error> {
+        let hex = |what: &str, s: &str| {
+            decode_hex(s).map_err(|e| Error::parameter_mismatch(format!("{}: {}", what, e)))
+        };
+        if self.branching_factor != L {
+            return Err(Error::parameter_mismatch(format!(
+                "The vector has branching factor {}, the tree {}",
+                self.branching_factor, L
+            )));
+        }
+        let path = hex("the path", &self.path)?;
+        let version = path.first().map_or(Ok(FormatVersion::CURRENT), |byte| {
+            FormatVersion::from_byte(*byte)
+        })?;
+        let parameters = SelRerandParameters::<P0, P1>::for_version(
+            self.generators_length,
+            self.generators_length,
+            version,
+        );
+        let leaves = self
+            .leaves
+            .iter()
+            .map(|leaf| from_hex::<Affine<P0>>(leaf))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| Error::parameter_mismatch(format!("the leaves: {}", e)))?;
+        let tree = CurveTree::<L, P0, P1>::from_set(&leaves, &parameters, Some(self.height))?;
+        let root = match tree.root() {
+            CyclePoint::Even(root) => to_hex(&root.0),
+            CyclePoint::Odd(root) => to_hex(&root.0),
+        };
+        if root != self.root {
+            return Err(Error::parameter_mismatch(
+                "The leaves do not make a tree of the root of the vector",
+            ));
+        }
+
+        let proof = MembershipProof {
+            path: SelectAndRerandomizePath::deserialize_compressed_with_limits(
+                path.as_slice(),
+                &DeserializeLimits::default(),
+            )?,
+            even_proof: R1CSProof::deserialize_compressed(
+                hex("the even proof", &self.even_proof)?.as_slice(),
+            )?,
+            odd_proof: R1CSProof::deserialize_compressed(
+                hex("the odd proof", &self.odd_proof)?.as_slice(),
+            )?,
+            parameters_fingerprint: parameters.fingerprint(),
+            leaf_count: tree.leaf_count() as u64,
+        };
+        if to_hex(&proof.verify(&tree, &parameters)?) != self.rerandomized_leaf {
+            return Err(Error::parameter_mismatch(
+                "The proof is not of the rerandomized leaf of the vector",
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,338 @@
+use bulletproofs::r1cs::*;
+
+use crate::curve_tree::SelRerandParameters;
+use crate::protocol;
+use crate::single_level_select_and_rerandomize::*;
+
+use ark_ec::{models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine, AffineRepr};
+use ark_ff::{PrimeField, Zero};
+use ark_serialize::CanonicalSerialize;
+use merlin::Transcript;
+use std::fmt::Write;
+use std::marker::PhantomData;
+
+/// The branching factors considered by `TreeConfig::recommend`, the powers of two up to 2^12.
+pub const CANDIDATE_BRANCHING_FACTORS: [usize; 12] =
+    [2, 4, 8, 16, 32, 64, 128, 256, 512, 1024, 2048, 4096];
+
+/// What `TreeConfig::recommend` minimizes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SizeOrSpeed {
+    /// The size of the membership proofs, then the number of multipliers.
+    Size,
+    /// The number of multipliers of the membership proofs, which the prover's time is about linear in, then their size.
+    Speed,
+}
+
+/// A branching factor and a depth of curve trees, with the costs of their membership proofs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TreeConfig {
+    pub branching_factor: usize,
+    pub depth: usize,
+    /// The number of leaves of a full tree, saturated at `u64::MAX`.
+    pub capacity: u64,
+    /// The multipliers of the even and odd proofs of a membership proof.
+    pub multipliers: (usize, usize),
+    /// The multipliers rounded up to the next power of two, as the proofs are.
+    /// The parameters need at least as many generators on each curve.
+    pub padded_multipliers: (usize, usize),
+    /// The size in bytes of a compressed `MembershipProof`.
+    /// Exact for R1CS proofs without second phase commitments, which a proof ending with a half assigned multiplier has,
+    /// adding four points to it.
+    pub proof_size: usize,
+}
+
+impl TreeConfig {
+    /// The configuration of the candidate branching factors, each with the least depth holding `n_leaves` leaves,
+    /// which minimizes what `optimize_for` asks for, see `candidates`.
+    pub fn recommend<
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+    >(
+        n_leaves: u64,
+        optimize_for: SizeOrSpeed,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> TreeConfig {
+        let candidates = Self::candidates(n_leaves, parameters);
+        let key = |config: &TreeConfig| {
+            let multipliers = config.padded_multipliers.0 + config.padded_multipliers.1;
+            match optimize_for {
+                SizeOrSpeed::Size => (config.proof_size, multipliers),
+                SizeOrSpeed::Speed => (multipliers, config.proof_size),
+            }
+        };
+        candidates
+            .into_iter()
+            .min_by_key(key)
+            .expect("there are candidate branching factors")
+    }
+
+    /// The configurations of the candidate branching factors, each with the least depth holding `n_leaves` leaves.
+    ///
+    /// The costs are estimated from the multipliers of a single level of the select and rerandomize gadget
+    /// laid down on each curve, and do not depend on the generators of the parameters, which may be few.
+    pub fn candidates<
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+    >(
+        n_leaves: u64,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Vec<TreeConfig> {
+        // The levels of the even proof select points of the odd curve, and the other way around.
+        let even_level = level_multipliers::<_, _, P1, Affine<P0>>(&parameters.odd_parameters);
+        let odd_level = level_multipliers::<_, _, P0, Affine<P1>>(&parameters.even_parameters);
+        CANDIDATE_BRANCHING_FACTORS
+            .iter()
+            .map(|&branching_factor| {
+                let mut depth = 1;
+                let mut capacity = branching_factor as u64;
+                while capacity < n_leaves {
+                    depth += 1;
+                    capacity = capacity.saturating_mul(branching_factor as u64);
+                }
+                Self::with_level_multipliers::<P0, P1>(
+                    branching_factor,
+                    depth,
+                    even_level,
+                    odd_level,
+                )
+            })
+            .collect()
+    }
+
+    /// The configuration of trees of `branching_factor` and `depth`, from the multipliers of a single level
+    /// on the even and on the odd curve.
+    pub(crate) fn with_level_multipliers<P0: SWCurveConfig, P1: SWCurveConfig>(
+        branching_factor: usize,
+        depth: usize,
+        even_level: usize,
+        odd_level: usize,
+    ) -> TreeConfig {
+        let capacity = (0..depth).fold(1u64, |capacity, _| {
+            capacity.saturating_mul(branching_factor as u64)
+        });
+        // a path of depth `depth` has `depth / 2` odd and `depth - depth / 2` even commitments,
+        // the even ones are rerandomized in the odd proof
+        let multipliers = (
+            depth / 2 * (even_level + branching_factor - 1),
+            (depth - depth / 2) * (odd_level + branching_factor - 1),
+        );
+        let padded_multipliers = (
+            multipliers.0.next_power_of_two(),
+            multipliers.1.next_power_of_two(),
+        );
+        let proof_size = 1 // the version byte of the path
+            + path_size::<P0>(depth - depth / 2)
+            + path_size::<P1>(depth / 2)
+            + r1cs_proof_size::<P0>(padded_multipliers.0)
+            + r1cs_proof_size::<P1>(padded_multipliers.1)
+            + 32 // the parameters fingerprint
+            + 8; // the leaf count
+        TreeConfig {
+            branching_factor,
+            depth,
+            capacity,
+            multipliers,
+            padded_multipliers,
+            proof_size,
+        }
+    }
+
+    /// A table of the configurations, one per line, with the recommendations of `recommend` marked.
+    pub fn comparison_table<
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+    >(
+        n_leaves: u64,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> String {
+        let size = Self::recommend(n_leaves, SizeOrSpeed::Size, parameters);
+        let speed = Self::recommend(n_leaves, SizeOrSpeed::Speed, parameters);
+        let mut table = format!(
+            "{:>6} {:>5} {:>20} {:>11} {:>11} {:>10} {:>10}  {}\n",
+            "L", "depth", "capacity", "even mults", "odd mults", "padded", "size", "best for"
+        );
+        for config in Self::candidates(n_leaves, parameters) {
+            let best_for = match (config == size, config == speed) {
+                (true, true) => "size, speed",
+                (true, false) => "size",
+                (false, true) => "speed",
+                (false, false) => "",
+            };
+            writeln!(
+                table,
+                "{:>6} {:>5} {:>20} {:>11} {:>11} {:>10} {:>10}  {}",
+                config.branching_factor,
+                config.depth,
+                config.capacity,
+                config.multipliers.0,
+                config.multipliers.1,
+                config.padded_multipliers.0 + config.padded_multipliers.1,
+                config.proof_size,
+                best_for
+            )
+            .expect("writing to a string does not fail");
+        }
+        table
+    }
+
+    /// Prints `comparison_table`.
+    pub fn print_comparison_table<
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+    >(
+        n_leaves: u64,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) {
+        print!("{}", Self::comparison_table(n_leaves, parameters));
+    }
+}
+
+// The multipliers of a level of a single child: the select gadget costs one multiplier per child but the first.
+pub(crate) fn level_multipliers<
+    Fb: PrimeField,
+    Fs: PrimeField,
+    C2: SWCurveConfig<BaseField = Fs, ScalarField = Fb> + Copy,
+    C: AffineRepr<ScalarField = Fs>,
+>(
+    parameters: &SingleLayerParameters<C2>,
+) -> usize {
+    let mut verifier = Verifier::<_, C>::new(Transcript::new(protocol::SHAPE));
+    single_level_select_and_rerandomize(
+        &mut verifier,
+        parameters,
+        &Affine::generator(),
+        &[Variable::One(PhantomData).into()],
+        None,
+        None,
+    );
+    verifier.metrics().multipliers
+}
+
+fn path_size<P: SWCurveConfig>(commitments: usize) -> usize {
+    8 + commitments * Affine::<P>::generator().compressed_size()
+}
+
+// A version byte, three first phase commitments and a flag, seven commitments to the coefficients of t, three scalars,
+// and the inner product proof of a round per power of two.
+fn r1cs_proof_size<P: SWCurveConfig>(padded_multipliers: usize) -> usize {
+    let point = Affine::<P>::generator().compressed_size();
+    let scalar = P::ScalarField::zero().compressed_size();
+    let rounds = padded_multipliers.trailing_zeros() as usize;
+    1 + 3 * point + 1 + (8 + 7 * point) + 3 * scalar + 2 * (8 + rounds * point) + 2 * scalar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve_tree::CurveTree;
+    use crate::cycle::EvenScalar;
+    use crate::shape::TreeShape;
+
+    type PallasParameters = ark_pallas::PallasConfig;
+    type VestaParameters = ark_vesta::VestaConfig;
+
+    fn parameters() -> SelRerandParameters<PallasParameters, VestaParameters> {
+        SelRerandParameters::new(1 << 12, 1 << 12)
+    }
+
+    #[test]
+    fn test_estimates_match_proofs() {
+        let mut rng = rand::thread_rng();
+        let parameters = parameters();
+        let candidates = TreeConfig::candidates(1000, &parameters);
+        let config = candidates
+            .iter()
+            .find(|config| config.branching_factor == 32)
+            .unwrap();
+        assert_eq!(config.depth, 2);
+        assert_eq!(config.capacity, 1024);
+        assert_eq!(
+            config.padded_multipliers,
+            TreeShape::<32, 2>::padded_multipliers(&parameters).unwrap()
+        );
+
+        let (leaf, blinding) = parameters.even_parameters.uh.permissible_commitment(
+            &parameters.even_parameters.pc_gens.B,
+            &parameters.even_parameters.pc_gens.B_blinding,
+        );
+        let tree = CurveTree::<32, PallasParameters, VestaParameters>::from_set(
+            &[leaf],
+            &parameters,
+            Some(2),
+        )
+        .unwrap();
+        let (proof, _) = tree
+            .prove_membership(0, EvenScalar(blinding), &parameters, &mut rng)
+            .unwrap();
+        assert_eq!(config.proof_size, proof.compressed_size());
+
+        // A single level, with an empty even proof.
+        let config = TreeConfig::candidates(32, &parameters)[4];
+        assert_eq!((config.branching_factor, config.depth), (32, 1));
+        let tree = CurveTree::<32, PallasParameters, VestaParameters>::from_set(
+            &[leaf],
+            &parameters,
+            Some(1),
+        )
+        .unwrap();
+        let (proof, _) = tree
+            .prove_membership(0, EvenScalar(blinding), &parameters, &mut rng)
+            .unwrap();
+        assert_eq!(
+            (config.padded_multipliers, config.proof_size),
+            (
+                (
+                    proof.even_proof.padded_multipliers().unwrap(),
+                    proof.odd_proof.padded_multipliers().unwrap()
+                ),
+                proof.compressed_size()
+            )
+        );
+    }
+
+    #[test]
+    fn test_recommend() {
+        let parameters = parameters();
+        for n_leaves in [1_000, 1_000_000, 1_000_000_000] {
+            let candidates = TreeConfig::candidates(n_leaves, &parameters);
+            let size = TreeConfig::recommend(n_leaves, SizeOrSpeed::Size, &parameters);
+            let speed = TreeConfig::recommend(n_leaves, SizeOrSpeed::Speed, &parameters);
+            for config in [size, speed] {
+                assert!(candidates.contains(&config));
+                // the least depth holding the leaves
+                assert!(config.capacity >= n_leaves);
+                assert!(config.capacity / (config.branching_factor as u64) < n_leaves);
+            }
+            assert!(candidates
+                .iter()
+                .all(|config| config.proof_size >= size.proof_size));
+            let total =
+                |config: &TreeConfig| config.padded_multipliers.0 + config.padded_multipliers.1;
+            assert!(candidates
+                .iter()
+                .all(|config| total(config) >= total(&speed)));
+            // neither extreme of the branching factors is best: binary trees are deep, and the widest ones
+            // select among thousands of children at every level
+            for config in [size, speed] {
+                assert!(config.branching_factor > 2 && config.branching_factor < 4096);
+            }
+        }
+        // shallow trees: a level costs about as much as a thousand children to select from
+        let speed = TreeConfig::recommend(1_000_000, SizeOrSpeed::Speed, &parameters);
+        assert!(speed.depth <= 4);
+        let size = TreeConfig::recommend(1_000_000_000, SizeOrSpeed::Size, &parameters);
+        assert!(size.depth <= 5);
+
+        let table = TreeConfig::comparison_table(1_000_000, &parameters);
+        assert_eq!(table.lines().count(), CANDIDATE_BRANCHING_FACTORS.len() + 1);
+    }
+}
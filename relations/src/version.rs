@@ -0,0 +1,132 @@
+//! The versions of the serialized formats of proofs, paths and transactions.
+//!
+//! Serialized `R1CSProof`s, `SelectAndRerandomizePath`s and `SignedTx`s start with a version byte,
+//! and deserializing data of an unknown version fails with `Error::UnsupportedVersion`
+//! (`R1CSError::UnsupportedVersion` for proofs, when they are verified).
+//! Each version implies the circuits its proofs were made for, so a change to a circuit, a transcript label
+//! or a serialization is a new version: proofs stored on chain remain readable as what they are, or are refused.
+
+use ark_ec::AffineRepr;
+use bulletproofs::PedersenGens;
+use merlin::Transcript;
+
+use crate::circuit::{CircuitDescription, CircuitStep};
+use crate::error::Error;
+use crate::protocol;
+
+/// The known versions of the formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FormatVersion {
+    /// Paths and transactions of the version 1 of the protocol, with the proofs of `R1CS_PROOF_VERSION` 1,
+    /// made with parameters blinding with the base of `PedersenGens::hashed_from_basepoint`.
+    V1 = 1,
+    /// As version 1, with parameters blinding with the base of `PedersenGens::default`, drawn from `PEDERSEN_DOMAIN`.
+    V2 = 2,
+}
+
+impl FormatVersion {
+    /// The version written by this build.
+    pub const CURRENT: Self = Self::V2;
+
+    /// The known versions, oldest first.
+    pub const ALL: [Self; 2] = [Self::V1, Self::V2];
+
+    /// The version of a version byte, failing with `Error::UnsupportedVersion` for an unknown one.
+    pub fn from_byte(byte: u8) -> Result<Self, Error> {
+        match byte {
+            1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
+            version => Err(Error::UnsupportedVersion { version }),
+        }
+    }
+
+    /// The version byte of serialized data.
+    pub fn byte(self) -> u8 {
+        self as u8
+    }
+
+    /// The version of the R1CS proofs of this version.
+    pub fn r1cs_proof_version(self) -> u8 {
+        match self {
+            Self::V1 | Self::V2 => 1,
+        }
+    }
+
+    /// The version of the serialized circuit descriptions of this version.
+    pub fn circuit_description_version(self) -> u8 {
+        match self {
+            Self::V1 | Self::V2 => 1,
+        }
+    }
+
+    /// The Pedersen generators of the parameters of this version, see `SelRerandParameters::for_version`.
+    pub fn pedersen_gens<C: AffineRepr>(self) -> PedersenGens<C> {
+        match self {
+            Self::V1 => PedersenGens::hashed_from_basepoint(),
+            Self::V2 => PedersenGens::default(),
+        }
+    }
+
+    /// The circuit of a membership proof in a tree of this shape: a single select and rerandomize step,
+    /// in versions 1 and 2 the constraints of `SelectAndRerandomizePath::even_verifier_gadget` and `odd_verifier_gadget`.
+    pub fn membership_circuit(self, branching_factor: usize, height: usize) -> CircuitDescription {
+        match self {
+            Self::V1 | Self::V2 => {
+                CircuitDescription::new(vec![CircuitStep::SelectAndRerandomize {
+                    branching_factor,
+                    height,
+                }])
+            }
+        }
+    }
+
+    /// Appends the version byte to both transcripts, see `SelRerandParameters::bind`.
+    pub fn bind(self, even_transcript: &mut Transcript, odd_transcript: &mut Transcript) {
+        even_transcript.append_u64(protocol::FORMAT_VERSION, self.byte() as u64);
+        odd_transcript.append_u64(protocol::FORMAT_VERSION, self.byte() as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::CIRCUIT_DESCRIPTION_VERSION;
+    use bulletproofs::r1cs::R1CS_PROOF_VERSION;
+
+    #[test]
+    fn test_current_version() {
+        assert_eq!(
+            FormatVersion::CURRENT.r1cs_proof_version(),
+            R1CS_PROOF_VERSION
+        );
+        assert_eq!(
+            FormatVersion::CURRENT.circuit_description_version(),
+            CIRCUIT_DESCRIPTION_VERSION
+        );
+        assert_eq!(
+            FormatVersion::from_byte(FormatVersion::CURRENT.byte()).unwrap(),
+            FormatVersion::CURRENT
+        );
+        assert!(matches!(
+            FormatVersion::from_byte(0),
+            Err(Error::UnsupportedVersion { version: 0 })
+        ));
+        assert!(matches!(
+            FormatVersion::from_byte(3),
+            Err(Error::UnsupportedVersion { version: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_versions_round_trip() {
+        for version in FormatVersion::ALL {
+            assert_eq!(FormatVersion::from_byte(version.byte()).unwrap(), version);
+        }
+        assert_eq!(FormatVersion::ALL.last(), Some(&FormatVersion::CURRENT));
+        // The versions differ in their blinding base alone.
+        let v1 = FormatVersion::V1.pedersen_gens::<ark_pallas::Affine>();
+        let v2 = FormatVersion::V2.pedersen_gens::<ark_pallas::Affine>();
+        assert_eq!(v1.B, v2.B);
+        assert_ne!(v1.B_blinding, v2.B_blinding);
+    }
+}
@@ -0,0 +1,511 @@
+use crate::coin::{Coin, CoinId, SpendingInfo};
+use crate::curve_tree::{LeafRef, ParametersFingerprint, SelRerandParameters};
+use crate::encoding::Tag;
+use crate::error::Error;
+use crate::events::{self, Event, SharedEventSink};
+use crate::protocol;
+use crate::version::FormatVersion;
+
+use ark_crypto_primitives::signature::schnorr::{Parameters, PublicKey, SecretKey};
+use ark_ec::{
+    models::short_weierstrass::SWCurveConfig, short_weierstrass::Affine, AffineRepr, CurveGroup,
+};
+use ark_ff::PrimeField;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use blake2::Blake2s256 as Blake2s;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use rand::{CryptoRng, RngCore};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashSet};
+
+/// The spending tags revealed on chain, by the pours of the blocks a wallet or a node has seen.
+#[derive(Clone, Debug)]
+pub struct TagSet<F: PrimeField> {
+    tags: HashSet<Tag<F>>,
+    event_sink: Option<SharedEventSink>,
+}
+
+impl<F: PrimeField> Default for TagSet<F> {
+    fn default() -> Self {
+        Self {
+            tags: HashSet::new(),
+            event_sink: None,
+        }
+    }
+}
+
+impl<F: PrimeField> TagSet<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a revealed tag, returns whether it was not already revealed.
+    pub fn insert(&mut self, tag: F) -> bool {
+        let inserted = self.tags.insert(Tag(tag));
+        if let (true, Some(sink)) = (inserted, &self.event_sink) {
+            sink.record(&Event::tag_added(&tag));
+        }
+        inserted
+    }
+
+    pub fn contains(&self, tag: &F) -> bool {
+        self.tags.contains(&Tag(*tag))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// The digest of the tags, whatever the order they were revealed in, see `events::tags_digest`.
+    pub fn digest(&self) -> [u8; 32] {
+        events::tags_digest(self.tags.iter().map(|tag| &tag.0))
+    }
+
+    /// Records the tags revealed from now on in `sink`, as `Event::TagAdded`, a tag already revealed not again.
+    /// The tags already revealed are recorded first, in the order of their encodings,
+    /// so that the log replays from an empty set.
+    pub fn set_event_sink(&mut self, sink: SharedEventSink) {
+        let mut tags: Vec<_> = self
+            .tags
+            .iter()
+            .map(|tag| Event::tag_added(&tag.0))
+            .collect();
+        tags.sort_unstable_by(|a, b| match (a, b) {
+            (Event::TagAdded { tag: a }, Event::TagAdded { tag: b }) => a.cmp(b),
+            _ => unreachable!("only tags are recorded"),
+        });
+        for event in &tags {
+            sink.record(event);
+        }
+        self.event_sink = Some(sink);
+    }
+}
+
+impl<F: PrimeField> Extend<F> for TagSet<F> {
+    fn extend<I: IntoIterator<Item = F>>(&mut self, tags: I) {
+        for tag in tags {
+            self.insert(tag);
+        }
+    }
+}
+
+/// A coin held by a wallet, with the commitment it was minted with.
+#[derive(Clone)]
+struct StoredCoin<P0: SWCurveConfig + Clone, C: CurveGroup> {
+    coin: Coin<P0, C>,
+    commitment: Affine<P0>,
+    spent: bool,
+}
+
+/// The coins of a wallet, keyed by `Coin::id`, and whether their tags were revealed on chain.
+///
+/// The store holds no secret key: the rerandomized public key of a coin is derived again from the key pair
+/// of the wallet by `spending_info`, with the index of the coin in the tree, e.g. after a restart.
+/// It serializes to persist across restarts, the ids are recomputed when it is deserialized.
+#[derive(Clone)]
+pub struct WalletStore<P0: SWCurveConfig + Clone, C: CurveGroup> {
+    coins: BTreeMap<CoinId, StoredCoin<P0, C>>,
+}
+
+impl<P0: SWCurveConfig + Clone, C: CurveGroup> Default for WalletStore<P0, C> {
+    fn default() -> Self {
+        Self {
+            coins: BTreeMap::new(),
+        }
+    }
+}
+
+impl<
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+        C: CurveGroup,
+    > WalletStore<P0, C>
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a received coin, minted with `commitment`, as unspent and returns its id.
+    /// Adding a coin again keeps its spent status.
+    pub fn insert(&mut self, coin: Coin<P0, C>, commitment: Affine<P0>) -> CoinId {
+        let id = coin.id(&commitment);
+        self.coins.entry(id).or_insert(StoredCoin {
+            coin,
+            commitment,
+            spent: false,
+        });
+        id
+    }
+
+    pub fn coin(&self, id: &CoinId) -> Option<&Coin<P0, C>> {
+        self.coins.get(id).map(|stored| &stored.coin)
+    }
+
+    pub fn commitment(&self, id: &CoinId) -> Option<&Affine<P0>> {
+        self.coins.get(id).map(|stored| &stored.commitment)
+    }
+
+    /// Whether the coin was spent, `None` if the wallet does not hold it.
+    pub fn is_spent(&self, id: &CoinId) -> Option<bool> {
+        self.coins.get(id).map(|stored| stored.spent)
+    }
+
+    /// Marks spent the coins whose tags are in `tags`, and returns the ids of those not marked before.
+    pub fn sync(&mut self, tags: &TagSet<F0>) -> Vec<CoinId> {
+        self.coins
+            .iter_mut()
+            .filter(|(_, stored)| !stored.spent && tags.contains(&stored.coin.tag))
+            .map(|(id, stored)| {
+                stored.spent = true;
+                *id
+            })
+            .collect()
+    }
+
+    /// The ids and coins not spent yet, in the order of their ids.
+    pub fn unspent(&self) -> impl Iterator<Item = (&CoinId, &Coin<P0, C>)> {
+        self.coins
+            .iter()
+            .filter(|(_, stored)| !stored.spent)
+            .map(|(id, stored)| (id, &stored.coin))
+    }
+
+    /// The sum of the values of the unspent coins.
+    pub fn balance(&self) -> u128 {
+        self.unspent().map(|(_, coin)| u128::from(coin.value)).sum()
+    }
+
+    pub fn len(&self) -> usize {
+        self.coins.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coins.is_empty()
+    }
+
+    /// The spending info of the unspent coin `id` at `leaf` in the tree, owned by the key pair `pk`, `sk`.
+    /// Fails with `Error::InconsistentWitness` if the wallet does not hold the coin, if it is spent,
+    /// or if the key pair does not own it.
+    pub fn spending_info(
+        &self,
+        id: &CoinId,
+        leaf: LeafRef,
+        pk: &PublicKey<C>,
+        sk: &SecretKey<C>,
+        parameters: &Parameters<C, Blake2s>,
+    ) -> Result<SpendingInfo<P0, C>, Error> {
+        let stored = self
+            .coins
+            .get(id)
+            .ok_or_else(|| Error::inconsistent_witness("The wallet does not hold the coin"))?;
+        if stored.spent {
+            return Err(Error::inconsistent_witness("The coin is spent"));
+        }
+        let randomized_pk =
+            Coin::<P0, C>::rerandomized_pk(pk, &stored.coin.pk_randomness.total(), parameters);
+        if Coin::<P0, C>::pk_to_scalar(&randomized_pk) != stored.coin.tag {
+            return Err(Error::inconsistent_witness(
+                "The public key does not own the coin",
+            ));
+        }
+        Ok(SpendingInfo {
+            leaf,
+            coin_aux: stored.coin.clone(),
+            randomized_pk,
+            sk: sk.clone(),
+        })
+    }
+
+    /// The store encrypted to the recovery key `recovery_pk` of the odd curve, as a backup restored by `import_encrypted`.
+    ///
+    /// The backup is an ECIES ciphertext: a Diffie–Hellman key with an ephemeral key derives the key of a ChaCha20-Poly1305
+    /// encryption, whose associated data are the format version, the fingerprint of `parameters` and the ephemeral key,
+    /// written in clear before the ciphertext. The key encrypts this backup alone, under the zero nonce.
+    /// As the store, the backup holds no secret key: restoring it needs the key pair of the wallet, see `spending_info`.
+    /// Fails with `Error::IdentityPoint` if `recovery_pk` is the identity.
+    pub fn export_encrypted<P1, R>(
+        &self,
+        recovery_pk: &Affine<P1>,
+        parameters: &SelRerandParameters<P0, P1>,
+        rng: &mut R,
+    ) -> Result<Vec<u8>, Error>
+    where
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+        R: RngCore + CryptoRng,
+    {
+        if recovery_pk.is_zero() {
+            return Err(Error::IdentityPoint);
+        }
+        let ephemeral_sk = loop {
+            let sk = F1::rand(rng);
+            if !sk.is_zero() {
+                break sk;
+            }
+        };
+        let ephemeral_pk = (Affine::<P1>::generator() * ephemeral_sk).into_affine();
+        let shared = (*recovery_pk * ephemeral_sk).into_affine();
+
+        let mut blob = vec![FormatVersion::CURRENT.byte()];
+        blob.extend_from_slice(&parameters.fingerprint());
+        ephemeral_pk.serialize_compressed(&mut blob)?;
+        let mut plaintext = Vec::new();
+        self.serialize_compressed(&mut plaintext)?;
+        let ciphertext = ChaCha20Poly1305::new(&backup_key(&ephemeral_pk, &shared))
+            .encrypt(
+                &Nonce::default(),
+                Payload {
+                    msg: &plaintext,
+                    aad: &blob,
+                },
+            )
+            .expect("a backup is shorter than the limit of ChaCha20-Poly1305");
+        blob.extend_from_slice(&ciphertext);
+        Ok(blob)
+    }
+
+    /// Restores a store from a backup of `export_encrypted`, with the secret recovery key `recovery_sk`.
+    ///
+    /// Fails with `Error::UnsupportedVersion` for a backup of a format version this build does not know,
+    /// with `Error::ParameterFingerprintMismatch` if it was made with other parameters than `parameters`,
+    /// and with `Error::InvalidBackup`, before decrypting anything, if its authentication tag does not verify:
+    /// the blob was altered or truncated, or `recovery_sk` is not the key it was encrypted to.
+    pub fn import_encrypted<P1>(
+        blob: &[u8],
+        recovery_sk: &F1,
+        parameters: &SelRerandParameters<P0, P1>,
+    ) -> Result<Self, Error>
+    where
+        P1: SWCurveConfig<BaseField = F0, ScalarField = F1> + Copy,
+    {
+        let (&version, rest) = blob.split_first().ok_or(Error::InvalidBackup)?;
+        FormatVersion::from_byte(version)?;
+        let fingerprint_len = std::mem::size_of::<ParametersFingerprint>();
+        let point_len = Affine::<P1>::generator().compressed_size();
+        let tag_len = 16;
+        if rest.len() < fingerprint_len + point_len + tag_len {
+            return Err(Error::InvalidBackup);
+        }
+        let mut fingerprint = ParametersFingerprint::default();
+        fingerprint.copy_from_slice(&rest[..fingerprint_len]);
+        parameters.check_fingerprint(&fingerprint)?;
+        let ephemeral_pk =
+            Affine::<P1>::deserialize_compressed(&rest[fingerprint_len..][..point_len])
+                .map_err(|_| Error::InvalidBackup)?;
+        if ephemeral_pk.is_zero() {
+            return Err(Error::InvalidBackup);
+        }
+
+        let (header, ciphertext) = blob.split_at(1 + fingerprint_len + point_len);
+        let shared = (ephemeral_pk * *recovery_sk).into_affine();
+        let plaintext = ChaCha20Poly1305::new(&backup_key(&ephemeral_pk, &shared))
+            .decrypt(
+                &Nonce::default(),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| Error::InvalidBackup)?;
+        Ok(Self::deserialize_compressed(plaintext.as_slice())?)
+    }
+}
+
+/// The key of the AEAD of a backup, derived with HKDF from the Diffie–Hellman key, salted with the ephemeral key.
+/// The domain holds the protocol version, so a backup of another version of the protocol does not decrypt.
+fn backup_key<P: SWCurveConfig>(ephemeral_pk: &Affine<P>, shared: &Affine<P>) -> Key {
+    let mut salt = Vec::new();
+    ephemeral_pk
+        .serialize_compressed(&mut salt)
+        .expect("serializing to a vector does not fail");
+    let mut secret = Vec::new();
+    shared
+        .serialize_compressed(&mut secret)
+        .expect("serializing to a vector does not fail");
+    let mut key = Key::default();
+    Hkdf::<Sha256>::new(Some(&salt), &secret)
+        .expand(protocol::WALLET_BACKUP_KEY_DOMAIN, &mut key)
+        .expect("a key of 32 bytes is a valid length of HKDF-SHA256");
+    key
+}
+
+impl<P0: SWCurveConfig + Clone, C: CurveGroup> CanonicalSerialize for WalletStore<P0, C> {
+    fn serialized_size(&self, compress: Compress) -> usize {
+        (self.coins.len() as u64).serialized_size(compress)
+            + self
+                .coins
+                .values()
+                .map(|stored| {
+                    stored.coin.serialized_size(compress)
+                        + stored.commitment.serialized_size(compress)
+                        + stored.spent.serialized_size(compress)
+                })
+                .sum::<usize>()
+    }
+
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        (self.coins.len() as u64).serialize_with_mode(&mut writer, compress)?;
+        for stored in self.coins.values() {
+            stored.coin.serialize_with_mode(&mut writer, compress)?;
+            stored
+                .commitment
+                .serialize_with_mode(&mut writer, compress)?;
+            stored.spent.serialize_with_mode(&mut writer, compress)?;
+        }
+        Ok(())
+    }
+}
+
+impl<P0: SWCurveConfig + Clone, C: CurveGroup> Valid for WalletStore<P0, C> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl<
+        F0: PrimeField,
+        F1: PrimeField,
+        P0: SWCurveConfig<BaseField = F1, ScalarField = F0> + Copy,
+        C: CurveGroup,
+    > CanonicalDeserialize for WalletStore<P0, C>
+{
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let len = u64::deserialize_with_mode(&mut reader, compress, validate)?;
+        let mut store = Self::new();
+        for _ in 0..len {
+            let coin = Coin::<P0, C>::deserialize_with_mode(&mut reader, compress, validate)?;
+            let commitment = Affine::<P0>::deserialize_with_mode(&mut reader, compress, validate)?;
+            let spent = bool::deserialize_with_mode(&mut reader, compress, validate)?;
+            let id = coin.id(&commitment);
+            if store
+                .coins
+                .insert(
+                    id,
+                    StoredCoin {
+                        coin,
+                        commitment,
+                        spent,
+                    },
+                )
+                .is_some()
+            {
+                // the serialization of a store holds each coin once
+                return Err(SerializationError::InvalidData);
+            }
+        }
+        Ok(store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve_tree::SelRerandParameters;
+    use crate::layout::CommitmentLayout;
+    use ark_crypto_primitives::signature::{schnorr::Schnorr, SignatureScheme};
+
+    type PallasParameters = ark_pallas::PallasConfig;
+    type VestaParameters = ark_vesta::VestaConfig;
+    type PallasP = ark_pallas::Projective;
+
+    #[test]
+    fn test_wallet_store() {
+        let mut rng = rand::thread_rng();
+        let sr_params =
+            SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 8, 1 << 8);
+        let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let layout = CommitmentLayout::coin();
+        let (pk, sk) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+        let (other_pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+
+        let mut store = WalletStore::<PallasParameters, PallasP>::new();
+        let ids: Vec<_> = [19, 23, 0]
+            .iter()
+            .map(|value| {
+                let (coin, commitment) = Coin::new(
+                    *value,
+                    &pk,
+                    &schnorr_parameters,
+                    &sr_params.even_parameters,
+                    &layout,
+                    &mut rng,
+                )
+                .unwrap();
+                assert_eq!(coin.id(&commitment), coin.clone().id(&commitment));
+                store.insert(coin, commitment)
+            })
+            .collect();
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.balance(), 42);
+
+        // The tag of the first coin is revealed on chain, by a pour spending it.
+        let mut tags = TagSet::new();
+        tags.insert(store.coin(&ids[0]).unwrap().tag);
+        assert_eq!(store.sync(&tags), vec![ids[0]]);
+        assert!(store.sync(&tags).is_empty());
+        assert_eq!(store.is_spent(&ids[0]), Some(true));
+        assert_eq!(store.is_spent(&ids[1]), Some(false));
+        assert_eq!(store.balance(), 23);
+
+        // After a restart.
+        let mut bytes = Vec::new();
+        store.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), store.compressed_size());
+        let restored =
+            WalletStore::<PallasParameters, PallasP>::deserialize_compressed(bytes.as_slice())
+                .unwrap();
+        assert_eq!(restored.len(), store.len());
+        for id in &ids {
+            assert_eq!(restored.is_spent(id), store.is_spent(id));
+            assert!(restored.coin(id).unwrap().opening() == store.coin(id).unwrap().opening());
+            assert_eq!(restored.commitment(id), store.commitment(id));
+        }
+
+        // The spending info is derived again from the key pair and the leaf of the coin.
+        let leaf = |index| LeafRef {
+            index,
+            root_epoch: 0,
+        };
+        let info = restored
+            .spending_info(&ids[1], leaf(1), &pk, &sk, &schnorr_parameters)
+            .unwrap();
+        assert_eq!(info.leaf, leaf(1));
+        assert_eq!(
+            info.randomized_pk,
+            Coin::<PallasParameters, PallasP>::rerandomized_pk(
+                &pk,
+                &store.coin(&ids[1]).unwrap().pk_randomness.total(),
+                &schnorr_parameters
+            )
+        );
+        assert!(matches!(
+            restored.spending_info(&ids[0], leaf(0), &pk, &sk, &schnorr_parameters),
+            Err(Error::InconsistentWitness { .. })
+        ));
+        assert!(matches!(
+            restored.spending_info(&ids[1], leaf(1), &other_pk, &sk, &schnorr_parameters),
+            Err(Error::InconsistentWitness { .. })
+        ));
+        assert!(matches!(
+            restored.spending_info(&[0; 32], leaf(1), &pk, &sk, &schnorr_parameters),
+            Err(Error::InconsistentWitness { .. })
+        ));
+    }
+}
@@ -0,0 +1,290 @@
+//! Browser bindings for wallet operations, enabled by the `wasm` feature.
+//!
+//! Coins live on Pallas, the tree alternates with Vesta and has branching factor `BRANCHING_FACTOR`.
+//! Keys, coins, commitments and proofs cross the boundary as `Uint8Array`s of their compressed canonical serializations,
+//! while the parameters and the tree stay in wasm memory behind opaque handles.
+//! Build with `--no-default-features --features wasm`, the `parallel` feature needs threads.
+
+use wasm_bindgen::prelude::*;
+
+use ark_crypto_primitives::signature::{
+    schnorr::{Parameters, PublicKey, Schnorr, SecretKey},
+    SignatureScheme,
+};
+use ark_ec::short_weierstrass::Affine;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use blake2::Blake2s256 as Blake2s;
+use bulletproofs::r1cs::{Prover, R1CSProof, Verifier};
+use merlin::Transcript;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::coin::{verify_mint, Coin};
+use crate::ct::{field_ct_eq, point_ct_eq};
+use crate::curve_tree::{CurveTree, SelRerandParameters};
+use crate::layout::{CommitmentLayout, TAG, VALUE};
+use crate::protocol;
+
+type PallasConfig = ark_pallas::PallasConfig;
+type VestaConfig = ark_vesta::VestaConfig;
+type PallasA = Affine<PallasConfig>;
+type PallasP = ark_pallas::Projective;
+type VestaA = Affine<VestaConfig>;
+type WalletCoin = Coin<PallasConfig, PallasP>;
+
+/// The branching factor of trees built through the bindings.
+pub const BRANCHING_FACTOR: usize = 256;
+
+fn to_bytes<T: CanonicalSerialize>(t: &T) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(t.compressed_size());
+    t.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+fn from_bytes<T: CanonicalDeserialize>(reader: &mut &[u8]) -> Result<T, JsError> {
+    T::deserialize_compressed(reader).map_err(|e| JsError::new(&e.to_string()))
+}
+
+// value || tag || commitment_blinding || pk_randomness, the blindings as their totals
+fn coin_to_bytes(coin: &WalletCoin) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    coin.value.serialize_compressed(&mut bytes).unwrap();
+    coin.tag.serialize_compressed(&mut bytes).unwrap();
+    coin.commitment_blinding
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    coin.pk_randomness.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+fn coin_from_bytes(mut bytes: &[u8]) -> Result<WalletCoin, JsError> {
+    let reader = &mut bytes;
+    Ok(Coin {
+        value: from_bytes(reader)?,
+        tag: from_bytes(reader)?,
+        commitment_blinding: from_bytes(reader)?,
+        pk_randomness: from_bytes(reader)?,
+    })
+}
+
+/// Handle to the generators of both curves and the signature parameters.
+#[wasm_bindgen]
+pub struct WalletParameters {
+    sr_parameters: SelRerandParameters<PallasConfig, VestaConfig>,
+    sig_parameters: Parameters<PallasP, Blake2s>,
+    layout: CommitmentLayout,
+}
+
+#[wasm_bindgen]
+impl WalletParameters {
+    /// Derives all parameters deterministically from a 32 byte seed.
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: &[u8], generators_length_log_2: usize) -> Result<WalletParameters, JsError> {
+        let seed: [u8; 32] = seed
+            .try_into()
+            .map_err(|_| JsError::new("The seed must be 32 bytes"))?;
+        let mut rng = StdRng::from_seed(seed);
+        let generators_length = 1 << generators_length_log_2;
+        Ok(WalletParameters {
+            sr_parameters: SelRerandParameters::new(generators_length, generators_length),
+            sig_parameters: Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap(),
+            layout: CommitmentLayout::coin(),
+        })
+    }
+}
+
+/// Handle to a Curve Tree of coins.
+#[wasm_bindgen]
+pub struct WalletTree {
+    tree: CurveTree<BRANCHING_FACTOR, PallasConfig, VestaConfig>,
+}
+
+#[wasm_bindgen]
+impl WalletTree {
+    /// Builds a tree from the concatenated compressed coin commitments, padded to `height`.
+    pub fn from_leaves(
+        parameters: &WalletParameters,
+        leaves: &[u8],
+        height: usize,
+    ) -> Result<WalletTree, JsError> {
+        let mut reader = leaves;
+        let mut set: Vec<PallasA> = Vec::new();
+        while !reader.is_empty() {
+            set.push(from_bytes(&mut reader)?);
+        }
+        if set.is_empty() {
+            return Err(JsError::new("The curve tree must have at least one leaf"));
+        }
+        Ok(WalletTree {
+            tree: CurveTree::from_set(&set, &parameters.sr_parameters, Some(height))?,
+        })
+    }
+}
+
+/// A signing key pair, the public key is what senders mint coins to.
+#[wasm_bindgen]
+pub struct KeyPair {
+    public_key: PublicKey<PallasP>,
+    secret_key: SecretKey<PallasP>,
+}
+
+#[wasm_bindgen]
+impl KeyPair {
+    pub fn generate(parameters: &WalletParameters) -> KeyPair {
+        let (public_key, secret_key) =
+            Schnorr::keygen(&parameters.sig_parameters, &mut rand::thread_rng()).unwrap();
+        KeyPair {
+            public_key,
+            secret_key,
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn public_key(&self) -> Vec<u8> {
+        to_bytes(&self.public_key)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn secret_key(&self) -> Vec<u8> {
+        to_bytes(&self.secret_key.0)
+    }
+}
+
+/// A freshly minted coin: the public commitment and range proof, and the secret opening for the receiver.
+#[wasm_bindgen]
+pub struct MintedCoin {
+    commitment: Vec<u8>,
+    proof: Vec<u8>,
+    coin: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl MintedCoin {
+    #[wasm_bindgen(getter)]
+    pub fn commitment(&self) -> Vec<u8> {
+        self.commitment.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn proof(&self) -> Vec<u8> {
+        self.proof.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn coin(&self) -> Vec<u8> {
+        self.coin.clone()
+    }
+}
+
+/// Mints a coin of `value` to the public key `pk` and proves that the value is in range.
+#[wasm_bindgen]
+pub fn mint(parameters: &WalletParameters, value: u64, pk: &[u8]) -> Result<MintedCoin, JsError> {
+    let pk: PublicKey<PallasP> = from_bytes(&mut &pk[..])?;
+    let sr_parameters = &parameters.sr_parameters;
+    let mut prover: Prover<_, PallasA> = Prover::new(
+        &sr_parameters.even_parameters.pc_gens,
+        Transcript::new(protocol::WASM_MINT),
+    );
+    let (coin, commitment, _) = WalletCoin::mint(
+        value,
+        &pk,
+        &parameters.sig_parameters,
+        &sr_parameters.even_parameters,
+        &parameters.layout,
+        &sr_parameters.value_policy,
+        &mut rand::thread_rng(),
+        &mut prover,
+    )?;
+    let proof = prover
+        .prove(&sr_parameters.even_parameters.bp_gens)
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(MintedCoin {
+        commitment: to_bytes(&commitment),
+        proof: to_bytes(&proof),
+        coin: coin_to_bytes(&coin),
+    })
+}
+
+/// Verifies the range proof of a coin minted by `mint`.
+#[wasm_bindgen]
+pub fn verify_minted(
+    parameters: &WalletParameters,
+    commitment: &[u8],
+    proof: &[u8],
+) -> Result<bool, JsError> {
+    let commitment: PallasA = from_bytes(&mut &commitment[..])?;
+    let proof: R1CSProof<PallasA> = from_bytes(&mut &proof[..])?;
+    let even_parameters = &parameters.sr_parameters.even_parameters;
+    let mut verifier = Verifier::new(Transcript::new(protocol::WASM_MINT));
+    verify_mint(
+        &mut verifier,
+        commitment,
+        &parameters.layout,
+        &parameters.sr_parameters.value_policy,
+    )?;
+    Ok(verifier
+        .verify(&proof, &even_parameters.pc_gens, &even_parameters.bp_gens)
+        .is_ok())
+}
+
+/// Checks whether the coin opening received out of band opens `commitment` to a coin spendable by `pk`.
+#[wasm_bindgen]
+pub fn scan_output(
+    parameters: &WalletParameters,
+    pk: &[u8],
+    commitment: &[u8],
+    coin: &[u8],
+) -> Result<bool, JsError> {
+    let pk: PublicKey<PallasP> = from_bytes(&mut &pk[..])?;
+    let commitment: PallasA = from_bytes(&mut &commitment[..])?;
+    let coin = coin_from_bytes(coin)?;
+    let randomized_pk =
+        WalletCoin::rerandomized_pk(&pk, &coin.pk_randomness.total(), &parameters.sig_parameters);
+    let recomputed = parameters.sr_parameters.even_parameters.commit(
+        &parameters
+            .layout
+            .vector(&[(VALUE, coin.value.into()), (TAG, coin.tag)])?,
+        coin.commitment_blinding.total().0,
+        0,
+    )?;
+    Ok((point_ct_eq(&recomputed, &commitment)
+        & field_ct_eq(&coin.tag, &WalletCoin::pk_to_scalar(&randomized_pk)))
+    .into())
+}
+
+/// Proves that the coin at `index` is in the tree, returning the rerandomized path and the proofs on both curves.
+#[wasm_bindgen]
+pub fn prove_spend(
+    parameters: &WalletParameters,
+    tree: &WalletTree,
+    index: usize,
+    coin: &[u8],
+) -> Result<Vec<u8>, JsError> {
+    let coin = coin_from_bytes(coin)?;
+    let sr_parameters = &parameters.sr_parameters;
+    let mut even_prover: Prover<_, PallasA> = Prover::new(
+        &sr_parameters.even_parameters.pc_gens,
+        Transcript::new(protocol::WASM_SPEND),
+    );
+    let mut odd_prover: Prover<_, VestaA> = Prover::new(
+        &sr_parameters.odd_parameters.pc_gens,
+        Transcript::new(protocol::WASM_SPEND),
+    );
+    let (path, _) = coin.prove_spend(
+        index,
+        &mut even_prover,
+        &mut odd_prover,
+        sr_parameters,
+        &parameters.layout,
+        &tree.tree,
+    )?;
+    let even_proof = even_prover
+        .prove(&sr_parameters.even_parameters.bp_gens)
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    let odd_proof = odd_prover
+        .prove(&sr_parameters.odd_parameters.bp_gens)
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    let mut bytes = to_bytes(&path);
+    even_proof.serialize_compressed(&mut bytes).unwrap();
+    odd_proof.serialize_compressed(&mut bytes).unwrap();
+    Ok(bytes)
+}
@@ -0,0 +1,120 @@
+extern crate bulletproofs;
+extern crate relations;
+
+use bulletproofs::r1cs::*;
+use relations::curve_tree::*;
+
+use ark_std::UniformRand;
+use merlin::Transcript;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+type PallasParameters = ark_pallas::PallasConfig;
+type VestaParameters = ark_vesta::VestaConfig;
+type PallasA = ark_pallas::Affine;
+type VestaA = ark_vesta::Affine;
+type PallasScalar = ark_pallas::Fr;
+
+// Counts the allocations and reallocations of every thread.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let result = f();
+    (result, ALLOCATIONS.load(Ordering::Relaxed) - before)
+}
+
+// The only test of this binary, so that no other test allocates while it counts.
+#[test]
+fn test_select_and_rerandomize_allocations() {
+    const L: usize = 256;
+    let mut rng = rand::thread_rng();
+    let parameters = fixtures::pallas_vesta();
+    let leaves: Vec<_> = (0..L + 1)
+        .map(|_| {
+            parameters
+                .even_parameters
+                .permissible_commitment(
+                    &[PallasScalar::rand(&mut rng)],
+                    PallasScalar::rand(&mut rng),
+                    0,
+                )
+                .unwrap()
+                .0
+        })
+        .collect();
+    let tree =
+        CurveTree::<L, PallasParameters, VestaParameters>::from_set(&leaves, parameters, Some(3))
+            .unwrap();
+    let witness = tree.select_and_rerandomize_prover_witness(L).unwrap();
+
+    let prove = |witness: &CurveTreeWitnessPath<L, PallasParameters, VestaParameters>| {
+        let mut even_prover: Prover<_, PallasA> = Prover::new(
+            &parameters.even_parameters.pc_gens,
+            Transcript::new(b"allocations"),
+        );
+        let mut odd_prover: Prover<_, VestaA> = Prover::new(
+            &parameters.odd_parameters.pc_gens,
+            Transcript::new(b"allocations"),
+        );
+        allocations(|| {
+            witness
+                .select_and_rerandomize_prover_gadget(
+                    &mut even_prover,
+                    &mut odd_prover,
+                    parameters,
+                    &mut rand::thread_rng(),
+                )
+                .unwrap()
+        })
+    };
+    // Warm up whatever is initialized once, e.g. the thread pool.
+    let ((path, _), _) = prove(&witness);
+    let (_, prover_allocations) = prove(&witness);
+
+    let (_, verifier_allocations) = {
+        let mut even_verifier = Verifier::<_, PallasA>::new(Transcript::new(b"allocations"));
+        let mut odd_verifier = Verifier::<_, VestaA>::new(Transcript::new(b"allocations"));
+        allocations(|| {
+            tree.select_and_rerandomize_verifier_gadget(
+                &mut even_verifier,
+                &mut odd_verifier,
+                path.clone(),
+                parameters,
+            )
+            .unwrap()
+        })
+    };
+    // Three levels of 256 children: the select gadget borrows the children, reused across the levels,
+    // and the lookups and curve additions of the rerandomizations build their linear combinations in place.
+    // Before, proving made about 38000 allocations and verifying as many, now about 19000.
+    assert!(
+        prover_allocations < 24_000,
+        "{prover_allocations} allocations to prove"
+    );
+    assert!(
+        verifier_allocations < 24_000,
+        "{verifier_allocations} allocations to verify"
+    );
+}
@@ -0,0 +1,227 @@
+//! Encrypted backups of a wallet: restored with the recovery key, the coins can be spent again,
+//! and an altered backup or another key fails to restore.
+
+#![cfg(feature = "payments")]
+
+extern crate relations;
+
+use ark_crypto_primitives::signature::schnorr::Schnorr;
+use ark_crypto_primitives::signature::SignatureScheme;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_std::UniformRand;
+use blake2::Blake2s256 as Blake2s;
+use bulletproofs::r1cs::Prover;
+use merlin::Transcript;
+use relations::coin::*;
+use relations::curve_tree::*;
+use relations::layout::CommitmentLayout;
+use relations::policy::ValuePolicy;
+use relations::version::FormatVersion;
+use relations::wallet::{TagSet, WalletStore};
+use relations::Error;
+
+type PallasParameters = ark_pallas::PallasConfig;
+type VestaParameters = ark_vesta::VestaConfig;
+type PallasP = ark_pallas::Projective;
+type VestaA = ark_ec::short_weierstrass::Affine<VestaParameters>;
+type Store = WalletStore<PallasParameters, PallasP>;
+
+const L: usize = 32;
+const LABEL: &[u8] = b"backup";
+
+#[test]
+fn test_restored_backup_spends() {
+    let mut rng = fixtures::insecure_test_rng(0);
+    let parameters = fixtures::pallas_vesta();
+    let sig_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+    let layout = CommitmentLayout::coin();
+    let (pk, sk) = Schnorr::keygen(&sig_parameters, &mut rng).unwrap();
+    let recovery_sk = ark_vesta::Fr::rand(&mut rng);
+    let recovery_pk = (VestaA::generator() * recovery_sk).into_affine();
+
+    let mut store = Store::new();
+    let mut leaves = Vec::new();
+    let ids: Vec<_> = [19, 23]
+        .iter()
+        .map(|value| {
+            let (coin, commitment) = Coin::new(
+                *value,
+                &pk,
+                &sig_parameters,
+                &parameters.even_parameters,
+                &layout,
+                &mut rng,
+            )
+            .unwrap();
+            leaves.push(commitment);
+            store.insert(coin, commitment)
+        })
+        .collect();
+    let mut tree =
+        CurveTree::<L, PallasParameters, VestaParameters>::from_set(&leaves, parameters, Some(2))
+            .unwrap();
+
+    // The wallet is lost, the backup is restored with the recovery key.
+    let blob = store
+        .export_encrypted(&recovery_pk, parameters, &mut rng)
+        .unwrap();
+    let restored = Store::import_encrypted(&blob, &recovery_sk, parameters).unwrap();
+    assert_eq!(restored.len(), 2);
+    assert_eq!(restored.balance(), 42);
+
+    let input_0 = restored
+        .spending_info(
+            &ids[0],
+            tree.leaf_ref(0).unwrap(),
+            &pk,
+            &sk,
+            &sig_parameters,
+        )
+        .unwrap();
+    let input_1 = restored
+        .spending_info(
+            &ids[1],
+            tree.leaf_ref(1).unwrap(),
+            &pk,
+            &sk,
+            &sig_parameters,
+        )
+        .unwrap();
+    let tx = prove_pour(
+        Prover::new(&parameters.even_parameters.pc_gens, Transcript::new(LABEL)),
+        Prover::new(&parameters.odd_parameters.pc_gens, Transcript::new(LABEL)),
+        parameters,
+        &layout,
+        &tree,
+        &input_0,
+        &input_1,
+        30,
+        pk,
+        12,
+        pk,
+        &sig_parameters,
+        &mut rng,
+    )
+    .unwrap();
+    let mut tags = TagSet::new();
+    apply_transaction(
+        &mut tree,
+        &mut tags,
+        &tx,
+        LABEL,
+        parameters,
+        &layout,
+        &sig_parameters,
+    )
+    .unwrap();
+    let mut restored = restored;
+    assert_eq!(restored.sync(&tags).len(), 2);
+    assert_eq!(restored.balance(), 0);
+}
+
+#[test]
+fn test_altered_backup_fails() {
+    let mut rng = fixtures::insecure_test_rng(1);
+    let parameters = fixtures::pallas_vesta();
+    let sig_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+    let layout = CommitmentLayout::coin();
+    let (pk, _) = Schnorr::keygen(&sig_parameters, &mut rng).unwrap();
+    let recovery_sk = ark_vesta::Fr::rand(&mut rng);
+    let recovery_pk = (VestaA::generator() * recovery_sk).into_affine();
+
+    let mut store = Store::new();
+    let (coin, commitment) = Coin::new(
+        7,
+        &pk,
+        &sig_parameters,
+        &parameters.even_parameters,
+        &layout,
+        &mut rng,
+    )
+    .unwrap();
+    store.insert(coin, commitment);
+    let blob = store
+        .export_encrypted(&recovery_pk, parameters, &mut rng)
+        .unwrap();
+    assert_eq!(
+        Store::import_encrypted(&blob, &recovery_sk, parameters)
+            .unwrap()
+            .len(),
+        1
+    );
+
+    // Another recovery key.
+    let other_sk = ark_vesta::Fr::rand(&mut rng);
+    assert!(matches!(
+        Store::import_encrypted(&blob, &other_sk, parameters),
+        Err(Error::InvalidBackup)
+    ));
+
+    // Any byte altered after the fingerprint, in the ephemeral key, the ciphertext or the authentication tag.
+    for position in [
+        33,
+        34,
+        40,
+        80,
+        blob.len() / 2,
+        blob.len() - 17,
+        blob.len() - 1,
+    ] {
+        let mut altered = blob.clone();
+        altered[position] ^= 1;
+        assert!(matches!(
+            Store::import_encrypted(&altered, &recovery_sk, parameters),
+            Err(Error::InvalidBackup)
+        ));
+    }
+    for len in [0, 1, 33, 65, 81, blob.len() - 1] {
+        assert!(matches!(
+            Store::import_encrypted(&blob[..len], &recovery_sk, parameters),
+            Err(Error::InvalidBackup)
+        ));
+    }
+
+    // The version and the fingerprint fail loudly, before the authentication tag.
+    let mut newer = blob.clone();
+    newer[0] = 3;
+    assert!(matches!(
+        Store::import_encrypted(&newer, &recovery_sk, parameters),
+        Err(Error::UnsupportedVersion { version: 3 })
+    ));
+    let mut altered = blob.clone();
+    altered[1] ^= 1;
+    assert!(matches!(
+        Store::import_encrypted(&altered, &recovery_sk, parameters),
+        Err(Error::ParameterFingerprintMismatch)
+    ));
+    let other_parameters = parameters
+        .with_value_policy(ValuePolicy {
+            bits: 32,
+            ..ValuePolicy::default()
+        })
+        .unwrap();
+    assert!(matches!(
+        Store::import_encrypted(&blob, &recovery_sk, &other_parameters),
+        Err(Error::ParameterFingerprintMismatch)
+    ));
+
+    // The header is associated data: a backup relabelled with another known version,
+    // or for other parameters, does not decrypt.
+    let mut older = blob.clone();
+    older[0] = FormatVersion::V1.byte();
+    assert!(matches!(
+        Store::import_encrypted(&older, &recovery_sk, parameters),
+        Err(Error::InvalidBackup)
+    ));
+    let mut relabelled = blob.clone();
+    relabelled[1..33].copy_from_slice(&other_parameters.fingerprint());
+    assert!(matches!(
+        Store::import_encrypted(&relabelled, &recovery_sk, &other_parameters),
+        Err(Error::InvalidBackup)
+    ));
+
+    assert!(matches!(
+        store.export_encrypted(&VestaA::zero(), parameters, &mut rng),
+        Err(Error::IdentityPoint)
+    ));
+}
@@ -0,0 +1,294 @@
+extern crate bulletproofs;
+extern crate relations;
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::UniformRand;
+use bulletproofs::r1cs::R1CSError;
+use relations::circuit::*;
+use relations::curve_tree::*;
+use relations::metrics::{self, AtomicMetrics};
+use relations::Error;
+
+use ark_pallas::{Fr as PallasScalar, PallasConfig};
+use ark_vesta::VestaConfig;
+use std::sync::Arc;
+
+const L: usize = 32;
+const LABEL: &[u8] = b"circuit test";
+
+fn statements_for_verifier(
+    statements: &[StepStatement<L, PallasConfig, VestaConfig>],
+) -> Vec<StepStatement<L, PallasConfig, VestaConfig>> {
+    statements
+        .iter()
+        .map(|statement| match statement {
+            StepStatement::SelectAndRerandomize { path, .. } => {
+                StepStatement::SelectAndRerandomize {
+                    path: path.clone(),
+                    rerandomization: None,
+                }
+            }
+            StepStatement::RangeProof { commitment } => StepStatement::RangeProof {
+                commitment: *commitment,
+            },
+        })
+        .collect()
+}
+
+#[test]
+pub fn test_circuit_description() {
+    let mut rng = rand::thread_rng();
+    let parameters = fixtures::pallas_vesta();
+    let leaves: Vec<_> = (0..3)
+        .map(|_| {
+            parameters
+                .even_parameters
+                .permissible_commitment(
+                    &[PallasScalar::rand(&mut rng)],
+                    PallasScalar::rand(&mut rng),
+                    0,
+                )
+                .unwrap()
+                .0
+        })
+        .collect();
+    let tree =
+        CurveTree::<L, PallasConfig, VestaConfig>::from_set(&leaves, parameters, Some(2)).unwrap();
+
+    let description = CircuitDescription::new(vec![
+        CircuitStep::SelectAndRerandomize {
+            branching_factor: L,
+            height: 2,
+        },
+        CircuitStep::RangeProof { bits: 64 },
+    ]);
+    let mut bytes = Vec::new();
+    description.serialize_compressed(&mut bytes).unwrap();
+    let loaded = CircuitDescription::deserialize_compressed(bytes.as_slice()).unwrap();
+    assert_eq!(loaded, description);
+    assert_eq!(loaded.hash(), description.hash());
+    bytes[0] += 1;
+    assert!(CircuitDescription::deserialize_compressed(bytes.as_slice()).is_err());
+
+    let (proof, statements) = description
+        .prove(
+            LABEL,
+            &[
+                StepWitness::SelectAndRerandomize {
+                    tree: &tree,
+                    index: 1,
+                },
+                StepWitness::RangeProof {
+                    value: u64::MAX,
+                    blinding: PallasScalar::rand(&mut rng),
+                },
+            ],
+            parameters,
+            &mut rng,
+        )
+        .unwrap();
+    match &statements[0] {
+        StepStatement::SelectAndRerandomize {
+            path,
+            rerandomization: Some(rerandomization),
+        } => {
+            let blinding = parameters.even_parameters.pc_gens.B_blinding * rerandomization.0;
+            assert_eq!(path.get_rerandomized_leaf(), leaves[1] + blinding);
+        }
+        _ => panic!("Expected a select and rerandomize statement"),
+    }
+
+    let mut proof_bytes = Vec::new();
+    proof.serialize_compressed(&mut proof_bytes).unwrap();
+    let proof = CircuitProof::deserialize_compressed(proof_bytes.as_slice()).unwrap();
+    loaded
+        .verify(
+            LABEL,
+            statements_for_verifier(&statements),
+            &[&tree],
+            &proof,
+            parameters,
+        )
+        .unwrap();
+
+    // The proof is bound to the description it was made for.
+    let other = CircuitDescription::new(vec![
+        CircuitStep::SelectAndRerandomize {
+            branching_factor: L,
+            height: 2,
+        },
+        CircuitStep::RangeProof { bits: 63 },
+    ]);
+    assert_ne!(other.hash(), description.hash());
+    assert!(matches!(
+        other.verify(
+            LABEL,
+            statements_for_verifier(&statements),
+            &[&tree],
+            &proof,
+            parameters,
+        ),
+        Err(Error::R1CS(R1CSError::VerificationError))
+    ));
+
+    // Inputs that do not match the steps are rejected before laying down constraints.
+    assert!(matches!(
+        description.verify(
+            LABEL,
+            statements_for_verifier(&statements[1..]),
+            &[&tree],
+            &proof,
+            parameters,
+        ),
+        Err(Error::ParameterMismatch { .. })
+    ));
+    let taller =
+        CurveTree::<L, PallasConfig, VestaConfig>::from_set(&leaves, parameters, Some(4)).unwrap();
+    assert!(matches!(
+        description.verify(
+            LABEL,
+            statements_for_verifier(&statements),
+            &[&taller],
+            &proof,
+            parameters,
+        ),
+        Err(Error::ParameterMismatch { .. })
+    ));
+    assert!(matches!(
+        CircuitDescription::new(vec![CircuitStep::SelectAndRerandomize {
+            branching_factor: 256,
+            height: 2,
+        }])
+        .prove(
+            LABEL,
+            &[StepWitness::SelectAndRerandomize {
+                tree: &tree,
+                index: 0,
+            }],
+            parameters,
+            &mut rng,
+        ),
+        Err(Error::ParameterMismatch { .. })
+    ));
+    // As are witnesses that do not match the steps.
+    let witnesses: [StepWitness<L, PallasConfig, VestaConfig>; 1] = [StepWitness::RangeProof {
+        value: 1,
+        blinding: PallasScalar::rand(&mut rng),
+    }];
+    assert!(matches!(
+        description.prove(LABEL, &witnesses, parameters, &mut rng),
+        Err(Error::InconsistentWitness { .. })
+    ));
+}
+
+#[test]
+pub fn test_verification_context() {
+    let mut rng = rand::thread_rng();
+    let parameters = fixtures::pallas_vesta();
+    let leaves: Vec<_> = (0..3)
+        .map(|_| {
+            parameters
+                .even_parameters
+                .permissible_commitment(
+                    &[PallasScalar::rand(&mut rng)],
+                    PallasScalar::rand(&mut rng),
+                    0,
+                )
+                .unwrap()
+                .0
+        })
+        .collect();
+    let mut prove = |height| {
+        let tree =
+            CurveTree::<L, PallasConfig, VestaConfig>::from_set(&leaves, parameters, Some(height))
+                .unwrap();
+        let description = CircuitDescription::new(vec![
+            CircuitStep::SelectAndRerandomize {
+                branching_factor: L,
+                height,
+            },
+            CircuitStep::RangeProof { bits: 64 },
+        ]);
+        let (proof, statements) = description
+            .prove(
+                LABEL,
+                &[
+                    StepWitness::SelectAndRerandomize {
+                        tree: &tree,
+                        index: 2,
+                    },
+                    StepWitness::RangeProof {
+                        value: 1 << 40,
+                        blinding: PallasScalar::rand(&mut rng),
+                    },
+                ],
+                parameters,
+                &mut rng,
+            )
+            .unwrap();
+        (description, tree, proof, statements)
+    };
+    let (description, tree, proof, statements) = prove(3);
+    let (_, other_tree, other_proof, other_statements) = prove(2);
+
+    let counters = Arc::new(AtomicMetrics::new());
+    let context = SelRerandVerificationContext::<L, _, _>::new(parameters, description.clone())
+        .unwrap()
+        .with_metrics(counters.clone());
+    assert_eq!(context.description(), &description);
+    for _ in 0..2 {
+        context
+            .verify(
+                LABEL,
+                statements_for_verifier(&statements),
+                &[&tree],
+                &proof,
+            )
+            .unwrap();
+    }
+    let mut forged_statements = statements_for_verifier(&statements);
+    forged_statements[1] = StepStatement::RangeProof {
+        commitment: parameters.even_parameters.pc_gens.B,
+    };
+    assert!(matches!(
+        context.verify(LABEL, forged_statements, &[&tree], &proof),
+        Err(Error::R1CS(R1CSError::VerificationError))
+    ));
+
+    // Paths and proofs made for a tree of another height are rejected by their shape.
+    assert!(matches!(
+        context.verify(
+            LABEL,
+            statements_for_verifier(&other_statements),
+            &[&other_tree],
+            &other_proof,
+        ),
+        Err(Error::ShapeMismatch { .. })
+    ));
+    assert!(matches!(
+        context.verify(
+            LABEL,
+            statements_for_verifier(&statements),
+            &[&tree],
+            &other_proof,
+        ),
+        Err(Error::ShapeMismatch { .. })
+    ));
+
+    // Two proofs verified, of a multiscalar multiplication on each curve, and three failures by kind.
+    assert_eq!(counters.counter(metrics::PROOFS_VERIFIED, None), 2);
+    assert_eq!(counters.counter(metrics::PROOF_FAILURES, Some("R1CS")), 1);
+    assert_eq!(
+        counters.counter(metrics::PROOF_FAILURES, Some("ShapeMismatch")),
+        2
+    );
+    // Proofs rejected by their shape never reach the multiscalar multiplication.
+    let msm = counters.histogram(metrics::MSM_SIZE);
+    assert_eq!(msm.count, 6);
+    assert_eq!(
+        msm.sum,
+        3 * (4 + 2
+            * (proof.even_proof.padded_multipliers().unwrap()
+                + proof.odd_proof.padded_multipliers().unwrap()) as u64)
+    );
+}
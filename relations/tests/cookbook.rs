@@ -0,0 +1,378 @@
+//! Recipes composing the gadgets of the crate into small statements, written against the public API only.
+//!
+//! Each scenario is a prover and a verifier function in `recipes`, and a test proving and verifying it,
+//! then checking that a false statement does not verify. The recipes are meant to be copied and adapted:
+//! - `prove_in_whitelist`: a committed value is one of the values of a committed whitelist, see `select`,
+//! - `prove_value_at_least`: the value of a coin is at least a public threshold, see `OpenedLeaf` and `range_proof`,
+//! - `prove_same_owner`: two leaves of a curve tree commit to the same owner key, revealing neither leaf,
+//!   see `CurveTree::select_and_rerandomize_prover_gadget`.
+
+extern crate relations;
+
+use ark_ec::short_weierstrass::Affine;
+use ark_std::UniformRand;
+use rand::{CryptoRng, Rng};
+use relations::curve_tree::*;
+use relations::Error;
+
+use ark_pallas::{Fr as PallasScalar, PallasConfig};
+use ark_vesta::VestaConfig;
+
+type PallasA = Affine<PallasConfig>;
+
+/// The recipes, each a pair of functions laying down the same constraints on the prover's and the verifier's side.
+///
+/// A prover and its verifier must agree on everything absorbed into the transcript: its label,
+/// the layouts bound to it, and the order in which the commitments are made.
+mod recipes {
+    use super::*;
+
+    use bulletproofs::r1cs::{ConstraintSystem, Prover, R1CSProof, Variable, Verifier};
+    use merlin::Transcript;
+    use relations::layout::{CommitmentLayout, VALUE};
+    use relations::opened::OpenedLeaf;
+    use relations::range_proof::range_proof;
+    use relations::select::select;
+    use relations::single_level_select_and_rerandomize::SingleLayerParameters;
+
+    pub const WHITELIST: &[u8] = b"cookbook whitelist";
+    pub const THRESHOLD: &[u8] = b"cookbook threshold";
+    pub const SAME_OWNER: &[u8] = b"cookbook same owner";
+
+    /// The slot of the owner key of the leaves of `owned_layout`.
+    pub const OWNER: &str = "owner";
+
+    // --- A committed value in a committed whitelist ---
+
+    /// A proof that the value committed to in `value_commitment` is one of the values committed to in `whitelist_commitment`.
+    pub struct WhitelistProof {
+        pub whitelist_commitment: PallasA,
+        pub value_commitment: PallasA,
+        pub proof: R1CSProof<PallasA>,
+    }
+
+    /// Commits to `whitelist` and to its value at `index`, and proves the value is in the whitelist.
+    /// The whitelist is committed to as a vector, so it needs as many generators as it has values.
+    pub fn prove_in_whitelist<R: Rng + CryptoRng>(
+        whitelist: &[PallasScalar],
+        index: usize,
+        parameters: &SingleLayerParameters<PallasConfig>,
+        rng: &mut R,
+    ) -> Result<WhitelistProof, Error> {
+        let mut prover = Prover::new(&parameters.pc_gens, Transcript::new(WHITELIST));
+        let (whitelist_commitment, whitelist_variables) =
+            prover.commit_vec(whitelist, PallasScalar::rand(rng), &parameters.bp_gens);
+        let (value_commitment, value_variable) =
+            prover.commit(whitelist[index], PallasScalar::rand(rng));
+        enforce_in_whitelist(&mut prover, value_variable, &whitelist_variables);
+        Ok(WhitelistProof {
+            whitelist_commitment,
+            value_commitment,
+            proof: prover.prove(&parameters.bp_gens)?,
+        })
+    }
+
+    /// Verifier counterpart of `prove_in_whitelist`, for a whitelist of `whitelist_length` values.
+    pub fn verify_in_whitelist(
+        proof: &WhitelistProof,
+        whitelist_length: usize,
+        parameters: &SingleLayerParameters<PallasConfig>,
+    ) -> Result<(), Error> {
+        let mut verifier = Verifier::new(Transcript::new(WHITELIST));
+        let whitelist_variables = verifier.commit_vec(whitelist_length, proof.whitelist_commitment);
+        let value_variable = verifier.commit(proof.value_commitment);
+        enforce_in_whitelist(&mut verifier, value_variable, &whitelist_variables);
+        verifier.verify(&proof.proof, &parameters.pc_gens, &parameters.bp_gens)?;
+        Ok(())
+    }
+
+    // The constraints, shared by the prover and the verifier.
+    fn enforce_in_whitelist<CS: ConstraintSystem<PallasScalar>>(
+        cs: &mut CS,
+        value: Variable<PallasScalar>,
+        whitelist: &[Variable<PallasScalar>],
+    ) {
+        let whitelist: Vec<_> = whitelist
+            .iter()
+            .map(|variable| (*variable).into())
+            .collect();
+        select(cs, &value.into(), &whitelist);
+    }
+
+    // --- The value of a coin at least a threshold ---
+
+    /// Proves that the value of the coin opened by `vector` and `blinding`, laid out as `layout`, is at least `threshold`,
+    /// e.g. the `ValueOpening` of `CoinOpening::value_opening`. Returns the coin commitment opened and the proof,
+    /// which does not verify if the value is below the threshold.
+    pub fn prove_value_at_least(
+        vector: &[PallasScalar],
+        blinding: PallasScalar,
+        threshold: u64,
+        layout: &CommitmentLayout,
+        parameters: &SingleLayerParameters<PallasConfig>,
+    ) -> Result<(PallasA, R1CSProof<PallasA>), Error> {
+        let mut prover = Prover::new(&parameters.pc_gens, Transcript::new(THRESHOLD));
+        layout.bind(prover.transcript());
+        let (commitment, opened) =
+            OpenedLeaf::commit_prover(&mut prover, layout, vector, blinding, &parameters.bp_gens)?;
+        enforce_value_at_least(&mut prover, &opened, threshold)?;
+        Ok((commitment, prover.prove(&parameters.bp_gens)?))
+    }
+
+    /// Verifier counterpart of `prove_value_at_least`, for the coin commitment `commitment`.
+    pub fn verify_value_at_least(
+        commitment: PallasA,
+        threshold: u64,
+        proof: &R1CSProof<PallasA>,
+        layout: &CommitmentLayout,
+        parameters: &SingleLayerParameters<PallasConfig>,
+    ) -> Result<(), Error> {
+        let mut verifier = Verifier::new(Transcript::new(THRESHOLD));
+        layout.bind(verifier.transcript());
+        let opened = OpenedLeaf::commit_verifier(&mut verifier, layout, commitment);
+        enforce_value_at_least(&mut verifier, &opened, threshold)?;
+        verifier.verify(proof, &parameters.pc_gens, &parameters.bp_gens)?;
+        Ok(())
+    }
+
+    // The value minus the threshold is in [0, 2^64), so the value is in [threshold, threshold + 2^64):
+    // with the value itself below 2^64, as its mint proves, it is at least the threshold.
+    fn enforce_value_at_least<CS: ConstraintSystem<PallasScalar>>(
+        cs: &mut CS,
+        opened: &OpenedLeaf<PallasScalar>,
+        threshold: u64,
+    ) -> Result<(), Error> {
+        // A value below the threshold wraps around to an assignment whose bits do not recompose the difference.
+        let assignment = opened
+            .value_u64(VALUE)?
+            .map(|value| value.wrapping_sub(threshold));
+        range_proof(
+            cs,
+            opened.variable(VALUE)? - PallasScalar::from(threshold),
+            assignment,
+            64,
+        )?;
+        Ok(())
+    }
+
+    // --- Two leaves of a tree with the same owner ---
+
+    /// The layout of the leaves of the same owner recipe: a value and the key of its owner.
+    pub fn owned_layout() -> CommitmentLayout {
+        CommitmentLayout::new(&[(VALUE, 0), (OWNER, 1)]).expect("the slots are distinct")
+    }
+
+    /// A leaf of `value` owned by `owner`, permissible to be inserted into a tree, with the vector and blinding it commits with.
+    pub fn owned_leaf<R: Rng + CryptoRng>(
+        value: u64,
+        owner: PallasScalar,
+        parameters: &SingleLayerParameters<PallasConfig>,
+        rng: &mut R,
+    ) -> Result<(PallasA, Vec<PallasScalar>, PallasScalar), Error> {
+        let vector = owned_layout().vector(&[(VALUE, value.into()), (OWNER, owner)])?;
+        let (leaf, blinding) =
+            parameters.permissible_commitment(&vector, PallasScalar::rand(rng), 0)?;
+        Ok((leaf, vector, blinding))
+    }
+
+    /// A proof that the leaves at the ends of two paths of a tree commit to the same owner.
+    pub struct SameOwnerProof<const L: usize> {
+        pub paths: [SelectAndRerandomizePath<L, PallasConfig, VestaConfig>; 2],
+        pub even_proof: R1CSProof<PallasA>,
+        pub odd_proof: R1CSProof<Affine<VestaConfig>>,
+    }
+
+    /// Proves that the leaves of `tree` at the indices of `leaves`, each with its vector and blinding
+    /// as returned by `owned_leaf`, commit to the same owner, revealing only their rerandomizations.
+    pub fn prove_same_owner<const L: usize, R: Rng + CryptoRng>(
+        tree: &CurveTree<L, PallasConfig, VestaConfig>,
+        leaves: [(usize, &[PallasScalar], PallasScalar); 2],
+        parameters: &SelRerandParameters<PallasConfig, VestaConfig>,
+        rng: &mut R,
+    ) -> Result<SameOwnerProof<L>, Error> {
+        let layout = owned_layout();
+        let mut even_prover = Prover::new(
+            &parameters.even_parameters.pc_gens,
+            Transcript::new(SAME_OWNER),
+        );
+        let mut odd_prover = Prover::new(
+            &parameters.odd_parameters.pc_gens,
+            Transcript::new(SAME_OWNER),
+        );
+        layout.bind(even_prover.transcript());
+        let mut prove_leaf = |(index, vector, blinding): (usize, &[PallasScalar], PallasScalar)| {
+            let (path, rerandomization) = tree.select_and_rerandomize_prover_gadget(
+                index,
+                &mut even_prover,
+                &mut odd_prover,
+                parameters,
+                rng,
+            )?;
+            // The rerandomized leaf is a commitment to the same vector, blinded by the rerandomization as well.
+            let (_, opened) = OpenedLeaf::commit_prover(
+                &mut even_prover,
+                &layout,
+                vector,
+                blinding + rerandomization.0,
+                &parameters.even_parameters.bp_gens,
+            )?;
+            Ok::<_, Error>((path, opened.variable(OWNER)?))
+        };
+        let (path_0, owner_0) = prove_leaf(leaves[0])?;
+        let (path_1, owner_1) = prove_leaf(leaves[1])?;
+        even_prover.constrain(owner_0 - owner_1);
+        let (even_proof, odd_proof) = prove_pair(even_prover, odd_prover, parameters)?;
+        Ok(SameOwnerProof {
+            paths: [path_0, path_1],
+            even_proof,
+            odd_proof,
+        })
+    }
+
+    /// Verifier counterpart of `prove_same_owner`.
+    pub fn verify_same_owner<const L: usize>(
+        tree: &CurveTree<L, PallasConfig, VestaConfig>,
+        proof: &SameOwnerProof<L>,
+        parameters: &SelRerandParameters<PallasConfig, VestaConfig>,
+    ) -> Result<(), Error> {
+        let layout = owned_layout();
+        let mut even_verifier = Verifier::new(Transcript::new(SAME_OWNER));
+        let mut odd_verifier = Verifier::new(Transcript::new(SAME_OWNER));
+        layout.bind(even_verifier.transcript());
+        let mut owners = Vec::new();
+        for path in &proof.paths {
+            let leaf = tree.select_and_rerandomize_verifier_gadget(
+                &mut even_verifier,
+                &mut odd_verifier,
+                path.clone(),
+                parameters,
+            )?;
+            let opened = OpenedLeaf::commit_verifier(&mut even_verifier, &layout, leaf);
+            owners.push(opened.variable(OWNER)?);
+        }
+        even_verifier.constrain(owners[0] - owners[1]);
+        even_verifier.verify(
+            &proof.even_proof,
+            &parameters.even_parameters.pc_gens,
+            &parameters.even_parameters.bp_gens,
+        )?;
+        odd_verifier.verify(
+            &proof.odd_proof,
+            &parameters.odd_parameters.pc_gens,
+            &parameters.odd_parameters.bp_gens,
+        )?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_value_in_whitelist() {
+    let mut rng = fixtures::insecure_test_rng(0);
+    let parameters = &fixtures::pallas_vesta().even_parameters;
+    let whitelist: Vec<_> = (0..16).map(|_| PallasScalar::rand(&mut rng)).collect();
+
+    let proof = recipes::prove_in_whitelist(&whitelist, 5, parameters, &mut rng).unwrap();
+    recipes::verify_in_whitelist(&proof, whitelist.len(), parameters).unwrap();
+
+    // A value committed apart, not in the whitelist.
+    let mut other = recipes::prove_in_whitelist(&whitelist, 5, parameters, &mut rng).unwrap();
+    other.value_commitment = parameters
+        .commit(
+            &[PallasScalar::rand(&mut rng)],
+            PallasScalar::rand(&mut rng),
+            0,
+        )
+        .unwrap();
+    assert!(recipes::verify_in_whitelist(&other, whitelist.len(), parameters).is_err());
+    // The whitelist read back with another length.
+    assert!(recipes::verify_in_whitelist(&proof, whitelist.len() - 1, parameters).is_err());
+}
+
+#[cfg(feature = "payments")]
+#[test]
+fn test_coin_value_at_least_threshold() {
+    use ark_crypto_primitives::signature::{schnorr::Schnorr, SignatureScheme};
+    use blake2::Blake2s256 as Blake2s;
+    use relations::coin::Coin;
+    use relations::layout::CommitmentLayout;
+
+    type PallasP = ark_pallas::Projective;
+
+    let mut rng = fixtures::insecure_test_rng(1);
+    let parameters = &fixtures::pallas_vesta().even_parameters;
+    let layout = CommitmentLayout::coin();
+    let sig_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+    let (pk, _) = Schnorr::keygen(&sig_parameters, &mut rng).unwrap();
+    let (coin, coin_commitment) = Coin::<PallasConfig, PallasP>::new(
+        1000,
+        &pk,
+        &sig_parameters,
+        parameters,
+        &layout,
+        &mut rng,
+    )
+    .unwrap();
+    let opening = coin.opening().value_opening(&layout).unwrap();
+
+    for threshold in [0, 999, 1000] {
+        let (commitment, proof) = recipes::prove_value_at_least(
+            &opening.vector,
+            opening.blinding,
+            threshold,
+            &layout,
+            parameters,
+        )
+        .unwrap();
+        assert_eq!(commitment, coin_commitment);
+        recipes::verify_value_at_least(coin_commitment, threshold, &proof, &layout, parameters)
+            .unwrap();
+        // The same proof for a higher threshold.
+        assert!(recipes::verify_value_at_least(
+            coin_commitment,
+            threshold + 1,
+            &proof,
+            &layout,
+            parameters
+        )
+        .is_err());
+    }
+    let (_, proof) =
+        recipes::prove_value_at_least(&opening.vector, opening.blinding, 1001, &layout, parameters)
+            .unwrap();
+    assert!(
+        recipes::verify_value_at_least(coin_commitment, 1001, &proof, &layout, parameters).is_err()
+    );
+}
+
+#[test]
+fn test_leaves_of_same_owner() {
+    let mut rng = fixtures::insecure_test_rng(2);
+    let parameters = fixtures::pallas_vesta();
+    let owner = PallasScalar::rand(&mut rng);
+    let other_owner = PallasScalar::rand(&mut rng);
+    let leaves: Vec<_> = [
+        (10, owner),
+        (20, other_owner),
+        (30, owner),
+        (40, other_owner),
+    ]
+    .into_iter()
+    .map(|(value, owner)| {
+        recipes::owned_leaf(value, owner, &parameters.even_parameters, &mut rng).unwrap()
+    })
+    .collect();
+    let tree = CurveTree::<32, PallasConfig, VestaConfig>::from_set(
+        &leaves.iter().map(|(leaf, _, _)| *leaf).collect::<Vec<_>>(),
+        parameters,
+        Some(2),
+    )
+    .unwrap();
+    let at = |index: usize| (index, leaves[index].1.as_slice(), leaves[index].2);
+
+    let proof = recipes::prove_same_owner(&tree, [at(0), at(2)], parameters, &mut rng).unwrap();
+    recipes::verify_same_owner(&tree, &proof, parameters).unwrap();
+
+    // Leaves of different owners.
+    let proof = recipes::prove_same_owner(&tree, [at(0), at(1)], parameters, &mut rng).unwrap();
+    assert!(recipes::verify_same_owner(&tree, &proof, parameters).is_err());
+}
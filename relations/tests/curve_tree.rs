@@ -2,10 +2,13 @@ extern crate bulletproofs;
 extern crate relations;
 
 use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use bulletproofs::r1cs::*;
 
 use rand::thread_rng;
 use relations::curve_tree::*;
+use relations::cycle::{EvenPoint, EvenScalar, OddPoint, OddScalar};
+use relations::Error;
 
 use ark_ec::{
     short_weierstrass::{Affine, SWCurveConfig},
@@ -18,22 +21,21 @@ type PallasParameters = ark_pallas::PallasConfig;
 type VestaParameters = ark_vesta::VestaConfig;
 type PallasP = ark_pallas::Projective;
 
-use ark_pallas::{Fq as PallasBase, PallasConfig};
+use ark_pallas::{Fq as PallasBase, Fr as PallasScalar, PallasConfig};
 use ark_vesta::VestaConfig;
 
-use ark_secp256k1::{Config as SecpConfig, Fq as SecpBase};
-use ark_secq256k1::Config as SecqConfig;
+use ark_secp256k1::Fq as SecpBase;
 
 #[test]
 pub fn test_curve_tree_even_depth() {
-    test_curve_tree_with_parameters::<32, PallasBase, PallasConfig, VestaConfig>(4, 11);
-    test_curve_tree_with_parameters::<32, SecpBase, SecpConfig, SecqConfig>(4, 11);
+    test_curve_tree_with_parameters::<32, PallasBase, _, _>(fixtures::pallas_vesta(), 4);
+    test_curve_tree_with_parameters::<32, SecpBase, _, _>(fixtures::secp_secq(), 4);
 }
 
 #[test]
 pub fn test_curve_tree_odd_depth() {
-    test_curve_tree_with_parameters::<32, PallasBase, PallasConfig, VestaConfig>(3, 11);
-    test_curve_tree_with_parameters::<32, SecpBase, SecpConfig, SecqConfig>(3, 11);
+    test_curve_tree_with_parameters::<32, PallasBase, _, _>(fixtures::pallas_vesta(), 3);
+    test_curve_tree_with_parameters::<32, SecpBase, _, _>(fixtures::secp_secq(), 3);
 }
 
 pub fn test_curve_tree_with_parameters<
@@ -42,14 +44,10 @@ pub fn test_curve_tree_with_parameters<
     P0: SWCurveConfig<BaseField = F> + Copy,
     P1: SWCurveConfig<BaseField = P0::ScalarField, ScalarField = P0::BaseField> + Copy,
 >(
+    sr_params: &SelRerandParameters<P0, P1>,
     depth: usize,
-    generators_length_log_2: usize,
 ) {
     let mut rng = rand::thread_rng();
-    let generators_length = 1 << generators_length_log_2;
-
-    let sr_params =
-        SelRerandParameters::<P0, P1>::new(generators_length, generators_length, &mut rng);
 
     let pallas_transcript = Transcript::new(b"select_and_rerandomize");
     let mut pallas_prover: Prover<_, Affine<P0>> =
@@ -65,16 +63,18 @@ pub fn test_curve_tree_with_parameters<
         .uh
         .permissible_commitment(&some_point, &sr_params.even_parameters.pc_gens.B_blinding);
     let set = vec![permissible_point];
-    let curve_tree = CurveTree::<L, P0, P1>::from_set(&set, &sr_params, Some(depth));
+    let curve_tree = CurveTree::<L, P0, P1>::from_set(&set, sr_params, Some(depth)).unwrap();
     assert_eq!(curve_tree.height(), depth);
 
-    let (path_commitments, _) = curve_tree.select_and_rerandomize_prover_gadget(
-        0,
-        &mut pallas_prover,
-        &mut vesta_prover,
-        &sr_params,
-        &mut rng,
-    );
+    let (path_commitments, _) = curve_tree
+        .select_and_rerandomize_prover_gadget(
+            0,
+            &mut pallas_prover,
+            &mut vesta_prover,
+            sr_params,
+            &mut rng,
+        )
+        .unwrap();
 
     let pallas_proof = pallas_prover
         .prove(&sr_params.even_parameters.bp_gens)
@@ -83,18 +83,28 @@ pub fn test_curve_tree_with_parameters<
         .prove(&sr_params.odd_parameters.bp_gens)
         .unwrap();
 
+    // Shorter paths are rejected, including ones that keep the parity of the root.
+    assert!(curve_tree.is_valid_path(&path_commitments));
+    let mut short_path = path_commitments.clone();
+    short_path.even_commitments.pop();
+    assert!(!curve_tree.is_valid_path(&short_path));
+    short_path.odd_commitments.pop();
+    assert!(!curve_tree.is_valid_path(&short_path));
+
     {
         let pallas_transcript = Transcript::new(b"select_and_rerandomize");
         let mut pallas_verifier = Verifier::new(pallas_transcript);
         let vesta_transcript = Transcript::new(b"select_and_rerandomize");
         let mut vesta_verifier = Verifier::new(vesta_transcript);
 
-        let _rerandomized_leaf = curve_tree.select_and_rerandomize_verifier_gadget(
-            &mut pallas_verifier,
-            &mut vesta_verifier,
-            path_commitments,
-            &sr_params,
-        );
+        let _rerandomized_leaf = curve_tree
+            .select_and_rerandomize_verifier_gadget(
+                &mut pallas_verifier,
+                &mut vesta_verifier,
+                path_commitments,
+                sr_params,
+            )
+            .unwrap();
         let vesta_res = vesta_verifier.verify(
             &vesta_proof,
             &sr_params.odd_parameters.pc_gens,
@@ -113,13 +123,7 @@ pub fn test_curve_tree_with_parameters<
 #[test]
 pub fn test_curve_tree_batch_verification() {
     let mut rng = rand::thread_rng();
-    let generators_length = 1 << 12;
-
-    let sr_params = SelRerandParameters::<PallasParameters, VestaParameters>::new(
-        generators_length,
-        generators_length,
-        &mut rng,
-    );
+    let sr_params = fixtures::pallas_vesta();
 
     let some_point = PallasP::rand(&mut rng).into_affine();
     let (permissible_point, _) = sr_params
@@ -128,7 +132,8 @@ pub fn test_curve_tree_batch_verification() {
         .permissible_commitment(&some_point, &sr_params.even_parameters.pc_gens.B_blinding);
     let set = vec![permissible_point];
     let curve_tree =
-        CurveTree::<32, PallasParameters, VestaParameters>::from_set(&set, &sr_params, Some(4));
+        CurveTree::<32, PallasParameters, VestaParameters>::from_set(&set, sr_params, Some(4))
+            .unwrap();
     assert_eq!(curve_tree.height(), 4);
 
     let pallas_transcript = Transcript::new(b"select_and_rerandomize");
@@ -139,13 +144,15 @@ pub fn test_curve_tree_batch_verification() {
     let mut vesta_prover: Prover<_, Affine<VestaParameters>> =
         Prover::new(&sr_params.odd_parameters.pc_gens, vesta_transcript);
 
-    let (path_commitments, _) = curve_tree.select_and_rerandomize_prover_gadget(
-        0,
-        &mut pallas_prover,
-        &mut vesta_prover,
-        &sr_params,
-        &mut thread_rng(),
-    );
+    let (path_commitments, _) = curve_tree
+        .select_and_rerandomize_prover_gadget(
+            0,
+            &mut pallas_prover,
+            &mut vesta_prover,
+            sr_params,
+            &mut thread_rng(),
+        )
+        .unwrap();
 
     let pallas_proof = pallas_prover
         .prove(&sr_params.even_parameters.bp_gens)
@@ -160,12 +167,14 @@ pub fn test_curve_tree_batch_verification() {
         let vesta_transcript = Transcript::new(b"select_and_rerandomize");
         let mut vesta_verifier = Verifier::new(vesta_transcript);
 
-        let _rerandomized_leaf = curve_tree.select_and_rerandomize_verifier_gadget(
-            &mut pallas_verifier,
-            &mut vesta_verifier,
-            path_commitments.clone(),
-            &sr_params,
-        );
+        let _rerandomized_leaf = curve_tree
+            .select_and_rerandomize_verifier_gadget(
+                &mut pallas_verifier,
+                &mut vesta_verifier,
+                path_commitments.clone(),
+                sr_params,
+            )
+            .unwrap();
         let vesta_verification_tuples = vesta_verifier
             .verification_scalars_and_points(&vesta_proof)
             .unwrap();
@@ -180,12 +189,14 @@ pub fn test_curve_tree_batch_verification() {
         let vesta_transcript = Transcript::new(b"select_and_rerandomize");
         let mut vesta_verifier = Verifier::new(vesta_transcript);
 
-        let _rerandomized_leaf = curve_tree.select_and_rerandomize_verifier_gadget(
-            &mut pallas_verifier,
-            &mut vesta_verifier,
-            path_commitments,
-            &sr_params,
-        );
+        let _rerandomized_leaf = curve_tree
+            .select_and_rerandomize_verifier_gadget(
+                &mut pallas_verifier,
+                &mut vesta_verifier,
+                path_commitments,
+                sr_params,
+            )
+            .unwrap();
         let vesta_verification_tuples = vesta_verifier
             .verification_scalars_and_points(&vesta_proof)
             .unwrap();
@@ -207,3 +218,1693 @@ pub fn test_curve_tree_batch_verification() {
     assert_eq!(pallas_res, vesta_res);
     assert_eq!(pallas_res, Ok(()));
 }
+
+#[test]
+pub fn test_curve_tree_membership_proof() {
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+    let fixtures::TreeFixture {
+        tree: curve_tree,
+        values,
+        leaves,
+        blindings,
+    } = fixtures::small_tree();
+
+    let (proof, rerandomized_blinding) = curve_tree
+        .prove_membership(1, EvenScalar(blindings[1]), sr_params, &mut rng)
+        .unwrap();
+    assert_eq!(
+        proof.rerandomized_leaf(),
+        sr_params
+            .even_parameters
+            .commit(&[values[1]], rerandomized_blinding.0, 0)
+            .unwrap()
+    );
+
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    // The components of the size report sum to the serialized size, the rounds to two points per round.
+    let report = proof.size_report();
+    assert_eq!(report.total(), bytes.len());
+    let rounds = proof
+        .even_proof
+        .padded_multipliers()
+        .unwrap()
+        .trailing_zeros() as usize;
+    let (point, scalar) = (
+        proof.rerandomized_leaf().compressed_size(),
+        rerandomized_blinding.0.compressed_size(),
+    );
+    assert_eq!(
+        report.get("even proof rounds").unwrap(),
+        2 * 8 + 2 * rounds * point + 2 * scalar
+    );
+    let proof = MembershipProof::<32, PallasParameters, VestaParameters>::deserialize_compressed(
+        bytes.as_slice(),
+    )
+    .unwrap();
+    assert_eq!(
+        proof.verify(curve_tree, sr_params).unwrap(),
+        proof.rerandomized_leaf()
+    );
+
+    // Another tree, or a path of the wrong shape, does not verify.
+    let other_tree = CurveTree::<32, PallasParameters, VestaParameters>::from_set(
+        &leaves[..2],
+        sr_params,
+        Some(2),
+    )
+    .unwrap();
+    assert!(proof.verify(&other_tree, sr_params).is_err());
+    let mut short = proof.clone();
+    short.path.odd_commitments.pop();
+    assert!(matches!(
+        short.verify(curve_tree, sr_params),
+        Err(Error::InvalidPath)
+    ));
+}
+
+// Every corruption of a single component of either proof of a membership proof is rejected.
+#[test]
+pub fn test_curve_tree_corrupted_membership_proofs() {
+    use bulletproofs::r1cs::simulate::Corruption;
+
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+    let fixtures::TreeFixture {
+        leaves, blindings, ..
+    } = fixtures::small_tree();
+    let curve_tree =
+        CurveTree::<32, PallasParameters, VestaParameters>::from_set(leaves, sr_params, Some(3))
+            .unwrap();
+    let (proof, _) = curve_tree
+        .prove_membership(1, EvenScalar(blindings[1]), sr_params, &mut rng)
+        .unwrap();
+    assert!(proof.verify(&curve_tree, sr_params).is_ok());
+
+    for corruption in Corruption::all(&proof.even_proof) {
+        let mut corrupted = proof.clone();
+        corrupted.even_proof = corruption.apply(&proof.even_proof);
+        assert!(
+            corrupted.verify(&curve_tree, sr_params).is_err(),
+            "{:?} of the even proof verifies",
+            corruption
+        );
+    }
+    for corruption in Corruption::all(&proof.odd_proof) {
+        let mut corrupted = proof.clone();
+        corrupted.odd_proof = corruption.apply(&proof.odd_proof);
+        assert!(
+            corrupted.verify(&curve_tree, sr_params).is_err(),
+            "{:?} of the odd proof verifies",
+            corruption
+        );
+    }
+}
+
+#[test]
+pub fn test_curve_tree_parameters_fingerprint() {
+    let mut rng = rand::thread_rng();
+    let sr_params = SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 11, 1 << 11);
+
+    let (leaf, blinding) = sr_params
+        .even_parameters
+        .permissible_commitment(&[PallasScalar::from(1u64)], PallasScalar::rand(&mut rng), 0)
+        .unwrap();
+    let curve_tree =
+        CurveTree::<32, PallasParameters, VestaParameters>::from_set(&[leaf], &sr_params, Some(2))
+            .unwrap();
+    let (proof, _) = curve_tree
+        .prove_membership(0, EvenScalar(blinding), &sr_params, &mut rng)
+        .unwrap();
+    assert_eq!(proof.parameters_fingerprint, sr_params.fingerprint());
+
+    // The parameters are derived deterministically, another seed gives the same fingerprint.
+    let regenerated =
+        SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 11, 1 << 11);
+    assert_eq!(regenerated.fingerprint(), sr_params.fingerprint());
+    assert!(proof.verify(&curve_tree, &regenerated).is_ok());
+
+    // Generators of another capacity.
+    let other_params =
+        SelRerandParameters::<PallasParameters, VestaParameters>::new(1 << 12, 1 << 11);
+    assert_ne!(other_params.fingerprint(), sr_params.fingerprint());
+    assert!(matches!(
+        proof.verify(&curve_tree, &other_params),
+        Err(Error::ParameterFingerprintMismatch)
+    ));
+
+    // The fingerprint is absorbed in the transcripts, claiming the other one does not help.
+    let mut relabeled = proof.clone();
+    relabeled.parameters_fingerprint = other_params.fingerprint();
+    assert!(matches!(
+        relabeled.verify(&curve_tree, &other_params),
+        Err(Error::R1CS(R1CSError::VerificationError))
+    ));
+    assert!(matches!(
+        relabeled.verify(&curve_tree, &sr_params),
+        Err(Error::ParameterFingerprintMismatch)
+    ));
+}
+
+#[test]
+pub fn test_curve_tree_parameters_self_check() {
+    use relations::limits::{DeserializeLimits, DeserializeWithLimits};
+
+    type Parameters = SelRerandParameters<PallasParameters, VestaParameters>;
+    let mut rng = rand::thread_rng();
+    let sr_params = Parameters::new(1 << 11, 1 << 11);
+    assert!(sr_params.self_check().is_ok());
+    assert!(fixtures::secp_secq().self_check().is_ok());
+
+    let mut bytes = Vec::new();
+    sr_params.serialize_compressed(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), sr_params.compressed_size());
+    let deserialized = Parameters::deserialize_compressed(bytes.as_slice()).unwrap();
+    assert_eq!(deserialized.fingerprint(), sr_params.fingerprint());
+
+    // A proof made with the parameters verifies with those read back.
+    let (leaf, blinding) = sr_params
+        .even_parameters
+        .permissible_commitment(&[PallasScalar::from(1u64)], PallasScalar::rand(&mut rng), 0)
+        .unwrap();
+    let curve_tree =
+        CurveTree::<32, PallasParameters, VestaParameters>::from_set(&[leaf], &sr_params, Some(2))
+            .unwrap();
+    let (proof, _) = curve_tree
+        .prove_membership(0, EvenScalar(blinding), &sr_params, &mut rng)
+        .unwrap();
+    assert!(proof.verify(&curve_tree, &deserialized).is_ok());
+
+    // The even parameters are a point B, a point B_blinding, the capacity and the four constants of the hash.
+    let point_size = sr_params.even_parameters.pc_gens.B.compressed_size();
+    let base_size = PallasBase::from(0u64).compressed_size();
+    let capacity_at = 2 * point_size;
+    let constants_at = capacity_at + 8;
+    let layer_size = constants_at + 4 * base_size;
+    fn encode<T: CanonicalSerialize>(t: T) -> Vec<u8> {
+        let mut encoding = Vec::new();
+        t.serialize_compressed(&mut encoding).unwrap();
+        encoding
+    }
+    let corrupt = |at: usize, encoding: Vec<u8>| {
+        let mut corrupted = bytes.clone();
+        corrupted[at..at + encoding.len()].copy_from_slice(&encoding);
+        corrupted
+    };
+    let assert_mismatch = |corrupted: &[u8], what: &str| {
+        assert!(
+            matches!(
+                Parameters::deserialize_compressed_with_limits(
+                    corrupted,
+                    &DeserializeLimits::default()
+                ),
+                Err(Error::ParameterMismatch { .. })
+            ),
+            "{}",
+            what
+        );
+        assert!(Parameters::deserialize_compressed(corrupted).is_err());
+    };
+
+    let pc_gens = &sr_params.even_parameters.pc_gens;
+    assert_mismatch(&corrupt(point_size, encode(pc_gens.B)), "B_blinding = B");
+    assert_mismatch(
+        &corrupt(
+            point_size,
+            encode((pc_gens.B * PallasScalar::from(3u64)).into_affine()),
+        ),
+        "B_blinding = 3 * B",
+    );
+    assert_mismatch(
+        &corrupt(
+            0,
+            encode((-pc_gens.B_blinding * PallasScalar::from(200u64)).into_affine()),
+        ),
+        "B = -200 * B_blinding",
+    );
+    assert_mismatch(
+        &corrupt(0, encode(Affine::<PallasConfig>::identity())),
+        "B = 0",
+    );
+    // A Pedersen generator which is also a vector generator of either curve.
+    let g0 = *sr_params
+        .even_parameters
+        .bp_gens
+        .share(0)
+        .G(1)
+        .next()
+        .unwrap();
+    assert_mismatch(&corrupt(point_size, encode(g0)), "B_blinding = G[0]");
+    let last_h = *sr_params
+        .odd_parameters
+        .bp_gens
+        .share(0)
+        .H(1 << 11)
+        .last()
+        .unwrap();
+    assert_mismatch(&corrupt(layer_size, encode(last_h)), "odd B = H[2047]");
+    assert_mismatch(&corrupt(capacity_at, encode(0u64)), "no generators");
+    assert_mismatch(
+        &corrupt(constants_at, encode(PallasBase::from(0u64))),
+        "alpha = 0",
+    );
+    assert_mismatch(
+        &corrupt(constants_at + 3 * base_size, encode(PallasBase::from(8u64))),
+        "another coefficient b",
+    );
+    assert_mismatch(&corrupt(2 * layer_size, encode(65u8)), "values of 65 bits");
+
+    // A capacity above the limits fails before deriving the generators.
+    let huge = corrupt(capacity_at, encode(1u64 << 40));
+    assert!(matches!(
+        Parameters::deserialize_compressed_with_limits(
+            huge.as_slice(),
+            &DeserializeLimits::default()
+        ),
+        Err(Error::LimitExceeded { .. })
+    ));
+
+    // The even parameters read as those of both curves: each is valid, but the curves are not a cycle.
+    let mut same_curve = bytes[..layer_size].to_vec();
+    same_curve.extend_from_slice(&bytes[..layer_size]);
+    same_curve.extend_from_slice(&bytes[2 * layer_size..]);
+    assert!(matches!(
+        SelRerandParameters::<PallasConfig, PallasConfig>::deserialize_compressed_with_limits(
+            same_curve.as_slice(),
+            &DeserializeLimits::default()
+        ),
+        Err(Error::ParameterMismatch { .. })
+    ));
+}
+
+#[test]
+pub fn test_assert_independent_bases() {
+    use relations::single_level_select_and_rerandomize::SingleLayerParameters;
+    use std::sync::Arc;
+
+    let sr_params = fixtures::pallas_vesta();
+    assert!(assert_independent_bases(sr_params).is_ok());
+
+    // Parameters built with a Pedersen generator taken from the vector generators, as no constructor derives them.
+    let mut even = SingleLayerParameters::<PallasConfig>::new::<VestaConfig>(1 << 4);
+    even.pc_gens.B = *even.bp_gens.share(0).G(3).last().unwrap();
+    let colliding = SelRerandParameters {
+        even_parameters: Arc::new(even),
+        odd_parameters: sr_params.odd_parameters.clone(),
+        value_policy: sr_params.value_policy,
+    };
+    assert!(matches!(
+        assert_independent_bases(&colliding),
+        Err(Error::ParameterMismatch { description }) if description.contains("G[2]")
+    ));
+    assert!(colliding.self_check().is_err());
+
+    let mut odd = SingleLayerParameters::<VestaConfig>::new::<PallasConfig>(1 << 4);
+    odd.pc_gens.B_blinding = *odd.bp_gens.share(0).H(1).next().unwrap();
+    let colliding = SelRerandParameters {
+        even_parameters: sr_params.even_parameters.clone(),
+        odd_parameters: Arc::new(odd),
+        value_policy: sr_params.value_policy,
+    };
+    assert!(matches!(
+        assert_independent_bases(&colliding),
+        Err(Error::ParameterMismatch { description }) if description.contains("H[0]")
+    ));
+}
+
+#[test]
+pub fn test_curve_tree_leaf_curve_parity() {
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+
+    let even_values: Vec<_> = (0..3u64).map(PallasScalar::from).collect();
+    let (even_leaves, even_blindings): (Vec<_>, Vec<_>) = even_values
+        .iter()
+        .map(|v| {
+            sr_params
+                .even_parameters
+                .permissible_commitment(&[*v], PallasScalar::rand(&mut rng), 0)
+                .unwrap()
+        })
+        .unzip();
+    let odd_values: Vec<_> = (0..3u64).map(PallasBase::from).collect();
+    let (odd_leaves, odd_blindings): (Vec<_>, Vec<_>) = odd_values
+        .iter()
+        .map(|v| {
+            sr_params
+                .odd_parameters
+                .permissible_commitment(&[*v], PallasBase::rand(&mut rng), 0)
+                .unwrap()
+        })
+        .unzip();
+
+    for height in [2, 3] {
+        let even_tree = LeafCurveTree::<32, PallasParameters, VestaParameters>::from_even_set(
+            &even_leaves,
+            sr_params,
+            Some(height),
+        )
+        .unwrap();
+        let odd_tree = LeafCurveTree::<32, PallasParameters, VestaParameters>::from_odd_set(
+            &odd_leaves,
+            sr_params,
+            Some(height),
+        )
+        .unwrap();
+        assert_eq!(even_tree.height(), height);
+        assert_eq!(odd_tree.height(), height);
+        // The root is on the curve of the leaves for even heights.
+        assert_eq!(
+            matches!(even_tree.root(), CyclePoint::Even(_)),
+            height % 2 == 0
+        );
+        assert_eq!(
+            matches!(odd_tree.root(), CyclePoint::Odd(_)),
+            height % 2 == 0
+        );
+
+        let (even_proof, even_blinding) = even_tree
+            .prove_membership(
+                1,
+                CycleScalar::Even(EvenScalar(even_blindings[1])),
+                sr_params,
+                &mut rng,
+            )
+            .unwrap();
+        let (odd_proof, odd_blinding) = odd_tree
+            .prove_membership(
+                1,
+                CycleScalar::Odd(OddScalar(odd_blindings[1])),
+                sr_params,
+                &mut rng,
+            )
+            .unwrap();
+
+        // The rerandomized leaves land on the curve of the leaves.
+        let (CycleScalar::Even(even_blinding), CycleScalar::Odd(odd_blinding)) =
+            (even_blinding, odd_blinding)
+        else {
+            panic!("the blindings are on the curves of the leaves");
+        };
+        assert!(
+            even_proof.verify(&even_tree, sr_params).unwrap()
+                == CyclePoint::Even(EvenPoint(
+                    sr_params
+                        .even_parameters
+                        .commit(&[even_values[1]], even_blinding.0, 0)
+                        .unwrap()
+                ))
+        );
+        assert!(
+            odd_proof.verify(&odd_tree, sr_params).unwrap()
+                == CyclePoint::Odd(OddPoint(
+                    sr_params
+                        .odd_parameters
+                        .commit(&[odd_values[1]], odd_blinding.0, 0)
+                        .unwrap()
+                ))
+        );
+        assert!(matches!(
+            even_proof.verify(&odd_tree, sr_params),
+            Err(Error::InvalidPath)
+        ));
+
+        // The proofs mirror each other: the inner proof of the odd tree has its leaves, and so its "even" curve, on Vesta.
+        // Each has one rerandomized commitment for each level below the root,
+        // and the proofs on the curves of the leaves, and of their parents, have the same sizes.
+        let (LeafCurveMembershipProof::Even(even_proof), LeafCurveMembershipProof::Odd(odd_proof)) =
+            (even_proof, odd_proof)
+        else {
+            panic!("the proofs are of the parities of the trees");
+        };
+        assert_eq!(
+            even_proof.path.even_commitments.len() + even_proof.path.odd_commitments.len(),
+            height
+        );
+        assert_eq!(
+            (
+                odd_proof.path.even_commitments.len(),
+                odd_proof.path.odd_commitments.len()
+            ),
+            (
+                even_proof.path.even_commitments.len(),
+                even_proof.path.odd_commitments.len()
+            )
+        );
+        let point_size = PallasParameters::GENERATOR.compressed_size();
+        assert_eq!(VestaParameters::GENERATOR.compressed_size(), point_size);
+        assert_eq!(
+            even_proof.path.compressed_size(),
+            1 + 2 * 8 + point_size * height
+        );
+        assert_eq!(
+            even_proof.even_proof.compressed_size(),
+            odd_proof.even_proof.compressed_size()
+        );
+        assert_eq!(
+            even_proof.odd_proof.compressed_size(),
+            odd_proof.odd_proof.compressed_size()
+        );
+        assert_eq!(even_proof.compressed_size(), odd_proof.compressed_size());
+    }
+}
+
+#[test]
+pub fn test_curve_tree_accumulator() {
+    use relations::accumulator::Accumulator;
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+    let medium = fixtures::medium_tree();
+    let (values, leaves, blindings) = (
+        &medium.values[..6],
+        &medium.leaves[..6],
+        &medium.blindings[..6],
+    );
+
+    for storage in [PointStorage::Affine, PointStorage::Compressed] {
+        let mut tree = CurveTree::<4, PallasParameters, VestaParameters>::from_set_with_storage(
+            &leaves[..3],
+            sr_params,
+            Some(2),
+            storage,
+        )
+        .unwrap();
+        assert_eq!(tree.leaves(), leaves[..3]);
+        let root = Accumulator::root(&tree);
+
+        let witnesses = tree.insert(&leaves[3..], sr_params).unwrap();
+        assert_eq!(witnesses, vec![3, 4, 5]);
+        assert_eq!(tree.leaves(), leaves);
+        assert_eq!(tree.height(), 2);
+        assert_eq!(tree.storage(), storage);
+        assert!(Accumulator::root(&tree) != root);
+
+        let (proof, blinding) = Accumulator::prove_membership(
+            &tree,
+            &witnesses[1],
+            EvenScalar(blindings[4]),
+            sr_params,
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(
+            tree.verify_membership(&proof, sr_params).unwrap(),
+            sr_params
+                .even_parameters
+                .commit(&[values[4]], blinding.0, 0)
+                .unwrap()
+        );
+    }
+}
+
+// Proofs from a prepared witness are fresh rerandomizations of the same leaf, until the root changes.
+#[test]
+pub fn test_curve_tree_prepared_membership() {
+    use relations::accumulator::Accumulator;
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+    let medium = fixtures::medium_tree();
+    let (values, leaves, blindings) = (
+        &medium.values[..6],
+        &medium.leaves[..6],
+        &medium.blindings[..6],
+    );
+    let mut tree = CurveTree::<4, PallasParameters, VestaParameters>::from_set(
+        &leaves[..5],
+        sr_params,
+        Some(2),
+    )
+    .unwrap();
+
+    let prepared = tree
+        .prepare_membership(4, EvenScalar(blindings[4]))
+        .unwrap();
+    assert!(prepared.root() == tree.root());
+    let mut rerandomized_leaves = Vec::new();
+    for _ in 0..3 {
+        let (proof, blinding) = tree
+            .prove_membership_prepared(&prepared, sr_params, &mut rng)
+            .unwrap();
+        let rerandomized_leaf = proof.verify(&tree, sr_params).unwrap();
+        assert_eq!(
+            rerandomized_leaf,
+            sr_params
+                .even_parameters
+                .commit(&[values[4]], blinding.0, 0)
+                .unwrap()
+        );
+        assert!(!rerandomized_leaves.contains(&rerandomized_leaf));
+        rerandomized_leaves.push(rerandomized_leaf);
+    }
+
+    tree.insert(&leaves[5..], sr_params).unwrap();
+    assert!(!prepared.is_valid_for(&tree));
+    assert!(matches!(
+        tree.prove_membership_prepared(&prepared, sr_params, &mut rng),
+        Err(Error::InconsistentWitness { .. })
+    ));
+}
+
+// Every root the tree had keeps its number of leaves, which membership proofs against it commit to.
+#[test]
+pub fn test_curve_tree_leaf_count_at_root() {
+    use relations::accumulator::Accumulator;
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+    let medium = fixtures::medium_tree();
+    let (leaves, blindings) = (&medium.leaves[..6], &medium.blindings[..6]);
+    let mut tree = CurveTree::<4, PallasParameters, VestaParameters>::from_set(
+        &leaves[..1],
+        sr_params,
+        Some(2),
+    )
+    .unwrap();
+
+    let mut roots = vec![(tree.root(), 1)];
+    for (first, last) in [(1, 3), (3, 4), (4, 6)] {
+        let (proof, _) = tree
+            .prove_membership(
+                first - 1,
+                EvenScalar(blindings[first - 1]),
+                sr_params,
+                &mut rng,
+            )
+            .unwrap();
+        assert_eq!(proof.leaf_count, first as u64);
+        assert!(proof.verify(&tree, sr_params).is_ok());
+        tree.insert(&leaves[first..last], sr_params).unwrap();
+        roots.push((tree.root(), last as u64));
+    }
+    for (root, leaf_count) in &roots {
+        assert_eq!(tree.leaf_count_at_root(root), Some(*leaf_count));
+    }
+    let other_tree = CurveTree::<4, PallasParameters, VestaParameters>::from_set(
+        &leaves[1..],
+        sr_params,
+        Some(2),
+    )
+    .unwrap();
+    assert_eq!(tree.leaf_count_at_root(&other_tree.root()), None);
+
+    // The count is serialized with the proof, and a proof claiming another count is rejected.
+    let (proof, _) = tree
+        .prove_membership(2, EvenScalar(blindings[2]), sr_params, &mut rng)
+        .unwrap();
+    assert_eq!(proof.leaf_count, 6);
+    let mut lying = proof.clone();
+    lying.leaf_count = 1 << 20;
+    assert!(matches!(
+        lying.verify(&tree, sr_params),
+        Err(Error::InvalidPath)
+    ));
+    let mut bytes = Vec::new();
+    lying.serialize_compressed(&mut bytes).unwrap();
+    let decoded = MembershipProof::<4, PallasParameters, VestaParameters>::deserialize_compressed(
+        bytes.as_slice(),
+    )
+    .unwrap();
+    assert_eq!(decoded.leaf_count, 1 << 20);
+
+    // Only the most recent previous roots are kept, the current root always.
+    tree.set_root_history_window(2);
+    assert_eq!(tree.leaf_count_at_root(&roots[0].0), None);
+    for (root, leaf_count) in &roots[1..] {
+        assert_eq!(tree.leaf_count_at_root(root), Some(*leaf_count));
+    }
+    tree.insert(&medium.leaves[6..7], sr_params).unwrap();
+    assert_eq!(tree.leaf_count_at_root(&roots[1].0), None);
+    for (root, leaf_count) in &roots[2..] {
+        assert_eq!(tree.leaf_count_at_root(root), Some(*leaf_count));
+    }
+    assert_eq!(tree.leaf_count_at_root(&tree.root()), Some(7));
+    tree.set_root_history_window(0);
+    assert_eq!(tree.leaf_count_at_root(&roots[3].0), None);
+    assert_eq!(tree.leaf_count_at_root(&tree.root()), Some(7));
+}
+
+// A proof is verified against the tree of the window with the number of leaves it claims, and rejected once it left.
+#[test]
+pub fn test_curve_tree_verify_against_roots() {
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+    let medium = fixtures::medium_tree();
+    let (leaves, blindings) = (&medium.leaves[..5], &medium.blindings[..5]);
+    // The trees after each leaf is added.
+    let trees: Vec<_> = (1..=leaves.len())
+        .map(|count| {
+            CurveTree::<4, PallasParameters, VestaParameters>::from_set(
+                &leaves[..count],
+                sr_params,
+                Some(2),
+            )
+            .unwrap()
+        })
+        .collect();
+    // The window of the three most recent roots.
+    let window: Vec<_> = trees[2..].iter().collect();
+
+    let (oldest, _) = trees[2]
+        .prove_membership(0, EvenScalar(blindings[0]), sr_params, &mut rng)
+        .unwrap();
+    let (index, rerandomized_leaf) = oldest.verify_against_roots(&window, sr_params).unwrap();
+    assert_eq!(index, 0);
+    assert_eq!(
+        rerandomized_leaf,
+        oldest.verify(&trees[2], sr_params).unwrap()
+    );
+    let (newest, _) = trees[4]
+        .prove_membership(4, EvenScalar(blindings[4]), sr_params, &mut rng)
+        .unwrap();
+    assert_eq!(
+        newest.verify_against_roots(&window, sr_params).unwrap().0,
+        2
+    );
+
+    // A root out of the window, and a proof claiming the count of a root of the window it was not made against.
+    let (outside, _) = trees[1]
+        .prove_membership(1, EvenScalar(blindings[1]), sr_params, &mut rng)
+        .unwrap();
+    assert!(matches!(
+        outside.verify_against_roots(&window, sr_params),
+        Err(Error::InvalidPath)
+    ));
+    let mut lying = outside;
+    lying.leaf_count = 3;
+    assert!(lying.verify_against_roots(&window, sr_params).is_err());
+}
+
+#[test]
+pub fn test_curve_tree_inclusion_attestation() {
+    use relations::accumulator::Accumulator;
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+    let medium = fixtures::medium_tree();
+    let leaves = &medium.leaves[..];
+    let (absent, _) = sr_params
+        .even_parameters
+        .permissible_commitment(
+            &[PallasScalar::from(21u64)],
+            PallasScalar::rand(&mut rng),
+            0,
+        )
+        .unwrap();
+    let absent = &absent;
+
+    for storage in [PointStorage::Affine, PointStorage::Compressed] {
+        // A single leaf, a tree with an odd root and one with an even root.
+        for (count, height) in [(1, None), (21, None), (21, Some(4))] {
+            let tree = CurveTree::<4, PallasParameters, VestaParameters>::from_set_with_storage(
+                &leaves[..count],
+                sr_params,
+                height,
+                storage,
+            )
+            .unwrap();
+            for (index, leaf) in leaves[..count].iter().enumerate() {
+                assert_eq!(tree.leaf_indices_of(leaf), vec![index]);
+                let attestation = tree.inclusion_attestation(index).unwrap();
+                assert_eq!(
+                    attestation.even_levels.len() + attestation.odd_levels.len(),
+                    tree.height()
+                );
+                verify_attestation(&tree.root(), leaf, &attestation, sr_params).unwrap();
+            }
+            assert!(tree.leaf_indices_of(absent).is_empty());
+            assert!(matches!(
+                tree.inclusion_attestation(count),
+                Err(Error::ParameterMismatch { .. })
+            ));
+        }
+    }
+
+    // The index is extended when leaves are inserted.
+    let mut tree =
+        CurveTree::<4, PallasParameters, VestaParameters>::from_set(&leaves[..3], sr_params, None)
+            .unwrap();
+    tree.insert(&[*absent], sr_params).unwrap();
+    assert_eq!(tree.leaf_indices_of(absent), vec![3]);
+
+    // Tampered attestations, in a tree of height 3 with an odd root.
+    let tree = &medium.tree;
+    let root = tree.root();
+    let attestation = tree.inclusion_attestation(5).unwrap();
+    let rejects =
+        |root: &CyclePoint<PallasConfig, VestaConfig>,
+         leaf: &Affine<PallasConfig>,
+         attestation: &InclusionAttestation<4, PallasConfig, VestaConfig>| {
+            matches!(
+                verify_attestation(root, leaf, attestation, sr_params),
+                Err(Error::InvalidPath)
+            )
+        };
+    assert!(rejects(&root, &leaves[6], &attestation));
+    assert!(rejects(&root, absent, &attestation));
+    let other_tree =
+        CurveTree::<4, PallasParameters, VestaParameters>::from_set(&leaves[1..], sr_params, None)
+            .unwrap();
+    assert!(rejects(&other_tree.root(), &leaves[5], &attestation));
+
+    let mut other_index = attestation.clone();
+    other_index.index = 6;
+    assert!(rejects(&root, &leaves[5], &other_index));
+    // The same child indices, beyond the capacity of the tree.
+    other_index.index = 5 + 4usize.pow(3);
+    assert!(rejects(&root, &leaves[5], &other_index));
+
+    let mut other_offset = attestation.clone();
+    other_offset.odd_levels[0].blinding_offset += PallasBase::from(1u64);
+    assert!(rejects(&root, &leaves[5], &other_offset));
+    let mut other_sibling = attestation.clone();
+    other_sibling.even_levels[0].children[0] = Affine::<VestaConfig>::rand(&mut rng);
+    assert!(rejects(&root, &leaves[5], &other_sibling));
+    let mut missing_level = attestation.clone();
+    missing_level.odd_levels.pop();
+    assert!(rejects(&root, &leaves[5], &missing_level));
+
+    // The identity is not a leaf in an empty slot: the last leaf is alone in its node.
+    let mut empty_slot = tree.inclusion_attestation(20).unwrap();
+    empty_slot.odd_levels[1].children.push(Affine::identity());
+    empty_slot.index = 21;
+    assert!(rejects(&root, &Affine::identity(), &empty_slot));
+}
+
+// A commitment inserted more than once is a leaf at each index, whose membership can be proven and attested.
+#[test]
+pub fn test_curve_tree_duplicate_leaves() {
+    use relations::accumulator::Accumulator;
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+    let medium = fixtures::medium_tree();
+    let (value, duplicate, blinding) = (medium.values[7], medium.leaves[7], medium.blindings[7]);
+    let others = &medium.leaves[..4];
+
+    for storage in [PointStorage::Affine, PointStorage::Compressed] {
+        let mut tree = CurveTree::<4, PallasParameters, VestaParameters>::from_set_with_storage(
+            &[others[0], duplicate, others[1]],
+            sr_params,
+            Some(2),
+            storage,
+        )
+        .unwrap();
+        // Again in the same node, and in the next one.
+        let witnesses = tree
+            .insert(&[duplicate, others[2], duplicate], sr_params)
+            .unwrap();
+        assert_eq!(witnesses, vec![3, 4, 5]);
+        assert_eq!(tree.leaf_indices_of(&duplicate), vec![1, 3, 5]);
+        assert_eq!(tree.leaf_indices_of(&others[2]), vec![4]);
+        assert!(tree.leaf_indices_of(&others[3]).is_empty());
+
+        for index in tree.leaf_indices_of(&duplicate) {
+            let (proof, rerandomized_blinding) = Accumulator::prove_membership(
+                &tree,
+                &index,
+                EvenScalar(blinding),
+                sr_params,
+                &mut rng,
+            )
+            .unwrap();
+            assert_eq!(
+                tree.verify_membership(&proof, sr_params).unwrap(),
+                sr_params
+                    .even_parameters
+                    .commit(&[value], rerandomized_blinding.0, 0)
+                    .unwrap()
+            );
+            let attestation = tree.inclusion_attestation(index).unwrap();
+            verify_attestation(&tree.root(), &duplicate, &attestation, sr_params).unwrap();
+        }
+    }
+}
+
+// The bloom filter of a tree finds each of its leaves, inserted at once or later, and survives serialization.
+#[test]
+pub fn test_curve_tree_leaf_bloom() {
+    use relations::accumulator::Accumulator;
+    use relations::bloom::LeafBloom;
+    let sr_params = fixtures::pallas_vesta();
+    let medium = fixtures::medium_tree();
+    let (leaves, absent) = medium.leaves.split_at(medium.leaves.len() - 8);
+    let mut tree = CurveTree::<4, PallasParameters, VestaParameters>::from_set(
+        &leaves[..5],
+        sr_params,
+        Some(3),
+    )
+    .unwrap();
+    for chunk in leaves[5..].chunks(7) {
+        tree.insert(chunk, sr_params).unwrap();
+    }
+    for bits_per_leaf in [1, 4, 10] {
+        let bloom = tree.build_bloom(bits_per_leaf);
+        assert!(leaves.iter().all(|leaf| bloom.maybe_contains(leaf)));
+        let mut bytes = Vec::new();
+        bloom.serialize_compressed(&mut bytes).unwrap();
+        let decoded = LeafBloom::deserialize_compressed(bytes.as_slice()).unwrap();
+        assert!(leaves.iter().all(|leaf| decoded.maybe_contains(leaf)));
+    }
+    // Not a proof of absence, but 10 bits per leaf rule out most commitments that are not leaves.
+    let bloom = tree.build_bloom(10);
+    assert!(
+        absent
+            .iter()
+            .filter(|leaf| bloom.maybe_contains(*leaf))
+            .count()
+            < absent.len()
+    );
+}
+
+// Pruning keeps the root and the proofs of the other leaves, including those sharing a parent with a pruned leaf,
+// and refuses the pruned leaves with a typed error, also after leaves are inserted.
+#[test]
+pub fn test_curve_tree_prune() {
+    use relations::accumulator::Accumulator;
+    use relations::events::EventWriter;
+    use std::sync::{Arc, Mutex};
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+    let medium = fixtures::medium_tree();
+    let (leaves, blindings) = (&medium.leaves[..10], &medium.blindings[..10]);
+    let mut tree = CurveTree::<4, PallasParameters, VestaParameters>::from_set(
+        &leaves[..8],
+        sr_params,
+        Some(3),
+    )
+    .unwrap();
+    let root = tree.root();
+
+    tree.prune(&[1, 2, 7]).unwrap();
+    assert!(tree.root() == root);
+    assert_eq!(tree.leaves(), [0, 3, 4, 5, 6].map(|index| leaves[index]));
+    assert_eq!(tree.leaf(3), Some(leaves[3]));
+    assert_eq!(tree.leaf(2), None);
+    assert_eq!(tree.pruned_indices(), vec![1, 2, 7]);
+    assert!(tree.is_pruned(2) && !tree.is_pruned(3));
+    assert!(tree.leaf_indices_of(&leaves[1]).is_empty());
+    assert_eq!(tree.leaf_indices_of(&leaves[3]), vec![3]);
+    for index in [0, 3, 6] {
+        let (proof, _) = tree
+            .prove_membership(index, EvenScalar(blindings[index]), sr_params, &mut rng)
+            .unwrap();
+        assert!(proof.verify(&tree, sr_params).is_ok());
+    }
+    assert!(matches!(
+        tree.prove_membership(2, EvenScalar(blindings[2]), sr_params, &mut rng),
+        Err(Error::PrunedLeaf { index: 2 })
+    ));
+    assert!(matches!(
+        tree.select_and_rerandomize_prover_witness(7),
+        Err(Error::PrunedLeaf { index: 7 })
+    ));
+    assert!(matches!(
+        tree.inclusion_attestation(1),
+        Err(Error::PrunedLeaf { index: 1 })
+    ));
+    assert!(tree.prune(&[8]).is_err());
+
+    tree.insert(&leaves[8..], sr_params).unwrap();
+    assert_eq!(tree.pruned_indices(), vec![1, 2, 7]);
+    assert!(matches!(
+        tree.prepare_membership(7, EvenScalar(blindings[7])),
+        Err(Error::PrunedLeaf { index: 7 })
+    ));
+    let (proof, _) = tree
+        .prove_membership(9, EvenScalar(blindings[9]), sr_params, &mut rng)
+        .unwrap();
+    assert!(proof.verify(&tree, sr_params).is_ok());
+
+    // The nodes below a full node all of whose leaves are pruned are dropped, with either storage:
+    // the other leaves are proven, and leaves inserted, as before.
+    for storage in [PointStorage::Affine, PointStorage::Compressed] {
+        let mut tree = CurveTree::<4, PallasParameters, VestaParameters>::from_set_with_storage(
+            &leaves[..8],
+            sr_params,
+            Some(3),
+            storage,
+        )
+        .unwrap();
+        tree.prune(&[4, 5, 1, 6, 7]).unwrap();
+        assert!(tree.root() == root);
+        assert_eq!(tree.leaves(), [0, 2, 3].map(|index| leaves[index]));
+        assert_eq!(tree.leaf(5), None);
+        assert_eq!(tree.pruned_indices(), vec![1, 4, 5, 6, 7]);
+        assert!(tree.prune(&[5]).is_ok());
+        for index in [0, 3] {
+            let (proof, _) = tree
+                .prove_membership(index, EvenScalar(blindings[index]), sr_params, &mut rng)
+                .unwrap();
+            assert!(proof.verify(&tree, sr_params).is_ok());
+        }
+        assert!(matches!(
+            tree.inclusion_attestation(6),
+            Err(Error::PrunedLeaf { index: 6 })
+        ));
+        // The log of the tree would not replay it.
+        assert!(matches!(
+            tree.set_event_sink(Arc::new(Mutex::new(EventWriter::new(Vec::new()))).into()),
+            Err(Error::ParameterMismatch { .. })
+        ));
+
+        tree.insert(&leaves[8..], sr_params).unwrap();
+        let built =
+            CurveTree::<4, PallasParameters, VestaParameters>::from_set(leaves, sr_params, Some(3))
+                .unwrap();
+        assert!(tree.root() == built.root());
+        assert_eq!(tree.leaves(), [0, 2, 3, 8, 9].map(|index| leaves[index]));
+        let (proof, _) = tree
+            .prove_membership(9, EvenScalar(blindings[9]), sr_params, &mut rng)
+            .unwrap();
+        assert!(proof.verify(&built, sr_params).is_ok());
+        tree.compact(sr_params).unwrap();
+        assert_eq!(tree.leaves(), [0, 2, 3, 8, 9].map(|index| leaves[index]));
+        assert_eq!(tree.storage(), storage);
+    }
+
+    // A repeated leaf is pruned at each of its indices separately.
+    let (a, b) = (leaves[0], leaves[1]);
+    let mut tree = CurveTree::<4, PallasParameters, VestaParameters>::from_set(
+        &[a, b, a, a],
+        sr_params,
+        Some(2),
+    )
+    .unwrap();
+    tree.prune(&[0]).unwrap();
+    assert_eq!(tree.leaf_indices_of(&a), vec![2, 3]);
+    tree.prune(&[3, 3]).unwrap();
+    assert_eq!(tree.leaf_indices_of(&a), vec![2]);
+    assert_eq!(tree.leaf_indices_of(&b), vec![1]);
+}
+
+// Inserting and pruning keep the references to leaves, compacting and rebuilding move the leaves and make
+// the references taken before stale, until refreshed by the value of their leaf.
+#[test]
+pub fn test_curve_tree_leaf_refs() {
+    use relations::accumulator::Accumulator;
+    use relations::events::EventWriter;
+    use std::sync::{Arc, Mutex};
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+    let medium = fixtures::medium_tree();
+    let (leaves, blindings) = (&medium.leaves[..10], &medium.blindings[..10]);
+    let mut tree = CurveTree::<4, PallasParameters, VestaParameters>::from_set(
+        &leaves[..8],
+        sr_params,
+        Some(3),
+    )
+    .unwrap();
+    assert_eq!(tree.root_epoch(), 0);
+    let old_ref = tree.leaf_ref(3).unwrap();
+    assert_eq!(
+        old_ref,
+        LeafRef {
+            index: 3,
+            root_epoch: 0
+        }
+    );
+    assert!(tree.leaf_ref(8).is_err());
+    let bytes = {
+        let mut bytes = Vec::new();
+        old_ref.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    };
+    assert_eq!(
+        LeafRef::deserialize_compressed(&bytes[..]).unwrap(),
+        old_ref
+    );
+
+    tree.insert(&leaves[8..], sr_params).unwrap();
+    tree.prune(&[1, 2, 7]).unwrap();
+    assert_eq!(tree.root_epoch(), 0);
+    assert_eq!(tree.resolve_ref(&old_ref).unwrap(), 3);
+    assert!(tree.prover_witness_for_ref(&old_ref).is_ok());
+
+    tree.compact(sr_params).unwrap();
+    assert_eq!(tree.root_epoch(), 1);
+    assert_eq!(tree.height(), 3);
+    assert_eq!(
+        tree.leaves(),
+        [0, 3, 4, 5, 6, 8, 9].map(|index| leaves[index])
+    );
+    assert!(tree.pruned_indices().is_empty());
+    assert!(matches!(
+        tree.resolve_ref(&old_ref),
+        Err(Error::StaleLeafRef {
+            index: 3,
+            root_epoch: 0,
+            current_epoch: 1
+        })
+    ));
+    assert!(matches!(
+        tree.prover_witness_for_ref(&old_ref),
+        Err(Error::StaleLeafRef { .. })
+    ));
+
+    let fresh = tree.refresh_ref(&old_ref, &leaves[3]).unwrap();
+    assert_eq!(
+        fresh,
+        LeafRef {
+            index: 1,
+            root_epoch: 1
+        }
+    );
+    assert_eq!(tree.refresh_ref(&fresh, &leaves[3]).unwrap(), fresh);
+    assert!(matches!(
+        tree.refresh_ref(&tree.leaf_ref(0).unwrap(), &leaves[2]),
+        Err(Error::InconsistentWitness { .. })
+    ));
+    let (proof, _) = tree
+        .prove_membership(
+            tree.resolve_ref(&fresh).unwrap(),
+            EvenScalar(blindings[3]),
+            sr_params,
+            &mut rng,
+        )
+        .unwrap();
+    assert!(proof.verify(&tree, sr_params).is_ok());
+
+    // Rebuilding from a set in another order moves to the next epoch again.
+    tree.rebuild(&[leaves[9], leaves[3], leaves[0]], sr_params)
+        .unwrap();
+    assert_eq!(tree.root_epoch(), 2);
+    assert_eq!(tree.height(), 3);
+    assert!(tree.resolve_ref(&fresh).is_err());
+    assert_eq!(
+        tree.refresh_ref(&fresh, &leaves[3]).unwrap(),
+        LeafRef {
+            index: 1,
+            root_epoch: 2
+        }
+    );
+
+    // The log of a tree recording its events would no longer replay it.
+    tree.set_event_sink(Arc::new(Mutex::new(EventWriter::new(Vec::new()))).into())
+        .unwrap();
+    assert!(matches!(
+        tree.rebuild(&leaves[..2], sr_params),
+        Err(Error::ParameterMismatch { .. })
+    ));
+    assert_eq!(tree.root_epoch(), 2);
+}
+
+#[test]
+pub fn test_curve_tree_compressed_storage() {
+    let sr_params = fixtures::pallas_vesta();
+    let fixtures::TreeFixture {
+        leaves, blindings, ..
+    } = fixtures::medium_tree();
+
+    for (count, height) in [
+        (1, None),
+        (1, Some(3)),
+        (16, None),
+        (21, None),
+        (21, Some(4)),
+    ] {
+        let build = |storage| {
+            CurveTree::<4, PallasParameters, VestaParameters>::from_set_with_storage(
+                &leaves[..count],
+                sr_params,
+                height,
+                storage,
+            )
+            .unwrap()
+        };
+        let affine_tree = build(PointStorage::Affine);
+        let compressed_tree = build(PointStorage::Compressed);
+        assert_eq!(compressed_tree.height(), affine_tree.height());
+        if affine_tree.height() % 2 == 0 {
+            assert_eq!(
+                compressed_tree.root_node().unwrap(),
+                affine_tree.root_node().unwrap()
+            );
+        }
+        if affine_tree.height() == 0 {
+            continue;
+        }
+
+        // The same randomness gives the same path, and the proofs verify against either tree,
+        // also when the decompressed children of the path are read from the caches.
+        for index in [count - 1, count - 1] {
+            let prove = |tree: &CurveTree<4, PallasParameters, VestaParameters>| {
+                let (proof, _) = tree
+                    .prove_membership(
+                        index,
+                        EvenScalar(blindings[index]),
+                        sr_params,
+                        &mut fixtures::insecure_test_rng(index as u64),
+                    )
+                    .unwrap();
+                let mut bytes = Vec::new();
+                proof.path.serialize_compressed(&mut bytes).unwrap();
+                (proof, bytes)
+            };
+            let (affine_proof, affine_bytes) = prove(&affine_tree);
+            let (compressed_proof, compressed_bytes) = prove(&compressed_tree);
+            assert_eq!(compressed_bytes, affine_bytes);
+            assert_eq!(
+                compressed_proof.verify(&affine_tree, sr_params).unwrap(),
+                compressed_proof.rerandomized_leaf()
+            );
+            assert_eq!(
+                affine_proof.verify(&compressed_tree, sr_params).unwrap(),
+                affine_proof.rerandomized_leaf()
+            );
+        }
+    }
+}
+
+// Inserting commits only the nodes on the paths of the new leaves, and gives the tree `from_set_with_storage`
+// builds from all the leaves: the same height, root, leaves and witnesses, with either storage,
+// from a single leaf or a taller tree, the tree growing and its root changing curve on the way.
+#[test]
+pub fn test_curve_tree_insert_matches_from_set() {
+    let sr_params = fixtures::pallas_vesta();
+    let fixtures::TreeFixture {
+        leaves, blindings, ..
+    } = fixtures::medium_tree();
+
+    for storage in [PointStorage::Affine, PointStorage::Compressed] {
+        for (first, height, chunk) in [(1, None, 2), (5, None, 7), (3, Some(4), 5)] {
+            let mut tree =
+                CurveTree::<4, PallasParameters, VestaParameters>::from_set_with_storage(
+                    &leaves[..first],
+                    sr_params,
+                    height,
+                    storage,
+                )
+                .unwrap();
+            let mut count = first;
+            for chunk in leaves[first..].chunks(chunk) {
+                tree.insert_with_metrics(chunk, sr_params, &relations::metrics::NoMetrics)
+                    .unwrap();
+                count += chunk.len();
+                let built =
+                    CurveTree::<4, PallasParameters, VestaParameters>::from_set_with_storage(
+                        &leaves[..count],
+                        sr_params,
+                        height,
+                        storage,
+                    )
+                    .unwrap();
+                assert_eq!(tree.height(), built.height());
+                assert!(tree.root() == built.root());
+                assert_eq!(tree.leaves(), built.leaves());
+                assert_eq!(tree.leaf_count(), count);
+                // A single leaf has no node to compress, the tree grown from it has affine nodes.
+                if first > 1 {
+                    assert_eq!(tree.storage(), storage);
+                }
+                for index in 0..count {
+                    assert!(
+                        tree.inclusion_attestation(index).unwrap()
+                            == built.inclusion_attestation(index).unwrap()
+                    );
+                }
+            }
+
+            // The same randomness gives the same path in either tree.
+            let index = count - 1;
+            let path = |tree: &CurveTree<4, PallasParameters, VestaParameters>| {
+                let (proof, _) = tree
+                    .prove_membership(
+                        index,
+                        EvenScalar(blindings[index]),
+                        sr_params,
+                        &mut fixtures::insecure_test_rng(index as u64),
+                    )
+                    .unwrap();
+                bytes(&proof.path)
+            };
+            let built = CurveTree::<4, PallasParameters, VestaParameters>::from_set_with_storage(
+                leaves, sr_params, height, storage,
+            )
+            .unwrap();
+            assert_eq!(path(&tree), path(&built));
+        }
+    }
+}
+
+// A prover who builds a tree of their own around a forged leaf gets a well-formed path,
+// which must not verify against the verifier's tree.
+#[test]
+pub fn test_curve_tree_self_rooted_path_fails() {
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+    let fixtures::TreeFixture {
+        tree: curve_tree,
+        leaves,
+        ..
+    } = fixtures::small_tree();
+
+    let (forged_leaf, forged_blinding) = sr_params
+        .even_parameters
+        .permissible_commitment(
+            &[PallasScalar::from(1_000_000u64)],
+            PallasScalar::rand(&mut rng),
+            0,
+        )
+        .unwrap();
+    let mut forged_leaves = leaves.clone();
+    forged_leaves[1] = forged_leaf;
+    let forged_tree = CurveTree::<32, PallasParameters, VestaParameters>::from_set(
+        &forged_leaves,
+        sr_params,
+        Some(2),
+    )
+    .unwrap();
+    let (forged_proof, _) = forged_tree
+        .prove_membership(1, EvenScalar(forged_blinding), sr_params, &mut rng)
+        .unwrap();
+    assert_ne!(
+        forged_tree.root_node().unwrap(),
+        curve_tree.root_node().unwrap()
+    );
+    assert!(forged_proof.verify(&forged_tree, sr_params).is_ok());
+
+    assert!(curve_tree.is_valid_path(&forged_proof.path));
+    assert!(matches!(
+        forged_proof.verify(curve_tree, sr_params),
+        Err(Error::R1CS(R1CSError::VerificationError))
+    ));
+
+    // The path-level gadgets refuse a path starting at another root.
+    let forged_commitments = forged_tree
+        .select_and_rerandomize_verification_commitments(forged_proof.path.clone())
+        .unwrap();
+    let mut pallas_verifier = Verifier::new(Transcript::new(b"select_and_rerandomize"));
+    assert!(matches!(
+        forged_commitments.even_verifier_gadget(&mut pallas_verifier, sr_params, curve_tree),
+        Err(Error::InvalidPath)
+    ));
+}
+
+#[test]
+pub fn test_curve_tree_errors() {
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+
+    assert!(matches!(
+        CurveTree::<32, PallasParameters, VestaParameters>::from_set(&[], sr_params, Some(2)),
+        Err(Error::ParameterMismatch { .. })
+    ));
+
+    // more values than generators
+    let values = vec![PallasScalar::from(1u64); fixtures::GENERATORS_LENGTH + 1];
+    assert!(matches!(
+        sr_params
+            .even_parameters
+            .permissible_commitment(&values, PallasScalar::rand(&mut rng), 0),
+        Err(Error::ParameterMismatch { .. })
+    ));
+
+    let (leaf, blinding) = sr_params
+        .even_parameters
+        .permissible_commitment(&values[..1], PallasScalar::rand(&mut rng), 0)
+        .unwrap();
+    let curve_tree =
+        CurveTree::<32, PallasParameters, VestaParameters>::from_set(&[leaf], sr_params, Some(2))
+            .unwrap();
+    assert!(matches!(
+        curve_tree.prove_membership(1, EvenScalar(blinding), sr_params, &mut rng),
+        Err(Error::InconsistentWitness { .. })
+    ));
+}
+
+// Two sessions proving the same path, which only bind their context to the even transcript.
+// The binding of the transcripts makes the odd proof of one session invalid in the other.
+#[test]
+pub fn test_curve_tree_swapped_odd_proof_fails() {
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+    let some_point = PallasP::rand(&mut rng).into_affine();
+    let (permissible_point, _) = sr_params
+        .even_parameters
+        .uh
+        .permissible_commitment(&some_point, &sr_params.even_parameters.pc_gens.B_blinding);
+    let curve_tree = CurveTree::<32, PallasParameters, VestaParameters>::from_set(
+        &[permissible_point],
+        sr_params,
+        Some(2),
+    )
+    .unwrap();
+
+    let prove = |session: &[u8]| {
+        let mut pallas_transcript = Transcript::new(b"select_and_rerandomize");
+        pallas_transcript.append_message(b"session", session);
+        let mut pallas_prover: Prover<_, Affine<PallasParameters>> =
+            Prover::new(&sr_params.even_parameters.pc_gens, pallas_transcript);
+        let mut vesta_prover: Prover<_, Affine<VestaParameters>> = Prover::new(
+            &sr_params.odd_parameters.pc_gens,
+            Transcript::new(b"select_and_rerandomize"),
+        );
+        // the same rerandomizations in both sessions
+        let (path, _) = curve_tree
+            .select_and_rerandomize_prover_gadget(
+                0,
+                &mut pallas_prover,
+                &mut vesta_prover,
+                sr_params,
+                &mut fixtures::insecure_test_rng(0),
+            )
+            .unwrap();
+        let pallas_proof = pallas_prover
+            .prove(&sr_params.even_parameters.bp_gens)
+            .unwrap();
+        let vesta_proof = vesta_prover
+            .prove(&sr_params.odd_parameters.bp_gens)
+            .unwrap();
+        (path, pallas_proof, vesta_proof)
+    };
+    let verify = |session: &[u8], path, pallas_proof, vesta_proof| {
+        let mut pallas_transcript = Transcript::new(b"select_and_rerandomize");
+        pallas_transcript.append_message(b"session", session);
+        let mut pallas_verifier = Verifier::new(pallas_transcript);
+        let mut vesta_verifier = Verifier::new(Transcript::new(b"select_and_rerandomize"));
+        let _rerandomized_leaf = curve_tree
+            .select_and_rerandomize_verifier_gadget(
+                &mut pallas_verifier,
+                &mut vesta_verifier,
+                path,
+                sr_params,
+            )
+            .unwrap();
+        pallas_verifier.verify(
+            &pallas_proof,
+            &sr_params.even_parameters.pc_gens,
+            &sr_params.even_parameters.bp_gens,
+        )?;
+        vesta_verifier.verify(
+            &vesta_proof,
+            &sr_params.odd_parameters.pc_gens,
+            &sr_params.odd_parameters.bp_gens,
+        )
+    };
+
+    let (path_a, pallas_proof_a, vesta_proof_a) = prove(b"a");
+    let (path_b, _, vesta_proof_b) = prove(b"b");
+    let mut bytes_a = Vec::new();
+    path_a.serialize_compressed(&mut bytes_a).unwrap();
+    let mut bytes_b = Vec::new();
+    path_b.serialize_compressed(&mut bytes_b).unwrap();
+    assert_eq!(bytes_a, bytes_b);
+
+    assert_eq!(
+        verify(b"a", path_a.clone(), pallas_proof_a.clone(), vesta_proof_a),
+        Ok(())
+    );
+    assert_eq!(
+        verify(b"a", path_a, pallas_proof_a, vesta_proof_b),
+        Err(R1CSError::VerificationError)
+    );
+}
+
+// Coarse check that the prover's work does not depend on the selected index.
+// Timing is noisy on shared machines, so this is not run by default.
+#[test]
+#[ignore]
+pub fn test_curve_tree_prover_timing_is_index_independent() {
+    let mut rng = rand::thread_rng();
+    let generators_length = 1 << 12;
+
+    let sr_params = SelRerandParameters::<PallasParameters, VestaParameters>::new(
+        generators_length,
+        generators_length,
+    );
+
+    let set: Vec<_> = (0..64)
+        .map(|_| {
+            let some_point = PallasP::rand(&mut rng).into_affine();
+            sr_params
+                .even_parameters
+                .uh
+                .permissible_commitment(&some_point, &sr_params.even_parameters.pc_gens.B_blinding)
+                .0
+        })
+        .collect();
+    let curve_tree =
+        CurveTree::<8, PallasParameters, VestaParameters>::from_set(&set, &sr_params, Some(4))
+            .unwrap();
+
+    let mut median_time = |index: usize| {
+        let mut times: Vec<_> = (0..25)
+            .map(|_| {
+                let mut pallas_prover: Prover<_, Affine<PallasParameters>> = Prover::new(
+                    &sr_params.even_parameters.pc_gens,
+                    Transcript::new(b"select_and_rerandomize"),
+                );
+                let mut vesta_prover: Prover<_, Affine<VestaParameters>> = Prover::new(
+                    &sr_params.odd_parameters.pc_gens,
+                    Transcript::new(b"select_and_rerandomize"),
+                );
+                let start = std::time::Instant::now();
+                curve_tree
+                    .select_and_rerandomize_prover_gadget(
+                        index,
+                        &mut pallas_prover,
+                        &mut vesta_prover,
+                        &sr_params,
+                        &mut rng,
+                    )
+                    .unwrap();
+                start.elapsed()
+            })
+            .collect();
+        times.sort();
+        times[times.len() / 2].as_secs_f64()
+    };
+
+    let first = median_time(0);
+    let last = median_time(set.len() - 1);
+    let ratio = first.max(last) / first.min(last);
+    assert!(ratio < 1.2, "first: {}s, last: {}s", first, last);
+}
+
+// Nodes building a tree from the same leaves agree on its root, and on the proofs against it.
+#[test]
+pub fn test_curve_tree_reproducible_construction() {
+    use relations::accumulator::Accumulator;
+    let fixtures::TreeFixture {
+        tree,
+        leaves,
+        blindings,
+        ..
+    } = fixtures::medium_tree();
+    let sr_params = fixtures::pallas_vesta();
+    // Parameters generated independently, from another seed.
+    let other_params = SelRerandParameters::<PallasParameters, VestaParameters>::new(
+        fixtures::GENERATORS_LENGTH,
+        fixtures::GENERATORS_LENGTH,
+    );
+
+    let rebuilt =
+        CurveTree::<4, PallasParameters, VestaParameters>::from_set(leaves, &other_params, None)
+            .unwrap();
+    let compressed = CurveTree::<4, PallasParameters, VestaParameters>::from_set_with_storage(
+        leaves,
+        &other_params,
+        None,
+        PointStorage::Compressed,
+    )
+    .unwrap();
+    // Leaves inserted over time, growing the tree.
+    let mut grown = CurveTree::<4, PallasParameters, VestaParameters>::from_set(
+        &leaves[..2],
+        &other_params,
+        None,
+    )
+    .unwrap();
+    for chunk in leaves[2..].chunks(5) {
+        grown.insert(chunk, &other_params).unwrap();
+    }
+    for other in [&rebuilt, &compressed, &grown] {
+        assert_eq!(other.height(), tree.height());
+        assert!(other.root() == tree.root());
+    }
+
+    for index in [0, 17, 20] {
+        let (proof, _) = tree
+            .prove_membership(
+                index,
+                EvenScalar(blindings[index]),
+                sr_params,
+                &mut thread_rng(),
+            )
+            .unwrap();
+        for other in [&rebuilt, &compressed, &grown] {
+            assert_eq!(
+                proof.verify(other, &other_params).unwrap(),
+                proof.rerandomized_leaf()
+            );
+        }
+        let (proof, _) = grown
+            .prove_membership(
+                index,
+                EvenScalar(blindings[index]),
+                &other_params,
+                &mut thread_rng(),
+            )
+            .unwrap();
+        assert_eq!(
+            proof.verify(tree, sr_params).unwrap(),
+            proof.rerandomized_leaf()
+        );
+    }
+}
+
+// Verifiers whose constraints are built once, on the path of a first proof, verify the proofs of other leaves
+// with the statements of their own paths, see `Verifier::verify_ref`, and reject a proof paired with another path.
+#[test]
+pub fn test_curve_tree_reused_membership_verifier() {
+    let fixtures::TreeFixture {
+        tree, blindings, ..
+    } = fixtures::medium_tree();
+    let parameters = fixtures::pallas_vesta();
+    let proofs: Vec<_> = [3, 18]
+        .into_iter()
+        .map(|index| {
+            let (proof, _) = tree
+                .prove_membership(
+                    index,
+                    EvenScalar(blindings[index]),
+                    parameters,
+                    &mut thread_rng(),
+                )
+                .unwrap();
+            proof
+        })
+        .collect();
+    let verifiers = || {
+        let mut even_verifier = Verifier::new(Transcript::new(relations::protocol::MEMBERSHIP));
+        let mut odd_verifier = Verifier::new(Transcript::new(relations::protocol::MEMBERSHIP));
+        bind_leaf_count(
+            even_verifier.transcript(),
+            odd_verifier.transcript(),
+            tree.leaf_count() as u64,
+        );
+        (even_verifier, odd_verifier)
+    };
+
+    let (mut even_verifier, mut odd_verifier) = verifiers();
+    let rerandomized_leaf = tree
+        .select_and_rerandomize_verifier_gadget(
+            &mut even_verifier,
+            &mut odd_verifier,
+            proofs[0].path.clone(),
+            parameters,
+        )
+        .unwrap();
+    assert_eq!(rerandomized_leaf, proofs[0].rerandomized_leaf());
+    let verify =
+        |proof: &MembershipProof<4, PallasParameters, VestaParameters>,
+         path: &SelectAndRerandomizePath<4, PallasParameters, VestaParameters>| {
+            let (mut even_statement, mut odd_statement) = verifiers();
+            let rerandomized_leaf = tree.select_and_rerandomize_verifier_statement(
+                &mut even_statement,
+                &mut odd_statement,
+                path.clone(),
+                parameters,
+            )?;
+            even_verifier.verify_ref(
+                even_statement,
+                &proof.even_proof,
+                &parameters.even_parameters.pc_gens,
+                &parameters.even_parameters.bp_gens,
+            )?;
+            odd_verifier.verify_ref(
+                odd_statement,
+                &proof.odd_proof,
+                &parameters.odd_parameters.pc_gens,
+                &parameters.odd_parameters.bp_gens,
+            )?;
+            Ok::<_, Error>(rerandomized_leaf)
+        };
+
+    for proof in &proofs {
+        assert_eq!(
+            verify(proof, &proof.path).unwrap(),
+            proof.rerandomized_leaf()
+        );
+    }
+    // The rerandomized points of the other path replace those of the proof in the constraints, which do not hold.
+    assert!(matches!(
+        verify(&proofs[1], &proofs[0].path),
+        Err(Error::R1CS(R1CSError::VerificationError))
+    ));
+    assert!(matches!(
+        verify(&proofs[0], &proofs[1].path),
+        Err(Error::R1CS(R1CSError::VerificationError))
+    ));
+}
+
+fn bytes<T: CanonicalSerialize>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    value.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+// Proving both curves at once, with the `parallel` feature, gives the proofs of proving them one after the other.
+#[test]
+pub fn test_prove_pair_matches_serial_proving() {
+    let mut rng = fixtures::insecure_test_rng(0);
+    let sr_params = fixtures::pallas_vesta();
+    let (leaf, _) = sr_params
+        .even_parameters
+        .permissible_commitment(&[PallasScalar::from(7u64)], PallasScalar::rand(&mut rng), 0)
+        .unwrap();
+    let curve_tree =
+        CurveTree::<32, PallasParameters, VestaParameters>::from_set(&[leaf], sr_params, Some(2))
+            .unwrap();
+
+    // The provers of a membership proof of the leaf, as `CurveTree::prove_membership` lays them down,
+    // with the transcripts bound to the parameters and to each other by the gadget.
+    let provers = || {
+        let mut even_prover = Prover::new(
+            &sr_params.even_parameters.pc_gens,
+            Transcript::new(relations::protocol::MEMBERSHIP),
+        );
+        let mut odd_prover = Prover::new(
+            &sr_params.odd_parameters.pc_gens,
+            Transcript::new(relations::protocol::MEMBERSHIP),
+        );
+        bind_leaf_count(even_prover.transcript(), odd_prover.transcript(), 1);
+        let (path, _) = curve_tree
+            .select_and_rerandomize_prover_gadget(
+                0,
+                &mut even_prover,
+                &mut odd_prover,
+                sr_params,
+                &mut fixtures::insecure_test_rng(0),
+            )
+            .unwrap();
+        (even_prover, odd_prover, path)
+    };
+    let seeded = |seed| fixtures::insecure_test_rng(seed);
+
+    let (even_prover, odd_prover, path) = provers();
+    let (even_proof, odd_proof) = prove_pair_with_rng(
+        even_prover,
+        odd_prover,
+        sr_params,
+        &mut seeded(1),
+        &mut seeded(2),
+    )
+    .unwrap();
+    let (even_prover, odd_prover, _) = provers();
+    let serial_even_proof = even_prover
+        .prove_with_rng(&sr_params.even_parameters.bp_gens, &mut seeded(1))
+        .unwrap();
+    let serial_odd_proof = odd_prover
+        .prove_with_rng(&sr_params.odd_parameters.bp_gens, &mut seeded(2))
+        .unwrap();
+    assert_eq!(bytes(&even_proof), bytes(&serial_even_proof));
+    assert_eq!(bytes(&odd_proof), bytes(&serial_odd_proof));
+
+    let proof = MembershipProof {
+        path,
+        even_proof,
+        odd_proof,
+        parameters_fingerprint: sr_params.fingerprint(),
+        leaf_count: 1,
+    };
+    assert!(proof.verify(&curve_tree, sr_params).is_ok());
+}
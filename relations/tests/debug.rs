@@ -0,0 +1,101 @@
+//! Finding where a prover and a verifier building the mint relation diverge, from their operation logs.
+#![cfg(all(feature = "debug", feature = "payments"))]
+
+extern crate bulletproofs;
+extern crate relations;
+
+use ark_crypto_primitives::signature::schnorr::Schnorr;
+use ark_crypto_primitives::signature::SignatureScheme;
+use ark_ec::short_weierstrass::Affine;
+use blake2::Blake2s256 as Blake2s;
+use bulletproofs::r1cs::*;
+use merlin::Transcript;
+
+use relations::coin::*;
+use relations::layout::CommitmentLayout;
+
+use ark_pallas::PallasConfig;
+
+type PallasA = Affine<PallasConfig>;
+type PallasP = ark_pallas::Projective;
+
+#[test]
+fn diagnose_desynchronized_mint() {
+    let mut rng = fixtures::insecure_test_rng(0);
+    let sr_params = fixtures::pallas_vesta();
+    let (pc_gens, bp_gens) = (
+        &sr_params.even_parameters.pc_gens,
+        &sr_params.even_parameters.bp_gens,
+    );
+    let schnorr_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+    let (pk, _) = Schnorr::keygen(&schnorr_parameters, &mut rng).unwrap();
+    let layout = CommitmentLayout::coin();
+
+    // The prover mints with a bound of 1000, a value of 10 bits.
+    let mut prover: Prover<_, PallasA> = Prover::new(pc_gens, Transcript::new(b"mint"));
+    let (_, commitment, _) = Coin::<PallasConfig, PallasP>::mint_bounded(
+        7,
+        Some(1000),
+        &pk,
+        &schnorr_parameters,
+        &sr_params.even_parameters,
+        &layout,
+        &sr_params.value_policy,
+        &mut rng,
+        &mut prover,
+    )
+    .unwrap();
+    let prover_log = prover.operation_log().clone();
+    let proof = prover.prove(bp_gens).unwrap();
+
+    let verifier = |bound| {
+        let mut verifier = Verifier::new(Transcript::new(b"mint"));
+        verify_mint_bounded(
+            &mut verifier,
+            commitment,
+            &layout,
+            bound,
+            &sr_params.value_policy,
+        )
+        .unwrap();
+        verifier
+    };
+    assert!(verifier(Some(1000))
+        .verify_diagnosed(&proof, pc_gens, bp_gens, &prover_log)
+        .is_ok());
+
+    // A verifier forgetting the bound proves 64 bits: after the commitment and the 10 bits of the prover,
+    // each a multiplier and two constraints, the prover recomposes the value while the verifier takes the next bit.
+    let unbounded = verifier(None);
+    let mismatch = diagnose_mismatch(&prover_log, unbounded.operation_log()).unwrap();
+    assert_eq!(mismatch.index, 1 + 10 * 3);
+    assert!(matches!(mismatch.prover, Some(Operation::Constrain(_))));
+    assert_eq!(mismatch.verifier, Some(Operation::AllocateMultiplier));
+    match unbounded.verify_diagnosed(&proof, pc_gens, bp_gens, &prover_log) {
+        Err(R1CSError::OperationMismatch { description }) => {
+            assert!(description.starts_with("operation 31: the prover did constrain"))
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+
+    // A verifier committing one more value diverges at the first operation.
+    let mut extra_commit = Verifier::new(Transcript::new(b"mint"));
+    extra_commit.commit(commitment);
+    verify_mint_bounded(
+        &mut extra_commit,
+        commitment,
+        &layout,
+        Some(1000),
+        &sr_params.value_policy,
+    )
+    .unwrap();
+    let mismatch = diagnose_mismatch(&prover_log, extra_commit.operation_log()).unwrap();
+    assert_eq!(
+        mismatch,
+        Mismatch {
+            index: 0,
+            prover: Some(Operation::CommitVec(layout.width())),
+            verifier: Some(Operation::Commit),
+        }
+    );
+}
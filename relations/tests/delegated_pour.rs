@@ -0,0 +1,189 @@
+//! Simulates a device holding the spending keys that delegates proving a pour to an untrusted server.
+//! The two roles only share public parameters and exchange serialized messages:
+//! the device sends a `PourRequest`, the server returns an unsigned `Pour`, and the device signs it.
+#![cfg(feature = "payments")]
+
+extern crate bulletproofs;
+extern crate relations;
+
+use ark_crypto_primitives::signature::schnorr::{Parameters, Schnorr};
+use ark_crypto_primitives::signature::SignatureScheme;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use blake2::Blake2s256 as Blake2s;
+use bulletproofs::r1cs::*;
+use merlin::Transcript;
+use rand::{CryptoRng, Rng};
+
+use relations::coin::*;
+use relations::curve_tree::*;
+use relations::layout::CommitmentLayout;
+use relations::Error;
+
+use ark_pallas::{PallasConfig, Projective as PallasP};
+use ark_vesta::VestaConfig;
+
+const L: usize = 32;
+const HEIGHT: usize = 2;
+const LABEL: &[u8] = b"select_and_rerandomize";
+
+type Request = PourRequest<PallasConfig, PallasP>;
+type Input = SpendingInfo<PallasConfig, PallasP>;
+
+/// What both roles know.
+struct Public {
+    sr_parameters: &'static SelRerandParameters<PallasConfig, VestaConfig>,
+    sig_parameters: Parameters<PallasP, Blake2s>,
+    layout: CommitmentLayout,
+    tree: CurveTree<L, PallasConfig, VestaConfig>,
+}
+
+fn device_request<R: Rng + CryptoRng>(
+    public: &Public,
+    inputs: &[Input; 2],
+    rng: &mut R,
+) -> (Request, Vec<u8>) {
+    let (receiver_pk, _) = Schnorr::keygen(&public.sig_parameters, rng).unwrap();
+    let (request, _minted_coins) = PourRequest::new(
+        &inputs[0],
+        &inputs[1],
+        30,
+        receiver_pk,
+        12,
+        receiver_pk,
+        &public.sig_parameters,
+        &public.sr_parameters.even_parameters,
+        &public.layout,
+        rng,
+    )
+    .unwrap();
+    let mut bytes = Vec::new();
+    request.serialize_compressed(&mut bytes).unwrap();
+    (request, bytes)
+}
+
+fn server_prove(public: &Public, request_bytes: &[u8]) -> Vec<u8> {
+    let request = Request::deserialize_compressed(request_bytes).unwrap();
+    let sr_parameters = public.sr_parameters;
+    let pour = request
+        .prove(
+            Prover::new(
+                &sr_parameters.even_parameters.pc_gens,
+                Transcript::new(LABEL),
+            ),
+            Prover::new(
+                &sr_parameters.odd_parameters.pc_gens,
+                Transcript::new(LABEL),
+            ),
+            sr_parameters,
+            &public.layout,
+            &public.tree,
+        )
+        .unwrap();
+    let mut bytes = Vec::new();
+    pour.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+fn device_sign<R: Rng + CryptoRng>(
+    public: &Public,
+    request: &Request,
+    inputs: &[Input; 2],
+    pour_bytes: &[u8],
+    rng: &mut R,
+) -> Result<Vec<u8>, Error> {
+    let pour = Pour::<L, PallasConfig, VestaConfig, PallasP>::deserialize_compressed(pour_bytes)?;
+    let tx = request.sign(&pour, &inputs[0], &inputs[1], &public.sig_parameters, rng)?;
+    let mut bytes = Vec::new();
+    tx.serialize_compressed(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn verify(public: &Public, tx_bytes: &[u8]) -> Result<(), Error> {
+    let tx = SignedTx::<PallasConfig, VestaConfig, PallasP>::deserialize_compressed(tx_bytes)?;
+    let (even_verifier, odd_verifier) = tx.verification_gadget(
+        LABEL,
+        public.sr_parameters,
+        &public.layout,
+        &public.tree,
+        &public.sig_parameters,
+    )?;
+    batch_verify(
+        vec![even_verifier],
+        &public.sr_parameters.even_parameters.pc_gens,
+        &public.sr_parameters.even_parameters.bp_gens,
+    )?;
+    batch_verify(
+        vec![odd_verifier],
+        &public.sr_parameters.odd_parameters.pc_gens,
+        &public.sr_parameters.odd_parameters.bp_gens,
+    )?;
+    Ok(())
+}
+
+#[test]
+pub fn test_delegated_pour() {
+    let mut rng = rand::thread_rng();
+    let sr_parameters = fixtures::pallas_vesta();
+    let sig_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+    let layout = CommitmentLayout::coin();
+    let (pk, sk) = Schnorr::keygen(&sig_parameters, &mut rng).unwrap();
+
+    let mut coins = Vec::new();
+    let mut leaves = Vec::new();
+    for value in [19, 23] {
+        let (coin, leaf) = Coin::<PallasConfig, PallasP>::new(
+            value,
+            &pk,
+            &sig_parameters,
+            &sr_parameters.even_parameters,
+            &layout,
+            &mut rng,
+        )
+        .unwrap();
+        coins.push(coin);
+        leaves.push(leaf);
+    }
+    let tree = CurveTree::from_set(&leaves, sr_parameters, Some(HEIGHT)).unwrap();
+    let public = Public {
+        sr_parameters,
+        sig_parameters,
+        layout,
+        tree,
+    };
+    let input = |index: usize| SpendingInfo {
+        leaf: public.tree.leaf_ref(index).unwrap(),
+        coin_aux: coins[index].clone(),
+        randomized_pk: Coin::<PallasConfig, PallasP>::rerandomized_pk(
+            &pk,
+            &coins[index].pk_randomness.total(),
+            &public.sig_parameters,
+        ),
+        sk: sk.clone(),
+    };
+    let inputs = [input(0), input(1)];
+
+    let (request, request_bytes) = device_request(&public, &inputs, &mut rng);
+    let pour_bytes = server_prove(&public, &request_bytes);
+    let tx_bytes = device_sign(&public, &request, &inputs, &pour_bytes, &mut rng).unwrap();
+    verify(&public, &tx_bytes).unwrap();
+
+    // The device does not sign a pour minting other coins than the requested ones.
+    let (other_request, other_request_bytes) = device_request(&public, &inputs, &mut rng);
+    let other_pour_bytes = server_prove(&public, &other_request_bytes);
+    assert!(matches!(
+        device_sign(&public, &request, &inputs, &other_pour_bytes, &mut rng),
+        Err(Error::ParameterMismatch { .. })
+    ));
+    // Nor a pour for the request of other spending infos.
+    let swapped = [input(1), input(0)];
+    assert!(matches!(
+        device_sign(
+            &public,
+            &other_request,
+            &swapped,
+            &other_pour_bytes,
+            &mut rng
+        ),
+        Err(Error::InconsistentWitness { .. })
+    ));
+}
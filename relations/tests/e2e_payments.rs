@@ -0,0 +1,1020 @@
+//! Simulates a chain of payments: users mint and pay each other over many blocks,
+//! the chain verifies each block in a batch against its recent roots, records the spent tags,
+//! rejects double spends, and occasionally rolls back its last blocks.
+#![cfg(feature = "payments")]
+
+extern crate bulletproofs;
+extern crate relations;
+
+use std::collections::{HashSet, VecDeque};
+use std::rc::Rc;
+
+use ark_crypto_primitives::signature::schnorr::{
+    Parameters, PublicKey, Schnorr, SecretKey, Signature,
+};
+use ark_crypto_primitives::signature::SignatureScheme;
+use ark_ec::short_weierstrass::Affine;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::UniformRand;
+use blake2::Blake2s256 as Blake2s;
+use bulletproofs::r1cs::*;
+use merlin::Transcript;
+use rand::{CryptoRng, Rng};
+
+use relations::blinding::{BlindingAccumulator, INITIAL};
+use relations::coin::*;
+use relations::curve_tree::*;
+use relations::cycle::EvenScalar;
+use relations::encoding::{Root, Tag};
+use relations::layout::{CommitmentLayout, TAG, VALUE};
+use relations::metrics::{self, AtomicMetrics};
+use relations::policy::ValuePolicy;
+use relations::pool::{verify_transition, PoolDigest, PoolState};
+use relations::wallet::TagSet;
+use relations::Error;
+
+use ark_pallas::{Fr as PallasScalar, PallasConfig};
+use ark_vesta::VestaConfig;
+
+type PallasA = Affine<PallasConfig>;
+type PallasP = ark_pallas::Projective;
+type Tree = CurveTree<L, PallasConfig, VestaConfig>;
+type Tx = SignedTx<PallasConfig, VestaConfig, PallasP>;
+
+const L: usize = 32;
+// An even height, as only trees with even roots have a root to prove against.
+const HEIGHT: usize = 2;
+const USERS: usize = 20;
+const BLOCKS: usize = 50;
+// Number of recent roots the chain accepts spends against.
+const ROOT_HISTORY: usize = 4;
+const MAX_MINT: u64 = 1000;
+const LABEL: &[u8] = b"e2e payments";
+
+struct Setup<'a> {
+    parameters: &'a SelRerandParameters<PallasConfig, VestaConfig>,
+    sig_parameters: Parameters<PallasP, Blake2s>,
+    layout: CommitmentLayout,
+}
+
+#[derive(Clone)]
+struct OwnedCoin {
+    coin: Coin<PallasConfig, PallasP>,
+    commitment: PallasA,
+    index: usize,
+}
+
+#[derive(Clone)]
+struct Wallet {
+    pk: PublicKey<PallasP>,
+    sk: SecretKey<PallasP>,
+    coins: Vec<OwnedCoin>,
+}
+
+impl Wallet {
+    fn balance(&self) -> u64 {
+        self.coins.iter().map(|c| c.coin.value).sum()
+    }
+
+    fn spending_info(
+        &self,
+        owned: &OwnedCoin,
+        setup: &Setup,
+        tree: &Tree,
+    ) -> SpendingInfo<PallasConfig, PallasP> {
+        SpendingInfo {
+            leaf: tree.leaf_ref(owned.index).unwrap(),
+            coin_aux: owned.coin.clone(),
+            randomized_pk: Coin::<PallasConfig, PallasP>::rerandomized_pk(
+                &self.pk,
+                &owned.coin.pk_randomness.total(),
+                &setup.sig_parameters,
+            ),
+            sk: self.sk.clone(),
+        }
+    }
+}
+
+/// A transaction of a block.
+#[derive(Clone)]
+enum Transaction {
+    /// Issues a coin of public value.
+    Mint {
+        commitment: PallasA,
+        value: u64,
+        proof: Box<R1CSProof<PallasA>>,
+    },
+    /// Spends two coins proven to be in the tree with root `root` and mints two.
+    Pour { root: PallasA, tx: Tx },
+}
+
+/// A coin created by a transaction, to be added to the receiver's wallet if the block is accepted.
+#[derive(Clone)]
+struct Output {
+    receiver: usize,
+    coin: Coin<PallasConfig, PallasP>,
+    commitment: PallasA,
+}
+
+/// A pending transaction: the transaction, its outputs and the coins it spends.
+#[derive(Clone)]
+struct Pending {
+    transaction: Transaction,
+    outputs: Vec<Output>,
+    spent: Option<(usize, [OwnedCoin; 2])>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Rejection {
+    DoubleSpend,
+    UnknownRoot,
+    InvalidPath,
+    InvalidProof,
+}
+
+#[derive(Clone)]
+struct Chain {
+    leaves: Vec<PallasA>,
+    tags: HashSet<Tag<PallasScalar>>,
+    // The trees of the last blocks with their number of leaves, oldest first.
+    history: VecDeque<(Root<PallasConfig>, usize, Rc<Tree>)>,
+    supply: u64,
+    pours: usize,
+}
+
+impl Chain {
+    fn new() -> Self {
+        Chain {
+            leaves: Vec::new(),
+            tags: HashSet::new(),
+            history: VecDeque::new(),
+            supply: 0,
+            pours: 0,
+        }
+    }
+
+    fn root(&self) -> Option<Root<PallasConfig>> {
+        self.history.back().map(|(root, _, _)| *root)
+    }
+
+    fn tree(&self, root: PallasA) -> Option<&Tree> {
+        self.history
+            .iter()
+            .find(|(r, _, _)| r.0 == root)
+            .map(|(_, _, tree)| tree.as_ref())
+    }
+
+    /// Verifies the transactions of a block in a batch and applies them, or rejects the whole block.
+    fn apply_block(&mut self, block: &[Transaction], setup: &Setup) -> Result<(), Rejection> {
+        let mut block_tags = HashSet::new();
+        let mut even_vts = Vec::new();
+        let mut odd_vts = Vec::new();
+        let mut minted = Vec::new();
+        let mut issued = 0;
+        for transaction in block {
+            match transaction {
+                Transaction::Mint {
+                    commitment,
+                    value,
+                    proof,
+                } => {
+                    let mut verifier = Verifier::new(Transcript::new(LABEL));
+                    let value_var = verify_mint(
+                        &mut verifier,
+                        *commitment,
+                        &setup.layout,
+                        &setup.parameters.value_policy,
+                    )
+                    .map_err(|_| Rejection::InvalidProof)?;
+                    verifier.constrain(value_var - PallasScalar::from(*value));
+                    even_vts.push(
+                        verifier
+                            .verification_scalars_and_points(proof)
+                            .map_err(|_| Rejection::InvalidProof)?,
+                    );
+                    minted.push(*commitment);
+                    issued += value;
+                }
+                Transaction::Pour { root, tx } => {
+                    let pour = tx.pour::<L>().map_err(|_| Rejection::InvalidProof)?;
+                    for tag in pour.tags() {
+                        if self.tags.contains(&Tag(tag)) || !block_tags.insert(Tag(tag)) {
+                            return Err(Rejection::DoubleSpend);
+                        }
+                    }
+                    let tree = self.tree(*root).ok_or(Rejection::UnknownRoot)?;
+                    if !tree.is_valid_path(&pour.randomized_path_0)
+                        || !tree.is_valid_path(&pour.randomized_path_1)
+                    {
+                        return Err(Rejection::InvalidPath);
+                    }
+                    let (even_vt, odd_vt) = tx
+                        .clone()
+                        .verification_gadget(
+                            LABEL,
+                            setup.parameters,
+                            &setup.layout,
+                            tree,
+                            &setup.sig_parameters,
+                        )
+                        .map_err(|e| match e {
+                            Error::InvalidPath => Rejection::InvalidPath,
+                            _ => Rejection::InvalidProof,
+                        })?;
+                    even_vts.push(even_vt);
+                    odd_vts.push(odd_vt);
+                    minted.push(pour.minted_coin_commitment_0);
+                    minted.push(pour.minted_coin_commitment_1);
+                }
+            }
+        }
+        batch_verify(
+            even_vts,
+            &setup.parameters.even_parameters.pc_gens,
+            &setup.parameters.even_parameters.bp_gens,
+        )
+        .map_err(|_| Rejection::InvalidProof)?;
+        batch_verify(
+            odd_vts,
+            &setup.parameters.odd_parameters.pc_gens,
+            &setup.parameters.odd_parameters.bp_gens,
+        )
+        .map_err(|_| Rejection::InvalidProof)?;
+
+        self.pours += block
+            .iter()
+            .filter(|t| matches!(t, Transaction::Pour { .. }))
+            .count();
+        self.tags.extend(block_tags);
+        self.supply += issued;
+        self.leaves.extend(minted);
+        if !self.leaves.is_empty() {
+            let tree = Tree::from_set(&self.leaves, setup.parameters, Some(HEIGHT)).unwrap();
+            assert_eq!(tree.height(), HEIGHT, "the simulation outgrew the tree");
+            self.history.push_back((
+                Root(tree.root_node().unwrap()),
+                self.leaves.len(),
+                Rc::new(tree),
+            ));
+            if self.history.len() > ROOT_HISTORY {
+                self.history.pop_front();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+struct State {
+    chain: Chain,
+    wallets: Vec<Wallet>,
+}
+
+impl State {
+    /// Applies the block to the chain, and on success moves the coins between the wallets.
+    fn apply_block(&mut self, block: &[Pending], setup: &Setup) -> Result<(), Rejection> {
+        let transactions: Vec<Transaction> = block.iter().map(|p| p.transaction.clone()).collect();
+        self.chain.apply_block(&transactions, setup)?;
+        for pending in block {
+            if let Some((owner, spent)) = &pending.spent {
+                self.wallets[*owner]
+                    .coins
+                    .retain(|c| spent.iter().all(|s| s.index != c.index));
+            }
+            for output in &pending.outputs {
+                let index = self
+                    .chain
+                    .leaves
+                    .iter()
+                    .position(|leaf| *leaf == output.commitment)
+                    .unwrap();
+                self.wallets[output.receiver].coins.push(OwnedCoin {
+                    coin: output.coin.clone(),
+                    commitment: output.commitment,
+                    index,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_invariants(&self) {
+        // Every coin ever issued is held by exactly one wallet, as pours preserve value.
+        let held: u64 = self.wallets.iter().map(Wallet::balance).sum();
+        assert_eq!(held, self.chain.supply);
+        // Every pour revealed two fresh tags.
+        assert_eq!(self.chain.tags.len(), 2 * self.chain.pours);
+        // Held coins are in the tree and unspent.
+        let mut indices = HashSet::new();
+        for wallet in &self.wallets {
+            for owned in &wallet.coins {
+                assert_eq!(self.chain.leaves[owned.index], owned.commitment);
+                assert!(!self.chain.tags.contains(&Tag(owned.coin.tag)));
+                assert!(indices.insert(owned.index));
+            }
+        }
+    }
+}
+
+fn mint<R: Rng + CryptoRng>(
+    receiver: usize,
+    value: u64,
+    state: &State,
+    setup: &Setup,
+    rng: &mut R,
+) -> Pending {
+    let mut prover = Prover::new(
+        &setup.parameters.even_parameters.pc_gens,
+        Transcript::new(LABEL),
+    );
+    let (coin, commitment, value_var) = Coin::<PallasConfig, PallasP>::mint(
+        value,
+        &state.wallets[receiver].pk,
+        &setup.sig_parameters,
+        &setup.parameters.even_parameters,
+        &setup.layout,
+        &setup.parameters.value_policy,
+        rng,
+        &mut prover,
+    )
+    .unwrap();
+    prover.constrain(value_var - PallasScalar::from(value));
+    let proof = prover
+        .prove(&setup.parameters.even_parameters.bp_gens)
+        .unwrap();
+    Pending {
+        transaction: Transaction::Mint {
+            commitment,
+            value,
+            proof: Box::new(proof),
+        },
+        outputs: vec![Output {
+            receiver,
+            coin,
+            commitment,
+        }],
+        spent: None,
+    }
+}
+
+/// `sender` pays `amount` of the value of `inputs` to `receiver` and the rest back to itself,
+/// proving against the oldest recent root whose tree contains both inputs if `stale`.
+#[allow(clippy::too_many_arguments)]
+fn pour<R: Rng + CryptoRng>(
+    sender: usize,
+    inputs: [OwnedCoin; 2],
+    receiver: usize,
+    amount: u64,
+    stale: bool,
+    state: &State,
+    setup: &Setup,
+    rng: &mut R,
+) -> Pending {
+    let wallet = &state.wallets[sender];
+    let needed = inputs[0].index.max(inputs[1].index) + 1;
+    let history = &state.chain.history;
+    let (root, _, tree) = if stale {
+        history
+            .iter()
+            .find(|(_, leaves, _)| *leaves >= needed)
+            .unwrap()
+    } else {
+        history.back().unwrap()
+    };
+    let change = inputs[0].coin.value + inputs[1].coin.value - amount;
+    let (tx, [coin_0, coin_1]) = prove_pour_with_outputs(
+        Prover::new(
+            &setup.parameters.even_parameters.pc_gens,
+            Transcript::new(LABEL),
+        ),
+        Prover::new(
+            &setup.parameters.odd_parameters.pc_gens,
+            Transcript::new(LABEL),
+        ),
+        setup.parameters,
+        &setup.layout,
+        tree,
+        &wallet.spending_info(&inputs[0], setup, tree),
+        &wallet.spending_info(&inputs[1], setup, tree),
+        amount,
+        state.wallets[receiver].pk,
+        change,
+        wallet.pk,
+        &setup.sig_parameters,
+        rng,
+    )
+    .unwrap();
+    let pour = tx.pour::<L>().unwrap();
+    Pending {
+        transaction: Transaction::Pour { root: root.0, tx },
+        outputs: vec![
+            Output {
+                receiver,
+                coin: coin_0,
+                commitment: pour.minted_coin_commitment_0,
+            },
+            Output {
+                receiver: sender,
+                coin: coin_1,
+                commitment: pour.minted_coin_commitment_1,
+            },
+        ],
+        spent: Some((sender, inputs)),
+    }
+}
+
+// The longest test of the suite, run with `--features slow-tests`.
+#[test]
+#[cfg_attr(not(feature = "slow-tests"), ignore)]
+pub fn test_payments_simulation() {
+    let mut rng = fixtures::insecure_test_rng(0x5eed);
+    let parameters = fixtures::pallas_vesta();
+    let sig_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+    let setup = Setup {
+        parameters,
+        sig_parameters,
+        layout: CommitmentLayout::coin(),
+    };
+
+    let wallets = (0..USERS)
+        .map(|_| {
+            let (pk, sk) = Schnorr::keygen(&setup.sig_parameters, &mut rng).unwrap();
+            Wallet {
+                pk,
+                sk,
+                coins: Vec::new(),
+            }
+        })
+        .collect();
+    let mut state = State {
+        chain: Chain::new(),
+        wallets,
+    };
+
+    // Genesis: every user gets two coins to start paying with.
+    let genesis: Vec<Pending> = (0..2 * USERS)
+        .map(|i| {
+            mint(
+                i % USERS,
+                rng.gen_range(1..MAX_MINT),
+                &state,
+                &setup,
+                &mut rng,
+            )
+        })
+        .collect();
+    state.apply_block(&genesis, &setup).unwrap();
+    state.check_invariants();
+
+    // The state after each block, to roll back to.
+    let mut snapshots = vec![state.clone()];
+    // Coins spent by an accepted pour, to attempt double spends with.
+    let mut spent_pairs: Vec<(usize, [OwnedCoin; 2])> = Vec::new();
+    let (mut double_spends, mut stale_spends, mut rollbacks) = (0, 0, 0);
+
+    for block_number in 0..BLOCKS {
+        let mut block = Vec::new();
+        if rng.gen_bool(0.5) {
+            let receiver = rng.gen_range(0..USERS);
+            block.push(mint(
+                receiver,
+                rng.gen_range(1..MAX_MINT),
+                &state,
+                &setup,
+                &mut rng,
+            ));
+        }
+        let sender = rng.gen_range(0..USERS);
+        if rng.gen_bool(0.6) && state.wallets[sender].coins.len() >= 2 {
+            let coins = &state.wallets[sender].coins;
+            let first = rng.gen_range(0..coins.len());
+            let second = (first + rng.gen_range(1..coins.len())) % coins.len();
+            let inputs = [coins[first].clone(), coins[second].clone()];
+            let receiver = (sender + rng.gen_range(1..USERS)) % USERS;
+            let amount = rng.gen_range(0..=inputs[0].coin.value + inputs[1].coin.value);
+            let stale = rng.gen_bool(0.25);
+            stale_spends += stale as usize;
+            block.push(pour(
+                sender, inputs, receiver, amount, stale, &state, &setup, &mut rng,
+            ));
+        }
+
+        // Every few blocks someone tries to spend coins again, either coins spent in an earlier
+        // block or coins spent by another pour of this block, and the whole block is rejected.
+        if block_number % 10 == 4 {
+            let attempt = match (
+                block.iter().find_map(|p| p.spent.clone()),
+                spent_pairs.last(),
+            ) {
+                (Some((owner, inputs)), _) if block_number % 20 == 4 => Some((owner, inputs)),
+                (_, Some((owner, inputs))) => Some((*owner, inputs.clone())),
+                (Some((owner, inputs)), None) => Some((owner, inputs)),
+                (None, None) => None,
+            };
+            if let Some((owner, inputs)) = attempt {
+                let receiver = (owner + 1) % USERS;
+                let mut attack = block.clone();
+                attack.push(pour(
+                    owner, inputs, receiver, 0, false, &state, &setup, &mut rng,
+                ));
+                let before = state.chain.root();
+                assert_eq!(
+                    state.apply_block(&attack, &setup),
+                    Err(Rejection::DoubleSpend)
+                );
+                assert_eq!(state.chain.root(), before);
+                double_spends += 1;
+            }
+        }
+
+        state.apply_block(&block, &setup).unwrap();
+        spent_pairs.extend(block.iter().filter_map(|p| p.spent.clone()));
+        state.check_invariants();
+
+        // A replayed transaction spends the same tags.
+        if let Some(Pending {
+            transaction: transaction @ Transaction::Pour { .. },
+            ..
+        }) = block.iter().find(|p| p.spent.is_some())
+        {
+            let replay = Pending {
+                transaction: transaction.clone(),
+                outputs: Vec::new(),
+                spent: None,
+            };
+            assert_eq!(
+                state.apply_block(&[replay], &setup),
+                Err(Rejection::DoubleSpend)
+            );
+        }
+        snapshots.push(state.clone());
+
+        // Occasionally the last blocks are reorganized away, which returns their spent coins.
+        if block_number % 10 == 9 && snapshots.len() > 3 {
+            let depth = rng.gen_range(1..=2);
+            snapshots.truncate(snapshots.len() - depth);
+            let restored = snapshots.last().unwrap().clone();
+            state = restored;
+            spent_pairs.retain(|(_, inputs)| {
+                inputs
+                    .iter()
+                    .all(|c| state.chain.tags.contains(&Tag(c.coin.tag)))
+            });
+            state.check_invariants();
+            rollbacks += 1;
+        }
+    }
+
+    // A spend against a root which fell out of the history is rejected,
+    // and pointing it at the current root does not make it verify.
+    let sender = (0..USERS)
+        .find(|u| state.wallets[*u].coins.len() >= 2)
+        .unwrap();
+    let inputs = [
+        state.wallets[sender].coins[0].clone(),
+        state.wallets[sender].coins[1].clone(),
+    ];
+    let mut stale = pour(sender, inputs, sender, 0, false, &state, &setup, &mut rng);
+    let old_root = state.chain.root().unwrap();
+    for _ in 0..ROOT_HISTORY {
+        let issuance = mint(0, 1, &state, &setup, &mut rng);
+        state.apply_block(&[issuance], &setup).unwrap();
+    }
+    assert!(state.chain.tree(old_root.0).is_none());
+    assert_eq!(
+        state.apply_block(std::slice::from_ref(&stale), &setup),
+        Err(Rejection::UnknownRoot)
+    );
+    if let Transaction::Pour { root, .. } = &mut stale.transaction {
+        *root = state.chain.root().unwrap().0;
+    }
+    assert_eq!(
+        state.apply_block(&[stale], &setup),
+        Err(Rejection::InvalidProof)
+    );
+    state.check_invariants();
+
+    assert!(state.chain.pours > 0);
+    assert!(double_spends > 0);
+    assert!(stale_spends > 0);
+    assert!(rollbacks > 0);
+}
+
+/// The payment flow under policies of fewer bits than the default: coins of the largest value of the policy
+/// are minted and poured, a larger value is not proven, and the proofs are rejected by a chain of the default policy.
+#[test]
+pub fn test_payments_under_value_policies() {
+    for bits in [32, 51] {
+        let mut rng = fixtures::insecure_test_rng(bits as u64);
+        let policy = ValuePolicy {
+            bits,
+            ..ValuePolicy::default()
+        };
+        let parameters = fixtures::pallas_vesta().with_value_policy(policy).unwrap();
+        assert_ne!(
+            parameters.fingerprint(),
+            fixtures::pallas_vesta().fingerprint()
+        );
+        let sig_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+        let setup = Setup {
+            parameters: &parameters,
+            sig_parameters: sig_parameters.clone(),
+            layout: CommitmentLayout::coin(),
+        };
+        let default_setup = Setup {
+            parameters: fixtures::pallas_vesta(),
+            sig_parameters,
+            layout: CommitmentLayout::coin(),
+        };
+        let wallets = (0..2)
+            .map(|_| {
+                let (pk, sk) = Schnorr::keygen(&setup.sig_parameters, &mut rng).unwrap();
+                Wallet {
+                    pk,
+                    sk,
+                    coins: Vec::new(),
+                }
+            })
+            .collect();
+        let mut state = State {
+            chain: Chain::new(),
+            wallets,
+        };
+
+        let max = policy.max_value();
+        let genesis = [
+            mint(0, max, &state, &setup, &mut rng),
+            mint(0, 1, &state, &setup, &mut rng),
+        ];
+        assert_eq!(
+            state.clone().apply_block(&genesis, &default_setup),
+            Err(Rejection::InvalidProof)
+        );
+        state.apply_block(&genesis, &setup).unwrap();
+
+        // The inputs sum above the range of a value, the outputs are each within it.
+        let inputs = [
+            state.wallets[0].coins[0].clone(),
+            state.wallets[0].coins[1].clone(),
+        ];
+        let payment = [pour(0, inputs, 1, max, false, &state, &setup, &mut rng)];
+        assert_eq!(
+            state.clone().apply_block(&payment, &default_setup),
+            Err(Rejection::InvalidProof)
+        );
+        state.apply_block(&payment, &setup).unwrap();
+        state.check_invariants();
+        assert_eq!(state.wallets[1].balance(), max);
+
+        let mut prover = Prover::new(&parameters.even_parameters.pc_gens, Transcript::new(LABEL));
+        assert!(matches!(
+            Coin::<PallasConfig, PallasP>::mint(
+                max + 1,
+                &state.wallets[1].pk,
+                &setup.sig_parameters,
+                &parameters.even_parameters,
+                &setup.layout,
+                &policy,
+                &mut rng,
+                &mut prover,
+            ),
+            Err(Error::ValueOutOfRange { .. })
+        ));
+    }
+    assert!(matches!(
+        fixtures::pallas_vesta().with_value_policy(ValuePolicy {
+            bits: 65,
+            ..ValuePolicy::default()
+        }),
+        Err(Error::ParameterMismatch { .. })
+    ));
+}
+
+/// A node applying a pour one transaction at a time: the tree and the spent tags change together or not at all,
+/// and a minted coin commitment which is not permissible is inserted as its permissible leaf.
+#[test]
+pub fn test_apply_transaction() {
+    let mut rng = fixtures::insecure_test_rng(0xa991);
+    let setup = Setup {
+        parameters: fixtures::pallas_vesta(),
+        sig_parameters: Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap(),
+        layout: CommitmentLayout::coin(),
+    };
+    let even_parameters = &setup.parameters.even_parameters;
+    let (pk, sk) = Schnorr::keygen(&setup.sig_parameters, &mut rng).unwrap();
+    let wallet = Wallet {
+        pk,
+        sk,
+        coins: [20, 22]
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let (coin, commitment) = Coin::new(
+                    *value,
+                    &pk,
+                    &setup.sig_parameters,
+                    even_parameters,
+                    &setup.layout,
+                    &mut rng,
+                )
+                .unwrap();
+                OwnedCoin {
+                    coin,
+                    commitment,
+                    index,
+                }
+            })
+            .collect(),
+    };
+    let leaves: Vec<_> = wallet.coins.iter().map(|c| c.commitment).collect();
+    let mut tree = Tree::from_set(&leaves, setup.parameters, Some(HEIGHT)).unwrap();
+    let mut tags = TagSet::new();
+
+    let inputs = [
+        wallet.spending_info(&wallet.coins[0], &setup, &tree),
+        wallet.spending_info(&wallet.coins[1], &setup, &tree),
+    ];
+    let (mut request, minted_coins) = PourRequest::new(
+        &inputs[0],
+        &inputs[1],
+        30,
+        pk,
+        12,
+        pk,
+        &setup.sig_parameters,
+        even_parameters,
+        &setup.layout,
+        &mut rng,
+    )
+    .unwrap();
+    // A prover may mint a coin without searching a permissible commitment, the pour still verifies.
+    let vector = setup
+        .layout
+        .vector(&[
+            (VALUE, PallasScalar::from(30u64)),
+            (TAG, request.outputs[0].tag),
+        ])
+        .unwrap();
+    let (blinding, commitment) = loop {
+        let blinding = PallasScalar::rand(&mut rng);
+        let commitment = even_parameters.commit(&vector, blinding, 0).unwrap();
+        if !open_permissible_leaf(&commitment, even_parameters)
+            .1
+            .is_trivial()
+        {
+            break (blinding, commitment);
+        }
+    };
+    request.outputs[0].commitment_blinding =
+        BlindingAccumulator::from_term(INITIAL, EvenScalar(blinding));
+    request.output_commitments[0] = commitment;
+    let pour = request
+        .prove(
+            Prover::new(&even_parameters.pc_gens, Transcript::new(LABEL)),
+            Prover::new(
+                &setup.parameters.odd_parameters.pc_gens,
+                Transcript::new(LABEL),
+            ),
+            setup.parameters,
+            &setup.layout,
+            &tree,
+        )
+        .unwrap();
+    let tx = request
+        .sign(
+            &pour,
+            &inputs[0],
+            &inputs[1],
+            &setup.sig_parameters,
+            &mut rng,
+        )
+        .unwrap();
+
+    // A node only computes the leaves, the receiver opens its leaf adding the offset of the search to the blinding.
+    let outputs = tx.outputs_for_insertion::<L>(even_parameters).unwrap();
+    let leaf = permissible_leaf(&commitment, even_parameters).permissible_coin;
+    assert_eq!(outputs, vec![leaf, pour.minted_coin_commitment_1]);
+    let (opened_leaf, opening) = open_permissible_leaf(&commitment, even_parameters);
+    assert_eq!(opened_leaf.permissible_coin, leaf);
+    let mut received = minted_coins[0].clone();
+    received.commitment_blinding = BlindingAccumulator::from_term(INITIAL, EvenScalar(blinding));
+    let received = received.with_permissible_opening(&opening);
+    assert_eq!(
+        even_parameters
+            .commit(&vector, received.commitment_blinding.total().0, 0)
+            .unwrap(),
+        leaf
+    );
+    // The leaf of a permissible commitment is the commitment, which opens with the blinding of the coin as it is.
+    let (own_leaf, own_opening) =
+        open_permissible_leaf(&pour.minted_coin_commitment_1, even_parameters);
+    assert_eq!(own_leaf.permissible_coin, pour.minted_coin_commitment_1);
+    assert!(own_opening.is_trivial());
+
+    // A node checking the ownership signatures itself, against the rerandomized public keys the proofs certify.
+    let spends = tx
+        .verify::<L>(
+            LABEL,
+            setup.parameters,
+            &setup.layout,
+            &tree,
+            &setup.sig_parameters,
+        )
+        .unwrap();
+    assert_eq!(spends.clone().map(|spend| spend.tag()), pour.tags());
+    let signatures = [
+        Signature {
+            verifier_challenge: tx.signature_verifier_challenge_0,
+            prover_response: tx.signature_prover_response_0,
+        },
+        Signature {
+            verifier_challenge: tx.signature_verifier_challenge_1,
+            prover_response: tx.signature_prover_response_1,
+        },
+    ];
+    let verify = |pk: &PublicKey<PallasP>, signature| {
+        Schnorr::<PallasP, Blake2s>::verify(&setup.sig_parameters, pk, &tx.pour_bytes, signature)
+            .unwrap()
+    };
+    assert!(verify(&spends[0].rerandomized_pk(), &signatures[0]));
+    assert!(verify(&spends[1].rerandomized_pk(), &signatures[1]));
+    // The key of the other input, of the same owner, is rerandomized apart.
+    assert!(!verify(&spends[1].rerandomized_pk(), &signatures[0]));
+    assert!(!verify(&spends[0].rerandomized_pk(), &signatures[1]));
+    // The application reads the key, the tag and the value commitment in this order.
+    let mut bytes = Vec::new();
+    spends[1].serialize_compressed(&mut bytes).unwrap();
+    let mut reader = bytes.as_slice();
+    assert_eq!(
+        PublicKey::<PallasP>::deserialize_compressed(&mut reader).unwrap(),
+        spends[1].rerandomized_pk()
+    );
+    assert_eq!(
+        PallasScalar::deserialize_compressed(&mut reader).unwrap(),
+        spends[1].tag()
+    );
+    assert_eq!(
+        Affine::<PallasConfig>::deserialize_compressed(&mut reader).unwrap(),
+        spends[1].value_commitment()
+    );
+    assert!(reader.is_empty());
+
+    // The node counts what it applies and rejects.
+    let counters = AtomicMetrics::new();
+    let apply = |tree: &mut Tree, tags: &mut TagSet<PallasScalar>, tx: &Tx| {
+        apply_transaction_with_metrics(
+            tree,
+            tags,
+            tx,
+            LABEL,
+            setup.parameters,
+            &setup.layout,
+            &setup.sig_parameters,
+            &counters,
+        )
+    };
+
+    let mut forged = tx.clone();
+    forged.signature_prover_response_0 += PallasScalar::from(1u64);
+    assert!(matches!(
+        apply(&mut tree, &mut tags, &forged),
+        Err(Error::InvalidSignature)
+    ));
+    assert_eq!(tree.leaves(), leaves);
+    assert!(tags.is_empty());
+
+    // Another node, with a tree of its own, inserts the same leaves for the same mints and gets the same root.
+    let mut other_tree = Tree::from_set(&leaves, setup.parameters, Some(HEIGHT)).unwrap();
+    let mut other_tags = TagSet::new();
+    // The other node also commits to its state, which a light client follows from the transactions and the new root.
+    let mut state = PoolState::new(&other_tree, setup.parameters);
+    let old_state = state;
+    assert_eq!(apply(&mut tree, &mut tags, &tx).unwrap(), vec![2, 3]);
+    assert_eq!(
+        state
+            .apply_transaction(
+                &mut other_tree,
+                &mut other_tags,
+                &tx,
+                LABEL,
+                setup.parameters,
+                &setup.layout,
+                &setup.sig_parameters,
+            )
+            .unwrap(),
+        vec![2, 3]
+    );
+    assert_eq!(state.leaf_count(), 4);
+    assert_eq!(state.tag_count(), 2);
+    let verify_transition = |block: &[Tx], root, digest: &PoolDigest| {
+        verify_transition::<L, _, _, _, _, _>(&old_state, &old_state.digest(), block, root, digest)
+    };
+    let light = verify_transition(std::slice::from_ref(&tx), tree.root(), &state.digest()).unwrap();
+    assert!(light == state);
+    assert!(matches!(
+        verify_transition(std::slice::from_ref(&tx), old_state.root(), &state.digest()),
+        Err(Error::StateMismatch)
+    ));
+    assert!(matches!(
+        verify_transition(&[], tree.root(), &state.digest()),
+        Err(Error::StateMismatch)
+    ));
+    assert!(matches!(
+        verify_transition(&[tx.clone(), tx.clone()], tree.root(), &state.digest()),
+        Err(Error::SpentTag)
+    ));
+    assert_eq!(tree.leaves()[2..], outputs[..]);
+    assert_eq!(other_tree.leaves(), tree.leaves());
+    assert!(other_tree.root() == tree.root());
+    assert!(pour.tags().iter().all(|tag| tags.contains(tag)));
+
+    // Either node's leaves are checked against the minted coin commitments of the verified pour.
+    let minted = [pour.minted_coin_commitment_0, pour.minted_coin_commitment_1];
+    for (commitment, leaf) in minted.iter().zip(&other_tree.leaves()[2..]) {
+        verify_inserted_leaf(commitment, leaf, even_parameters).unwrap();
+    }
+    // A node inserting the commitment which is not permissible as it is, or the leaf of the other coin.
+    assert!(matches!(
+        verify_inserted_leaf(&commitment, &commitment, even_parameters),
+        Err(Error::LeafMismatch)
+    ));
+    assert!(matches!(
+        verify_inserted_leaf(&minted[1], &leaf, even_parameters),
+        Err(Error::LeafMismatch)
+    ));
+
+    // A replay spends the same tags.
+    let applied = tree.leaves();
+    assert!(matches!(
+        apply(&mut tree, &mut tags, &tx),
+        Err(Error::SpentTag)
+    ));
+    assert_eq!(tree.leaves(), applied);
+    assert_eq!(tags.len(), 2);
+    assert_eq!(counters.counter(metrics::TRANSACTIONS_APPLIED, None), 1);
+    for kind in ["InvalidSignature", "SpentTag"] {
+        assert_eq!(
+            counters.counter(metrics::TRANSACTION_FAILURES, Some(kind)),
+            1
+        );
+    }
+    assert_eq!(counters.total(metrics::TRANSACTION_FAILURES), 2);
+    // The leaves of the applied pour only, with at least a search attempt per inner node on their paths.
+    assert_eq!(counters.counter(metrics::LEAVES_INSERTED, None), 2);
+    assert!(counters.counter(metrics::PERMISSIBLE_ATTEMPTS, None) >= HEIGHT as u64);
+
+    // The receiver spends the coin from the leaf and its opening, withdrawing its value.
+    let (mut even_prover, mut odd_prover) = (
+        Prover::new(&even_parameters.pc_gens, Transcript::new(LABEL)),
+        Prover::new(
+            &setup.parameters.odd_parameters.pc_gens,
+            Transcript::new(LABEL),
+        ),
+    );
+    let path = received
+        .prove_withdraw(
+            30,
+            2,
+            &mut even_prover,
+            &mut odd_prover,
+            setup.parameters,
+            &setup.layout,
+            &tree,
+        )
+        .unwrap();
+    let even_proof = even_prover.prove(&even_parameters.bp_gens).unwrap();
+    let odd_proof = odd_prover
+        .prove(&setup.parameters.odd_parameters.bp_gens)
+        .unwrap();
+    let mut even_verifier = Verifier::new(Transcript::new(LABEL));
+    let mut odd_verifier = Verifier::new(Transcript::new(LABEL));
+    verify_withdraw::<L, _, _, _, _, PallasP>(
+        &mut even_verifier,
+        &mut odd_verifier,
+        path,
+        setup.parameters,
+        &setup.layout,
+        &Coin::<PallasConfig, PallasP>::rerandomized_pk(
+            &pk,
+            &received.pk_randomness.total(),
+            &setup.sig_parameters,
+        ),
+        &tree,
+        30,
+    )
+    .unwrap();
+    even_verifier
+        .verify(
+            &even_proof,
+            &even_parameters.pc_gens,
+            &even_parameters.bp_gens,
+        )
+        .unwrap();
+    odd_verifier
+        .verify(
+            &odd_proof,
+            &setup.parameters.odd_parameters.pc_gens,
+            &setup.parameters.odd_parameters.bp_gens,
+        )
+        .unwrap();
+}
@@ -0,0 +1,155 @@
+//! Logs a session of leaves inserted into a tree and tags revealed on chain, and replays it.
+
+#![cfg(feature = "payments")]
+
+extern crate relations;
+
+use ark_ec::short_weierstrass::Affine;
+use ark_std::UniformRand;
+use rand::{CryptoRng, Rng};
+use relations::accumulator::Accumulator;
+use relations::curve_tree::*;
+use relations::events::*;
+use relations::wallet::TagSet;
+use relations::Error;
+use std::sync::{Arc, Mutex};
+
+type PallasParameters = ark_pallas::PallasConfig;
+type VestaParameters = ark_vesta::VestaConfig;
+
+use ark_pallas::Fr as PallasScalar;
+
+fn random_leaf<R: Rng + CryptoRng>(
+    parameters: &SelRerandParameters<PallasParameters, VestaParameters>,
+    rng: &mut R,
+) -> Affine<PallasParameters> {
+    parameters
+        .even_parameters
+        .permissible_commitment(&[PallasScalar::rand(rng)], PallasScalar::rand(rng), 0)
+        .unwrap()
+        .0
+}
+
+#[test]
+pub fn test_replay_randomized_session() {
+    let mut rng = fixtures::insecure_test_rng(0);
+    let sr_params = fixtures::pallas_vesta();
+
+    for storage in [PointStorage::Affine, PointStorage::Compressed] {
+        let initial: Vec<_> = (0..3).map(|_| random_leaf(sr_params, &mut rng)).collect();
+        let mut tree = CurveTree::<4, PallasParameters, VestaParameters>::from_set_with_storage(
+            &initial, sr_params, None, storage,
+        )
+        .unwrap();
+        let mut tags = TagSet::new();
+        tags.insert(PallasScalar::rand(&mut rng));
+
+        let log = Arc::new(Mutex::new(EventWriter::new(Vec::new())));
+        // The leaves and tags from before the sinks are set are logged first.
+        tree.set_event_sink(log.clone().into()).unwrap();
+        tags.set_event_sink(log.clone().into());
+
+        let mut revealed = vec![];
+        for _ in 0..20 {
+            if rng.gen_bool(0.5) {
+                let leaves: Vec<_> = (0..rng.gen_range(1..4))
+                    .map(|_| random_leaf(sr_params, &mut rng))
+                    .collect();
+                tree.insert(&leaves, sr_params).unwrap();
+            } else if !revealed.is_empty() && rng.gen_bool(0.25) {
+                // A tag revealed again is not logged again.
+                let tag = revealed[rng.gen_range(0..revealed.len())];
+                assert!(!tags.insert(tag));
+            } else {
+                let tag = PallasScalar::rand(&mut rng);
+                revealed.push(tag);
+                tags.extend([tag]);
+            }
+        }
+        // The session grew the tree, the sink moved to its new roots.
+        assert!(tree.height() > 1);
+
+        // The tree and the tag set still share the sink.
+        let log = log.lock().unwrap().get_ref().clone();
+        let state = replay::<PallasParameters, _>(log.as_slice()).unwrap();
+        assert_eq!(state.leaves, tree.leaves());
+        assert_eq!(state.tags.len(), tags.len());
+        let replayed = state
+            .check::<4, _, VestaParameters>(
+                &tree.root(),
+                &tags.digest(),
+                sr_params,
+                Some(tree.height()),
+                storage,
+            )
+            .unwrap();
+        assert_eq!(replayed.height(), tree.height());
+
+        // A digest of other tags, or a log cut within a record, does not check.
+        let mut other_tags = tags.clone();
+        other_tags.insert(PallasScalar::rand(&mut rng));
+        assert!(matches!(
+            state.check::<4, _, VestaParameters>(
+                &tree.root(),
+                &other_tags.digest(),
+                sr_params,
+                Some(tree.height()),
+                storage,
+            ),
+            Err(Error::InvalidEventLog { .. })
+        ));
+        assert!(matches!(
+            replay::<PallasParameters, _>(&log[..log.len() - 1]),
+            Err(Error::InvalidEventLog { .. })
+        ));
+    }
+}
+
+#[test]
+pub fn test_replay_rejects_malformed_logs() {
+    let mut rng = fixtures::insecure_test_rng(1);
+    let sr_params = fixtures::pallas_vesta();
+    let leaf = random_leaf(sr_params, &mut rng);
+    let tag = PallasScalar::rand(&mut rng);
+
+    let log = |events: &[Event]| {
+        let mut log = Vec::new();
+        for event in events {
+            event.write(&mut log).unwrap();
+        }
+        log
+    };
+
+    // A leaf skipping an index.
+    assert!(matches!(
+        replay::<PallasParameters, _>(log(&[Event::insert(1, &leaf)]).as_slice()),
+        Err(Error::InvalidEventLog { .. })
+    ));
+    // A tag added twice.
+    assert!(matches!(
+        replay::<PallasParameters, _>(
+            log(&[Event::tag_added(&tag), Event::tag_added(&tag)]).as_slice()
+        ),
+        Err(Error::InvalidEventLog { .. })
+    ));
+    // A record of an unknown kind.
+    let mut unknown = 1u64.to_le_bytes().to_vec();
+    unknown.push(7);
+    assert!(matches!(
+        replay::<PallasParameters, _>(unknown.as_slice()),
+        Err(Error::InvalidEventLog { .. })
+    ));
+    // A length prefix above the limit is rejected before the record is read.
+    let oversized = ((MAX_EVENT_SIZE + 1) as u64).to_le_bytes();
+    assert!(matches!(
+        replay::<PallasParameters, _>(&oversized[..]),
+        Err(Error::LimitExceeded { .. })
+    ));
+
+    let events = [Event::insert(0, &leaf), Event::tag_added(&tag)];
+    let mut reader = &log(&events)[..];
+    for event in &events {
+        assert_eq!(Event::read(&mut reader).unwrap().as_ref(), Some(event));
+    }
+    assert_eq!(Event::read(&mut reader).unwrap(), None);
+}
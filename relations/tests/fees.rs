@@ -0,0 +1,242 @@
+//! Pours paying hidden fees: each publishes a commitment to its fee, the block producer sums the commitments
+//! of a block with the openings the senders hand it, and publishes the opening of the aggregate to claim the fees.
+#![cfg(feature = "payments")]
+
+extern crate bulletproofs;
+extern crate relations;
+
+use ark_crypto_primitives::signature::schnorr::{Parameters, Schnorr};
+use ark_crypto_primitives::signature::SignatureScheme;
+use ark_ec::{AffineRepr, CurveGroup};
+use blake2::Blake2s256 as Blake2s;
+use bulletproofs::r1cs::*;
+use merlin::Transcript;
+use rand::{CryptoRng, Rng};
+
+use relations::coin::*;
+use relations::curve_tree::*;
+use relations::layout::CommitmentLayout;
+use relations::Error;
+
+use ark_pallas::{PallasConfig, Projective as PallasP};
+use ark_vesta::VestaConfig;
+
+const L: usize = 32;
+const HEIGHT: usize = 2;
+const LABEL: &[u8] = b"select_and_rerandomize";
+
+type Tree = CurveTree<L, PallasConfig, VestaConfig>;
+type Input = SpendingInfo<PallasConfig, PallasP>;
+type Tx = SignedTx<PallasConfig, VestaConfig, PallasP>;
+
+struct Public {
+    sr_parameters: &'static SelRerandParameters<PallasConfig, VestaConfig>,
+    sig_parameters: Parameters<PallasP, Blake2s>,
+    layout: CommitmentLayout,
+    tree: Tree,
+}
+
+/// Pours `inputs` into two coins of `values`, paying the rest as fee.
+fn pay<R: Rng + CryptoRng>(
+    public: &Public,
+    inputs: &[Input; 2],
+    values: [u64; 2],
+    fee: u64,
+    rng: &mut R,
+) -> (Tx, PourRequest<PallasConfig, PallasP>) {
+    let (receiver_pk, _) = Schnorr::keygen(&public.sig_parameters, rng).unwrap();
+    let (request, _) = PourRequest::new_with_fee(
+        &inputs[0],
+        &inputs[1],
+        values[0],
+        receiver_pk,
+        values[1],
+        receiver_pk,
+        fee,
+        &public.sig_parameters,
+        &public.sr_parameters.even_parameters,
+        &public.layout,
+        rng,
+    )
+    .unwrap();
+    let pour = prove(public, &request);
+    let tx = request
+        .sign(&pour, &inputs[0], &inputs[1], &public.sig_parameters, rng)
+        .unwrap();
+    (tx, request)
+}
+
+fn prove(
+    public: &Public,
+    request: &PourRequest<PallasConfig, PallasP>,
+) -> Pour<L, PallasConfig, VestaConfig, PallasP> {
+    let sr_parameters = public.sr_parameters;
+    request
+        .prove(
+            Prover::new(
+                &sr_parameters.even_parameters.pc_gens,
+                Transcript::new(LABEL),
+            ),
+            Prover::new(
+                &sr_parameters.odd_parameters.pc_gens,
+                Transcript::new(LABEL),
+            ),
+            sr_parameters,
+            &public.layout,
+            &public.tree,
+        )
+        .unwrap()
+}
+
+fn verify(public: &Public, pour: Pour<L, PallasConfig, VestaConfig, PallasP>) -> Result<(), Error> {
+    let (even_vt, odd_vt) =
+        pour.verification_gadget(LABEL, public.sr_parameters, &public.layout, &public.tree)?;
+    batch_verify(
+        vec![even_vt],
+        &public.sr_parameters.even_parameters.pc_gens,
+        &public.sr_parameters.even_parameters.bp_gens,
+    )?;
+    batch_verify(
+        vec![odd_vt],
+        &public.sr_parameters.odd_parameters.pc_gens,
+        &public.sr_parameters.odd_parameters.bp_gens,
+    )?;
+    Ok(())
+}
+
+#[test]
+fn test_aggregate_fees() {
+    let mut rng = fixtures::insecure_test_rng(0);
+    let sr_parameters = fixtures::pallas_vesta();
+    let sig_parameters = Schnorr::<PallasP, Blake2s>::setup(&mut rng).unwrap();
+    let layout = CommitmentLayout::coin();
+    let (pk, sk) = Schnorr::keygen(&sig_parameters, &mut rng).unwrap();
+
+    let values = [19, 23, 30, 12, 8, 8];
+    let mut coins = Vec::new();
+    let mut leaves = Vec::new();
+    for value in values {
+        let (coin, leaf) = Coin::<PallasConfig, PallasP>::new(
+            value,
+            &pk,
+            &sig_parameters,
+            &sr_parameters.even_parameters,
+            &layout,
+            &mut rng,
+        )
+        .unwrap();
+        coins.push(coin);
+        leaves.push(leaf);
+    }
+    let tree = CurveTree::from_set(&leaves, sr_parameters, Some(HEIGHT)).unwrap();
+    let public = Public {
+        sr_parameters,
+        sig_parameters,
+        layout,
+        tree,
+    };
+    let input = |index: usize| SpendingInfo {
+        leaf: public.tree.leaf_ref(index).unwrap(),
+        coin_aux: coins[index].clone(),
+        randomized_pk: Coin::<PallasConfig, PallasP>::rerandomized_pk(
+            &pk,
+            &coins[index].pk_randomness.total(),
+            &public.sig_parameters,
+        ),
+        sk: sk.clone(),
+    };
+
+    // The pour must balance with its fee.
+    let (receiver_pk, _) = Schnorr::keygen(&public.sig_parameters, &mut rng).unwrap();
+    assert!(matches!(
+        PourRequest::new_with_fee(
+            &input(0),
+            &input(1),
+            30,
+            receiver_pk,
+            10,
+            receiver_pk,
+            3,
+            &public.sig_parameters,
+            &sr_parameters.even_parameters,
+            &public.layout,
+            &mut rng,
+        ),
+        Err(Error::InconsistentWitness { .. })
+    ));
+
+    // Three pours of a block, paying 2, 5 and 1.
+    let payments = [
+        ([input(0), input(1)], [30, 10], 2),
+        ([input(2), input(3)], [20, 17], 5),
+        ([input(4), input(5)], [15, 0], 1),
+    ];
+    let mut block = Vec::new();
+    let mut requests = Vec::new();
+    for (inputs, values, fee) in &payments {
+        let (tx, request) = pay(&public, inputs, *values, *fee, &mut rng);
+        verify(&public, tx.pour::<L>().unwrap()).unwrap();
+        let (opening, _) = request.fee.unwrap();
+        assert_eq!(opening.value, *fee);
+        block.push((tx, opening));
+        requests.push(request);
+    }
+
+    // A node sums the fee commitments of the transactions, the producer opens the sum to claim 8.
+    let (aggregate, opening) =
+        aggregate_fees::<L, _, _, _, _, _>(&block, &sr_parameters.even_parameters).unwrap();
+    let summed = block
+        .iter()
+        .fold(ark_pallas::Affine::zero().into_group(), |sum, (tx, _)| {
+            sum + tx.fee_commitment::<L>().unwrap().unwrap()
+        });
+    assert_eq!(summed.into_affine(), aggregate);
+    assert_eq!(opening.value, 8);
+    opening
+        .verify(&aggregate, &sr_parameters.even_parameters)
+        .unwrap();
+    let mut claimed = opening;
+    claimed.value += 1;
+    assert!(matches!(
+        claimed.verify(&aggregate, &sr_parameters.even_parameters),
+        Err(Error::FeeMismatch)
+    ));
+
+    // A sender handing the producer an opening of a higher fee than its pour commits to.
+    let mut lying = block.clone();
+    lying[1].1.value += 1;
+    assert!(matches!(
+        aggregate_fees::<L, _, _, _, _, _>(&lying, &sr_parameters.even_parameters),
+        Err(Error::FeeMismatch)
+    ));
+
+    // Nor can a pour commit to a higher fee than it balances with.
+    let mut pour = prove(&public, &requests[0]);
+    let (opening, _) = requests[0].fee.unwrap();
+    pour.fee_commitment = Some(
+        FeeOpening {
+            value: opening.value + 1,
+            ..opening
+        }
+        .commitment(&sr_parameters.even_parameters),
+    );
+    assert!(verify(&public, pour).is_err());
+
+    // A pour paying no fee has no fee commitment to aggregate.
+    let (free, _) = PourRequest::new(
+        &input(0),
+        &input(1),
+        40,
+        receiver_pk,
+        2,
+        receiver_pk,
+        &public.sig_parameters,
+        &sr_parameters.even_parameters,
+        &public.layout,
+        &mut rng,
+    )
+    .unwrap();
+    let pour = prove(&public, &free);
+    assert_eq!(pour.fee_commitment(), None);
+    verify(&public, pour).unwrap();
+}
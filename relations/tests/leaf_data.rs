@@ -0,0 +1,162 @@
+extern crate bulletproofs;
+extern crate relations;
+
+use ark_ec::short_weierstrass::Affine;
+use ark_std::UniformRand;
+use bulletproofs::r1cs::{ConstraintSystem, Prover, R1CSProof, Verifier};
+use merlin::Transcript;
+use relations::curve_tree::*;
+use relations::layout::DATA_HASH;
+use relations::leaf_data::{open_data_leaf_verifier, LeafHasher, LeafWitness, Sha3LeafHasher};
+use relations::Error;
+
+use ark_pallas::{Fr as PallasScalar, PallasConfig};
+use ark_vesta::VestaConfig;
+
+type PallasA = Affine<PallasConfig>;
+type VestaA = Affine<VestaConfig>;
+
+const CREDENTIAL: &[u8] = b"curve trees test credential proof";
+
+const CREDENTIAL_BLOB: &[u8] =
+    b"{\"subject\":\"alice\",\"issuer\":\"registry\",\"claims\":{\"member\":true},\"expires\":1893456000}";
+
+/// A proof that the tree has a leaf committing to the hash committed to in `hash_commitment`.
+struct CredentialProof {
+    path: SelectAndRerandomizePath<32, PallasConfig, VestaConfig>,
+    hash_commitment: PallasA,
+    even_proof: R1CSProof<PallasA>,
+    odd_proof: R1CSProof<VestaA>,
+}
+
+fn prove_credential(
+    tree: &CurveTree<32, PallasConfig, VestaConfig>,
+    witness: &LeafWitness<PallasConfig>,
+    committed_hash: PallasScalar,
+    parameters: &SelRerandParameters<PallasConfig, VestaConfig>,
+    rng: &mut rand::rngs::StdRng,
+) -> Result<CredentialProof, Error> {
+    let mut even_prover = Prover::new(
+        &parameters.even_parameters.pc_gens,
+        Transcript::new(CREDENTIAL),
+    );
+    let mut odd_prover = Prover::new(
+        &parameters.odd_parameters.pc_gens,
+        Transcript::new(CREDENTIAL),
+    );
+    let (path, rerandomization) = tree.select_and_rerandomize_prover_gadget(
+        witness.index,
+        &mut even_prover,
+        &mut odd_prover,
+        parameters,
+        rng,
+    )?;
+    let (_, opened) = witness.open_prover(&mut even_prover, rerandomization, parameters)?;
+    let (hash_commitment, hash) = even_prover.commit(committed_hash, PallasScalar::rand(rng));
+    even_prover.constrain(opened.variable(DATA_HASH)? - hash);
+    let (even_proof, odd_proof) = prove_pair(even_prover, odd_prover, parameters)?;
+    Ok(CredentialProof {
+        path,
+        hash_commitment,
+        even_proof,
+        odd_proof,
+    })
+}
+
+fn verify_credential(
+    tree: &CurveTree<32, PallasConfig, VestaConfig>,
+    proof: &CredentialProof,
+    parameters: &SelRerandParameters<PallasConfig, VestaConfig>,
+) -> Result<(), Error> {
+    let mut even_verifier = Verifier::new(Transcript::new(CREDENTIAL));
+    let mut odd_verifier = Verifier::new(Transcript::new(CREDENTIAL));
+    let leaf = tree.select_and_rerandomize_verifier_gadget(
+        &mut even_verifier,
+        &mut odd_verifier,
+        proof.path.clone(),
+        parameters,
+    )?;
+    let opened = open_data_leaf_verifier(&mut even_verifier, leaf);
+    let hash = even_verifier.commit(proof.hash_commitment);
+    even_verifier.constrain(opened.variable(DATA_HASH)? - hash);
+    even_verifier.verify(
+        &proof.even_proof,
+        &parameters.even_parameters.pc_gens,
+        &parameters.even_parameters.bp_gens,
+    )?;
+    odd_verifier.verify(
+        &proof.odd_proof,
+        &parameters.odd_parameters.pc_gens,
+        &parameters.odd_parameters.bp_gens,
+    )?;
+    Ok(())
+}
+
+#[test]
+fn test_insert_and_prove_credential() {
+    let mut rng = fixtures::insecure_test_rng(0);
+    let parameters = fixtures::pallas_vesta();
+    let mut tree =
+        CurveTree::<32, _, _>::from_set(&fixtures::small_tree().leaves, parameters, Some(2))
+            .unwrap();
+    let first = tree.leaf_count();
+
+    let (index, witness) = tree
+        .insert_data(CREDENTIAL_BLOB, parameters, &mut rng)
+        .unwrap();
+    assert_eq!((index, witness.index), (first, first));
+    assert_eq!(tree.leaf_count(), first + 1);
+    let hash: PallasScalar = Sha3LeafHasher::default().hash(CREDENTIAL_BLOB);
+    assert_eq!(witness.data_hash, hash);
+    // The inserted leaf is the permissible commitment to the hash with the blinding of the witness.
+    assert_eq!(
+        tree.leaves()[index],
+        parameters
+            .even_parameters
+            .commit(&witness.vector(), witness.blinding.0, 0)
+            .unwrap()
+    );
+    // Another blob, or the same one again, is another leaf.
+    let (other_index, other) = tree
+        .insert_data(b"another credential", parameters, &mut rng)
+        .unwrap();
+    assert_eq!(other_index, first + 1);
+    let (_, again) = tree
+        .insert_data(CREDENTIAL_BLOB, parameters, &mut rng)
+        .unwrap();
+    assert_eq!(again.data_hash, witness.data_hash);
+    assert_ne!(tree.leaves()[again.index], tree.leaves()[index]);
+
+    let proof = prove_credential(&tree, &witness, hash, parameters, &mut rng).unwrap();
+    verify_credential(&tree, &proof, parameters).unwrap();
+
+    // The leaf does not commit to the hash of another blob.
+    let forged = Sha3LeafHasher::default().hash(b"another credential");
+    let proof = prove_credential(&tree, &witness, forged, parameters, &mut rng).unwrap();
+    assert!(verify_credential(&tree, &proof, parameters).is_err());
+    // Nor does a leaf of another blob commit to the hash of the credential.
+    let proof = prove_credential(&tree, &other, hash, parameters, &mut rng).unwrap();
+    assert!(verify_credential(&tree, &proof, parameters).is_err());
+}
+
+#[test]
+fn test_insert_data_with_application_hasher() {
+    let mut rng = fixtures::insecure_test_rng(1);
+    let parameters = fixtures::pallas_vesta();
+    let mut tree =
+        CurveTree::<32, _, _>::from_set(&fixtures::small_tree().leaves, parameters, Some(2))
+            .unwrap();
+    let hasher = Sha3LeafHasher::new(relations::application_label!("registry", "credential"));
+
+    let (_, witness) = tree
+        .insert_data_with(&hasher, CREDENTIAL_BLOB, parameters, &mut rng)
+        .unwrap();
+    let hash: PallasScalar = hasher.hash(CREDENTIAL_BLOB);
+    assert_eq!(witness.data_hash, hash);
+    assert_ne!(
+        hash,
+        LeafHasher::<PallasScalar>::hash(&Sha3LeafHasher::default(), CREDENTIAL_BLOB)
+    );
+    let proof = prove_credential(&tree, &witness, hash, parameters, &mut rng).unwrap();
+    verify_credential(&tree, &proof, parameters).unwrap();
+}
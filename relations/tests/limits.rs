@@ -0,0 +1,320 @@
+extern crate bulletproofs;
+extern crate relations;
+
+use ark_ec::{short_weierstrass::Affine, AffineRepr};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::UniformRand;
+use bulletproofs::r1cs::*;
+use merlin::Transcript;
+use relations::circuit::*;
+use relations::curve_tree::*;
+use relations::cycle::EvenScalar;
+use relations::limits::*;
+use relations::linking::MigrationProof;
+use relations::shape::TypedMembershipProof;
+use relations::version::FormatVersion;
+use relations::Error;
+use std::time::{Duration, Instant};
+
+use ark_pallas::{Fr as PallasScalar, PallasConfig};
+use ark_vesta::VestaConfig;
+
+const L: usize = 4;
+
+type Path = SelectAndRerandomizePath<L, PallasConfig, VestaConfig>;
+
+/// Serializes `prefix` as a length prefix of a vector, followed by no elements.
+fn oversized(mut bytes: Vec<u8>, prefix: u64) -> Vec<u8> {
+    prefix.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+/// The serialization of two proofs of an empty constraint system, on the even and then the odd curve.
+/// Their vectors are capped by bulletproofs, whatever the limits.
+fn empty_proofs(parameters: &SelRerandParameters<PallasConfig, VestaConfig>) -> Vec<u8> {
+    let even_proof = Prover::new(
+        &parameters.even_parameters.pc_gens,
+        Transcript::new(b"limits"),
+    )
+    .prove(&parameters.even_parameters.bp_gens)
+    .unwrap();
+    let odd_proof = Prover::new(
+        &parameters.odd_parameters.pc_gens,
+        Transcript::new(b"limits"),
+    )
+    .prove(&parameters.odd_parameters.bp_gens)
+    .unwrap();
+    let mut bytes = Vec::new();
+    even_proof.serialize_compressed(&mut bytes).unwrap();
+    odd_proof.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+fn assert_limit_exceeded<T: DeserializeWithLimits + CanonicalDeserialize>(
+    bytes: &[u8],
+    limits: &DeserializeLimits,
+) {
+    assert!(matches!(
+        T::deserialize_compressed_with_limits(bytes, limits),
+        Err(Error::LimitExceeded { .. })
+    ));
+    // Without explicit limits, the defaults apply.
+    if limits == &DeserializeLimits::default() {
+        assert!(matches!(
+            T::deserialize_compressed(bytes),
+            Err(SerializationError::InvalidData)
+        ));
+    }
+}
+
+#[test]
+fn test_oversized_path_prefixes() {
+    let limits = DeserializeLimits::default();
+    let too_deep = limits.max_depth as u64 + 1;
+    let version = || vec![FormatVersion::CURRENT.byte()];
+    assert_limit_exceeded::<Path>(&oversized(version(), too_deep), &limits);
+    assert_limit_exceeded::<Path>(&oversized(version(), u64::MAX), &limits);
+    assert_limit_exceeded::<Path>(&oversized(oversized(version(), 0), too_deep), &limits);
+
+    assert_limit_exceeded::<MembershipProof<L, PallasConfig, VestaConfig>>(
+        &oversized(version(), u64::MAX),
+        &limits,
+    );
+    assert_limit_exceeded::<TypedMembershipProof<L, 2, PallasConfig, VestaConfig>>(
+        &oversized(version(), u64::MAX),
+        &limits,
+    );
+    assert_limit_exceeded::<MigrationProof<L, PallasConfig, VestaConfig>>(
+        &oversized(version(), u64::MAX),
+        &limits,
+    );
+
+    // Nor is a path of an unknown version read any further.
+    assert!(matches!(
+        Path::deserialize_compressed_with_limits(oversized(vec![3], 0).as_slice(), &limits),
+        Err(Error::UnsupportedVersion { version: 3 })
+    ));
+}
+
+#[test]
+fn test_membership_proof_under_limits() {
+    let mut rng = rand::thread_rng();
+    let parameters = fixtures::pallas_vesta();
+    let (leaves, blindings): (Vec<_>, Vec<_>) = (0..5u64)
+        .map(|v| {
+            parameters
+                .even_parameters
+                .permissible_commitment(&[v.into()], PallasScalar::rand(&mut rng), 0)
+                .unwrap()
+        })
+        .unzip();
+    let tree =
+        CurveTree::<L, PallasConfig, VestaConfig>::from_set(&leaves, parameters, Some(4)).unwrap();
+    let (proof, _) = tree
+        .prove_membership(1, EvenScalar(blindings[1]), parameters, &mut rng)
+        .unwrap();
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+
+    let decoded =
+        MembershipProof::<L, PallasConfig, VestaConfig>::deserialize_compressed_with_limits(
+            bytes.as_slice(),
+            &DeserializeLimits::default(),
+        )
+        .unwrap();
+    assert_eq!(
+        decoded.verify(&tree, parameters).unwrap(),
+        proof.rerandomized_leaf()
+    );
+
+    // A tree of height 4 has paths of 2 commitments on each curve.
+    let shallow = DeserializeLimits {
+        max_depth: 1,
+        ..DeserializeLimits::default()
+    };
+    assert_limit_exceeded::<MembershipProof<L, PallasConfig, VestaConfig>>(&bytes, &shallow);
+}
+
+#[test]
+fn test_oversized_circuit_prefixes() {
+    let limits = DeserializeLimits::default();
+    let step = |branching_factor: u64, height: u64| {
+        // the tag of a select and rerandomize step
+        let mut bytes = vec![0u8];
+        branching_factor.serialize_compressed(&mut bytes).unwrap();
+        height.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    };
+    CircuitStep::deserialize_compressed_with_limits(step(256, 4).as_slice(), &limits).unwrap();
+    assert_limit_exceeded::<CircuitStep>(&step(limits.max_arity as u64 + 1, 4), &limits);
+    assert_limit_exceeded::<CircuitStep>(&step(256, u64::MAX), &limits);
+
+    let description = oversized(
+        vec![CIRCUIT_DESCRIPTION_VERSION],
+        limits.max_circuit_steps as u64 + 1,
+    );
+    assert_limit_exceeded::<CircuitDescription>(&description, &limits);
+    let mut description = oversized(vec![CIRCUIT_DESCRIPTION_VERSION], 1);
+    description.extend(step(u64::MAX, 4));
+    assert_limit_exceeded::<CircuitDescription>(&description, &limits);
+}
+
+#[test]
+fn test_over_deep_paths_rejected_before_constraints() {
+    let parameters = fixtures::pallas_vesta();
+    let tree = &fixtures::medium_tree().tree;
+    let limits = DeserializeLimits::default();
+    let too_deep = 10_000;
+    let path = || Path {
+        even_commitments: vec![Affine::generator(); too_deep],
+        odd_commitments: vec![Affine::generator(); too_deep],
+    };
+    assert!(matches!(
+        path().validate(&limits),
+        Err(Error::LimitExceeded { .. })
+    ));
+
+    // The odd root of the tree starts the path the level gadgets are given.
+    let mut from_root = path();
+    match tree.root() {
+        CyclePoint::Odd(root) => from_root.odd_commitments[0] = root.0,
+        CyclePoint::Even(_) => unreachable!("the tree has height 3"),
+    }
+    let mut even_verifier = Verifier::new(Transcript::new(b"limits"));
+    let mut odd_verifier = Verifier::new(Transcript::new(b"limits"));
+    let start = Instant::now();
+    assert!(matches!(
+        tree.select_and_rerandomize_verifier_gadget(
+            &mut even_verifier,
+            &mut odd_verifier,
+            path(),
+            parameters
+        ),
+        Err(Error::InvalidPath)
+    ));
+    assert!(matches!(
+        from_root.even_verifier_gadget(&mut even_verifier, parameters, tree),
+        Err(Error::InvalidPath)
+    ));
+    assert!(matches!(
+        from_root.odd_verifier_gadget(&mut odd_verifier, parameters, tree),
+        Err(Error::InvalidPath)
+    ));
+    // Rejected from the lengths alone, far below the time of laying down a single level.
+    assert!(start.elapsed() < Duration::from_millis(50));
+    assert_eq!(even_verifier.metrics().multipliers, 0);
+    assert_eq!(odd_verifier.metrics().multipliers, 0);
+
+    // Nor are trees or circuits that deep built.
+    let leaf = fixtures::medium_tree().leaves[0];
+    assert!(matches!(
+        CurveTree::<L, PallasConfig, VestaConfig>::from_set(
+            &[leaf],
+            parameters,
+            Some(relations::MAX_SUPPORTED_DEPTH + 1)
+        ),
+        Err(Error::LimitExceeded { .. })
+    ));
+    // Nor trees with more positions than a usize counts, below that depth for a wide branching factor.
+    assert!(matches!(
+        CurveTree::<256, PallasConfig, VestaConfig>::from_set(
+            &[leaf],
+            parameters,
+            Some(usize::BITS as usize / 8)
+        ),
+        Err(Error::LimitExceeded { .. })
+    ));
+    let description = CircuitDescription::new(vec![CircuitStep::SelectAndRerandomize {
+        branching_factor: L,
+        height: relations::MAX_SUPPORTED_DEPTH + 1,
+    }]);
+    assert!(matches!(
+        description.check::<L>(),
+        Err(Error::LimitExceeded { .. })
+    ));
+}
+
+#[test]
+fn test_verifier_constraints_linear_in_depth() {
+    let parameters = fixtures::pallas_vesta();
+    let leaf = fixtures::medium_tree().leaves[0];
+    // The multipliers and constraints of the verifier gadget of a tree of each height, over both curves.
+    let costs: Vec<_> = (1..=8)
+        .map(|height| {
+            let tree = CurveTree::<L, PallasConfig, VestaConfig>::from_set(
+                &[leaf],
+                parameters,
+                Some(height),
+            )
+            .unwrap();
+            let (even_length, odd_length) = tree.path_lengths();
+            let path = Path {
+                even_commitments: vec![Affine::generator(); even_length],
+                odd_commitments: vec![Affine::generator(); odd_length],
+            };
+            let mut even_verifier = Verifier::new(Transcript::new(b"limits"));
+            let mut odd_verifier = Verifier::new(Transcript::new(b"limits"));
+            let _rerandomized_leaf = tree
+                .select_and_rerandomize_verifier_gadget(
+                    &mut even_verifier,
+                    &mut odd_verifier,
+                    path,
+                    parameters,
+                )
+                .unwrap();
+            let (even, odd) = (even_verifier.metrics(), odd_verifier.metrics());
+            (
+                even.multipliers + odd.multipliers,
+                even.constraints + odd.constraints,
+            )
+        })
+        .collect();
+    // A tree of height 1 is a single level, each level above adds the same cost.
+    let per_level = (costs[1].0 - costs[0].0, costs[1].1 - costs[0].1);
+    assert!(per_level.0 > 0);
+    for (height, pair) in costs.windows(2).enumerate() {
+        assert_eq!(
+            (pair[1].0 - pair[0].0, pair[1].1 - pair[0].1),
+            per_level,
+            "the level above height {}",
+            height + 1
+        );
+    }
+}
+
+#[cfg(feature = "payments")]
+mod payments {
+    use super::*;
+    use relations::coin::{Pour, SignedTx};
+
+    type PallasP = ark_pallas::Projective;
+
+    #[test]
+    fn test_oversized_transaction_prefixes() {
+        let mut rng = rand::thread_rng();
+        let parameters = SelRerandParameters::<PallasConfig, VestaConfig>::new(1 << 4, 1 << 4);
+        let limits = DeserializeLimits::default();
+
+        // The two tags, both proofs, then the first rerandomized path.
+        let mut pour = Vec::new();
+        for _ in 0..2 {
+            PallasScalar::rand(&mut rng)
+                .serialize_compressed(&mut pour)
+                .unwrap();
+        }
+        pour.extend(empty_proofs(&parameters));
+        pour.push(FormatVersion::CURRENT.byte());
+        let pour = oversized(pour, u64::MAX);
+        assert_limit_exceeded::<Pour<L, PallasConfig, VestaConfig, PallasP>>(&pour, &limits);
+
+        // The version byte, the four signature scalars, then the pour bytes.
+        let mut tx = vec![FormatVersion::CURRENT.byte()];
+        for _ in 0..4 {
+            PallasScalar::rand(&mut rng)
+                .serialize_compressed(&mut tx)
+                .unwrap();
+        }
+        let tx = oversized(tx, limits.max_transaction_size as u64 + 1);
+        assert_limit_exceeded::<SignedTx<PallasConfig, VestaConfig, PallasP>>(&tx, &limits);
+    }
+}
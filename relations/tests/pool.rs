@@ -0,0 +1,148 @@
+//! Digests of pool states applying sequences of blocks: reproducible by every node, and sensitive to their order.
+
+#![cfg(feature = "payments")]
+
+extern crate relations;
+
+use ark_ec::short_weierstrass::Affine;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::UniformRand;
+use rand::{CryptoRng, Rng};
+use relations::curve_tree::*;
+use relations::policy::ValuePolicy;
+use relations::pool::PoolState;
+use relations::staging::TreeTransaction;
+use relations::wallet::TagSet;
+
+type PallasParameters = ark_pallas::PallasConfig;
+type VestaParameters = ark_vesta::VestaConfig;
+type Tree = CurveTree<4, PallasParameters, VestaParameters>;
+type State = PoolState<PallasParameters, VestaParameters>;
+
+use ark_pallas::Fr as PallasScalar;
+
+/// The coins a block mints and the tags it reveals.
+#[derive(Clone)]
+struct Block {
+    coins: Vec<Affine<PallasParameters>>,
+    tags: Vec<PallasScalar>,
+}
+
+fn random_blocks<R: Rng + CryptoRng>(
+    parameters: &SelRerandParameters<PallasParameters, VestaParameters>,
+    count: usize,
+    rng: &mut R,
+) -> Vec<Block> {
+    (0..count)
+        .map(|_| Block {
+            coins: (0..2)
+                .map(|_| {
+                    parameters
+                        .even_parameters
+                        .permissible_commitment(
+                            &[PallasScalar::rand(rng)],
+                            PallasScalar::rand(rng),
+                            0,
+                        )
+                        .unwrap()
+                        .0
+                })
+                .collect(),
+            tags: (0..3).map(|_| PallasScalar::rand(rng)).collect(),
+        })
+        .collect()
+}
+
+/// A node applying `blocks` from a tree of the coins of `genesis`, staging each against its tree and tag set,
+/// and its digest after each.
+fn apply_blocks<'a>(
+    genesis: &Block,
+    blocks: impl IntoIterator<Item = &'a Block>,
+    parameters: &SelRerandParameters<PallasParameters, VestaParameters>,
+) -> (State, Vec<[u8; 32]>) {
+    let mut tree = Tree::from_set(&genesis.coins, parameters, Some(2)).unwrap();
+    let mut tags = TagSet::new();
+    let mut state = State::new(&tree, parameters);
+    let mut digests = vec![state.digest()];
+    for block in blocks {
+        let mut transaction = TreeTransaction::new(vec![&mut tree], &mut tags, parameters);
+        transaction.insert(0, &block.coins).unwrap();
+        for tag in &block.tags {
+            transaction.reveal_tag(*tag).unwrap();
+            state.reveal_tag(tag);
+        }
+        transaction.commit().unwrap();
+        state.update_tree(&tree);
+        digests.push(state.digest());
+    }
+    (state, digests)
+}
+
+#[test]
+fn test_pool_digests_are_reproducible() {
+    let mut rng = fixtures::insecure_test_rng(0);
+    let parameters = fixtures::pallas_vesta();
+    let mut blocks = random_blocks(parameters, 5, &mut rng);
+    let genesis = blocks.remove(0);
+
+    let (state, digests) = apply_blocks(&genesis, &blocks, parameters);
+    let (other_state, other_digests) = apply_blocks(&genesis, &blocks, parameters);
+    assert_eq!(digests, other_digests);
+    assert!(state == other_state);
+    assert_eq!(state.leaf_count(), 10);
+    assert_eq!(state.tag_count(), 12);
+    assert_eq!(state.fingerprint(), parameters.fingerprint());
+    // Every block changes the digest.
+    for pair in digests.windows(2) {
+        assert_ne!(pair[0], pair[1]);
+    }
+
+    let mut bytes = Vec::new();
+    state.serialize_compressed(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), state.compressed_size());
+    let deserialized = State::deserialize_compressed(bytes.as_slice()).unwrap();
+    assert!(deserialized == state);
+    assert_eq!(deserialized.digest(), state.digest());
+    bytes[0] = 2;
+    assert!(State::deserialize_compressed(bytes.as_slice()).is_err());
+
+    // The same blocks verified with parameters of another value policy.
+    let policy = ValuePolicy {
+        bits: 32,
+        ..ValuePolicy::default()
+    };
+    let other_parameters = parameters.with_value_policy(policy).unwrap();
+    let (other_state, _) = apply_blocks(&genesis, &blocks, &other_parameters);
+    assert!(other_state.root() == state.root());
+    assert_ne!(other_state.digest(), state.digest());
+}
+
+#[test]
+fn test_pool_digests_are_order_sensitive() {
+    let mut rng = fixtures::insecure_test_rng(1);
+    let parameters = fixtures::pallas_vesta();
+    let mut blocks = random_blocks(parameters, 4, &mut rng);
+    let genesis = blocks.remove(0);
+    let (_, digests) = apply_blocks(&genesis, &blocks, parameters);
+
+    // The blocks in another order insert the leaves at other positions.
+    let (_, swapped) = apply_blocks(&genesis, [&blocks[1], &blocks[0], &blocks[2]], parameters);
+    assert_ne!(swapped.last(), digests.last());
+
+    // The same leaves with the tags of a block revealed in another order: the tag set is the same, the chain is not.
+    let mut reordered = blocks.clone();
+    reordered[2].tags.reverse();
+    let (state, reordered_digests) = apply_blocks(&genesis, &reordered, parameters);
+    assert_eq!(reordered_digests[..3], digests[..3]);
+    assert_ne!(reordered_digests[3], digests[3]);
+    let (original, _) = apply_blocks(&genesis, &blocks, parameters);
+    assert!(state.root() == original.root());
+
+    // A tag moved to the next block.
+    let mut moved = blocks.clone();
+    let tag = moved[0].tags.pop().unwrap();
+    moved[1].tags.insert(0, tag);
+    let (_, moved_digests) = apply_blocks(&genesis, &moved, parameters);
+    assert_ne!(moved_digests[1], digests[1]);
+    assert_eq!(moved_digests[2], digests[2]);
+}
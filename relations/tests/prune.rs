@@ -0,0 +1,80 @@
+extern crate relations;
+
+use relations::curve_tree::*;
+use relations::cycle::EvenScalar;
+
+use ark_std::UniformRand;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+type PallasParameters = ark_pallas::PallasConfig;
+type VestaParameters = ark_vesta::VestaConfig;
+type PallasScalar = ark_pallas::Fr;
+
+// Counts the bytes allocated and not yet freed, by every thread.
+struct CountingAllocator;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// The only test of this binary, so that no other test allocates while it counts.
+// Pruning the 64 leaves below a node of height 3 drops them and the nodes between, with either storage,
+// more than the entries of the pruned leaves take in the index of the leaves.
+#[test]
+fn test_prune_drops_the_nodes_below_pruned_subtrees() {
+    const L: usize = 4;
+    let mut rng = rand::thread_rng();
+    let parameters = fixtures::pallas_vesta();
+    let values: Vec<_> = (0..L.pow(4))
+        .map(|_| ([PallasScalar::rand(&mut rng)], PallasScalar::rand(&mut rng)))
+        .collect();
+    let (leaves, blindings): (Vec<_>, Vec<_>) = parameters
+        .even_parameters
+        .blinded_permissible_commitments(&values, 0)
+        .unwrap()
+        .into_iter()
+        .unzip();
+    let pruned: Vec<_> = (64..128).collect();
+
+    for storage in [PointStorage::Affine, PointStorage::Compressed] {
+        let mut tree = CurveTree::<L, PallasParameters, VestaParameters>::from_set_with_storage(
+            &leaves, parameters, None, storage,
+        )
+        .unwrap();
+        let root = tree.root();
+
+        let live_bytes = LIVE_BYTES.load(Ordering::Relaxed);
+        tree.prune(&pruned).unwrap();
+        let dropped = live_bytes as isize - LIVE_BYTES.load(Ordering::Relaxed) as isize;
+        assert!(
+            dropped > 0,
+            "{:?} storage: {} bytes dropped",
+            storage,
+            dropped
+        );
+
+        assert!(tree.root() == root);
+        assert_eq!(tree.leaves().len(), leaves.len() - pruned.len());
+        // The neighbours of the pruned leaves are proven with the commitments kept.
+        for index in [63, 128] {
+            let (proof, _) = tree
+                .prove_membership(index, EvenScalar(blindings[index]), parameters, &mut rng)
+                .unwrap();
+            assert!(proof.verify(&tree, parameters).is_ok());
+        }
+    }
+}
@@ -0,0 +1,144 @@
+extern crate relations;
+
+use ark_ec::short_weierstrass::Affine;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::UniformRand;
+use relations::accumulator::Accumulator;
+use relations::curve_tree::CurveTree;
+use relations::registry::{
+    prove_attribute, registry_entry, verify_attribute, AttributePredicate, AttributeProof,
+    RegistryWitness,
+};
+use relations::Error;
+
+use ark_pallas::PallasConfig;
+use ark_vesta::VestaConfig;
+
+const ENTRIES: u64 = 1000;
+
+type Registry = CurveTree<32, PallasConfig, VestaConfig>;
+
+// A registry whose entry `i` has a random key and the attribute `i % 100`, e.g. an age.
+fn registry() -> (Registry, Vec<RegistryWitness<PallasConfig>>) {
+    let parameters = fixtures::pallas_vesta();
+    let mut rng = fixtures::insecure_test_rng(0);
+    let (leaves, witnesses): (Vec<_>, Vec<_>) = (0..ENTRIES)
+        .map(|i| {
+            let key = Affine::<VestaConfig>::rand(&mut rng);
+            registry_entry(&key, i % 100, parameters, &mut rng).unwrap()
+        })
+        .unzip();
+    let tree = Registry::from_set(&leaves, parameters, Some(2)).unwrap();
+    (tree, witnesses)
+}
+
+#[test]
+fn test_registry_attributes() {
+    let parameters = fixtures::pallas_vesta();
+    let mut rng = fixtures::insecure_test_rng(1);
+    let (tree, witnesses) = registry();
+    assert_eq!(tree.leaf_count() as u64, ENTRIES);
+
+    let adult = AttributePredicate::GreaterEq(18);
+    let working_age = AttributePredicate::Range { min: 18, max: 65 };
+    // The entry 630 is 30.
+    let index = 630;
+    let witness = &witnesses[index];
+    assert_eq!(witness.attribute, 30);
+
+    let proof = prove_attribute(&tree, index, witness, adult, parameters, &mut rng).unwrap();
+    verify_attribute(&tree, &proof, adult, parameters).unwrap();
+    // The proof is of its predicate only.
+    for other in [
+        AttributePredicate::GreaterEq(31),
+        AttributePredicate::GreaterEq(17),
+        AttributePredicate::Equals(30),
+        working_age,
+    ] {
+        assert!(verify_attribute(&tree, &proof, other, parameters).is_err());
+    }
+    // Nor of another registry.
+    let mut grown = Registry::from_set(&tree.leaves(), parameters, Some(2)).unwrap();
+    let (leaf, _) = registry_entry(
+        &Affine::<VestaConfig>::rand(&mut rng),
+        40,
+        parameters,
+        &mut rng,
+    )
+    .unwrap();
+    grown.insert(&[leaf], parameters).unwrap();
+    assert!(matches!(
+        verify_attribute(&grown, &proof, adult, parameters),
+        Err(Error::InvalidPath)
+    ));
+
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    let deserialized =
+        AttributeProof::<32, PallasConfig, VestaConfig>::deserialize_compressed(&bytes[..])
+            .unwrap();
+    verify_attribute(&tree, &deserialized, adult, parameters).unwrap();
+
+    // A range whose bounds are the attribute, and an equality.
+    for predicate in [
+        AttributePredicate::Range { min: 30, max: 30 },
+        AttributePredicate::Equals(30),
+    ] {
+        let proof =
+            prove_attribute(&tree, index, witness, predicate, parameters, &mut rng).unwrap();
+        verify_attribute(&tree, &proof, predicate, parameters).unwrap();
+    }
+}
+
+#[test]
+fn test_registry_violating_attributes() {
+    let parameters = fixtures::pallas_vesta();
+    let mut rng = fixtures::insecure_test_rng(2);
+    let (tree, witnesses) = registry();
+
+    // The entry 917 is 17, the entry 66 is 66.
+    for (index, predicate) in [
+        (917, AttributePredicate::GreaterEq(18)),
+        (917, AttributePredicate::Range { min: 18, max: 65 }),
+        (66, AttributePredicate::Range { min: 18, max: 65 }),
+        (66, AttributePredicate::Equals(65)),
+    ] {
+        assert!(!predicate.holds(witnesses[index].attribute));
+        assert!(matches!(
+            prove_attribute(
+                &tree,
+                index,
+                &witnesses[index],
+                predicate,
+                parameters,
+                &mut rng
+            ),
+            Err(Error::InconsistentWitness { .. })
+        ));
+    }
+
+    // Claiming another attribute, or the entry of another key, does not open the leaf.
+    let mut forged = witnesses[917].clone();
+    forged.attribute = 18;
+    let adult = AttributePredicate::GreaterEq(18);
+    assert!(matches!(
+        prove_attribute(&tree, 917, &forged, adult, parameters, &mut rng),
+        Err(Error::InconsistentWitness { .. })
+    ));
+    assert!(matches!(
+        prove_attribute(&tree, 917, &witnesses[918], adult, parameters, &mut rng),
+        Err(Error::InconsistentWitness { .. })
+    ));
+
+    assert!(matches!(
+        prove_attribute(
+            &tree,
+            0,
+            &witnesses[0],
+            AttributePredicate::Range { min: 1, max: 0 },
+            parameters,
+            &mut rng
+        ),
+        Err(Error::ParameterMismatch { .. })
+    ));
+}
@@ -0,0 +1,150 @@
+extern crate bulletproofs;
+extern crate relations;
+
+use ark_std::UniformRand;
+use bulletproofs::r1cs::{R1CSError, R1CSProof, Verifier};
+use merlin::Transcript;
+use relations::circuit::*;
+use relations::range_proof::range_proof;
+use relations::session::*;
+use relations::Error;
+
+use fixtures::{PallasConfig, PallasScalar, VestaConfig};
+
+const L: usize = 32;
+
+fn statements_for_verifier(
+    statements: &[StepStatement<L, PallasConfig, VestaConfig>],
+) -> Vec<StepStatement<L, PallasConfig, VestaConfig>> {
+    statements
+        .iter()
+        .map(|statement| match statement {
+            StepStatement::SelectAndRerandomize { path, .. } => {
+                StepStatement::SelectAndRerandomize {
+                    path: path.clone(),
+                    rerandomization: None,
+                }
+            }
+            StepStatement::RangeProof { commitment } => StepStatement::RangeProof {
+                commitment: *commitment,
+            },
+        })
+        .collect()
+}
+
+#[test]
+pub fn test_session_forks_are_independent() {
+    let mut rng = rand::thread_rng();
+    let parameters = fixtures::pallas_vesta();
+    let fixture = fixtures::small_tree();
+    let tree = &fixture.tree;
+    let description = CircuitDescription::new(vec![
+        CircuitStep::SelectAndRerandomize {
+            branching_factor: L,
+            height: 2,
+        },
+        CircuitStep::RangeProof { bits: 64 },
+    ]);
+    let witnesses = |index, value| {
+        vec![
+            StepWitness::SelectAndRerandomize { tree, index },
+            StepWitness::RangeProof {
+                value,
+                blinding: PallasScalar::rand(&mut rand::thread_rng()),
+            },
+        ]
+    };
+
+    let mut session = ProvingSession::new(parameters);
+    let (reserves, reserves_statements) = session
+        .prove(b"reserves", &description, &witnesses(0, 100), &mut rng)
+        .unwrap();
+    let (payment, payment_statements) = session
+        .prove(b"payment", &description, &witnesses(1, 7), &mut rng)
+        .unwrap();
+
+    // The verifier checks the forks in any order, with the label each was proven with.
+    let verification = VerificationSession::new(parameters);
+    assert_eq!(verification.fingerprint(), session.fingerprint());
+    let verify = |label, statements: &[_], proof| {
+        verification.verify(
+            label,
+            &description,
+            statements_for_verifier(statements),
+            &[tree],
+            proof,
+        )
+    };
+    verify(b"payment", &payment_statements, &payment).unwrap();
+    verify(b"reserves", &reserves_statements, &reserves).unwrap();
+    assert!(verify(b"reserves", &payment_statements, &payment).is_err());
+
+    // A fork is not a proof of the description on its own.
+    assert!(description
+        .verify(
+            b"payment",
+            statements_for_verifier(&payment_statements),
+            &[tree],
+            &payment,
+            parameters,
+        )
+        .is_err());
+
+    // Corrupting one proof leaves the other valid.
+    let corrupted = CircuitProof {
+        even_proof: reserves.even_proof.clone(),
+        odd_proof: payment.odd_proof.clone(),
+    };
+    assert!(verify(b"payment", &payment_statements, &corrupted).is_err());
+    verify(b"reserves", &reserves_statements, &reserves).unwrap();
+}
+
+#[test]
+pub fn test_session_provers_and_verifiers() {
+    let mut rng = rand::thread_rng();
+    let parameters = fixtures::pallas_vesta();
+
+    let mut session = ProvingSession::new(parameters);
+    let mut prove = |label, value| -> Result<_, Error> {
+        let (mut even_prover, _) = session.new_prover(label)?;
+        let (commitment, variable) =
+            even_prover.commit(PallasScalar::from(value), PallasScalar::rand(&mut rng));
+        range_proof(&mut even_prover, variable.into(), Some(value), 16)?;
+        let proof = even_prover.prove(&parameters.even_parameters.bp_gens)?;
+        Ok((commitment, proof))
+    };
+    let (first_commitment, first_proof) = prove(b"first", 3).unwrap();
+    let (second_commitment, second_proof) = prove(b"second", 1 << 15).unwrap();
+    // A label is forked once per session.
+    assert!(matches!(
+        prove(b"first", 3),
+        Err(Error::ParameterMismatch { .. })
+    ));
+
+    let verification = VerificationSession::new(parameters);
+    let verify = |label, commitment, proof: &R1CSProof<_>| -> Result<(), R1CSError> {
+        let (mut even_verifier, _) = verification.new_verifier(label);
+        let variable = even_verifier.commit(commitment);
+        range_proof(&mut even_verifier, variable.into(), None, 16)?;
+        even_verifier.verify(
+            proof,
+            &parameters.even_parameters.pc_gens,
+            &parameters.even_parameters.bp_gens,
+        )
+    };
+    verify(b"second", second_commitment, &second_proof).unwrap();
+    verify(b"first", first_commitment, &first_proof).unwrap();
+    assert!(verify(b"second", first_commitment, &first_proof).is_err());
+
+    // A verifier forked from the session differs from one of a fresh transcript of the same label.
+    let mut verifier = Verifier::new(Transcript::new(b"first"));
+    let variable = verifier.commit(first_commitment);
+    range_proof(&mut verifier, variable.into(), None, 16).unwrap();
+    assert!(verifier
+        .verify(
+            &first_proof,
+            &parameters.even_parameters.pc_gens,
+            &parameters.even_parameters.bp_gens,
+        )
+        .is_err());
+}
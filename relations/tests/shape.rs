@@ -0,0 +1,95 @@
+extern crate bulletproofs;
+extern crate relations;
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::UniformRand;
+use relations::curve_tree::*;
+use relations::cycle::EvenScalar;
+use relations::shape::*;
+use relations::Error;
+
+use ark_pallas::{Fr as PallasScalar, PallasConfig};
+use ark_vesta::VestaConfig;
+
+const L: usize = 32;
+
+#[test]
+pub fn test_typed_shapes() {
+    let mut rng = rand::thread_rng();
+    let parameters = fixtures::pallas_vesta();
+    let (leaf, blinding) = parameters
+        .even_parameters
+        .permissible_commitment(
+            &[PallasScalar::rand(&mut rng)],
+            PallasScalar::rand(&mut rng),
+            0,
+        )
+        .unwrap();
+
+    assert_eq!(TreeShape::<L, 2>::PATH_LENGTHS, (1, 1));
+    assert_eq!(TreeShape::<L, 3>::PATH_LENGTHS, (2, 1));
+
+    let tree =
+        TypedCurveTree::<L, 2, PallasConfig, VestaConfig>::from_set(&[leaf], parameters).unwrap();
+    assert_eq!(tree.tree().path_lengths(), TreeShape::<L, 2>::PATH_LENGTHS);
+    assert_eq!(
+        matches!(tree.tree(), CurveTree::Even(_)),
+        TreeShape::<L, 2>::ROOT_IS_EVEN
+    );
+    let (proof, _) = tree
+        .prove_membership(0, EvenScalar(blinding), parameters, &mut rng)
+        .unwrap();
+    assert_eq!(
+        proof.verify(&tree, parameters).unwrap(),
+        proof.proof().rerandomized_leaf()
+    );
+    assert_eq!(
+        TreeShape::<L, 2>::padded_multipliers(parameters).unwrap(),
+        (
+            proof.proof().even_proof.padded_multipliers().unwrap(),
+            proof.proof().odd_proof.padded_multipliers().unwrap()
+        )
+    );
+
+    // A depth-2 tree, path or proof does not convert into a depth-3 one.
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    assert!(
+        TypedMembershipProof::<L, 2, PallasConfig, VestaConfig>::deserialize_compressed(
+            bytes.as_slice()
+        )
+        .is_ok()
+    );
+    assert!(
+        TypedMembershipProof::<L, 3, PallasConfig, VestaConfig>::deserialize_compressed(
+            bytes.as_slice()
+        )
+        .is_err()
+    );
+    let proof = proof.into_inner();
+    assert!(matches!(
+        TypedPath::<L, 3, PallasConfig, VestaConfig>::try_from(proof.path.clone()),
+        Err(Error::ShapeMismatch { .. })
+    ));
+    assert!(matches!(
+        TypedMembershipProof::<L, 3, PallasConfig, VestaConfig>::try_from(proof),
+        Err(Error::ShapeMismatch { .. })
+    ));
+    assert!(matches!(
+        TypedCurveTree::<L, 3, PallasConfig, VestaConfig>::try_from(tree.into_inner()),
+        Err(Error::ShapeMismatch { .. })
+    ));
+
+    // The taller tree proves and verifies at its own depth.
+    let taller = TypedCurveTree::<L, 3, PallasConfig, VestaConfig>::try_from(
+        CurveTree::from_set(&[leaf], parameters, Some(3)).unwrap(),
+    )
+    .unwrap();
+    let (proof, _) = taller
+        .prove_membership(0, EvenScalar(blinding), parameters, &mut rng)
+        .unwrap();
+    assert_eq!(
+        proof.verify(&taller, parameters).unwrap(),
+        proof.proof().rerandomized_leaf()
+    );
+}
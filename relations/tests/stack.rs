@@ -0,0 +1,93 @@
+//! Trees and circuits built, proved and verified on threads with small stacks, e.g. those of musl or wasm.
+
+extern crate relations;
+
+use relations::circuit::*;
+use relations::curve_tree::*;
+
+use ark_pallas::{Fr as PallasScalar, PallasConfig};
+use ark_vesta::VestaConfig;
+
+const LABEL: &[u8] = b"stack test";
+
+/// The default stack of a thread with musl. Unoptimized, the serialization of a point in arkworks alone takes more.
+const STACK_SIZE: usize = if cfg!(debug_assertions) {
+    512 << 10
+} else {
+    128 << 10
+};
+
+fn on_small_stack<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, f)
+            .unwrap()
+            .join()
+            .unwrap()
+    })
+}
+
+/// Builds a tree of the given height, proves and verifies the membership of a leaf and a range proof, and drops the tree.
+fn prove_and_verify<const L: usize>(height: usize) {
+    let parameters = fixtures::pallas_vesta();
+    let leaves = &fixtures::medium_tree().leaves[..3];
+    let tree =
+        CurveTree::<L, PallasConfig, VestaConfig>::from_set(leaves, parameters, Some(height))
+            .unwrap();
+    let description = CircuitDescription::new(vec![
+        CircuitStep::SelectAndRerandomize {
+            branching_factor: L,
+            height,
+        },
+        CircuitStep::RangeProof { bits: 64 },
+    ]);
+    let (proof, statements) = description
+        .prove(
+            LABEL,
+            &[
+                StepWitness::SelectAndRerandomize {
+                    tree: &tree,
+                    index: 2,
+                },
+                StepWitness::RangeProof {
+                    value: 42,
+                    blinding: PallasScalar::from(7u8),
+                },
+            ],
+            parameters,
+            &mut fixtures::insecure_test_rng(0),
+        )
+        .unwrap();
+    let statements = statements
+        .into_iter()
+        .map(|statement| match statement {
+            StepStatement::SelectAndRerandomize { path, .. } => {
+                StepStatement::SelectAndRerandomize {
+                    path,
+                    rerandomization: None,
+                }
+            }
+            statement => statement,
+        })
+        .collect();
+    description
+        .verify(LABEL, statements, &[&tree], &proof, parameters)
+        .unwrap();
+}
+
+#[test]
+fn test_deep_circuit_on_small_stack() {
+    // The parameters are built once per test binary, on whichever thread asks first.
+    fixtures::pallas_vesta();
+    fixtures::medium_tree();
+    on_small_stack(|| prove_and_verify::<4>(8));
+}
+
+#[test]
+fn test_wide_tree_on_small_stack() {
+    fixtures::pallas_vesta();
+    fixtures::medium_tree();
+    // The children of a node were once laid out on the stack before being boxed, an array of 256 nodes each time.
+    on_small_stack(|| prove_and_verify::<256>(4));
+}
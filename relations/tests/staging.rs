@@ -0,0 +1,190 @@
+//! Blocks staged against a tree of coins, a registry of keys and the tag set, committed at once or not at all.
+
+#![cfg(feature = "payments")]
+
+extern crate relations;
+
+use ark_ec::short_weierstrass::Affine;
+use ark_std::UniformRand;
+use rand::{CryptoRng, Rng};
+use relations::curve_tree::*;
+use relations::events::*;
+use relations::staging::TreeTransaction;
+use relations::wallet::TagSet;
+use relations::Error;
+use std::sync::{Arc, Mutex};
+
+type PallasParameters = ark_pallas::PallasConfig;
+type VestaParameters = ark_vesta::VestaConfig;
+type Tree = CurveTree<4, PallasParameters, VestaParameters>;
+
+use ark_pallas::Fr as PallasScalar;
+
+const COINS: usize = 0;
+const REGISTRY: usize = 1;
+
+fn random_leaves<R: Rng + CryptoRng>(
+    parameters: &SelRerandParameters<PallasParameters, VestaParameters>,
+    count: usize,
+    rng: &mut R,
+) -> Vec<Affine<PallasParameters>> {
+    (0..count)
+        .map(|_| {
+            parameters
+                .even_parameters
+                .permissible_commitment(&[PallasScalar::rand(rng)], PallasScalar::rand(rng), 0)
+                .unwrap()
+                .0
+        })
+        .collect()
+}
+
+/// A pour of a block: the tags it spends and the coins it mints, and the keys a registration adds.
+struct BlockEntry {
+    tags: Vec<PallasScalar>,
+    coins: Vec<Affine<PallasParameters>>,
+    keys: Vec<Affine<PallasParameters>>,
+}
+
+fn apply_block(
+    transaction: &mut TreeTransaction<4, PallasParameters, VestaParameters>,
+    block: &[BlockEntry],
+) -> Result<(), Error> {
+    for entry in block {
+        for tag in &entry.tags {
+            transaction.reveal_tag(*tag)?;
+        }
+        transaction.insert(COINS, &entry.coins)?;
+        transaction.insert(REGISTRY, &entry.keys)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_failed_block_leaves_trees_and_tags_untouched() {
+    let mut rng = fixtures::insecure_test_rng(0);
+    let parameters = fixtures::pallas_vesta();
+    let mut coins =
+        Tree::from_set(&random_leaves(parameters, 3, &mut rng), parameters, None).unwrap();
+    let mut registry =
+        Tree::from_set(&random_leaves(parameters, 2, &mut rng), parameters, None).unwrap();
+    let spent = PallasScalar::rand(&mut rng);
+    let mut tags = TagSet::new();
+    tags.insert(spent);
+    let log = Arc::new(Mutex::new(EventWriter::new(Vec::new())));
+    coins.set_event_sink(log.clone().into()).unwrap();
+    registry.set_event_sink(log.clone().into()).unwrap();
+    tags.set_event_sink(log.clone().into());
+    let logged = log.lock().unwrap().get_ref().len();
+    let roots = (coins.root(), registry.root());
+    let digest = tags.digest();
+
+    // The second pour spends a tag spent in an earlier block, after the first staged coins, keys and a tag.
+    let fresh = PallasScalar::rand(&mut rng);
+    let block = [
+        BlockEntry {
+            tags: vec![fresh],
+            coins: random_leaves(parameters, 2, &mut rng),
+            keys: random_leaves(parameters, 1, &mut rng),
+        },
+        BlockEntry {
+            tags: vec![spent],
+            coins: random_leaves(parameters, 2, &mut rng),
+            keys: vec![],
+        },
+    ];
+    let mut transaction =
+        TreeTransaction::new(vec![&mut coins, &mut registry], &mut tags, parameters);
+    assert!(matches!(
+        apply_block(&mut transaction, &block[..1]).and_then(|_| transaction.staged_roots()),
+        Ok(staged) if staged[COINS] != roots.0 && staged[REGISTRY] != roots.1
+    ));
+    assert!(matches!(
+        apply_block(&mut transaction, &block[1..]),
+        Err(Error::SpentTag)
+    ));
+    transaction.abort();
+
+    assert!(coins.root() == roots.0);
+    assert!(registry.root() == roots.1);
+    assert_eq!((coins.leaf_count(), registry.leaf_count()), (3, 2));
+    assert!(!tags.contains(&fresh));
+    assert_eq!(tags.digest(), digest);
+    assert_eq!(log.lock().unwrap().get_ref().len(), logged);
+
+    // Nor does a block spending the same tag twice change anything, dropped without an explicit abort.
+    {
+        let mut transaction =
+            TreeTransaction::new(vec![&mut coins, &mut registry], &mut tags, parameters);
+        let twice = BlockEntry {
+            tags: vec![fresh, fresh],
+            coins: vec![],
+            keys: vec![],
+        };
+        assert!(matches!(
+            apply_block(&mut transaction, &[twice]),
+            Err(Error::DuplicateTag)
+        ));
+        assert!(matches!(
+            transaction.insert(2, &[]),
+            Err(Error::ParameterMismatch { .. })
+        ));
+    }
+    assert!(coins.root() == roots.0 && registry.root() == roots.1);
+    assert_eq!(tags.digest(), digest);
+}
+
+#[test]
+fn test_committed_block_updates_every_tree() {
+    let mut rng = fixtures::insecure_test_rng(1);
+    let parameters = fixtures::pallas_vesta();
+    let initial_coins = random_leaves(parameters, 3, &mut rng);
+    let initial_keys = random_leaves(parameters, 2, &mut rng);
+    let mut coins = Tree::from_set(&initial_coins, parameters, None).unwrap();
+    let mut registry = Tree::from_set(&initial_keys, parameters, None).unwrap();
+    let mut tags = TagSet::new();
+    let old_roots = (coins.root(), registry.root());
+
+    let block = [
+        BlockEntry {
+            tags: vec![PallasScalar::rand(&mut rng)],
+            coins: random_leaves(parameters, 2, &mut rng),
+            keys: random_leaves(parameters, 1, &mut rng),
+        },
+        BlockEntry {
+            tags: vec![PallasScalar::rand(&mut rng), PallasScalar::rand(&mut rng)],
+            coins: random_leaves(parameters, 3, &mut rng),
+            keys: vec![],
+        },
+    ];
+    let mut transaction =
+        TreeTransaction::new(vec![&mut coins, &mut registry], &mut tags, parameters);
+    apply_block(&mut transaction, &block).unwrap();
+    // The staged leaves follow those of the trees.
+    assert_eq!(transaction.insert(COINS, &[]).unwrap(), Vec::<usize>::new());
+    let staged = transaction.staged_roots().unwrap();
+    let committed = transaction.commit().unwrap();
+    assert!(staged == committed);
+    assert!(coins.root() == staged[COINS] && registry.root() == staged[REGISTRY]);
+
+    // The trees are those of all their leaves, and remember their roots before the block.
+    let all_coins: Vec<_> = initial_coins
+        .iter()
+        .chain(block.iter().flat_map(|entry| &entry.coins))
+        .copied()
+        .collect();
+    assert_eq!(coins.leaves(), all_coins);
+    assert!(
+        Tree::from_set(&all_coins, parameters, Some(coins.height()))
+            .unwrap()
+            .root()
+            == coins.root()
+    );
+    assert_eq!(registry.leaf_count(), 3);
+    assert_eq!(coins.leaf_count_at_root(&old_roots.0), Some(3));
+    assert_eq!(registry.leaf_count_at_root(&old_roots.1), Some(2));
+    assert_eq!(tags.len(), 3);
+    for tag in block.iter().flat_map(|entry| &entry.tags) {
+        assert!(tags.contains(tag));
+    }
+}
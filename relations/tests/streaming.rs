@@ -0,0 +1,185 @@
+extern crate relations;
+
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+use ark_serialize::CanonicalSerialize;
+use ark_std::UniformRand;
+use relations::curve_tree::*;
+use relations::cycle::EvenScalar;
+use relations::limits::DeserializeLimits;
+use relations::streaming::{verify_membership_streamed, ProofReader, ProofWriter};
+use relations::Error;
+
+use ark_pallas::{Fr as PallasScalar, PallasConfig};
+use ark_vesta::VestaConfig;
+
+const L: usize = 32;
+const CHUNK: usize = 7;
+
+type Tree = CurveTree<L, PallasConfig, VestaConfig>;
+
+// The sending end of an in-memory pipe, which sends whatever it is written in chunks of `CHUNK` bytes.
+struct PipeWriter(Sender<Vec<u8>>);
+
+impl Write for PipeWriter {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        for chunk in bytes.chunks(CHUNK) {
+            self.0
+                .send(chunk.to_vec())
+                .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// The receiving end, which blocks until a chunk arrives and reads the end of the stream once the sender is dropped.
+struct PipeReader {
+    receiver: Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, bytes: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            match self.receiver.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = bytes.len().min(self.chunk.len() - self.position);
+        bytes[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+fn pipe() -> (PipeWriter, PipeReader) {
+    let (sender, receiver) = channel();
+    (
+        PipeWriter(sender),
+        PipeReader {
+            receiver,
+            chunk: Vec::new(),
+            position: 0,
+        },
+    )
+}
+
+fn tree_and_proof() -> (Tree, MembershipProof<L, PallasConfig, VestaConfig>) {
+    let mut rng = fixtures::insecure_test_rng(0);
+    let parameters = fixtures::pallas_vesta();
+    let (leaves, blindings): (Vec<_>, Vec<_>) = (0..5u64)
+        .map(|v| {
+            parameters
+                .even_parameters
+                .permissible_commitment(&[v.into()], PallasScalar::rand(&mut rng), 0)
+                .unwrap()
+        })
+        .unzip();
+    let tree = Tree::from_set(&leaves, parameters, Some(4)).unwrap();
+    let (proof, _) = tree
+        .prove_membership(3, EvenScalar(blindings[3]), parameters, &mut rng)
+        .unwrap();
+    (tree, proof)
+}
+
+fn streamed(proof: &MembershipProof<L, PallasConfig, VestaConfig>) -> Vec<u8> {
+    let mut writer = ProofWriter::new(Vec::new());
+    writer.write_membership_proof(proof).unwrap();
+    writer.finish().unwrap()
+}
+
+#[test]
+fn test_stream_through_pipe() {
+    let parameters = fixtures::pallas_vesta();
+    let (tree, proof) = tree_and_proof();
+
+    let (sender, receiver) = pipe();
+    let prover = {
+        let proof = proof.clone();
+        thread::spawn(move || {
+            let mut writer = ProofWriter::new(sender);
+            writer.write_membership_proof(&proof).unwrap();
+            writer.finish().unwrap();
+        })
+    };
+    let rerandomized_leaf =
+        verify_membership_streamed(ProofReader::new(receiver), &tree, parameters).unwrap();
+    prover.join().unwrap();
+    assert_eq!(rerandomized_leaf, proof.rerandomized_leaf());
+
+    // Read back whole, the proof is that written.
+    let bytes = streamed(&proof);
+    let mut reader = ProofReader::new(bytes.as_slice());
+    let read = reader
+        .read_membership_proof::<L, PallasConfig, VestaConfig>()
+        .unwrap();
+    assert!(reader.finish().unwrap().is_empty());
+    let (mut expected, mut actual) = (Vec::new(), Vec::new());
+    proof.serialize_compressed(&mut expected).unwrap();
+    read.serialize_compressed(&mut actual).unwrap();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_truncated_stream() {
+    let parameters = fixtures::pallas_vesta();
+    let (tree, proof) = tree_and_proof();
+    let bytes = streamed(&proof);
+
+    // Cut anywhere, in a frame header, a payload or the integrity hash, the stream is rejected.
+    for length in [0, 3, 40, bytes.len() / 2, bytes.len() - 33, bytes.len() - 1] {
+        assert!(matches!(
+            verify_membership_streamed(ProofReader::new(&bytes[..length]), &tree, parameters),
+            Err(Error::Serialization(_))
+        ));
+    }
+}
+
+#[test]
+fn test_corrupted_stream() {
+    let parameters = fixtures::pallas_vesta();
+    let (tree, proof) = tree_and_proof();
+    let bytes = streamed(&proof);
+
+    // A flipped bit of the last scalar of the odd proof, which still deserializes, fails the integrity hash.
+    let mut flipped = bytes.clone();
+    let last_scalar = bytes.len() - 5 - 32 - 1;
+    flipped[last_scalar - 8] ^= 1;
+    assert!(matches!(
+        verify_membership_streamed(ProofReader::new(flipped.as_slice()), &tree, parameters),
+        Err(Error::CorruptedStream { .. })
+    ));
+
+    // A frame of the wrong kind is rejected as soon as it is read.
+    let mut reordered = bytes.clone();
+    reordered[0] = 1;
+    assert!(matches!(
+        verify_membership_streamed(ProofReader::new(reordered.as_slice()), &tree, parameters),
+        Err(Error::CorruptedStream { .. })
+    ));
+
+    // A path longer than the limits is rejected before its end arrives.
+    let limits = DeserializeLimits {
+        max_depth: 1,
+        ..DeserializeLimits::default()
+    };
+    assert!(matches!(
+        verify_membership_streamed(
+            ProofReader::with_limits(bytes.as_slice(), limits),
+            &tree,
+            parameters
+        ),
+        Err(Error::LimitExceeded { .. })
+    ));
+}
@@ -0,0 +1,133 @@
+#![cfg(feature = "test_vectors")]
+
+extern crate bulletproofs;
+extern crate relations;
+
+use bulletproofs::r1cs::{R1CSError, R1CS_PROOF_VERSION};
+use relations::test_vectors::TestVector;
+use relations::version::FormatVersion;
+use relations::Error;
+
+use ark_pallas::{PallasConfig, Projective as PallasP};
+use ark_secp256k1::{Config as SecpConfig, Projective as SecpP};
+use ark_secq256k1::Config as SecqConfig;
+use ark_vesta::VestaConfig;
+
+const VALUES: [u64; 5] = [0, 1, 42, 1 << 32, u64::MAX];
+
+fn fixture_path(name: &str) -> String {
+    format!("{}/tests/vectors/{}.json", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn read_fixture(name: &str) -> TestVector {
+    TestVector::from_json(&std::fs::read_to_string(fixture_path(name)).unwrap()).unwrap()
+}
+
+/// Compares `vector` byte for byte with the fixture `name`,
+/// or writes the fixture if `UPDATE_TEST_VECTORS` is set, after a deliberate change of the transcripts or encodings.
+fn check_fixture(name: &str, vector: &TestVector) {
+    let path = fixture_path(name);
+    let json = vector.to_json();
+    if std::env::var_os("UPDATE_TEST_VECTORS").is_some() {
+        std::fs::write(&path, json).unwrap();
+        return;
+    }
+    let fixture = std::fs::read_to_string(&path).unwrap();
+    assert!(
+        fixture == json,
+        "{} differs from the regenerated vector, set UPDATE_TEST_VECTORS to update it",
+        path
+    );
+}
+
+#[test]
+fn test_vectors_pallas_vesta() {
+    let vector = TestVector::generate::<4, _, _, PallasConfig, VestaConfig, PallasP>(
+        "pallas_vesta_v2",
+        1,
+        2,
+        1 << 11,
+        &VALUES,
+        3,
+    )
+    .unwrap();
+    check_fixture("pallas_vesta_v2", &vector);
+}
+
+#[test]
+fn test_vectors_secp_secq() {
+    let vector = TestVector::generate::<4, _, _, SecpConfig, SecqConfig, SecpP>(
+        "secp_secq_v2",
+        2,
+        2,
+        1 << 11,
+        &VALUES,
+        3,
+    )
+    .unwrap();
+    check_fixture("secp_secq_v2", &vector);
+}
+
+/// The checked-in fixtures of the version 1 of the formats, made with the blinding base of
+/// `PedersenGens::hashed_from_basepoint`: they must keep verifying whatever the refactors and later versions,
+/// until a change of the transcripts `PROTOCOL_VERSION`.
+#[test]
+fn test_version_1_fixtures_verify() {
+    let vector = read_fixture("pallas_vesta");
+    assert_eq!(TestVector::from_json(&vector.to_json()).unwrap(), vector);
+    assert!(vector.path.starts_with("01"));
+    for hex in [&vector.even_proof, &vector.odd_proof] {
+        assert!(hex.starts_with("01"));
+    }
+    vector
+        .verify::<4, _, _, PallasConfig, VestaConfig>()
+        .unwrap();
+    read_fixture("secp_secq")
+        .verify::<4, _, _, SecpConfig, SecqConfig>()
+        .unwrap();
+    assert_eq!(FormatVersion::V1.r1cs_proof_version(), 1);
+
+    // The version is bound to the proofs: those of version 1 do not verify with the parameters of version 2.
+    let mut relabeled = vector;
+    relabeled.path.replace_range(0..2, "02");
+    assert!(relabeled
+        .verify::<4, _, _, PallasConfig, VestaConfig>()
+        .is_err());
+}
+
+/// The checked-in fixtures of the version 2 of the formats, the current one, whose blinding base is drawn from
+/// `PEDERSEN_DOMAIN`: as those of version 1, they must keep verifying until a change of `PROTOCOL_VERSION`.
+#[test]
+fn test_version_2_fixtures_verify() {
+    let vector = read_fixture("pallas_vesta_v2");
+    assert_eq!(TestVector::from_json(&vector.to_json()).unwrap(), vector);
+    assert!(vector.path.starts_with("02"));
+    for hex in [&vector.even_proof, &vector.odd_proof] {
+        assert!(hex.starts_with("01"));
+    }
+    vector
+        .verify::<4, _, _, PallasConfig, VestaConfig>()
+        .unwrap();
+    read_fixture("secp_secq_v2")
+        .verify::<4, _, _, SecpConfig, SecqConfig>()
+        .unwrap();
+    assert_eq!(FormatVersion::CURRENT, FormatVersion::V2);
+    assert_eq!(R1CS_PROOF_VERSION, 1);
+}
+
+#[test]
+fn test_bumped_version_fixtures_rejected() {
+    let mut vector = read_fixture("pallas_vesta_v2");
+    vector.path.replace_range(0..2, "03");
+    assert!(matches!(
+        vector.verify::<4, _, _, PallasConfig, VestaConfig>(),
+        Err(Error::UnsupportedVersion { version: 3 })
+    ));
+
+    let mut vector = read_fixture("pallas_vesta_v2");
+    vector.even_proof.replace_range(0..2, "02");
+    assert!(matches!(
+        vector.verify::<4, _, _, PallasConfig, VestaConfig>(),
+        Err(Error::R1CS(R1CSError::UnsupportedVersion { version: 2 }))
+    ));
+}
@@ -0,0 +1,158 @@
+#![cfg(feature = "trace")]
+
+extern crate bulletproofs;
+extern crate relations;
+
+use std::sync::{Arc, Mutex};
+
+use ark_ec::short_weierstrass::Affine;
+use ark_std::UniformRand;
+use bulletproofs::r1cs::*;
+use merlin::Transcript;
+use relations::curve_tree::*;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+use ark_pallas::PallasConfig;
+use ark_vesta::VestaConfig;
+
+/// A span seen by `SpanRecorder`, with its integer fields.
+#[derive(Debug, Clone)]
+struct RecordedSpan {
+    name: &'static str,
+    fields: Vec<(&'static str, u64)>,
+}
+
+impl RecordedSpan {
+    fn field(&self, name: &str) -> Option<u64> {
+        self.fields
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+impl Visit for RecordedSpan {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields.push((field.name(), value));
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
+/// Records every span created while it is the default subscriber.
+#[derive(Clone, Default)]
+struct SpanRecorder {
+    spans: Arc<Mutex<Vec<RecordedSpan>>>,
+}
+
+impl SpanRecorder {
+    fn find(&self, name: &str) -> Vec<RecordedSpan> {
+        let spans = self.spans.lock().unwrap();
+        spans
+            .iter()
+            .filter(|span| span.name == name)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Subscriber for SpanRecorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let mut span = RecordedSpan {
+            name: attributes.metadata().name(),
+            fields: Vec::new(),
+        };
+        attributes.record(&mut span);
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(span);
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, id: &Id, values: &Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        values.record(&mut spans[id.into_u64() as usize - 1]);
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[test]
+fn select_and_rerandomize_spans() {
+    let mut rng = rand::thread_rng();
+    let sr_params = fixtures::pallas_vesta();
+    let recorder = SpanRecorder::default();
+
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let some_point = Affine::<PallasConfig>::rand(&mut rng);
+        let (permissible_point, _) = sr_params
+            .even_parameters
+            .uh
+            .permissible_commitment(&some_point, &sr_params.even_parameters.pc_gens.B_blinding);
+        let curve_tree = CurveTree::<32, PallasConfig, VestaConfig>::from_set(
+            &[permissible_point],
+            sr_params,
+            Some(3),
+        )
+        .unwrap();
+
+        let mut even_prover: Prover<_, Affine<PallasConfig>> = Prover::new(
+            &sr_params.even_parameters.pc_gens,
+            Transcript::new(b"trace"),
+        );
+        let mut odd_prover: Prover<_, Affine<VestaConfig>> =
+            Prover::new(&sr_params.odd_parameters.pc_gens, Transcript::new(b"trace"));
+        let (path, _) = curve_tree
+            .select_and_rerandomize_prover_gadget(
+                0,
+                &mut even_prover,
+                &mut odd_prover,
+                sr_params,
+                &mut rng,
+            )
+            .unwrap();
+
+        let mut even_verifier = Verifier::new(Transcript::new(b"trace"));
+        let mut odd_verifier = Verifier::new(Transcript::new(b"trace"));
+        let _rerandomized_leaf = curve_tree
+            .select_and_rerandomize_verifier_gadget(
+                &mut even_verifier,
+                &mut odd_verifier,
+                path,
+                sr_params,
+            )
+            .unwrap();
+    });
+
+    // A search for a single commitment records its attempts, a batched search the number of commitments.
+    let searches = recorder.find("permissible_search");
+    assert!(!searches.is_empty());
+    assert!(searches.iter().all(|span| {
+        span.field("attempts")
+            .or_else(|| span.field("commitments"))
+            .unwrap()
+            >= 1
+    }));
+
+    let traversal = &recorder.find("tree_traversal")[0];
+    assert_eq!(traversal.field("height"), Some(3));
+    assert_eq!(traversal.field("branching_factor"), Some(32));
+    let prover = &recorder.find("select_and_rerandomize_prover")[0];
+    assert_eq!(
+        prover.field("even_levels").unwrap() + prover.field("odd_levels").unwrap(),
+        3
+    );
+    let verifier = &recorder.find("select_and_rerandomize_verifier")[0];
+    assert_eq!(verifier.field("height"), Some(3));
+}
@@ -0,0 +1,38 @@
+//! Verifies the membership proof of `fixture.bin` with relations built with the `verify-only` feature alone,
+//! see `tests/verify_only.rs`: the parameters, the leaves of the tree and the height it was built with,
+//! then the proof, all serialized compressed.
+
+use ark_pallas::{Affine as PallasAffine, PallasConfig};
+use ark_serialize::CanonicalDeserialize;
+use ark_vesta::VestaConfig;
+use relations::curve_tree::{CurveTree, MembershipProof, SelRerandParameters};
+
+const L: usize = 4;
+
+const FIXTURE: &[u8] = include_bytes!("../fixture.bin");
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut reader = FIXTURE;
+    let parameters =
+        SelRerandParameters::<PallasConfig, VestaConfig>::deserialize_compressed(&mut reader)?;
+    let leaves = Vec::<PallasAffine>::deserialize_compressed(&mut reader)?;
+    let height = u64::deserialize_compressed(&mut reader)? as usize;
+    let proof =
+        MembershipProof::<L, PallasConfig, VestaConfig>::deserialize_compressed(&mut reader)?;
+
+    let tree = CurveTree::<L, _, _>::from_set(&leaves, &parameters, Some(height))?;
+    let rerandomized_leaf = proof.verify(&tree, &parameters)?;
+
+    // The same number of leaves in another order: another root, against which the proof must fail.
+    let reversed: Vec<_> = leaves.iter().rev().copied().collect();
+    let other = CurveTree::<L, _, _>::from_set(&reversed, &parameters, Some(height))?;
+    if proof.verify(&other, &parameters).is_ok() {
+        return Err("the proof verifies against another root".into());
+    }
+    println!(
+        "verified the membership of {} in a tree of {} leaves",
+        rerandomized_leaf,
+        leaves.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,178 @@
+//! The verifier alone: `tests/verifier_crate` depends on relations with `default-features = false, features = ["verify-only"]`,
+//! as a downstream crate embedding only the verifier would, and verifies the membership proof of its `fixture.bin`.
+
+extern crate relations;
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use fixtures::{insecure_test_rng, PallasConfig, PallasScalar, VestaConfig};
+use relations::curve_tree::{CurveTree, MembershipProof, SelRerandParameters};
+use relations::cycle::EvenScalar;
+
+use ark_pallas::Affine as PallasAffine;
+use ark_std::UniformRand;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Once;
+
+/// The branching factor of the tree of the fixture, that of `tests/verifier_crate`.
+const L: usize = 4;
+
+const HEIGHT: usize = 2;
+
+fn crate_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/verifier_crate")
+}
+
+/// The parameters, the leaves, the height of the tree and a membership proof of its leaf 3, as read by `tests/verifier_crate`.
+fn generate_fixture() -> Vec<u8> {
+    let mut rng = insecure_test_rng(0);
+    let parameters = SelRerandParameters::<PallasConfig, VestaConfig>::new(1 << 11, 1 << 11);
+    let (leaves, blindings): (Vec<_>, Vec<_>) = (0..5u64)
+        .map(|value| {
+            parameters
+                .even_parameters
+                .permissible_commitment(
+                    &[PallasScalar::from(value)],
+                    PallasScalar::rand(&mut rng),
+                    0,
+                )
+                .unwrap()
+        })
+        .unzip();
+    let tree = CurveTree::<L, _, _>::from_set(&leaves, &parameters, Some(HEIGHT)).unwrap();
+    let (proof, _) = tree
+        .prove_membership(3, EvenScalar(blindings[3]), &parameters, &mut rng)
+        .unwrap();
+
+    let mut bytes = Vec::new();
+    parameters.serialize_compressed(&mut bytes).unwrap();
+    leaves.serialize_compressed(&mut bytes).unwrap();
+    (HEIGHT as u64).serialize_compressed(&mut bytes).unwrap();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+/// Verifies the fixture with the full build, as `tests/verifier_crate` does with the verifier alone,
+/// or writes it if `UPDATE_VERIFY_ONLY_FIXTURE` is set, after a deliberate change of the transcripts or encodings.
+#[test]
+fn test_verify_only_fixture() {
+    let path = crate_dir().join("fixture.bin");
+    if std::env::var_os("UPDATE_VERIFY_ONLY_FIXTURE").is_some() {
+        std::fs::write(&path, generate_fixture()).unwrap();
+    }
+    let fixture = std::fs::read(&path).unwrap();
+    let mut reader = &fixture[..];
+    let parameters =
+        SelRerandParameters::<PallasConfig, VestaConfig>::deserialize_compressed(&mut reader)
+            .unwrap();
+    let leaves = Vec::<PallasAffine>::deserialize_compressed(&mut reader).unwrap();
+    let height = u64::deserialize_compressed(&mut reader).unwrap() as usize;
+    let proof =
+        MembershipProof::<L, PallasConfig, VestaConfig>::deserialize_compressed(&mut reader)
+            .unwrap();
+    assert!(reader.is_empty());
+
+    let tree = CurveTree::<L, _, _>::from_set(&leaves, &parameters, Some(height)).unwrap();
+    assert!(
+        proof.verify(&tree, &parameters).is_ok(),
+        "{} no longer verifies, set UPDATE_VERIFY_ONLY_FIXTURE to update it",
+        path.display()
+    );
+}
+
+static SEED_LOCKFILE: Once = Once::new();
+
+/// Runs cargo with `args` on `tests/verifier_crate`, building in a directory of its own, and returns its output.
+///
+/// The crate is not a member of the workspace, it starts from the lockfile of the workspace
+/// so that it builds with the versions the workspace was tested with, and offline if the workspace built.
+fn cargo(args: &[&str]) -> String {
+    let dir = crate_dir();
+    SEED_LOCKFILE.call_once(|| {
+        let workspace_lock = Path::new(env!("CARGO_MANIFEST_DIR")).join("../Cargo.lock");
+        if !dir.join("Cargo.lock").exists() && workspace_lock.exists() {
+            std::fs::copy(workspace_lock, dir.join("Cargo.lock")).unwrap();
+        }
+    });
+    let output = Command::new(env!("CARGO"))
+        .args(args)
+        .arg("--manifest-path")
+        .arg(dir.join("Cargo.toml"))
+        .env(
+            "CARGO_TARGET_DIR",
+            Path::new(env!("CARGO_TARGET_TMPDIR")).join("verifier_crate"),
+        )
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "cargo {} failed:\n{}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_verify_only_crate_verifies_fixture() {
+    // Debug builds of the verifier also print the degrees of its polynomials.
+    let stdout = cargo(&["run", "--quiet"]);
+    let last = stdout.lines().last().unwrap_or_default();
+    assert!(last.starts_with("verified the membership"), "{}", stdout);
+}
+
+/// The crates of the normal dependencies of `tests/verifier_crate`, one per line, with their enabled features.
+fn dependencies() -> Vec<String> {
+    cargo(&[
+        "tree", "-e", "normal", "--prefix", "none", "--format", "{p} {f}",
+    ])
+    .lines()
+    .map(str::to_owned)
+    .collect()
+}
+
+fn features_of<'a>(dependencies: &'a [String], name: &str) -> Vec<&'a str> {
+    let line = dependencies
+        .iter()
+        .find(|line| line.starts_with(&format!("{} ", name)))
+        .unwrap_or_else(|| panic!("{} is not a dependency", name));
+    line.rsplit(' ').next().unwrap().split(',').collect()
+}
+
+#[test]
+fn test_verify_only_dependencies() {
+    let dependencies = dependencies();
+
+    // No thread pool, no wallet, no primitives of the payments.
+    for excluded in ["rayon", "rayon-core", "ark-crypto-primitives", "blake2"] {
+        assert!(
+            !dependencies
+                .iter()
+                .any(|line| line.starts_with(&format!("{} ", excluded))),
+            "the verify-only build depends on {}",
+            excluded
+        );
+    }
+    let relations = features_of(&dependencies, "relations");
+    assert!(relations.contains(&"verify-only"), "{:?}", relations);
+    for excluded in ["prover", "payments", "parallel"] {
+        assert!(!relations.contains(&excluded), "{:?}", relations);
+    }
+    let bulletproofs = features_of(&dependencies, "bulletproofs");
+    assert!(bulletproofs.contains(&"verify-only"), "{:?}", bulletproofs);
+    for excluded in ["prover", "parallel", "rand"] {
+        assert!(!bulletproofs.contains(&excluded), "{:?}", bulletproofs);
+    }
+
+    // rand remains as a dependency of ark-std alone, which requires it whatever its features.
+    let rand_dependents = cargo(&[
+        "tree", "-e", "normal", "-i", "rand", "--depth", "1", "--prefix", "none",
+    ]);
+    for dependent in rand_dependents.lines().skip(1) {
+        assert!(
+            dependent.starts_with("ark-"),
+            "rand is a dependency of {}",
+            dependent
+        );
+    }
+}
@@ -0,0 +1,30 @@
+// Run with `wasm-pack test --node relations -- --no-default-features --features wasm`
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use relations::wasm::*;
+use wasm_bindgen_test::*;
+
+#[wasm_bindgen_test]
+fn test_mint_scan_and_verify() {
+    let parameters = WalletParameters::new(&[7u8; 32], 8).unwrap();
+    let keys = KeyPair::generate(&parameters);
+    let minted = mint(&parameters, 42, &keys.public_key()).unwrap();
+
+    assert!(verify_minted(&parameters, &minted.commitment(), &minted.proof()).unwrap());
+    assert!(scan_output(
+        &parameters,
+        &keys.public_key(),
+        &minted.commitment(),
+        &minted.coin()
+    )
+    .unwrap());
+
+    let other_keys = KeyPair::generate(&parameters);
+    assert!(!scan_output(
+        &parameters,
+        &other_keys.public_key(),
+        &minted.commitment(),
+        &minted.coin()
+    )
+    .unwrap());
+}